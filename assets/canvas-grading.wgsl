@@ -0,0 +1,55 @@
+// Final color grading pass over the low-resolution canvas.
+//
+// Color lookup tables are N² × N strip images: the blue channel selects one of N horizontal N × N slices, within
+// which red goes from left to right and green from top to bottom. Lookups interpolate between the eight surrounding
+// table entries, and the results of the two tables are blended while transitioning between them.
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0) var<uniform> blend: f32;
+@group(2) @binding(1) var canvas: texture_2d<f32>;
+@group(2) @binding(2) var canvas_sampler: sampler;
+@group(2) @binding(3) var from_lut: texture_2d<f32>;
+@group(2) @binding(4) var to_lut: texture_2d<f32>;
+
+fn lut_entry(lut: texture_2d<f32>, size: u32, entry: vec3<u32>) -> vec3<f32> {
+    return textureLoad(lut, vec2(entry.b * size + entry.r, entry.g), 0).rgb;
+}
+
+fn look_up(lut: texture_2d<f32>, color: vec3<f32>) -> vec3<f32> {
+    let size = textureDimensions(lut).y;
+    let position = clamp(color, vec3(0.), vec3(1.)) * f32(size - 1u);
+    let low = vec3<u32>(floor(position));
+    let high = min(low + 1u, vec3(size - 1u));
+    let t = fract(position);
+
+    let c000 = lut_entry(lut, size, low);
+    let c100 = lut_entry(lut, size, vec3(high.r, low.g, low.b));
+    let c010 = lut_entry(lut, size, vec3(low.r, high.g, low.b));
+    let c110 = lut_entry(lut, size, vec3(high.r, high.g, low.b));
+    let c001 = lut_entry(lut, size, vec3(low.r, low.g, high.b));
+    let c101 = lut_entry(lut, size, vec3(high.r, low.g, high.b));
+    let c011 = lut_entry(lut, size, vec3(low.r, high.g, high.b));
+    let c111 = lut_entry(lut, size, high);
+
+    let low_slice = mix(mix(c000, c100, t.r), mix(c010, c110, t.r), t.g);
+    let high_slice = mix(mix(c001, c101, t.r), mix(c011, c111, t.r), t.g);
+    return mix(low_slice, high_slice, t.b);
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(color, vec3(1. / 2.4)) - 0.055, color * 12.92, color <= vec3(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return select(pow((color + 0.055) / 1.055, vec3(2.4)), color / 12.92, color <= vec3(0.04045));
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let sample = textureSample(canvas, canvas_sampler, mesh.uv);
+    // Tables map sRGB-encoded colors, like the images they are authored on.
+    let color = linear_to_srgb(sample.rgb);
+    let graded = mix(look_up(from_lut, color), look_up(to_lut, color), blend);
+    return vec4(srgb_to_linear(graded), sample.a);
+}
@@ -41,26 +41,32 @@ impl AssetLoader for QOIAssetLoader {
 	) -> Result<Self::Asset, Self::Error> {
 		let mut bytes = Vec::new();
 		reader.read_to_end(&mut bytes).await?;
-		let mut decoder = Decoder::new(&bytes)?.with_channels(qoi::Channels::Rgba);
-		let decoded = decoder.decode_to_vec()?;
-		let header = decoder.header();
-
-		Ok(Image::new(
-			Extent3d { width: header.width, height: header.height, ..Default::default() },
-			TextureDimension::D2,
-			decoded,
-			match header.channels {
-				qoi::Channels::Rgb => Err(anyhow!("Rgb not supported.")),
-				qoi::Channels::Rgba => Ok(match header.colorspace {
-					qoi::ColorSpace::Srgb => TextureFormat::Rgba8UnormSrgb,
-					qoi::ColorSpace::Linear => TextureFormat::Rgba8Unorm,
-				}),
-			}?,
-			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
-		))
+		decode_image(&bytes)
 	}
 
 	fn extensions(&self) -> &[&str] {
 		&["qoi"]
 	}
 }
+
+/// Decodes an in-memory QOI image into a Bevy [`Image`]. This is what [`QOIAssetLoader`] uses internally, and it is
+/// useful for QOI data that doesn't come from an asset file, such as QOI images embedded in other file formats.
+pub fn decode_image(bytes: &[u8]) -> Result<Image, Box<dyn Error + Send + Sync + 'static>> {
+	let mut decoder = Decoder::new(bytes)?.with_channels(qoi::Channels::Rgba);
+	let decoded = decoder.decode_to_vec()?;
+	let header = decoder.header();
+
+	Ok(Image::new(
+		Extent3d { width: header.width, height: header.height, ..Default::default() },
+		TextureDimension::D2,
+		decoded,
+		match header.channels {
+			qoi::Channels::Rgb => Err(anyhow!("Rgb not supported.")),
+			qoi::Channels::Rgba => Ok(match header.colorspace {
+				qoi::ColorSpace::Srgb => TextureFormat::Rgba8UnormSrgb,
+				qoi::ColorSpace::Linear => TextureFormat::Rgba8Unorm,
+			}),
+		}?,
+		RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+	))
+}
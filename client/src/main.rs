@@ -1,5 +1,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+#[cfg(feature = "mods")]
+extern crate cmp_dylib;
+
 use bevy::prelude::*;
 use cmp::{CmpPlugin, GraphicsPlugin};
 
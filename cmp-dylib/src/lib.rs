@@ -0,0 +1,5 @@
+//! Links the CMP engine, including Bevy, as one dynamic library. The client and code mods that depend on this crate
+//! share this library instead of each containing a copy of the engine, so that the engine's statics, such as Bevy's
+//! schedule labels and hash seeds, are the same for both. See the `mods` module of the engine.
+
+extern crate cmp;
@@ -0,0 +1,275 @@
+//! Sound effects and ambient audio.
+//!
+//! Every button gets the standard hover and click sounds as soon as it is spawned, which are played by the UI
+//! animation systems. Other parts of the game request one-off sound effects with [`PlaySound`]; builds, demolitions
+//! and errors already do so here. Underneath it all, looping layers of ambient nature sounds are mixed according to
+//! the [`DayPhase`] and [`Weather`], fading smoothly whenever either changes.
+//!
+//! Music is handled separately by the [`music`] module.
+//!
+//! All sounds are Ogg Vorbis files in `assets/sounds`. They are optional: sounds whose files are missing are never
+//! loaded and stay silent, so the game runs quietly without them instead of failing to load them over and over.
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::model::time_of_day::{DayPhase, TimeOfDay};
+use crate::model::weather::Weather;
+use crate::ui::animate::InteractionSounds;
+use crate::ui::build::BuildCompleted;
+use crate::ui::clearing::PerformClearing;
+use crate::ui::demolish::PerformDemolish;
+use crate::ui::error::ErrorBox;
+
+pub(crate) mod music;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(music::MusicPlugin)
+			.init_resource::<SoundLibrary>()
+			.add_event::<PlaySound>()
+			.add_systems(Startup, spawn_ambient_layers)
+			.add_systems(
+				Update,
+				(add_button_sounds, request_game_sounds.in_set(GameState::InGame), play_sounds)
+					.chain()
+					.in_set(GameSet::UI),
+			)
+			.add_systems(Update, mix_ambient_layers.in_set(GameSet::Graphics));
+	}
+}
+
+/// One-off sound effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+	/// The cursor starts hovering over a button.
+	Hover,
+	/// A button is pressed.
+	Click,
+	/// Something was built.
+	Build,
+	/// Something was demolished.
+	Demolish,
+	/// An action failed, such as an invalid build.
+	Error,
+}
+
+pub const ALL_SOUND_EFFECTS: [SoundEffect; 5] =
+	[SoundEffect::Hover, SoundEffect::Click, SoundEffect::Build, SoundEffect::Demolish, SoundEffect::Error];
+
+impl SoundEffect {
+	pub const fn asset(&self) -> &'static str {
+		match self {
+			Self::Hover => "sounds/hover.ogg",
+			Self::Click => "sounds/click.ogg",
+			Self::Build => "sounds/build.ogg",
+			Self::Demolish => "sounds/demolish.ogg",
+			Self::Error => "sounds/error.ogg",
+		}
+	}
+}
+
+/// Looping layers of ambient sound, which are mixed depending on the time of day and the weather.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AmbientLayer {
+	Birds,
+	Crickets,
+	Rain,
+	Wind,
+}
+
+pub const ALL_AMBIENT_LAYERS: [AmbientLayer; 4] =
+	[AmbientLayer::Birds, AmbientLayer::Crickets, AmbientLayer::Rain, AmbientLayer::Wind];
+
+impl AmbientLayer {
+	pub const fn asset(&self) -> &'static str {
+		match self {
+			Self::Birds => "sounds/ambient-birds.ogg",
+			Self::Crickets => "sounds/ambient-crickets.ogg",
+			Self::Rain => "sounds/ambient-rain.ogg",
+			Self::Wind => "sounds/ambient-wind.ogg",
+		}
+	}
+
+	/// Volume of this layer in the mix, between 0 and 1. Birds sing loudest at dawn and crickets chirp at night, but
+	/// both fall silent in bad weather, which brings rain and wind instead.
+	pub fn level(&self, phase: DayPhase, weather: Weather) -> f32 {
+		match self {
+			Self::Birds => {
+				let time_factor = match phase {
+					DayPhase::Dawn => 1.,
+					DayPhase::Day => 0.7,
+					DayPhase::Dusk => 0.4,
+					DayPhase::Night => 0.,
+				};
+				let weather_factor = match weather {
+					Weather::Sunny => 1.,
+					Weather::Rain => 0.3,
+					Weather::Storm => 0.,
+				};
+				time_factor * weather_factor
+			},
+			Self::Crickets => {
+				let time_factor = match phase {
+					DayPhase::Dusk => 0.6,
+					DayPhase::Night => 1.,
+					DayPhase::Dawn | DayPhase::Day => 0.,
+				};
+				let weather_factor = match weather {
+					Weather::Sunny => 1.,
+					Weather::Rain => 0.2,
+					Weather::Storm => 0.,
+				};
+				time_factor * weather_factor
+			},
+			Self::Rain => match weather {
+				Weather::Sunny => 0.,
+				Weather::Rain => 0.8,
+				Weather::Storm => 1.,
+			},
+			Self::Wind => match weather {
+				Weather::Sunny => 0.1,
+				Weather::Rain => 0.3,
+				Weather::Storm => 1.,
+			},
+		}
+	}
+}
+
+/// How fast ambient layers fade towards their level, in full volume per second.
+const AMBIENT_FADE_RATE: f32 = 0.2;
+
+/// Loads the sound, unless its file is missing.
+fn load_sound(asset_server: &AssetServer, path: &'static str) -> Option<Handle<AudioSource>> {
+	if FileAssetReader::new("assets").root_path().join(path).is_file() {
+		Some(asset_server.load(path))
+	} else {
+		debug!("Sound {path} is missing and stays silent");
+		None
+	}
+}
+
+/// Handles to all sound effects that exist, which are loaded on startup so that the first click already has its sound.
+#[derive(Resource)]
+pub struct SoundLibrary {
+	effects: Vec<(SoundEffect, Handle<AudioSource>)>,
+}
+
+impl FromWorld for SoundLibrary {
+	fn from_world(world: &mut World) -> Self {
+		let asset_server = world.resource::<AssetServer>();
+		Self {
+			effects: ALL_SOUND_EFFECTS
+				.into_iter()
+				.filter_map(|effect| Some((effect, load_sound(asset_server, effect.asset())?)))
+				.collect(),
+		}
+	}
+}
+
+impl SoundLibrary {
+	/// The sound effect, or [`None`] if its file is missing.
+	pub fn get(&self, effect: SoundEffect) -> Option<Handle<AudioSource>> {
+		self.effects.iter().find(|(other, _)| *other == effect).map(|(_, handle)| handle.clone())
+	}
+}
+
+/// Requests playing a sound effect once at the effects volume.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlaySound(pub SoundEffect);
+
+/// A looping ambient layer and its current volume, before the ambient volume setting is applied.
+#[derive(Component, Clone, Copy, Debug)]
+struct AmbientSound {
+	layer: AmbientLayer,
+	level: f32,
+}
+
+/// Gives every new button the standard interaction sounds, unless it already has its own.
+fn add_button_sounds(
+	buttons: Query<Entity, (Added<Button>, Without<InteractionSounds>)>,
+	library: Res<SoundLibrary>,
+	mut commands: Commands,
+) {
+	for button in &buttons {
+		commands
+			.entity(button)
+			.insert(InteractionSounds::new(library.get(SoundEffect::Hover), library.get(SoundEffect::Click)));
+	}
+}
+
+/// Plays sounds for builds, demolitions and errors. Dragging can complete many builds at once, which should still
+/// only make one sound.
+fn request_game_sounds(
+	mut completed: EventReader<BuildCompleted>,
+	mut demolished: EventReader<PerformDemolish>,
+	mut cleared: EventReader<PerformClearing>,
+	mut errors: EventReader<ErrorBox>,
+	mut sounds: EventWriter<PlaySound>,
+) {
+	if !completed.is_empty() {
+		completed.clear();
+		sounds.send(PlaySound(SoundEffect::Build));
+	}
+	if !demolished.is_empty() || !cleared.is_empty() {
+		demolished.clear();
+		cleared.clear();
+		sounds.send(PlaySound(SoundEffect::Demolish));
+	}
+	if !errors.is_empty() {
+		errors.clear();
+		sounds.send(PlaySound(SoundEffect::Error));
+	}
+}
+
+fn play_sounds(
+	mut requests: EventReader<PlaySound>,
+	library: Res<SoundLibrary>,
+	settings: Res<GameSettings>,
+	mut commands: Commands,
+) {
+	for sound in requests.read().filter_map(|PlaySound(effect)| library.get(*effect)) {
+		commands.spawn((
+			AudioPlayer::new(sound),
+			PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.effects_volume)),
+		));
+	}
+}
+
+/// All layers keep playing for the whole lifetime of the game, and are silent while no game is running.
+fn spawn_ambient_layers(asset_server: Res<AssetServer>, mut commands: Commands) {
+	for (layer, sound) in
+		ALL_AMBIENT_LAYERS.into_iter().filter_map(|layer| Some((layer, load_sound(&asset_server, layer.asset())?)))
+	{
+		commands.spawn((AudioPlayer::new(sound), PlaybackSettings::LOOP.with_volume(Volume::new(0.)), AmbientSound {
+			layer,
+			level: 0.,
+		}));
+	}
+}
+
+fn mix_ambient_layers(
+	// Ambience keeps fading while the game is paused, where virtual time stands still.
+	time: Res<Time<Real>>,
+	state: Res<State<GameState>>,
+	time_of_day: Res<TimeOfDay>,
+	weather: Res<Weather>,
+	settings: Res<GameSettings>,
+	mut layers: Query<(&mut AmbientSound, Option<&AudioSink>)>,
+) {
+	let in_game = matches!(state.get(), GameState::InGame | GameState::Paused);
+	let max_change = AMBIENT_FADE_RATE * time.delta_secs();
+	for (mut sound, sink) in &mut layers {
+		let target = if in_game { sound.layer.level(time_of_day.phase(), *weather) } else { 0. };
+		sound.level += (target - sound.level).clamp(-max_change, max_change);
+		// The sink only exists once the sound has started playing.
+		if let Some(sink) = sink {
+			sink.set_volume(sound.level * settings.ambient_volume);
+		}
+	}
+}
@@ -0,0 +1,173 @@
+//! Background music, played from one playlist in the menus and another one in game.
+//!
+//! The playlists are the tracks in the `music/menu` and `music/game` asset folders, so players can add their own music
+//! by dropping Ogg Vorbis files into them. Tracks of the current playlist play in random order, without repeating the
+//! last track. When the game state switches playlists or the player skips a track with [`SkipTrack`], the old track
+//! fades out while the new one fades in.
+
+use bevy::asset::LoadedFolder;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+
+pub(super) struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<MusicPlayer>()
+			.add_event::<SkipTrack>()
+			.add_systems(Update, (pick_track, cross_fade_tracks).chain().in_set(GameSet::UI));
+	}
+}
+
+/// How long a track takes to fade in or out.
+const CROSS_FADE_SECONDS: f32 = 2.;
+
+/// A set of tracks played in some game states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Playlist {
+	Menu,
+	Game,
+}
+
+impl Playlist {
+	/// The playlist that is played in the game state.
+	pub const fn for_state(state: GameState) -> Self {
+		match state {
+			GameState::MainMenu | GameState::Loading => Self::Menu,
+			GameState::InGame | GameState::Paused => Self::Game,
+		}
+	}
+
+	/// Asset folder containing the playlist's tracks.
+	pub const fn folder(&self) -> &'static str {
+		match self {
+			Self::Menu => "music/menu",
+			Self::Game => "music/game",
+		}
+	}
+}
+
+/// Requests fading over to the next track of the current playlist.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct SkipTrack;
+
+/// The loaded playlists and the track that is playing.
+#[derive(Resource)]
+struct MusicPlayer {
+	menu_tracks: Handle<LoadedFolder>,
+	game_tracks: Handle<LoadedFolder>,
+	/// The track that is playing and not fading out.
+	current:     Option<Entity>,
+	last_track:  Option<AssetId<AudioSource>>,
+}
+
+impl FromWorld for MusicPlayer {
+	fn from_world(world: &mut World) -> Self {
+		let asset_server = world.resource::<AssetServer>();
+		Self {
+			menu_tracks: asset_server.load_folder(Playlist::Menu.folder()),
+			game_tracks: asset_server.load_folder(Playlist::Game.folder()),
+			current:     None,
+			last_track:  None,
+		}
+	}
+}
+
+impl MusicPlayer {
+	fn tracks(&self, playlist: Playlist, folders: &Assets<LoadedFolder>) -> Vec<Handle<AudioSource>> {
+		let folder = match playlist {
+			Playlist::Menu => &self.menu_tracks,
+			Playlist::Game => &self.game_tracks,
+		};
+		folders
+			.get(folder)
+			.map(|folder| folder.handles.iter().filter_map(|handle| handle.clone().try_typed().ok()).collect())
+			.unwrap_or_default()
+	}
+}
+
+/// A playing music track.
+#[derive(Component, Clone, Copy, Debug)]
+struct MusicTrack {
+	playlist:   Playlist,
+	/// Volume of the track before the music volume setting is applied, between 0 and 1.
+	level:      f32,
+	fading_out: bool,
+}
+
+/// Starts the next track when the current one ended, was skipped, or belongs to another playlist than the game state's.
+fn pick_track(
+	state: Res<State<GameState>>,
+	settings: Res<GameSettings>,
+	folders: Res<Assets<LoadedFolder>>,
+	mut skips: EventReader<SkipTrack>,
+	mut player: ResMut<MusicPlayer>,
+	mut tracks: Query<&mut MusicTrack>,
+	mut commands: Commands,
+) {
+	let playlist = Playlist::for_state(*state.get());
+	let skipped = !skips.is_empty();
+	skips.clear();
+
+	// Tracks despawn when they end.
+	let current = player.current.filter(|current| tracks.contains(*current));
+	if let Some(current) = current {
+		let mut track = tracks.get_mut(current).unwrap();
+		if !skipped && track.playlist == playlist {
+			return;
+		}
+		track.fading_out = true;
+	}
+	player.current = None;
+
+	let available = player.tracks(playlist, &folders);
+	let candidates = available
+		.iter()
+		.filter(|track| available.len() == 1 || Some(track.id()) != player.last_track)
+		.collect::<Vec<_>>();
+	if candidates.is_empty() {
+		return;
+	}
+	let next = candidates[fastrand::usize(.. candidates.len())].clone();
+	player.last_track = Some(next.id());
+	player.current = Some(
+		commands
+			.spawn((
+				AudioPlayer::new(next),
+				PlaybackSettings { paused: !settings.play_music, ..PlaybackSettings::DESPAWN }
+					.with_volume(Volume::new(0.)),
+				MusicTrack { playlist, level: 0., fading_out: false },
+			))
+			.id(),
+	);
+}
+
+fn cross_fade_tracks(
+	// Music keeps fading while the game is paused, where virtual time stands still.
+	time: Res<Time<Real>>,
+	settings: Res<GameSettings>,
+	mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+	mut commands: Commands,
+) {
+	let max_change = time.delta_secs() / CROSS_FADE_SECONDS;
+	for (entity, mut track, sink) in &mut tracks {
+		let target = if track.fading_out { 0. } else { 1. };
+		track.level += (target - track.level).clamp(-max_change, max_change);
+		if track.fading_out && track.level <= 0. {
+			commands.entity(entity).despawn();
+			continue;
+		}
+		// The sink only exists once the track has started playing.
+		if let Some(sink) = sink {
+			sink.set_volume(track.level * settings.music_volume);
+			if settings.play_music && sink.is_paused() {
+				sink.play();
+			} else if !settings.play_music && !sink.is_paused() {
+				sink.pause();
+			}
+		}
+	}
+}
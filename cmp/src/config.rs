@@ -1,4 +1,5 @@
 //! Game configuration and settings management
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -6,15 +7,48 @@ use argh::FromArgs;
 use bevy::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::graphics::lut::ColorLut;
+use crate::graphics::palette::Palette;
+use crate::input::{Action, ActionInput, InputMap};
+use crate::ui::accessibility::TextSize;
+use crate::ui::hud_layout::HudLayout;
+
 /// The Camping Madness Project
 #[derive(FromArgs, Resource, Clone, Debug, Default)]
 pub struct CommandLineArguments {
 	/// an alternative settings file to use instead of the system default
 	#[argh(option)]
-	pub settings_file: Option<PathBuf>,
+	pub settings_file:    Option<PathBuf>,
 	/// show CMP version information
 	#[argh(switch)]
-	pub version:       bool,
+	pub version:          bool,
+	/// write the schema of all savable components and resources as JSON to the given file and exit
+	#[argh(option)]
+	pub dump_save_schema: Option<PathBuf>,
+	/// print statistics about the given save file and check the assets it refers to, then exit
+	#[argh(option)]
+	pub inspect_save:     Option<PathBuf>,
+	/// together with --inspect-save, write the raw scene RON of the save to the given file
+	#[argh(option)]
+	pub extract_scene:    Option<PathBuf>,
+	/// run a soak test with random build actions for the given number of days, write a report and exit
+	#[argh(option)]
+	pub soak_days:        Option<u32>,
+	/// older save to diff against the save given with --diff-to
+	#[argh(option)]
+	pub diff_from:        Option<String>,
+	/// show this save with an overlay of its differences to the save given with --diff-from
+	#[argh(option)]
+	pub diff_to:          Option<String>,
+	/// run the simulation without a window, rendering or player input, starting a new game right away
+	#[argh(switch)]
+	pub headless:         bool,
+	/// host a multiplayer game on the given port
+	#[argh(option)]
+	pub host:             Option<u16>,
+	/// join the multiplayer game at the given address, such as `example.org:7777`
+	#[argh(option)]
+	pub join:             Option<String>,
 }
 
 /// Game settings for CMP. Game settings are stored by [`confy`] in TOML format in a system-defined config path. For
@@ -24,13 +58,74 @@ pub struct CommandLineArguments {
 pub struct GameSettings {
 	/// Whether to enable VSync.
 	#[serde(default = "_true")]
-	pub use_vsync:  bool,
+	pub use_vsync:            bool,
 	/// Whether to show a detailed FPS display in the upper left corner of the game window.
 	#[serde(default = "_false")]
-	pub show_fps:   bool,
+	pub show_fps:             bool,
 	/// Whether to show various debugging information in the world.
 	#[serde(default = "_false")]
-	pub show_debug: bool,
+	pub show_debug:           bool,
+	/// Whether to replace UI motion (springy button animations and similar) with instant transitions.
+	#[serde(default = "_false")]
+	pub reduce_motion:        bool,
+	/// Whether to skip purely cosmetic world effects, such as buildings popping into place.
+	#[serde(default = "_false")]
+	pub reduced_effects:      bool,
+	/// Whether areas like pools and pitches subtly tint the colors of everything within them.
+	#[serde(default = "_true")]
+	pub ambient_grading:      bool,
+	/// Whether the picture darkens and changes its colors with the time of day.
+	#[serde(default = "_true")]
+	pub day_night_cycle:      bool,
+	/// Whether to keep the system's mouse cursor instead of the game's own cursor.
+	#[serde(default = "_false")]
+	pub system_cursor:        bool,
+	/// Whether background music plays. Turning it off pauses the current track.
+	#[serde(default = "_true")]
+	pub play_music:           bool,
+	/// Whether new games start with the tutorial.
+	#[serde(default = "_true")]
+	pub tutorial:             bool,
+	/// Sandbox setting: whether buildings are finished as soon as they are placed, without a construction phase, and
+	/// obstacles are cleared right away.
+	#[serde(default = "_false")]
+	pub instant_construction: bool,
+	/// Volume of the background music, between 0 (muted) and 1.
+	#[serde(default = "_one")]
+	pub music_volume:         f32,
+	/// Volume of interface sounds, between 0 (muted) and 1.
+	#[serde(default = "_one")]
+	pub ui_volume:            f32,
+	/// Volume of sound effects in the world, such as building, between 0 (muted) and 1.
+	#[serde(default = "_one")]
+	pub effects_volume:       f32,
+	/// Volume of the ambient nature sounds, between 0 (muted) and 1.
+	#[serde(default = "_one")]
+	pub ambient_volume:       f32,
+	/// Size factor of the game's own cursor, between 0.5 and 2.
+	#[serde(default = "_one")]
+	pub cursor_scale:         f32,
+	/// In-game hours between two captures of a time-lapse.
+	#[serde(default = "_time_lapse_hours")]
+	pub time_lapse_hours:     f32,
+	/// Accessibility: size factor of all UI, between 0.5 and 2, on top of the window's scale factor.
+	#[serde(default = "_one")]
+	pub ui_scale:             f32,
+	/// Accessibility: how much larger than normal UI text is.
+	#[serde(default)]
+	pub text_size:            TextSize,
+	/// Accessibility: whether overlays, notification borders and the save diff use colorblind-safe colors.
+	#[serde(default = "_false")]
+	pub colorblind_palette:   bool,
+	/// Number of backups of the previous version that are kept for every save slot, up to [`MAX_SAVE_BACKUPS`].
+	#[serde(default = "_save_backups")]
+	pub save_backups:         u8,
+	/// Positions of HUD panels that the player moved.
+	#[serde(default)]
+	pub hud_layout:           HudLayout,
+	/// Color lookup table forced over the whole picture for debugging, instead of the one chosen by the game.
+	#[serde(default)]
+	pub color_lut:            ColorLut,
 }
 
 fn _true() -> bool {
@@ -39,14 +134,61 @@ fn _true() -> bool {
 fn _false() -> bool {
 	false
 }
+fn _one() -> f32 {
+	1.
+}
+fn _time_lapse_hours() -> f32 {
+	2.
+}
+fn _save_backups() -> u8 {
+	3
+}
 
 impl Default for GameSettings {
 	fn default() -> Self {
-		Self { use_vsync: true, show_fps: false, show_debug: false }
+		Self {
+			use_vsync:            true,
+			show_fps:             false,
+			show_debug:           false,
+			reduce_motion:        false,
+			reduced_effects:      false,
+			ambient_grading:      true,
+			day_night_cycle:      true,
+			system_cursor:        false,
+			play_music:           true,
+			tutorial:             true,
+			instant_construction: false,
+			music_volume:         1.,
+			ui_volume:            1.,
+			effects_volume:       1.,
+			ambient_volume:       1.,
+			cursor_scale:         1.,
+			time_lapse_hours:     _time_lapse_hours(),
+			ui_scale:             1.,
+			text_size:            TextSize::Normal,
+			colorblind_palette:   false,
+			save_backups:         _save_backups(),
+			hud_layout:           HudLayout::default(),
+			color_lut:            ColorLut::Neutral,
+		}
 	}
 }
 
 impl GameSettings {
+	/// The colors that UI and overlays use to tell good from bad.
+	pub fn palette(&self) -> Palette {
+		if self.colorblind_palette {
+			Palette::ColorblindSafe
+		} else {
+			Palette::Standard
+		}
+	}
+
+	/// Number of backups that are kept for every save slot.
+	pub fn save_backup_count(&self) -> usize {
+		usize::from(self.save_backups.min(MAX_SAVE_BACKUPS))
+	}
+
 	pub fn from_arg_path(cli_arguments: &CommandLineArguments) -> Self {
 		let maybe_config = if let Some(alternate_settings_file) = &cli_arguments.settings_file {
 			confy::load_path(alternate_settings_file)
@@ -64,7 +206,43 @@ impl GameSettings {
 }
 
 pub const APP_NAME: &str = "cmp";
+/// Largest number of backups that are kept for every save slot.
+pub const MAX_SAVE_BACKUPS: u8 = 10;
 const CONFIG_NAME: &str = "game-settings";
+const KEY_BINDINGS_NAME: &str = "key-bindings";
+
+/// Contents of the key bindings file, which is stored next to the game settings. With an alternative settings file,
+/// the key bindings are stored in `key-bindings.toml` in the same directory.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct KeyBindings {
+	/// Key chords of every action, by action name.
+	#[serde(default)]
+	bindings: BTreeMap<String, Vec<String>>,
+}
+
+impl KeyBindings {
+	fn alternate_path(cli_arguments: &CommandLineArguments) -> Option<PathBuf> {
+		cli_arguments
+			.settings_file
+			.as_ref()
+			.map(|settings_file| settings_file.with_file_name(format!("{KEY_BINDINGS_NAME}.toml")))
+	}
+
+	fn load(cli_arguments: &CommandLineArguments) -> InputMap {
+		let maybe_bindings: Result<Self, _> = if let Some(path) = Self::alternate_path(cli_arguments) {
+			confy::load_path(path)
+		} else {
+			confy::load(APP_NAME, KEY_BINDINGS_NAME)
+		};
+		match maybe_bindings {
+			Err(why) => {
+				error!("Couldn’t load key bindings: {}, falling back to defaults.", why);
+				InputMap::default()
+			},
+			Ok(bindings) => InputMap::from_names(&bindings.bindings),
+		}
+	}
+}
 
 pub struct ConfigPlugin(pub Arc<CommandLineArguments>, pub Arc<GameSettings>);
 
@@ -74,8 +252,9 @@ pub struct CLIResource(pub Arc<CommandLineArguments>);
 impl Plugin for ConfigPlugin {
 	fn build(&self, app: &mut App) {
 		app.insert_resource(*self.1)
+			.insert_resource(KeyBindings::load(&self.0))
 			.insert_resource(CLIResource(self.0.clone()))
-			.add_systems(Update, (save_settings, modify_graphics_settings));
+			.add_systems(Update, (save_settings, save_key_bindings, modify_graphics_settings));
 	}
 }
 
@@ -92,8 +271,22 @@ fn save_settings(settings: Res<GameSettings>, cli_arguments: Res<CLIResource>) {
 	}
 }
 
-fn modify_graphics_settings(mut settings: ResMut<GameSettings>, keys: Res<ButtonInput<KeyCode>>) {
-	if keys.just_pressed(KeyCode::KeyV) && keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+fn save_key_bindings(input_map: Res<InputMap>, cli_arguments: Res<CLIResource>) {
+	if input_map.is_changed() {
+		let bindings = KeyBindings { bindings: input_map.to_names() };
+		let result = if let Some(path) = KeyBindings::alternate_path(&cli_arguments) {
+			confy::store_path(path, bindings)
+		} else {
+			confy::store(APP_NAME, KEY_BINDINGS_NAME, bindings)
+		};
+		if let Err(why) = result {
+			error!("Couldn’t save key bindings: {}", why);
+		}
+	}
+}
+
+fn modify_graphics_settings(mut settings: ResMut<GameSettings>, actions: ActionInput) {
+	if actions.just_pressed(Action::ToggleVsync) {
 		settings.use_vsync = !settings.use_vsync;
 	}
 }
@@ -0,0 +1,369 @@
+//! Developer console for manipulating the running game.
+//!
+//! The console is only available with `show_debug` enabled and is toggled with the backtick key. Every line typed into
+//! it is split at whitespace into a command name and its arguments, and run by the command of that name from the
+//! [`ConsoleCommands`] registry; `help` lists all commands. Modules add their own commands with
+//! [`ConsoleCommandExt::add_console_command`]. Commands run with exclusive world access, so they can do anything a
+//! system can.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+
+use bevy::ecs::system::SystemState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+
+use super::inspector::{component_names, inspect_fields};
+use super::{DebugVisualizations, ALL_DEBUG_VISUALIZATIONS};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::{world_to_engine_space, InGameCamera};
+use crate::input::{Action, ActionInput, InputMap};
+use crate::model::rollup::DailyTally;
+use crate::model::{GridPosition, ALL_BUILDABLES};
+use crate::ui::build::BuildRequests;
+use crate::util::text_input::{TextInput, TextInputSubmitted};
+
+/// Number of lines that the console remembers.
+const LOG_LENGTH: usize = 200;
+/// Number of lines shown above the input line.
+const SHOWN_LINES: usize = 24;
+/// Maximum length of a command line.
+const MAX_LINE_LENGTH: usize = 120;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Console>()
+			.init_resource::<DebugVisualizations>()
+			.add_console_command(
+				"spawn",
+				"spawn <buildable> <x> <y> [<x2> <y2>]",
+				"Builds the buildable as if dragged from the first to the second tile, and charges for it.",
+				spawn_command,
+			)
+			.add_console_command("money", "money <amount>", "Adds the amount to today's income.", money_command)
+			.add_console_command("teleport", "teleport <x> <y>", "Centers the camera on the tile.", teleport_command)
+			.add_console_command(
+				"dump",
+				"dump <entity>",
+				"Lists the components of the entity, such as 42 or 42v1, with the values of reflected ones.",
+				dump_command,
+			)
+			.add_console_command(
+				"debug",
+				"debug [<visualization>]",
+				"Toggles a debug visualization, or lists them all.",
+				debug_command,
+			)
+			.add_systems(Startup, create_console)
+			.add_systems(
+				Update,
+				(toggle_console, submit_console_line, run_console_commands, update_console)
+					.chain()
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			// The console's text input would take the keys of the pause menu.
+			.add_systems(OnExit(GameState::InGame), close_console);
+	}
+}
+
+/// What a command prints, or why it failed.
+pub type CommandResult = Result<String, String>;
+
+type CommandHandler = Box<dyn Fn(&mut World, &[&str]) -> CommandResult + Send + Sync>;
+
+struct ConsoleCommand {
+	usage: &'static str,
+	help:  &'static str,
+	run:   CommandHandler,
+}
+
+/// All commands of the console by name. Registered with [`ConsoleCommandExt::add_console_command`].
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(BTreeMap<&'static str, ConsoleCommand>);
+
+impl ConsoleCommands {
+	/// Runs a command line.
+	fn run(&self, world: &mut World, line: &str) -> CommandResult {
+		let mut words = line.split_whitespace();
+		let Some(name) = words.next() else {
+			return Ok(String::new());
+		};
+		let arguments = words.collect::<Vec<_>>();
+		// Help needs the registry itself, which is taken out of the world while commands run.
+		if name == "help" {
+			return Ok(self
+				.0
+				.values()
+				.map(|command| format!("{}: {}", command.usage, command.help))
+				.chain(["help: Lists all commands.".to_string()])
+				.collect::<Vec<_>>()
+				.join("\n"));
+		}
+		let command = self.0.get(name).ok_or_else(|| format!("Unknown command {name}. Type help for a list."))?;
+		(command.run)(world, &arguments).map_err(|why| format!("{why}\nUsage: {}", command.usage))
+	}
+}
+
+/// Extension point for adding commands to the developer console.
+pub trait ConsoleCommandExt {
+	/// Registers a console command. `run` receives the world and the command's arguments, and returns what the
+	/// console prints.
+	fn add_console_command(
+		&mut self,
+		name: &'static str,
+		usage: &'static str,
+		help: &'static str,
+		run: impl Fn(&mut World, &[&str]) -> CommandResult + Send + Sync + 'static,
+	) -> &mut Self;
+}
+
+impl ConsoleCommandExt for App {
+	fn add_console_command(
+		&mut self,
+		name: &'static str,
+		usage: &'static str,
+		help: &'static str,
+		run: impl Fn(&mut World, &[&str]) -> CommandResult + Send + Sync + 'static,
+	) -> &mut Self {
+		self.init_resource::<ConsoleCommands>();
+		self.world_mut().resource_mut::<ConsoleCommands>().0.insert(name, ConsoleCommand {
+			usage,
+			help,
+			run: Box::new(run),
+		});
+		self
+	}
+}
+
+/// Parses the argument at the index.
+pub fn argument<T: FromStr>(arguments: &[&str], index: usize, name: &str) -> Result<T, String> {
+	let argument = arguments.get(index).ok_or_else(|| format!("Missing {name}"))?;
+	argument.parse().map_err(|_| format!("Invalid {name} {argument}"))
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+struct Console {
+	open:    bool,
+	log:     VecDeque<String>,
+	/// Submitted lines that haven't run yet.
+	pending: Vec<String>,
+}
+
+impl Console {
+	fn print(&mut self, text: &str) {
+		self.log.extend(text.lines().map(ToString::to_string));
+		while self.log.len() > LOG_LENGTH {
+			self.log.pop_front();
+		}
+	}
+}
+
+#[derive(Component)]
+struct ConsolePanel;
+
+#[derive(Component)]
+struct ConsoleLog;
+
+#[derive(Component)]
+struct ConsoleInput;
+
+fn create_console(mut commands: Commands, asset_server: Res<AssetServer>) {
+	let font = TextFont {
+		font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+		font_size: 15.0,
+		..default()
+	};
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.),
+				display: Display::None,
+				flex_direction: FlexDirection::Column,
+				position_type: PositionType::Absolute,
+				padding: UiRect::all(Val::Px(5.)),
+				..default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.8)),
+			// Like the inspector, the console should always appear on top.
+			GlobalZIndex(1000),
+			ConsolePanel,
+		))
+		.with_children(|parent| {
+			parent.spawn((Text::default(), font.clone(), TextColor(Color::WHITE), ConsoleLog));
+			parent.spawn((
+				TextInput { max_length: MAX_LINE_LENGTH, ..default() },
+				font,
+				TextColor(Color::srgb(1., 0.8, 0.)),
+				ConsoleInput,
+			));
+		});
+}
+
+/// While the console is open, its text input takes all keys, so closing it is detected from the keyboard events.
+fn toggle_console(
+	settings: Res<GameSettings>,
+	actions: ActionInput,
+	input_map: Res<InputMap>,
+	mut typed: EventReader<KeyboardInput>,
+	mut console: ResMut<Console>,
+	mut inputs: Query<&mut TextInput, With<ConsoleInput>>,
+) {
+	let close_pressed = typed
+		.read()
+		.filter(|event| {
+			event.state.is_pressed()
+				&& (event.logical_key == Key::Escape
+					|| input_map.chords(Action::Console).iter().any(|chord| chord.key == event.key_code))
+		})
+		.count()
+		> 0;
+	let open = if console.open { !close_pressed } else { actions.just_pressed(Action::Console) };
+	let open = open && settings.show_debug;
+	if open == console.open {
+		return;
+	}
+	console.open = open;
+	for mut input in &mut inputs {
+		input.focused = open;
+		input.value.clear();
+	}
+}
+
+fn close_console(mut console: ResMut<Console>, mut inputs: Query<&mut TextInput, With<ConsoleInput>>) {
+	console.open = false;
+	for mut input in &mut inputs {
+		input.focused = false;
+	}
+}
+
+fn submit_console_line(
+	mut submitted: EventReader<TextInputSubmitted>,
+	mut console: ResMut<Console>,
+	mut inputs: Query<&mut TextInput, With<ConsoleInput>>,
+) {
+	for event in submitted.read() {
+		if let Ok(mut input) = inputs.get_mut(event.entity) {
+			console.pending.push(event.value.clone());
+			input.value.clear();
+		}
+	}
+}
+
+fn run_console_commands(world: &mut World) {
+	let lines = std::mem::take(&mut world.resource_mut::<Console>().pending);
+	for line in lines {
+		let result = world.resource_scope(|world, commands: Mut<ConsoleCommands>| commands.run(world, &line));
+		let mut console = world.resource_mut::<Console>();
+		console.print(&format!("> {line}"));
+		match result {
+			Ok(output) => console.print(&output),
+			Err(why) => console.print(&format!("Error: {why}")),
+		}
+	}
+}
+
+fn update_console(
+	console: Res<Console>,
+	mut panels: Query<&mut Node, With<ConsolePanel>>,
+	mut logs: Query<&mut Text, With<ConsoleLog>>,
+) {
+	if !console.is_changed() {
+		return;
+	}
+	for mut panel in &mut panels {
+		panel.display = if console.open { Display::Flex } else { Display::None };
+	}
+	let shown = console.log.iter().skip(console.log.len().saturating_sub(SHOWN_LINES));
+	let text = shown.cloned().collect::<Vec<_>>().join("\n");
+	for mut log in &mut logs {
+		log.0.clone_from(&text);
+	}
+}
+
+/// Lowercases the name and removes everything but letters and digits, so that "Flower Bed" can be typed as
+/// "flowerbed".
+fn simplify(name: &str) -> String {
+	name.chars().filter(char::is_ascii_alphanumeric).map(|character| character.to_ascii_lowercase()).collect()
+}
+
+fn spawn_command(world: &mut World, arguments: &[&str]) -> CommandResult {
+	let name = arguments.first().ok_or("Missing buildable")?;
+	let buildable = ALL_BUILDABLES
+		.into_iter()
+		.find(|buildable| simplify(&buildable.to_string()) == simplify(name))
+		.ok_or_else(|| {
+			let names = ALL_BUILDABLES.map(|buildable| simplify(&buildable.to_string()));
+			format!("Unknown buildable {name}. Buildables are {}.", names.join(", "))
+		})?;
+	let start = GridPosition::from((argument::<i32>(arguments, 1, "x")?, argument::<i32>(arguments, 2, "y")?, 0));
+	let end = if arguments.len() > 3 {
+		GridPosition::from((argument::<i32>(arguments, 3, "x2")?, argument::<i32>(arguments, 4, "y2")?, 0))
+	} else {
+		start
+	};
+
+	let mut state = SystemState::<BuildRequests>::new(world);
+	let mut build_requests = state.get_mut(world);
+	build_requests.check(buildable, start, end).map_err(|why| why.to_string())?;
+	build_requests.send(buildable, start, end);
+	state.apply(world);
+	Ok(format!("Building {buildable} from {start} to {end}"))
+}
+
+fn money_command(world: &mut World, arguments: &[&str]) -> CommandResult {
+	let amount = argument::<u64>(arguments, 0, "amount")?;
+	world.resource_mut::<DailyTally>().record_income(amount);
+	Ok(format!("Added {amount} to today's income"))
+}
+
+fn teleport_command(world: &mut World, arguments: &[&str]) -> CommandResult {
+	let tile = GridPosition::from((argument::<i32>(arguments, 0, "x")?, argument::<i32>(arguments, 1, "y")?, 0));
+	let mut cameras = world.query_filtered::<(&mut Transform, &OrthographicProjection), With<InGameCamera>>();
+	let (mut transform, projection) = cameras.get_single_mut(world).map_err(|why| why.to_string())?;
+	let target = world_to_engine_space(tile.0.as_vec3a() + Vec3A::new(0.5, 0.5, 0.));
+	let translation = (target - projection.area.center()).round();
+	transform.translation = translation.extend(transform.translation.z);
+	Ok(format!("Camera centered on {tile}"))
+}
+
+/// Entities are written like Bevy displays them, such as 42v1, or only with their index, such as 42.
+fn dump_command(world: &mut World, arguments: &[&str]) -> CommandResult {
+	let name = arguments.first().ok_or("Missing entity")?;
+	let index = name.split('v').next().unwrap_or_default();
+	let index = index.parse::<u32>().map_err(|_| format!("Invalid entity {name}"))?;
+	let entity = world
+		.entities()
+		.resolve_from_id(index)
+		.filter(|entity| world.entities().contains(*entity) && (!name.contains('v') || entity.to_string() == *name))
+		.ok_or_else(|| format!("Entity {name} doesn't exist"))?;
+
+	let mut lines = vec![format!("{entity}: {}", component_names(world, entity).join(", "))];
+	lines.extend(inspect_fields(world, entity).into_iter().map(|field| format!("  {}: {}", field.label, field.value)));
+	Ok(lines.join("\n"))
+}
+
+fn debug_command(world: &mut World, arguments: &[&str]) -> CommandResult {
+	let mut visualizations = world.resource_mut::<DebugVisualizations>();
+	let Some(name) = arguments.first() else {
+		return Ok(ALL_DEBUG_VISUALIZATIONS
+			.into_iter()
+			.map(|visualization| {
+				let state = if visualizations.hidden.contains(&visualization) { "off" } else { "on" };
+				format!("{}: {state}", visualization.name())
+			})
+			.collect::<Vec<_>>()
+			.join("\n"));
+	};
+	let visualization = ALL_DEBUG_VISUALIZATIONS
+		.into_iter()
+		.find(|visualization| visualization.name() == *name)
+		.ok_or_else(|| format!("Unknown visualization {name}"))?;
+	let shown = visualizations.toggle(visualization);
+	Ok(format!("{name} is {}", if shown { "on" } else { "off" }))
+}
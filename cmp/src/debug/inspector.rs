@@ -0,0 +1,333 @@
+//! In-game entity inspector based on Bevy reflection, for diagnosing world state without external tools.
+//!
+//! The inspector is only available with `show_debug` enabled and is toggled with F3. While it is open, typing filters
+//! the entity list by component type name. Up and down select an entity, Tab selects one of its fields, and Page Up and
+//! Page Down change the selected field; numbers are incremented and decremented, booleans are toggled.
+
+use std::any::TypeId;
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::reflect::{ReflectMut, ReflectRef};
+
+use crate::config::GameSettings;
+use crate::gamemode::GameSet;
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::input::{Action, ActionInput};
+
+/// Number of entities listed above and below the selected entity.
+const LISTED_ENTITY_RADIUS: usize = 8;
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Inspector>()
+			.add_systems(Startup, create_inspector)
+			.add_systems(Update, (handle_inspector_input, update_inspector).chain().in_set(GameSet::UI));
+	}
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+struct Inspector {
+	open:           bool,
+	/// Case-insensitive search term matched against component type names.
+	search:         String,
+	selected:       usize,
+	selected_field: usize,
+	/// Pending change of the selected field, requested by input handling and applied with world access.
+	pending_edit:   Option<Edit>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Edit {
+	Increment,
+	Decrement,
+}
+
+#[derive(Component)]
+struct InspectorPanel;
+
+#[derive(Component)]
+struct InspectorText;
+
+/// A single field of a reflected component, or an entire component that has no fields.
+pub(super) struct InspectedField {
+	component:        TypeId,
+	/// Index of the field within the component; [`None`] if the component is displayed as a whole.
+	index:            Option<usize>,
+	pub(super) label: String,
+	pub(super) value: String,
+}
+
+fn create_inspector(mut commands: Commands, asset_server: Res<AssetServer>) {
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(40.),
+				height: Val::Percent(100.),
+				right: Val::Px(0.),
+				display: Display::None,
+				position_type: PositionType::Absolute,
+				overflow: Overflow::clip(),
+				padding: UiRect::all(Val::Px(5.)),
+				..default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.8)),
+			// Like debug stats, the inspector should always appear on top.
+			GlobalZIndex(1000),
+			InspectorPanel,
+		))
+		.with_children(|parent| {
+			parent.spawn((
+				Text::default(),
+				TextFont {
+					font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+					font_size: 15.0,
+					..default()
+				},
+				TextColor(Color::WHITE),
+				InspectorText,
+			));
+		});
+}
+
+fn handle_inspector_input(
+	settings: Res<GameSettings>,
+	actions: ActionInput,
+	mut typed: EventReader<KeyboardInput>,
+	mut inspector: ResMut<Inspector>,
+) {
+	if actions.just_pressed(Action::Inspector) {
+		inspector.open = !inspector.open && settings.show_debug;
+	}
+	if !settings.show_debug {
+		inspector.open = false;
+	}
+	if !inspector.open {
+		typed.clear();
+		return;
+	}
+
+	for event in typed.read().filter(|event| event.state.is_pressed()) {
+		match &event.logical_key {
+			Key::Character(characters) if characters.chars().all(|character| !character.is_control()) => {
+				inspector.search.push_str(characters);
+				inspector.selected = 0;
+			},
+			Key::Backspace => {
+				inspector.search.pop();
+				inspector.selected = 0;
+			},
+			_ => {},
+		}
+	}
+
+	if actions.just_pressed(Action::CursorDown) {
+		inspector.selected += 1;
+		inspector.selected_field = 0;
+	}
+	if actions.just_pressed(Action::CursorUp) {
+		inspector.selected = inspector.selected.saturating_sub(1);
+		inspector.selected_field = 0;
+	}
+	if actions.just_pressed(Action::FocusNext) {
+		inspector.selected_field += 1;
+	}
+	if actions.just_pressed(Action::InspectorIncrement) {
+		inspector.pending_edit = Some(Edit::Increment);
+	}
+	if actions.just_pressed(Action::InspectorDecrement) {
+		inspector.pending_edit = Some(Edit::Decrement);
+	}
+}
+
+fn update_inspector(world: &mut World) {
+	let mut inspector = world.resource::<Inspector>().clone();
+	world.resource_mut::<Inspector>().pending_edit = None;
+
+	let mut panels = world.query_filtered::<&mut Node, With<InspectorPanel>>();
+	for mut panel in panels.iter_mut(world) {
+		panel.display = if inspector.open { Display::Flex } else { Display::None };
+	}
+	if !inspector.open {
+		return;
+	}
+
+	let entities = matching_entities(world, &inspector.search);
+	inspector.selected = inspector.selected.min(entities.len().saturating_sub(1));
+	let selected_entity = entities.get(inspector.selected).copied();
+
+	if let (Some(entity), Some(edit)) = (selected_entity, inspector.pending_edit) {
+		let fields = inspect_fields(world, entity);
+		if let Some(field) = fields.get(inspector.selected_field % fields.len().max(1)) {
+			edit_field(world, entity, field, edit);
+		}
+	}
+
+	let mut text = format!(
+		"Entity inspector (F3 to close)\nSearch: \"{}\" ({} matching entities)\n\n",
+		inspector.search,
+		entities.len()
+	);
+	let first_listed = inspector.selected.saturating_sub(LISTED_ENTITY_RADIUS);
+	for (index, entity) in entities.iter().enumerate().skip(first_listed).take(LISTED_ENTITY_RADIUS * 2 + 1) {
+		if index != inspector.selected {
+			text += &format!("  {}: {}\n", entity, component_names(world, *entity).join(", "));
+			continue;
+		}
+		text += &format!("> {}\n", entity);
+		let fields = inspect_fields(world, *entity);
+		let selected_field = inspector.selected_field % fields.len().max(1);
+		for (field_index, field) in fields.iter().enumerate() {
+			let marker = if field_index == selected_field { ">" } else { " " };
+			text += &format!("    {} {}: {}\n", marker, field.label, field.value);
+		}
+	}
+
+	let mut texts = world.query_filtered::<&mut Text, With<InspectorText>>();
+	for mut inspector_text in texts.iter_mut(world) {
+		inspector_text.0.clone_from(&text);
+	}
+}
+
+/// Short type names of all components of the entity, sorted alphabetically.
+pub(super) fn component_names(world: &World, entity: Entity) -> Vec<String> {
+	let Ok(entity) = world.get_entity(entity) else {
+		return Vec::new();
+	};
+	let mut names = entity
+		.archetype()
+		.components()
+		.filter_map(|component| world.components().get_info(component))
+		.map(|info| disqualify(info.name()))
+		.collect::<Vec<_>>();
+	names.sort();
+	names
+}
+
+/// Removes module paths from a type name, so `cmp::model::pitch::Pitch` becomes `Pitch`.
+fn disqualify(type_name: &str) -> String {
+	type_name.split('<').next().unwrap_or(type_name).rsplit("::").next().unwrap_or(type_name).to_string()
+}
+
+fn matching_entities(world: &World, search: &str) -> Vec<Entity> {
+	let search = search.to_lowercase();
+	let mut entities = world
+		.iter_entities()
+		.map(|entity| entity.id())
+		.filter(|entity| {
+			search.is_empty()
+				|| component_names(world, *entity).iter().any(|name| name.to_lowercase().contains(&search))
+		})
+		.collect::<Vec<_>>();
+	entities.sort();
+	entities
+}
+
+/// Lists all fields of all reflected components of the entity, sorted by component name.
+pub(super) fn inspect_fields(world: &World, entity: Entity) -> Vec<InspectedField> {
+	let type_registry = world.resource::<AppTypeRegistry>().read();
+	let Ok(entity_ref) = world.get_entity(entity) else {
+		return Vec::new();
+	};
+
+	let mut components = entity_ref
+		.archetype()
+		.components()
+		.filter_map(|component| world.components().get_info(component)?.type_id())
+		.filter_map(|type_id| {
+			let registration = type_registry.get(type_id)?;
+			let component = registration.data::<ReflectComponent>()?.reflect(entity_ref)?;
+			Some((registration.type_info().type_path_table().short_path(), type_id, component))
+		})
+		.collect::<Vec<_>>();
+	components.sort_by_key(|(name, ..)| *name);
+
+	let mut fields = Vec::new();
+	for (name, type_id, component) in components {
+		match component.reflect_ref() {
+			ReflectRef::Struct(component_struct) if component_struct.field_len() > 0 =>
+				fields.extend((0 .. component_struct.field_len()).filter_map(|index| {
+					Some(InspectedField {
+						component: type_id,
+						index:     Some(index),
+						label:     format!("{}.{}", name, component_struct.name_at(index)?),
+						value:     format!("{:?}", component_struct.field_at(index)?),
+					})
+				})),
+			ReflectRef::TupleStruct(tuple_struct) if tuple_struct.field_len() > 0 =>
+				fields.extend((0 .. tuple_struct.field_len()).filter_map(|index| {
+					Some(InspectedField {
+						component: type_id,
+						index:     Some(index),
+						label:     format!("{}.{}", name, index),
+						value:     format!("{:?}", tuple_struct.field(index)?),
+					})
+				})),
+			_ => fields.push(InspectedField {
+				component: type_id,
+				index:     None,
+				label:     name.to_string(),
+				value:     format!("{:?}", component.as_partial_reflect()),
+			}),
+		}
+	}
+	fields
+}
+
+fn edit_field(world: &mut World, entity: Entity, field: &InspectedField, edit: Edit) {
+	let Some(index) = field.index else {
+		return;
+	};
+	let type_registry = world.resource::<AppTypeRegistry>().clone();
+	let type_registry = type_registry.read();
+	let Some(reflect_component) = type_registry.get_type_data::<ReflectComponent>(field.component) else {
+		return;
+	};
+	let Ok(entity) = world.get_entity_mut(entity) else {
+		return;
+	};
+	let Some(mut component) = reflect_component.reflect_mut(entity) else {
+		return;
+	};
+
+	let value = match component.reflect_mut() {
+		ReflectMut::Struct(component_struct) => component_struct.field_at_mut(index),
+		ReflectMut::TupleStruct(tuple_struct) => tuple_struct.field_mut(index),
+		_ => None,
+	};
+	if let Some(value) = value {
+		edit_value(value, edit);
+	}
+}
+
+/// Toggles booleans and increments or decrements numbers by one. Other values are left unchanged.
+fn edit_value(value: &mut dyn PartialReflect, edit: Edit) {
+	let sign = match edit {
+		Edit::Increment => 1,
+		Edit::Decrement => -1,
+	};
+	if let Some(boolean) = value.try_downcast_mut::<bool>() {
+		*boolean = !*boolean;
+		return;
+	}
+	macro_rules! edit_number {
+		($($number:ty),*) => {
+			$(if let Some(number) = value.try_downcast_mut::<$number>() {
+				*number = if sign > 0 { *number + (1 as $number) } else { *number - (1 as $number) };
+				return;
+			})*
+		};
+	}
+	edit_number!(f32, f64, i8, i16, i32, i64, isize);
+	macro_rules! edit_unsigned {
+		($($number:ty),*) => {
+			$(if let Some(number) = value.try_downcast_mut::<$number>() {
+				*number = if sign > 0 { number.saturating_add(1) } else { number.saturating_sub(1) };
+				return;
+			})*
+		};
+	}
+	edit_unsigned!(u8, u16, u32, u64, usize);
+}
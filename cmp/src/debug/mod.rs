@@ -2,10 +2,65 @@ use std::collections::VecDeque;
 use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 
 use crate::config::GameSettings;
 use crate::graphics::library::{font_for, FontStyle, FontWeight};
 
+pub mod console;
+pub mod inspector;
+pub mod save_diff;
+pub mod soak;
+
+/// Debugging information drawn into the world while `show_debug` is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugVisualization {
+	/// Edges of the nav meshes and connections between their clusters.
+	NavMesh,
+	/// The path between the last clicked tiles.
+	Pathfinding,
+	/// Index of the area that every tile belongs to.
+	Areas,
+}
+
+pub const ALL_DEBUG_VISUALIZATIONS: [DebugVisualization; 3] =
+	[DebugVisualization::NavMesh, DebugVisualization::Pathfinding, DebugVisualization::Areas];
+
+impl DebugVisualization {
+	/// Name of the visualization in the developer console.
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::NavMesh => "navmesh",
+			Self::Pathfinding => "pathfinding",
+			Self::Areas => "areas",
+		}
+	}
+}
+
+/// Debug visualizations that were turned off in the developer console. All others are shown while `show_debug` is
+/// enabled.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct DebugVisualizations {
+	hidden: HashSet<DebugVisualization>,
+}
+
+impl DebugVisualizations {
+	/// Whether the visualization is drawn.
+	pub fn shows(&self, settings: &GameSettings, visualization: DebugVisualization) -> bool {
+		settings.show_debug && !self.hidden.contains(&visualization)
+	}
+
+	/// Turns the visualization off if it was on and vice versa, returning whether it is on now.
+	pub fn toggle(&mut self, visualization: DebugVisualization) -> bool {
+		if self.hidden.remove(&visualization) {
+			true
+		} else {
+			self.hidden.insert(visualization);
+			false
+		}
+	}
+}
+
 // Account for up to 600fps and the 10 second metrics.
 const FRAME_TIMES_COUNT: usize = 600 * 11;
 /// Marker component for the text that’s responsible for performance statistics display.
@@ -91,11 +146,7 @@ pub fn create_stats(mut commands: Commands, asset_server: Res<AssetServer>) {
 		});
 }
 
-pub fn print_stats(
-	time: Res<Time<Real>>,
-	settings: Res<GameSettings>,
-	mut stat_ui: Query<(&mut Text, &mut StatUI)>,
-) {
+pub fn print_stats(time: Res<Time<Real>>, settings: Res<GameSettings>, mut stat_ui: Query<(&mut Text, &mut StatUI)>) {
 	let (mut ui, mut stats) = stat_ui.single_mut();
 
 	stats.last_frame_times.push_front(time.delta());
@@ -104,10 +155,10 @@ pub fn print_stats(
 	}
 
 	if settings.show_fps {
-		let last_second_avg = stats.average(Duration::SECOND);
-		let last_second_95p = stats.percentile(Duration::SECOND, 0.95);
-		let last_10s_avg = stats.average(Duration::SECOND * 10);
-		let last_10s_95p = stats.percentile(Duration::SECOND * 10, 0.95);
+		let last_second_avg = stats.average(Duration::from_secs(1));
+		let last_second_95p = stats.percentile(Duration::from_secs(1), 0.95);
+		let last_10s_avg = stats.average(Duration::from_secs(10));
+		let last_10s_95p = stats.percentile(Duration::from_secs(10), 0.95);
 		let worst = stats.worst();
 
 		*ui = Text(format!(
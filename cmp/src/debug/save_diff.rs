@@ -0,0 +1,266 @@
+//! Visual diff between two saves, started with the `--diff-from` and `--diff-to` command-line arguments.
+//!
+//! Both saves are read without loading them into the world, and their ground tiles and other objects are compared.
+//! Tiles are identified by their position; other objects, such as buildings and fences, by their components and their
+//! position, since entities have different identities in every save. Then the newer save is loaded with a paused
+//! simulation, and a colored overlay marks added (green), removed (red) and changed (yellow) tiles and objects; with
+//! the colorblind-safe palette, added ones are blue instead. Tiles are covered in the color, while objects get a
+//! colored square on their tile, so that they can be told apart from the ground. This helps with debugging save
+//! migrations and checking that replays are deterministic.
+
+use std::any::TypeId;
+
+use bevy::color::palettes::css::{LIME, RED, YELLOW};
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::scene::ron;
+use bevy::scene::serde::SceneDeserializer;
+use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+
+use crate::config::{CLIResource, GameSettings};
+use crate::gamemode::{GameSpeed, GameState};
+use crate::graphics::library::image_for_ground;
+use crate::graphics::palette::{Palette, SAFE_SKY_BLUE, SAFE_VERMILLION, SAFE_YELLOW};
+use crate::graphics::{ObjectPriority, TILE_WIDTH};
+use crate::model::{GridBox, GridPosition, GroundKind, GroundMap};
+use crate::save::LoadSave;
+use crate::ui::dialog::OpenDialog;
+
+/// Opacity of the diff overlay, so that the world underneath remains recognizable.
+const OVERLAY_ALPHA: f32 = 0.6;
+/// Size of the squares marking objects, which is about half a tile.
+const OBJECT_MARKER_SIZE: Vec2 = Vec2::splat(TILE_WIDTH / 2.);
+
+pub struct SaveDiffPlugin;
+
+impl Plugin for SaveDiffPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(PostStartup, start_save_diff)
+			.add_systems(
+				OnTransition { exited: GameState::Loading, entered: GameState::InGame },
+				show_save_diff.run_if(resource_exists::<SaveDiff>),
+			)
+			.add_systems(OnTransition { exited: GameState::Paused, entered: GameState::MainMenu }, close_save_diff);
+	}
+}
+
+/// How a tile or object differs between the two saves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Difference {
+	Added,
+	Removed,
+	Changed,
+}
+
+impl Difference {
+	fn color(&self, palette: Palette) -> Color {
+		match (self, palette) {
+			(Self::Added, Palette::Standard) => LIME.into(),
+			(Self::Added, Palette::ColorblindSafe) => SAFE_SKY_BLUE,
+			(Self::Removed, Palette::Standard) => RED.into(),
+			(Self::Removed, Palette::ColorblindSafe) => SAFE_VERMILLION,
+			(Self::Changed, Palette::Standard) => YELLOW.into(),
+			(Self::Changed, Palette::ColorblindSafe) => SAFE_YELLOW,
+		}
+		.with_alpha(OVERLAY_ALPHA)
+	}
+}
+
+/// Kind of an object that isn't a tile, described by the names of its components.
+type ObjectKind = String;
+
+/// The contents of a save that are compared.
+#[derive(Debug, Default)]
+struct SaveSnapshot {
+	tiles:   HashMap<GridPosition, GroundKind>,
+	/// Description of all components of every object, for detecting changed objects.
+	objects: HashMap<(ObjectKind, GridPosition), String>,
+}
+
+impl SaveSnapshot {
+	/// Reads the world data of a save without loading it.
+	fn read(load: &LoadSave, type_registry: &TypeRegistry) -> anyhow::Result<Self> {
+		let mut deserializer = ron::Deserializer::from_bytes(load.world_data())?;
+		let scene = SceneDeserializer { type_registry }.deserialize(&mut deserializer)?;
+
+		// Hierarchy components refer to entities, whose identities differ between saves.
+		let ignored_components = [TypeId::of::<Parent>(), TypeId::of::<Children>()];
+		let mut snapshot = Self::default();
+		// Newer saves store the ground map directly, older ones store every tile as an entity.
+		let ground_map = scene
+			.resources
+			.iter()
+			.filter(|resource| {
+				resource.get_represented_type_info().is_some_and(|info| info.type_id() == TypeId::of::<GroundMap>())
+			})
+			.find_map(|resource| GroundMap::from_reflect(&**resource));
+		if let Some(ground_map) = ground_map {
+			snapshot.tiles.extend(ground_map.kinds());
+		}
+		for entity in &scene.entities {
+			let components = entity
+				.components
+				.iter()
+				.filter_map(|component| Some((component.get_represented_type_info()?, component)))
+				.filter(|(info, _)| !ignored_components.contains(&info.type_id()))
+				.collect::<Vec<_>>();
+			let find = |type_id| components.iter().find(|(info, _)| info.type_id() == type_id).map(|(_, c)| &***c);
+
+			let position = find(TypeId::of::<GridPosition>()).and_then(GridPosition::from_reflect).or_else(|| {
+				find(TypeId::of::<GridBox>()).and_then(GridBox::from_reflect).map(|grid_box| grid_box.corner)
+			});
+			let Some(position) = position else {
+				continue;
+			};
+			if let Some(kind) = find(TypeId::of::<GroundKind>()).and_then(GroundKind::from_reflect) {
+				snapshot.tiles.insert(position, kind);
+				continue;
+			}
+
+			let mut names = components.iter().map(|(info, _)| info.type_path_table().short_path()).collect::<Vec<_>>();
+			names.sort_unstable();
+			let mut descriptions = components.iter().map(|(_, component)| format!("{component:?}")).collect::<Vec<_>>();
+			descriptions.sort_unstable();
+			snapshot.objects.insert((names.join(" + "), position), descriptions.join(", "));
+		}
+		Ok(snapshot)
+	}
+}
+
+/// Differences between two saves.
+#[derive(Resource, Debug, Default)]
+struct SaveDiff {
+	from:    String,
+	to:      String,
+	/// Differing tiles, with the tile kind in the newer save, or in the older save for removed tiles.
+	tiles:   Vec<(GridPosition, GroundKind, Difference)>,
+	objects: Vec<(ObjectKind, GridPosition, Difference)>,
+}
+
+impl SaveDiff {
+	fn compare(from: String, to: String, old: &SaveSnapshot, new: &SaveSnapshot) -> Self {
+		let mut diff = Self { from, to, ..Default::default() };
+		for (position, kind) in &new.tiles {
+			match old.tiles.get(position) {
+				None => diff.tiles.push((*position, *kind, Difference::Added)),
+				Some(old_kind) if old_kind != kind => diff.tiles.push((*position, *kind, Difference::Changed)),
+				Some(_) => {},
+			}
+		}
+		for (position, kind) in &old.tiles {
+			if !new.tiles.contains_key(position) {
+				diff.tiles.push((*position, *kind, Difference::Removed));
+			}
+		}
+		for ((kind, position), description) in &new.objects {
+			match old.objects.get(&(kind.clone(), *position)) {
+				None => diff.objects.push((kind.clone(), *position, Difference::Added)),
+				Some(old_description) if old_description != description =>
+					diff.objects.push((kind.clone(), *position, Difference::Changed)),
+				Some(_) => {},
+			}
+		}
+		for (kind, position) in old.objects.keys() {
+			if !new.objects.contains_key(&(kind.clone(), *position)) {
+				diff.objects.push((kind.clone(), *position, Difference::Removed));
+			}
+		}
+		diff
+	}
+
+	fn count(&self, difference: Difference) -> usize {
+		self.tiles.iter().filter(|(.., tile_difference)| *tile_difference == difference).count()
+			+ self.objects.iter().filter(|(.., object_difference)| *object_difference == difference).count()
+	}
+}
+
+/// Overlay sprites marking a difference.
+#[derive(Component, Clone, Copy, Debug)]
+struct SaveDiffOverlay;
+
+fn start_save_diff(
+	cli_arguments: Res<CLIResource>,
+	type_registry: Res<AppTypeRegistry>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut exit: EventWriter<AppExit>,
+	mut commands: Commands,
+) {
+	let (Some(from), Some(to)) = (&cli_arguments.diff_from, &cli_arguments.diff_to) else {
+		if cli_arguments.diff_from.is_some() || cli_arguments.diff_to.is_some() {
+			error!("Diffing saves requires both --diff-from and --diff-to");
+			exit.send(AppExit::error());
+		}
+		return;
+	};
+	let type_registry = type_registry.read();
+	let read = |save_name: &String| -> anyhow::Result<_> {
+		let load = LoadSave::new(save_name.clone())?;
+		Ok((SaveSnapshot::read(&load, &type_registry)?, load))
+	};
+	let ((old, _), (new, load)) = match read(from).and_then(|old| Ok((old, read(to)?))) {
+		Ok(snapshots) => snapshots,
+		Err(why) => {
+			error!("Couldn’t read saves to diff: {why}");
+			exit.send(AppExit::error());
+			return;
+		},
+	};
+
+	let diff = SaveDiff::compare(from.clone(), to.clone(), &old, &new);
+	info!(
+		"Diff from save {from} to {to}: {} added, {} removed, {} changed",
+		diff.count(Difference::Added),
+		diff.count(Difference::Removed),
+		diff.count(Difference::Changed)
+	);
+	for (kind, position, difference) in &diff.objects {
+		debug!("{difference:?} {kind} at {position:?}");
+	}
+	commands.insert_resource(diff);
+	commands.insert_resource(load);
+	next_state.set(GameState::Loading);
+}
+
+fn show_save_diff(
+	diff: Res<SaveDiff>,
+	mut speed: ResMut<GameSpeed>,
+	mut dialogs: EventWriter<OpenDialog>,
+	settings: Res<GameSettings>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	speed.paused = true;
+	for (position, kind, difference) in &diff.tiles {
+		commands.spawn((*position, ObjectPriority::Overlay, SaveDiffOverlay, Sprite {
+			color: difference.color(settings.palette()),
+			image: asset_server.load(image_for_ground(*kind)),
+			..Default::default()
+		}));
+	}
+	for (_, position, difference) in &diff.objects {
+		commands.spawn((*position, ObjectPriority::Overlay, SaveDiffOverlay, Sprite {
+			color: difference.color(settings.palette()),
+			custom_size: Some(OBJECT_MARKER_SIZE),
+			..Default::default()
+		}));
+	}
+
+	dialogs.send(
+		OpenDialog::new("Save diff")
+			.paragraph(format!(
+				"Showing save “{}” with the differences to save “{}”. The simulation is paused.",
+				diff.to, diff.from
+			))
+			.property("Added", diff.count(Difference::Added).to_string())
+			.property("Removed", diff.count(Difference::Removed).to_string())
+			.property("Changed", diff.count(Difference::Changed).to_string()),
+	);
+}
+
+fn close_save_diff(overlays: Query<Entity, With<SaveDiffOverlay>>, mut commands: Commands) {
+	for overlay in &overlays {
+		commands.entity(overlay).despawn_recursive();
+	}
+	commands.remove_resource::<SaveDiff>();
+}
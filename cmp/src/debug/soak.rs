@@ -0,0 +1,263 @@
+//! Long-running soak test, started with the `--soak-days` command-line argument.
+//!
+//! The soak test runs the simulation at high speed while a scripted agent performs random build actions, like a very
+//! impatient player would. At the end of every soak day, world invariants are checked, and the money ledger of the day
+//! is checked to add up with the daily history. Once all days have passed, a
+//! JSON report with per-day statistics and all invariant violations is written to [`REPORT_PATH`] and the game exits,
+//! with a failure exit code if any invariant was violated. This catches slow leaks and drift that short play sessions
+//! never run into.
+//!
+//! Soak days are the game's own days, so the checks run right after each day's rollup. Together with `--headless`, the
+//! soak test runs without a window, for example in CI.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use serde_derive::Serialize;
+
+use crate::config::CLIResource;
+use crate::gamemode::{GameSet, GameSpeed, GameState};
+use crate::model::area::{Area, ImmutableArea, Pool};
+use crate::model::commerce::CommercialTally;
+use crate::model::rollup::{DailyHistory, DayClock, DayEnded, DaySummary, RollupSet};
+use crate::model::{ActorPosition, GridPosition, GroundKind, GroundMap, Pitch, ALL_BUILDABLES};
+use crate::ui::build::BuildRequests;
+
+/// Speed-up of simulated time during the soak test.
+const SOAK_SPEED: f32 = 16.;
+/// Simulated time between two actions of the scripted agent.
+const ACTION_INTERVAL: Duration = Duration::from_secs(2);
+/// The agent builds within this many tiles of the world origin.
+const ACTION_RADIUS: i32 = 40;
+/// Largest extent of a single build action.
+const MAX_ACTION_SIZE: i32 = 6;
+/// Where the soak report is written.
+const REPORT_PATH: &str = "soak-report.json";
+
+pub struct SoakPlugin;
+
+impl Plugin for SoakPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Startup, start_soak)
+			.add_systems(
+				Update,
+				soak_agent.run_if(resource_exists::<Soak>).in_set(GameSet::Input).in_set(GameState::InGame),
+			)
+			.add_systems(
+				FixedUpdate,
+				finish_soak_day
+					.run_if(resource_exists::<Soak>.and(on_event::<DayEnded>))
+					.after(RollupSet::Notify)
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// State of a running soak test.
+#[derive(Resource, Debug)]
+struct Soak {
+	days:         u32,
+	/// Simulated time since the agent's last action.
+	since_action: Duration,
+	actions:      u32,
+	frames:       u32,
+	day_started:  Instant,
+	/// Profit of all soak days, as their summaries reported it when the days ended.
+	total_profit: i64,
+	report:       SoakReport,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct SoakReport {
+	total_violations: usize,
+	days:             Vec<DayReport>,
+}
+
+#[derive(Serialize, Debug)]
+struct DayReport {
+	day:                   u32,
+	entities:              usize,
+	tiles:                 usize,
+	areas:                 usize,
+	actions:               u32,
+	profit:                i64,
+	average_frame_time_ms: f64,
+	violations:            Vec<String>,
+}
+
+fn start_soak(
+	cli_arguments: Res<CLIResource>,
+	mut virtual_time: ResMut<Time<Virtual>>,
+	mut speed: ResMut<GameSpeed>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut commands: Commands,
+) {
+	let Some(days) = cli_arguments.soak_days else {
+		return;
+	};
+	info!("Starting soak test over {days} days");
+	// Skip the main menu.
+	next_state.set(GameState::Loading);
+	speed.multiplier = SOAK_SPEED;
+	// Allow fixed updates to catch up with the sped-up time instead of slowing it down.
+	virtual_time.set_max_delta(Duration::from_secs_f32(SOAK_SPEED));
+	commands.insert_resource(Soak {
+		days,
+		since_action: Duration::ZERO,
+		actions: 0,
+		frames: 0,
+		day_started: Instant::now(),
+		total_profit: 0,
+		report: SoakReport::default(),
+	});
+}
+
+/// Scripted agent performing random build actions.
+fn soak_agent(time: Res<Time>, mut soak: ResMut<Soak>, mut build_requests: BuildRequests) {
+	soak.frames += 1;
+	soak.since_action += time.delta();
+	while soak.since_action >= ACTION_INTERVAL {
+		soak.since_action -= ACTION_INTERVAL;
+		soak.actions += 1;
+
+		let buildable = ALL_BUILDABLES[fastrand::usize(.. ALL_BUILDABLES.len())];
+		let start = GridPosition::from((
+			fastrand::i32(-ACTION_RADIUS ..= ACTION_RADIUS),
+			fastrand::i32(-ACTION_RADIUS ..= ACTION_RADIUS),
+			0,
+		));
+		let end = start + IVec3::new(fastrand::i32(0 .. MAX_ACTION_SIZE), fastrand::i32(0 .. MAX_ACTION_SIZE), 0);
+		build_requests.send(buildable, start, end);
+	}
+}
+
+fn finish_soak_day(
+	mut soak: ResMut<Soak>,
+	ground_map: Res<GroundMap>,
+	tiles: Query<(&GridPosition, &GroundKind)>,
+	actors: Query<(Entity, &ActorPosition)>,
+	transforms: Query<(Entity, &Transform)>,
+	pools: Query<&Area, With<Pool>>,
+	pitches: Query<(Option<&Area>, Option<&ImmutableArea>), With<Pitch>>,
+	entities: Query<Entity>,
+	clock: Res<DayClock>,
+	history: Res<DailyHistory>,
+	commercial_tally: Res<CommercialTally>,
+	mut exit: EventWriter<AppExit>,
+) {
+	let mut violations = Vec::new();
+	for (entity, position) in &actors {
+		if !position.is_finite() {
+			violations.push(format!("{entity} has a non-finite actor position {:?}", position.0));
+		}
+	}
+	for (entity, transform) in &transforms {
+		if !transform.is_finite() {
+			violations.push(format!("{entity} has a non-finite transform {transform:?}"));
+		}
+	}
+	for (position, entity, kind) in ground_map.iter() {
+		match tiles.get(entity) {
+			Err(_) => violations.push(format!("ground map entry at {position:?} refers to missing tile {entity}")),
+			Ok((tile_position, tile_kind)) if *tile_position != position || *tile_kind != kind => violations.push(
+				format!("ground map has {kind} at {position:?}, but tile {entity} is {tile_kind} at {tile_position:?}"),
+			),
+			Ok(_) => {},
+		}
+	}
+	let pitch_areas = pitches.iter().filter_map(|(area, immutable_area)| area.or(immutable_area.map(|area| &area.0)));
+	let areas =
+		pools.iter().map(|area| (area, GroundKind::PoolPath)).chain(pitch_areas.map(|area| (area, Pitch::GROUND_TYPE)));
+	let mut area_count = 0;
+	for (area, expected_kind) in areas {
+		area_count += 1;
+		for tile in area.tiles_iter() {
+			let kind = ground_map.kind_of(&tile);
+			if kind != Some(expected_kind) {
+				violations.push(format!("area tile at {tile:?} should be {expected_kind}, but is {kind:?}"));
+			}
+		}
+	}
+
+	// The day has already been counted, so the day that ended is the one before the current day.
+	let ended_day = clock.day() - 1;
+	let mut profit = 0;
+	match history.last() {
+		Some(summary) if summary.day == ended_day => {
+			profit = summary.profit();
+			soak.total_profit += profit;
+			let shops = commercial_tally.last_day;
+			if summary.income < shops.revenue {
+				violations.push(format!(
+					"day {ended_day} has an income of {}, but shops alone earned {}",
+					summary.income, shops.revenue
+				));
+			}
+			if summary.expenses < shops.operating_costs {
+				violations.push(format!(
+					"day {ended_day} has expenses of {}, but shops alone cost {}",
+					summary.expenses, shops.operating_costs
+				));
+			}
+			if let Some(satisfaction) =
+				summary.average_satisfaction.filter(|satisfaction| !(0. ..= 1.).contains(satisfaction))
+			{
+				violations.push(format!("day {ended_day} has an average satisfaction of {satisfaction}"));
+			}
+		},
+		last => violations.push(format!(
+			"the daily history ends with day {:?} instead of day {ended_day}",
+			last.map(|summary| summary.day)
+		)),
+	}
+	if let Some(days) = history.0.windows(2).find(|days| days[1].day != days[0].day + 1) {
+		violations.push(format!("the daily history skips from day {} to day {}", days[0].day, days[1].day));
+	}
+	let history_profit = history.0.iter().map(DaySummary::profit).sum::<i64>();
+	if history_profit != soak.total_profit {
+		violations.push(format!(
+			"the daily history adds up to a profit of {history_profit}, but the days reported {}",
+			soak.total_profit
+		));
+	}
+
+	for violation in &violations {
+		error!("Soak invariant violated: {violation}");
+	}
+	let day = soak.report.days.len() as u32 + 1;
+	let day_report = DayReport {
+		day,
+		entities: entities.iter().count(),
+		tiles: ground_map.iter().count(),
+		areas: area_count,
+		actions: soak.actions,
+		profit,
+		average_frame_time_ms: soak.day_started.elapsed().as_secs_f64() * 1000. / soak.frames.max(1) as f64,
+		violations,
+	};
+	info!("Soak day {day} finished: {} entities, {} violations", day_report.entities, day_report.violations.len());
+	soak.report.total_violations += day_report.violations.len();
+	soak.report.days.push(day_report);
+	soak.actions = 0;
+	soak.frames = 0;
+	soak.day_started = Instant::now();
+
+	if day < soak.days {
+		return;
+	}
+	let result = serde_json::to_string_pretty(&soak.report)
+		.map_err(anyhow::Error::from)
+		.and_then(|json| std::fs::write(REPORT_PATH, json).map_err(anyhow::Error::from));
+	if let Err(why) = result {
+		error!("Couldn’t write soak report to {REPORT_PATH}: {why}");
+		exit.send(AppExit::error());
+	} else if soak.report.total_violations > 0 {
+		error!("Soak test failed with {} violations; see {REPORT_PATH}", soak.report.total_violations);
+		exit.send(AppExit::error());
+	} else {
+		info!("Soak test passed; see {REPORT_PATH}");
+		exit.send(AppExit::Success);
+	}
+}
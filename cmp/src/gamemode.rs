@@ -1,18 +1,95 @@
 use bevy::prelude::*;
 
+use crate::input::{Action, ActionInput, InputState};
+
 /// Current game state, affects how game runs.
 #[derive(States, SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
 pub enum GameState {
+	/// The main menu is shown, before any game was started.
 	#[default]
 	MainMenu,
+	/// Commonly used assets are being loaded before entering the game.
+	Loading,
+	/// A game is being played.
 	InGame,
+	/// The game is paused behind the pause menu.
 	Paused,
 }
 
-pub fn pause_fixed_timer(state: Res<State<GameState>>, mut game_time: ResMut<Time<Virtual>>) {
-	if state.get() != &GameState::InGame {
+/// How fast the simulation runs. Pausing keeps the speed, so that resuming continues at the same speed.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct GameSpeed {
+	/// Whether the simulation is paused. While paused, the camera, menus and inspection keep working.
+	pub paused:     bool,
+	/// Factor by which simulated time runs faster than real time; one of [`GameSpeed::MULTIPLIERS`] in regular play.
+	pub multiplier: f32,
+}
+
+impl GameSpeed {
+	/// The speeds that the player can choose.
+	pub const MULTIPLIERS: [f32; 3] = [1., 2., 3.];
+}
+
+impl Default for GameSpeed {
+	fn default() -> Self {
+		Self { paused: false, multiplier: 1. }
+	}
+}
+
+/// Applies the game speed to virtual time. Outside of the game, virtual time always stands still.
+pub fn apply_game_speed(state: Res<State<GameState>>, speed: Res<GameSpeed>, mut game_time: ResMut<Time<Virtual>>) {
+	if state.get() != &GameState::InGame || speed.paused {
 		game_time.pause();
 	} else {
 		game_time.unpause();
+		game_time.set_relative_speed(speed.multiplier);
+	}
+}
+
+/// Pauses or resumes the simulation while in game, and changes the game speed with the number keys. Unlike leaving
+/// [`GameState::InGame`], pausing keeps the camera, menus and inspection usable. The speed only changes while no tool
+/// is in use, since tools such as painting select their options with the number keys as well.
+pub fn change_game_speed(actions: ActionInput, input_state: Res<State<InputState>>, mut speed: ResMut<GameSpeed>) {
+	if actions.just_pressed(Action::Pause) {
+		speed.paused = !speed.paused;
+	}
+	if input_state.get() != &InputState::Idle {
+		return;
 	}
+	for (action, multiplier) in
+		[Action::NormalSpeed, Action::FastSpeed, Action::FastestSpeed].into_iter().zip(GameSpeed::MULTIPLIERS)
+	{
+		if actions.just_pressed(action) {
+			*speed = GameSpeed { paused: false, multiplier };
+		}
+	}
+}
+
+/// Whether the simulation is running: the game is in progress and not paused.
+pub fn simulation_active(state: Res<State<GameState>>, game_time: Res<Time<Virtual>>) -> bool {
+	state.get() == &GameState::InGame && !game_time.is_paused()
+}
+
+/// Systems that mutate gameplay in response to the player, such as building, only run while [`simulation_active`].
+/// Fixed-schedule simulation doesn't need this set, since fixed time doesn't advance while paused anyways.
+#[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct SimulationActive;
+
+/// Domain-specific system sets. In every schedule, the sets run in the order they are declared in here; see
+/// `configure_sets` in the crate root. Systems should be added to the set of their domain instead of being ordered
+/// against systems of other domains individually.
+#[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GameSet {
+	/// Handling player input that isn't tied to UI elements, such as camera movement.
+	Input,
+	/// Simulation that the rest of the simulation depends on, such as synchronizing the ground map with the world or
+	/// recomputing areas.
+	SimulationEarly,
+	/// Main simulation of the world.
+	Simulation,
+	/// Updating the graphical representation of the world. In [`PostUpdate`], this runs before transform propagation,
+	/// so that newly positioned objects are rendered in the same frame.
+	Graphics,
+	/// Updating the UI, including world info panels.
+	UI,
 }
@@ -0,0 +1,121 @@
+//! Frame-by-frame sprite animation from multi-frame sprite sheets.
+//!
+//! An entity with a [`SpriteAnimation`] shows one of the animations of its [`SpriteSheet`], selected by its
+//! [`AnimationState`]. The sprite's image and texture atlas are set up automatically, so spawning the component on an
+//! entity with a position is enough. Gameplay systems change the state, for example to [`AnimationState::Walk`] while
+//! an actor is moving, and the animation restarts from the first frame of the new state. Animations run on virtual
+//! time, so they freeze while the game is paused.
+//!
+//! See [`SpriteSheet`] for the layout of sheet images.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::library::SpriteSheet;
+use crate::gamemode::GameSet;
+
+pub(super) struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<SheetLayouts>()
+			.register_type::<AnimationState>()
+			.add_systems(Update, (start_sprite_animations, animate_sprites).chain().in_set(GameSet::Graphics));
+	}
+}
+
+/// The states that an animated sprite can be in, each with its own animation.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AnimationState {
+	/// Standing still, or the only animation of scenery like water.
+	#[default]
+	Idle,
+	Walk,
+	/// Performing a task, such as cleaning.
+	Work,
+}
+
+/// Plays the animation of the current state from a [`SpriteSheet`] on the entity's sprite.
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+	sheet:   SpriteSheet,
+	state:   AnimationState,
+	/// Frame of the current animation, starting at 0.
+	frame:   usize,
+	elapsed: Duration,
+}
+
+impl SpriteAnimation {
+	pub fn new(sheet: SpriteSheet, state: AnimationState) -> Self {
+		Self { sheet, state, frame: 0, elapsed: Duration::ZERO }
+	}
+
+	pub const fn state(&self) -> AnimationState {
+		self.state
+	}
+
+	/// Switches to the state's animation, starting it over if the state changed.
+	pub fn set_state(&mut self, state: AnimationState) {
+		if state != self.state {
+			self.state = state;
+			self.frame = 0;
+			self.elapsed = Duration::ZERO;
+		}
+	}
+
+	/// Atlas index of the frame that is currently shown.
+	pub fn atlas_index(&self) -> usize {
+		self.sheet.frames_of(self.state).0 + self.frame
+	}
+
+	fn advance(&mut self, delta: Duration) {
+		self.elapsed += delta;
+		let (_, frames) = self.sheet.frames_of(self.state);
+		while self.elapsed >= self.sheet.frame_time && !self.sheet.frame_time.is_zero() {
+			self.elapsed -= self.sheet.frame_time;
+			self.frame = (self.frame + 1) % frames;
+		}
+	}
+}
+
+/// Texture atlas layouts of all sprite sheets in use, so that entities with the same sheet share one layout.
+#[derive(Resource, Default)]
+struct SheetLayouts(HashMap<&'static str, Handle<TextureAtlasLayout>>);
+
+fn start_sprite_animations(
+	mut animated: Query<(Entity, &SpriteAnimation, Option<&mut Sprite>), Added<SpriteAnimation>>,
+	asset_server: Res<AssetServer>,
+	mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+	mut layouts: ResMut<SheetLayouts>,
+	mut commands: Commands,
+) {
+	for (entity, animation, sprite) in &mut animated {
+		let layout = layouts
+			.0
+			.entry(animation.sheet.image)
+			.or_insert_with(|| texture_atlases.add(animation.sheet.layout()))
+			.clone();
+		let image = asset_server.load(animation.sheet.image);
+		let atlas = TextureAtlas { layout, index: animation.atlas_index() };
+		// Keep the anchor and other properties of an existing sprite.
+		if let Some(mut sprite) = sprite {
+			sprite.image = image;
+			sprite.texture_atlas = Some(atlas);
+		} else {
+			commands.entity(entity).insert(Sprite::from_atlas_image(image, atlas));
+		}
+	}
+}
+
+fn animate_sprites(time: Res<Time>, mut animated: Query<(&mut SpriteAnimation, &mut Sprite)>) {
+	for (mut animation, mut sprite) in &mut animated {
+		animation.advance(time.delta());
+		let index = animation.atlas_index();
+		// Only touch the sprite when the frame changes, so that it isn't marked as changed every frame.
+		if sprite.texture_atlas.as_ref().is_some_and(|atlas| atlas.index != index) {
+			sprite.texture_atlas.as_mut().unwrap().index = index;
+		}
+	}
+}
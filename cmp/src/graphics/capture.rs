@@ -0,0 +1,238 @@
+//! Screenshots and time-lapses.
+//!
+//! [`Action::Screenshot`] saves the pixel-perfect canvas at its native resolution, without any UI, and
+//! [`Action::FullScreenshot`] saves the window as the player sees it. [`Action::TimeLapse`] starts or stops a
+//! time-lapse, which captures the canvas every couple of in-game hours (see [`GameSettings::time_lapse_hours`]) into
+//! its own directory. When a time-lapse is stopped, its captures are stitched next to each other into one strip.
+//! Everything is saved as PNG into the `screenshots` directory next to the saves.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::window::PrimaryWindow;
+
+use super::Canvas;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::input::{Action, ActionInput};
+use crate::model::rollup::DayClock;
+use crate::save::save_directory;
+use crate::ui::notifications::NotificationEvent;
+
+pub(super) struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<TimeLapse>().add_systems(
+			Update,
+			(
+				take_screenshots,
+				toggle_time_lapse.in_set(GameState::InGame),
+				capture_time_lapse.in_set(GameState::InGame),
+			)
+				.chain()
+				.in_set(GameSet::UI),
+		);
+	}
+}
+
+/// A time-lapse strip shows at most this many captures. Longer time-lapses leave out captures evenly.
+const MAX_STRIP_CAPTURES: usize = 48;
+
+/// A running time-lapse.
+#[derive(Resource, Default)]
+struct TimeLapse {
+	/// Directory that the captures are saved to, while a time-lapse is running.
+	directory:       Option<PathBuf>,
+	/// Number of captures taken so far.
+	captures_taken:  u32,
+	/// In-game hours since the start of the game at which the next capture is taken.
+	next_capture_at: f32,
+	/// Captures that go into the strip, every `strip_stride`-th one that was taken.
+	strip:           Vec<Image>,
+	strip_stride:    u32,
+}
+
+impl TimeLapse {
+	fn add_to_strip(&mut self, index: u32, capture: Image) {
+		if index % self.strip_stride != 0 {
+			return;
+		}
+		self.strip.push(capture);
+		// Thinning out the strip keeps it evenly spaced over the whole time-lapse.
+		if self.strip.len() >= 2 * MAX_STRIP_CAPTURES {
+			let mut keep = false;
+			self.strip.retain(|_| {
+				keep = !keep;
+				keep
+			});
+			self.strip_stride *= 2;
+		}
+	}
+}
+
+/// Directory that screenshots are saved to, which is created if necessary.
+fn screenshot_directory() -> Option<PathBuf> {
+	let directory = save_directory()?.join("screenshots");
+	std::fs::create_dir_all(&directory).ok()?;
+	Some(directory)
+}
+
+/// A name for a new screenshot or time-lapse, which sorts by the time it was taken.
+fn timestamped_name(prefix: &str) -> String {
+	let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+	format!("{prefix}-{}", since_epoch.as_millis())
+}
+
+/// In-game hours since the start of the game.
+fn elapsed_hours(clock: &DayClock) -> f32 {
+	(clock.day() - 1) as f32 * 24. + clock.progress() * 24.
+}
+
+fn write_png(image: Image, path: &Path) -> anyhow::Result<()> {
+	// The alpha channel of the canvas doesn't hold transparency.
+	image.try_into_dynamic()?.to_rgb8().save(path)?;
+	Ok(())
+}
+
+/// Places the captures next to each other. All captures have the size and format of the canvas.
+fn stitch(captures: &[Image]) -> Option<Image> {
+	const PIXEL_SIZE: usize = 4;
+	let first = captures.first()?;
+	let (width, height) = (first.width() as usize, first.height() as usize);
+	let row_size = width * PIXEL_SIZE;
+
+	let mut data = Vec::with_capacity(row_size * height * captures.len());
+	for y in 0 .. height {
+		for capture in captures {
+			data.extend_from_slice(capture.data.get(y * row_size .. (y + 1) * row_size)?);
+		}
+	}
+	Some(Image::new(
+		Extent3d {
+			width:                 (width * captures.len()) as u32,
+			height:                height as u32,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		data,
+		first.texture_descriptor.format,
+		RenderAssetUsages::MAIN_WORLD,
+	))
+}
+
+fn take_screenshots(
+	actions: ActionInput,
+	canvas: Query<&Canvas>,
+	window: Query<Entity, With<PrimaryWindow>>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut commands: Commands,
+) {
+	let screenshot = if actions.just_pressed(Action::Screenshot) {
+		let Ok(canvas) = canvas.get_single() else { return };
+		Screenshot::image(canvas.image.clone())
+	} else if actions.just_pressed(Action::FullScreenshot) {
+		let Ok(window) = window.get_single() else { return };
+		Screenshot::window(window)
+	} else {
+		return;
+	};
+	let Some(directory) = screenshot_directory() else {
+		notifications.send(NotificationEvent::error("Couldn’t create the screenshot directory."));
+		return;
+	};
+
+	let path = directory.join(timestamped_name("screenshot")).with_extension("png");
+	commands.spawn(screenshot).observe(
+		move |trigger: Trigger<ScreenshotCaptured>, mut notifications: EventWriter<NotificationEvent>| {
+			notifications.send(match write_png(trigger.event().0.clone(), &path) {
+				Ok(()) => NotificationEvent::info(format!("Saved a screenshot to {}.", path.display())),
+				Err(why) => NotificationEvent::error(format!("Couldn’t save the screenshot: {why}")),
+			});
+		},
+	);
+}
+
+fn toggle_time_lapse(
+	actions: ActionInput,
+	clock: Res<DayClock>,
+	settings: Res<GameSettings>,
+	mut time_lapse: ResMut<TimeLapse>,
+	mut notifications: EventWriter<NotificationEvent>,
+) {
+	if !actions.just_pressed(Action::TimeLapse) {
+		return;
+	}
+
+	let Some(directory) = time_lapse.directory.take() else {
+		let Some(directory) = screenshot_directory().map(|directory| directory.join(timestamped_name("time-lapse")))
+		else {
+			notifications.send(NotificationEvent::error("Couldn’t create the screenshot directory."));
+			return;
+		};
+		if let Err(why) = std::fs::create_dir_all(&directory) {
+			notifications.send(NotificationEvent::error(format!("Couldn’t start the time-lapse: {why}")));
+			return;
+		}
+		*time_lapse = TimeLapse {
+			directory:       Some(directory),
+			captures_taken:  0,
+			next_capture_at: elapsed_hours(&clock),
+			strip:           Vec::new(),
+			strip_stride:    1,
+		};
+		notifications.send(NotificationEvent::info(format!(
+			"Started a time-lapse, capturing every {} hours.",
+			settings.time_lapse_hours
+		)));
+		return;
+	};
+
+	let strip = std::mem::take(&mut time_lapse.strip);
+	let Some(strip) = stitch(&strip) else {
+		notifications.send(NotificationEvent::info("Stopped the time-lapse before anything was captured."));
+		return;
+	};
+	let path = directory.join("strip.png");
+	notifications.send(match write_png(strip, &path) {
+		Ok(()) => NotificationEvent::info(format!("Saved the time-lapse to {}.", directory.display())),
+		Err(why) => NotificationEvent::error(format!("Couldn’t save the time-lapse strip: {why}")),
+	});
+}
+
+fn capture_time_lapse(
+	clock: Res<DayClock>,
+	settings: Res<GameSettings>,
+	canvas: Query<&Canvas>,
+	mut time_lapse: ResMut<TimeLapse>,
+	mut commands: Commands,
+) {
+	let Some(directory) = time_lapse.directory.clone() else { return };
+	let Ok(canvas) = canvas.get_single() else { return };
+	let elapsed_hours = elapsed_hours(&clock);
+	if elapsed_hours < time_lapse.next_capture_at {
+		return;
+	}
+	// Loading a save jumps in time, so the schedule restarts from the current time instead of catching up.
+	time_lapse.next_capture_at = elapsed_hours + settings.time_lapse_hours.max(1.);
+
+	let index = time_lapse.captures_taken;
+	time_lapse.captures_taken += 1;
+	let path = directory.join(format!("{index:05}.png"));
+	commands.spawn(Screenshot::image(canvas.image.clone())).observe(
+		move |trigger: Trigger<ScreenshotCaptured>, mut time_lapse: ResMut<TimeLapse>| {
+			let capture = trigger.event().0.clone();
+			if let Err(why) = write_png(capture.clone(), &path) {
+				warn!("Couldn’t save time-lapse capture {}: {why}", path.display());
+			}
+			// The time-lapse may have been stopped or restarted while this capture was taken.
+			if time_lapse.directory.as_deref() == path.parent() {
+				time_lapse.add_to_strip(index, capture);
+			}
+		},
+	);
+}
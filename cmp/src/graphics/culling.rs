@@ -0,0 +1,238 @@
+//! Chunk-based culling of world sprites.
+//!
+//! Bevy checks the visibility of every sprite against the camera view each frame, which gets expensive on large
+//! campgrounds where most sprites are far off-screen. Instead, world sprites are sorted into square chunks of engine
+//! space, and whole chunks outside the camera view (plus a margin for sprites reaching beyond their chunk) are hidden.
+//! Only chunks that enter or leave the view and sprites that move between chunks are touched, so the per-frame cost
+//! is independent of the map size.
+//!
+//! Only top-level sprites on the world grid are culled; their children, like borders and buildings, follow them
+//! through visibility inheritance. Overlays such as previews and selections are never culled.
+//!
+//! Chunks far away from the center of the view are only shown at a lower [`ChunkDetail`]: their ground tiles are
+//! hidden as well, and drawn by a single impostor sprite per chunk instead (see [`super::impostors`]).
+
+use bevy::prelude::*;
+use bevy::render::camera::CameraUpdateSystem;
+use bevy::render::view::VisibilitySystems;
+use bevy::utils::{HashMap, HashSet};
+
+use super::{InGameCamera, ObjectPriority};
+use crate::gamemode::GameState;
+use crate::model::{GridBox, GridPosition, GroundKind};
+
+pub(super) struct CullingPlugin;
+
+impl Plugin for CullingPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<CullingChunks>()
+			.add_systems(
+				PostUpdate,
+				// Culling needs the propagated transforms, which only exist after the graphics set, so it can't be part of it.
+				cull_chunks
+					.in_set(CullingSystem)
+					.after(TransformSystem::TransformPropagate)
+					.after(CameraUpdateSystem)
+					.before(VisibilitySystems::VisibilityPropagate)
+					.in_set(GameState::InGame),
+			)
+			// Start over for every new or loaded game.
+			.add_systems(OnEnter(GameState::Loading), reset_culling);
+	}
+}
+
+/// The system that updates which chunks are visible.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct CullingSystem;
+
+/// Side length of a chunk in engine pixels.
+pub(super) const CHUNK_SIZE: f32 = 128.;
+/// Number of chunks around the camera view that stay visible, so that large sprites anchored outside the view are
+/// still drawn.
+const MARGIN_CHUNKS: i32 = 1;
+/// Distance in engine pixels from the center of the view beyond which chunks are drawn with impostors. This is only
+/// reached when zoomed out far enough, so that the loss of detail isn't noticeable.
+const IMPOSTOR_DISTANCE: f32 = 512.;
+
+pub(super) type ChunkCoordinate = IVec2;
+
+/// How much of a visible chunk is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ChunkDetail {
+	/// All sprites of the chunk are drawn.
+	Live,
+	/// Ground tiles are drawn by the chunk's impostor; all other sprites are drawn.
+	Impostor,
+}
+
+/// Index of culled sprites by chunk.
+#[derive(Resource, Default)]
+pub(super) struct CullingChunks {
+	chunks:         HashMap<ChunkCoordinate, HashSet<Entity>>,
+	entity_chunks:  HashMap<Entity, ChunkCoordinate>,
+	/// Sprites of ground tiles, which are replaced by impostors in far chunks.
+	ground_tiles:   HashSet<Entity>,
+	visible_chunks: HashMap<ChunkCoordinate, ChunkDetail>,
+}
+
+impl CullingChunks {
+	/// Moves the entity into the given chunk and returns whether it changed chunks.
+	fn insert(&mut self, entity: Entity, chunk: ChunkCoordinate) -> bool {
+		let previous_chunk = self.entity_chunks.insert(entity, chunk);
+		if previous_chunk == Some(chunk) {
+			return false;
+		}
+		if let Some(previous_chunk) = previous_chunk {
+			self.remove_from_chunk(entity, previous_chunk);
+		}
+		self.chunks.entry(chunk).or_default().insert(entity);
+		true
+	}
+
+	fn remove(&mut self, entity: Entity) {
+		if let Some(chunk) = self.entity_chunks.remove(&entity) {
+			self.remove_from_chunk(entity, chunk);
+		}
+		self.ground_tiles.remove(&entity);
+	}
+
+	fn remove_from_chunk(&mut self, entity: Entity, chunk: ChunkCoordinate) {
+		if let Some(entities) = self.chunks.get_mut(&chunk) {
+			entities.remove(&entity);
+			if entities.is_empty() {
+				self.chunks.remove(&chunk);
+			}
+		}
+	}
+
+	/// Whether the sprite should be drawn if it is in a chunk with the given detail.
+	fn is_visible(&self, entity: Entity, detail: Option<ChunkDetail>) -> bool {
+		match detail {
+			Some(ChunkDetail::Live) => true,
+			Some(ChunkDetail::Impostor) => !self.ground_tiles.contains(&entity),
+			None => false,
+		}
+	}
+
+	/// How much of the chunk is drawn, or `None` if it isn't visible.
+	pub(super) fn detail(&self, chunk: ChunkCoordinate) -> Option<ChunkDetail> {
+		self.visible_chunks.get(&chunk).copied()
+	}
+
+	/// Visible chunks whose ground tiles are drawn by impostors.
+	pub(super) fn impostor_chunks(&self) -> impl Iterator<Item = ChunkCoordinate> + '_ {
+		self.visible_chunks.iter().filter(|(_, detail)| **detail == ChunkDetail::Impostor).map(|(chunk, _)| *chunk)
+	}
+}
+
+/// Marks sprites hidden by culling, so that sprites hidden for other reasons are never shown by accident.
+#[derive(Component, Clone, Copy, Debug)]
+struct Culled;
+
+pub(super) fn chunk_of(translation: Vec3) -> ChunkCoordinate {
+	(translation.truncate() / CHUNK_SIZE).floor().as_ivec2()
+}
+
+fn set_culled(
+	entity: Entity,
+	visible: bool,
+	sprites: &mut Query<(&mut Visibility, Has<Culled>)>,
+	commands: &mut Commands,
+) {
+	let Ok((mut visibility, culled)) = sprites.get_mut(entity) else {
+		return;
+	};
+	if visible && culled {
+		*visibility = Visibility::Inherited;
+		commands.entity(entity).remove::<Culled>();
+	} else if !visible && !culled && *visibility != Visibility::Hidden {
+		*visibility = Visibility::Hidden;
+		commands.entity(entity).insert(Culled);
+	}
+}
+
+fn cull_chunks(
+	mut index: ResMut<CullingChunks>,
+	camera: Query<(&GlobalTransform, &OrthographicProjection), With<InGameCamera>>,
+	moved_sprites: Query<
+		(Entity, &GlobalTransform, Option<&ObjectPriority>, Has<GroundKind>),
+		(With<Sprite>, Without<Parent>, Or<(With<GridPosition>, With<GridBox>)>, Changed<GlobalTransform>),
+	>,
+	mut removed_sprites: RemovedComponents<GlobalTransform>,
+	mut sprites: Query<(&mut Visibility, Has<Culled>)>,
+	mut commands: Commands,
+) {
+	for entity in removed_sprites.read() {
+		index.remove(entity);
+	}
+
+	let Ok((camera_transform, projection)) = camera.get_single() else {
+		return;
+	};
+	let view = Rect::from_center_size(
+		camera_transform.translation().truncate() + projection.area.center(),
+		projection.area.size(),
+	);
+	let min_chunk = chunk_of(view.min.extend(0.)) - MARGIN_CHUNKS;
+	let max_chunk = chunk_of(view.max.extend(0.)) + MARGIN_CHUNKS;
+	let visible_chunks = (min_chunk.x ..= max_chunk.x)
+		.flat_map(|x| (min_chunk.y ..= max_chunk.y).map(move |y| ChunkCoordinate::new(x, y)))
+		.map(|chunk| {
+			let chunk_center = (chunk.as_vec2() + 0.5) * CHUNK_SIZE;
+			let detail = if chunk_center.distance(view.center()) > IMPOSTOR_DISTANCE {
+				ChunkDetail::Impostor
+			} else {
+				ChunkDetail::Live
+			};
+			(chunk, detail)
+		})
+		.collect::<HashMap<_, _>>();
+
+	for (entity, transform, priority, is_ground_tile) in &moved_sprites {
+		if matches!(priority, Some(ObjectPriority::Overlay)) {
+			continue;
+		}
+		if is_ground_tile {
+			index.ground_tiles.insert(entity);
+		}
+		let chunk = chunk_of(transform.translation());
+		if index.insert(entity, chunk) {
+			let visible = index.is_visible(entity, visible_chunks.get(&chunk).copied());
+			set_culled(entity, visible, &mut sprites, &mut commands);
+		}
+	}
+
+	if visible_chunks == index.visible_chunks {
+		return;
+	}
+	let changed_chunks = visible_chunks
+		.iter()
+		.map(|(chunk, detail)| (*chunk, Some(*detail)))
+		.chain(
+			index
+				.visible_chunks
+				.keys()
+				.filter(|chunk| !visible_chunks.contains_key(*chunk))
+				.map(|chunk| (*chunk, None)),
+		)
+		.filter(|(chunk, detail)| index.visible_chunks.get(chunk).copied() != *detail)
+		.collect::<Vec<_>>();
+	let mut changed_sprites = 0;
+	for (chunk, detail) in changed_chunks {
+		for entity in index.chunks.get(&chunk).into_iter().flatten() {
+			set_culled(*entity, index.is_visible(*entity, detail), &mut sprites, &mut commands);
+			changed_sprites += 1;
+		}
+	}
+	if changed_sprites > 0 {
+		debug!("culling changed visibility of {changed_sprites} sprites");
+	}
+	index.visible_chunks = visible_chunks;
+}
+
+fn reset_culling(mut index: ResMut<CullingChunks>, culled: Query<Entity, With<Culled>>, mut commands: Commands) {
+	*index = CullingChunks::default();
+	for entity in &culled {
+		commands.entity(entity).remove::<Culled>().insert(Visibility::Inherited);
+	}
+}
@@ -0,0 +1,133 @@
+//! Procedurally drawn images for ground decals.
+//!
+//! Decals are painted onto the isometric tile surface, so they are rasterized directly in tile space instead of being
+//! drawn as separate image assets for every variant and orientation. Numbers are the exception: they are drawn upright
+//! with a tiny pixel font, like paint stencils viewed from the camera.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::Anchor;
+use bevy::utils::HashMap;
+
+use super::{Sides, TILE_WIDTH};
+use crate::model::decal::DecalKind;
+
+/// Decal images are one tile wide and tall enough to cover the tile's diamond plus one pixel below it.
+const DECAL_WIDTH: u32 = TILE_WIDTH as u32;
+const DECAL_HEIGHT: u32 = 15;
+/// Pixel distance between the tile's bottom corner and its top corner.
+const DIAMOND_HEIGHT: f32 = 14.;
+/// Slightly translucent white, like worn paint.
+const PAINT: [u8; 4] = [240, 240, 230, 210];
+/// Width of painted lines, in tiles.
+const LINE_WIDTH: f32 = 0.08;
+/// 3×5 pixel glyphs for the digits 0 to 9, one row per byte from top to bottom, with the leftmost pixel in bit 2.
+const DIGITS: [[u8; 5]; 10] = [
+	[0b111, 0b101, 0b101, 0b101, 0b111],
+	[0b010, 0b110, 0b010, 0b010, 0b111],
+	[0b111, 0b001, 0b111, 0b100, 0b111],
+	[0b111, 0b001, 0b011, 0b001, 0b111],
+	[0b101, 0b101, 0b111, 0b001, 0b001],
+	[0b111, 0b100, 0b111, 0b001, 0b111],
+	[0b111, 0b100, 0b111, 0b101, 0b111],
+	[0b111, 0b001, 0b010, 0b010, 0b010],
+	[0b111, 0b101, 0b111, 0b101, 0b111],
+	[0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Anchor for decal sprites, placing the tile's bottom corner one pixel above the bottom of the image.
+pub fn decal_anchor() -> Anchor {
+	Anchor::Custom(Vec2::new(0., -0.5 + 1. / DECAL_HEIGHT as f32))
+}
+
+/// Cache of all decal images drawn so far.
+#[derive(Resource, Default)]
+pub struct DecalImages(HashMap<DecalKind, Handle<Image>>);
+
+impl DecalImages {
+	/// Returns the image for the decal, drawing it the first time it is requested.
+	pub fn get(&mut self, kind: DecalKind, images: &mut Assets<Image>) -> Handle<Image> {
+		self.0.entry(kind).or_insert_with(|| images.add(draw_decal(kind))).clone()
+	}
+}
+
+fn draw_decal(kind: DecalKind) -> Image {
+	let mut data = vec![0; (DECAL_WIDTH * DECAL_HEIGHT * 4) as usize];
+	for row in 0 .. DECAL_HEIGHT {
+		for column in 0 .. DECAL_WIDTH {
+			// Pixel center in engine space, relative to the tile's bottom corner.
+			let engine =
+				Vec2::new(column as f32 - DECAL_WIDTH as f32 / 2. + 0.5, DECAL_HEIGHT as f32 - 1.5 - row as f32);
+			if is_painted(kind, engine) {
+				let offset = ((row * DECAL_WIDTH + column) * 4) as usize;
+				data[offset .. offset + 4].copy_from_slice(&PAINT);
+			}
+		}
+	}
+	Image::new(
+		Extent3d { width: DECAL_WIDTH, height: DECAL_HEIGHT, depth_or_array_layers: 1 },
+		TextureDimension::D2,
+		data,
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::RENDER_WORLD,
+	)
+}
+
+/// Whether the pixel at the engine-space position relative to the tile's bottom corner is painted.
+fn is_painted(kind: DecalKind, engine: Vec2) -> bool {
+	// Invert the isometric projection: world x points right and up, world y points left and up.
+	let half_width = DECAL_WIDTH as f32 / 2.;
+	let half_height = DIAMOND_HEIGHT / 2.;
+	let tile =
+		Vec2::new(engine.x / half_width + engine.y / half_height, engine.y / half_height - engine.x / half_width) / 2.;
+	let on_tile = tile.cmpge(Vec2::ZERO).all() && tile.cmple(Vec2::ONE).all();
+
+	match kind {
+		DecalKind::Line(side) => on_tile && distance_to_side(tile, side) < LINE_WIDTH,
+		DecalKind::Arrow(side) => {
+			let direction = direction_of(side);
+			let from_center = tile - Vec2::splat(0.5);
+			let along = from_center.dot(direction);
+			let across = from_center.perp_dot(direction).abs();
+			let shaft = (-0.35 .. 0.1).contains(&along) && across < LINE_WIDTH;
+			let head = (0.05 .. 0.4).contains(&along) && across < 0.4 - along;
+			on_tile && (shaft || head)
+		},
+		DecalKind::Number(number) => is_digit_pixel(number, engine),
+	}
+}
+
+fn direction_of(side: Sides) -> Vec2 {
+	match side {
+		Sides::Top => Vec2::Y,
+		Sides::Bottom => Vec2::NEG_Y,
+		Sides::Left => Vec2::NEG_X,
+		_ => Vec2::X,
+	}
+}
+
+fn distance_to_side(tile: Vec2, side: Sides) -> f32 {
+	match side {
+		Sides::Top => 1. - tile.y,
+		Sides::Bottom => tile.y,
+		Sides::Left => tile.x,
+		_ => 1. - tile.x,
+	}
+}
+
+/// Draws the number centered on the tile, with one pixel of space between digits.
+fn is_digit_pixel(number: u16, engine: Vec2) -> bool {
+	let digits = number.to_string().bytes().map(|digit| (digit - b'0') as usize).collect::<Vec<_>>();
+	let text_width = digits.len() as i32 * 4 - 1;
+	// Top left corner of the text, in whole pixels relative to the tile's bottom corner.
+	let left = -text_width / 2;
+	let top = (DIAMOND_HEIGHT / 2.) as i32 + 2;
+	let x = engine.x.floor() as i32 - left;
+	let y = top - engine.y.floor() as i32;
+	if !(0 .. text_width).contains(&x) || !(0 .. 5).contains(&y) || x % 4 == 3 {
+		return false;
+	}
+	let row = DIGITS[digits[(x / 4) as usize]][y as usize];
+	row & (0b100 >> (x % 4)) != 0
+}
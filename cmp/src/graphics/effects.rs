@@ -0,0 +1,70 @@
+//! Small visual effects that make interacting with the world feel more alive.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::particles::{Emission, ParticleEmitter};
+use crate::config::GameSettings;
+use crate::util::physics_ease::MassDamperSystem;
+
+/// A short squash-and-stretch "pop" played on a sprite that was just built. The sprite starts out squashed and springs
+/// up into its normal shape, overshooting a little.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BuildPop {
+	system:  MassDamperSystem,
+	elapsed: Duration,
+}
+
+impl Default for BuildPop {
+	fn default() -> Self {
+		let mut system = MassDamperSystem::new(12., 250., 1.);
+		system.set_target(1.);
+		Self { system, elapsed: Duration::ZERO }
+	}
+}
+
+impl BuildPop {
+	/// After this time, the spring has settled closely enough that the sprite can snap back to its normal scale.
+	const DURATION: Duration = Duration::from_millis(700);
+	/// Vertical scale the sprite starts out with.
+	const MIN_STRETCH: f32 = 0.4;
+}
+
+pub(super) fn animate_build_pop(
+	time: Res<Time>,
+	settings: Res<GameSettings>,
+	mut popping: Query<(Entity, &mut Transform, &mut BuildPop)>,
+	mut commands: Commands,
+) {
+	for (entity, mut transform, mut pop) in &mut popping {
+		pop.elapsed += time.delta();
+		if settings.reduced_effects || pop.elapsed >= BuildPop::DURATION {
+			transform.scale = Vec3::ONE;
+			commands.entity(entity).remove::<BuildPop>();
+			continue;
+		}
+
+		pop.system.simulate(time.delta_secs());
+		let stretch = BuildPop::MIN_STRETCH.lerp(1., pop.system.position());
+		// Squash horizontally as much as we stretch vertically, so the sprite appears to keep its volume.
+		transform.scale = Vec3::new(2. - stretch, stretch, 1.);
+	}
+}
+
+/// Creates a short burst of dust kicked up around the base of something that was just built.
+pub fn build_dust() -> ParticleEmitter {
+	ParticleEmitter {
+		spawn_area: Rect::new(-10., -2., 10., 2.),
+		velocity: Rect::new(-18., 4., 18., 16.),
+		acceleration: Vec2::new(0., -30.),
+		lifetime: Duration::from_millis(300) .. Duration::from_millis(700),
+		colors: vec![
+			Color::srgb_u8(0xc8, 0xb0, 0x8a),
+			Color::srgb_u8(0xa8, 0x92, 0x70),
+			Color::srgb_u8(0xe0, 0xd4, 0xbc),
+		],
+		fade_out: true,
+		..ParticleEmitter::new(Emission::Burst(16), 16)
+	}
+}
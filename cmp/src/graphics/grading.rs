@@ -0,0 +1,148 @@
+//! Ambient color grading zones and seasonal grass.
+//!
+//! Some areas have a mood of their own: pitches lie in the shade of their trees, and pools are bright and cool. All
+//! sprites within such an area are subtly tinted with the area type's [`AmbientZone::TINT`]. Grading can be turned off
+//! with the `ambient_grading` setting. Outside of such areas, grass follows the season (see [`grass_tint`]).
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::overlay::{Overlay, OverlaySystems};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::model::area::{Area, ImmutableArea, Pool};
+use crate::model::calendar::{Calendar, Season};
+use crate::model::nav::NavComponent;
+use crate::model::{GroundKind, GroundMap, Pitch};
+
+pub(super) struct GradingPlugin;
+
+impl Plugin for GradingPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			PostUpdate,
+			apply_ambient_zones.after(OverlaySystems::Apply).in_set(GameSet::Graphics).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// An area type that tints the sprites within it.
+pub trait AmbientZone: Component {
+	/// Multiplied onto the color of all sprites in the area. Since sprite colors can't brighten, moods are created by
+	/// shifting the color balance instead.
+	const TINT: Color;
+}
+
+impl AmbientZone for Pool {
+	/// Cool and clear, like the water.
+	const TINT: Color = Color::srgb(0.93, 0.97, 1.);
+}
+
+impl AmbientZone for Pitch {
+	/// Soft green shade from the trees around the pitch.
+	const TINT: Color = Color::srgb(0.92, 0.97, 0.88);
+}
+
+/// Tint of grass in the given season. Summer grass is drawn as is.
+pub const fn grass_tint(season: Season) -> Color {
+	match season {
+		// Fresh and light.
+		Season::Spring => Color::srgb(0.9, 1., 0.86),
+		Season::Summer => Color::WHITE,
+		// Dry and golden.
+		Season::Autumn => Color::srgb(1., 0.88, 0.68),
+		// Pale and frosty.
+		Season::Winter => Color::srgb(0.86, 0.9, 0.94),
+	}
+}
+
+/// Marks sprites that are currently tinted by an ambient zone, so that their tint can be removed again.
+#[derive(Component, Clone, Copy, Debug)]
+struct AmbientTinted;
+
+fn apply_ambient_zones(
+	settings: Res<GameSettings>,
+	overlay: Res<Overlay>,
+	calendar: Res<Calendar>,
+	ground_map: Res<GroundMap>,
+	pools: Query<Ref<Area>, With<Pool>>,
+	pitches: Query<(Entity, Option<Ref<Area>>, Option<Ref<ImmutableArea>>), With<Pitch>>,
+	mut removed_areas: RemovedComponents<Area>,
+	changed_tiles: Query<(), (With<GroundKind>, Or<(Changed<GroundKind>, Changed<NavComponent>)>)>,
+	ground: Query<(Entity, &GroundKind)>,
+	tinted: Query<Entity, With<AmbientTinted>>,
+	mut sprites: Query<(&mut Sprite, Has<GroundKind>)>,
+	children: Query<&Children>,
+	mut commands: Commands,
+) {
+	let areas_changed = pools.iter().any(|area| area.is_changed())
+		|| pitches.iter().any(|(_, area, immutable_area)| {
+			area.is_some_and(|area| area.is_changed()) || immutable_area.is_some_and(|area| area.is_changed())
+		}) || removed_areas.read().count() > 0;
+	if !settings.is_changed()
+		&& !overlay.is_changed()
+		&& !calendar.is_changed()
+		&& !areas_changed
+		&& changed_tiles.is_empty()
+		// A separate `Added<Sprite>` query would conflict with the mutable sprite access.
+		&& !sprites.iter_mut().any(|(sprite, _)| sprite.is_added())
+	{
+		return;
+	}
+
+	let mut tints = HashMap::new();
+	let grass_tint = grass_tint(calendar.season());
+	if grass_tint != Color::WHITE {
+		tints.extend(
+			ground.iter().filter(|(_, kind)| **kind == GroundKind::Grass).map(|(entity, _)| (entity, grass_tint)),
+		);
+	}
+	if settings.ambient_grading {
+		for area in &pools {
+			for tile in area.tiles_iter() {
+				if let Some((entity, _)) = ground_map.get(&tile) {
+					tints.insert(entity, Pool::TINT);
+				}
+			}
+		}
+		for (entity, area, immutable_area) in &pitches {
+			let Some(area) = area.as_deref().or(immutable_area.as_deref().map(|area| &area.0)) else {
+				continue;
+			};
+			for tile in area.tiles_iter() {
+				if let Some((tile_entity, _)) = ground_map.get(&tile) {
+					tints.insert(tile_entity, Pitch::TINT);
+				}
+			}
+			// Buildings on the pitch.
+			tints.insert(entity, Pitch::TINT);
+		}
+	}
+	// Tiles and pitches tint their children as well, such as borders and buildings.
+	let tints = tints
+		.into_iter()
+		.flat_map(|(entity, tint)| children.iter_descendants(entity).chain([entity]).map(move |entity| (entity, tint)))
+		.collect::<HashMap<_, _>>();
+
+	// Overlays own the colors of ground tiles while they are shown.
+	let mut set_color = |entity: Entity, color: Color| match sprites.get_mut(entity) {
+		Ok((mut sprite, is_ground)) if !(is_ground && overlay.is_active()) => sprite.color = color,
+		_ => {},
+	};
+	for entity in &tinted {
+		if !tints.contains_key(&entity) {
+			set_color(entity, Color::WHITE);
+			commands.entity(entity).remove::<AmbientTinted>();
+		}
+	}
+	for (entity, tint) in tints {
+		set_color(entity, tint);
+		if tinted.contains(entity) {
+			continue;
+		}
+		// The entity may be despawned by earlier commands, such as a demolition in the same frame.
+		if let Some(mut entity_commands) = commands.get_entity(entity) {
+			entity_commands.try_insert(AmbientTinted);
+		}
+	}
+}
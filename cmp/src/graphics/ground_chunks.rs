@@ -0,0 +1,293 @@
+//! Baked ground chunks.
+//!
+//! Drawing every ground tile as its own sprite means tens of thousands of sprites on large campgrounds, which all need
+//! to be extracted, sorted and batched every frame. Instead, the ground tiles of every culling chunk are baked into a
+//! single mesh that draws from an atlas of all ground images. A chunk's mesh is only rebuilt when one of its tiles
+//! changes its sprite, such as its image or its tint. The tiles stay in the world for the simulation, but their own
+//! sprites are moved to a render layer that no camera draws; their children, like borders, are still drawn as usual.
+//! Tiles whose image isn't in the atlas yet keep drawing themselves until the atlas is rebuilt with it.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::utils::{HashMap, HashSet};
+
+use super::culling::{chunk_of, ChunkCoordinate, ChunkDetail, CullingChunks, CullingSystem, CHUNK_SIZE};
+use super::impostors::impostor_depth;
+use crate::gamemode::GameState;
+use crate::model::GroundKind;
+
+pub(super) struct GroundChunkPlugin;
+
+impl Plugin for GroundChunkPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<GroundChunks>()
+			.add_systems(
+				PostUpdate,
+				(track_ground_tiles, update_ground_atlas, bake_ground_chunks)
+					.chain()
+					.after(CullingSystem)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(GameState::Loading), reset_ground_chunks);
+	}
+}
+
+/// Render layer that no camera draws, for the sprites of baked ground tiles.
+pub const BAKED_TILE_LAYER: usize = 31;
+
+/// All ground images side by side in one texture.
+struct GroundAtlas {
+	material: Handle<ColorMaterial>,
+	texture:  Handle<Image>,
+	size:     Vec2,
+	/// Where each ground image is in the atlas, in pixels.
+	regions:  HashMap<AssetId<Image>, Rect>,
+	/// The ground images in the atlas, which are kept loaded so that their ids stay valid.
+	images:   Vec<Handle<Image>>,
+}
+
+/// Ground tiles by chunk, and the meshes that they are baked into.
+#[derive(Resource, Default)]
+struct GroundChunks {
+	atlas:          Option<GroundAtlas>,
+	/// Ground images that the atlas doesn't include yet.
+	missing_images: HashSet<Handle<Image>>,
+	tiles:          HashMap<ChunkCoordinate, HashSet<Entity>>,
+	tile_chunks:    HashMap<Entity, ChunkCoordinate>,
+	meshes:         HashMap<ChunkCoordinate, (Entity, Handle<Mesh>)>,
+	/// Chunks whose mesh needs to be rebuilt.
+	dirty:          HashSet<ChunkCoordinate>,
+}
+
+impl GroundChunks {
+	fn remove_tile(&mut self, entity: Entity) {
+		let Some(chunk) = self.tile_chunks.remove(&entity) else {
+			return;
+		};
+		if let Some(tiles) = self.tiles.get_mut(&chunk) {
+			tiles.remove(&entity);
+		}
+		self.dirty.insert(chunk);
+	}
+}
+
+/// Marks the mesh that a chunk's ground tiles are baked into.
+#[derive(Component, Clone, Copy, Debug)]
+struct GroundChunkMesh;
+
+fn track_ground_tiles(
+	mut chunks: ResMut<GroundChunks>,
+	changed_tiles: Query<
+		(Entity, &Sprite, &Transform),
+		(With<GroundKind>, Without<Parent>, Or<(Changed<Sprite>, Changed<Transform>)>),
+	>,
+	mut removed_tiles: RemovedComponents<GroundKind>,
+) {
+	for entity in removed_tiles.read() {
+		chunks.remove_tile(entity);
+	}
+	for (entity, sprite, transform) in &changed_tiles {
+		let chunk = chunk_of(transform.translation);
+		if chunks.tile_chunks.get(&entity) != Some(&chunk) {
+			chunks.remove_tile(entity);
+			chunks.tile_chunks.insert(entity, chunk);
+			chunks.tiles.entry(chunk).or_default().insert(entity);
+		}
+		chunks.dirty.insert(chunk);
+		if !chunks.atlas.as_ref().is_some_and(|atlas| atlas.regions.contains_key(&sprite.image.id())) {
+			chunks.missing_images.insert(sprite.image.clone());
+		}
+	}
+}
+
+/// Rebuilds the atlas once new ground images are loaded.
+fn update_ground_atlas(
+	mut chunks: ResMut<GroundChunks>,
+	mut images: ResMut<Assets<Image>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+	if !chunks.missing_images.iter().any(|image| images.contains(image)) {
+		return;
+	}
+	let mut atlas_images = chunks.atlas.as_ref().map(|atlas| atlas.images.clone()).unwrap_or_default();
+	atlas_images.extend(chunks.missing_images.iter().filter(|image| images.contains(*image)).cloned());
+	chunks.missing_images.retain(|image| !images.contains(image));
+
+	if let Some(previous) = chunks.atlas.take() {
+		images.remove(&previous.texture);
+		materials.remove(&previous.material);
+	}
+	chunks.atlas = build_atlas(atlas_images, &mut images, &mut materials);
+	let all_chunks = chunks.tiles.keys().copied().collect::<Vec<_>>();
+	chunks.dirty.extend(all_chunks);
+}
+
+/// Places the images next to each other in a new atlas. Only 8-bit RGBA images in the color space of the first image
+/// can be combined, so other images are left out.
+fn build_atlas(
+	atlas_images: Vec<Handle<Image>>,
+	images: &mut Assets<Image>,
+	materials: &mut Assets<ColorMaterial>,
+) -> Option<GroundAtlas> {
+	let format = images.get(atlas_images.first()?)?.texture_descriptor.format;
+	if !matches!(format, TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm) {
+		return None;
+	}
+	let atlas_images = atlas_images
+		.into_iter()
+		.filter(|image| images.get(image).is_some_and(|image| image.texture_descriptor.format == format))
+		.collect::<Vec<_>>();
+	let sizes = atlas_images.iter().filter_map(|image| Some(images.get(image)?.size())).collect::<Vec<_>>();
+	let width = sizes.iter().map(|size| size.x).sum::<u32>();
+	let height = sizes.iter().map(|size| size.y).max()?;
+
+	let mut data = vec![0; (width * height * 4) as usize];
+	let mut regions = HashMap::new();
+	let mut left = 0;
+	for (handle, size) in atlas_images.iter().zip(sizes) {
+		let image = images.get(handle)?;
+		let row_length = (size.x * 4) as usize;
+		for (row, source) in image.data.chunks_exact(row_length).enumerate() {
+			let start = (row * width as usize + left as usize) * 4;
+			data[start .. start + row_length].copy_from_slice(source);
+		}
+		regions.insert(handle.id(), Rect::new(left as f32, 0., (left + size.x) as f32, size.y as f32));
+		left += size.x;
+	}
+
+	let texture = images.add(Image::new(
+		Extent3d { width, height, depth_or_array_layers: 1 },
+		TextureDimension::D2,
+		data,
+		format,
+		RenderAssetUsages::RENDER_WORLD,
+	));
+	let material = materials.add(ColorMaterial { texture: Some(texture.clone()), ..default() });
+	Some(GroundAtlas { material, texture, size: Vec2::new(width as f32, height as f32), regions, images: atlas_images })
+}
+
+/// Builds the mesh of a chunk from the sprites of its tiles, drawing the tiles in the back first so that they overlap
+/// like the sprites would.
+fn chunk_mesh(chunk: ChunkCoordinate, atlas: &GroundAtlas, tiles: &[(&Sprite, &Transform)]) -> Mesh {
+	let origin = chunk.as_vec2() * CHUNK_SIZE;
+	let mut tiles = tiles
+		.iter()
+		.filter_map(|(sprite, transform)| Some((*sprite, *transform, atlas.regions.get(&sprite.image.id())?)))
+		.collect::<Vec<_>>();
+	tiles.sort_by(|(_, a, _), (_, b, _)| a.translation.z.total_cmp(&b.translation.z));
+
+	let mut positions = Vec::with_capacity(tiles.len() * 4);
+	let mut uvs = Vec::with_capacity(tiles.len() * 4);
+	let mut colors = Vec::with_capacity(tiles.len() * 4);
+	let mut indices = Vec::with_capacity(tiles.len() * 6);
+	for (sprite, transform, region) in tiles {
+		let size = sprite.custom_size.unwrap_or(region.size());
+		let center = transform.translation.truncate() - sprite.anchor.as_vec() * size - origin;
+		let (min, max) = (center - size / 2., center + size / 2.);
+		let (uv_min, uv_max) = (region.min / atlas.size, region.max / atlas.size);
+
+		let first = positions.len() as u32;
+		positions.extend([[min.x, max.y, 0.], [max.x, max.y, 0.], [max.x, min.y, 0.], [min.x, min.y, 0.]]);
+		uvs.extend([[uv_min.x, uv_min.y], [uv_max.x, uv_min.y], [uv_max.x, uv_max.y], [uv_min.x, uv_max.y]]);
+		colors.extend([sprite.color.to_linear().to_f32_array(); 4]);
+		indices.extend([3, 2, 1, 3, 1, 0].map(|index| first + index));
+	}
+
+	Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+		.with_inserted_indices(Indices::U32(indices))
+}
+
+fn bake_ground_chunks(
+	mut chunks: ResMut<GroundChunks>,
+	culling: Res<CullingChunks>,
+	tiles: Query<(&Sprite, &Transform, Has<RenderLayers>), With<GroundKind>>,
+	mut chunk_meshes: Query<&mut Visibility, With<GroundChunkMesh>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut commands: Commands,
+) {
+	let GroundChunks { atlas, tiles: chunk_tiles, meshes: chunk_entities, dirty, .. } = &mut *chunks;
+	let Some(atlas) = atlas else {
+		return;
+	};
+
+	for chunk in dirty.drain() {
+		let mut baked_tiles = Vec::new();
+		for entity in chunk_tiles.get(&chunk).into_iter().flatten() {
+			let Ok((sprite, transform, hidden)) = tiles.get(*entity) else {
+				continue;
+			};
+			let in_atlas = atlas.regions.contains_key(&sprite.image.id());
+			if in_atlas {
+				baked_tiles.push((sprite, transform));
+			}
+			if in_atlas && !hidden {
+				commands.entity(*entity).insert(RenderLayers::layer(BAKED_TILE_LAYER));
+			} else if !in_atlas && hidden {
+				commands.entity(*entity).remove::<RenderLayers>();
+			}
+		}
+
+		if baked_tiles.is_empty() {
+			if let Some((entity, mesh)) = chunk_entities.remove(&chunk) {
+				commands.entity(entity).despawn_recursive();
+				meshes.remove(&mesh);
+			}
+			continue;
+		}
+		let mesh = chunk_mesh(chunk, atlas, &baked_tiles);
+		match chunk_entities.get(&chunk) {
+			Some((entity, handle)) => {
+				meshes.insert(handle, mesh);
+				// The atlas may have been rebuilt with a new material.
+				commands.entity(*entity).insert(MeshMaterial2d(atlas.material.clone()));
+			},
+			None => {
+				let handle = meshes.add(mesh);
+				let entity = commands
+					.spawn((
+						GroundChunkMesh,
+						Mesh2d(handle.clone()),
+						MeshMaterial2d(atlas.material.clone()),
+						Transform::from_translation((chunk.as_vec2() * CHUNK_SIZE).extend(impostor_depth(chunk))),
+						// Culling chunks takes care of hiding far-away meshes.
+						NoFrustumCulling,
+						Visibility::Hidden,
+					))
+					.id();
+				chunk_entities.insert(chunk, (entity, handle));
+			},
+		}
+	}
+
+	// Far-away chunks draw their ground with impostors instead.
+	for (chunk, (entity, _)) in chunk_entities.iter() {
+		let visibility =
+			if culling.detail(*chunk) == Some(ChunkDetail::Live) { Visibility::Inherited } else { Visibility::Hidden };
+		if let Ok(mut chunk_visibility) = chunk_meshes.get_mut(*entity) {
+			chunk_visibility.set_if_neq(visibility);
+		}
+	}
+}
+
+fn reset_ground_chunks(
+	mut chunks: ResMut<GroundChunks>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	chunk_meshes: Query<Entity, With<GroundChunkMesh>>,
+	mut commands: Commands,
+) {
+	for (_, (_, mesh)) in chunks.meshes.drain() {
+		meshes.remove(&mesh);
+	}
+	chunks.tiles.clear();
+	chunks.tile_chunks.clear();
+	chunks.dirty.clear();
+	for entity in &chunk_meshes {
+		commands.entity(entity).despawn_recursive();
+	}
+}
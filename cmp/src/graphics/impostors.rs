@@ -0,0 +1,166 @@
+//! Low-detail impostors for the ground of far-away chunks.
+//!
+//! On very large campgrounds, even culled sprites get heavy when zoomed out, since most visible sprites are ground
+//! tiles that are only a few pixels large. Culling therefore hides the ground tiles of chunks far from the center of
+//! the view, and each such chunk's ground is drawn by a single sprite with a low-resolution texture instead. The
+//! texture is rendered lazily when the chunk first turns into an impostor and cached afterwards; it is discarded when a
+//! ground tile in the chunk changes. As the camera approaches, culling shows the live tiles again and the impostor
+//! sprite is removed.
+
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::Anchor;
+use bevy::utils::{HashMap, HashSet};
+
+use super::culling::{chunk_of, ChunkCoordinate, CullingChunks, CullingSystem, CHUNK_SIZE};
+use super::library::impostor_color_for_ground;
+use super::{engine_to_world_space, world_to_engine_space};
+use crate::gamemode::GameState;
+use crate::model::{GridPosition, GroundKind, GroundMap, WorldPosition};
+
+pub(super) struct ImpostorPlugin;
+
+impl Plugin for ImpostorPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Impostors>()
+			.add_systems(PostUpdate, update_impostors.after(CullingSystem).in_set(GameState::InGame))
+			.add_systems(OnEnter(GameState::Loading), reset_impostors);
+	}
+}
+
+/// Side length of an impostor texture in pixels. Four engine pixels on each side make up one impostor pixel, which is
+/// still finer than a tile.
+const IMPOSTOR_RESOLUTION: u32 = 32;
+
+/// Cached impostor textures and the impostor sprites currently shown.
+#[derive(Resource, Default)]
+struct Impostors {
+	textures: HashMap<ChunkCoordinate, Handle<Image>>,
+	sprites:  HashMap<ChunkCoordinate, Entity>,
+}
+
+/// Marks the sprite drawing a chunk's impostor.
+#[derive(Component, Clone, Copy, Debug)]
+struct Impostor;
+
+/// Renders the ground of a chunk by sampling the ground kind under every impostor pixel.
+fn render_impostor(chunk: ChunkCoordinate, ground_map: &GroundMap) -> Image {
+	let pixel_size = CHUNK_SIZE / IMPOSTOR_RESOLUTION as f32;
+	let origin = chunk.as_vec2() * CHUNK_SIZE;
+	let mut data = Vec::with_capacity((IMPOSTOR_RESOLUTION * IMPOSTOR_RESOLUTION * 4) as usize);
+	// Image rows go from top to bottom, while engine space goes from bottom to top.
+	for row in (0 .. IMPOSTOR_RESOLUTION).rev() {
+		for column in 0 .. IMPOSTOR_RESOLUTION {
+			let pixel_center = origin + (Vec2::new(column as f32, row as f32) + 0.5) * pixel_size;
+			// Impostors are only used for far-away ground, so the ground is assumed to be flat.
+			let tile = GridPosition::from(engine_to_world_space(pixel_center, 0.).floor().as_ivec3());
+			let color = ground_map
+				.kind_of(&tile)
+				.map_or([0; 4], |kind| impostor_color_for_ground(kind).to_srgba().to_u8_array());
+			data.extend_from_slice(&color);
+		}
+	}
+	Image::new(
+		Extent3d {
+			width:                 IMPOSTOR_RESOLUTION,
+			height:                IMPOSTOR_RESOLUTION,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		data,
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::RENDER_WORLD,
+	)
+}
+
+/// Depth of the impostor sprite. Any object that can overlap the chunk is either in it or in front of it, so it is
+/// drawn over the impostor as long as the impostor is behind all tiles of the chunk.
+pub(super) fn impostor_depth(chunk: ChunkCoordinate) -> f32 {
+	let top_left = Vec2::new(chunk.x as f32, chunk.y as f32 + 1.) * CHUNK_SIZE;
+	let world_position = engine_to_world_space(top_left, 0.);
+	-world_position.x - world_position.y - 1.
+}
+
+fn update_impostors(
+	culling: Res<CullingChunks>,
+	ground_map: Res<GroundMap>,
+	changed_tiles: Query<&GridPosition, Changed<GroundKind>>,
+	mut removed_tiles: RemovedComponents<GroundKind>,
+	mut impostors: ResMut<Impostors>,
+	mut images: ResMut<Assets<Image>>,
+	mut sprites: Query<&mut Sprite, With<Impostor>>,
+	mut commands: Commands,
+) {
+	// Removed tiles can't be located anymore, but they are rare enough to invalidate everything.
+	let outdated_chunks = if removed_tiles.read().count() > 0 {
+		impostors.textures.keys().copied().collect::<HashSet<_>>()
+	} else {
+		// A tile can reach into the neighboring chunks, so all chunks that its corners are in are outdated.
+		changed_tiles
+			.iter()
+			.flat_map(|position| {
+				[(0., 0.), (1., 0.), (0., 1.), (1., 1.)].map(|(x, y)| {
+					chunk_of(world_to_engine_space(position.position() + Vec3A::new(x, y, 0.)).extend(0.))
+				})
+			})
+			.collect::<HashSet<_>>()
+	};
+	for chunk in &outdated_chunks {
+		if let Some(texture) = impostors.textures.remove(chunk) {
+			images.remove(&texture);
+		}
+	}
+
+	let impostor_chunks = culling.impostor_chunks().collect::<HashSet<_>>();
+	let Impostors { textures, sprites: shown } = &mut *impostors;
+	shown.retain(|chunk, sprite| {
+		let keep = impostor_chunks.contains(chunk);
+		if !keep {
+			commands.entity(*sprite).despawn_recursive();
+		}
+		keep
+	});
+
+	for chunk in impostor_chunks {
+		let texture = textures.entry(chunk).or_insert_with(|| images.add(render_impostor(chunk, &ground_map))).clone();
+		match shown.get(&chunk) {
+			Some(sprite) if outdated_chunks.contains(&chunk) =>
+				if let Ok(mut sprite) = sprites.get_mut(*sprite) {
+					sprite.image = texture;
+				},
+			Some(_) => {},
+			None => {
+				let sprite = commands
+					.spawn((
+						Impostor,
+						Sprite {
+							image: texture,
+							custom_size: Some(Vec2::splat(CHUNK_SIZE)),
+							anchor: Anchor::BottomLeft,
+							..default()
+						},
+						Transform::from_translation((chunk.as_vec2() * CHUNK_SIZE).extend(impostor_depth(chunk))),
+					))
+					.id();
+				shown.insert(chunk, sprite);
+			},
+		}
+	}
+}
+
+fn reset_impostors(
+	mut impostors: ResMut<Impostors>,
+	mut images: ResMut<Assets<Image>>,
+	sprites: Query<Entity, With<Impostor>>,
+	mut commands: Commands,
+) {
+	for (_, texture) in impostors.textures.drain() {
+		images.remove(&texture);
+	}
+	impostors.sprites.clear();
+	for sprite in &sprites {
+		commands.entity(sprite).despawn_recursive();
+	}
+}
@@ -1,10 +1,23 @@
 //! Look-up tables and functions defining graphics assets for various in-engine data types.
 
-use bevy::sprite::Anchor;
+use std::time::Duration;
 
+use bevy::color::Color;
+use bevy::math::UVec2;
+use bevy::sprite::{Anchor, TextureAtlasLayout};
+use itertools::Itertools;
+
+use super::animation::AnimationState;
+use super::lut::ColorLut;
 use super::BorderKind;
-use crate::model::{Buildable, GroundKind, PitchType};
-use crate::ui::controls::BuildMenu;
+use crate::input::cursor::{CursorKind, ALL_CURSOR_KINDS};
+use crate::model::decoration::Decoration;
+use crate::model::facility::Facility;
+use crate::model::obstacle::{Obstacle, ALL_OBSTACLES};
+use crate::model::utilities::UtilityPartKind;
+use crate::model::{Buildable, GroundKind, PitchType, ALL_BUILDABLES};
+use crate::mods::{custom_ground_kind, custom_ground_kinds, custom_menus};
+use crate::ui::controls::{all_build_menus, BuildMenu};
 
 pub fn image_for_ground(kind: GroundKind) -> &'static str {
 	match kind {
@@ -12,6 +25,18 @@ pub fn image_for_ground(kind: GroundKind) -> &'static str {
 		GroundKind::Pathway => "gravel.qoi",
 		GroundKind::PoolPath => "pool.qoi",
 		GroundKind::Pitch => "pitch-tile.qoi",
+		GroundKind::Custom(number) => custom_ground_kind(number).map_or("grass.qoi", |kind| kind.image),
+	}
+}
+
+/// Average color of the ground kind's image, used where individual tiles are too small to make out.
+pub fn impostor_color_for_ground(kind: GroundKind) -> Color {
+	match kind {
+		GroundKind::Grass => Color::srgb_u8(93, 150, 64),
+		GroundKind::Pathway => Color::srgb_u8(160, 148, 128),
+		GroundKind::PoolPath => Color::srgb_u8(118, 178, 214),
+		GroundKind::Pitch => Color::srgb_u8(140, 126, 80),
+		GroundKind::Custom(number) => custom_ground_kind(number).map_or(Color::srgb_u8(93, 150, 64), |kind| kind.color),
 	}
 }
 
@@ -20,15 +45,34 @@ pub fn logo_for_build_menu(menu: BuildMenu) -> &'static str {
 		BuildMenu::Basics => "concrete.qoi",
 		BuildMenu::Pitch => "pitch-logo.qoi",
 		BuildMenu::Pool => "pool.qoi",
+		BuildMenu::Facilities => image_for_facility(Facility::Reception),
+		BuildMenu::Decoration => "trees.qoi",
+		BuildMenu::Blueprints => "2x3-house-template.qoi",
+		BuildMenu::Custom(number) => custom_menus().get(usize::from(number)).map_or("concrete.qoi", |menu| menu.logo),
 	}
 }
 
+/// Logo of the demolish tool.
+pub const DEMOLISH_LOGO: &str = "demolish-logo.qoi";
+/// Logo of the tool that clears obstacles.
+pub const CLEARING_LOGO: &str = "clearing-logo.qoi";
+
+/// Icon shown above objects that have a problem, such as pitches that visitors can't reach.
+pub const WARNING_ICON: &str = "warning-icon.qoi";
+
 pub fn logo_for_buildable(buildable: Buildable) -> &'static str {
 	match buildable {
 		Buildable::Ground(kind) => image_for_ground(kind),
 		Buildable::PitchType(kind) => image_for_pitch(kind),
 		Buildable::Pitch => "pitch-area-logo.qoi",
 		Buildable::PoolArea => "pool.qoi",
+		Buildable::Fence(_) => image_for_border_kind(BorderKind::Fence),
+		Buildable::Gate => image_for_border_kind(BorderKind::Gate),
+		Buildable::SupplyPoint(_) => image_for_utility(UtilityPartKind::SupplyPoint),
+		Buildable::Conduit(_) => image_for_utility(UtilityPartKind::Conduit),
+		Buildable::Decoration(decoration) => image_for_decoration(decoration),
+		Buildable::Facility(facility) => image_for_facility(facility),
+		Buildable::EntranceRoad => image_for_ground(GroundKind::Pathway),
 	}
 }
 
@@ -38,6 +82,13 @@ pub fn preview_image_for_buildable(buildable: Buildable) -> &'static str {
 		Buildable::PitchType(kind) => image_for_pitch(kind),
 		Buildable::Pitch => "pitch-tile.qoi",
 		Buildable::PoolArea => "pool.qoi",
+		Buildable::Fence(_) => image_for_border_kind(BorderKind::Fence),
+		Buildable::Gate => image_for_border_kind(BorderKind::Gate),
+		Buildable::SupplyPoint(_) => image_for_utility(UtilityPartKind::SupplyPoint),
+		Buildable::Conduit(_) => image_for_utility(UtilityPartKind::Conduit),
+		Buildable::Decoration(decoration) => image_for_decoration(decoration),
+		Buildable::Facility(facility) => image_for_facility(facility),
+		Buildable::EntranceRoad => image_for_ground(GroundKind::Pathway),
 	}
 }
 
@@ -51,9 +102,128 @@ pub fn image_for_pitch(kind: PitchType) -> &'static str {
 	}
 }
 
+/// There are no dedicated utility graphics yet, so utility parts use generic images tinted in the utility's color.
+pub fn image_for_utility(kind: UtilityPartKind) -> &'static str {
+	match kind {
+		UtilityPartKind::SupplyPoint => "concrete.qoi",
+		UtilityPartKind::Conduit => "gravel.qoi",
+	}
+}
+
+/// Only trees have dedicated graphics yet; the other decorations use generic images.
+pub fn image_for_decoration(decoration: Decoration) -> &'static str {
+	match decoration {
+		Decoration::Tree | Decoration::FlowerBed => "trees.qoi",
+		Decoration::Bench | Decoration::Lamp => "concrete.qoi",
+	}
+}
+
+pub fn image_for_obstacle(obstacle: Obstacle) -> &'static str {
+	match obstacle {
+		Obstacle::Rock => "rock.qoi",
+		Obstacle::MatureTree => "mature-tree.qoi",
+	}
+}
+
+pub fn image_for_facility(facility: Facility) -> &'static str {
+	match facility {
+		Facility::Reception => "reception.qoi",
+		Facility::Toilets => "toilets.qoi",
+		Facility::Showers => "showers.qoi",
+		Facility::Kiosk => "kiosk.qoi",
+		Facility::Restaurant => "restaurant.qoi",
+	}
+}
+
+/// Lookup table image of the color LUT; the neutral table is generated instead of loaded.
+pub fn image_for_lut(lut: ColorLut) -> Option<&'static str> {
+	match lut {
+		ColorLut::Neutral => None,
+		ColorLut::Night => Some("lut-night.qoi"),
+		ColorLut::Sunset => Some("lut-sunset.qoi"),
+		ColorLut::Sepia => Some("lut-sepia.qoi"),
+		ColorLut::Deuteranopia => Some("lut-deuteranopia.qoi"),
+		ColorLut::HighContrast => Some("lut-high-contrast.qoi"),
+	}
+}
+
+/// Shader for drawing the canvas graded by color LUTs.
+pub const CANVAS_GRADING_SHADER: &str = "canvas-grading.wgsl";
+
 pub fn image_for_border_kind(kind: BorderKind) -> &'static str {
 	match kind {
 		BorderKind::Pitch => "pitch-border.qoi",
+		BorderKind::Fence => "pool-fence.qoi",
+		BorderKind::Gate => "gate.qoi",
+	}
+}
+
+pub fn image_for_cursor(kind: CursorKind) -> &'static str {
+	match kind {
+		CursorKind::Default => "cursor-default.qoi",
+		CursorKind::Build => "cursor-build.qoi",
+		CursorKind::Grab => "cursor-grab.qoi",
+		CursorKind::Demolish => "cursor-demolish.qoi",
+	}
+}
+
+/// Images that are used commonly enough that they are loaded before entering the game.
+pub fn preload_images() -> impl Iterator<Item = &'static str> {
+	ALL_BUILDABLES
+		.into_iter()
+		.flat_map(|buildable| [logo_for_buildable(buildable), preview_image_for_buildable(buildable)])
+		.chain(all_build_menus().map(logo_for_build_menu))
+		.chain([GroundKind::Grass, GroundKind::Pathway, GroundKind::PoolPath, GroundKind::Pitch].map(image_for_ground))
+		.chain(custom_ground_kinds().map(image_for_ground))
+		.chain([BorderKind::Pitch, BorderKind::Fence, BorderKind::Gate].map(image_for_border_kind))
+		.chain(ALL_CURSOR_KINDS.map(image_for_cursor))
+		.chain(ALL_OBSTACLES.map(image_for_obstacle))
+		.chain([DEMOLISH_LOGO, CLEARING_LOGO, WARNING_ICON])
+		.unique()
+}
+
+/// A multi-frame sprite sheet image.
+///
+/// Sheets are QOI images named `<name>-sheet.qoi`. All frames have the same size. Each row holds the frames of one
+/// animation from left to right, and the rows are in the order of [`Self::animations`]. Rows of shorter animations are
+/// padded with empty frames at the end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteSheet {
+	pub image:      &'static str,
+	/// Size of a single frame in pixels.
+	pub frame_size: UVec2,
+	/// The animations in this sheet with their number of frames, one per row.
+	pub animations: &'static [(AnimationState, usize)],
+	/// How long every frame is shown.
+	pub frame_time: Duration,
+}
+
+impl SpriteSheet {
+	/// The atlas index of the first frame of the state's animation and its number of frames. States without an
+	/// animation in this sheet use the first animation.
+	pub fn frames_of(&self, state: AnimationState) -> (usize, usize) {
+		let columns = self.columns();
+		self.animations
+			.iter()
+			.enumerate()
+			.find(|(_, (animation, _))| *animation == state)
+			.or_else(|| self.animations.iter().enumerate().next())
+			.map(|(row, (_, frames))| (row * columns, (*frames).max(1)))
+			.unwrap_or((0, 1))
+	}
+
+	pub fn columns(&self) -> usize {
+		self.animations.iter().map(|(_, frames)| *frames).max().unwrap_or(1).max(1)
+	}
+
+	pub fn layout(&self) -> TextureAtlasLayout {
+		TextureAtlasLayout::from_grid(
+			self.frame_size,
+			self.columns() as u32,
+			self.animations.len().max(1) as u32,
+			None,
+			None,
+		)
 	}
 }
 
@@ -80,6 +250,14 @@ pub enum FontStyle {
 	Italic,
 }
 
+/// All fonts, which are loaded before entering the game.
+pub fn preload_fonts() -> impl Iterator<Item = String> {
+	[FontWeight::Regular, FontWeight::Bold]
+		.into_iter()
+		.cartesian_product([FontStyle::Regular, FontStyle::Italic])
+		.map(|(weight, style)| font_for(weight, style))
+}
+
 pub fn font_for(weight: FontWeight, style: FontStyle) -> String {
 	format!(
 		"CrimsonPro-{}{}.ttf",
@@ -0,0 +1,249 @@
+//! Color lookup tables (LUTs) for grading the whole canvas.
+//!
+//! The canvas isn't drawn as a plain sprite, but with a [`CanvasMaterial`] that maps every pixel through a color
+//! lookup table as the final step before it reaches the screen. This is used for moods like the time of day, photo
+//! filters and accessibility modes. Lookup tables are ordinary image assets (see [`ColorLut`]), so they can be
+//! replaced or edited without touching the game code.
+//!
+//! The simulation requests a table through [`CanvasGrading::requested`], which follows the time of day in game; the
+//! `color_lut` setting overrides it for debugging. When the table changes, the canvas smoothly blends from the old
+//! table to the new one.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{AsBindGroup, Extent3d, ShaderRef, TextureDimension, TextureFormat};
+use bevy::sprite::{Material2d, Material2dPlugin};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::library::{image_for_lut, CANVAS_GRADING_SHADER};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::model::time_of_day::{DayPhase, TimeOfDay};
+
+pub(super) struct LutPlugin;
+
+impl Plugin for LutPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(Material2dPlugin::<CanvasMaterial>::default())
+			.add_systems(Update, (select_lut, blend_luts).chain().in_set(GameSet::Graphics))
+			.add_systems(
+				Update,
+				follow_time_of_day.before(select_lut).in_set(GameSet::Graphics).in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(GameState::MainMenu), reset_requested_lut);
+	}
+}
+
+/// How long it takes to blend from one lookup table to another.
+const BLEND_DURATION: Duration = Duration::from_millis(1500);
+
+/// Number of entries along each color axis of the neutral lookup table.
+const NEUTRAL_LUT_SIZE: u32 = 16;
+
+/// The color lookup tables that the canvas can be graded with.
+///
+/// Tables are stored as N² × N strip images in linear QOI: the blue channel selects one of N horizontal N × N slices,
+/// within which red increases from left to right and green from top to bottom. Entries are sRGB-encoded colors.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorLut {
+	/// Leaves all colors unchanged.
+	#[default]
+	Neutral,
+	/// Dark and blue, for the night.
+	Night,
+	/// Warm golden light.
+	Sunset,
+	/// Old photo filter.
+	Sepia,
+	/// Moves red-green differences into blue, for players with deuteranopia.
+	Deuteranopia,
+	/// Stronger contrast and saturation.
+	HighContrast,
+}
+
+impl ColorLut {
+	pub const ALL: [Self; 6] =
+		[Self::Neutral, Self::Night, Self::Sunset, Self::Sepia, Self::Deuteranopia, Self::HighContrast];
+
+	/// The next table in [`Self::ALL`], wrapping around at the end.
+	pub fn next(&self) -> Self {
+		let index = Self::ALL.iter().position(|lut| lut == self).unwrap_or_default();
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+}
+
+impl From<DayPhase> for ColorLut {
+	fn from(phase: DayPhase) -> Self {
+		match phase {
+			DayPhase::Dawn | DayPhase::Dusk => Self::Sunset,
+			DayPhase::Day => Self::Neutral,
+			DayPhase::Night => Self::Night,
+		}
+	}
+}
+
+impl std::fmt::Display for ColorLut {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Neutral => "Neutral",
+			Self::Night => "Night",
+			Self::Sunset => "Sunset",
+			Self::Sepia => "Sepia",
+			Self::Deuteranopia => "Deuteranopia",
+			Self::HighContrast => "High contrast",
+		})
+	}
+}
+
+/// Draws the low-resolution canvas, graded by a blend of two lookup tables.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct CanvasMaterial {
+	/// How far the blend from the first to the second table has progressed, between 0 and 1.
+	#[uniform(0)]
+	blend:    f32,
+	#[texture(1)]
+	#[sampler(2)]
+	canvas:   Handle<Image>,
+	// Tables are interpolated manually in the shader, so they don't need samplers.
+	#[texture(3)]
+	from_lut: Handle<Image>,
+	#[texture(4)]
+	to_lut:   Handle<Image>,
+}
+
+impl CanvasMaterial {
+	/// Draws the canvas without grading.
+	pub fn neutral(canvas: Handle<Image>, neutral_lut: Handle<Image>) -> Self {
+		Self { blend: 1., canvas, from_lut: neutral_lut.clone(), to_lut: neutral_lut }
+	}
+}
+
+impl Material2d for CanvasMaterial {
+	fn fragment_shader() -> ShaderRef {
+		CANVAS_GRADING_SHADER.into()
+	}
+}
+
+/// The lookup table which maps every color to itself.
+pub fn neutral_lut() -> Image {
+	let size = NEUTRAL_LUT_SIZE;
+	let entry = |index: u32| (index * 255 / (size - 1)) as u8;
+	let data = (0 .. size)
+		.flat_map(|green| {
+			(0 .. size * size).flat_map(move |column| [entry(column % size), entry(green), entry(column / size), 255])
+		})
+		.collect();
+	Image::new(
+		Extent3d { width: size * size, height: size, depth_or_array_layers: 1 },
+		TextureDimension::D2,
+		data,
+		TextureFormat::Rgba8Unorm,
+		RenderAssetUsages::RENDER_WORLD,
+	)
+}
+
+/// State of the canvas grading.
+#[derive(Resource, Debug)]
+pub struct CanvasGrading {
+	/// The table that the simulation wants the canvas to be graded with.
+	pub requested: ColorLut,
+	/// The table being blended from.
+	from:          ColorLut,
+	/// The table being blended to, which is fully shown once the blend is done.
+	to:            ColorLut,
+	blend:         f32,
+	/// Handles of all tables used so far, which keep them loaded.
+	tables:        HashMap<ColorLut, Handle<Image>>,
+}
+
+impl CanvasGrading {
+	pub fn new(neutral_lut: Handle<Image>) -> Self {
+		Self {
+			requested: ColorLut::Neutral,
+			from:      ColorLut::Neutral,
+			to:        ColorLut::Neutral,
+			blend:     1.,
+			tables:    HashMap::from([(ColorLut::Neutral, neutral_lut)]),
+		}
+	}
+
+	fn table(&mut self, lut: ColorLut, asset_server: &AssetServer) -> Handle<Image> {
+		self.tables
+			.entry(lut)
+			.or_insert_with(|| image_for_lut(lut).map(|path| asset_server.load(path)).unwrap_or_default())
+			.clone()
+	}
+}
+
+fn select_lut(
+	settings: Res<GameSettings>,
+	asset_server: Res<AssetServer>,
+	images: Res<Assets<Image>>,
+	mut grading: ResMut<CanvasGrading>,
+) {
+	let wanted = if settings.color_lut != ColorLut::Neutral { settings.color_lut } else { grading.requested };
+	if wanted == grading.bypass_change_detection().to {
+		return;
+	}
+	// The canvas can't be drawn while one of its tables is missing, so the old table stays until the new one loaded.
+	let table = grading.bypass_change_detection().table(wanted, &asset_server);
+	if !images.contains(&table) {
+		return;
+	}
+
+	let grading = &mut *grading;
+	if wanted == grading.from {
+		// Reversing a blend continues from the current mix instead of jumping.
+		(grading.from, grading.to) = (grading.to, grading.from);
+		grading.blend = 1. - grading.blend;
+	} else {
+		// Only two tables can be mixed, so an unfinished blend continues from whichever table is more visible.
+		if grading.blend >= 0.5 {
+			grading.from = grading.to;
+		}
+		grading.to = wanted;
+		grading.blend = 0.;
+	}
+}
+
+fn blend_luts(
+	time: Res<Time<Real>>,
+	mut grading: ResMut<CanvasGrading>,
+	canvases: Query<&MeshMaterial2d<CanvasMaterial>>,
+	mut materials: ResMut<Assets<CanvasMaterial>>,
+) {
+	if grading.blend < 1. {
+		grading.blend = (grading.blend + time.delta_secs() / BLEND_DURATION.as_secs_f32()).min(1.);
+	}
+	if !grading.is_changed() {
+		return;
+	}
+	let grading = &mut *grading;
+	let (Some(from_lut), Some(to_lut)) = (grading.tables.get(&grading.from), grading.tables.get(&grading.to)) else {
+		return;
+	};
+	for canvas in &canvases {
+		if let Some(material) = materials.get_mut(canvas) {
+			material.blend = grading.blend;
+			material.from_lut = from_lut.clone();
+			material.to_lut = to_lut.clone();
+		}
+	}
+}
+
+/// Requests the table for the current phase of the day.
+fn follow_time_of_day(settings: Res<GameSettings>, time_of_day: Res<TimeOfDay>, mut grading: ResMut<CanvasGrading>) {
+	let lut = if settings.day_night_cycle { time_of_day.phase().into() } else { ColorLut::Neutral };
+	if grading.requested != lut {
+		grading.requested = lut;
+	}
+}
+
+fn reset_requested_lut(grading: Option<ResMut<CanvasGrading>>) {
+	if let Some(mut grading) = grading {
+		grading.requested = ColorLut::Neutral;
+	}
+}
@@ -1,18 +1,34 @@
-use std::ops::{BitAnd, BitXor, BitXorAssign};
+use std::ops::{BitAnd, BitOr, BitOrAssign, BitXor, BitXorAssign};
 use std::sync::OnceLock;
 
 use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy::utils::HashMap;
-use moonshine_save::save::Save;
 
+pub use self::effects::{build_dust, BuildPop};
+pub use self::overlay::{Overlay, OverlayLayer, OverlayLayerExt, OverlaySystems, WalkingSpeedLayer, NO_DATA_COLOR};
 use self::rendering::*;
-pub use self::rendering::{InGameCamera, HIGH_RES_LAYERS, RES_HEIGHT, RES_WIDTH};
+pub use self::rendering::{Canvas, CanvasViewport, InGameCamera, HIGH_RES_LAYERS};
+use crate::gamemode::GameSet;
 use crate::model::area::{Area, ImmutableArea};
+use crate::model::spatial::{update_spatial_index, SpatialIndex};
 use crate::model::{ActorPosition, GridBox, GridPosition, GroundMap, WorldPosition};
 
+pub(crate) mod animation;
+mod capture;
+mod culling;
+pub(crate) mod decals;
+mod effects;
+mod grading;
+mod ground_chunks;
+mod impostors;
 pub(crate) mod library;
+pub(crate) mod lut;
+mod overlay;
+pub(crate) mod palette;
+pub(crate) mod particles;
+mod rain;
 mod rendering;
 
 /// Plugin responsible for setting up a window and running and initializing graphics.
@@ -20,22 +36,43 @@ pub struct GraphicsPlugin;
 
 impl Plugin for GraphicsPlugin {
 	fn build(&self, app: &mut App) {
-		app.init_resource::<BorderTextures>()
-			.register_type::<BorderKind>()
-			.register_type::<Sides>()
-			.register_type::<ObjectPriority>()
-			.add_systems(Startup, initialize_rendering)
-			.add_systems(
-				PreUpdate,
-				(add_transforms::<ActorPosition>, add_transforms::<GridPosition>, add_transforms::<GridBox>),
-			)
-			.add_systems(
-				PostUpdate,
-				(position_objects::<ActorPosition>, position_objects::<GridPosition>, position_objects::<GridBox>)
-					.before(move_edge_objects_in_front_of_boxes),
-			)
-			.add_systems(PostUpdate, move_edge_objects_in_front_of_boxes)
-			.add_systems(Update, (fit_canvas, update_area_borders, update_immutable_area_borders, fix_window_aspect));
+		app.add_plugins((
+			particles::ParticlePlugin,
+			animation::SpriteAnimationPlugin,
+			capture::CapturePlugin,
+			overlay::OverlayPlugin,
+			grading::GradingPlugin,
+			culling::CullingPlugin,
+			impostors::ImpostorPlugin,
+			lut::LutPlugin,
+			rain::RainPlugin,
+			ground_chunks::GroundChunkPlugin,
+		))
+		.init_resource::<BorderTextures>()
+		.register_type::<BorderKind>()
+		.register_type::<Sides>()
+		.register_type::<ObjectPriority>()
+		.add_systems(Startup, initialize_rendering)
+		.add_systems(
+			PreUpdate,
+			(add_transforms::<ActorPosition>, add_transforms::<GridPosition>, add_transforms::<GridBox>)
+				.in_set(GameSet::Graphics),
+		)
+		.add_systems(
+			PostUpdate,
+			(position_objects::<ActorPosition>, position_objects::<GridPosition>, position_objects::<GridBox>)
+				.before(move_edge_objects_in_front_of_boxes)
+				.in_set(GameSet::Graphics),
+		)
+		.add_systems(
+			PostUpdate,
+			move_edge_objects_in_front_of_boxes.after(update_spatial_index).in_set(GameSet::Graphics),
+		)
+		.add_systems(
+			Update,
+			(fit_canvas, effects::animate_build_pop, update_area_borders, update_immutable_area_borders)
+				.in_set(GameSet::Graphics),
+		);
 	}
 }
 
@@ -43,6 +80,8 @@ impl Plugin for GraphicsPlugin {
 #[reflect(Component)]
 pub enum BorderKind {
 	Pitch,
+	Fence,
+	Gate,
 }
 
 #[derive(Resource, Default)]
@@ -77,10 +116,9 @@ pub struct BorderSprite {
 	pub(crate) sprite: Sprite,
 	pub offset:        ActorPosition,
 	priority:          ObjectPriority,
-	save:              Save,
 }
 
-#[derive(Debug, Component, Reflect, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Component, Reflect, Clone, Copy, Eq, PartialEq, Hash)]
 #[reflect(Component)]
 pub struct Sides(u8);
 
@@ -92,6 +130,20 @@ impl BitAnd for Sides {
 	}
 }
 
+impl BitOr for Sides {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self::Output {
+		Self(self.0 | rhs.0)
+	}
+}
+
+impl BitOrAssign for Sides {
+	fn bitor_assign(&mut self, rhs: Self) {
+		self.0 |= rhs.0
+	}
+}
+
 impl BitXor for Sides {
 	type Output = Self;
 
@@ -125,6 +177,15 @@ impl Sides {
 		Self(0b1111)
 	}
 
+	pub const fn empty() -> Self {
+		Self(0)
+	}
+
+	/// The sides across from these sides.
+	pub const fn opposite(self) -> Self {
+		Self((self.0 << 2 | self.0 >> 2) & 0b1111)
+	}
+
 	pub fn to_sprite_index(self) -> usize {
 		match self {
 			Self::Top => 0,
@@ -159,6 +220,13 @@ impl Sides {
 		Anchor::Custom(self.tile_offset())
 	}
 
+	/// Like [`Self::anchor`], but for border sprites positioned at their tile's corner instead of at their
+	/// [`Self::world_offset`], such as in building previews.
+	pub fn anchor_at_tile(self) -> Anchor {
+		const BORDER_SIZE: Vec2 = Vec2::new(TILE_WIDTH, 16.);
+		Anchor::Custom(self.tile_offset() - world_to_engine_space(self.world_offset()) / BORDER_SIZE)
+	}
+
 	pub fn world_offset(self) -> Vec3A {
 		self.iter()
 			.map(|side| match side {
@@ -188,7 +256,6 @@ impl BorderSprite {
 				sprite: Sprite::from_atlas_image(image, TextureAtlas { layout, index: side.to_sprite_index() }),
 				offset: side.world_offset().into(),
 				priority: ObjectPriority::Border,
-				save: Save,
 			};
 			this.sprite.anchor = side.anchor();
 			this
@@ -223,13 +290,16 @@ fn update_immutable_area_borders(
 }
 
 /// Graphical object priorities assist in z-sorting objects at the same position.
-#[derive(Clone, Copy, Debug, Component, Reflect)]
+#[derive(Clone, Copy, Debug, Default, Component, Reflect)]
 #[reflect(Component)]
 pub enum ObjectPriority {
 	/// Ground objects have the lowest priority.
 	Ground,
+	/// Decals are painted onto the ground, so they appear directly on top of it but below everything else.
+	Decal,
 	/// Normal objects have a priority higher than ground objects so they always appear on top of ground objects on the
 	/// same tile.
+	#[default]
 	Normal,
 	/// Tiles on borders need to be elevated since their position makes them a lower z index than they should actually
 	/// be.
@@ -239,16 +309,11 @@ pub enum ObjectPriority {
 	Overlay,
 }
 
-impl Default for ObjectPriority {
-	fn default() -> Self {
-		Self::Normal
-	}
-}
-
 impl ObjectPriority {
 	pub fn index(&self) -> f32 {
 		match self {
 			ObjectPriority::Ground => 0.,
+			ObjectPriority::Decal => 0.1,
 			ObjectPriority::Normal => 1.,
 			ObjectPriority::Border => 1.5,
 			ObjectPriority::Overlay => 1000.,
@@ -262,13 +327,9 @@ pub static TRANSFORMATION_MATRIX: OnceLock<Mat3> = OnceLock::new();
 pub const TILE_HEIGHT: f32 = 12.;
 pub const TILE_WIDTH: f32 = 16.;
 
-fn position_objects<PositionType: WorldPosition>(
-	mut entities: Query<
-		(&mut Transform, &PositionType, Option<&ObjectPriority>),
-		Or<(Changed<PositionType>, Added<PositionType>, Added<Transform>)>,
-	>,
-) {
-	TRANSFORMATION_MATRIX.get_or_init(|| {
+/// The affine transform from world space to engine space.
+fn transformation_matrix() -> Mat3 {
+	*TRANSFORMATION_MATRIX.get_or_init(|| {
 		// Our iso grid is a simple affine transform away from the real world position.
 		// We only have a small, roughly 45°-rotation to the right, then a vertical scale.
 		// The exact parameters are calculated with the fact that the triangle describing a tile corner has width 8 and
@@ -277,10 +338,18 @@ fn position_objects<PositionType: WorldPosition>(
 		let y_vector = (-(TILE_WIDTH / 2.).round(), (TILE_HEIGHT / 2.).round() + 1., 0.).into();
 		// Only map z onto the y and z axes. Applying it to z as well will make 2D z sorting work correctly.
 		Mat3::from_cols(x_vector, y_vector, Vec3::Y * (TILE_HEIGHT / 4.).round() + Vec3::Z)
-	});
+	})
+}
+
+fn position_objects<PositionType: WorldPosition>(
+	mut entities: Query<
+		(&mut Transform, &PositionType, Option<&ObjectPriority>),
+		Or<(Changed<PositionType>, Added<PositionType>, Added<Transform>)>,
+	>,
+) {
+	let matrix = transformation_matrix();
 	for (mut bevy_transform, world_position_type, priority) in &mut entities {
 		let world_position = world_position_type.position();
-		let matrix = TRANSFORMATION_MATRIX.get().cloned().unwrap();
 		// The translation rounding here is about 90% of pixel-perfectness:
 		// - Make sure everything is camera-space pixel aligned (this code)
 		// - Make sure all sprite anchors fall on pixel corners (sprite initialization code)
@@ -312,7 +381,7 @@ fn add_transforms<PositionType: WorldPosition>(
 fn move_edge_objects_in_front_of_boxes(
 	mut edge_objects: Query<(&mut Transform, &ActorPosition, Option<&Parent>), Changed<Transform>>,
 	possible_parents: Query<&GridPosition, With<Children>>,
-	boxed_entities: Query<&GridBox>,
+	index: Res<SpatialIndex>,
 ) {
 	edge_objects.par_iter_mut().for_each(|(mut bevy_transform, edge_object_position, parent)| {
 		let own_position = if let Some(parent) = parent.and_then(|parent| possible_parents.get(parent.get()).ok()) {
@@ -321,9 +390,13 @@ fn move_edge_objects_in_front_of_boxes(
 			**edge_object_position
 		};
 
-		// PERFORMANCE: This is a prime optimization candidate.
-		if let Some(smallest_edge_box) = boxed_entities
-			.iter()
+		// A box with the position on its smaller edges covers the tile of the position or one of the tiles below and
+		// to the left of it.
+		let tile = GridPosition(own_position.floor().as_ivec3());
+		if let Some(smallest_edge_box) = [IVec2::ZERO, IVec2::NEG_X, IVec2::NEG_Y, IVec2::NEG_ONE]
+			.into_iter()
+			.flat_map(|offset| index.at(tile + offset))
+			.map(|(_, grid_box)| grid_box)
 			.filter(|grid_box| grid_box.has_on_smaller_edges(own_position))
 			.min_by_key(|grid_box| grid_box.corner.x + grid_box.corner.y)
 		{
@@ -333,13 +406,18 @@ fn move_edge_objects_in_front_of_boxes(
 	});
 }
 
+/// Translates from world space to a pixel-aligned bevy engine position, ignoring z sorting.
+pub fn world_to_engine_space(world_position: Vec3A) -> Vec2 {
+	(transformation_matrix() * world_position).truncate().round()
+}
+
 /// Translates from a bevy engine position back to world space. Note that z needs to be provided and generally
 /// depends on the surface at the specific location.
 pub fn engine_to_world_space(engine_position: Vec2, z: f32) -> ActorPosition {
 	// The matrix is invertible, since we keep the z dimension when using it normally, so we can make use of that by
 	// synthetically re-inserting the z coordinate into the 2D engine position and getting a precise inverse transform
 	// for free.
-	let matrix = TRANSFORMATION_MATRIX.get().unwrap().inverse();
+	let matrix = transformation_matrix().inverse();
 	let engine_space_with_synthetic_z: Vec3 = (engine_position, z).into();
 	// The z coordinate here is garbage; discard it and replace it with the given one.
 	let mut world_space = matrix * engine_space_with_synthetic_z;
@@ -0,0 +1,239 @@
+//! Overlays that recolor the ground to visualize simulation data.
+//!
+//! Every overlay shows one data layer, such as walking speed or foot traffic, by tinting all ground tiles on a color
+//! ramp; tiles for which the layer has no data are gray. Subsystems provide their layers by implementing
+//! [`OverlayLayer`] and registering it with [`OverlayLayerExt::add_overlay_layer`]. The player cycles through the
+//! registered layers with N and Shift+N, and a legend explains the colors of the shown layer.
+//!
+//! Computing a layer can mean looking at every tile, so the values of the shown layer are only refreshed every
+//! [`REFRESH_INTERVAL`], and layers that aren't shown aren't computed at all.
+
+use std::any::TypeId;
+use std::time::Duration;
+
+use bevy::ecs::system::{StaticSystemParam, SystemParam, SystemParamItem};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::palette::Palette;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::input::{Action, ActionInput};
+use crate::model::nav::{NavCategory, NavComponent};
+use crate::model::{GridPosition, GroundKind};
+
+/// Highest traversal speed of any ground kind; tiles with this speed are colored fully green by the walking speed
+/// layer.
+const MAX_SPEED: u32 = 2;
+/// How often the values of the shown layer are recomputed.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// Color of tiles for which the shown layer has no data.
+pub const NO_DATA_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+
+/// A data layer that an overlay can show on the ground tiles.
+pub trait OverlayLayer: Send + Sync + 'static {
+	/// Player-facing name of the layer, shown in the legend.
+	const NAME: &'static str;
+	/// Legend labels of the lowest and highest value.
+	const LEGEND: [&'static str; 2];
+	/// Hues of the lowest and highest value. By default, low values are red and high values green. The colorblind-safe
+	/// palette ignores the hues.
+	const HUES: [f32; 2] = [0., 120.];
+
+	/// The world data that the layer is computed from.
+	type Param: SystemParam + 'static;
+
+	/// Computes the layer's value for all tiles that have one, between 0 for the lowest and 1 for the highest value.
+	fn values(param: SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32>;
+}
+
+/// A registered overlay layer.
+#[derive(Clone, Copy, Debug)]
+pub struct LayerInfo {
+	type_id:    TypeId,
+	pub name:   &'static str,
+	pub legend: [&'static str; 2],
+	pub hues:   [f32; 2],
+}
+
+impl LayerInfo {
+	/// Overlay color for a value of the layer, or for a tile without a value ([`None`]).
+	pub fn color(&self, value: Option<f32>, palette: Palette) -> Color {
+		match value {
+			None => NO_DATA_COLOR,
+			Some(value) => palette.ramp(self.hues, value),
+		}
+	}
+}
+
+/// All registered overlay layers, and the one that is currently shown.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Overlay {
+	/// Layers in the order they are cycled through.
+	layers: Vec<LayerInfo>,
+	/// Index of the shown layer, or [`None`] while no overlay is shown.
+	shown:  Option<usize>,
+}
+
+impl Overlay {
+	/// The layer that is currently shown, if any.
+	pub fn shown(&self) -> Option<&LayerInfo> {
+		self.shown.and_then(|index| self.layers.get(index))
+	}
+
+	/// Whether any overlay is shown.
+	pub fn is_active(&self) -> bool {
+		self.shown().is_some()
+	}
+
+	/// Whether the layer `L` is shown.
+	pub fn shows<L: OverlayLayer>(&self) -> bool {
+		self.shown().is_some_and(|layer| layer.type_id == TypeId::of::<L>())
+	}
+
+	/// Shows the next or previous layer. Before the first and after the last layer, no overlay is shown.
+	fn cycle(&mut self, forward: bool) {
+		let count = self.layers.len();
+		self.shown = match (self.shown, forward) {
+			(None, true) => (count > 0).then_some(0),
+			(None, false) => count.checked_sub(1),
+			(Some(index), true) => (index + 1 < count).then_some(index + 1),
+			(Some(index), false) => index.checked_sub(1),
+		};
+	}
+}
+
+/// The values of the shown layer.
+#[derive(Resource, Clone, Debug, Default)]
+struct OverlayValues {
+	tiles: HashMap<GridPosition, f32>,
+	/// Whether the values need to be recomputed.
+	stale: bool,
+}
+
+/// Extension point for registering overlay layers.
+pub trait OverlayLayerExt {
+	/// Registers the layer `L`, which is appended to the layers that the player cycles through.
+	fn add_overlay_layer<L: OverlayLayer>(&mut self) -> &mut Self;
+}
+
+impl OverlayLayerExt for App {
+	fn add_overlay_layer<L: OverlayLayer>(&mut self) -> &mut Self {
+		self.init_resource::<Overlay>().init_resource::<OverlayValues>();
+		self.world_mut().resource_mut::<Overlay>().layers.push(LayerInfo {
+			type_id: TypeId::of::<L>(),
+			name:    L::NAME,
+			legend:  L::LEGEND,
+			hues:    L::HUES,
+		});
+		self.add_systems(
+			PostUpdate,
+			sample_layer::<L>.in_set(OverlaySystems::Sample).in_set(GameSet::Graphics).in_set(GameState::InGame),
+		)
+	}
+}
+
+/// The stages of updating the overlay, in order.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OverlaySystems {
+	/// Marks the values as stale when they need to be recomputed.
+	Refresh,
+	/// Recomputes the values of the shown layer.
+	Sample,
+	/// Colors the ground tiles.
+	Apply,
+}
+
+/// How fast people can walk across a tile, which is what their paths prefer.
+pub struct WalkingSpeedLayer;
+
+impl OverlayLayer for WalkingSpeedLayer {
+	type Param = Query<'static, 'static, (&'static GridPosition, &'static NavComponent), With<GroundKind>>;
+
+	const LEGEND: [&'static str; 2] = ["Slow", "Fast"];
+	const NAME: &'static str = "Walking speed";
+
+	/// Tiles that people can't walk on at all have no value.
+	fn values(tiles: SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		tiles
+			.iter()
+			.filter(|(_, vertex)| vertex.navigability != NavCategory::None)
+			.map(|(position, vertex)| {
+				(*position, (vertex.speed.saturating_sub(1) as f32 / (MAX_SPEED - 1) as f32).clamp(0., 1.))
+			})
+			.collect()
+	}
+}
+
+pub(super) struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Overlay>()
+			.init_resource::<OverlayValues>()
+			.configure_sets(
+				PostUpdate,
+				(OverlaySystems::Refresh, OverlaySystems::Sample, OverlaySystems::Apply).chain(),
+			)
+			.add_overlay_layer::<WalkingSpeedLayer>()
+			.add_systems(Update, cycle_overlay.in_set(GameSet::Input).in_set(GameState::InGame))
+			.add_systems(
+				PostUpdate,
+				(refresh_overlay.in_set(OverlaySystems::Refresh), apply_overlay.in_set(OverlaySystems::Apply))
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+fn cycle_overlay(actions: ActionInput, mut overlay: ResMut<Overlay>) {
+	if actions.just_pressed(Action::NextOverlay) {
+		overlay.cycle(true);
+	} else if actions.just_pressed(Action::PreviousOverlay) {
+		overlay.cycle(false);
+	}
+}
+
+fn refresh_overlay(
+	time: Res<Time>,
+	overlay: Res<Overlay>,
+	mut since_refresh: Local<Duration>,
+	mut values: ResMut<OverlayValues>,
+) {
+	*since_refresh += time.delta();
+	if overlay.is_changed() || (overlay.is_active() && *since_refresh >= REFRESH_INTERVAL) {
+		*since_refresh = Duration::ZERO;
+		values.stale = true;
+	}
+}
+
+fn sample_layer<L: OverlayLayer>(
+	overlay: Res<Overlay>,
+	mut values: ResMut<OverlayValues>,
+	param: StaticSystemParam<L::Param>,
+) {
+	if overlay.shows::<L>() && values.stale {
+		values.tiles = L::values(param.into_inner());
+		values.stale = false;
+	}
+}
+
+/// Ground chunks are rebuilt whenever a tile's color changes, so colors are only assigned when they differ.
+fn apply_overlay(
+	overlay: Res<Overlay>,
+	values: Res<OverlayValues>,
+	settings: Res<GameSettings>,
+	mut tiles: Query<(&GridPosition, &mut Sprite), With<GroundKind>>,
+) {
+	if !overlay.is_changed() && !values.is_changed() && !settings.is_changed() {
+		return;
+	}
+	let palette = settings.palette();
+	for (position, mut sprite) in &mut tiles {
+		let color =
+			overlay.shown().map_or(Color::WHITE, |layer| layer.color(values.tiles.get(position).copied(), palette));
+		if sprite.color != color {
+			sprite.color = color;
+		}
+	}
+}
@@ -0,0 +1,42 @@
+//! Colors that tell good from bad, in a standard and a colorblind-safe variant.
+//!
+//! Overlays, notification borders and the save diff use color to tell good from bad. The standard palette uses the
+//! familiar red, orange and green, which players with red-green color blindness can't tell apart. The colorblind-safe
+//! palette, selected with the `colorblind_palette` setting, uses colors from the Okabe-Ito palette instead, which
+//! differ in brightness as well as hue. Its overlay ramps run from blue to yellow, regardless of the layer's hues.
+
+use bevy::color::{Mix, Oklaba};
+use bevy::prelude::*;
+
+/// Blue of the Okabe-Ito palette.
+pub const SAFE_BLUE: Color = Color::srgb(0., 0.447, 0.698);
+/// Sky blue of the Okabe-Ito palette.
+pub const SAFE_SKY_BLUE: Color = Color::srgb(0.337, 0.706, 0.914);
+/// Yellow of the Okabe-Ito palette.
+pub const SAFE_YELLOW: Color = Color::srgb(0.941, 0.894, 0.259);
+/// Vermillion of the Okabe-Ito palette.
+pub const SAFE_VERMILLION: Color = Color::srgb(0.835, 0.369, 0.);
+
+/// The set of colors that UI and overlays use to tell good from bad.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+	#[default]
+	Standard,
+	ColorblindSafe,
+}
+
+impl Palette {
+	/// Color of a value on a ramp between the lowest (0) and the highest value (1). The standard palette moves between
+	/// the two hues.
+	pub fn ramp(self, hues: [f32; 2], value: f32) -> Color {
+		let value = value.clamp(0., 1.);
+		match self {
+			Self::Standard => {
+				let [low, high] = hues;
+				Color::hsl(low + (high - low) * value, 0.8, 0.6)
+			},
+			// Blending in Oklab keeps the brightness increasing evenly along the ramp.
+			Self::ColorblindSafe => Oklaba::from(SAFE_BLUE).mix(&Oklaba::from(SAFE_YELLOW), value).into(),
+		}
+	}
+}
@@ -0,0 +1,208 @@
+//! A lightweight particle system rendering single-pixel particles onto the low-resolution canvas.
+//!
+//! Particles are simulated in engine space, where one unit is one canvas pixel, and snapped to whole pixels for
+//! rendering so that they blend in with the pixel art. Since effects like rain constantly create and destroy many
+//! particles, particle entities are pooled and reused instead of being despawned.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// How a [`ParticleEmitter`] emits its particles.
+#[derive(Clone, Copy, Debug)]
+pub enum Emission {
+	/// Emit this many particles at once, then stop. The emitter despawns itself once all of its particles have died.
+	Burst(u32),
+	/// Emit particles continuously at this rate, in particles per second.
+	Continuous(f32),
+}
+
+/// An entity emitting particles around its position. Emitters can be attached to any entity with a transform, including
+/// children of other entities.
+#[derive(Component, Clone, Debug)]
+pub struct ParticleEmitter {
+	/// How particles are emitted.
+	pub emission:      Emission,
+	/// Maximum number of this emitter's particles alive at the same time. Emission pauses while the cap is reached.
+	pub max_particles: u32,
+	/// Particles spawn at a random position within this rectangle, relative to the emitter.
+	pub spawn_area:    Rect,
+	/// Particles start with a random velocity within this rectangle, in pixels per second.
+	pub velocity:      Rect,
+	/// Constant acceleration applied to all particles, such as gravity, in pixels per second squared.
+	pub acceleration:  Vec2,
+	/// Range of particle lifetimes.
+	pub lifetime:      Range<Duration>,
+	/// Every particle has a random color out of this list.
+	pub colors:        Vec<Color>,
+	/// Whether particles fade out over their lifetime.
+	pub fade_out:      bool,
+}
+
+impl ParticleEmitter {
+	/// Creates an emitter with the given emission mode and particle cap, emitting white, motionless particles from a
+	/// single point. Use struct update syntax to configure the other properties.
+	pub fn new(emission: Emission, max_particles: u32) -> Self {
+		Self {
+			emission,
+			max_particles,
+			spawn_area: Rect::default(),
+			velocity: Rect::default(),
+			acceleration: Vec2::ZERO,
+			lifetime: Duration::from_secs(1) .. Duration::from_secs(1),
+			colors: vec![Color::WHITE],
+			fade_out: false,
+		}
+	}
+}
+
+/// Runtime state of a [`ParticleEmitter`], added to it automatically.
+#[derive(Component, Clone, Copy, Debug, Default)]
+struct EmitterState {
+	/// Number of particles that are due for emission; the fractional part carries over between frames.
+	pending:        f32,
+	started:        bool,
+	live_particles: u32,
+}
+
+/// A single particle. Particles don't have a [`ParticleEmitter`] as their parent, since they must move independently of
+/// the emitter once emitted.
+#[derive(Component, Clone, Debug)]
+struct Particle {
+	emitter:      Entity,
+	position:     Vec2,
+	/// The z coordinate is taken from the emitter and kept constant.
+	z:            f32,
+	velocity:     Vec2,
+	acceleration: Vec2,
+	age:          Duration,
+	lifetime:     Duration,
+	color:        Color,
+	fade_out:     bool,
+}
+
+/// Hidden particle entities that are ready for reuse.
+#[derive(Resource, Default)]
+struct ParticlePool {
+	free: Vec<Entity>,
+}
+
+/// Plugin for the particle system.
+pub(super) struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ParticlePool>().add_systems(
+			PostUpdate,
+			(emit_particles, simulate_particles).chain().after(TransformSystem::TransformPropagate),
+		);
+	}
+}
+
+fn random_in(rect: Rect) -> Vec2 {
+	rect.min + rect.size() * Vec2::new(fastrand::f32(), fastrand::f32())
+}
+
+fn emit_particles(
+	time: Res<Time>,
+	mut emitters: Query<(Entity, &ParticleEmitter, Option<&mut EmitterState>, &GlobalTransform)>,
+	mut pool: ResMut<ParticlePool>,
+	mut commands: Commands,
+) {
+	for (entity, emitter, state, global_transform) in &mut emitters {
+		// The emitter's global transform is only valid starting with the frame after it was spawned, so the first frame
+		// is only used to set up the emitter's state.
+		let Some(mut state) = state else {
+			commands.entity(entity).insert(EmitterState::default());
+			continue;
+		};
+		if matches!(emitter.emission, Emission::Burst(_)) && state.started && state.live_particles == 0 {
+			commands.entity(entity).despawn_recursive();
+			continue;
+		}
+
+		match emitter.emission {
+			Emission::Burst(count) if !state.started => state.pending += count as f32,
+			Emission::Burst(_) => {},
+			Emission::Continuous(rate) => state.pending += rate * time.delta_secs(),
+		}
+		state.started = true;
+
+		let available = emitter.max_particles.saturating_sub(state.live_particles);
+		let count = (state.pending as u32).min(available);
+		// Particles that can't be emitted due to the cap are dropped instead of being emitted in a large clump later.
+		state.pending = state.pending.fract();
+		state.live_particles += count;
+
+		let origin = global_transform.translation();
+		for _ in 0 .. count {
+			let lifetime_spread = emitter.lifetime.end.saturating_sub(emitter.lifetime.start);
+			let color =
+				emitter.colors.get(fastrand::usize(.. emitter.colors.len().max(1))).copied().unwrap_or_default();
+			let particle = Particle {
+				emitter: entity,
+				position: origin.truncate() + random_in(emitter.spawn_area),
+				z: origin.z,
+				velocity: random_in(emitter.velocity),
+				acceleration: emitter.acceleration,
+				age: Duration::ZERO,
+				lifetime: emitter.lifetime.start + lifetime_spread.mul_f32(fastrand::f32()),
+				color,
+				fade_out: emitter.fade_out,
+			};
+			// Set the global transform directly, so that the particle doesn't appear at the origin for one frame.
+			let transform = Transform::from_translation(particle.position.round().extend(particle.z));
+			let components = (
+				Sprite::from_color(color, Vec2::ONE),
+				transform,
+				GlobalTransform::from(transform),
+				Visibility::Visible,
+				particle,
+			);
+			match pool.free.pop() {
+				Some(reused) => {
+					commands.entity(reused).insert(components);
+				},
+				None => {
+					commands.spawn(components);
+				},
+			}
+		}
+	}
+}
+
+fn simulate_particles(
+	time: Res<Time>,
+	mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut GlobalTransform, &mut Sprite, &mut Visibility)>,
+	mut emitters: Query<&mut EmitterState>,
+	mut pool: ResMut<ParticlePool>,
+	mut commands: Commands,
+) {
+	let delta = time.delta();
+	for (entity, mut particle, mut transform, mut global_transform, mut sprite, mut visibility) in &mut particles {
+		let particle = &mut *particle;
+		particle.age += delta;
+		if particle.age >= particle.lifetime {
+			// The emitter may already be gone, in which case nobody needs to know about this particle's death.
+			if let Ok(mut state) = emitters.get_mut(particle.emitter) {
+				state.live_particles = state.live_particles.saturating_sub(1);
+			}
+			*visibility = Visibility::Hidden;
+			commands.entity(entity).remove::<Particle>();
+			pool.free.push(entity);
+			continue;
+		}
+
+		let dt = delta.as_secs_f32();
+		particle.velocity += particle.acceleration * dt;
+		particle.position += particle.velocity * dt;
+		transform.translation = particle.position.round().extend(particle.z);
+		*global_transform = GlobalTransform::from(*transform);
+
+		if particle.fade_out {
+			let remaining = 1. - particle.age.as_secs_f32() / particle.lifetime.as_secs_f32();
+			sprite.color = particle.color.with_alpha(particle.color.alpha() * remaining);
+		}
+	}
+}
@@ -0,0 +1,75 @@
+//! Rain falling over the whole canvas in bad weather.
+//!
+//! The rain is a single particle emitter attached to the [`InGameCamera`], in front of everything else. Its spawn area
+//! covers the visible part of the world and scales with the zoom, so that the rain looks the same at every zoom level.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::particles::{Emission, ParticleEmitter};
+use super::rendering::{InGameCamera, NEAR_PLANE, RES_HEIGHT, RES_WIDTH};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::model::weather::Weather;
+
+pub(super) struct RainPlugin;
+
+impl Plugin for RainPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Update, update_rain.in_set(GameSet::Graphics));
+	}
+}
+
+/// Marks the emitter of the rain.
+#[derive(Component, Clone, Copy, Debug)]
+struct Rain;
+
+/// Emission rate and particle cap of the rain in the given weather.
+const fn rain_intensity(weather: Weather) -> (f32, u32) {
+	match weather {
+		Weather::Sunny => (0., 0),
+		Weather::Rain => (300., 300),
+		Weather::Storm => (900., 900),
+	}
+}
+
+fn rain_emitter() -> ParticleEmitter {
+	ParticleEmitter {
+		lifetime: Duration::from_millis(200) .. Duration::from_millis(500),
+		colors: vec![Color::srgb_u8(0xa4, 0xb8, 0xd0), Color::srgb_u8(0x8c, 0xa0, 0xbc)],
+		..ParticleEmitter::new(Emission::Continuous(0.), 0)
+	}
+}
+
+fn update_rain(
+	weather: Res<Weather>,
+	settings: Res<GameSettings>,
+	state: Res<State<GameState>>,
+	cameras: Query<(Entity, &OrthographicProjection), With<InGameCamera>>,
+	mut rain: Query<&mut ParticleEmitter, With<Rain>>,
+	mut commands: Commands,
+) {
+	let Ok((camera, projection)) = cameras.get_single() else {
+		return;
+	};
+	let Ok(mut emitter) = rain.get_single_mut() else {
+		// Just inside the near plane, so that the rain falls in front of the whole world.
+		commands.entity(camera).with_child((rain_emitter(), Transform::from_xyz(0., 0., -NEAR_PLANE - 1.), Rain));
+		return;
+	};
+
+	let raining = !settings.reduced_effects && matches!(state.get(), GameState::InGame | GameState::Paused);
+	let (rate, max_particles) = if raining { rain_intensity(*weather) } else { (0., 0) };
+	let scale = projection.scale;
+	let half_size = Vec2::new(RES_WIDTH as f32, RES_HEIGHT as f32) * scale / 2.;
+	// Storms blow the rain sideways.
+	let wind = if *weather == Weather::Storm { -90. } else { -20. };
+
+	emitter.emission = Emission::Continuous(rate);
+	emitter.max_particles = max_particles;
+	emitter.spawn_area = Rect::from_corners(-half_size, half_size);
+	emitter.velocity = Rect::new(wind - 10., -260., wind + 10., -200.);
+	emitter.velocity.min *= scale;
+	emitter.velocity.max *= scale;
+}
@@ -7,7 +7,9 @@ use bevy::prelude::*;
 use bevy::render::camera::RenderTarget;
 use bevy::render::render_resource::*;
 use bevy::render::view::RenderLayers;
-use bevy::window::{PrimaryWindow, WindowResized};
+use bevy::window::PrimaryWindow;
+
+use super::lut::{neutral_lut, CanvasGrading, CanvasMaterial};
 
 /// In-game resolution width.
 pub const RES_WIDTH: u32 = 160 * 2;
@@ -26,9 +28,12 @@ pub const HIGH_RES_LAYERS: RenderLayers = RenderLayers::layer(1);
 pub const NEAR_PLANE: f32 = -10000.;
 
 /// Low-resolution texture that contains the pixel-perfect world.
-/// Canvas itself is rendered to the high-resolution world.
+/// Canvas itself is rendered to the high-resolution world, graded by a [`CanvasMaterial`].
 #[derive(Component)]
-pub struct Canvas;
+pub struct Canvas {
+	/// The texture that the pixel-perfect world is rendered to.
+	pub image: Handle<Image>,
+}
 
 /// Camera that renders the pixel-perfect world to the [`Canvas`].
 #[derive(Component)]
@@ -42,20 +47,24 @@ pub fn initialize_rendering(
 	mut commands: Commands,
 	_asset_server: Res<AssetServer>,
 	mut images: ResMut<Assets<Image>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<CanvasMaterial>>,
 ) {
 	let canvas_size = Extent3d { width: RES_WIDTH, height: RES_HEIGHT, ..default() };
 
 	// this Image serves as a canvas representing the low-resolution game screen
 	let mut canvas = Image {
 		texture_descriptor: TextureDescriptor {
-			label:           Some("Pixel-perfect camera target texture".into()),
+			label:           Some("Pixel-perfect camera target texture"),
 			size:            canvas_size,
 			dimension:       TextureDimension::D2,
 			format:          TextureFormat::Bgra8UnormSrgb,
 			mip_level_count: 1,
 			sample_count:    1,
+			// COPY_SRC allows reading back the canvas for save thumbnails.
 			usage:           TextureUsages::TEXTURE_BINDING
 				| TextureUsages::COPY_DST
+				| TextureUsages::COPY_SRC
 				| TextureUsages::RENDER_ATTACHMENT,
 			view_formats:    &[],
 		},
@@ -75,7 +84,7 @@ pub fn initialize_rendering(
 			// render before the "main pass" camera
 			order: -1,
 			hdr: true,
-			target: RenderTarget::Image(image_handle.clone().into()),
+			target: RenderTarget::Image(image_handle.clone()),
 			..default()
 		},
 		OrthographicProjection { near: NEAR_PLANE, ..OrthographicProjection::default_2d() },
@@ -86,8 +95,15 @@ pub fn initialize_rendering(
 		PIXEL_PERFECT_LAYERS,
 	));
 
-	// spawn the canvas
-	commands.spawn((Sprite::from_image(image_handle), Canvas, HIGH_RES_LAYERS));
+	// spawn the canvas, which is drawn through the color grading LUTs
+	let neutral_lut = images.add(neutral_lut());
+	commands.insert_resource(CanvasGrading::new(neutral_lut.clone()));
+	commands.spawn((
+		Mesh2d(meshes.add(Rectangle::new(RES_WIDTH as f32, RES_HEIGHT as f32))),
+		MeshMaterial2d(materials.add(CanvasMaterial::neutral(image_handle.clone(), neutral_lut))),
+		Canvas { image: image_handle },
+		HIGH_RES_LAYERS,
+	));
 
 	// the "outer" camera renders whatever is on `HIGH_RES_LAYERS` to the screen.
 	// here, the canvas and one of the sample sprites will be rendered by this camera
@@ -103,41 +119,75 @@ pub fn initialize_rendering(
 	));
 }
 
-/// Scales camera projection to fit the window (integer multiples only).
-pub fn fit_canvas(
-	mut resize_events: EventReader<WindowResized>,
-	mut projection: Query<&mut OrthographicProjection, With<OuterCamera>>,
-) {
-	let Ok(mut projection) = projection.get_single_mut() else {
-		return;
-	};
-	for event in resize_events.read() {
-		let h_scale = event.width / RES_WIDTH as f32;
-		let v_scale = event.height / RES_HEIGHT as f32;
-		projection.scale = 1. / h_scale.min(v_scale);
-	}
+/// Where the canvas is shown within a window.
+///
+/// Every canvas pixel covers the same whole number of physical pixels, so that the pixel art stays crisp; only windows
+/// smaller than the canvas resolution scale it down. The canvas is centered in the window and aligned to physical
+/// pixels. Positions and sizes are in logical pixels like all other window coordinates, which differ from physical
+/// pixels on high-DPI displays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CanvasViewport {
+	/// Position of the canvas' top left corner in the window.
+	pub offset:    Vec2,
+	/// Size of the canvas in the window.
+	pub size:      Vec2,
+	/// Translation of the outer camera that aligns the canvas to physical pixels, in world units.
+	camera_offset: Vec2,
 }
 
-/// Desired window aspect ratio
-pub const DESIRED_RATIO: f32 = RES_WIDTH as f32 / RES_HEIGHT as f32;
+impl CanvasViewport {
+	/// Computes the canvas viewport for the window's current size and scale factor.
+	pub fn of(window: &Window) -> Self {
+		let resolution = Vec2::new(RES_WIDTH as f32, RES_HEIGHT as f32);
+		let physical_size = window.physical_size().as_vec2();
+		let fit = (physical_size / resolution).min_element();
+		// Physical pixels per canvas pixel.
+		let pixel_size = if fit >= 1. { fit.floor() } else { fit.max(f32::EPSILON) };
+		let physical_canvas_size = resolution * pixel_size;
+		let physical_offset = ((physical_size - physical_canvas_size) / 2.).floor();
+		// The outer camera centers the canvas, which puts it half a physical pixel off when the remaining space is odd.
+		let misalignment = physical_offset + physical_canvas_size / 2. - physical_size / 2.;
+		let scale_factor = window.scale_factor();
+		Self {
+			offset:        physical_offset / scale_factor,
+			size:          physical_canvas_size / scale_factor,
+			camera_offset: Vec2::new(-misalignment.x, misalignment.y) / pixel_size,
+		}
+	}
 
-/// Mouse positions cannot be properly translated if the window is not 16:9.
-/// “Solve” this by fixing the window to a 16:9 ratio.
-pub fn fix_window_aspect(
-	mut resize_events: EventReader<WindowResized>,
-	mut windows: Query<&mut bevy::prelude::Window, With<PrimaryWindow>>,
+	/// Converts a position in the window to a pixel position on the canvas, with the origin in the top left corner.
+	pub fn window_to_canvas(&self, position: Vec2) -> Vec2 {
+		(position - self.offset) * Vec2::new(RES_WIDTH as f32, RES_HEIGHT as f32) / self.size
+	}
+
+	/// Converts a pixel position on the canvas to a position in the window.
+	pub fn canvas_to_window(&self, position: Vec2) -> Vec2 {
+		position * self.size / Vec2::new(RES_WIDTH as f32, RES_HEIGHT as f32) + self.offset
+	}
+
+	/// Size of one canvas pixel in the window.
+	pub fn pixel_size(&self) -> f32 {
+		self.size.x / RES_WIDTH as f32
+	}
+}
+
+/// Scales and aligns the outer camera to show the canvas in its [`CanvasViewport`]. This runs whenever the window
+/// changes, which includes resizing it and moving it to a monitor with a different scale factor.
+pub fn fit_canvas(
+	windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+	mut cameras: Query<(&mut OrthographicProjection, &mut Transform), With<OuterCamera>>,
 ) {
-	let Ok(mut window) = windows.get_single_mut() else {
+	let (Ok(window), Ok((mut projection, mut transform))) = (windows.get_single(), cameras.get_single_mut()) else {
 		return;
 	};
-
-	for _event in resize_events.read() {
-		let (width, height) = (window.resolution.width(), window.resolution.height());
-		let current_ratio = width / height;
-		if current_ratio != DESIRED_RATIO {
-			// width / (width / height) = height
-			let ideal_height = width / DESIRED_RATIO;
-			window.resolution.set(width, ideal_height);
-		}
+	let viewport = CanvasViewport::of(window);
+	// The canvas is RES_WIDTH world units wide, and the projection's scale is in world units per logical pixel.
+	let scale = RES_WIDTH as f32 / viewport.size.x;
+	if projection.scale != scale {
+		projection.scale = scale;
+	}
+	let translation = viewport.camera_offset.extend(transform.translation.z);
+	if transform.translation != translation {
+		transform.translation = translation;
 	}
 }
@@ -0,0 +1,126 @@
+//! Custom pixel-art mouse cursor.
+//!
+//! The system cursor is hidden inside the window and replaced by a cursor that matches the game's art style and shows
+//! what a click will do. Since the cursor is part of the UI, it is drawn in high resolution and on top of everything
+//! else. The `system_cursor` setting keeps the system cursor instead.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::keyboard::VirtualCursor;
+use super::{DragStartPosition, InputState};
+use crate::config::GameSettings;
+use crate::gamemode::GameSet;
+use crate::graphics::library::image_for_cursor;
+use crate::graphics::{CanvasViewport, HIGH_RES_LAYERS};
+
+/// Cursor pixels are this many times smaller than game pixels.
+const CURSOR_PIXEL_RATIO: f32 = 3.;
+/// Width and height of all cursor images.
+const CURSOR_SIZE: f32 = 16.;
+
+pub(super) struct CursorPlugin;
+
+impl Plugin for CursorPlugin {
+	fn build(&self, app: &mut App) {
+		// The cursor is shown in all game states, including menus.
+		app.add_systems(Startup, spawn_cursor).add_systems(Update, update_cursor.in_set(GameSet::UI));
+	}
+}
+
+/// The different cursor images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorKind {
+	/// Pointing arrow for selecting things.
+	Default,
+	/// Hammer while placing buildings or painting.
+	Build,
+	/// Closed hand while dragging the camera.
+	Grab,
+	/// Red cross while demolishing.
+	Demolish,
+}
+
+/// All cursor kinds.
+pub const ALL_CURSOR_KINDS: [CursorKind; 4] =
+	[CursorKind::Default, CursorKind::Build, CursorKind::Grab, CursorKind::Demolish];
+
+impl CursorKind {
+	/// The pixel within the cursor image that points at the cursor position.
+	const fn hotspot(&self) -> Vec2 {
+		match self {
+			Self::Default => Vec2::ZERO,
+			Self::Build => Vec2::new(2., 2.),
+			Self::Grab | Self::Demolish => Vec2::new(7., 7.),
+		}
+	}
+}
+
+/// Marks the UI node that displays the custom cursor.
+#[derive(Component, Clone, Copy, Debug)]
+struct GameCursor;
+
+fn spawn_cursor(mut commands: Commands) {
+	commands.spawn((
+		GameCursor,
+		ImageNode::default(),
+		Node { position_type: PositionType::Absolute, ..Default::default() },
+		// Above all other UI.
+		GlobalZIndex(i32::MAX),
+		Visibility::Hidden,
+		HIGH_RES_LAYERS,
+	));
+}
+
+fn update_cursor(
+	settings: Res<GameSettings>,
+	input_state: Res<State<InputState>>,
+	mouse: Res<ButtonInput<MouseButton>>,
+	drag_start: Res<DragStartPosition>,
+	mut windows: Query<&mut Window, With<PrimaryWindow>>,
+	mut cursor: Query<(&mut ImageNode, &mut Node, &mut Visibility), With<GameCursor>>,
+	asset_server: Res<AssetServer>,
+	virtual_cursor: Res<VirtualCursor>,
+	mut shown_kind: Local<Option<CursorKind>>,
+) {
+	let (Ok(mut window), Ok((mut image, mut node, mut visibility))) =
+		(windows.get_single_mut(), cursor.get_single_mut())
+	else {
+		return;
+	};
+	if window.cursor_options.visible != settings.system_cursor {
+		window.cursor_options.visible = settings.system_cursor;
+	}
+
+	let kind = match input_state.get() {
+		_ if settings.system_cursor => None,
+		// The camera follows the walker, so there's nothing to point at.
+		InputState::Walking => None,
+		InputState::Building
+		| InputState::Painting
+		| InputState::CaptureBlueprint
+		| InputState::StampBlueprint
+		| InputState::Selecting
+		| InputState::AutoRoute => Some(CursorKind::Build),
+		InputState::Demolish | InputState::Clearing => Some(CursorKind::Demolish),
+		InputState::Idle if drag_start.0.is_some() && mouse.pressed(MouseButton::Left) => Some(CursorKind::Grab),
+		_ => Some(CursorKind::Default),
+	};
+	let (Some(kind), Some(position)) = (kind, virtual_cursor.pointer_position(&window)) else {
+		visibility.set_if_neq(Visibility::Hidden);
+		return;
+	};
+	visibility.set_if_neq(Visibility::Visible);
+
+	if *shown_kind != Some(kind) {
+		image.image = asset_server.load(image_for_cursor(kind));
+		*shown_kind = Some(kind);
+	}
+	let pixel_size =
+		(CanvasViewport::of(&window).pixel_size() / CURSOR_PIXEL_RATIO * settings.cursor_scale).round().max(1.);
+	let top_left = position - kind.hotspot() * pixel_size;
+	node.left = Val::Px(top_left.x);
+	node.top = Val::Px(top_left.y);
+	node.width = Val::Px(CURSOR_SIZE * pixel_size);
+	node.height = Val::Px(CURSOR_SIZE * pixel_size);
+}
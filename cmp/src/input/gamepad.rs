@@ -0,0 +1,103 @@
+//! Camera controls for gamepads.
+//!
+//! The right stick pans the camera and the triggers zoom it in and out. While a gamepad is used, the virtual cursor
+//! stays on the tile in the middle of the screen, so that the world moves under it and the face buttons can place and
+//! cancel builds there. The d-pad and face buttons for the UI are handled together with the keyboard in
+//! [`super::keyboard`].
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use super::keyboard::{press_with_virtual_cursor, update_virtual_cursor_position, VirtualCursor};
+use super::picking::tile_under;
+use super::{InputState, ZOOM_RANGE};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::InGameCamera;
+use crate::model::GridPosition;
+
+/// Camera panning speed at full stick deflection, in engine units per second at a camera scale of 1.
+const PAN_SPEED: f32 = 400.;
+
+pub(super) struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			PreUpdate,
+			point_at_screen_center
+				.after(InputSystem)
+				.before(press_with_virtual_cursor)
+				.run_if(in_state(GameState::InGame)),
+		)
+		.add_systems(
+			Update,
+			(pan_camera_with_gamepad, zoom_camera_with_gamepad)
+				.before(update_virtual_cursor_position)
+				.run_if(not(in_state(InputState::Walking)))
+				.in_set(GameSet::Input)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// The tile in the middle of the camera's view.
+fn center_tile(camera_transform: &Transform, projection: &OrthographicProjection) -> GridPosition {
+	tile_under(camera_transform.translation.truncate() + projection.area.center())
+}
+
+/// Any gamepad input switches to the virtual cursor, before the south face button is turned into a click.
+fn point_at_screen_center(
+	gamepads: Query<&Gamepad>,
+	camera: Query<(&Transform, &OrthographicProjection), With<InGameCamera>>,
+	mut cursor: ResMut<VirtualCursor>,
+) {
+	let used = gamepads
+		.iter()
+		.any(|gamepad| gamepad.get_just_pressed().next().is_some() || gamepad.right_stick() != Vec2::ZERO);
+	if !used || cursor.is_active() {
+		return;
+	}
+	if let Ok((transform, projection)) = camera.get_single() {
+		cursor.point_at(center_tile(transform, projection));
+	}
+}
+
+/// Fractions of pixels are kept in `remainder`, since the camera only moves by whole pixels.
+fn pan_camera_with_gamepad(
+	gamepads: Query<&Gamepad>,
+	time: Res<Time<Real>>,
+	mut camera: Query<(&mut Transform, &OrthographicProjection), With<InGameCamera>>,
+	mut cursor: ResMut<VirtualCursor>,
+	mut remainder: Local<Vec2>,
+) {
+	let stick = gamepads.iter().map(Gamepad::right_stick).sum::<Vec2>().clamp_length_max(1.);
+	if stick == Vec2::ZERO {
+		*remainder = Vec2::ZERO;
+		return;
+	}
+	let Ok((mut transform, projection)) = camera.get_single_mut() else {
+		return;
+	};
+	let movement = stick * PAN_SPEED * projection.scale * time.delta_secs() + *remainder;
+	let whole_pixels = movement.round();
+	*remainder = movement - whole_pixels;
+	transform.translation += whole_pixels.extend(0.);
+	cursor.point_at(center_tile(&transform, projection));
+}
+
+/// Each trigger press zooms by one power of two, like a step of the mouse wheel.
+fn zoom_camera_with_gamepad(
+	gamepads: Query<&Gamepad>,
+	mut camera: Query<&mut OrthographicProjection, With<InGameCamera>>,
+) {
+	let zoom_in = gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::RightTrigger2));
+	let zoom_out = gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::LeftTrigger2));
+	if zoom_in == zoom_out {
+		return;
+	}
+	let Ok(mut projection) = camera.get_single_mut() else {
+		return;
+	};
+	let steps = if zoom_in { -1. } else { 1. };
+	projection.scale = 2f32.powf(projection.scale.log2().round() + steps).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+}
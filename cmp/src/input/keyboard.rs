@@ -0,0 +1,301 @@
+//! Playing without a mouse.
+//!
+//! The cursor movement actions (the arrow keys by default) move a virtual cursor across the world's tiles, and so does
+//! panning with a gamepad (see [`super::gamepad`]). While it is active, the virtual cursor replaces the mouse pointer
+//! for picking, and the confirm action (Enter or a gamepad's south face button) acts as the left mouse button, so that
+//! all pointer-driven tools (building, demolishing, painting, selecting) work the same with either. Moving the mouse
+//! switches back to the mouse pointer.
+//!
+//! The focus actions (Tab and Shift-Tab by default) move the keyboard focus between the visible buttons, and so does a
+//! gamepad's d-pad, which moves to the closest button in its direction. Confirming presses the focused button, and
+//! cancelling removes the focus.
+
+use bevy::input::InputSystem;
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy::window::{CursorMoved, PrimaryWindow};
+
+use super::{world_to_camera, Action, ActionInput, InputState};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{world_to_engine_space, InGameCamera};
+use crate::input::picking::tile_under;
+use crate::model::GridPosition;
+use crate::util::text_input::edit_text_inputs;
+
+/// Outline of the button with keyboard focus.
+const FOCUS_OUTLINE: Outline = Outline { width: Val::Px(3.), offset: Val::Px(2.), color: Color::srgb(1., 0.8, 0.) };
+/// The camera follows the keyboard cursor once it leaves this fraction of the window around the center.
+const CAMERA_FOLLOW_AREA: f32 = 0.6;
+
+pub(super) struct KeyboardPlugin;
+
+impl Plugin for KeyboardPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<VirtualCursor>()
+			.init_resource::<KeyboardFocus>()
+			.add_systems(
+				PreUpdate,
+				(
+					press_with_virtual_cursor.after(InputSystem).after(UiSystem::Focus),
+					press_focused_button.after(UiSystem::Focus),
+					move_keyboard_focus,
+				)
+					.after(edit_text_inputs),
+			)
+			.add_systems(
+				Update,
+				(move_keyboard_cursor, update_virtual_cursor_position)
+					.chain()
+					.run_if(not(in_state(InputState::Walking)))
+					.in_set(GameSet::Input)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnExit(GameState::InGame), deactivate_virtual_cursor);
+	}
+}
+
+/// The world cursor controlled by the keyboard or a gamepad, which points at tiles independently of the mouse.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct VirtualCursor {
+	/// The tile under the virtual cursor, or [`None`] if the mouse is used instead.
+	tile:            Option<GridPosition>,
+	/// Screen position of the center of the tile, in logical window pixels.
+	screen_position: Option<Vec2>,
+}
+
+impl VirtualCursor {
+	/// Whether the virtual cursor currently replaces the mouse pointer.
+	pub fn is_active(&self) -> bool {
+		self.tile.is_some()
+	}
+
+	/// The screen position that pointer-driven systems should use: the virtual cursor's if it is active, otherwise
+	/// the mouse cursor's.
+	pub fn pointer_position(&self, window: &Window) -> Option<Vec2> {
+		if self.is_active() {
+			self.screen_position
+		} else {
+			window.cursor_position()
+		}
+	}
+
+	/// Moves the virtual cursor to the tile, replacing the mouse pointer if it was used before.
+	pub(super) fn point_at(&mut self, tile: GridPosition) {
+		self.tile = Some(tile);
+	}
+}
+
+/// The button with keyboard focus, if any.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct KeyboardFocus(Option<Entity>);
+
+impl KeyboardFocus {
+	/// The focused button.
+	pub fn get(&self) -> Option<Entity> {
+		self.0
+	}
+}
+
+/// Grid offset for each cursor movement action. Since the grid axes run diagonally on screen, each direction moves to
+/// the neighbor that is closest to it, going clockwise: up moves up and right.
+const CURSOR_OFFSETS: [(Action, IVec3); 4] = [
+	(Action::CursorUp, IVec3::new(1, 0, 0)),
+	(Action::CursorRight, IVec3::new(0, -1, 0)),
+	(Action::CursorDown, IVec3::new(-1, 0, 0)),
+	(Action::CursorLeft, IVec3::new(0, 1, 0)),
+];
+
+/// Modifiers are allowed while moving, since they change how tools such as the selection treat the drag.
+fn move_keyboard_cursor(
+	actions: ActionInput,
+	mut cursor_moved: EventReader<CursorMoved>,
+	mut cursor: ResMut<VirtualCursor>,
+	window: Query<&Window, With<PrimaryWindow>>,
+	mut camera: Query<(&Camera, &mut Transform, &GlobalTransform), With<InGameCamera>>,
+) {
+	if cursor_moved.read().count() > 0 {
+		cursor.tile = None;
+	}
+	let offset = CURSOR_OFFSETS
+		.into_iter()
+		.filter(|(action, _)| actions.just_pressed_allowing_modifiers(*action))
+		.map(|(_, offset)| offset)
+		.sum::<IVec3>();
+	if offset == IVec3::ZERO {
+		return;
+	}
+	let (Ok(window), Ok((camera, mut camera_transform, camera_global_transform))) =
+		(window.get_single(), camera.get_single_mut())
+	else {
+		return;
+	};
+
+	let tile = match cursor.tile {
+		Some(tile) => tile + offset,
+		// Start in the middle of the screen.
+		None => {
+			let Some(center) =
+				super::camera_to_world(window.size() / 2., window, camera, camera_global_transform).map(tile_under)
+			else {
+				return;
+			};
+			center
+		},
+	};
+	cursor.tile = Some(tile);
+
+	let engine_position = tile_center(tile);
+	let Some(screen_position) = world_to_camera(engine_position.extend(0.), window, camera, camera_global_transform)
+	else {
+		return;
+	};
+	let follow_area = window.size() * CAMERA_FOLLOW_AREA;
+	let distance_from_center = (screen_position - window.size() / 2.).abs();
+	if distance_from_center.x > follow_area.x / 2. || distance_from_center.y > follow_area.y / 2. {
+		camera_transform.translation = engine_position.extend(camera_transform.translation.z).round();
+	}
+}
+
+/// The screen position has to be updated every frame, since the camera may move for other reasons, such as zooming.
+pub(super) fn update_virtual_cursor_position(
+	mut cursor: ResMut<VirtualCursor>,
+	window: Query<&Window, With<PrimaryWindow>>,
+	camera: Query<(&Camera, &GlobalTransform), With<InGameCamera>>,
+) {
+	let (Some(tile), Ok(window), Ok((camera, camera_transform))) =
+		(cursor.tile, window.get_single(), camera.get_single())
+	else {
+		cursor.screen_position = None;
+		return;
+	};
+	cursor.screen_position = world_to_camera(tile_center(tile).extend(0.), window, camera, camera_transform);
+}
+
+fn deactivate_virtual_cursor(mut cursor: ResMut<VirtualCursor>) {
+	*cursor = VirtualCursor::default();
+}
+
+/// Engine position of the center of the tile.
+fn tile_center(tile: GridPosition) -> Vec2 {
+	world_to_engine_space(Vec3A::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5, tile.z as f32))
+}
+
+/// Holding the confirm key holds the left mouse button, so that dragging with the cursor movement keys works like
+/// dragging the mouse. Modifiers are allowed, so that for example Shift extends a selection as with the mouse.
+pub(super) fn press_with_virtual_cursor(
+	actions: ActionInput,
+	cursor: Res<VirtualCursor>,
+	focus: Res<KeyboardFocus>,
+	mut mouse: ResMut<ButtonInput<MouseButton>>,
+) {
+	if actions.just_pressed_allowing_modifiers(Action::Confirm) && cursor.is_active() && focus.0.is_none() {
+		mouse.press(MouseButton::Left);
+	}
+	// Also release when the virtual cursor was deactivated in the meantime, so the button doesn't get stuck.
+	if actions.just_released(Action::Confirm) && mouse.pressed(MouseButton::Left) {
+		mouse.release(MouseButton::Left);
+	}
+}
+
+/// Screen direction of each d-pad button, with y pointing down like UI coordinates.
+const DPAD_DIRECTIONS: [(GamepadButton, Vec2); 4] = [
+	(GamepadButton::DPadUp, Vec2::NEG_Y),
+	(GamepadButton::DPadRight, Vec2::X),
+	(GamepadButton::DPadDown, Vec2::Y),
+	(GamepadButton::DPadLeft, Vec2::NEG_X),
+];
+
+/// The button closest to the position in the direction. Buttons off to the side count as farther away, so that moving
+/// down a column of buttons doesn't jump to a neighboring column.
+fn closest_in_direction(position: Vec2, direction: Vec2, buttons: &[(Entity, Vec2)]) -> Option<Entity> {
+	buttons
+		.iter()
+		.filter_map(|(entity, button_position)| {
+			let offset = *button_position - position;
+			let along = offset.dot(direction);
+			(along > 0.).then(|| (*entity, along + 2. * offset.perp_dot(direction).abs()))
+		})
+		.min_by(|(_, a), (_, b)| a.total_cmp(b))
+		.map(|(entity, _)| entity)
+}
+
+fn move_keyboard_focus(
+	actions: ActionInput,
+	gamepads: Query<&Gamepad>,
+	mut focus: ResMut<KeyboardFocus>,
+	buttons: Query<(Entity, &ComputedNode, &InheritedVisibility, &GlobalTransform), With<Button>>,
+	mut commands: Commands,
+) {
+	let previous_focus = focus.0;
+	// Reading order: top to bottom, then left to right.
+	let visible_buttons = || {
+		let mut visible_buttons = buttons
+			.iter()
+			.filter(|(_, node, visibility, _)| visibility.get() && node.size() != Vec2::ZERO)
+			.map(|(entity, _, _, transform)| (entity, transform.translation().truncate()))
+			.collect::<Vec<_>>();
+		visible_buttons.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+		visible_buttons
+	};
+	let dpad_direction = DPAD_DIRECTIONS
+		.into_iter()
+		.filter(|(button, _)| gamepads.iter().any(|gamepad| gamepad.just_pressed(*button)))
+		.map(|(_, direction)| direction)
+		.sum::<Vec2>();
+
+	if actions.just_pressed(Action::Cancel) {
+		focus.0 = None;
+	} else if dpad_direction != Vec2::ZERO {
+		let visible_buttons = visible_buttons();
+		let current_position = focus
+			.0
+			.and_then(|focused| visible_buttons.iter().find(|(entity, _)| *entity == focused))
+			.map(|(_, position)| *position);
+		focus.0 = match current_position {
+			Some(position) => closest_in_direction(position, dpad_direction.normalize(), &visible_buttons).or(focus.0),
+			None => visible_buttons.first().map(|(entity, _)| *entity),
+		};
+	} else if actions.any_just_pressed([Action::FocusNext, Action::FocusPrevious]) {
+		let visible_buttons = visible_buttons();
+		let current_index =
+			focus.0.and_then(|focused| visible_buttons.iter().position(|(entity, _)| *entity == focused));
+		let backwards = actions.just_pressed(Action::FocusPrevious);
+		let count = visible_buttons.len();
+		focus.0 = match (current_index, backwards) {
+			_ if count == 0 => None,
+			(None, false) => Some(0),
+			(None, true) => Some(count - 1),
+			(Some(index), false) => Some((index + 1) % count),
+			(Some(index), true) => Some((index + count - 1) % count),
+		}
+		.map(|index| visible_buttons[index].0);
+	} else if focus.0.is_some_and(|focused| !buttons.contains(focused)) {
+		// The focused button was despawned.
+		focus.0 = None;
+	}
+
+	if focus.0 != previous_focus {
+		if let Some(mut entity) = previous_focus.and_then(|previous| commands.get_entity(previous)) {
+			entity.remove::<Outline>();
+		}
+		if let Some(focused) = focus.0 {
+			commands.entity(focused).insert(FOCUS_OUTLINE);
+		}
+	}
+}
+
+/// Runs after the UI computed the interactions, so that the press isn't immediately overwritten. On the next frame, the
+/// interaction returns to normal by itself.
+fn press_focused_button(
+	actions: ActionInput,
+	focus: Res<KeyboardFocus>,
+	mut interactions: Query<&mut Interaction, With<Button>>,
+) {
+	if !actions.just_pressed_allowing_modifiers(Action::Confirm) {
+		return;
+	}
+	if let Some(mut interaction) = focus.0.and_then(|focused| interactions.get_mut(focused).ok()) {
+		*interaction = Interaction::Pressed;
+	}
+}
@@ -0,0 +1,754 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum};
+use bevy::window::{PrimaryWindow, WindowMode};
+
+use self::picking::Picking;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{CanvasViewport, InGameCamera};
+
+pub(crate) mod cursor;
+mod gamepad;
+pub(crate) mod keyboard;
+pub(crate) mod picking;
+mod walk;
+
+/// What the player is currently doing in the UI.
+#[derive(States, Hash, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InputState {
+	/// Not doing anything.
+	#[default]
+	Idle,
+	/// Placing a building.
+	Building,
+	/// Walking around the world in first-person walk mode.
+	Walking,
+	/// Painting decals onto the ground.
+	Painting,
+	/// Selecting things to demolish.
+	Demolish,
+	/// Selecting obstacles to clear.
+	Clearing,
+	/// Selecting a region to capture as a blueprint.
+	CaptureBlueprint,
+	/// Placing copies of a blueprint.
+	StampBlueprint,
+	/// Selecting tiles for bulk actions.
+	Selecting,
+	/// Picking a pitch to connect to the utilities automatically.
+	AutoRoute,
+}
+
+#[derive(Default)]
+pub struct GUIInputPlugin;
+
+impl Plugin for GUIInputPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins((walk::WalkPlugin, cursor::CursorPlugin, keyboard::KeyboardPlugin, gamepad::GamepadPlugin))
+			// The config plugin replaces this with the player's key bindings.
+			.init_resource::<InputMap>()
+			.init_state::<InputState>()
+			.init_resource::<DragStartPosition>()
+			.add_event::<MouseClick>()
+			.add_systems(
+				Update,
+				(
+					move_camera.run_if(in_state(InputState::Idle)),
+					fix_camera.run_if(not(in_state(InputState::Idle))),
+					zoom_camera,
+					fullscreen,
+				)
+					.in_set(GameSet::Input)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Something the player does with a key press. Which keys trigger an action is configured in the [`InputMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+	Cancel,
+	Confirm,
+	Fullscreen,
+	ToggleVsync,
+	QuickSave,
+	QuickLoad,
+	Pause,
+	NormalSpeed,
+	FastSpeed,
+	FastestSpeed,
+	Undo,
+	Redo,
+	NextOverlay,
+	PreviousOverlay,
+	Visitors,
+	Modifiers,
+	Staff,
+	Calculator,
+	Pricing,
+	SearchBuildables,
+	EditLayout,
+	PaintMode,
+	PaintNumber,
+	PaintArrow,
+	PaintLine,
+	RotateMarking,
+	SelectMode,
+	DemolishSelection,
+	AutoRoute,
+	WalkMode,
+	WalkForward,
+	WalkBackward,
+	WalkLeft,
+	WalkRight,
+	CursorUp,
+	CursorRight,
+	CursorDown,
+	CursorLeft,
+	FocusNext,
+	FocusPrevious,
+	Screenshot,
+	FullScreenshot,
+	TimeLapse,
+	Inspector,
+	InspectorIncrement,
+	InspectorDecrement,
+	Console,
+}
+
+impl Action {
+	/// All actions, in the order in which they are shown to the player.
+	pub const ALL: [Self; 47] = [
+		Self::Cancel,
+		Self::Confirm,
+		Self::Pause,
+		Self::NormalSpeed,
+		Self::FastSpeed,
+		Self::FastestSpeed,
+		Self::Undo,
+		Self::Redo,
+		Self::QuickSave,
+		Self::QuickLoad,
+		Self::Visitors,
+		Self::Modifiers,
+		Self::Staff,
+		Self::Calculator,
+		Self::Pricing,
+		Self::SearchBuildables,
+		Self::NextOverlay,
+		Self::PreviousOverlay,
+		Self::EditLayout,
+		Self::PaintMode,
+		Self::PaintNumber,
+		Self::PaintArrow,
+		Self::PaintLine,
+		Self::RotateMarking,
+		Self::SelectMode,
+		Self::DemolishSelection,
+		Self::AutoRoute,
+		Self::WalkMode,
+		Self::WalkForward,
+		Self::WalkBackward,
+		Self::WalkLeft,
+		Self::WalkRight,
+		Self::CursorUp,
+		Self::CursorRight,
+		Self::CursorDown,
+		Self::CursorLeft,
+		Self::FocusNext,
+		Self::FocusPrevious,
+		Self::Screenshot,
+		Self::FullScreenshot,
+		Self::TimeLapse,
+		Self::Fullscreen,
+		Self::ToggleVsync,
+		Self::Inspector,
+		Self::InspectorIncrement,
+		Self::InspectorDecrement,
+		Self::Console,
+	];
+
+	/// Name of the action in the key bindings file.
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Cancel => "cancel",
+			Self::Confirm => "confirm",
+			Self::Fullscreen => "fullscreen",
+			Self::ToggleVsync => "toggle_vsync",
+			Self::QuickSave => "quick_save",
+			Self::QuickLoad => "quick_load",
+			Self::Pause => "pause",
+			Self::NormalSpeed => "normal_speed",
+			Self::FastSpeed => "fast_speed",
+			Self::FastestSpeed => "fastest_speed",
+			Self::Undo => "undo",
+			Self::Redo => "redo",
+			Self::NextOverlay => "next_overlay",
+			Self::PreviousOverlay => "previous_overlay",
+			Self::Visitors => "visitors",
+			Self::Modifiers => "modifiers",
+			Self::Staff => "staff",
+			Self::Calculator => "calculator",
+			Self::Pricing => "pricing",
+			Self::SearchBuildables => "search_buildables",
+			Self::EditLayout => "edit_layout",
+			Self::PaintMode => "paint_mode",
+			Self::PaintNumber => "paint_number",
+			Self::PaintArrow => "paint_arrow",
+			Self::PaintLine => "paint_line",
+			Self::RotateMarking => "rotate_marking",
+			Self::SelectMode => "select_mode",
+			Self::DemolishSelection => "demolish_selection",
+			Self::AutoRoute => "auto_route",
+			Self::WalkMode => "walk_mode",
+			Self::WalkForward => "walk_forward",
+			Self::WalkBackward => "walk_backward",
+			Self::WalkLeft => "walk_left",
+			Self::WalkRight => "walk_right",
+			Self::CursorUp => "cursor_up",
+			Self::CursorRight => "cursor_right",
+			Self::CursorDown => "cursor_down",
+			Self::CursorLeft => "cursor_left",
+			Self::FocusNext => "focus_next",
+			Self::FocusPrevious => "focus_previous",
+			Self::Screenshot => "screenshot",
+			Self::FullScreenshot => "full_screenshot",
+			Self::TimeLapse => "time_lapse",
+			Self::Inspector => "inspector",
+			Self::InspectorIncrement => "inspector_increment",
+			Self::InspectorDecrement => "inspector_decrement",
+			Self::Console => "console",
+		}
+	}
+
+	/// Short description of the action for the key binding settings.
+	pub const fn description(self) -> &'static str {
+		match self {
+			Self::Cancel => "Cancel / close",
+			Self::Confirm => "Click / press button",
+			Self::Fullscreen => "Toggle fullscreen",
+			Self::ToggleVsync => "Toggle VSync",
+			Self::QuickSave => "Quick save",
+			Self::QuickLoad => "Quick load",
+			Self::Pause => "Pause / resume",
+			Self::NormalSpeed => "Normal speed",
+			Self::FastSpeed => "Fast speed",
+			Self::FastestSpeed => "Fastest speed",
+			Self::Undo => "Undo building",
+			Self::Redo => "Redo building",
+			Self::NextOverlay => "Next overlay",
+			Self::PreviousOverlay => "Previous overlay",
+			Self::Visitors => "Visitor statistics",
+			Self::Modifiers => "Modifiers",
+			Self::Staff => "Staff",
+			Self::Calculator => "Calculator",
+			Self::Pricing => "Pitch prices",
+			Self::SearchBuildables => "Search build menus",
+			Self::EditLayout => "Edit HUD layout",
+			Self::PaintMode => "Paint markings",
+			Self::PaintNumber => "Paint numbers",
+			Self::PaintArrow => "Paint arrows",
+			Self::PaintLine => "Paint lines",
+			Self::RotateMarking => "Rotate marking",
+			Self::SelectMode => "Select tiles",
+			Self::DemolishSelection => "Demolish selection",
+			Self::AutoRoute => "Route utilities",
+			Self::WalkMode => "Walk mode",
+			Self::WalkForward => "Walk forward",
+			Self::WalkBackward => "Walk backward",
+			Self::WalkLeft => "Walk left",
+			Self::WalkRight => "Walk right",
+			Self::CursorUp => "Move cursor up",
+			Self::CursorRight => "Move cursor right",
+			Self::CursorDown => "Move cursor down",
+			Self::CursorLeft => "Move cursor left",
+			Self::FocusNext => "Focus next button",
+			Self::FocusPrevious => "Focus previous button",
+			Self::Screenshot => "Screenshot of the world",
+			Self::FullScreenshot => "Screenshot of the window",
+			Self::TimeLapse => "Start / stop time-lapse",
+			Self::Inspector => "Debug inspector",
+			Self::InspectorIncrement => "Increase inspected field",
+			Self::InspectorDecrement => "Decrease inspected field",
+			Self::Console => "Developer console",
+		}
+	}
+
+	/// The key chords that trigger the action unless the player changed them. No two actions share a default chord.
+	pub fn default_chords(self) -> Vec<KeyChord> {
+		let key = KeyChord::new;
+		match self {
+			Self::Cancel => vec![key(KeyCode::Escape)],
+			Self::Confirm => vec![key(KeyCode::Enter)],
+			Self::Fullscreen => vec![key(KeyCode::F11)],
+			Self::ToggleVsync => vec![key(KeyCode::KeyV).with_ctrl()],
+			Self::QuickSave => vec![key(KeyCode::KeyS).with_ctrl()],
+			Self::QuickLoad => vec![key(KeyCode::KeyO).with_ctrl()],
+			Self::Pause => vec![key(KeyCode::Space)],
+			Self::NormalSpeed => vec![key(KeyCode::Digit1)],
+			Self::FastSpeed => vec![key(KeyCode::Digit2)],
+			Self::FastestSpeed => vec![key(KeyCode::Digit3)],
+			Self::Undo => vec![key(KeyCode::KeyZ).with_ctrl()],
+			Self::Redo => vec![key(KeyCode::KeyY).with_ctrl(), key(KeyCode::KeyZ).with_ctrl().with_shift()],
+			Self::NextOverlay => vec![key(KeyCode::KeyN)],
+			Self::PreviousOverlay => vec![key(KeyCode::KeyN).with_shift()],
+			Self::Visitors => vec![key(KeyCode::KeyG)],
+			Self::Modifiers => vec![key(KeyCode::KeyM)],
+			Self::Staff => vec![key(KeyCode::KeyH)],
+			Self::Calculator => vec![key(KeyCode::KeyC)],
+			Self::Pricing => vec![key(KeyCode::KeyT)],
+			Self::SearchBuildables => vec![key(KeyCode::KeyB)],
+			Self::EditLayout => vec![key(KeyCode::KeyL)],
+			Self::PaintMode => vec![key(KeyCode::KeyP)],
+			Self::PaintNumber => vec![key(KeyCode::Digit1).with_shift()],
+			Self::PaintArrow => vec![key(KeyCode::Digit2).with_shift()],
+			Self::PaintLine => vec![key(KeyCode::Digit3).with_shift()],
+			Self::RotateMarking => vec![key(KeyCode::KeyR)],
+			Self::SelectMode => vec![key(KeyCode::KeyE)],
+			Self::DemolishSelection => vec![key(KeyCode::Delete)],
+			Self::AutoRoute => vec![key(KeyCode::KeyU)],
+			Self::WalkMode => vec![key(KeyCode::KeyF)],
+			Self::WalkForward => vec![key(KeyCode::KeyW)],
+			Self::WalkBackward => vec![key(KeyCode::KeyS)],
+			Self::WalkLeft => vec![key(KeyCode::KeyA)],
+			Self::WalkRight => vec![key(KeyCode::KeyD)],
+			Self::CursorUp => vec![key(KeyCode::ArrowUp)],
+			Self::CursorRight => vec![key(KeyCode::ArrowRight)],
+			Self::CursorDown => vec![key(KeyCode::ArrowDown)],
+			Self::CursorLeft => vec![key(KeyCode::ArrowLeft)],
+			Self::FocusNext => vec![key(KeyCode::Tab)],
+			Self::FocusPrevious => vec![key(KeyCode::Tab).with_shift()],
+			Self::Screenshot => vec![key(KeyCode::F12)],
+			Self::FullScreenshot => vec![key(KeyCode::F12).with_shift()],
+			Self::TimeLapse => vec![key(KeyCode::F12).with_ctrl()],
+			Self::Inspector => vec![key(KeyCode::F3)],
+			Self::InspectorIncrement => vec![key(KeyCode::PageUp)],
+			Self::InspectorDecrement => vec![key(KeyCode::PageDown)],
+			Self::Console => vec![key(KeyCode::Backquote)],
+		}
+	}
+
+	/// The gamepad button that also triggers the action, if any. Gamepad buttons can't be changed, since most of them
+	/// are taken by camera and cursor controls.
+	pub const fn gamepad_button(self) -> Option<GamepadButton> {
+		match self {
+			Self::Cancel => Some(GamepadButton::East),
+			Self::Confirm => Some(GamepadButton::South),
+			Self::Pause => Some(GamepadButton::Start),
+			_ => None,
+		}
+	}
+
+	fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|action| action.name() == name)
+	}
+}
+
+/// A key together with the modifier keys that have to be held while pressing it.
+///
+/// In the key bindings file, chords are written as the modifiers and the key name joined by `+`, such as `Ctrl+KeyS`.
+/// Key names are the names of the [`KeyCode`] variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+	pub key:   KeyCode,
+	pub ctrl:  bool,
+	pub shift: bool,
+	pub alt:   bool,
+}
+
+impl KeyChord {
+	/// A chord of only the key, without modifiers.
+	pub const fn new(key: KeyCode) -> Self {
+		Self { key, ctrl: false, shift: false, alt: false }
+	}
+
+	pub const fn with_ctrl(self) -> Self {
+		Self { ctrl: true, ..self }
+	}
+
+	pub const fn with_shift(self) -> Self {
+		Self { shift: true, ..self }
+	}
+
+	/// The chord of a key that was just pressed with the modifiers that are currently held. Modifier keys don't form
+	/// chords on their own.
+	pub fn held_with(key: KeyCode, keys: &ButtonInput<KeyCode>) -> Option<Self> {
+		let modifiers = [
+			KeyCode::ControlLeft,
+			KeyCode::ControlRight,
+			KeyCode::ShiftLeft,
+			KeyCode::ShiftRight,
+			KeyCode::AltLeft,
+			KeyCode::AltRight,
+			KeyCode::SuperLeft,
+			KeyCode::SuperRight,
+		];
+		(!modifiers.contains(&key)).then(|| Self {
+			key,
+			ctrl: keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+			shift: keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+			alt: keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+		})
+	}
+
+	/// Whether the key was just pressed with exactly the chord's modifiers held, so that for example Ctrl+Z doesn't
+	/// also trigger Ctrl+Shift+Z.
+	pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+		keys.just_pressed(self.key) && Self::held_with(self.key, keys).is_some_and(|held| held == *self)
+	}
+
+	/// Whether the key and all of the chord's modifiers are held. Further modifiers are allowed, so that held actions
+	/// like walking don't stop when a modifier is pressed in between.
+	pub fn pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+		Self::held_with(self.key, keys).is_some_and(|held| {
+			keys.pressed(self.key)
+				&& (held.ctrl || !self.ctrl)
+				&& (held.shift || !self.shift)
+				&& (held.alt || !self.alt)
+		})
+	}
+
+	/// Human-readable name of the chord, such as "Ctrl+S".
+	pub fn label(&self) -> String {
+		let name = self.key.variant_name();
+		let key = name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")).unwrap_or(name);
+		let mut parts = self.modifier_names().collect::<Vec<_>>();
+		parts.push(key);
+		parts.join("+")
+	}
+
+	fn modifier_names(&self) -> impl Iterator<Item = &'static str> {
+		[(self.ctrl, "Ctrl"), (self.shift, "Shift"), (self.alt, "Alt")]
+			.into_iter()
+			.filter_map(|(held, name)| held.then_some(name))
+	}
+}
+
+impl Display for KeyChord {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for modifier in self.modifier_names() {
+			write!(f, "{modifier}+")?;
+		}
+		write!(f, "{}", self.key.variant_name())
+	}
+}
+
+impl FromStr for KeyChord {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+		let key_name = parts.pop().unwrap_or_default();
+		// Only unit variants name a single key; the others carry platform-specific key codes.
+		let key = KeyCode::from_reflect(&DynamicEnum::new(key_name, DynamicVariant::Unit))
+			.ok_or_else(|| format!("unknown key {key_name:?}"))?;
+		let mut chord = Self::new(key);
+		for modifier in parts {
+			match modifier {
+				"Ctrl" => chord.ctrl = true,
+				"Shift" => chord.shift = true,
+				"Alt" => chord.alt = true,
+				_ => return Err(format!("unknown modifier {modifier:?}")),
+			}
+		}
+		Ok(chord)
+	}
+}
+
+/// The key chords that trigger each [`Action`]. An action can have several chords, or none at all.
+///
+/// The map is stored by the config plugin next to the game settings. Systems should not query the map directly, but
+/// use [`ActionInput`].
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct InputMap {
+	bindings: BTreeMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for InputMap {
+	fn default() -> Self {
+		Self { bindings: Action::ALL.into_iter().map(|action| (action, action.default_chords())).collect() }
+	}
+}
+
+impl InputMap {
+	pub fn chords(&self, action: Action) -> &[KeyChord] {
+		self.bindings.get(&action).map_or(&[], Vec::as_slice)
+	}
+
+	/// Makes the chord the only one that triggers the action.
+	pub fn bind(&mut self, action: Action, chord: KeyChord) {
+		self.bindings.insert(action, vec![chord]);
+	}
+
+	/// Whether any other action is triggered by the chord.
+	pub fn conflicts(&self, action: Action, chord: KeyChord) -> impl Iterator<Item = Action> + '_ {
+		self.bindings
+			.iter()
+			.filter(move |(other, chords)| **other != action && chords.contains(&chord))
+			.map(|(other, _)| *other)
+	}
+
+	/// All chords that trigger more than one action, together with those actions.
+	pub fn duplicates(&self) -> Vec<(KeyChord, Vec<Action>)> {
+		let mut chords = Vec::<(KeyChord, Vec<Action>)>::new();
+		for (action, action_chords) in &self.bindings {
+			for chord in action_chords {
+				match chords.iter_mut().find(|(other, _)| other == chord) {
+					Some((_, actions)) => actions.push(*action),
+					None => chords.push((*chord, vec![*action])),
+				}
+			}
+		}
+		chords.retain(|(_, actions)| actions.len() > 1);
+		chords
+	}
+
+	/// Reads the map from the names used in the key bindings file. Actions that are missing from the file keep their
+	/// default chords, and entries that can't be read are skipped, so that files from other versions of the game still
+	/// load.
+	pub fn from_names(names: &BTreeMap<String, Vec<String>>) -> Self {
+		let mut map = Self::default();
+		for (name, chords) in names {
+			let Some(action) = Action::from_name(name) else {
+				warn!("Ignoring key binding for unknown action {name:?}");
+				continue;
+			};
+			let chords = chords
+				.iter()
+				.filter_map(|chord| {
+					chord.parse().inspect_err(|why| warn!("Ignoring key binding {chord:?} for {name}: {why}")).ok()
+				})
+				.collect();
+			map.bindings.insert(action, chords);
+		}
+		map
+	}
+
+	/// The map with the names used in the key bindings file.
+	pub fn to_names(&self) -> BTreeMap<String, Vec<String>> {
+		self.bindings
+			.iter()
+			.map(|(action, chords)| (action.name().to_owned(), chords.iter().map(KeyChord::to_string).collect()))
+			.collect()
+	}
+}
+
+/// Keyboard and gamepad input in terms of [`Action`]s, using the player's key bindings.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+	keys:     Res<'w, ButtonInput<KeyCode>>,
+	map:      Res<'w, InputMap>,
+	gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+	/// Whether a chord or the gamepad button of the action was pressed this frame.
+	pub fn just_pressed(&self, action: Action) -> bool {
+		self.map.chords(action).iter().any(|chord| chord.just_pressed(&self.keys))
+			|| action
+				.gamepad_button()
+				.is_some_and(|button| self.gamepads.iter().any(|gamepad| gamepad.just_pressed(button)))
+	}
+
+	/// Whether a chord or the gamepad button of the action was pressed this frame, even if further modifiers are held.
+	/// This suits actions that are combined with modifiers held for other purposes, such as confirming a selection
+	/// while holding Shift to extend it.
+	pub fn just_pressed_allowing_modifiers(&self, action: Action) -> bool {
+		self.map.chords(action).iter().any(|chord| self.keys.just_pressed(chord.key) && chord.pressed(&self.keys))
+			|| action
+				.gamepad_button()
+				.is_some_and(|button| self.gamepads.iter().any(|gamepad| gamepad.just_pressed(button)))
+	}
+
+	/// Whether the key of a chord or the gamepad button of the action was released this frame, regardless of the
+	/// modifiers.
+	pub fn just_released(&self, action: Action) -> bool {
+		self.map.chords(action).iter().any(|chord| self.keys.just_released(chord.key))
+			|| action
+				.gamepad_button()
+				.is_some_and(|button| self.gamepads.iter().any(|gamepad| gamepad.just_released(button)))
+	}
+
+	/// Whether any of the actions was pressed this frame.
+	pub fn any_just_pressed(&self, actions: impl IntoIterator<Item = Action>) -> bool {
+		actions.into_iter().any(|action| self.just_pressed(action))
+	}
+
+	/// Whether a chord of the action is held.
+	pub fn pressed(&self, action: Action) -> bool {
+		self.map.chords(action).iter().any(|chord| chord.pressed(&self.keys))
+	}
+}
+
+/// Both a world-space and camera-space position.
+#[derive(Default, Copy, Clone)]
+struct MatchedPosition {
+	screen_pos: Vec2,
+	camera_pos: Vec3,
+}
+#[derive(Resource, Default, Copy, Clone)]
+struct DragStartPosition(Option<MatchedPosition>);
+
+const DRAG_THRESHOLD: f32 = 0.2;
+/// Smallest and largest camera scale. Only powers of two in between are used.
+const ZOOM_RANGE: (f32, f32) = (1. / 16., 8.);
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MouseClick {
+	pub screen_position: Vec2,
+	pub engine_position: Vec2,
+}
+
+pub fn camera_to_world(
+	position: Vec2,
+	window: &Window,
+	camera: &Camera,
+	camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+	// Transform the window position into the kind of position that the pixel perfect camera would see
+	let canvas_position = CanvasViewport::of(window).window_to_canvas(position);
+	camera.viewport_to_world(camera_transform, canvas_position).map(|p| p.origin.truncate()).ok()
+}
+
+pub fn world_to_camera(
+	position: Vec3,
+	window: &Window,
+	camera: &Camera,
+	camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+	let canvas_position = camera.world_to_viewport(camera_transform, position).ok()?;
+	Some(CanvasViewport::of(window).canvas_to_window(canvas_position))
+}
+
+fn move_camera(
+	mouse: Res<ButtonInput<MouseButton>>,
+	window: Query<&Window, With<PrimaryWindow>>,
+	mut camera_q: Query<(&Camera, &mut Transform, &GlobalTransform), With<InGameCamera>>,
+	mut drag_start_position: ResMut<DragStartPosition>,
+	mut click_event: EventWriter<MouseClick>,
+	virtual_cursor: Res<keyboard::VirtualCursor>,
+) {
+	let Ok(window) = window.get_single() else {
+		return;
+	};
+	let (camera, mut camera_transform, camera_global_transform) = camera_q.single_mut();
+
+	if let Some(current_screen_position) = virtual_cursor.pointer_position(window) {
+		let Some(current_engine_position) =
+			camera_to_world(current_screen_position, window, camera, camera_global_transform)
+		else {
+			return;
+		};
+
+		'pos: {
+			// The camera follows the virtual cursor by itself, so dragging it would move the camera twice.
+			if let Some(drag_start_screen_position) =
+				drag_start_position.0.filter(|_| mouse.pressed(MouseButton::Left) && !virtual_cursor.is_active())
+			{
+				let Some(drag_start_engine_position) =
+					camera_to_world(drag_start_screen_position.screen_pos, window, camera, camera_global_transform)
+				else {
+					break 'pos;
+				};
+				// Snap camera to pixel grid. This masks all issues with slightly misaligned objects that wouldn’t move
+				// in sync when dragging the camera. The steppy movement is only really noticeable at large zoom
+				// levels, and not too jarring since it works correctly no matter the drag speed.
+				let delta = (drag_start_engine_position - current_engine_position).round();
+				camera_transform.translation =
+					(drag_start_screen_position.camera_pos + Vec3::from((delta, 0.))).round();
+			}
+		}
+
+		if mouse.just_pressed(MouseButton::Left) {
+			drag_start_position.0 =
+				Some(MatchedPosition { screen_pos: current_screen_position, camera_pos: camera_transform.translation });
+		}
+
+		'drag: {
+			if let Some(drag_start_screen_position) =
+				drag_start_position.0.filter(|_| mouse.just_released(MouseButton::Left))
+			{
+				let Some(drag_start_world_position) =
+					camera_to_world(drag_start_screen_position.screen_pos, window, camera, camera_global_transform)
+				else {
+					break 'drag;
+				};
+				let delta = drag_start_world_position - current_engine_position;
+
+				if delta.length() < DRAG_THRESHOLD {
+					click_event.send(MouseClick {
+						screen_position: current_screen_position,
+						engine_position: current_engine_position,
+					});
+				}
+			}
+		}
+
+		if mouse.just_released(MouseButton::Left) {
+			drag_start_position.0 = None;
+		}
+	}
+}
+
+fn fix_camera(mut drag_start_position: ResMut<DragStartPosition>) {
+	// Prevents large screen jumps due to a press registering "across" the input mode change.
+	drag_start_position.0 = None;
+}
+
+/// `accumulated_scroll` takes care of small-increment smooth scrolling devices like trackpads.
+fn zoom_camera(
+	mut scroll_events: EventReader<MouseWheel>,
+	mut camera_q: Query<&mut OrthographicProjection, With<InGameCamera>>,
+	mut accumulated_scroll: Local<f32>,
+	picking: Picking,
+) {
+	let mut camera_projection = camera_q.single_mut();
+
+	let amount = scroll_events.read().map(|scroll| scroll.y).sum::<f32>();
+	// Scrolling over the UI scrolls the UI instead, such as the build menus.
+	if amount == 0. || picking.is_ui_blocked() {
+		return;
+	}
+
+	// If changing scroll direction, snap accumulation to 0 so that it doesn’t take longer to zoom than if you didn’t
+	// change direction.
+	if accumulated_scroll.signum() != amount.signum() {
+		*accumulated_scroll = 0.;
+	}
+	// Accumulate scroll so that small scroll increments don’t get lost.
+	*accumulated_scroll += amount;
+	if accumulated_scroll.abs() < 1. {
+		// Below a total scroll of 1, nothing happens due to the zoom math below, so we can skip updating the camera
+		// transform altogether.
+		return;
+	}
+
+	// Only allow power-of-two scales, since those will not cause off-by-one rendering glitches.
+	camera_projection.scale =
+		2f32.powf(camera_projection.scale.log2().round() - *accumulated_scroll).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+	// HACK: Exact scale of 1 is very glitchy for some reason
+	// if camera_projection.scale == 1. {
+	// 	camera_projection.scale = 1.0001;
+	// }
+
+	// Since we just scrolled, reset the accumulator.
+	*accumulated_scroll = 0.;
+}
+
+fn fullscreen(actions: ActionInput, mut windows: Query<&mut bevy::prelude::Window, With<PrimaryWindow>>) {
+	let Ok(mut window) = windows.get_single_mut() else { return };
+
+	if actions.just_pressed(Action::Fullscreen) {
+		window.mode = match window.mode {
+			// FIXME: only use borderless fullscreen on Wayland?
+			WindowMode::Windowed => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+			_ => WindowMode::Windowed,
+		};
+	}
+}
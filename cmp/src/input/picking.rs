@@ -0,0 +1,116 @@
+//! Spatial picking shared by all pointer-driven systems.
+//!
+//! Translating a screen position into the world involves the pixel-perfect camera, the isometric projection and the
+//! UI, which may be covering the world. [`Picking`] does all of this in one call, so that selecting, building and
+//! painting always agree on what is under the pointer.
+
+use std::sync::Arc;
+
+use bevy::ecs::system::SystemParam;
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy::window::PrimaryWindow;
+use parking_lot::Mutex;
+
+use super::camera_to_world;
+use super::keyboard::VirtualCursor;
+use crate::graphics::{engine_to_world_space, InGameCamera, TILE_HEIGHT, TILE_WIDTH};
+use crate::model::GridPosition;
+use crate::ui::world_info::WorldInfoProperties;
+
+/// Maximum engine-space distance between the pointer and an entity for the entity to be picked.
+const PICK_TOLERANCE: f32 = 2. * TILE_WIDTH;
+
+/// Everything that is under a screen position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pick {
+	/// The picked screen position, in logical window pixels.
+	pub screen_position: Vec2,
+	/// The picked position in bevy engine space.
+	pub engine_position: Vec2,
+	/// The tile under the position, assuming ground level.
+	pub tile:            GridPosition,
+	/// The topmost inspectable entity close to the position, if any.
+	pub entity:          Option<Entity>,
+	/// Whether the position is covered by a UI element that blocks interaction with the world.
+	pub ui_blocked:      bool,
+}
+
+/// Picking service for translating screen positions into tiles and entities.
+#[derive(SystemParam)]
+pub struct Picking<'w, 's> {
+	window:      Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+	camera:      Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<InGameCamera>>,
+	blocking_ui: Query<'w, 's, (&'static FocusPolicy, &'static Interaction)>,
+	pickable:    Query<'w, 's, (Entity, &'static GlobalTransform), With<WorldInfoProperties>>,
+	cursor:      Res<'w, VirtualCursor>,
+}
+
+impl Picking<'_, '_> {
+	/// Picks whatever is under the mouse cursor, if the cursor is inside the window. While the virtual cursor of the
+	/// keyboard or a gamepad is active, it is used instead.
+	pub fn under_cursor(&self) -> Option<Pick> {
+		self.pick(self.cursor.pointer_position(self.window.get_single().ok()?)?)
+	}
+
+	/// Picks whatever is under the given screen position.
+	pub fn pick(&self, screen_position: Vec2) -> Option<Pick> {
+		let window = self.window.get_single().ok()?;
+		let (camera, camera_transform) = self.camera.get_single().ok()?;
+		let engine_position = camera_to_world(screen_position, window, camera, camera_transform)?;
+
+		Some(Pick {
+			screen_position,
+			engine_position,
+			tile: tile_under(engine_position),
+			entity: self.topmost_entity(engine_position),
+			ui_blocked: self.is_ui_blocked(),
+		})
+	}
+
+	/// Whether any UI element blocking the world is currently hovered or pressed. The virtual cursor is never blocked,
+	/// since the UI only reacts to the mouse.
+	pub fn is_ui_blocked(&self) -> bool {
+		!self.cursor.is_active()
+			&& self
+				.blocking_ui
+				.iter()
+				.any(|(policy, interaction)| *policy == FocusPolicy::Block && *interaction != Interaction::None)
+	}
+
+	/// Finds the pickable entity closest to the engine position, preferring entities drawn on top.
+	fn topmost_entity(&self, engine_position: Vec2) -> Option<Entity> {
+		// Entity translations are at the sprite anchor, which is half a tile below the sprite's visual center.
+		let cursor_position = Vec3A::from((engine_position, 0.)) - Vec3A::from((0., TILE_HEIGHT / 2., 0.));
+
+		let best: Arc<Mutex<Option<(Entity, f32, f32)>>> = Arc::default();
+		// PERFORMANCE: Run distance checks in parallel, only locking the current-best entity once we have something
+		// that's within the pick tolerance anyways.
+		self.pickable.par_iter().for_each(|(entity, transform)| {
+			let mut position = transform.translation_vec3a();
+			let depth = position.z;
+			position.z = 0.;
+			let distance = position.distance(cursor_position);
+			if distance >= PICK_TOLERANCE {
+				return;
+			}
+			let mut best = best.lock();
+			let is_better = best.is_none_or(|(_, best_distance, best_depth)| {
+				distance < best_distance || (distance == best_distance && depth > best_depth)
+			});
+			if is_better {
+				*best = Some((entity, distance, depth));
+			}
+		});
+		let best = *best.lock();
+		best.map(|(entity, ..)| entity)
+	}
+}
+
+/// The ground-level tile under an engine-space position.
+pub fn tile_under(engine_position: Vec2) -> GridPosition {
+	// Since we measure positions from corners, offset the position half a tile to find the tile it is on.
+	// FIXME: Use ray casting + structure data to figure out the elevation under the position.
+	(engine_to_world_space(engine_position, 0.) - Vec3::new(0.5, 0.5, 0.)).round()
+}
@@ -0,0 +1,176 @@
+//! First-person walk mode, where the camera follows a walker that the player controls with WASD.
+//!
+//! Walk mode is purely for immersion and inspecting the campground up close; the walker isn't saved and doesn't
+//! interact with the simulation. It can only walk on tiles that people can navigate.
+
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use super::{Action, ActionInput, InputState};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{engine_to_world_space, world_to_engine_space, InGameCamera, ObjectPriority};
+use crate::model::nav::{NavCategory, NavComponent, Pedestrian};
+use crate::model::{ActorPosition, GridPosition, GroundMap};
+use crate::ui::controls::DialogContainer;
+
+/// Camera zoom while walking; a power of two like all other zoom levels.
+const WALK_ZOOM: f32 = 1. / 4.;
+/// How many tiles away from the camera center to look for a navigable tile to start walking on.
+const SPAWN_SEARCH_RADIUS: i32 = 16;
+
+pub(super) struct WalkPlugin;
+
+impl Plugin for WalkPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Update, toggle_walk_mode.in_set(GameSet::Input).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				walk.run_if(in_state(InputState::Walking)).in_set(GameSet::Input).in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(InputState::Walking), start_walking)
+			.add_systems(OnExit(InputState::Walking), stop_walking);
+	}
+}
+
+/// The actor that the camera follows in walk mode.
+#[derive(Component, Clone, Copy, Debug)]
+struct Walker;
+
+/// State to restore once walk mode ends.
+#[derive(Resource, Clone, Debug)]
+struct WalkMode {
+	previous_camera: Vec3,
+	previous_zoom:   f32,
+	/// UI roots hidden during walk mode, with their previous visibility.
+	hidden_ui:       Vec<(Entity, Visibility)>,
+}
+
+fn toggle_walk_mode(
+	actions: ActionInput,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	match state.get() {
+		InputState::Idle if actions.just_pressed(Action::WalkMode) => next_state.set(InputState::Walking),
+		InputState::Walking if actions.any_just_pressed([Action::WalkMode, Action::Cancel]) =>
+			next_state.set(InputState::Idle),
+		_ => {},
+	}
+}
+
+/// Whether people can walk on the tile at the given position.
+fn is_walkable(position: GridPosition, ground_map: &GroundMap, vertices: &Query<&NavComponent>) -> bool {
+	ground_map
+		.get(&position)
+		.and_then(|(entity, _)| vertices.get(entity).ok())
+		.is_some_and(|vertex| vertex.navigability != NavCategory::None)
+}
+
+fn start_walking(
+	mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<InGameCamera>>,
+	ui_roots: Query<(Entity, &Visibility), (With<Node>, Without<Parent>, Without<DialogContainer>)>,
+	ground_map: Res<GroundMap>,
+	vertices: Query<&NavComponent>,
+	mut next_state: ResMut<NextState<InputState>>,
+	mut commands: Commands,
+) {
+	let (mut camera_transform, mut projection) = camera.single_mut();
+
+	let center = ActorPosition(*engine_to_world_space(camera_transform.translation.truncate(), 0.)).tile();
+	// Search in growing squares around the camera center, so the walker starts close to what the player was looking at.
+	let start = (0 ..= SPAWN_SEARCH_RADIUS).find_map(|radius| {
+		(-radius ..= radius)
+			.flat_map(|x| (-radius ..= radius).map(move |y| (x, y)))
+			.filter(|(x, y)| x.abs() == radius || y.abs() == radius)
+			.map(|(x, y)| GridPosition(center.0 + IVec3::new(x, y, 0)))
+			.find(|position| is_walkable(*position, &ground_map, &vertices))
+	});
+	let Some(start) = start else {
+		warn!("No walkable ground near the camera; can’t start walk mode.");
+		next_state.set(InputState::Idle);
+		return;
+	};
+
+	let hidden_ui = ui_roots.iter().map(|(entity, visibility)| (entity, *visibility)).collect::<Vec<_>>();
+	for (entity, _) in &hidden_ui {
+		commands.entity(*entity).insert(Visibility::Hidden);
+	}
+	commands.insert_resource(WalkMode {
+		previous_camera: camera_transform.translation,
+		previous_zoom: projection.scale,
+		hidden_ui,
+	});
+
+	let position = ActorPosition(start.0.as_vec3a() + Vec3A::new(0.5, 0.5, 0.));
+	commands.spawn((Walker, position, ObjectPriority::Normal, Sprite {
+		anchor: Anchor::BottomCenter,
+		..Sprite::from_color(Color::srgb(0.9, 0.3, 0.2), Vec2::new(3., 8.))
+	}));
+	camera_transform.translation = world_to_engine_space(position.0).extend(camera_transform.translation.z);
+	projection.scale = WALK_ZOOM;
+}
+
+fn stop_walking(
+	walkers: Query<Entity, With<Walker>>,
+	mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<InGameCamera>>,
+	walk_mode: Option<Res<WalkMode>>,
+	mut commands: Commands,
+) {
+	for walker in &walkers {
+		commands.entity(walker).despawn_recursive();
+	}
+	let Some(walk_mode) = walk_mode else {
+		return;
+	};
+	let (mut camera_transform, mut projection) = camera.single_mut();
+	camera_transform.translation = walk_mode.previous_camera;
+	projection.scale = walk_mode.previous_zoom;
+	for (entity, visibility) in &walk_mode.hidden_ui {
+		if let Some(mut entity) = commands.get_entity(*entity) {
+			entity.insert(*visibility);
+		}
+	}
+	commands.remove_resource::<WalkMode>();
+}
+
+fn walk(
+	actions: ActionInput,
+	time: Res<Time>,
+	ground_map: Res<GroundMap>,
+	vertices: Query<&NavComponent>,
+	mut walker: Query<&mut ActorPosition, With<Walker>>,
+	mut camera: Query<&mut Transform, With<InGameCamera>>,
+) {
+	let Ok(mut position) = walker.get_single_mut() else {
+		return;
+	};
+
+	// Screen up is along both positive world axes, screen right is along positive x and negative y.
+	let pressed = |action| if actions.pressed(action) { 1. } else { 0. };
+	let up = pressed(Action::WalkForward) - pressed(Action::WalkBackward);
+	let right = pressed(Action::WalkRight) - pressed(Action::WalkLeft);
+	let direction = Vec3A::new(up + right, up - right, 0.).normalize_or_zero();
+	if direction == Vec3A::ZERO {
+		return;
+	}
+
+	let speed = ground_map
+		.get(&position.tile())
+		.and_then(|(entity, _)| vertices.get(entity).ok())
+		.map_or(1, |vertex| vertex.speed) as f32
+		* Pedestrian::Player.speed_factor();
+	let step = direction * speed * time.delta_secs();
+
+	// Slide along unwalkable tiles by trying each axis on its own if the full step is blocked.
+	let target = [step, step * Vec3A::X, step * Vec3A::Y]
+		.into_iter()
+		.map(|step| position.0 + step)
+		.find(|target| is_walkable(ActorPosition(*target).tile(), &ground_map, &vertices));
+	if let Some(target) = target {
+		position.0 = target;
+	}
+
+	let mut camera_transform = camera.single_mut();
+	camera_transform.translation = world_to_engine_space(position.0).extend(camera_transform.translation.z);
+}
@@ -1,63 +1,95 @@
 //! CMP core engine.
-#![feature(
-	test,
-	duration_constants,
-	let_chains,
-	gen_blocks,
-	iter_from_coroutine,
-	coroutine_trait,
-	coroutines,
-	try_blocks,
-	iter_intersperse,
-	extract_if,
-	adt_const_params,
-	trivial_bounds,
-	trait_alias
-)]
+//!
+//! The engine is developed on nightly Rust and uses some unstable features there. Building without the default
+//! `nightly` feature replaces them with stable equivalents that behave identically, which is slightly less convenient
+//! to work with but allows distributing builds made with a stable toolchain.
+#![cfg_attr(feature = "nightly", feature(test, iter_from_coroutine, coroutines, adt_const_params))]
 
+#[cfg(feature = "nightly")]
 extern crate test;
 
 use std::sync::Arc;
-#[allow(unused)]
 use std::time::Duration;
 
+use audio::SoundPlugin;
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::asset::AssetMetaCheck;
 use bevy::ecs::schedule::ScheduleLabel;
 use bevy::log::{Level, LogPlugin};
 use bevy::prelude::*;
-use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
+use bevy::render::settings::{RenderCreation, WgpuSettings};
 use bevy::render::RenderPlugin;
-use bevy::window::{EnabledButtons, PresentMode, PrimaryWindow, WindowResolution};
-use bevy::winit::WinitWindows;
+use bevy::window::{EnabledButtons, ExitCondition, PresentMode, PrimaryWindow, WindowResolution};
+use bevy::winit::{WinitPlugin, WinitWindows};
 use config::{CommandLineArguments, ConfigPlugin, GameSettings};
-use gamemode::{pause_fixed_timer, GameState};
+use debug::console::ConsolePlugin;
+use debug::inspector::InspectorPlugin;
+use debug::save_diff::SaveDiffPlugin;
+use debug::soak::SoakPlugin;
+use gamemode::{
+	apply_game_speed, change_game_speed, simulation_active, GameSet, GameSpeed, GameState, SimulationActive,
+};
 use input::GUIInputPlugin;
 use model::area::AreaManagement;
+use model::balance::BalanceManagement;
+use model::booking::BookingManagement;
+use model::buildables::BuildableManagement;
+use model::calendar::CalendarManagement;
+use model::commerce::CommerceManagement;
+use model::construction::ConstructionManagement;
+use model::decal::DecalManagement;
+use model::decoration::DecorationManagement;
+use model::demand::DemandManagement;
+use model::errand::ErrandManagement;
+use model::facility::FacilityManagement;
+use model::fence::FenceManagement;
+use model::grass::GrassManagement;
+use model::modifiers::{Modifier, ModifierEffect, ModifierTarget, Modifiers};
 use model::nav::NavManagement;
+use model::obstacle::ObstacleManagement;
+use model::reachability::ReachabilityManagement;
+use model::reception::ReceptionManagement;
+use model::road::RoadManagement;
+use model::rollup::RollupManagement;
+use model::spatial::SpatialManagement;
+use model::special_event::SpecialEventManagement;
+use model::staff::StaffManagement;
+use model::time_of_day::TimeOfDayManagement;
+use model::traffic::TrafficManagement;
+use model::utilities::UtilityManagement;
+use model::vehicle::VehicleManagement;
+use model::visitor::VisitorManagement;
+use model::weather::WeatherManagement;
 use model::{
-	AccommodationManagement, ActorPosition, BoundingBox, Buildable, BuildableType, GridBox, GridPosition,
-	TileManagement,
+	AccommodationManagement, ActorPosition, BoundingBox, Buildable, BuildableType, CampgroundName, GridBox,
+	GridPosition, TileManagement,
 };
+use net::NetPlugin;
 use save::Saving;
 use ui::UIPlugin;
 use winit::window::Icon;
 
+pub(crate) mod audio;
 pub(crate) mod config;
 pub(crate) mod debug;
 pub(crate) mod gamemode;
 pub(crate) mod graphics;
 pub(crate) mod input;
 pub(crate) mod model;
+pub mod mods;
+pub(crate) mod net;
 pub(crate) mod save;
 pub(crate) mod ui;
 pub mod util;
+pub(crate) mod wire;
 
 pub use graphics::GraphicsPlugin;
 
 /// Hash set wrapper, because bevy doesn't have a serialization implementation for HashSet.
 pub type HashSet<T> = bevy::utils::HashMap<T, ()>;
 
-const VERSION: &str =
+/// Version of the game.
+pub const VERSION: &str =
 	env!("CARGO_PKG_VERSION", "CMP must be built under Cargo, or set the CARGO_PKG_VERSION variable manually.");
 
 /// Base plugin for the entire core engine.
@@ -81,42 +113,52 @@ impl Plugin for CmpPlugin {
 		let settings = Arc::new(GameSettings::from_arg_path(&args));
 		let log_level = if settings.show_debug { Level::TRACE } else { Level::INFO };
 
-		app.add_plugins(
-			DefaultPlugins
-				.build()
-				.set(AssetPlugin {
-					file_path:       "assets".into(),
-					processed_file_path: "../processed-assets".into(),
-					#[cfg(debug_assertions)]
-        			watch_for_changes_override: Some(true),
-					#[cfg(not(debug_assertions))]
-					watch_for_changes_override: Some(false),
-        			mode: AssetMode::Unprocessed,
-					meta_check: AssetMetaCheck::Always,
+		let default_plugins = DefaultPlugins
+			.build()
+			.set(AssetPlugin {
+				file_path:                  "assets".into(),
+				processed_file_path:        "../processed-assets".into(),
+				watch_for_changes_override: Some(cfg!(debug_assertions)),
+				mode:                       AssetMode::Unprocessed,
+				meta_check:                 AssetMetaCheck::Always,
+			})
+			.set(ImagePlugin::default_nearest())
+			.set(AnimationPlugin)
+			.set(LogPlugin {
+				level: log_level,
+				filter: "info,cmp=trace,wgpu=error,bevy=warn".into(),
+				..Default::default()
+			});
+		// Save inspections only print to the terminal, so they don't need a window either.
+		let is_windowless = args.headless || args.inspect_save.is_some();
+		let default_plugins = if is_windowless {
+			// Without winit, nothing drives the main loop, so the schedule runner has to. Without any graphics backend,
+			// the renderer is never initialized, but all rendering types (images, sprites, UI nodes) still exist, so
+			// that the engine doesn't need to distinguish between headless and windowed mode.
+			default_plugins
+				.disable::<WinitPlugin>()
+				.set(WindowPlugin {
+					primary_window:       None,
+					exit_condition:       ExitCondition::DontExit,
+					close_when_requested: false,
 				})
-				.set(ImagePlugin::default_nearest()).set(AnimationPlugin)
-				.set(LogPlugin {
-					level: log_level,
-					filter: "info,cmp=trace,wgpu=error,bevy=warn".into(),
-					..Default::default()
-				// }).set(RenderPlugin {
-				// 	render_creation: RenderCreation::Automatic(WgpuSettings {
-				// 		// backends: Some(Backends::VULKAN),
-				// 		..default()
-				// 	}),
-				// 	..default()
-				}).set(WindowPlugin {
-					primary_window: Some(Window {
-						resolution: WindowResolution::new(1920.0, 1080.0),
-						enabled_buttons: EnabledButtons {
-							maximize: false,
-							..Default::default()
-						},
-						..Default::default()
-					}),
+				.set(RenderPlugin {
+					render_creation: RenderCreation::Automatic(WgpuSettings { backends: None, ..default() }),
+					..default()
+				})
+				.add(ScheduleRunnerPlugin::run_loop(HEADLESS_FRAME_TIME))
+		} else {
+			default_plugins.set(WindowPlugin {
+				primary_window: Some(Window {
+					resolution: WindowResolution::new(1920.0, 1080.0),
+					enabled_buttons: EnabledButtons { maximize: false, ..Default::default() },
 					..Default::default()
 				}),
-		)
+				..Default::default()
+			})
+		};
+
+		app.add_plugins(default_plugins)
 		.register_type::<HashSet<GridPosition>>()
 		.register_type::<GridBox>()
 		.register_type::<BoundingBox>()
@@ -124,42 +166,85 @@ impl Plugin for CmpPlugin {
 		.register_type::<GridPosition>()
 		.register_type::<BuildableType>()
 		.register_type::<ActorPosition>()
+		.register_type::<Modifiers>()
+		.register_type::<Modifier>()
+		.register_type::<Vec<Modifier>>()
+		.register_type::<ModifierTarget>()
+		.register_type::<ModifierEffect>()
+		.init_resource::<Modifiers>()
+		.register_type::<CampgroundName>()
+		.init_resource::<CampgroundName>()
+		.init_resource::<GameSpeed>()
 		.register_asset_loader(bevy_qoi::QOIAssetLoader)
 		// Fixed update runs every two seconds and performs slow work that can take this long.
 		.insert_resource(Time::<Fixed>::from_seconds(0.5))
 		.init_state::<GameState>()
-		.add_plugins((GUIInputPlugin, UIPlugin, TileManagement, AccommodationManagement, AreaManagement, NavManagement, Saving, ConfigPlugin(args.clone(), settings.clone())))
-		.insert_resource(WindowIcon::default())
-		.add_systems(Startup, (debug::create_stats, setup_window))
+		.add_plugins((GUIInputPlugin, UIPlugin, (TileManagement, AccommodationManagement, AreaManagement, DecalManagement, FenceManagement, NavManagement, VisitorManagement, RollupManagement, UtilityManagement, VehicleManagement, StaffManagement, TimeOfDayManagement, BalanceManagement, BookingManagement, (WeatherManagement, (CalendarManagement, SpecialEventManagement, GrassManagement), DecorationManagement, SpatialManagement, BuildableManagement, ReachabilityManagement, FacilityManagement, ReceptionManagement, ErrandManagement, CommerceManagement, TrafficManagement, DemandManagement, ConstructionManagement, ObstacleManagement, RoadManagement)), SoundPlugin, Saving, InspectorPlugin, ConsolePlugin, SoakPlugin, SaveDiffPlugin, NetPlugin, ConfigPlugin(args.clone(), settings.clone())))
+		.add_systems(Startup, debug::create_stats)
 		.add_systems(PostStartup, print_program_info)
-		.add_systems(Update, (set_window_icon, debug::print_stats, apply_window_settings))
-		.add_systems(Update, pause_fixed_timer.run_if(state_changed::<GameState>))
-		.add_systems(PreStartup, go_to_game);
-
-		configure_set(app, PreUpdate);
-		configure_set(app, Update);
-		configure_set(app, FixedPostUpdate);
-		configure_set(app, FixedPreUpdate);
-		configure_set(app, FixedUpdate);
-		configure_set(app, First);
-		configure_set(app, Last);
-		configure_set(app, Startup);
-		configure_set(app, PreStartup);
-		configure_set(app, PostStartup);
+		.add_systems(Update, debug::print_stats)
+		.add_systems(Update, apply_game_speed.run_if(state_changed::<GameState>.or(resource_changed::<GameSpeed>)))
+		.add_systems(Update, change_game_speed.in_set(GameSet::Input).in_set(GameState::InGame));
+
+		// Mods change the game's content, so they have to come last.
+		#[cfg(feature = "mods")]
+		mods::load_mods(app);
+
+		configure_sets(app, PreUpdate);
+		configure_sets(app, Update);
+		configure_sets(app, FixedPostUpdate);
+		configure_sets(app, FixedPreUpdate);
+		configure_sets(app, FixedUpdate);
+		configure_sets(app, First);
+		configure_sets(app, Last);
+		configure_sets(app, Startup);
+		configure_sets(app, PreStartup);
+		configure_sets(app, PostStartup);
+		configure_sets(app, PostUpdate);
+		// Objects must be positioned before their transforms are propagated, or they will show up one frame late.
+		app.configure_sets(PostUpdate, GameSet::Graphics.before(TransformSystem::TransformPropagate));
+
+		if args.headless {
+			app.add_systems(Startup, start_headless_game);
+		}
+		if !is_windowless {
+			app.insert_resource(WindowIcon::default())
+				.add_systems(Startup, setup_window)
+				.add_systems(Update, (set_window_icon, apply_window_settings));
+		}
 	}
 }
 
-fn configure_set<S>(app: &mut App, set: S)
+/// Time between two frames when running headless.
+const HEADLESS_FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// There is no main menu to start a game from when running headless.
+fn start_headless_game(mut next_state: ResMut<NextState<GameState>>) {
+	info!("Running headless");
+	next_state.set(GameState::Loading);
+}
+
+/// Configures the system sets used throughout the engine on the given schedule:
+/// - [`GameState`] sets only run while the game is in the respective state.
+/// - [`GameSet`] sets run in their declaration order: input, early simulation, simulation, graphics, UI.
+/// - [`SimulationActive`] only runs while the simulation is running.
+fn configure_sets<S>(app: &mut App, schedule: S)
 where
-	S: ScheduleLabel,
+	S: ScheduleLabel + Clone,
 {
 	app.configure_sets(
-		set,
+		schedule.clone(),
 		(
 			GameState::InGame.run_if(in_state(GameState::InGame)),
 			GameState::MainMenu.run_if(in_state(GameState::MainMenu)),
+			GameState::Loading.run_if(in_state(GameState::Loading)),
 			GameState::Paused.run_if(in_state(GameState::Paused)),
+			SimulationActive.run_if(simulation_active),
 		),
+	)
+	.configure_sets(
+		schedule,
+		(GameSet::Input, GameSet::SimulationEarly, GameSet::Simulation, GameSet::Graphics, GameSet::UI).chain(),
 	);
 }
 
@@ -233,7 +318,3 @@ fn set_window_icon(
 		}
 	}
 }
-
-fn go_to_game(mut next: ResMut<NextState<GameState>>) {
-	next.set(GameState::InGame);
-}
@@ -1,32 +1,172 @@
 use std::collections::VecDeque;
+use std::ops::DerefMut;
 
 use bevy::color::palettes::css::RED;
 use bevy::prelude::*;
-use bevy::utils::Instant;
+use bevy::utils::{HashMap, Instant};
 use itertools::Itertools;
 use moonshine_save::save::Save;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::{BoundingBox, GridBox, GridPosition, GroundKind, GroundMap, Pitch};
 use crate::config::GameSettings;
-use crate::gamemode::GameState;
+use crate::debug::{DebugVisualization, DebugVisualizations};
+use crate::gamemode::{GameSet, GameState};
 use crate::graphics::{BorderSprite, BorderTextures, ObjectPriority, Sides};
-use crate::ui::world_info::WorldInfoProperties;
+use crate::ui::world_info::{WorldInfoOwner, WorldInfoProperties};
 use crate::HashSet;
 
+/// Side length of the chunks that an area's tiles are grouped by.
+const AREA_CHUNK_SIZE: i32 = 16;
+
+/// The tiles of an area, grouped by chunk so that changes to a small region only need to look at the tiles nearby. The
+/// tiles are saved as a flat set.
+#[derive(Reflect, Clone, Debug, Default)]
+#[reflect(opaque, Serialize, Deserialize, Default, Debug)]
+pub struct AreaTiles {
+	chunks: HashMap<IVec2, HashSet<GridPosition>>,
+	len:    usize,
+}
+
+impl AreaTiles {
+	fn chunk_of(position: &GridPosition) -> IVec2 {
+		position.truncate().div_euclid(IVec2::splat(AREA_CHUNK_SIZE))
+	}
+
+	/// Adds the tile and returns whether it was new.
+	pub fn insert(&mut self, position: GridPosition) -> bool {
+		let is_new = self.chunks.entry(Self::chunk_of(&position)).or_default().insert(position, ()).is_none();
+		self.len += is_new as usize;
+		is_new
+	}
+
+	/// Removes the tile and returns whether it was present.
+	pub fn remove(&mut self, position: &GridPosition) -> bool {
+		let chunk = Self::chunk_of(position);
+		let Some(tiles) = self.chunks.get_mut(&chunk) else {
+			return false;
+		};
+		let was_present = tiles.remove(position).is_some();
+		if tiles.is_empty() {
+			self.chunks.remove(&chunk);
+		}
+		self.len -= was_present as usize;
+		was_present
+	}
+
+	#[inline]
+	pub fn contains(&self, position: &GridPosition) -> bool {
+		self.chunks.get(&Self::chunk_of(position)).is_some_and(|tiles| tiles.contains_key(position))
+	}
+
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = GridPosition> + '_ {
+		self.chunks.values().flat_map(|tiles| tiles.keys().copied())
+	}
+
+	pub fn retain(&mut self, predicate: impl Fn(&GridPosition) -> bool) {
+		for tiles in self.chunks.values_mut() {
+			tiles.retain(|tile, _| predicate(tile));
+		}
+		self.chunks.retain(|_, tiles| !tiles.is_empty());
+		self.len = self.chunks.values().map(HashSet::len).sum();
+	}
+
+	/// The tiles inside the region on the xy plane. Only the chunks that the region overlaps are visited.
+	pub fn in_region(&self, region: GridBox) -> impl Iterator<Item = GridPosition> + '_ {
+		let (smallest, largest) = (region.smallest().truncate(), region.largest().truncate());
+		let (smallest_chunk, largest_chunk) = (Self::chunk_of(&region.smallest()), Self::chunk_of(&region.largest()));
+		let region_chunks = (largest_chunk - smallest_chunk + IVec2::ONE).element_product() as usize;
+		// Large regions are cheaper to check against the chunks that have tiles.
+		let chunks = if region_chunks <= self.chunks.len() {
+			(smallest_chunk.x ..= largest_chunk.x)
+				.cartesian_product(smallest_chunk.y ..= largest_chunk.y)
+				.filter_map(|(x, y)| self.chunks.get(&IVec2::new(x, y)))
+				.collect_vec()
+		} else {
+			self.chunks
+				.iter()
+				.filter(|(chunk, _)| chunk.cmpge(smallest_chunk).all() && chunk.cmple(largest_chunk).all())
+				.map(|(_, tiles)| tiles)
+				.collect_vec()
+		};
+		chunks.into_iter().flat_map(|tiles| tiles.keys().copied()).filter(move |tile| {
+			let tile = tile.truncate();
+			tile.cmpge(smallest).all() && tile.cmple(largest).all()
+		})
+	}
+
+	/// The smallest and largest tile coordinates on the xy plane. Only the outermost chunks can contain the extreme
+	/// tiles, so the other chunks are skipped.
+	fn bounds(&self) -> Option<(IVec2, IVec2)> {
+		let smallest_chunk = self.chunks.keys().copied().reduce(IVec2::min)?;
+		let largest_chunk = self.chunks.keys().copied().reduce(IVec2::max)?;
+		self.chunks
+			.iter()
+			.filter(|(chunk, _)| chunk.cmpeq(smallest_chunk).any() || chunk.cmpeq(largest_chunk).any())
+			.flat_map(|(_, tiles)| tiles.keys().map(|tile| tile.truncate()))
+			.fold(None, |bounds, tile| {
+				let (smallest, largest) = bounds.unwrap_or((tile, tile));
+				Some((smallest.min(tile), largest.max(tile)))
+			})
+	}
+}
+
+impl FromIterator<GridPosition> for AreaTiles {
+	fn from_iter<T: IntoIterator<Item = GridPosition>>(iter: T) -> Self {
+		let mut tiles = Self::default();
+		tiles.extend(iter);
+		tiles
+	}
+}
+
+impl Extend<GridPosition> for AreaTiles {
+	fn extend<T: IntoIterator<Item = GridPosition>>(&mut self, iter: T) {
+		for tile in iter {
+			self.insert(tile);
+		}
+	}
+}
+
+impl Serialize for AreaTiles {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_map(self.iter().map(|tile| (tile, ())))
+	}
+}
+
+impl<'de> Deserialize<'de> for AreaTiles {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let tiles = std::collections::HashMap::<GridPosition, ()>::deserialize(deserializer)?;
+		Ok(tiles.into_keys().collect())
+	}
+}
+
 /// A continuous area on the ground, containing various tiles (often of a homogenous type) and demarcating some
 /// important region. For example, pools and pitches are fundamentally areas.
 #[derive(Component, Reflect, Clone, Debug)]
 #[reflect(Component)]
 #[require(WorldInfoProperties, Transform, Visibility)]
 pub struct Area {
-	tiles: HashSet<GridPosition>,
+	tiles: AreaTiles,
 	// A bounding box for intersection acceleration.
 	aabb:  GridBox,
 }
 
 impl Default for Area {
 	fn default() -> Self {
-		Self { tiles: HashSet::new(), aabb: GridBox::new(GridPosition::default(), BoundingBox::fixed::<0, 0, 0>()) }
+		Self {
+			tiles: AreaTiles::default(),
+			aabb:  GridBox::new(GridPosition::default(), BoundingBox::fixed::<0, 0, 0>()),
+		}
 	}
 }
 
@@ -38,20 +178,18 @@ impl Area {
 		let tiles = (aabb.smallest().x ..= aabb.largest().x)
 			.cartesian_product(aabb.smallest().y ..= aabb.largest().y)
 			.map(GridPosition::from)
-			.map(|x| (x, ()))
 			.collect();
 		aabb.enlargen((1, 1, 1).into());
 		Self { tiles, aabb }
 	}
 
 	pub fn recompute_bounds(&mut self) {
-		let (smallest_x, largest_x) = self.tiles.keys().map(|tile| tile.x).minmax().into_option().unwrap_or((0, 0));
-		let (smallest_y, largest_y) = self.tiles.keys().map(|tile| tile.y).minmax().into_option().unwrap_or((0, 0));
-		self.aabb = GridBox::from_corners((smallest_x, smallest_y, 0).into(), (largest_x + 1, largest_y + 1, 1).into());
+		let (smallest, largest) = self.tiles.bounds().unwrap_or_default();
+		self.aabb = GridBox::from_corners(smallest.extend(0).into(), (largest + IVec2::ONE).extend(1).into());
 	}
 
 	pub fn retain_tiles(&mut self, predicate: impl Fn(&GridPosition) -> bool) {
-		self.tiles.retain(|x, _| predicate(x));
+		self.tiles.retain(predicate);
 		self.recompute_bounds();
 	}
 
@@ -68,14 +206,11 @@ impl Area {
 		// Flood fill to determine continuity.
 		let mut candidate_tiles = self.tiles.clone();
 		let mut nearby_tiles = VecDeque::new();
-		nearby_tiles.push_back(*candidate_tiles.keys().next().unwrap());
+		nearby_tiles.push_back(candidate_tiles.iter().next().unwrap());
 		while !nearby_tiles.is_empty() {
 			let current_tile = nearby_tiles.pop_front().unwrap();
-			for neighbor in current_tile
-				.neighbors()
-				.into_iter()
-				.filter(|neighbor| candidate_tiles.contains_key(neighbor))
-				.collect_vec()
+			for neighbor in
+				current_tile.neighbors().into_iter().filter(|neighbor| candidate_tiles.contains(neighbor)).collect_vec()
 			{
 				nearby_tiles.push_back(neighbor);
 				candidate_tiles.remove(&neighbor);
@@ -92,16 +227,22 @@ impl Area {
 
 	#[inline]
 	pub fn contains(&self, position: &GridPosition) -> bool {
-		self.tiles.contains_key(position)
+		self.tiles.contains(position)
 	}
 
 	pub fn fits(&self, aabb: &GridBox) -> bool {
 		aabb.floor_positions().all(|grid_position| self.contains(&grid_position))
 	}
 
+	/// Bounding box of all tiles.
+	#[inline]
+	pub const fn aabb(&self) -> GridBox {
+		self.aabb
+	}
+
 	#[inline]
 	pub fn tiles_iter(&self) -> impl Iterator<Item = GridPosition> + '_ {
-		self.tiles.keys().copied()
+		self.tiles.iter()
 	}
 
 	pub fn instantiate_borders(
@@ -112,15 +253,15 @@ impl Area {
 		texture_atlases: &mut Assets<TextureAtlasLayout>,
 		border_textures: &mut BorderTextures,
 	) {
-		for position in self.tiles.keys() {
-			if let Some((entity, kind)) = ground_map.get(position) {
+		for position in self.tiles.iter() {
+			if let Some((entity, kind)) = ground_map.get(&position) {
 				if let Some(border_kind) = kind.border_kind() {
 					let mut sides = Sides::all();
 					for neighbor in position.neighbors().into_iter().filter(|neighbor| {
-						self.tiles.contains_key(neighbor)
+						self.tiles.contains(neighbor)
 							&& ground_map.kind_of(neighbor).is_some_and(|neighbor_kind| neighbor_kind == kind)
 					}) {
-						sides ^= match *(neighbor - *position) {
+						sides ^= match *(neighbor - position) {
 							IVec3::X => Sides::Right,
 							IVec3::NEG_X => Sides::Left,
 							IVec3::Y => Sides::Top,
@@ -182,26 +323,209 @@ impl Plugin for AreaManagement {
 		app.init_resource::<Events<UpdateAreas>>()
 			.register_type::<Pool>()
 			.register_type::<DebugAreaText>()
+			.register_type::<AreaTiles>()
 			.register_type::<Area>()
 			.register_type::<ImmutableArea>()
 			.add_systems(
 				FixedUpdate,
 				(update_areas::<Pool>, update_areas::<Pitch>)
-					.before(clean_area_events)
-					.before(update_area_world_info)
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::SimulationEarly)
+					.in_set(GameState::InGame),
 			)
-			.add_systems(FixedUpdate, (clean_area_events, update_area_world_info).run_if(in_state(GameState::InGame)));
+			.add_systems(FixedUpdate, clean_area_events.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(FixedUpdate, update_area_world_info.in_set(GameSet::UI).in_set(GameState::InGame));
 	}
 }
 
-#[derive(Event, Default)]
-pub struct UpdateAreas;
+/// Requests that the areas around changed tiles are recomputed. Only areas with tiles in or next to the region are
+/// merged with the areas they touch and split into their connected parts.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct UpdateAreas {
+	/// The changed tiles; heights are ignored.
+	pub region: GridBox,
+}
+
+impl UpdateAreas {
+	/// Updates the areas around the rectangle between two corner tiles.
+	pub fn rect(first_corner: GridPosition, second_corner: GridPosition) -> Self {
+		Self {
+			region: GridBox::from_corners(
+				first_corner.component_wise_min(second_corner),
+				first_corner.component_wise_max(second_corner),
+			),
+		}
+	}
+
+	/// Updates the areas around all positions, or nothing if there are no positions.
+	pub fn around(positions: impl IntoIterator<Item = GridPosition>) -> Option<Self> {
+		let (smallest, largest) = positions.into_iter().fold(None, |bounds, position| {
+			let (smallest, largest): (GridPosition, GridPosition) = bounds.unwrap_or((position, position));
+			Some((smallest.component_wise_min(position), largest.component_wise_max(position)))
+		})?;
+		Some(Self::rect(smallest, largest))
+	}
+
+	/// Updates the areas around the area, for example when it became mutable again.
+	pub fn of(area: &Area) -> Self {
+		Self { region: area.aabb }
+	}
+}
 
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 pub struct DebugAreaText;
 
+/// Result of recomputing the areas around changed regions.
+#[derive(Default, Debug)]
+struct AreaChanges {
+	/// Areas whose tiles were changed in place.
+	changed: Vec<Entity>,
+	/// Areas that split off from changed areas.
+	new:     Vec<Area>,
+	/// Areas that were merged into others or lost all their tiles.
+	removed: Vec<Entity>,
+}
+
+/// Recomputes the areas with tiles in or next to the regions. Tiles in the regions that aren't allowed anymore are
+/// dropped, touching areas are merged into the largest of them, and areas that lost tiles are split into their
+/// connected parts. All other areas are never accessed mutably, so their change detection isn't triggered.
+fn recompute_areas<A: DerefMut<Target = Area>>(
+	areas: impl IntoIterator<Item = (Entity, A)>,
+	regions: &[GridBox],
+	is_allowed: impl Fn(&GridPosition) -> bool,
+) -> AreaChanges {
+	// Areas touching a changed tile have a tile next to it.
+	let regions = regions
+		.iter()
+		.map(|region| {
+			GridBox::from_corners(region.smallest() - IVec3::new(1, 1, 0), region.largest() + IVec3::new(1, 1, 0))
+		})
+		.collect_vec();
+
+	let mut affected = Vec::new();
+	for (entity, area) in areas {
+		let nearby_tiles = regions
+			.iter()
+			.filter(|region| area.aabb.intersects_2d(**region))
+			.flat_map(|region| area.tiles.in_region(*region))
+			.collect_vec();
+		if !nearby_tiles.is_empty() {
+			affected.push((entity, area, nearby_tiles));
+		}
+	}
+
+	// Drop tiles whose ground changed; these areas may have fallen apart.
+	let mut lost_tiles = vec![false; affected.len()];
+	for (index, (_, area, nearby_tiles)) in affected.iter_mut().enumerate() {
+		let (allowed, disallowed): (Vec<_>, Vec<_>) = nearby_tiles.iter().partition(|tile| is_allowed(tile));
+		if !disallowed.is_empty() {
+			for tile in &disallowed {
+				area.tiles.remove(tile);
+			}
+			lost_tiles[index] = true;
+			*nearby_tiles = allowed;
+		}
+	}
+
+	// Group areas that overlap or touch. New contacts always involve a changed tile, so only the nearby tiles need to
+	// be checked.
+	fn root(parents: &mut [usize], mut index: usize) -> usize {
+		while parents[index] != index {
+			parents[index] = parents[parents[index]];
+			index = parents[index];
+		}
+		index
+	}
+	let mut parents = (0 .. affected.len()).collect_vec();
+	let mut owners = HashMap::new();
+	for (index, (_, _, nearby_tiles)) in affected.iter().enumerate() {
+		for tile in nearby_tiles {
+			if let Some(owner) = owners.insert(*tile, index) {
+				let (owner_root, own_root) = (root(&mut parents, owner), root(&mut parents, index));
+				parents[own_root] = owner_root;
+			}
+		}
+	}
+	for (index, (_, _, nearby_tiles)) in affected.iter().enumerate() {
+		for neighbor in nearby_tiles.iter().flat_map(GridPosition::neighbors) {
+			if let Some(owner) = owners.get(&neighbor) {
+				let (owner_root, own_root) = (root(&mut parents, *owner), root(&mut parents, index));
+				parents[own_root] = owner_root;
+			}
+		}
+	}
+	let roots = (0 .. affected.len()).map(|index| root(&mut parents, index)).collect_vec();
+	let groups = (0 .. affected.len()).into_group_map_by(|index| roots[*index]);
+
+	let mut changes = AreaChanges::default();
+	let mut affected = affected.into_iter().map(|(entity, area, _)| Some((entity, area))).collect_vec();
+	for members in groups.into_values() {
+		let needs_split = members.iter().any(|member| lost_tiles[*member]);
+		if members.len() == 1 && !needs_split {
+			continue;
+		}
+
+		let largest = members
+			.iter()
+			.copied()
+			.max_by_key(|member| affected[*member].as_ref().map_or(0, |(_, area)| area.size()))
+			.unwrap();
+		let Some((entity, mut area)) = affected[largest].take() else {
+			continue;
+		};
+		for member in members.into_iter().filter(|member| *member != largest) {
+			if let Some((merged_entity, merged_area)) = affected[member].take() {
+				area.tiles.extend(merged_area.tiles.iter());
+				changes.removed.push(merged_entity);
+			}
+		}
+
+		if needs_split {
+			let mut parts = connected_parts(&area.tiles);
+			parts.sort_by_key(|part| std::cmp::Reverse(part.len()));
+			for part in parts.into_iter().skip(1) {
+				for tile in &part {
+					area.tiles.remove(tile);
+				}
+				let mut new_area = Area { tiles: part.into_iter().collect(), ..default() };
+				new_area.recompute_bounds();
+				changes.new.push(new_area);
+			}
+		}
+
+		if area.is_empty() {
+			changes.removed.push(entity);
+		} else {
+			area.recompute_bounds();
+			changes.changed.push(entity);
+		}
+	}
+	changes
+}
+
+/// Splits the tiles into groups of connected tiles.
+fn connected_parts(tiles: &AreaTiles) -> Vec<Vec<GridPosition>> {
+	let mut visited = HashSet::new();
+	let mut parts = Vec::new();
+	for start in tiles.iter() {
+		if visited.insert(start, ()).is_some() {
+			continue;
+		}
+		let mut part = vec![start];
+		let mut adjacent_tiles = VecDeque::from([start]);
+		while let Some(tile) = adjacent_tiles.pop_front() {
+			for neighbor in tile.neighbors() {
+				if tiles.contains(&neighbor) && visited.insert(neighbor, ()).is_none() {
+					part.push(neighbor);
+					adjacent_tiles.push_back(neighbor);
+				}
+			}
+		}
+		parts.push(part);
+	}
+	parts
+}
+
 fn update_areas<T: AreaMarker + Default>(
 	tiles: Res<GroundMap>,
 	mut areas: Query<(Entity, &mut Area, &T)>,
@@ -211,66 +535,37 @@ fn update_areas<T: AreaMarker + Default>(
 	// debugging
 	asset_server: Res<AssetServer>,
 	settings: Res<GameSettings>,
+	visualizations: Res<DebugVisualizations>,
 ) {
 	let start = Instant::now();
 	if update.is_empty() {
 		return;
 	}
 
-	old_area_markers.iter().for_each(|x| commands.entity(x).despawn());
-
-	// Perform flood fill on the areas to update them.
-	let mut remaining_tiles = HashSet::<GridPosition>::new();
-	for (_, area, marker) in &areas {
-		remaining_tiles.extend(
-			area.tiles
-				.iter()
-				.filter(|(tile, _)| tiles.kind_of(tile).is_some_and(|kind| marker.is_allowed_ground_type(kind))),
-		);
-	}
-
-	let mut new_areas = Vec::new();
-	let mut active_area = Area::default();
-	let mut adjacent_tiles = VecDeque::new();
-	if !remaining_tiles.is_empty() {
-		adjacent_tiles.push_front(*remaining_tiles.keys().next().unwrap());
-	}
-	while !remaining_tiles.is_empty() {
-		// No more adjacent tiles; start new area.
-		if adjacent_tiles.is_empty() {
-			active_area.recompute_bounds();
-			new_areas.push(active_area);
-			active_area = Area::default();
-			// Extract an arbitrary new tile to start the next area.
-			adjacent_tiles.push_front(*remaining_tiles.keys().next().unwrap());
-		}
-		let next_tile = adjacent_tiles.pop_back().unwrap();
-
-		let did_remove = remaining_tiles.remove(&next_tile).is_some();
-		if !did_remove {
-			debug!("BUG! {:?} wasn’t a remaining tile, but it was in the queue!", next_tile);
-		}
-
-		active_area.tiles.insert(next_tile, ());
-		for new_tile in next_tile.neighbors() {
-			// Not a queued tile already, but we need to handle it.
-			if !adjacent_tiles.contains(&new_tile) && remaining_tiles.contains_key(&new_tile) {
-				adjacent_tiles.push_front(new_tile);
-			}
-		}
-	}
-	active_area.recompute_bounds();
-	new_areas.push(active_area);
+	let regions = update.iter_current_update_events().map(|update| update.region).collect_vec();
+	let marker = T::default();
+	let changes = recompute_areas(areas.iter_mut().map(|(entity, area, _)| (entity, area)), &regions, |tile| {
+		tiles.kind_of(tile).is_some_and(|kind| marker.is_allowed_ground_type(kind))
+	});
 	let computation_time = Instant::now() - start;
 
-	debug!("after unification, {} areas remain (in {:?})", new_areas.len(), computation_time);
+	debug!(
+		"{} areas changed, {} split off and {} were removed near {} regions (in {:?})",
+		changes.changed.len(),
+		changes.new.len(),
+		changes.removed.len(),
+		regions.len(),
+		computation_time
+	);
 
 	// debugging
-	if settings.show_debug {
-		for (i, area) in new_areas.iter().enumerate() {
-			for tile in area.tiles.keys() {
+	old_area_markers.iter().for_each(|x| commands.entity(x).despawn());
+	if visualizations.shows(&settings, DebugVisualization::Areas) {
+		let areas = areas.iter().map(|(_, area, _)| area).chain(&changes.new);
+		for (i, area) in areas.enumerate() {
+			for tile in area.tiles.iter() {
 				commands.spawn((
-					*tile + IVec3::new(0, 0, 3),
+					tile + IVec3::new(0, 0, 3),
 					Text2d(format!("{}", i)),
 					TextFont {
 						font: asset_server.load(crate::graphics::library::font_for(
@@ -290,41 +585,128 @@ fn update_areas<T: AreaMarker + Default>(
 		}
 	}
 
-	for result in new_areas.into_iter().zip_longest(areas.iter_mut()) {
-		match result {
-			itertools::EitherOrBoth::Both(new, (old_entity, mut old_area, _)) => {
-				*old_area = new;
-				commands.entity(old_entity).despawn_descendants();
-			},
-			itertools::EitherOrBoth::Left(new) => {
-				T::init_new(new, &mut commands);
-			},
-			itertools::EitherOrBoth::Right((old_entity, ..)) => {
-				commands.entity(old_entity).despawn_recursive();
-			},
-		}
+	for entity in changes.changed {
+		commands.entity(entity).despawn_descendants();
+	}
+	for entity in changes.removed {
+		commands.entity(entity).despawn_recursive();
+	}
+	for area in changes.new {
+		T::init_new(area, &mut commands);
 	}
 }
 
-fn clean_area_events(mut update: ResMut<Events<UpdateAreas>>) {
+pub(super) fn clean_area_events(mut update: ResMut<Events<UpdateAreas>>) {
 	update.clear();
 }
 
+/// Points the tiles of changed areas to their area, so that the world info shows the area's properties for them.
+/// Only the owner is stored; its properties are looked up when the world info is displayed.
 fn update_area_world_info(
-	finalized_pitches: Query<(&WorldInfoProperties, &ImmutableArea), (Without<Area>, Changed<WorldInfoProperties>)>,
-	unfinalized_pitches: Query<(&WorldInfoProperties, &Area), (Without<ImmutableArea>, Changed<WorldInfoProperties>)>,
+	changed_areas: Query<(Entity, AnyOf<(&Area, &ImmutableArea)>), Or<(Changed<Area>, Changed<ImmutableArea>)>>,
 	ground_map: Res<GroundMap>,
-	mut tiles: Query<&mut WorldInfoProperties, (With<GroundKind>, Without<ImmutableArea>, Without<Area>)>,
+	mut tiles: Query<
+		(Entity, &GridPosition, Option<&mut WorldInfoOwner>),
+		(With<GroundKind>, Without<Area>, Without<ImmutableArea>),
+	>,
+	mut commands: Commands,
 ) {
-	for (properties, area) in
-		unfinalized_pitches.iter().chain(finalized_pitches.iter().map(|(properties, area)| (properties, &area.0)))
-	{
+	let changed_areas = changed_areas
+		.iter()
+		.map(|(entity, (area, immutable_area))| (entity, area.or(immutable_area.map(|area| &area.0))))
+		.filter_map(|(entity, area)| Some((entity, area?)))
+		.collect::<HashMap<_, _>>();
+	if changed_areas.is_empty() {
+		return;
+	}
+
+	// Release tiles that a changed area lost.
+	for (tile_entity, position, owner) in &tiles {
+		if owner.is_some_and(|owner| changed_areas.get(&owner.0).is_some_and(|area| !area.contains(position))) {
+			commands.entity(tile_entity).remove::<WorldInfoOwner>();
+		}
+	}
+
+	for (area_entity, area) in changed_areas {
 		for tile in area.tiles_iter() {
-			if let Some((tile_entity, _)) = ground_map.get(&tile) {
-				if let Ok(mut tile_properties) = tiles.get_mut(tile_entity) {
-					*tile_properties = properties.clone();
-				}
+			let Some((tile_entity, _)) = ground_map.get(&tile) else {
+				continue;
+			};
+			match tiles.get_mut(tile_entity) {
+				Ok((_, _, Some(mut owner))) => {
+					owner.set_if_neq(WorldInfoOwner(area_entity));
+				},
+				Ok((_, _, None)) => {
+					commands.entity(tile_entity).insert(WorldInfoOwner(area_entity));
+				},
+				Err(_) => {},
 			}
 		}
 	}
 }
+
+#[cfg(feature = "nightly")]
+mod test {
+	/// A square area of 16384 tiles.
+	fn large_area() -> super::Area {
+		super::Area::from_rect((0, 0).into(), (127, 127).into())
+	}
+
+	#[bench]
+	fn bench_update_far_from_large_area(bench: &mut test::Bencher) {
+		let mut area = large_area();
+		let region = super::UpdateAreas::rect((1000, 1000).into(), (1010, 1010).into()).region;
+		bench.iter(|| {
+			test::black_box(super::recompute_areas(
+				[(bevy::ecs::entity::Entity::from_raw(0), &mut area)],
+				&[region],
+				|_| true,
+			))
+		});
+	}
+
+	#[bench]
+	fn bench_update_edge_of_large_area(bench: &mut test::Bencher) {
+		let mut area = large_area();
+		let region = super::UpdateAreas::rect((120, 60).into(), (130, 70).into()).region;
+		bench.iter(|| {
+			test::black_box(super::recompute_areas(
+				[(bevy::ecs::entity::Entity::from_raw(0), &mut area)],
+				&[region],
+				|_| true,
+			))
+		});
+	}
+
+	#[bench]
+	fn bench_merge_into_large_area(bench: &mut test::Bencher) {
+		let area = large_area();
+		let strip = super::Area::from_rect((128, 0).into(), (128, 127).into());
+		let region = super::UpdateAreas::rect((128, 0).into(), (128, 127).into()).region;
+		bench.iter(|| {
+			let (mut area, mut strip) = (area.clone(), strip.clone());
+			test::black_box(super::recompute_areas(
+				[
+					(bevy::ecs::entity::Entity::from_raw(0), &mut area),
+					(bevy::ecs::entity::Entity::from_raw(1), &mut strip),
+				],
+				&[region],
+				|_| true,
+			))
+		});
+	}
+
+	#[bench]
+	fn bench_split_large_area(bench: &mut test::Bencher) {
+		let area = large_area();
+		let region = super::UpdateAreas::rect((64, 0).into(), (64, 127).into()).region;
+		bench.iter(|| {
+			let mut area = area.clone();
+			test::black_box(super::recompute_areas(
+				[(bevy::ecs::entity::Entity::from_raw(0), &mut area)],
+				&[region],
+				|tile| tile.x != 64,
+			))
+		});
+	}
+}
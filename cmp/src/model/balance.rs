@@ -0,0 +1,144 @@
+//! Economic balance of the campground: what things cost and what visitors pay.
+//!
+//! All prices live in the [`Balance`] resource, which the simulation charges and pays from, so that planning tools
+//! like the calculator panel show the same numbers that the game actually uses. Pitch types cost money when they are
+//! assigned to a pitch, and visitors pay for every night of their stay when they leave. What they pay is up to the
+//! player, see [`super::demand::PitchPricing`]; the nightly prices here are the usual prices that demand is measured
+//! against. Shops charge visitors for every visit and cost a daily fee to run, and clearing natural obstacles costs a
+//! fee per obstacle. Demolishing refunds part of what the demolished builds cost, see [`Balance::demolition_refund`].
+
+use bevy::prelude::*;
+
+use super::facility::Facility;
+use super::obstacle::{Obstacle, ALL_OBSTACLES};
+use super::pitch::{AccommodationMultiplicity, Pitch, PitchType, ALL_PITCH_TYPES};
+
+pub struct BalanceManagement;
+
+impl Plugin for BalanceManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Balance>();
+	}
+}
+
+/// Prices of one kind of pitch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PitchPrices {
+	/// Paid once when the pitch type is assigned to a pitch, per unit of multiplicity.
+	pub build_cost:    u64,
+	/// What visitors usually pay for every night that they stay, per unit of multiplicity. At this price, demand is
+	/// neither raised nor lowered by the price.
+	pub nightly_price: u64,
+}
+
+/// Prices of a shop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShopPrices {
+	/// Paid by every visitor that buys something at the shop.
+	pub visit_price:          u64,
+	/// Paid at the end of every day to run the shop.
+	pub daily_operating_cost: u64,
+}
+
+/// Prices and economic assumptions of the simulation.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Balance {
+	/// Prices of each pitch type, in the order of [`ALL_PITCH_TYPES`].
+	pitches:                [PitchPrices; ALL_PITCH_TYPES.len()],
+	kiosk:                  ShopPrices,
+	restaurant:             ShopPrices,
+	/// Cost of clearing each obstacle, in the order of [`ALL_OBSTACLES`].
+	clearing_costs:         [u64; ALL_OBSTACLES.len()],
+	/// Fraction of nights that a pitch is expected to be occupied, for planning.
+	pub expected_occupancy: f32,
+	/// Fraction of their build cost that demolished builds refund. Ground refunds its whole material cost instead,
+	/// since it can be dug up and reused.
+	pub demolition_refund:  f32,
+}
+
+impl Default for Balance {
+	fn default() -> Self {
+		Self {
+			pitches:            ALL_PITCH_TYPES.map(|kind| match kind {
+				PitchType::TentPitch => PitchPrices { build_cost: 150, nightly_price: 15 },
+				PitchType::CaravanPitch => PitchPrices { build_cost: 400, nightly_price: 35 },
+				PitchType::PermanentTent => PitchPrices { build_cost: 1200, nightly_price: 45 },
+				PitchType::MobileHome => PitchPrices { build_cost: 3500, nightly_price: 70 },
+				PitchType::Cottage => PitchPrices { build_cost: 8000, nightly_price: 140 },
+			}),
+			kiosk:              ShopPrices { visit_price: 6, daily_operating_cost: 30 },
+			restaurant:         ShopPrices { visit_price: 25, daily_operating_cost: 150 },
+			clearing_costs:     ALL_OBSTACLES.map(|obstacle| match obstacle {
+				Obstacle::Rock => 80,
+				Obstacle::MatureTree => 120,
+			}),
+			expected_occupancy: 0.6,
+			demolition_refund:  0.3,
+		}
+	}
+}
+
+impl Balance {
+	/// Prices of the given pitch type.
+	pub fn pitch_prices(&self, kind: PitchType) -> PitchPrices {
+		self.pitches[ALL_PITCH_TYPES.iter().position(|other| *other == kind).unwrap_or_default()]
+	}
+
+	/// Changes what assigning the pitch type costs per unit of multiplicity.
+	pub fn set_pitch_build_cost(&mut self, kind: PitchType, build_cost: u64) {
+		if let Some(index) = ALL_PITCH_TYPES.iter().position(|other| *other == kind) {
+			self.pitches[index].build_cost = build_cost;
+		}
+	}
+
+	/// Prices of the facility if it is a shop, or [`None`] for facilities that visitors don't pay for.
+	pub const fn shop_prices(&self, facility: Facility) -> Option<ShopPrices> {
+		match facility {
+			Facility::Kiosk => Some(self.kiosk),
+			Facility::Restaurant => Some(self.restaurant),
+			Facility::Reception | Facility::Toilets | Facility::Showers => None,
+		}
+	}
+
+	/// What clearing the obstacle costs.
+	pub fn clearing_cost(&self, obstacle: Obstacle) -> u64 {
+		self.clearing_costs[ALL_OBSTACLES.iter().position(|other| *other == obstacle).unwrap_or_default()]
+	}
+
+	/// What assigning the pitch's type costs.
+	pub fn build_cost(&self, pitch: &Pitch) -> u64 {
+		pitch.kind.map_or(0, |kind| self.pitch_prices(kind).build_cost * *pitch.multiplicity)
+	}
+
+	/// What visitors pay for one night on the pitch, at the pitch's own price.
+	pub fn nightly_price(&self, pitch: &Pitch) -> u64 {
+		pitch.kind.map_or(0, |_| pitch.nightly_price * *pitch.multiplicity)
+	}
+
+	/// Planning figures for a pitch that is yet to be built, which charges the given nightly price per unit of
+	/// multiplicity.
+	pub fn plan(&self, kind: PitchType, multiplicity: AccommodationMultiplicity, nightly_price: u64) -> PitchPlan {
+		let pitch = Pitch { kind: Some(kind), multiplicity, nightly_price, ..Default::default() };
+		let build_cost = self.build_cost(&pitch);
+		let expected_nightly_income = self.nightly_price(&pitch) as f32 * self.expected_occupancy;
+		PitchPlan {
+			required_area: pitch.required_area(),
+			build_cost,
+			expected_nightly_income,
+			payback_nights: (expected_nightly_income > 0.)
+				.then(|| (build_cost as f32 / expected_nightly_income).ceil() as u32),
+		}
+	}
+}
+
+/// Planned figures of a pitch, see [`Balance::plan`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PitchPlan {
+	/// Tiles that the pitch needs at least.
+	pub required_area:           usize,
+	pub build_cost:              u64,
+	/// Income per night at the expected occupancy.
+	pub expected_nightly_income: f32,
+	/// Nights until the expected income has paid for the build cost, or [`None`] if it never does.
+	pub payback_nights:          Option<u32>,
+}
@@ -0,0 +1,236 @@
+//! Bookings and the occupancy of pitches.
+//!
+//! Visitors book caravan pitches up to [`BOOKING_DAYS`] days in advance, as often as the [`super::demand`] for the
+//! pitch allows. Every booking is for one night, and every pitch can be booked once per day. On the day of a booking,
+//! its visitors arrive in their vehicle as soon as the pitch is free (see [`super::vehicle`]); bookings whose visitors
+//! couldn't arrive by the end of their day lapse. The [`Bookings`] of a pitch are shown as a booking calendar in its
+//! world info.
+//!
+//! Every booking is made by visitors of a [`VisitorSegment`], who only book pitches within their budget. Visitors whose
+//! pitch is already booked on their day take another free caravan pitch instead. The campground never
+//! takes more bookings for a day than its [`SiteCapacity`] has pitches, and once no pitch is left for the day, the
+//! visitors are turned away: they are counted in the [`DailyTally`], rate the campground poorly, and the player is
+//! warned on the first time each day that demand exceeds the capacity.
+//!
+//! The [`Occupancy`] of a pitch follows its visitors: the pitch is reserved while they are on their way, occupied while
+//! they stay, and free again once they left. Visitors that left the pitch dirty leave it in need of cleaning instead,
+//! and no new visitors arrive until a cleaner has cleaned it.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::balance::Balance;
+use super::calendar::Calendar;
+use super::demand::DemandModel;
+use super::pitch::{Occupancy, Pitch, PitchType, SiteCapacity};
+use super::rollup::{DailyTally, DayClock};
+use super::staff::PitchUpkeep;
+use super::utilities::Connected;
+use super::vehicle::Vehicle;
+use super::visitor::VisitorSegment;
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::notifications::NotificationEvent;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct BookingManagement;
+
+impl Plugin for BookingManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Booking>()
+			.register_type::<Vec<Booking>>()
+			.register_type::<Bookings>()
+			.add_systems(
+				FixedUpdate,
+				(add_bookings, advance_bookings, book_pitches, update_occupancy)
+					.chain()
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_world_info_section::<Pitch>("Occupancy", |pitch| {
+				if pitch.kind.is_none() {
+					return Vec::new();
+				}
+				vec![DialogWidget::Property { name: "Status".to_string(), value: pitch.occupancy.to_string() }]
+			})
+			.add_world_info_section::<Bookings>("Booking calendar", |bookings| {
+				(bookings.today .. bookings.today + BOOKING_DAYS)
+					.map(|day| DialogWidget::Property {
+						name:  Calendar::for_day(day).to_string(),
+						value: match bookings.get(day) {
+							None => "Free".to_string(),
+							Some(Booking { arrived: false, segment, .. }) =>
+								format!("Booked by {}", segment.to_string().to_lowercase()),
+							Some(Booking { arrived: true, segment, .. }) => format!("{segment} arrived"),
+						},
+					})
+					.collect()
+			});
+	}
+}
+
+/// How many days in advance visitors book pitches, including the current day.
+pub const BOOKING_DAYS: u32 = 7;
+/// Chance per fixed update that visitors book a caravan pitch at average demand.
+const BOOKING_CHANCE: f32 = 0.005;
+/// How visitors that were turned away rate the campground, between 0 and 1.
+const TURNED_AWAY_SATISFACTION: f32 = 0.2;
+
+/// A night that visitors booked on a pitch.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Booking {
+	/// The day that the visitors arrive on, as counted by the [`DayClock`].
+	pub day:     u32,
+	/// Whether the visitors arrived already.
+	pub arrived: bool,
+	/// The segment of the visitors. Missing from older saves.
+	#[reflect(default)]
+	pub segment: VisitorSegment,
+}
+
+/// The upcoming bookings of a pitch with a pitch type.
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Bookings {
+	/// The current day, as counted by the [`DayClock`]. Bookings before it have lapsed.
+	today:    u32,
+	/// Bookings ordered by their day, with at most one booking per day.
+	bookings: Vec<Booking>,
+}
+
+impl Bookings {
+	/// The booking for the day, if any.
+	pub fn get(&self, day: u32) -> Option<&Booking> {
+		self.bookings.binary_search_by_key(&day, |booking| booking.day).ok().map(|index| &self.bookings[index])
+	}
+
+	/// The booking of the current day, if its visitors haven't arrived yet.
+	pub fn expected_arrival(&self) -> Option<&Booking> {
+		self.get(self.today).filter(|booking| !booking.arrived)
+	}
+
+	/// Records that the visitors of the current day's booking arrived.
+	pub fn record_arrival(&mut self) {
+		if let Ok(index) = self.bookings.binary_search_by_key(&self.today, |booking| booking.day) {
+			self.bookings[index].arrived = true;
+		}
+	}
+
+	/// Books the day for visitors of the segment if it is still free, and returns whether it was.
+	fn book(&mut self, day: u32, segment: VisitorSegment) -> bool {
+		match self.bookings.binary_search_by_key(&day, |booking| booking.day) {
+			Ok(_) => false,
+			Err(index) => {
+				self.bookings.insert(index, Booking { day, arrived: false, segment });
+				true
+			},
+		}
+	}
+}
+
+fn add_bookings(clock: Res<DayClock>, pitches: Query<(Entity, &Pitch), Without<Bookings>>, mut commands: Commands) {
+	for (entity, pitch) in &pitches {
+		if pitch.kind.is_some() {
+			commands.entity(entity).insert(Bookings { today: clock.day(), bookings: Vec::new() });
+		}
+	}
+}
+
+/// Moves all booking calendars to the current day, which lets the bookings of past days lapse.
+fn advance_bookings(clock: Res<DayClock>, mut pitches: Query<&mut Bookings>) {
+	let today = clock.day();
+	for mut bookings in &mut pitches {
+		if bookings.today != today {
+			bookings.today = today;
+			bookings.bookings.retain(|booking| booking.day >= today);
+		}
+	}
+}
+
+/// Whether visitors can book the pitch. Only caravan pitches with working utilities are booked, since only their
+/// visitors arrive in vehicles.
+fn is_bookable(pitch: &Pitch, connected: Option<&Connected>) -> bool {
+	pitch.kind == Some(PitchType::CaravanPitch) && connected.is_some_and(Connected::is_functional)
+}
+
+fn book_pitches(
+	clock: Res<DayClock>,
+	calendar: Res<Calendar>,
+	demand: DemandModel,
+	balance: Res<Balance>,
+	capacity: Res<SiteCapacity>,
+	mut tally: ResMut<DailyTally>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut pitches: Query<(Entity, &Pitch, &mut Bookings, Option<&Connected>)>,
+) {
+	// Every bookable pitch draws visitors according to its own demand, and they ask for it first if they can afford it.
+	let requests = pitches
+		.iter()
+		.filter(|(_, pitch, _, connected)| {
+			is_bookable(pitch, *connected)
+				&& fastrand::f32()
+					< BOOKING_CHANCE * demand.demand(PitchType::CaravanPitch, pitch.nightly_price).total()
+		})
+		.filter_map(|(entity, pitch, ..)| {
+			let segment = VisitorSegment::draw(PitchType::CaravanPitch, calendar.season())?;
+			segment
+				.accepts_price(balance.nightly_price(pitch))
+				.then(|| (entity, clock.day() + fastrand::u32(0 .. BOOKING_DAYS), segment))
+		})
+		.collect::<Vec<_>>();
+
+	for (requested, day, segment) in requests {
+		let booked = pitches.iter().filter(|(_, _, bookings, _)| bookings.get(day).is_some()).count() as u32;
+		let free_pitch = (booked < capacity.pitches)
+			.then(|| {
+				let is_free =
+					|(entity, pitch, bookings, connected): (Entity, &Pitch, &Bookings, Option<&Connected>)| {
+						(is_bookable(pitch, connected)
+							&& bookings.get(day).is_none()
+							&& segment.accepts_price(balance.nightly_price(pitch)))
+						.then_some(entity)
+					};
+				pitches.get(requested).ok().and_then(is_free).or_else(|| pitches.iter().find_map(is_free))
+			})
+			.flatten();
+
+		match free_pitch.and_then(|entity| pitches.get_mut(entity).ok()) {
+			Some((entity, _, mut bookings, _)) => {
+				bookings.book(day, segment);
+				debug!("Pitch {entity} was booked by {segment} for {}", Calendar::for_day(day));
+			},
+			None => {
+				tally.record_turned_away();
+				tally.record_satisfaction(TURNED_AWAY_SATISFACTION);
+				if tally.turned_away() == 1 {
+					notifications.send(NotificationEvent::warning(format!(
+						"Visitors are being turned away, since no pitch is free for {}. Build more pitches to take \
+						 them in.",
+						Calendar::for_day(day)
+					)));
+				}
+			},
+		}
+	}
+}
+
+/// Moves every pitch on to the occupancy that its visitors' vehicle is in. Vehicles aren't saved, so pitches whose
+/// visitors were on their way or staying become free or need cleaning after loading.
+fn update_occupancy(vehicles: Query<&Vehicle>, mut pitches: Query<(Entity, &mut Pitch, Option<&PitchUpkeep>)>) {
+	let visitors = vehicles.iter().map(|vehicle| (vehicle.pitch, vehicle)).collect::<HashMap<_, _>>();
+	for (entity, mut pitch, upkeep) in &mut pitches {
+		let is_dirty = upkeep.is_some_and(PitchUpkeep::needs_cleaning);
+		let occupancy = match visitors.get(&entity) {
+			Some(vehicle) if vehicle.is_parked() => Occupancy::Occupied,
+			Some(vehicle) if vehicle.is_arriving() => Occupancy::Reserved,
+			// The visitors left the pitch, or turned around before they got there.
+			_ => match pitch.occupancy {
+				Occupancy::Occupied | Occupancy::NeedsCleaning if is_dirty => Occupancy::NeedsCleaning,
+				_ => Occupancy::Free,
+			},
+		};
+		if pitch.occupancy != occupancy {
+			pitch.occupancy = occupancy;
+		}
+	}
+}
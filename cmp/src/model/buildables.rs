@@ -0,0 +1,341 @@
+//! Data-driven definitions of what the player can build.
+//!
+//! How a buildable behaves is defined by its [`Buildable`] kind in code, but everything about how it is offered to the
+//! player comes from the buildable manifest, `base.buildables.ron` in the assets: the name and description, the build
+//! menu it appears in, what it costs, how long it takes to construct, its footprint, its logo and preview sprite, and
+//! the preview sprite's anchor. The manifest also decides which buildables are offered at all and in which order, so
+//! that designers and mods can rename, reprice, regroup, reorder and reskin buildables, or offer combinations of
+//! existing kinds that the game doesn't offer by default, without recompiling.
+//!
+//! Code mods register further definitions through [`crate::mods::CmpModApi`], which are offered after the manifest's
+//! and replace its definitions of the same buildables.
+//!
+//! Until the manifest is loaded, and if it fails to load, the built-in definitions of [`ALL_BUILDABLES`] are used. The
+//! manifest is reloaded whenever the asset changes.
+//!
+//! Only buildables that are built one at a time, such as facilities, have a footprint; everything else covers the tiles
+//! that the player drags out. The footprint of a pitch type's building has to fit into the pitch area that the pitch
+//! type requires, since pitches are only checked for that area when the type is assigned and when their capacity grows.
+//! Footprints that break these rules are replaced by the usual footprint, with a warning. Buildings that were already
+//! built keep the tiles they were built on.
+
+use std::time::Duration;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::balance::Balance;
+use super::decoration::Decoration;
+use super::facility::Facility;
+use super::rollup::in_game_hours;
+use super::{BoundingBox, Buildable, GridBox, GridPosition, ALL_BUILDABLES};
+use crate::graphics::library::{anchor_for_image, logo_for_buildable, preview_image_for_buildable};
+use crate::ui::build::BuildMode;
+use crate::ui::controls::BuildMenu;
+use crate::util::{Tooltip, Tooltipable};
+
+/// Asset path of the buildable manifest.
+const MANIFEST_PATH: &str = "base.buildables.ron";
+
+/// Loads the buildable manifest into the [`BuildableRegistry`].
+pub struct BuildableManagement;
+
+impl Plugin for BuildableManagement {
+	fn build(&self, app: &mut App) {
+		app.init_asset::<BuildableManifest>()
+			.register_asset_loader(BuildableManifestLoader)
+			.init_resource::<BuildableRegistry>()
+			.add_systems(Startup, load_buildable_manifest)
+			.add_systems(Update, apply_buildable_manifest);
+	}
+}
+
+/// How one buildable is offered to the player.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BuildableDefinition {
+	/// The kind of buildable, which defines how it behaves.
+	pub buildable:          Buildable,
+	/// Name shown in the build menu.
+	pub name:               String,
+	/// Description shown in the build menu.
+	pub description:        String,
+	/// The build menu that the buildable appears in.
+	pub menu:               BuildMenu,
+	/// What building the buildable costs. For pitch types, this is paid per unit of multiplicity when the type is
+	/// assigned; for everything else, per tile that the build covers.
+	#[serde(default)]
+	pub cost:               u64,
+	/// In-game hours that constructing the buildable takes, see [`super::construction`]. Buildables without a
+	/// construction time are finished as soon as they are placed.
+	#[serde(default)]
+	pub construction_hours: f32,
+	/// Footprint of the buildable in tiles along the x and y axis, for buildables that are built one at a time.
+	/// Without a size, the buildable's usual footprint is used.
+	#[serde(default)]
+	pub size:               Option<UVec2>,
+	/// Image shown in the build menu.
+	pub logo:               String,
+	/// Image of the build preview.
+	pub preview:            String,
+	/// Anchor of the preview image, relative to its size like [`Anchor::Custom`]. Without an anchor, the image's usual
+	/// anchor is used.
+	#[serde(default)]
+	pub anchor:             Option<Vec2>,
+}
+
+impl BuildableDefinition {
+	/// The definition that the game uses without a manifest.
+	pub fn builtin(buildable: Buildable) -> Self {
+		Self {
+			buildable,
+			name: buildable.to_string(),
+			description: buildable.description().to_string(),
+			menu: buildable.menu(),
+			cost: match buildable {
+				Buildable::PitchType(kind) => Balance::default().pitch_prices(kind).build_cost,
+				_ => 0,
+			},
+			construction_hours: match buildable {
+				Buildable::Facility(Facility::Reception | Facility::Showers) => 4.,
+				Buildable::Facility(Facility::Toilets | Facility::Kiosk) => 3.,
+				Buildable::Facility(Facility::Restaurant) => 8.,
+				Buildable::Decoration(Decoration::Tree | Decoration::Lamp) => 1.,
+				Buildable::Decoration(Decoration::FlowerBed | Decoration::Bench) => 0.5,
+				_ => 0.,
+			},
+			size: None,
+			logo: logo_for_buildable(buildable).to_string(),
+			preview: preview_image_for_buildable(buildable).to_string(),
+			anchor: None,
+		}
+	}
+
+	/// Simulated time that constructing the buildable takes.
+	pub fn construction_time(&self) -> Duration {
+		in_game_hours(self.construction_hours)
+	}
+
+	/// Anchor of the preview sprite.
+	pub fn preview_anchor(&self) -> Anchor {
+		self.anchor.map_or_else(|| anchor_for_image(&self.preview), Anchor::Custom)
+	}
+
+	/// Footprint of the buildable.
+	pub fn size(&self) -> BoundingBox {
+		let usual = self.buildable.size();
+		self.size.map_or(usual, |size| BoundingBox(size.extend(usual.height())))
+	}
+
+	/// Drops a size that the buildable can't have, see the [module documentation](self).
+	fn validate_size(&mut self) {
+		let Some(size) = self.size else {
+			return;
+		};
+		let problem = match self.buildable {
+			buildable if buildable.build_mode() != BuildMode::Single =>
+				"it covers the tiles that are dragged out instead".to_string(),
+			_ if size.min_element() == 0 => "it has to cover at least one tile".to_string(),
+			Buildable::PitchType(kind) if (size.x * size.y) as usize > kind.required_area() =>
+				format!("its building has to fit into the {} tiles that its pitches need", kind.required_area()),
+			_ => return,
+		};
+		warn!("{} can’t have a size of {}×{}, since {problem}", self.name, size.x, size.y);
+		self.size = None;
+	}
+}
+
+impl From<&BuildableDefinition> for Tooltip {
+	fn from(definition: &BuildableDefinition) -> Self {
+		Self { title: definition.name.clone(), body: definition.description.clone() }
+	}
+}
+
+/// Contents of a buildable manifest file.
+#[derive(Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct BuildableManifest {
+	/// All buildables that are offered to the player, in the order they appear in the build menus.
+	pub buildables: Vec<BuildableDefinition>,
+}
+
+/// Errors while loading a buildable manifest.
+#[derive(Error, Debug)]
+pub enum BuildableManifestError {
+	/// The manifest file couldn't be read.
+	#[error("Couldn’t read the buildable manifest: {0}")]
+	Io(#[from] std::io::Error),
+	/// The manifest isn't valid RON or doesn't describe buildables.
+	#[error("Invalid buildable manifest: {0}")]
+	Ron(#[from] ron::error::SpannedError),
+}
+
+/// Loads buildable manifests from `.buildables.ron` files.
+#[derive(Default)]
+struct BuildableManifestLoader;
+
+impl AssetLoader for BuildableManifestLoader {
+	type Asset = BuildableManifest;
+	type Error = BuildableManifestError;
+	type Settings = ();
+
+	async fn load(
+		&self,
+		reader: &mut dyn Reader,
+		_: &Self::Settings,
+		_: &mut LoadContext<'_>,
+	) -> Result<Self::Asset, Self::Error> {
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes).await?;
+		Ok(ron::de::from_bytes(&bytes)?)
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["buildables.ron"]
+	}
+}
+
+/// All buildables that are offered to the player, see the [module documentation](self).
+#[derive(Resource, Clone, Debug)]
+pub struct BuildableRegistry {
+	definitions:     Vec<BuildableDefinition>,
+	/// Definitions that mods registered, which are offered after the manifest's and replace them.
+	mod_definitions: Vec<BuildableDefinition>,
+	/// Keeps the manifest loaded, so that changes to it are picked up.
+	manifest:        Option<Handle<BuildableManifest>>,
+}
+
+impl Default for BuildableRegistry {
+	fn default() -> Self {
+		Self {
+			definitions:     ALL_BUILDABLES.map(BuildableDefinition::builtin).into(),
+			mod_definitions: Vec::new(),
+			manifest:        None,
+		}
+	}
+}
+
+impl BuildableRegistry {
+	/// All offered buildables, in build menu order.
+	pub fn iter(&self) -> impl Iterator<Item = &BuildableDefinition> {
+		self.definitions.iter()
+	}
+
+	/// The offered buildables of one build menu, in order.
+	pub fn in_menu(&self, menu: BuildMenu) -> impl Iterator<Item = &BuildableDefinition> {
+		self.iter().filter(move |definition| definition.menu == menu)
+	}
+
+	/// The definition of the buildable, if it is offered.
+	pub fn get(&self, buildable: Buildable) -> Option<&BuildableDefinition> {
+		self.iter().find(|definition| definition.buildable == buildable)
+	}
+
+	/// The definition of the buildable. Buildables that aren't offered still have their built-in definition, since they
+	/// may still be built from blueprints.
+	pub fn definition(&self, buildable: Buildable) -> BuildableDefinition {
+		self.get(buildable).cloned().unwrap_or_else(|| BuildableDefinition::builtin(buildable))
+	}
+
+	/// Footprint of the buildable.
+	pub fn size(&self, buildable: Buildable) -> BoundingBox {
+		self.get(buildable).map_or_else(|| buildable.size(), BuildableDefinition::size)
+	}
+
+	/// The tiles that the buildable takes up when built one at a time at the position.
+	pub fn grid_box_at(&self, buildable: Buildable, position: GridPosition) -> GridBox {
+		GridBox::around(position, self.size(buildable).flat())
+	}
+
+	/// All tiles covered by building the buildable from the start to the end position, with the buildable's footprint
+	/// from its definition.
+	pub fn footprint(
+		&self,
+		buildable: Buildable,
+		start_position: GridPosition,
+		end_position: GridPosition,
+	) -> Vec<GridPosition> {
+		match buildable.build_mode() {
+			BuildMode::Single => self.grid_box_at(buildable, start_position).floor_positions().collect(),
+			_ => buildable.footprint(start_position, end_position),
+		}
+	}
+
+	/// The manifest that the definitions come from, once it is being loaded.
+	pub fn manifest(&self) -> Option<&Handle<BuildableManifest>> {
+		self.manifest.as_ref()
+	}
+
+	/// Offers the definitions of mods in addition to the current ones, see [`crate::mods`].
+	pub(crate) fn add_mod_definitions(&mut self, definitions: Vec<BuildableDefinition>) {
+		self.mod_definitions.extend(definitions);
+		self.apply_mod_definitions();
+	}
+
+	/// Replaces the definitions with the manifest's, followed by those of mods. Definitions that can't be offered are
+	/// skipped.
+	fn apply(&mut self, manifest: &BuildableManifest) {
+		self.definitions.clear();
+		for definition in &manifest.buildables {
+			if definition.menu == BuildMenu::Blueprints {
+				warn!("{} can’t be offered in the blueprints menu", definition.name);
+			} else if self.definitions.iter().any(|other| other.buildable == definition.buildable) {
+				warn!("{:?} is defined more than once in the buildable manifest", definition.buildable);
+			} else {
+				let mut definition = definition.clone();
+				definition.validate_size();
+				self.definitions.push(definition);
+			}
+		}
+		self.apply_mod_definitions();
+	}
+
+	/// Adds the definitions of mods, each replacing an earlier definition of the same buildable.
+	fn apply_mod_definitions(&mut self) {
+		for definition in &self.mod_definitions {
+			if definition.menu == BuildMenu::Blueprints {
+				warn!("{} can’t be offered in the blueprints menu", definition.name);
+				continue;
+			}
+			let mut definition = definition.clone();
+			definition.validate_size();
+			match self.definitions.iter_mut().find(|other| other.buildable == definition.buildable) {
+				Some(other) => *other = definition,
+				None => self.definitions.push(definition),
+			}
+		}
+	}
+}
+
+fn load_buildable_manifest(mut registry: ResMut<BuildableRegistry>, asset_server: Res<AssetServer>) {
+	registry.manifest = Some(asset_server.load(MANIFEST_PATH));
+}
+
+fn apply_buildable_manifest(
+	mut events: EventReader<AssetEvent<BuildableManifest>>,
+	manifests: Res<Assets<BuildableManifest>>,
+	mut registry: ResMut<BuildableRegistry>,
+	mut balance: ResMut<Balance>,
+) {
+	for event in events.read() {
+		let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event else {
+			continue;
+		};
+		if registry.manifest.as_ref().is_none_or(|manifest| manifest.id() != *id) {
+			continue;
+		}
+		let Some(manifest) = manifests.get(*id) else {
+			continue;
+		};
+		registry.apply(manifest);
+		info!("Loaded {} buildables from the manifest", registry.definitions.len());
+		// Pitch types are charged through the balance, so their costs have to end up there.
+		for definition in &registry.definitions {
+			if let Buildable::PitchType(kind) = definition.buildable {
+				balance.set_pitch_build_cost(kind, definition.cost);
+			}
+		}
+	}
+}
@@ -0,0 +1,177 @@
+//! Burst placement for building large parts of the world at once.
+//!
+//! Placing tiles with [`GroundMap::set`] goes through commands one tile at a time, which is fine for player builds but
+//! stalls when worldgen or scenario loading creates tens of thousands of tiles. Instead, such callers queue their
+//! tiles and entities in the [`BurstPlacement`] resource. An exclusive system then builds the tile layer and spawns
+//! entities in batches, spending at most [`FRAME_BUDGET`] per frame so that the loading screen stays responsive. The
+//! loading screen waits for the queue to drain and shows its [`BurstPlacement::progress`].
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Instant};
+
+use super::{GridPosition, GroundKind, GroundMap, GroundTile};
+use crate::ui::world_info::WorldInfoProperties;
+use crate::util::Tooltipable;
+
+/// Time per frame spent on placing queued tiles and entities.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(8);
+/// Number of tiles or entities placed at once; the frame budget is checked between batches.
+const BATCH_SIZE: usize = 1024;
+
+/// Spawns one batch of queued entities.
+type EntityBatch = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// Tiles and entities queued for placement in batches.
+#[derive(Resource, Default)]
+pub struct BurstPlacement {
+	tiles:    VecDeque<(GridPosition, GroundKind)>,
+	/// Entity batches and their sizes, spawned after all tiles are placed.
+	entities: VecDeque<(usize, EntityBatch)>,
+	/// Number of tiles and entities queued since the queue was last empty.
+	queued:   usize,
+	/// Number of those that were placed already.
+	placed:   usize,
+}
+
+impl BurstPlacement {
+	/// Queues a ground tile. If a tile already exists at the position once the tile is placed, its kind is changed
+	/// instead. Of several tiles queued for the same position, the last one wins.
+	pub fn queue_tile(&mut self, position: GridPosition, kind: GroundKind) {
+		self.tiles.push_back((position, kind));
+		self.queued += 1;
+	}
+
+	/// Queues many ground tiles; see [`Self::queue_tile`].
+	pub fn queue_tiles(&mut self, tiles: impl IntoIterator<Item = (GridPosition, GroundKind)>) {
+		let old_length = self.tiles.len();
+		self.tiles.extend(tiles);
+		self.queued += self.tiles.len() - old_length;
+	}
+
+	/// Queues a rectangle of ground tiles of one kind between the two corners (inclusive) on the start position's
+	/// level.
+	pub fn queue_rect(&mut self, start_position: GridPosition, end_position: GridPosition, kind: GroundKind) {
+		let smaller_corner = start_position.component_wise_min(end_position);
+		let larger_corner = start_position.component_wise_max(end_position);
+		self.queue_tiles((smaller_corner.x ..= larger_corner.x).flat_map(|x| {
+			(smaller_corner.y ..= larger_corner.y).map(move |y| ((x, y, start_position.z).into(), kind))
+		}));
+	}
+
+	/// Queues entities to be spawned, such as the areas and buildings of a scenario. They are spawned after all queued
+	/// tiles have been placed.
+	pub fn queue_entities<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) {
+		let mut bundles = bundles.into_iter().peekable();
+		while bundles.peek().is_some() {
+			let batch = bundles.by_ref().take(BATCH_SIZE).collect::<Vec<_>>();
+			self.queued += batch.len();
+			self.entities.push_back((
+				batch.len(),
+				Box::new(move |world: &mut World| {
+					world.spawn_batch(batch);
+				}),
+			));
+		}
+	}
+
+	/// Queues work that needs the tiles queued so far to be placed, such as updating objects that refer to tiles. It
+	/// runs in order with the queued entities.
+	pub fn queue_task(&mut self, task: impl FnOnce(&mut World) + Send + Sync + 'static) {
+		self.entities.push_back((0, Box::new(task)));
+	}
+
+	/// Whether nothing is waiting to be placed.
+	pub fn is_empty(&self) -> bool {
+		self.tiles.is_empty() && self.entities.is_empty()
+	}
+
+	/// Fraction of the queued tiles and entities that have been placed, between 0 and 1.
+	pub fn progress(&self) -> f32 {
+		if self.queued == 0 {
+			1.
+		} else {
+			self.placed as f32 / self.queued as f32
+		}
+	}
+
+	/// Drops everything that is still queued.
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+}
+
+/// Places one batch of queued tiles. New tiles are spawned together, while existing tiles are changed in place.
+fn place_tile_batch(
+	tiles: Vec<(GridPosition, GroundKind)>,
+	world: &mut World,
+	ground_map: &mut GroundMap,
+	sprites: &mut HashMap<GroundKind, Sprite>,
+	asset_server: &AssetServer,
+) {
+	let mut new_tiles = HashMap::<GridPosition, GroundKind>::new();
+	for (position, kind) in tiles {
+		match ground_map.get(&position) {
+			Some((entity, _)) => {
+				if let Some(mut in_world_kind) = world.get_mut::<GroundKind>(entity) {
+					in_world_kind.set_if_neq(kind);
+				}
+				if let Some(mut world_info) = world.get_mut::<WorldInfoProperties>(entity) {
+					*world_info = WorldInfoProperties::basic(kind.to_string(), kind.description().to_string());
+				}
+				ground_map.update_with_existing_tile(entity, position, kind);
+			},
+			None => {
+				new_tiles.insert(position, kind);
+			},
+		}
+	}
+
+	let new_tiles = new_tiles.into_iter().collect::<Vec<_>>();
+	let bundles = new_tiles
+		.iter()
+		.map(|(position, kind)| {
+			let sprite = sprites.entry(*kind).or_insert_with(|| GroundTile::sprite_for(*kind, asset_server)).clone();
+			GroundTile::with_sprite(*kind, *position, sprite)
+		})
+		.collect::<Vec<_>>();
+	let entities = world.spawn_batch(bundles).collect::<Vec<_>>();
+	for (entity, (position, kind)) in entities.into_iter().zip(new_tiles) {
+		ground_map.update_with_existing_tile(entity, position, kind);
+	}
+}
+
+pub(super) fn place_bursts(world: &mut World) {
+	if world.get_resource::<BurstPlacement>().is_none_or(BurstPlacement::is_empty) {
+		return;
+	}
+	let start = Instant::now();
+	let asset_server = world.resource::<AssetServer>().clone();
+	let mut sprites = HashMap::new();
+
+	world.resource_scope(|world, mut burst: Mut<BurstPlacement>| {
+		while !burst.is_empty() && start.elapsed() < FRAME_BUDGET {
+			if !burst.tiles.is_empty() {
+				let batch_size = burst.tiles.len().min(BATCH_SIZE);
+				let batch = burst.tiles.drain(.. batch_size).collect();
+				world.resource_scope(|world, mut ground_map: Mut<GroundMap>| {
+					place_tile_batch(batch, world, &mut ground_map, &mut sprites, &asset_server);
+				});
+				burst.placed += batch_size;
+			} else if let Some((batch_size, spawn_batch)) = burst.entities.pop_front() {
+				spawn_batch(world);
+				burst.placed += batch_size;
+			}
+		}
+		debug!("Burst placement at {:.0}% after {:?}", burst.progress() * 100., start.elapsed());
+		if burst.is_empty() {
+			burst.clear();
+		}
+	});
+}
+
+pub(super) fn clear_bursts(mut burst: ResMut<BurstPlacement>) {
+	burst.clear();
+}
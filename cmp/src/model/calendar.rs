@@ -0,0 +1,160 @@
+//! In-game calendar with months and seasons.
+//!
+//! The [`Calendar`] follows the [`DayClock`]: every day that ends moves the calendar forward by one day, and months
+//! have [`DAYS_PER_MONTH`] days each. New campgrounds open on the first of [`OPENING_MONTH`], so that the first season
+//! is spring. Systems that care about the season can react to [`SeasonChanged`]; systems that roll over daily
+//! statistics subscribe to [`super::rollup::DayEnded`] and the [`super::rollup::RollupSet`] steps instead.
+//! Special events that take place on the same dates every year are scheduled in [`super::special_event`].
+
+use bevy::prelude::*;
+
+use super::rollup::{advance_day, DayClock};
+use crate::gamemode::{GameSet, GameState};
+
+/// Number of days in every month.
+pub const DAYS_PER_MONTH: u32 = 10;
+/// The month that new campgrounds open in, counted from 0 for January.
+pub const OPENING_MONTH: u32 = 3;
+
+const MONTH_NAMES: [&str; 12] = [
+	"January",
+	"February",
+	"March",
+	"April",
+	"May",
+	"June",
+	"July",
+	"August",
+	"September",
+	"October",
+	"November",
+	"December",
+];
+
+pub struct CalendarManagement;
+
+impl Plugin for CalendarManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Season>().init_resource::<Calendar>().add_event::<SeasonChanged>().add_systems(
+			FixedUpdate,
+			update_calendar.after(advance_day).in_set(GameSet::Simulation).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// The seasons of the year.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Season {
+	Spring,
+	Summer,
+	Autumn,
+	Winter,
+}
+
+/// All seasons, in calendar order.
+pub const ALL_SEASONS: [Season; 4] = [Season::Spring, Season::Summer, Season::Autumn, Season::Winter];
+
+impl Season {
+	/// The season that the month (counted from 0 for January) lies in.
+	pub const fn of_month(month: u32) -> Self {
+		match month % 12 {
+			2 ..= 4 => Self::Spring,
+			5 ..= 7 => Self::Summer,
+			8 ..= 10 => Self::Autumn,
+			_ => Self::Winter,
+		}
+	}
+}
+
+impl std::fmt::Display for Season {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Spring => "Spring",
+			Self::Summer => "Summer",
+			Self::Autumn => "Autumn",
+			Self::Winter => "Winter",
+		})
+	}
+}
+
+/// The current date.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calendar {
+	/// Days since the campground opened, starting at 0.
+	days_since_opening: u32,
+}
+
+impl Default for Calendar {
+	fn default() -> Self {
+		Self::for_day(1)
+	}
+}
+
+impl Calendar {
+	/// The date of the given day of the game, as counted by the [`DayClock`] starting at 1.
+	pub const fn for_day(day: u32) -> Self {
+		Self { days_since_opening: day.saturating_sub(1) }
+	}
+
+	/// Months since January of the first year.
+	const fn months(&self) -> u32 {
+		OPENING_MONTH + self.days_since_opening / DAYS_PER_MONTH
+	}
+
+	/// Day of the month, starting at 1.
+	pub const fn day_of_month(&self) -> u32 {
+		self.days_since_opening % DAYS_PER_MONTH + 1
+	}
+
+	/// Day of the year, starting at 0 for the first of January.
+	pub const fn day_of_year(&self) -> u32 {
+		self.month() * DAYS_PER_MONTH + self.day_of_month() - 1
+	}
+
+	/// Month of the year, counted from 0 for January.
+	pub const fn month(&self) -> u32 {
+		self.months() % 12
+	}
+
+	/// English name of the month.
+	pub const fn month_name(&self) -> &'static str {
+		MONTH_NAMES[self.month() as usize]
+	}
+
+	/// Year since the campground opened, starting at 1.
+	pub const fn year(&self) -> u32 {
+		self.months() / 12 + 1
+	}
+
+	/// The season that the date lies in.
+	pub const fn season(&self) -> Season {
+		Season::of_month(self.month())
+	}
+}
+
+impl std::fmt::Display for Calendar {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {}, year {}", self.day_of_month(), self.month_name(), self.year())
+	}
+}
+
+/// Sent when a new season begins.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SeasonChanged {
+	pub season: Season,
+}
+
+fn update_calendar(
+	clock: Res<DayClock>,
+	mut calendar: ResMut<Calendar>,
+	mut season_changed: EventWriter<SeasonChanged>,
+) {
+	let old_season = calendar.season();
+	if calendar.set_if_neq(Calendar::for_day(clock.day())) {
+		let season = calendar.season();
+		if season != old_season {
+			info!("{season} begins on {}", *calendar);
+			season_changed.send(SeasonChanged { season });
+		}
+	}
+}
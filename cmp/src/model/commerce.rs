@@ -0,0 +1,152 @@
+//! Shops, which earn money from visitors.
+//!
+//! Visitors walk to a [`Facility::Kiosk`] or [`Facility::Restaurant`] on their [`super::errand`]s and pay for every
+//! visit. In return, every shop costs a daily operating fee, which is paid in the end-of-day rollup like staff wages.
+//! Both go into the [`DailyTally`] like all other income and expenses. Every shop also keeps its own
+//! [`ShopFinances`], which its world info shows, and the [`CommercialTally`] adds up all shops for the end-of-day
+//! summary.
+
+use bevy::prelude::*;
+
+use super::balance::Balance;
+use super::errand::FacilityVisited;
+use super::facility::Facility;
+use super::modifiers::{ModifierTarget, Modifiers};
+use super::rollup::{DailyTally, DayEnded, RollupSet};
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::world_info::{WorldInfoProperties, WorldInfoProperty};
+
+pub struct CommerceManagement;
+
+impl Plugin for CommerceManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<ShopFinances>()
+			.register_type::<CommercialFigures>()
+			.register_type::<CommercialTally>()
+			.init_resource::<CommercialTally>()
+			.add_systems(
+				FixedUpdate,
+				(add_shop_finances, record_shop_visits).chain().in_set(GameSet::Simulation).in_set(GameState::InGame),
+			)
+			.add_systems(FixedUpdate, update_shop_world_info.in_set(GameSet::UI).in_set(GameState::InGame))
+			.add_systems(FixedUpdate, pay_operating_costs.in_set(RollupSet::Wages))
+			.add_systems(FixedUpdate, close_commercial_day.in_set(RollupSet::Aggregate));
+	}
+}
+
+/// What a shop earned and cost since it was built.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct ShopFinances {
+	pub revenue:         u64,
+	pub operating_costs: u64,
+	pub visits:          u32,
+}
+
+impl ShopFinances {
+	/// Revenue minus operating costs.
+	pub fn profit(&self) -> i64 {
+		self.revenue as i64 - self.operating_costs as i64
+	}
+}
+
+/// Figures of all shops taken together over one day.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommercialFigures {
+	pub revenue:         u64,
+	pub operating_costs: u64,
+	pub visits:          u32,
+}
+
+/// Running figures of all shops on the current day, and those of the day that ended last.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Resource)]
+pub struct CommercialTally {
+	today:        CommercialFigures,
+	pub last_day: CommercialFigures,
+}
+
+fn add_shop_finances(
+	facilities: Query<(Entity, &Facility), Without<ShopFinances>>,
+	balance: Res<Balance>,
+	mut commands: Commands,
+) {
+	for (entity, facility) in &facilities {
+		if balance.shop_prices(*facility).is_some() {
+			commands.entity(entity).insert(ShopFinances::default());
+		}
+	}
+}
+
+fn record_shop_visits(
+	mut visits: EventReader<FacilityVisited>,
+	balance: Res<Balance>,
+	mut shops: Query<(&Facility, &mut ShopFinances)>,
+	mut tally: ResMut<DailyTally>,
+	mut commercial_tally: ResMut<CommercialTally>,
+) {
+	for FacilityVisited { facility } in visits.read() {
+		let Ok((kind, mut finances)) = shops.get_mut(*facility) else {
+			continue;
+		};
+		let Some(prices) = balance.shop_prices(*kind) else {
+			continue;
+		};
+		finances.revenue += prices.visit_price;
+		finances.visits += 1;
+		commercial_tally.today.revenue += prices.visit_price;
+		commercial_tally.today.visits += 1;
+		tally.record_income(prices.visit_price);
+	}
+}
+
+/// Replaces the finance entries of a shop's world info when its finances change. Facilities receive their world info
+/// after they were spawned, so this also runs once it was added.
+fn update_shop_world_info(
+	mut shops: Query<
+		(&ShopFinances, &mut WorldInfoProperties),
+		Or<(Changed<ShopFinances>, Added<WorldInfoProperties>)>,
+	>,
+) {
+	for (finances, mut properties) in &mut shops {
+		properties.retain(|property| {
+			!matches!(
+				property,
+				WorldInfoProperty::Revenue(_) | WorldInfoProperty::OperatingCosts(_) | WorldInfoProperty::Profit(_)
+			)
+		});
+		properties.extend([
+			WorldInfoProperty::Revenue(finances.revenue),
+			WorldInfoProperty::OperatingCosts(finances.operating_costs),
+			WorldInfoProperty::Profit(finances.profit()),
+		]);
+	}
+}
+
+fn pay_operating_costs(
+	balance: Res<Balance>,
+	modifiers: Res<Modifiers>,
+	mut shops: Query<(&Facility, &mut ShopFinances)>,
+	mut tally: ResMut<DailyTally>,
+	mut commercial_tally: ResMut<CommercialTally>,
+) {
+	let mut total = 0;
+	for (facility, mut finances) in &mut shops {
+		let Some(prices) = balance.shop_prices(*facility) else {
+			continue;
+		};
+		let cost = (prices.daily_operating_cost as f32 * modifiers.factor(ModifierTarget::Upkeep)).round() as u64;
+		finances.operating_costs += cost;
+		total += cost;
+	}
+	if total > 0 {
+		commercial_tally.today.operating_costs += total;
+		tally.record_expense(total);
+	}
+}
+
+fn close_commercial_day(mut day_ended: EventReader<DayEnded>, mut commercial_tally: ResMut<CommercialTally>) {
+	for _ in day_ended.read() {
+		commercial_tally.last_day = std::mem::take(&mut commercial_tally.today);
+	}
+}
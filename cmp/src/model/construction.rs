@@ -0,0 +1,189 @@
+//! Construction: buildings take a while to build.
+//!
+//! Facilities and decorations aren't finished as soon as they are placed. Instead, a construction site with the
+//! [`UnderConstruction`] component takes up their tiles, and shows a translucent preview of the building with a
+//! progress bar above it. Once the site has been worked on for the construction time of its buildable (see
+//! [`BuildableDefinition::construction_hours`](super::buildables::BuildableDefinition::construction_hours)), it is
+//! replaced by the finished building. Only then does the building block the nav mesh, beautify its surroundings or
+//! serve visitors, since all of these follow the building's own component, which construction sites don't have.
+//!
+//! Construction time passes with the simulation. With the `instant_construction` sandbox setting, buildings are
+//! finished right away, and so are all sites when the setting is turned on.
+
+use std::time::Duration;
+
+use bevy::color::palettes::css::ORANGE;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use moonshine_save::save::Save;
+
+use super::buildables::BuildableRegistry;
+use super::decoration::spawn_decoration;
+use super::facility::spawn_facility;
+use super::{Buildable, GridBox};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::ObjectPriority;
+use crate::ui::world_info::WorldInfoProperties;
+
+pub struct ConstructionManagement;
+
+impl Plugin for ConstructionManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<UnderConstruction>()
+			.add_systems(FixedUpdate, advance_construction.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				(add_construction_graphics, update_progress_bars::<UnderConstruction>)
+					.chain()
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Width of a progress bar in world pixels.
+const PROGRESS_BAR_WIDTH: f32 = 16.;
+/// Height of a progress bar above the anchor of its parent, in world pixels.
+const PROGRESS_BAR_OFFSET: f32 = 24.;
+/// Tint of the building preview on a construction site.
+const SITE_TINT: Color = Color::srgba(1., 1., 1., 0.5);
+
+/// A building that is still being built on the tiles of the entity's [`GridBox`].
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct UnderConstruction {
+	/// The building that is being built.
+	pub buildable: Buildable,
+	/// Simulated time that was spent building so far.
+	pub elapsed:   Duration,
+	/// Simulated time that the building takes to build.
+	pub duration:  Duration,
+}
+
+impl UnderConstruction {
+	pub fn is_finished(&self) -> bool {
+		self.elapsed >= self.duration
+	}
+}
+
+/// Work on the world that takes a while, and shows its progress in a progress bar.
+pub trait WorkProgress {
+	/// How far the work has progressed, between 0 and 1.
+	fn progress(&self) -> f32;
+}
+
+impl WorkProgress for UnderConstruction {
+	fn progress(&self) -> f32 {
+		if self.duration.is_zero() {
+			1.
+		} else {
+			(self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).clamp(0., 1.)
+		}
+	}
+}
+
+/// Starts building the buildable on the tiles. Buildables without a construction time are finished right away.
+pub fn start_construction(buildable: Buildable, grid_box: GridBox, duration: Duration, commands: &mut Commands) {
+	if duration.is_zero() {
+		finish_construction(buildable, grid_box, commands);
+	} else {
+		commands.spawn((UnderConstruction { buildable, elapsed: Duration::ZERO, duration }, grid_box, Save));
+	}
+}
+
+/// Spawns the finished building. Only facilities and decorations are constructed.
+fn finish_construction(buildable: Buildable, grid_box: GridBox, commands: &mut Commands) {
+	match buildable {
+		Buildable::Facility(facility) => spawn_facility(facility, grid_box, commands),
+		Buildable::Decoration(decoration) => spawn_decoration(decoration, grid_box, commands),
+		_ => warn!("{buildable:?} can’t be constructed"),
+	}
+}
+
+fn advance_construction(
+	time: Res<Time>,
+	settings: Res<GameSettings>,
+	mut sites: Query<(Entity, &mut UnderConstruction, &GridBox)>,
+	mut commands: Commands,
+) {
+	for (entity, mut site, grid_box) in &mut sites {
+		site.elapsed += time.delta();
+		if site.is_finished() || settings.instant_construction {
+			commands.entity(entity).despawn_recursive();
+			finish_construction(site.buildable, *grid_box, &mut commands);
+		}
+	}
+}
+
+/// Marks the bar of a progress bar, whose width follows the progress of the work on its parent.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ProgressBarFill;
+
+/// The preview of the building and the progress bar are added separately, so that loaded sites receive them as well.
+fn add_construction_graphics(
+	sites: Query<(Entity, &UnderConstruction), Without<Sprite>>,
+	registry: Res<BuildableRegistry>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (entity, site) in &sites {
+		let definition = registry.definition(site.buildable);
+		commands
+			.entity(entity)
+			.insert((
+				Sprite {
+					anchor: definition.preview_anchor(),
+					image: asset_server.load(&definition.preview),
+					color: SITE_TINT,
+					..default()
+				},
+				ObjectPriority::Normal,
+				WorldInfoProperties::basic(
+					format!("{} (under construction)", definition.name),
+					definition.description.clone(),
+				),
+			))
+			.with_children(spawn_progress_bar);
+	}
+}
+
+/// Spawns a progress bar above the parent, which follows the progress of the parent's [`WorkProgress`] component once
+/// [`update_progress_bars`] runs for it.
+pub fn spawn_progress_bar(parent: &mut ChildBuilder) {
+	parent
+		.spawn((
+			Sprite {
+				color: Color::BLACK.with_alpha(0.6),
+				custom_size: Some(Vec2::new(PROGRESS_BAR_WIDTH + 2., 4.)),
+				..default()
+			},
+			Transform::from_xyz(0., PROGRESS_BAR_OFFSET, 0.5),
+		))
+		.with_child((
+			Sprite {
+				color: ORANGE.into(),
+				custom_size: Some(Vec2::new(0., 2.)),
+				anchor: Anchor::CenterLeft,
+				..default()
+			},
+			Transform::from_xyz(-PROGRESS_BAR_WIDTH / 2., 0., 0.1),
+			ProgressBarFill,
+		));
+}
+
+pub fn update_progress_bars<W: Component + WorkProgress>(
+	work: Query<&W>,
+	parents: Query<&Parent>,
+	mut bars: Query<(Entity, &mut Sprite), With<ProgressBarFill>>,
+) {
+	for (bar, mut sprite) in &mut bars {
+		let Some(work) = parents.iter_ancestors(bar).find_map(|ancestor| work.get(ancestor).ok()) else {
+			continue;
+		};
+		let width = (PROGRESS_BAR_WIDTH * work.progress()).round();
+		if sprite.custom_size.map(|size| size.x) != Some(width) {
+			sprite.custom_size = Some(Vec2::new(width, 2.));
+		}
+	}
+}
@@ -0,0 +1,113 @@
+//! Painted ground markings, such as pitch numbers, arrows and parking lines.
+//!
+//! Decals are purely cosmetic. Each tile has at most one decal, which is removed when the tile's ground kind changes,
+//! since the paint doesn't survive the ground being rebuilt.
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use moonshine_save::save::Save;
+
+use super::{GridPosition, GroundKind};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::decals::{decal_anchor, DecalImages};
+use crate::graphics::{ObjectPriority, Sides};
+
+pub struct DecalManagement;
+
+impl Plugin for DecalManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Decal>().register_type::<DecalKind>().init_resource::<DecalImages>().add_systems(
+			PostUpdate,
+			(clear_decals_on_ground_change, add_decal_sprites).in_set(GameSet::Graphics).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// The kinds of markings that can be painted.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DecalKind {
+	/// A number, such as a pitch number.
+	Number(u16),
+	/// An arrow pointing towards one side of the tile.
+	Arrow(Sides),
+	/// A line along one side of the tile, such as for parking spaces.
+	Line(Sides),
+}
+
+impl std::fmt::Display for DecalKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Number(number) => write!(f, "Number {}", number),
+			Self::Arrow(_) => write!(f, "Arrow"),
+			Self::Line(_) => write!(f, "Line"),
+		}
+	}
+}
+
+/// A decal painted on the tile at the same position.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(ObjectPriority(|| ObjectPriority::Decal))]
+pub struct Decal {
+	/// What is painted.
+	pub kind: DecalKind,
+}
+
+impl Decal {
+	/// Paints a decal on the given tile, replacing any decal that is already there.
+	pub fn paint(
+		kind: DecalKind,
+		position: GridPosition,
+		existing_decals: &Query<(Entity, &GridPosition), With<Decal>>,
+		commands: &mut Commands,
+	) {
+		Self::erase(position, existing_decals, commands);
+		commands.spawn((Decal { kind }, position, Save));
+	}
+
+	/// Removes the decal on the given tile, if there is one.
+	pub fn erase(
+		position: GridPosition,
+		existing_decals: &Query<(Entity, &GridPosition), With<Decal>>,
+		commands: &mut Commands,
+	) {
+		for (entity, _) in existing_decals.iter().filter(|(_, decal_position)| **decal_position == position) {
+			commands.entity(entity).despawn_recursive();
+		}
+	}
+}
+
+fn clear_decals_on_ground_change(
+	tiles: Query<(&GridPosition, Ref<GroundKind>)>,
+	decals: Query<(Entity, &GridPosition), With<Decal>>,
+	mut commands: Commands,
+) {
+	// Tiles that were just spawned or loaded don't invalidate decals.
+	let changed_positions = tiles
+		.iter()
+		.filter(|(_, kind)| kind.is_changed() && !kind.is_added())
+		.map(|(position, _)| *position)
+		.collect::<HashSet<_>>();
+	if changed_positions.is_empty() {
+		return;
+	}
+	for (entity, _) in decals.iter().filter(|(_, position)| changed_positions.contains(*position)) {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+/// Sprites aren't saved, so they are recreated from the decal kind after spawning or loading.
+fn add_decal_sprites(
+	decals: Query<(Entity, &Decal), Or<(Without<Sprite>, Changed<Decal>)>>,
+	mut decal_images: ResMut<DecalImages>,
+	mut images: ResMut<Assets<Image>>,
+	mut commands: Commands,
+) {
+	for (entity, decal) in &decals {
+		commands.entity(entity).insert(Sprite {
+			image: decal_images.get(decal.kind, &mut images),
+			anchor: decal_anchor(),
+			..default()
+		});
+	}
+}
@@ -0,0 +1,237 @@
+//! Decorations: trees, benches, lamps and flower beds.
+//!
+//! Decorations are placed on grass and take up the tiles of their [`GridBox`], which nobody can walk through anymore.
+//! Every decoration makes its surroundings more beautiful: pitches sum up the [`Decoration::beauty`] of all
+//! decorations within [`BEAUTY_RADIUS`] tiles into their [`LocalBeauty`], which makes their visitors happier.
+
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
+
+use super::area::{Area, ImmutableArea};
+use super::facility::Facility;
+use super::nav::{NavCategory, NavComponent};
+use super::obstacle::Obstacle;
+use super::{BoundingBox, GridBox, GridPosition, GroundKind, GroundMap, Metric, Pitch};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, image_for_decoration};
+use crate::graphics::{ObjectPriority, OverlayLayer, OverlayLayerExt};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::{WorldInfoProperties, WorldInfoSectionExt};
+use crate::util::Tooltipable;
+
+pub struct DecorationManagement;
+
+impl Plugin for DecorationManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Decoration>()
+			.add_systems(
+				FixedUpdate,
+				block_decorated_tiles
+					.after(super::tile::update_navigability_properties)
+					.in_set(GameSet::SimulationEarly)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(FixedUpdate, update_local_beauty.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(Update, add_decoration_graphics.in_set(GameSet::Graphics).in_set(GameState::InGame))
+			.add_overlay_layer::<BeautyLayer>()
+			.add_world_info_section::<LocalBeauty>("Surroundings", |beauty| {
+				vec![DialogWidget::Property { name: "Beauty".to_string(), value: beauty.0.to_string() }]
+			});
+	}
+}
+
+/// How far decorations make their surroundings more beautiful, in tiles.
+pub const BEAUTY_RADIUS: i32 = 3;
+/// Satisfaction that visitors gain at the most beautiful pitches.
+const MAX_BEAUTY_SATISFACTION: f32 = 0.2;
+
+pub type Beauty = Metric<0, 10>;
+
+/// A decorative object.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum Decoration {
+	Tree,
+	Bench,
+	Lamp,
+	FlowerBed,
+}
+
+/// All decorations, in the order they appear in the build menu.
+pub const ALL_DECORATIONS: [Decoration; 4] =
+	[Decoration::Tree, Decoration::FlowerBed, Decoration::Bench, Decoration::Lamp];
+
+impl Decoration {
+	pub const fn size(&self) -> BoundingBox {
+		match self {
+			Self::Tree | Self::Lamp => BoundingBox::fixed::<1, 1, 2>(),
+			Self::Bench => BoundingBox::fixed::<1, 1, 1>(),
+			Self::FlowerBed => BoundingBox::fixed::<2, 1, 1>(),
+		}
+	}
+
+	/// How much the decoration adds to the [`LocalBeauty`] of nearby pitches.
+	pub const fn beauty(&self) -> u64 {
+		match self {
+			Self::Tree => 3,
+			Self::FlowerBed => 2,
+			Self::Bench | Self::Lamp => 1,
+		}
+	}
+}
+
+impl std::fmt::Display for Decoration {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Tree => "Tree",
+			Self::Bench => "Bench",
+			Self::Lamp => "Lamp",
+			Self::FlowerBed => "Flower Bed",
+		})
+	}
+}
+
+impl Tooltipable for Decoration {
+	fn description(&self) -> &'static str {
+		match self {
+			Self::Tree => "A shady tree. Trees make their surroundings much more beautiful.",
+			Self::Bench => "A bench to sit down and enjoy the view.",
+			Self::Lamp => "A lamp lighting the campground at night.",
+			Self::FlowerBed =>
+				"A colorful bed of flowers, spanning two tiles. Flower beds make their surroundings more beautiful.",
+		}
+	}
+}
+
+/// The beauty of a pitch's surroundings, from the decorations near it.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LocalBeauty(pub Beauty);
+
+impl LocalBeauty {
+	/// Satisfaction that visitors of the pitch gain from its surroundings.
+	pub fn satisfaction_bonus(&self) -> f32 {
+		*self.0 as f32 / *Beauty::MAX as f32 * MAX_BEAUTY_SATISFACTION
+	}
+}
+
+/// Spawns a new decoration on the tiles. Its graphics are added separately, so that loaded decorations receive them as
+/// well.
+pub fn spawn_decoration(decoration: Decoration, grid_box: GridBox, commands: &mut Commands) {
+	commands.spawn((decoration, grid_box, Save));
+}
+
+fn add_decoration_graphics(
+	decorations: Query<(Entity, &Decoration), Without<Sprite>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (entity, decoration) in &decorations {
+		let image = image_for_decoration(*decoration);
+		commands.entity(entity).insert((
+			Sprite { anchor: anchor_for_image(image), image: asset_server.load(image), ..default() },
+			ObjectPriority::Normal,
+			WorldInfoProperties::basic(decoration.to_string(), decoration.description().to_string()),
+		));
+	}
+}
+
+/// Nobody can walk through decorations, facilities or obstacles, so their tiles are taken out of all nav meshes. This
+/// runs after the ground's own navigability was applied, and restores it on tiles whose decoration, facility or
+/// obstacle was removed.
+fn block_decorated_tiles(
+	decorations: Query<&GridBox, Or<(With<Decoration>, With<Facility>, With<Obstacle>)>>,
+	changed_decorations: Query<(), (Or<(With<Decoration>, With<Facility>, With<Obstacle>)>, Changed<GridBox>)>,
+	mut removed_decorations: RemovedComponents<Decoration>,
+	mut removed_facilities: RemovedComponents<Facility>,
+	mut removed_obstacles: RemovedComponents<Obstacle>,
+	mut tiles: Query<(&GridPosition, Ref<GroundKind>, &mut NavComponent)>,
+) {
+	// All readers have to be drained.
+	let removed =
+		removed_decorations.read().count() + removed_facilities.read().count() + removed_obstacles.read().count() > 0;
+	let tiles_changed = tiles.iter_mut().any(|(_, kind, vertex)| kind.is_changed() || vertex.is_added());
+	if changed_decorations.is_empty() && !tiles_changed && !removed {
+		return;
+	}
+
+	let blocked = decorations.iter().flat_map(GridBox::floor_positions).collect::<HashSet<_>>();
+	for (position, kind, mut vertex) in &mut tiles {
+		let navigability = if blocked.contains(position) { NavCategory::None } else { kind.navigability() };
+		if vertex.navigability != navigability {
+			vertex.navigability = navigability;
+		}
+	}
+}
+
+fn update_local_beauty(
+	decorations: Query<(&Decoration, Ref<GridBox>)>,
+	mut removed_decorations: RemovedComponents<Decoration>,
+	pitches: Query<(Entity, Option<Ref<Area>>, Option<Ref<ImmutableArea>>, Option<&LocalBeauty>), With<Pitch>>,
+	mut commands: Commands,
+) {
+	let decorations_changed = decorations.iter().any(|(_, position)| position.is_changed());
+	let pitches_changed = pitches.iter().any(|(_, area, immutable_area, beauty)| {
+		beauty.is_none()
+			|| area.is_some_and(|area| area.is_changed())
+			|| immutable_area.is_some_and(|area| area.is_changed())
+	});
+	if !decorations_changed && !pitches_changed && removed_decorations.read().count() == 0 {
+		return;
+	}
+
+	for (entity, area, immutable_area, beauty) in &pitches {
+		let Some(area) = area.as_deref().or(immutable_area.as_deref().map(|area| &area.0)) else {
+			continue;
+		};
+		let total = decorations
+			.iter()
+			.filter(|(_, position)| {
+				position.floor_positions().any(|decoration_tile| {
+					area.tiles_iter().any(|tile| {
+						let offset = (*tile - *decoration_tile).abs();
+						offset.x.max(offset.y) <= BEAUTY_RADIUS
+					})
+				})
+			})
+			.map(|(decoration, _)| decoration.beauty())
+			.sum::<u64>();
+		let new_beauty = LocalBeauty(Beauty::try_from(total.min(*Beauty::MAX)).unwrap_or_default());
+		if beauty != Some(&new_beauty) {
+			commands.entity(entity).insert(new_beauty);
+		}
+	}
+}
+
+/// How beautiful the surroundings of every ground tile are, summed up like the [`LocalBeauty`] of a pitch on the tile.
+pub struct BeautyLayer;
+
+impl OverlayLayer for BeautyLayer {
+	type Param = (Res<'static, GroundMap>, Query<'static, 'static, (&'static Decoration, &'static GridBox)>);
+
+	const LEGEND: [&'static str; 2] = ["Plain", "Beautiful"];
+	const NAME: &'static str = "Beauty";
+
+	fn values((ground_map, decorations): SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		let mut totals = ground_map.iter().map(|(tile, ..)| (tile, 0)).collect::<HashMap<_, u64>>();
+		for (decoration, grid_box) in &decorations {
+			// Every decoration only counts once per tile, even if several of its tiles are close.
+			let surroundings = grid_box
+				.floor_positions()
+				.flat_map(|decoration_tile| {
+					(-BEAUTY_RADIUS ..= BEAUTY_RADIUS).flat_map(move |x| {
+						(-BEAUTY_RADIUS ..= BEAUTY_RADIUS).map(move |y| decoration_tile + IVec2::new(x, y))
+					})
+				})
+				.collect::<HashSet<_>>();
+			for tile in surroundings {
+				if let Some(total) = totals.get_mut(&tile) {
+					*total += decoration.beauty();
+				}
+			}
+		}
+		totals.into_iter().map(|(tile, total)| (tile, total.min(*Beauty::MAX) as f32 / *Beauty::MAX as f32)).collect()
+	}
+}
@@ -0,0 +1,183 @@
+//! Demand: how eagerly visitors come to the campground's pitches.
+//!
+//! Visitors book pitches with a chance that the [`DemandModel`] scales by four factors:
+//! - The season: pitches are sought after in the seasons in which the [`VisitorSegment`]s that like them travel. Tent
+//!   pitches are empty in winter, while cottages draw luxury campers all year.
+//! - The price: the player sets a nightly price for every pitch type in the [`PitchPricing`]. Pitches that are cheaper
+//!   than usual draw more visitors, and expensive ones fewer.
+//! - The rating: the average satisfaction of the visitors of the last [`RATING_DAYS`] days. Campgrounds that nobody
+//!   rated yet have an average rating.
+//! - The weather and all other modifiers on [`ModifierTarget::Demand`].
+//!
+//! Every pitch tracks the price of its type in [`Pitch::nightly_price`], which its visitors pay when they leave.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::balance::Balance;
+use super::calendar::{Calendar, Season, ALL_SEASONS};
+use super::modifiers::{ModifierTarget, Modifiers};
+use super::pitch::{Pitch, PitchType, ALL_PITCH_TYPES};
+use super::rollup::DailyHistory;
+use super::visitor::{VisitorSegment, ALL_VISITOR_SEGMENTS};
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct DemandManagement;
+
+impl Plugin for DemandManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<PitchPricing>()
+			.init_resource::<PitchPricing>()
+			.add_systems(Update, apply_pitch_prices.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_world_info_section::<Pitch>("Pricing", |pitch| {
+				if pitch.kind.is_none() {
+					return Vec::new();
+				}
+				vec![DialogWidget::Property {
+					name:  "Nightly price".to_string(),
+					value: (pitch.nightly_price * *pitch.multiplicity).to_string(),
+				}]
+			});
+	}
+}
+
+/// Number of recent days whose satisfaction makes up the campground's rating.
+pub const RATING_DAYS: usize = 7;
+/// Rating of a campground that no visitor has rated yet.
+const DEFAULT_RATING: f32 = 0.5;
+/// How strongly demand follows the price: doubling the price divides demand by 2 to this power.
+const PRICE_ELASTICITY: f32 = 1.5;
+/// However cheap a pitch is, its demand never exceeds this multiple of the demand at the usual price.
+const MAX_PRICE_FACTOR: f32 = 2.;
+
+/// Nightly prices per unit of multiplicity that the player set for each pitch type.
+#[derive(Resource, Reflect, Clone, Debug, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct PitchPricing {
+	/// Prices in the order of [`ALL_PITCH_TYPES`].
+	prices: [u64; ALL_PITCH_TYPES.len()],
+}
+
+/// New campgrounds charge the usual prices.
+impl Default for PitchPricing {
+	fn default() -> Self {
+		let balance = Balance::default();
+		Self { prices: ALL_PITCH_TYPES.map(|kind| balance.pitch_prices(kind).nightly_price) }
+	}
+}
+
+impl PitchPricing {
+	/// The nightly price of the pitch type, per unit of multiplicity.
+	pub fn nightly_price(&self, kind: PitchType) -> u64 {
+		self.prices[ALL_PITCH_TYPES.iter().position(|other| *other == kind).unwrap_or_default()]
+	}
+
+	/// Changes the nightly price of the pitch type.
+	pub fn set_nightly_price(&mut self, kind: PitchType, price: u64) {
+		if let Some(index) = ALL_PITCH_TYPES.iter().position(|other| *other == kind) {
+			self.prices[index] = price;
+		}
+	}
+}
+
+/// The factors that make up the demand for a pitch. All of them are 1 for average demand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Demand {
+	pub season:    f32,
+	pub price:     f32,
+	pub rating:    f32,
+	/// Weather and all other modifiers.
+	pub modifiers: f32,
+}
+
+impl Demand {
+	/// The combined demand factor, which scales the chance that visitors arrive at a free pitch.
+	pub fn total(&self) -> f32 {
+		self.season * self.price * self.rating * self.modifiers
+	}
+}
+
+/// Everything that the demand for pitches depends on.
+#[derive(SystemParam)]
+pub struct DemandModel<'w> {
+	calendar:  Res<'w, Calendar>,
+	history:   Res<'w, DailyHistory>,
+	balance:   Res<'w, Balance>,
+	modifiers: Res<'w, Modifiers>,
+}
+
+impl DemandModel<'_> {
+	/// The demand for a pitch of the given type at the given nightly price per unit of multiplicity.
+	pub fn demand(&self, kind: PitchType, nightly_price: u64) -> Demand {
+		let usual_price = self.balance.pitch_prices(kind).nightly_price;
+		Demand {
+			season:    seasonal_demand(kind, self.calendar.season()),
+			price:     price_demand(nightly_price, usual_price),
+			rating:    0.5 + self.rating(),
+			modifiers: self.modifiers.factor(ModifierTarget::Demand),
+		}
+	}
+
+	/// The campground's rating between 0 and 1: the average satisfaction of the recent days on which visitors were
+	/// asked.
+	pub fn rating(&self) -> f32 {
+		let recent = self
+			.history
+			.0
+			.iter()
+			.rev()
+			.take(RATING_DAYS)
+			.filter_map(|summary| summary.average_satisfaction)
+			.collect::<Vec<_>>();
+		if recent.is_empty() {
+			DEFAULT_RATING
+		} else {
+			recent.iter().sum::<f32>() / recent.len() as f32
+		}
+	}
+
+	/// Whether anything that the demand depends on changed.
+	pub fn is_changed(&self) -> bool {
+		self.calendar.is_changed()
+			|| self.history.is_changed()
+			|| self.balance.is_changed()
+			|| self.modifiers.is_changed()
+	}
+}
+
+/// How much the segments that like the pitch type travel in the season, relative to their average season. Segments
+/// count by the [`weight`](super::visitor::Appeal::weight) of the pitch type to them.
+fn seasonal_demand(kind: PitchType, season: Season) -> f32 {
+	let weight = |segment: &VisitorSegment| segment.appeal(kind).weight();
+	let share_in = |season| {
+		ALL_VISITOR_SEGMENTS.iter().map(|segment| weight(segment) * segment.seasonal_share(season)).sum::<f32>()
+	};
+	let average = ALL_SEASONS.into_iter().map(share_in).sum::<f32>() / ALL_SEASONS.len() as f32;
+	if average > 0. {
+		share_in(season) / average
+	} else {
+		1.
+	}
+}
+
+fn price_demand(nightly_price: u64, usual_price: u64) -> f32 {
+	if nightly_price == 0 {
+		return MAX_PRICE_FACTOR;
+	}
+	(usual_price as f32 / nightly_price as f32).powf(PRICE_ELASTICITY).min(MAX_PRICE_FACTOR)
+}
+
+/// Pitches take over the price of their type when it is assigned, and whenever the player changes it.
+fn apply_pitch_prices(pricing: Res<PitchPricing>, mut pitches: Query<&mut Pitch>) {
+	for mut pitch in &mut pitches {
+		if !pricing.is_changed() && !pitch.is_changed() {
+			continue;
+		}
+		let price = pitch.kind.map_or(0, |kind| pricing.nightly_price(kind));
+		if pitch.nightly_price != price {
+			pitch.nightly_price = price;
+		}
+	}
+}
@@ -0,0 +1,550 @@
+//! Errands: visitors walking from their pitch to facilities such as toilets, showers and shops.
+//!
+//! While visitors stay at their pitch, their [`VisitorNeeds`] grow. Once a need is pressing, one of the visitors sets
+//! off on an [`Errand`]: they walk over the people [`NavMesh`] to the nearest facility that takes care of the need,
+//! spend some time there, and walk back to the pitch. Every facility that visitors run errands to has a
+//! [`FacilityUsage`] with a fixed number of places; visitors that find all places taken have to queue. Visitors rate
+//! every errand by how far they had to walk and how long they queued. Finding no toilets or showers at all is the worst
+//! rating, while visitors that find no shop simply eat at their pitch. The average rating lowers their satisfaction
+//! when they leave. What a visit means for the facility itself, such as the money spent in a shop, is up to the
+//! facility's own module, which learns about visits from [`FacilityVisited`] events.
+//!
+//! [`NavMesh`]: super::nav::NavMesh
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::HashSet;
+
+use super::area::ImmutableArea;
+use super::facility::{access_tile, Facility};
+use super::nav::{Path, Pedestrian, PeopleNavMesh};
+use super::pathfinding::{FoundPath, PeoplePathRequest};
+use super::rollup::DAY_LENGTH;
+use super::staff::{service_tile, tile_center};
+use super::vehicle::Vehicle;
+use super::{ActorPosition, GridBox, GridPosition, GroundMap, Pitch};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::ObjectPriority;
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct ErrandManagement;
+
+impl Plugin for ErrandManagement {
+	fn build(&self, app: &mut App) {
+		app.add_event::<FacilityVisited>()
+			.add_systems(
+				FixedUpdate,
+				(
+					add_facility_usage,
+					add_visitor_needs,
+					grow_needs,
+					start_errands,
+					use_facilities,
+					count_facility_traffic,
+				)
+					.chain()
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				walk_visitors.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(GameState::Loading), remove_errands)
+			.add_systems(OnEnter(GameState::MainMenu), remove_errands)
+			.add_world_info_section::<FacilityUsage>("Usage", |usage| {
+				vec![
+					DialogWidget::Property {
+						name:  "In use".into(),
+						value: format!("{} of {}", usage.users.len(), usage.places),
+					},
+					DialogWidget::Property { name: "Queueing".into(), value: usage.queue.len().to_string() },
+					DialogWidget::Property { name: "On the way".into(), value: usage.heading.to_string() },
+					DialogWidget::Property { name: "Visits".into(), value: usage.visits.to_string() },
+					DialogWidget::Property {
+						name:  "Average wait".into(),
+						value: format!("{:.0} s", usage.average_wait().as_secs_f32()),
+					},
+				]
+			});
+	}
+}
+
+/// Walks of up to this many tiles to a facility don't bother visitors.
+const COMFORTABLE_DISTANCE: usize = 15;
+/// How much every tile beyond the comfortable distance lowers the rating of an errand.
+const DISTANCE_PENALTY: f32 = 0.02;
+/// How much every second of queueing lowers the rating of an errand.
+const WAITING_PENALTY: f32 = 0.03;
+/// Rating of an errand on which the visitors found no toilets or showers they could walk to.
+const UNSERVED_RATING: f32 = 0.;
+
+/// The reasons for visitors to run an errand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Need {
+	Toilet,
+	Shower,
+	Snack,
+	Meal,
+}
+
+/// All needs, in the order they are taken care of when several are pressing.
+const ALL_NEEDS: [Need; 4] = [Need::Toilet, Need::Shower, Need::Snack, Need::Meal];
+
+impl Need {
+	/// The facility that takes care of the need.
+	pub const fn facility(self) -> Facility {
+		match self {
+			Self::Toilet => Facility::Toilets,
+			Self::Shower => Facility::Showers,
+			Self::Snack => Facility::Kiosk,
+			Self::Meal => Facility::Restaurant,
+		}
+	}
+
+	/// How long it takes the need to become pressing again after it was taken care of.
+	const fn interval(self) -> Duration {
+		match self {
+			Self::Toilet => Duration::from_secs(DAY_LENGTH.as_secs() / 4),
+			Self::Shower => Duration::from_secs(DAY_LENGTH.as_secs() * 2 / 3),
+			Self::Snack => Duration::from_secs(DAY_LENGTH.as_secs() / 3),
+			Self::Meal => Duration::from_secs(DAY_LENGTH.as_secs() / 2),
+		}
+	}
+
+	/// How long a visitor takes up a place in the facility.
+	const fn use_time(self) -> Duration {
+		match self {
+			Self::Toilet => Duration::from_secs(4),
+			Self::Shower => Duration::from_secs(12),
+			Self::Snack => Duration::from_secs(3),
+			Self::Meal => Duration::from_secs(20),
+		}
+	}
+
+	/// Whether visitors mind when no facility takes care of the need. Visitors bring their own food, but not their own
+	/// toilets.
+	const fn is_essential(self) -> bool {
+		matches!(self, Self::Toilet | Self::Shower)
+	}
+}
+
+/// Number of visitors that a facility takes at the same time, such as the cabins of toilets or the seats of a
+/// restaurant. [`None`] for facilities that visitors don't run errands to.
+const fn places(facility: Facility) -> Option<usize> {
+	match facility {
+		Facility::Toilets => Some(4),
+		Facility::Showers => Some(3),
+		Facility::Kiosk => Some(2),
+		Facility::Restaurant => Some(8),
+		Facility::Reception => None,
+	}
+}
+
+/// Sent when a visitor is done at a facility.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FacilityVisited {
+	pub facility: Entity,
+}
+
+/// The needs of the visitors of a vehicle, and how they liked their errands so far.
+#[derive(Component, Clone, Debug)]
+pub struct VisitorNeeds {
+	/// How pressing the needs are, from 0 (just taken care of) to 1 (someone sets off), in the order of
+	/// [`ALL_NEEDS`].
+	levels:       [f32; 4],
+	rating_total: f32,
+	errands:      u32,
+}
+
+impl Default for VisitorNeeds {
+	/// Visitors arrive with some needs already, so that not all of them set off at the same time.
+	fn default() -> Self {
+		Self { levels: ALL_NEEDS.map(|_| fastrand::f32() * 0.5), rating_total: 0., errands: 0 }
+	}
+}
+
+impl VisitorNeeds {
+	fn level(&self, need: Need) -> f32 {
+		self.levels[need as usize]
+	}
+
+	fn level_mut(&mut self, need: Need) -> &mut f32 {
+		&mut self.levels[need as usize]
+	}
+
+	fn rate_errand(&mut self, rating: f32) {
+		self.rating_total += rating;
+		self.errands += 1;
+	}
+
+	/// Records that nothing took care of the need.
+	fn miss(&mut self, need: Need) {
+		if need.is_essential() {
+			self.rate_errand(UNSERVED_RATING);
+		}
+	}
+
+	/// How satisfied the visitors are with their errands, between 0 and 1. Visitors that didn't need to go anywhere
+	/// yet are fully satisfied.
+	pub fn satisfaction(&self) -> f32 {
+		if self.errands == 0 {
+			1.
+		} else {
+			self.rating_total / self.errands as f32
+		}
+	}
+}
+
+/// How visitors rate an errand, between 0 and 1.
+fn errand_rating(distance: usize, waited: Duration) -> f32 {
+	let distance_penalty = (distance.saturating_sub(COMFORTABLE_DISTANCE) as f32 * DISTANCE_PENALTY).min(0.5);
+	let waiting_penalty = (waited.as_secs_f32() * WAITING_PENALTY).min(0.5);
+	1. - distance_penalty - waiting_penalty
+}
+
+/// The places of a facility and the visitors using them.
+#[derive(Component, Clone, Debug)]
+pub struct FacilityUsage {
+	places:     usize,
+	/// Visitors taking up the places.
+	users:      Vec<Entity>,
+	/// Visitors waiting for a free place, in the order they get one.
+	queue:      VecDeque<Entity>,
+	/// Visitors walking to the facility.
+	heading:    usize,
+	/// Visitors that used the facility since it was built or loaded.
+	visits:     u32,
+	/// How long all of these visitors queued, taken together.
+	total_wait: Duration,
+}
+
+impl FacilityUsage {
+	const fn new(places: usize) -> Self {
+		Self { places, users: Vec::new(), queue: VecDeque::new(), heading: 0, visits: 0, total_wait: Duration::ZERO }
+	}
+
+	/// Adds the visitor to the end of the queue.
+	fn join(&mut self, visitor: Entity) {
+		if !self.queue.contains(&visitor) {
+			self.queue.push_back(visitor);
+		}
+	}
+
+	/// Lets the visitor take a place if one is free and nobody is in front of them in the queue.
+	fn admit(&mut self, visitor: Entity) -> bool {
+		if self.users.len() >= self.places || self.queue.front() != Some(&visitor) {
+			return false;
+		}
+		self.queue.pop_front();
+		self.users.push(visitor);
+		true
+	}
+
+	fn finish(&mut self, visitor: Entity, waited: Duration) {
+		self.users.retain(|user| *user != visitor);
+		self.visits += 1;
+		self.total_wait += waited;
+	}
+
+	/// How long visitors queued on average.
+	pub fn average_wait(&self) -> Duration {
+		if self.visits == 0 {
+			Duration::ZERO
+		} else {
+			self.total_wait / self.visits
+		}
+	}
+}
+
+/// A visitor on the way to a facility, in it, or on the way back to their pitch. Errands aren't saved.
+#[derive(Component, Debug)]
+pub struct Errand {
+	/// The vehicle of the visitor's group.
+	vehicle:    Entity,
+	need:       Need,
+	/// The tile next to the pitch that the visitor set off from and returns to.
+	home:       GridPosition,
+	stage:      ErrandStage,
+	/// Remaining tiles to walk through.
+	path:       Path,
+	/// While planning, the facilities that the visitor looks for a way to, with the tile to use each one from.
+	candidates: Vec<(Entity, GridPosition)>,
+	/// Length of the way to the facility.
+	distance:   usize,
+	/// How long the visitor has been queueing.
+	waited:     Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrandStage {
+	/// Waiting for the way to the nearest facility.
+	Planning,
+	/// Walking to the facility.
+	Walking { building: Entity },
+	/// Waiting in the facility's queue for a free place.
+	Queueing { building: Entity },
+	/// Taking up a place in the facility for the remaining time.
+	Using { building: Entity, remaining: Duration },
+	/// Waiting for the way back to the pitch.
+	PlanningReturn,
+	/// Walking back to the pitch, where the visitor disappears at the end of the path.
+	Returning,
+}
+
+/// There are no visitor graphics yet, so visitors are drawn as colored rectangles like staff.
+fn visitor_sprite() -> Sprite {
+	Sprite { anchor: Anchor::BottomCenter, ..Sprite::from_color(Color::srgb(0.85, 0.35, 0.55), Vec2::new(3., 6.)) }
+}
+
+fn add_facility_usage(facilities: Query<(Entity, &Facility), Without<FacilityUsage>>, mut commands: Commands) {
+	for (entity, facility) in &facilities {
+		if let Some(places) = places(*facility) {
+			commands.entity(entity).insert(FacilityUsage::new(places));
+		}
+	}
+}
+
+fn add_visitor_needs(vehicles: Query<Entity, (With<Vehicle>, Without<VisitorNeeds>)>, mut commands: Commands) {
+	for vehicle in &vehicles {
+		commands.entity(vehicle).insert(VisitorNeeds::default());
+	}
+}
+
+/// Needs grow while visitors stay at their pitch. Once one is pressing, a visitor sets off to the nearest facility
+/// that takes care of it. Finding the way there takes a while, so this only requests the path, and [`start_errands`]
+/// picks up the result.
+fn grow_needs(
+	time: Res<Time>,
+	mesh: Res<PeopleNavMesh>,
+	pitches: Query<&ImmutableArea, With<Pitch>>,
+	buildings: Query<(Entity, &Facility, &GridBox), With<FacilityUsage>>,
+	errands: Query<&Errand>,
+	mut vehicles: Query<(Entity, &Vehicle, &mut VisitorNeeds)>,
+	mut commands: Commands,
+) {
+	let travelling = errands.iter().map(|errand| errand.vehicle).collect::<HashSet<_>>();
+	for (entity, vehicle, mut needs) in &mut vehicles {
+		if !vehicle.is_parked() {
+			continue;
+		}
+		for need in ALL_NEEDS {
+			let level = needs.level_mut(need);
+			*level = (*level + time.delta_secs() / need.interval().as_secs_f32()).min(1.);
+		}
+		// Only one visitor of a group is out at a time.
+		if travelling.contains(&entity) {
+			continue;
+		}
+		let Some(need) = ALL_NEEDS.into_iter().find(|need| needs.level(*need) >= 1.) else {
+			continue;
+		};
+		*needs.level_mut(need) = 0.;
+
+		let Some(home) = pitches.get(vehicle.pitch).ok().and_then(|area| service_tile(area, &mesh)) else {
+			needs.miss(need);
+			continue;
+		};
+		let mut candidates = buildings
+			.iter()
+			.filter(|(_, facility, _)| **facility == need.facility())
+			.filter_map(|(building, _, grid_box)| Some((building, access_tile(grid_box, &*mesh)?)))
+			.collect::<Vec<_>>();
+		if candidates.is_empty() {
+			needs.miss(need);
+			continue;
+		}
+		candidates.sort_by_key(|(_, tile)| (tile.0 - home.0).abs().element_sum());
+
+		let destinations = candidates.iter().map(|(_, tile)| *tile).collect::<Vec<_>>();
+		commands.spawn((
+			Errand {
+				vehicle: entity,
+				need,
+				home,
+				stage: ErrandStage::Planning,
+				path: Path::default(),
+				candidates,
+				distance: 0,
+				waited: Duration::ZERO,
+			},
+			PeoplePathRequest::new(home, destinations),
+			ActorPosition(tile_center(home)),
+			ObjectPriority::Normal,
+			visitor_sprite(),
+		));
+	}
+}
+
+/// Starts walking along the paths found for planning visitors.
+fn start_errands(
+	mut errands: Query<(Entity, &mut Errand, &mut FoundPath)>,
+	mut needs: Query<&mut VisitorNeeds>,
+	mut commands: Commands,
+) {
+	for (entity, mut errand, mut found_path) in &mut errands {
+		commands.entity(entity).remove::<FoundPath>();
+		let path = found_path.0.take();
+		match errand.stage {
+			ErrandStage::Planning => {
+				let candidates = std::mem::take(&mut errand.candidates);
+				let route = path.and_then(|path| {
+					let (building, _) = candidates.into_iter().find(|(_, tile)| path.end() == Some(tile))?;
+					Some((building, path))
+				});
+				let Some((building, path)) = route else {
+					// None of the facilities can be reached on foot, so the visitor stays at the pitch.
+					if let Ok(mut needs) = needs.get_mut(errand.vehicle) {
+						needs.miss(errand.need);
+					}
+					commands.entity(entity).despawn_recursive();
+					continue;
+				};
+				errand.distance = path.len();
+				errand.path = path;
+				errand.stage = ErrandStage::Walking { building };
+			},
+			ErrandStage::PlanningReturn => match path {
+				Some(path) => {
+					errand.path = path;
+					errand.stage = ErrandStage::Returning;
+				},
+				None => commands.entity(entity).despawn_recursive(),
+			},
+			_ => {},
+		}
+	}
+}
+
+/// Lets queueing visitors into free places and sends visitors home once they are done. Visitors whose vehicle left
+/// go with it.
+fn use_facilities(
+	time: Res<Time>,
+	vehicles: Query<&Vehicle>,
+	mut buildings: Query<&mut FacilityUsage>,
+	mut errands: Query<(Entity, &mut Errand, &ActorPosition)>,
+	mut needs: Query<&mut VisitorNeeds>,
+	mut visits: EventWriter<FacilityVisited>,
+	mut commands: Commands,
+) {
+	for mut usage in &mut buildings {
+		usage.users.retain(|visitor| errands.contains(*visitor));
+		usage.queue.retain(|visitor| errands.contains(*visitor));
+	}
+
+	for (entity, mut errand, position) in &mut errands {
+		if !vehicles.get(errand.vehicle).is_ok_and(Vehicle::is_parked) {
+			commands.entity(entity).despawn_recursive();
+			continue;
+		}
+		let rating = match errand.stage {
+			ErrandStage::Queueing { building } => match buildings.get_mut(building) {
+				Ok(mut usage) => {
+					errand.waited += time.delta();
+					if usage.admit(entity) {
+						errand.stage = ErrandStage::Using { building, remaining: errand.need.use_time() };
+					}
+					continue;
+				},
+				// The facility was demolished in front of the visitor.
+				Err(_) => None,
+			},
+			ErrandStage::Using { building, remaining } => {
+				let remaining = remaining.saturating_sub(time.delta());
+				if !remaining.is_zero() {
+					errand.stage = ErrandStage::Using { building, remaining };
+					continue;
+				}
+				if let Ok(mut usage) = buildings.get_mut(building) {
+					usage.finish(entity, errand.waited);
+					visits.send(FacilityVisited { facility: building });
+				}
+				Some(errand_rating(errand.distance, errand.waited))
+			},
+			_ => continue,
+		};
+		if let Ok(mut needs) = needs.get_mut(errand.vehicle) {
+			match rating {
+				Some(rating) => needs.rate_errand(rating),
+				None => needs.miss(errand.need),
+			}
+		}
+		commands.entity(entity).insert(PeoplePathRequest::new(position.tile(), [errand.home]));
+		errand.stage = ErrandStage::PlanningReturn;
+	}
+}
+
+fn count_facility_traffic(errands: Query<&Errand>, mut buildings: Query<&mut FacilityUsage>) {
+	for mut usage in &mut buildings {
+		usage.heading = 0;
+	}
+	for errand in &errands {
+		if let ErrandStage::Walking { building } = errand.stage {
+			if let Ok(mut usage) = buildings.get_mut(building) {
+				usage.heading += 1;
+			}
+		}
+	}
+}
+
+fn walk_visitors(
+	time: Res<Time>,
+	ground_map: Res<GroundMap>,
+	mesh: Res<PeopleNavMesh>,
+	mut buildings: Query<&mut FacilityUsage>,
+	mut errands: Query<(Entity, &mut Errand, &mut ActorPosition)>,
+	mut needs: Query<&mut VisitorNeeds>,
+	mut commands: Commands,
+) {
+	for (entity, mut errand, mut position) in &mut errands {
+		let building = match errand.stage {
+			ErrandStage::Walking { building } => Some(building),
+			ErrandStage::Returning => None,
+			_ => continue,
+		};
+		let Some(target) = errand.path.start().copied() else {
+			match building {
+				Some(building) => {
+					if let Ok(mut usage) = buildings.get_mut(building) {
+						usage.join(entity);
+					}
+					errand.stage = ErrandStage::Queueing { building };
+				},
+				None => commands.entity(entity).despawn_recursive(),
+			}
+			continue;
+		};
+		// The way was built over, so the visitor gives up.
+		if !mesh.contains(target) {
+			if building.is_some() {
+				if let Ok(mut needs) = needs.get_mut(errand.vehicle) {
+					needs.miss(errand.need);
+				}
+			}
+			commands.entity(entity).despawn_recursive();
+			continue;
+		}
+
+		let speed = ground_map.kind_of(&position.tile()).map_or(1, |kind| kind.traversal_speed()) as f32
+			* Pedestrian::Visitor.speed_factor();
+		let step = speed * time.delta_secs();
+		let target_position = tile_center(target);
+		let offset = target_position - position.0;
+		if offset.length() <= step {
+			position.0 = target_position;
+			errand.path.pop_start();
+		} else {
+			position.0 += offset.normalize() * step;
+		}
+	}
+}
+
+fn remove_errands(errands: Query<Entity, With<Errand>>, mut commands: Commands) {
+	for errand in &errands {
+		commands.entity(errand).despawn_recursive();
+	}
+}
@@ -0,0 +1,121 @@
+//! Facilities: buildings that serve visitors, such as the reception, toilets or shops.
+//!
+//! Like decorations, facilities take up the tiles of their [`GridBox`], which nobody can walk through. Visitors use a
+//! facility from its access tile, a nav mesh tile next to the facility. What a facility does for its visitors is
+//! implemented by the facility's own module, such as [`super::reception`]. Facilities
+//! that visitors walk to from their pitch are visited on [`super::errand`]s.
+
+use bevy::prelude::*;
+use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
+
+use super::nav::{NavCategoryParam, NavMesh};
+use super::{BoundingBox, GridBox, GridPosition};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, image_for_facility};
+use crate::graphics::ObjectPriority;
+use crate::ui::world_info::WorldInfoProperties;
+use crate::util::Tooltipable;
+
+pub struct FacilityManagement;
+
+impl Plugin for FacilityManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Facility>()
+			.add_systems(Update, add_facility_graphics.in_set(GameSet::Graphics).in_set(GameState::InGame));
+	}
+}
+
+/// A building that serves visitors.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum Facility {
+	/// Where visitors check in and out. Visitors only come to campgrounds with a reception.
+	Reception,
+	/// Toilet cabins that visitors walk to from their pitch.
+	Toilets,
+	/// Shower cabins that visitors walk to from their pitch.
+	Showers,
+	/// A small shop that sells snacks and drinks.
+	Kiosk,
+	/// Serves meals to visitors.
+	Restaurant,
+}
+
+/// All facilities, in the order they appear in the build menu.
+pub const ALL_FACILITIES: [Facility; 5] =
+	[Facility::Reception, Facility::Toilets, Facility::Showers, Facility::Kiosk, Facility::Restaurant];
+
+impl Facility {
+	pub const fn size(&self) -> BoundingBox {
+		match self {
+			Self::Reception | Self::Toilets | Self::Showers | Self::Kiosk | Self::Restaurant =>
+				BoundingBox::fixed::<2, 3, 2>(),
+		}
+	}
+}
+
+impl std::fmt::Display for Facility {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Reception => "Reception",
+			Self::Toilets => "Toilets",
+			Self::Showers => "Shower block",
+			Self::Kiosk => "Kiosk",
+			Self::Restaurant => "Restaurant",
+		})
+	}
+}
+
+impl Tooltipable for Facility {
+	fn description(&self) -> &'static str {
+		match self {
+			Self::Reception =>
+				"The office where visitors check in when they arrive and check out when they leave. Your campground \
+				 only opens once it has a reception next to a pathway that vehicles can reach.",
+			Self::Toilets =>
+				"A block of toilet cabins. Visitors regularly walk from their pitch to the nearest toilets, and they \
+				 don’t like long walks or queueing in front of busy toilets.",
+			Self::Showers =>
+				"A block of shower cabins. Visitors walk from their pitch to the nearest showers about once a day. \
+				 Showers take a while, so busy shower blocks soon have a queue.",
+			Self::Kiosk =>
+				"A small shop where visitors buy snacks and drinks. Every visitor pays for what they buy, and the \
+				 kiosk costs a daily fee to run.",
+			Self::Restaurant =>
+				"Serves meals to visitors, who pay well for them. Restaurants are expensive to run every day, so they \
+				 need a busy campground to make a profit.",
+		}
+	}
+}
+
+/// Spawns a new facility on the tiles. Its graphics are added separately, so that loaded facilities receive them as
+/// well.
+pub fn spawn_facility(facility: Facility, grid_box: GridBox, commands: &mut Commands) {
+	commands.spawn((facility, grid_box, Save));
+}
+
+/// The nav mesh tile next to the facility that visitors use it from, if there is any.
+pub fn access_tile<const N: NavCategoryParam>(grid_box: &GridBox, mesh: &NavMesh<N>) -> Option<GridPosition> {
+	let tiles = grid_box.floor_positions().collect::<Vec<_>>();
+	tiles
+		.iter()
+		.flat_map(GridPosition::neighbors)
+		.filter(|neighbor| !tiles.contains(neighbor) && mesh.contains(*neighbor))
+		.min_by_key(|tile| (tile.x, tile.y, tile.z))
+}
+
+fn add_facility_graphics(
+	facilities: Query<(Entity, &Facility), Without<Sprite>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (entity, facility) in &facilities {
+		let image = image_for_facility(*facility);
+		commands.entity(entity).insert((
+			Sprite { anchor: anchor_for_image(image), image: asset_server.load(image), ..default() },
+			ObjectPriority::Normal,
+			WorldInfoProperties::basic(facility.to_string(), facility.description().to_string()),
+		));
+	}
+}
@@ -0,0 +1,275 @@
+//! Fences and other objects that are placed on tile edges instead of on tiles.
+//!
+//! Edge objects are built by dragging from one tile corner to another. The drag is split into two straight runs, the
+//! longer one first, so that the corner between them is picked automatically. Every unit of the path then lies on
+//! exactly one tile side: runs along x use the bottom side of the tiles above them, runs along y use the left side of
+//! the tiles to their right. This is the same [`Sides`] representation that area borders use, so edge objects reuse
+//! the border sprites.
+//!
+//! Fences block movement across their sides: the fenced sides of a tile are removed from the exits of its
+//! [`NavComponent`], which disconnects the tile from its neighbor on that side in all nav meshes. [`Gate`]s are
+//! openings in a fence run that everyone can pass through.
+//!
+//! Fences come in several [`FenceStyle`]s, which only differ in their looks. A tile can have fences of different styles
+//! on its sides; building a fence of one style over a fence of another style restyles that part of the fence, which is
+//! how the player restyles the perimeter of their campground.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::nav::NavComponent;
+use super::tile::update_navigability_properties;
+use super::{GridPosition, GroundKind, GroundMap};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{BorderKind, BorderSprite, BorderTextures, Sides};
+
+pub struct FenceManagement;
+
+impl Plugin for FenceManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Fence>()
+			.register_type::<FenceStyle>()
+			.register_type::<Gate>()
+			.add_systems(
+				FixedUpdate,
+				block_fenced_exits
+					.after(update_navigability_properties)
+					.in_set(GameSet::SimulationEarly)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				PostUpdate,
+				(update_edge_sprites::<Fence>, update_edge_sprites::<Gate>)
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// An object that occupies some of the sides of the tile at its position.
+pub trait EdgeObject: Component {
+	/// The border sprites that show the object.
+	const BORDER_KIND: BorderKind;
+
+	/// The same object on other sides.
+	fn with_sides(&self, sides: Sides) -> Self;
+	fn sides(&self) -> Sides;
+	fn set_sides(&mut self, sides: Sides);
+
+	/// Whether the other object looks the same, so that both can be merged into one object on a tile.
+	fn is_alike(&self, _other: &Self) -> bool {
+		true
+	}
+
+	/// Tint of the object's border sprites.
+	fn tint(&self) -> Color {
+		Color::WHITE
+	}
+}
+
+/// The looks of a [`Fence`].
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FenceStyle {
+	#[default]
+	Wooden,
+	Hedge,
+	StoneWall,
+}
+
+/// All fence styles, in build menu order.
+pub const ALL_FENCE_STYLES: [FenceStyle; 3] = [FenceStyle::Wooden, FenceStyle::Hedge, FenceStyle::StoneWall];
+
+impl FenceStyle {
+	/// There are no dedicated sprites for the styles yet, so they tint the fence sprite.
+	pub const fn tint(self) -> Color {
+		match self {
+			Self::Wooden => Color::WHITE,
+			Self::Hedge => Color::srgb(0.45, 0.75, 0.35),
+			Self::StoneWall => Color::srgb(0.7, 0.7, 0.72),
+		}
+	}
+
+	pub const fn description(self) -> &'static str {
+		match self {
+			Self::Wooden =>
+				"Fences run along the edges of tiles and block everyone from passing. Drag to build a fence run with \
+				 up to one corner.",
+			Self::Hedge =>
+				"A hedge blocks everyone from passing just like a fence, but looks more natural. Building it over \
+				 another fence replaces that part of the fence.",
+			Self::StoneWall =>
+				"A stone wall blocks everyone from passing just like a fence, and makes for a sturdy perimeter. \
+				 Building it over another fence replaces that part of the fence.",
+		}
+	}
+}
+
+impl std::fmt::Display for FenceStyle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Wooden => "Fence",
+			Self::Hedge => "Hedge",
+			Self::StoneWall => "Stone Wall",
+		})
+	}
+}
+
+/// A fence on some of the sides of the tile at the same position.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Fence {
+	/// All sides of the tile that are fenced off.
+	pub sides: Sides,
+	/// Missing from older saves, whose fences are all wooden.
+	#[reflect(default)]
+	pub style: FenceStyle,
+}
+
+impl EdgeObject for Fence {
+	const BORDER_KIND: BorderKind = BorderKind::Fence;
+
+	fn with_sides(&self, sides: Sides) -> Self {
+		Self { sides, style: self.style }
+	}
+
+	fn sides(&self) -> Sides {
+		self.sides
+	}
+
+	fn set_sides(&mut self, sides: Sides) {
+		self.sides = sides;
+	}
+
+	fn is_alike(&self, other: &Self) -> bool {
+		self.style == other.style
+	}
+
+	fn tint(&self) -> Color {
+		self.style.tint()
+	}
+}
+
+/// A gate on some of the sides of the tile at the same position. Gates replace fences on their sides, and don't block
+/// movement.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Gate {
+	/// All sides of the tile that have a gate.
+	pub sides: Sides,
+}
+
+impl EdgeObject for Gate {
+	const BORDER_KIND: BorderKind = BorderKind::Gate;
+
+	fn with_sides(&self, sides: Sides) -> Self {
+		Self { sides }
+	}
+
+	fn sides(&self) -> Sides {
+		self.sides
+	}
+
+	fn set_sides(&mut self, sides: Sides) {
+		self.sides = sides;
+	}
+}
+
+/// Computes the tile sides along an edge drag between the corners of two tiles. Each returned side is a single side;
+/// corner tiles appear once per side. A drag without any extent still yields the bottom side of its tile, so that the
+/// player can see what a click would build.
+pub fn edge_path(start: GridPosition, end: GridPosition) -> Vec<(GridPosition, Sides)> {
+	let delta = *(end - start);
+	if delta.x == 0 && delta.y == 0 {
+		return vec![(start, Sides::Bottom)];
+	}
+
+	let mut path = Vec::with_capacity((delta.x.abs() + delta.y.abs()) as usize);
+	let mut corner = start;
+	let mut walk = |corner: &mut GridPosition, step: IVec3, side: Sides, count: i32| {
+		for _ in 0 .. count {
+			// Moving in the negative direction ends up on the corner of the tile whose side we're walking along.
+			if step.x < 0 || step.y < 0 {
+				*corner = *corner + step;
+				path.push((*corner, side));
+			} else {
+				path.push((*corner, side));
+				*corner = *corner + step;
+			}
+		}
+	};
+	let x_run = (IVec3::X * delta.x.signum(), Sides::Bottom, delta.x.abs());
+	let y_run = (IVec3::Y * delta.y.signum(), Sides::Left, delta.y.abs());
+	let (first, second) = if delta.x.abs() >= delta.y.abs() { (x_run, y_run) } else { (y_run, x_run) };
+	walk(&mut corner, first.0, first.1, first.2);
+	walk(&mut corner, second.0, second.1, second.2);
+	path
+}
+
+/// Removes the fenced sides of tiles from their nav mesh exits. Tiles are updated when fences on them change or are
+/// removed, and when their ground changes, since that resets their exits.
+fn block_fenced_exits(
+	fences: Query<(&GridPosition, &Fence)>,
+	changed_fences: Query<(Entity, &GridPosition), Changed<Fence>>,
+	mut removed_fences: RemovedComponents<Fence>,
+	// Removed fences don't have a position anymore.
+	mut fence_positions: Local<HashMap<Entity, GridPosition>>,
+	// Finding new vertices reads them, so the changed tiles and the vertices can't be queried at the same time.
+	mut tiles: ParamSet<(
+		Query<&GridPosition, (With<GroundKind>, Or<(Changed<GroundKind>, Added<NavComponent>)>)>,
+		Query<&mut NavComponent>,
+	)>,
+	ground_map: Res<GroundMap>,
+) {
+	let mut changed_positions = Vec::new();
+	for (entity, position) in &changed_fences {
+		fence_positions.insert(entity, *position);
+		changed_positions.push(*position);
+	}
+	changed_positions.extend(removed_fences.read().filter_map(|entity| fence_positions.remove(&entity)));
+	changed_positions.extend(tiles.p0().iter().copied());
+	if changed_positions.is_empty() {
+		return;
+	}
+
+	let mut fenced_sides = HashMap::<GridPosition, Sides>::new();
+	for (position, fence) in &fences {
+		*fenced_sides.entry(*position).or_insert(fence.sides) |= fence.sides;
+	}
+	let mut vertices = tiles.p1();
+	for position in changed_positions {
+		let Some(mut vertex) = ground_map.get(&position).and_then(|(tile, _)| vertices.get_mut(tile).ok()) else {
+			continue;
+		};
+		let fenced = fenced_sides.get(&position).copied().unwrap_or(Sides::empty());
+		let exits = Sides::all() ^ fenced;
+		if vertex.exits != exits {
+			vertex.exits = exits;
+		}
+	}
+}
+
+/// Edge object sprites are regenerated from the occupied sides whenever they change, which includes loading.
+fn update_edge_sprites<T: EdgeObject>(
+	objects: Query<(Entity, &T), Changed<T>>,
+	asset_server: Res<AssetServer>,
+	mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+	mut border_textures: ResMut<BorderTextures>,
+	mut commands: Commands,
+) {
+	for (entity, object) in &objects {
+		let borders = BorderSprite::new(
+			object.sides(),
+			T::BORDER_KIND,
+			&asset_server,
+			&mut texture_atlases,
+			&mut border_textures,
+		);
+		commands.entity(entity).despawn_descendants().with_children(|parent| {
+			for mut border in borders {
+				border.sprite.color = object.tint();
+				parent.spawn(border);
+			}
+		});
+	}
+}
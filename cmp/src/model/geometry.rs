@@ -29,6 +29,12 @@ impl ActorPosition {
 	pub fn round(self) -> GridPosition {
 		GridPosition(self.0.round().as_ivec3())
 	}
+
+	/// The tile that contains the position.
+	#[inline]
+	pub fn tile(self) -> GridPosition {
+		GridPosition(self.0.floor().as_ivec3())
+	}
 }
 
 impl WorldPosition for ActorPosition {
@@ -75,61 +81,7 @@ impl GridPosition {
 	/// Returns all grid positions on the straight line to `target`, effectively performing line rasterization.
 	/// FIXME: Respect the Z dimension; all positions will currently inherit the source's z height.
 	pub fn line_to_2d(self, target: Self) -> impl Iterator<Item = Self> {
-		// Relying on extra unstable features is fun! <https://rust-lang.github.io/rfcs/2033-experimental-coroutines.html>
-		std::iter::from_coroutine(
-			#[coroutine]
-			move || {
-				// Bresenham's algorithm
-				if (self.y - target.y).abs() < (self.x - target.x).abs() {
-					// non-steep slopes
-					let lower = *if self.x < target.x { self } else { target };
-					let upper = *if self.x >= target.x { self } else { target };
-
-					let dx = upper.x - lower.x;
-					let mut dy = upper.y - lower.y;
-
-					let mut y_dir = 1;
-					if dy < 0 {
-						y_dir = -1;
-						dy = -dy;
-					}
-					let mut d = (2 * dy) - dx;
-					let mut y = lower.y;
-					for x in lower.x ..= upper.x {
-						yield (x, y, self.z).into();
-						if d > 0 {
-							y += y_dir;
-							d += 2 * (dy - dx);
-						} else {
-							d += 2 * dy;
-						}
-					}
-				} else {
-					// steep slopes: iterate on y instead of x
-					let lower = *if self.y < target.y { self } else { target };
-					let upper = *if self.y >= target.y { self } else { target };
-
-					let mut dx = upper.x - lower.x;
-					let dy = upper.y - lower.y;
-					let mut x_dir = 1;
-					if dx < 0 {
-						x_dir = -1;
-						dx = -dx;
-					}
-					let mut d = (2 * dx) - dy;
-					let mut x = lower.x;
-					for y in lower.y ..= upper.y {
-						yield (x, y, self.z).into();
-						if d > 0 {
-							x += x_dir;
-							d += 2 * (dx - dy);
-						} else {
-							d += 2 * dx;
-						}
-					}
-				}
-			},
-		)
+		super::line::line_2d(self, target)
 	}
 
 	pub fn neighbors(&self) -> [GridPosition; 4] {
@@ -300,7 +252,6 @@ pub struct BoundingBox(pub UVec3);
 
 impl BoundingBox {
 	#[inline]
-	#[allow(unused)]
 	pub const fn height(&self) -> u32 {
 		self.0.z
 	}
@@ -388,6 +339,11 @@ impl From<GridPosition> for GridBox {
 	}
 }
 
+/// Whether the inclusive ranges of two boxes along one axis overlap.
+const fn axis_intersects(own_start: i32, own_end: i32, other_start: i32, other_end: i32) -> bool {
+	own_start <= other_end && other_start <= own_end
+}
+
 pub trait Extent {
 	fn as_ivec3(&self) -> IVec3;
 }
@@ -448,7 +404,6 @@ impl GridBox {
 	}
 
 	#[inline]
-	#[allow(unused)]
 	pub fn center(&self) -> GridPosition {
 		self.corner + (self.extents / 2).as_ivec3()
 	}
@@ -493,36 +448,16 @@ impl GridBox {
 	/// This is a lower-level API used by various high-level collision functions.
 	#[allow(unused)]
 	pub fn intersects(&self, other: GridBox) -> bool {
-		let axis_intersects = |own_start, own_end, other_start, other_end| {
-			// Either of our points is between the other’s start and end.
-			(own_start < other_end && own_start >= other_start) || (own_end < other_end && own_end >= other_start)
-		};
-
-		let own_start = self.corner;
-		let own_end = self.largest();
-		let other_start = other.corner;
-		let other_end = other.largest();
-
-		axis_intersects(own_start.x, own_end.x, other_start.x, other_end.x)
-			&& axis_intersects(own_start.y, own_end.y, other_start.y, other_end.y)
-			&& axis_intersects(own_start.z, own_end.z, other_start.z, other_end.z)
+		self.intersects_2d(other)
+			&& axis_intersects(self.smallest().z, self.largest().z, other.smallest().z, other.largest().z)
 	}
 
 	/// Returns whether the other box object intersects this box object on the xy plane.
 	///
 	/// This is a lower-level API used by various high-level collision functions.
-	#[allow(unused)]
 	pub fn intersects_2d(&self, other: GridBox) -> bool {
-		let axis_intersects = |own_start, own_end, other_start, other_end| {
-			// Either of our points is between the other’s start and end.
-			(own_start < other_end && own_start >= other_start) || (own_end < other_end && own_end >= other_start)
-		};
-
-		let own_start = self.corner;
-		let own_end = own_start + self.extents.as_ivec3();
-		let other_start = other.corner;
-		let other_end = other_start + other.extents.as_ivec3();
-
+		let (own_start, own_end) = (self.smallest(), self.largest());
+		let (other_start, other_end) = (other.smallest(), other.largest());
 		axis_intersects(own_start.x, own_end.x, other_start.x, other_end.x)
 			&& axis_intersects(own_start.y, own_end.y, other_start.y, other_end.y)
 	}
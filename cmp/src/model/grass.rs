@@ -0,0 +1,137 @@
+//! Grass health: grass wears down where many people walk and dries out in sunny weather.
+//!
+//! Every simulation step, grass tiles lose health in proportion to their [`FootTraffic`], while all worn grass slowly
+//! grows back, so that the shortcuts that people take become trampled paths. The [`Weather`] acts on all grass at
+//! once: every sunny day dries the ground out a bit more, and rain soaks it again. Unhealthy grass is tinted brown
+//! while no overlay is shown, and the grass health overlay shows the health of every grass tile.
+
+use std::time::Duration;
+
+use bevy::color::palettes::css::WHITE;
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::rollup::DAY_LENGTH;
+use super::traffic::FootTraffic;
+use super::weather::Weather;
+use super::{GridPosition, GroundKind, GroundMap};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{Overlay, OverlayLayer, OverlayLayerExt, OverlaySystems};
+
+pub struct GrassManagement;
+
+impl Plugin for GrassManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<GrassHealth>()
+			.add_systems(FixedUpdate, update_grass_health.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(OnEnter(GameState::Loading), reset_grass_health)
+			.add_systems(OnEnter(GameState::MainMenu), reset_grass_health)
+			.add_systems(
+				PostUpdate,
+				tint_grass.after(OverlaySystems::Apply).in_set(GameSet::Graphics).in_set(GameState::InGame),
+			)
+			.add_overlay_layer::<GrassHealthLayer>();
+	}
+}
+
+/// Wear that every step on a tile causes per second; a tile with 20 recent steps is worn down in about a day.
+const WEAR_PER_STEP: f32 = 1. / 6000.;
+/// Time that completely worn grass takes to grow back.
+const REGROWTH_TIME: Duration = Duration::from_secs(2 * DAY_LENGTH.as_secs());
+/// Time that sunny weather takes to dry out the ground completely.
+const DRYING_TIME: Duration = Duration::from_secs(3 * DAY_LENGTH.as_secs());
+/// Time that rain takes to soak the completely dry ground.
+const SOAKING_TIME: Duration = Duration::from_secs(DAY_LENGTH.as_secs() / 4);
+/// How much of its health the grass loses when the ground is completely dry.
+const DROUGHT_DAMAGE: f32 = 0.4;
+/// Tint of bare soil; healthier grass is tinted less.
+const WORN_TINT: Srgba = Srgba::rgb(0.75, 0.6, 0.35);
+/// Number of distinct tints, so that the ground isn't recolored for every tiny change in health.
+const TINT_STEPS: f32 = 8.;
+
+/// How worn and dry the grass is.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct GrassHealth {
+	/// Wear of grass tiles that aren't fully healthy, between 0 for untouched and 1 for bare soil.
+	wear:    HashMap<GridPosition, f32>,
+	/// Dryness of the ground everywhere, between 0 for soaked and 1 for parched.
+	dryness: f32,
+}
+
+impl GrassHealth {
+	/// Health of the grass on the tile, between 0 for bare soil and 1 for lush grass.
+	pub fn of(&self, tile: &GridPosition) -> f32 {
+		(1. - self.wear.get(tile).copied().unwrap_or(0.)) * (1. - self.dryness * DROUGHT_DAMAGE)
+	}
+}
+
+fn update_grass_health(
+	time: Res<Time>,
+	traffic: Res<FootTraffic>,
+	weather: Res<Weather>,
+	ground_map: Res<GroundMap>,
+	mut health: ResMut<GrassHealth>,
+) {
+	let delta = time.delta_secs();
+	let regrowth = delta / REGROWTH_TIME.as_secs_f32();
+	health.wear.retain(|tile, wear| {
+		*wear -= regrowth;
+		*wear > 0. && ground_map.kind_of(tile) == Some(GroundKind::Grass)
+	});
+	for (tile, steps) in traffic.iter().filter(|(tile, _)| ground_map.kind_of(tile) == Some(GroundKind::Grass)) {
+		let wear = health.wear.entry(tile).or_default();
+		*wear = (*wear + steps * WEAR_PER_STEP * delta).min(1.);
+	}
+
+	let drying = match *weather {
+		Weather::Sunny => delta / DRYING_TIME.as_secs_f32(),
+		Weather::Rain | Weather::Storm => -delta / SOAKING_TIME.as_secs_f32(),
+	};
+	health.dryness = (health.dryness + drying).clamp(0., 1.);
+}
+
+fn reset_grass_health(mut health: ResMut<GrassHealth>) {
+	*health = GrassHealth::default();
+}
+
+/// Ground chunks are rebuilt whenever a tile's color changes, so colors are only assigned when they differ. Overlays
+/// color the ground themselves.
+fn tint_grass(
+	overlay: Res<Overlay>,
+	health: Res<GrassHealth>,
+	mut tiles: Query<(&GridPosition, &GroundKind, &mut Sprite)>,
+) {
+	if overlay.is_active() || (!overlay.is_changed() && !health.is_changed()) {
+		return;
+	}
+	for (position, kind, mut sprite) in &mut tiles {
+		let color = if *kind == GroundKind::Grass {
+			let health = (health.of(position) * TINT_STEPS).round() / TINT_STEPS;
+			WORN_TINT.mix(&WHITE, health).into()
+		} else {
+			Color::WHITE
+		};
+		if sprite.color != color {
+			sprite.color = color;
+		}
+	}
+}
+
+/// How healthy the grass is. Tiles without grass have no value.
+pub struct GrassHealthLayer;
+
+impl OverlayLayer for GrassHealthLayer {
+	type Param = (Res<'static, GroundMap>, Res<'static, GrassHealth>);
+
+	const LEGEND: [&'static str; 2] = ["Worn", "Healthy"];
+	const NAME: &'static str = "Grass health";
+
+	fn values((ground_map, health): SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		ground_map
+			.iter()
+			.filter(|(.., kind)| *kind == GroundKind::Grass)
+			.map(|(tile, ..)| (tile, health.of(&tile)))
+			.collect()
+	}
+}
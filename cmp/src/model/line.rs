@@ -0,0 +1,15 @@
+//! Line rasterization on the grid, see [`super::GridPosition::line_to_2d`].
+//!
+//! On nightly, the rasterization is a coroutine. Stable compilers can't even parse coroutines, so that implementation
+//! lives in its own module that is only loaded with the `nightly` feature; stable builds use an iterator that keeps the
+//! same state explicitly.
+
+#[cfg(feature = "nightly")]
+mod coroutine;
+#[cfg(not(feature = "nightly"))]
+mod state;
+
+#[cfg(feature = "nightly")]
+pub(super) use self::coroutine::line_2d;
+#[cfg(not(feature = "nightly"))]
+pub(super) use self::state::line_2d;
@@ -0,0 +1,60 @@
+use crate::model::GridPosition;
+
+/// Rasterizes the line with Bresenham's algorithm.
+pub(crate) fn line_2d(start: GridPosition, target: GridPosition) -> impl Iterator<Item = GridPosition> {
+	// Relying on extra unstable features is fun! <https://rust-lang.github.io/rfcs/2033-experimental-coroutines.html>
+	std::iter::from_coroutine(
+		#[coroutine]
+		move || {
+			// Bresenham's algorithm
+			if (start.y - target.y).abs() < (start.x - target.x).abs() {
+				// non-steep slopes
+				let lower = *if start.x < target.x { start } else { target };
+				let upper = *if start.x >= target.x { start } else { target };
+
+				let dx = upper.x - lower.x;
+				let mut dy = upper.y - lower.y;
+
+				let mut y_dir = 1;
+				if dy < 0 {
+					y_dir = -1;
+					dy = -dy;
+				}
+				let mut d = (2 * dy) - dx;
+				let mut y = lower.y;
+				for x in lower.x ..= upper.x {
+					yield (x, y, start.z).into();
+					if d > 0 {
+						y += y_dir;
+						d += 2 * (dy - dx);
+					} else {
+						d += 2 * dy;
+					}
+				}
+			} else {
+				// steep slopes: iterate on y instead of x
+				let lower = *if start.y < target.y { start } else { target };
+				let upper = *if start.y >= target.y { start } else { target };
+
+				let mut dx = upper.x - lower.x;
+				let dy = upper.y - lower.y;
+				let mut x_dir = 1;
+				if dx < 0 {
+					x_dir = -1;
+					dx = -dx;
+				}
+				let mut d = (2 * dx) - dy;
+				let mut x = lower.x;
+				for y in lower.y ..= upper.y {
+					yield (x, y, start.z).into();
+					if d > 0 {
+						x += x_dir;
+						d += 2 * (dx - dy);
+					} else {
+						d += 2 * dx;
+					}
+				}
+			}
+		},
+	)
+}
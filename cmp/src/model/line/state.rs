@@ -0,0 +1,67 @@
+use crate::model::GridPosition;
+
+/// Rasterizes the line with Bresenham's algorithm.
+pub(crate) fn line_2d(start: GridPosition, target: GridPosition) -> impl Iterator<Item = GridPosition> {
+	Line2d::new(start, target)
+}
+
+/// Stable replacement for the coroutine, which keeps the state of Bresenham's algorithm explicitly. The major axis is
+/// the one that the line is longer along; it advances by one with every position.
+struct Line2d {
+	/// Whether the major axis is y instead of x.
+	steep:       bool,
+	major:       i32,
+	major_end:   i32,
+	minor:       i32,
+	minor_dir:   i32,
+	major_delta: i32,
+	minor_delta: i32,
+	/// Decision variable of the algorithm.
+	d:           i32,
+	z:           i32,
+}
+
+impl Line2d {
+	fn new(start: GridPosition, target: GridPosition) -> Self {
+		let steep = (start.y - target.y).abs() >= (start.x - target.x).abs();
+		let (major_of, minor_of): (fn(GridPosition) -> i32, fn(GridPosition) -> i32) = if steep {
+			(|position: GridPosition| position.y, |position: GridPosition| position.x)
+		} else {
+			(|position: GridPosition| position.x, |position: GridPosition| position.y)
+		};
+		let (lower, upper) = if major_of(start) < major_of(target) { (start, target) } else { (target, start) };
+		let major_delta = major_of(upper) - major_of(lower);
+		let minor_delta = (minor_of(upper) - minor_of(lower)).abs();
+		Self {
+			steep,
+			major: major_of(lower),
+			major_end: major_of(upper),
+			minor: minor_of(lower),
+			minor_dir: if minor_of(upper) < minor_of(lower) { -1 } else { 1 },
+			major_delta,
+			minor_delta,
+			d: 2 * minor_delta - major_delta,
+			z: start.z,
+		}
+	}
+}
+
+impl Iterator for Line2d {
+	type Item = GridPosition;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.major > self.major_end {
+			return None;
+		}
+		let position =
+			if self.steep { (self.minor, self.major, self.z) } else { (self.major, self.minor, self.z) }.into();
+		if self.d > 0 {
+			self.minor += self.minor_dir;
+			self.d += 2 * (self.minor_delta - self.major_delta);
+		} else {
+			self.d += 2 * self.minor_delta;
+		}
+		self.major += 1;
+		Some(position)
+	}
+}
@@ -1,24 +1,61 @@
 //! Internal world state data models and game mechanics.
 
 pub mod area;
+pub mod balance;
+pub mod booking;
+pub mod buildables;
+pub mod burst;
+pub mod calendar;
+pub mod commerce;
+pub mod construction;
+pub mod decal;
+pub mod decoration;
+pub mod demand;
+pub mod errand;
+pub mod facility;
+pub mod fence;
 pub mod geometry;
+pub mod grass;
+mod line;
+pub mod modifiers;
 pub mod nav;
+pub mod network;
+pub mod obstacle;
+pub mod pathfinding;
 pub mod pitch;
+pub mod placement;
+pub mod reachability;
+pub mod reception;
+pub mod road;
+pub mod rollup;
+pub mod routing;
+pub mod spatial;
+pub mod special_event;
+pub mod staff;
 pub mod tile;
-
-use std::marker::ConstParamTy;
+pub mod time_of_day;
+pub mod traffic;
+pub mod utilities;
+pub mod vehicle;
+pub mod visitor;
+pub mod weather;
 
 use bevy::prelude::*;
+use decoration::Decoration;
+use facility::Facility;
+use fence::FenceStyle;
 pub use geometry::*;
 pub use pitch::{Pitch, *};
+use serde::{Deserialize, Serialize};
 pub use tile::*;
+use utilities::{Utility, UtilityPart};
 
 use crate::ui::build::BuildMode;
 use crate::ui::controls::BuildMenu;
 use crate::util::Tooltipable;
 
 /// All build-able objects.
-#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, ConstParamTy)]
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Buildable {
 	/// A [`GroundTile`] of some [`GroundKind`].
@@ -29,15 +66,35 @@ pub enum Buildable {
 	Pitch,
 	/// Some [`PitchType`] specifying the kind of an already existing [`Pitch`].
 	PitchType(PitchType),
+	/// A [`fence::Fence`] of some [`FenceStyle`] along tile edges.
+	Fence(FenceStyle),
+	/// A [`fence::Gate`] along tile edges, which lets everyone through.
+	Gate,
+	/// A supply point of a [`Utility`], which feeds the utility's network.
+	SupplyPoint(Utility),
+	/// An underground pipe or cable of a [`Utility`], which connects supply points to pitches.
+	Conduit(Utility),
+	/// A [`Decoration`] that makes its surroundings more beautiful.
+	Decoration(Decoration),
+	/// A [`Facility`] that serves visitors.
+	Facility(Facility),
+	/// An additional [`road::EntranceRoad`] outside the campground.
+	EntranceRoad,
 }
 
-#[derive(Clone, Reflect, Copy, Debug, PartialEq, Eq, ConstParamTy)]
+#[derive(Clone, Reflect, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "nightly", derive(std::marker::ConstParamTy))]
 #[repr(u8)]
 pub enum BuildableType {
 	Ground,
 	PoolArea,
 	Pitch,
 	PitchType,
+	Fence,
+	Utility,
+	Decoration,
+	Facility,
+	Road,
 }
 
 impl From<Buildable> for BuildableType {
@@ -47,10 +104,32 @@ impl From<Buildable> for BuildableType {
 			Buildable::PoolArea => Self::PoolArea,
 			Buildable::Pitch => Self::Pitch,
 			Buildable::PitchType(_) => Self::PitchType,
+			Buildable::Fence(_) | Buildable::Gate => Self::Fence,
+			Buildable::SupplyPoint(_) | Buildable::Conduit(_) => Self::Utility,
+			Buildable::Decoration(_) => Self::Decoration,
+			Buildable::Facility(_) => Self::Facility,
+			Buildable::EntranceRoad => Self::Road,
 		}
 	}
 }
 
+/// The type of const parameters that select a [`BuildableType`], see [`nav::NavCategoryParam`].
+#[cfg(feature = "nightly")]
+pub type BuildableTypeParam = BuildableType;
+/// The type of const parameters that select a [`BuildableType`], see [`nav::NavCategoryParam`].
+#[cfg(not(feature = "nightly"))]
+pub type BuildableTypeParam = u8;
+
+impl BuildableType {
+	/// The const parameter that selects this buildable type.
+	pub const fn param(self) -> BuildableTypeParam {
+		#[cfg(feature = "nightly")]
+		return self;
+		#[cfg(not(feature = "nightly"))]
+		return self as u8;
+	}
+}
+
 impl std::fmt::Display for Buildable {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(f, "{}", match self {
@@ -58,6 +137,13 @@ impl std::fmt::Display for Buildable {
 			Self::Pitch => "Pitch Site".to_string(),
 			Self::Ground(kind) => kind.to_string(),
 			Self::PoolArea => "Pool Area".to_string(),
+			Self::Fence(style) => style.to_string(),
+			Self::Gate => "Gate".to_string(),
+			Self::SupplyPoint(_) | Self::Conduit(_) =>
+				UtilityPart::from_buildable(*self).map(|part| part.name()).unwrap_or_default().to_string(),
+			Self::Decoration(decoration) => decoration.to_string(),
+			Self::Facility(facility) => facility.to_string(),
+			Self::EntranceRoad => "Entrance Road".to_string(),
 		})
 	}
 }
@@ -71,13 +157,33 @@ impl Tooltipable for Buildable {
 				 specify the kind of pitch by building an pitch on top of this site.",
 			Self::Ground(kind) => kind.description(),
 			Self::PoolArea => "Demarcate a pool area to start building a pool.",
+			Self::Fence(style) => style.description(),
+			Self::Gate =>
+				"Gates are built along the edges of tiles like fences, but let everyone pass. Building a gate on a \
+				 fence replaces that part of the fence.",
+			Self::SupplyPoint(_) | Self::Conduit(_) =>
+				UtilityPart::from_buildable(*self).map(|part| part.description()).unwrap_or_default(),
+			Self::Decoration(decoration) => decoration.description(),
+			Self::Facility(facility) => facility.description(),
+			Self::EntranceRoad =>
+				"Another road to the outside world, built outside the campground next to a pathway. Visitors arrive \
+				 and leave on all entrance roads, and departing vehicles take the closest one.",
 		}
 	}
 }
 
-pub const ALL_BUILDABLES: [Buildable; 9] = [
+pub const ALL_BUILDABLES: [Buildable; 27] = [
 	Buildable::Ground(GroundKind::Pathway),
 	Buildable::Ground(GroundKind::Grass),
+	Buildable::Fence(FenceStyle::Wooden),
+	Buildable::Fence(FenceStyle::Hedge),
+	Buildable::Fence(FenceStyle::StoneWall),
+	Buildable::Gate,
+	Buildable::EntranceRoad,
+	Buildable::SupplyPoint(Utility::Water),
+	Buildable::Conduit(Utility::Water),
+	Buildable::SupplyPoint(Utility::Electricity),
+	Buildable::Conduit(Utility::Electricity),
 	Buildable::PoolArea,
 	Buildable::Pitch,
 	Buildable::PitchType(PitchType::TentPitch),
@@ -85,34 +191,71 @@ pub const ALL_BUILDABLES: [Buildable; 9] = [
 	Buildable::PitchType(PitchType::PermanentTent),
 	Buildable::PitchType(PitchType::MobileHome),
 	Buildable::PitchType(PitchType::Cottage),
+	Buildable::Facility(Facility::Reception),
+	Buildable::Facility(Facility::Toilets),
+	Buildable::Facility(Facility::Showers),
+	Buildable::Facility(Facility::Kiosk),
+	Buildable::Facility(Facility::Restaurant),
+	Buildable::Decoration(Decoration::Tree),
+	Buildable::Decoration(Decoration::FlowerBed),
+	Buildable::Decoration(Decoration::Bench),
+	Buildable::Decoration(Decoration::Lamp),
 ];
 
 impl Buildable {
+	/// The build menu that the buildable appears in without a buildable manifest.
 	pub fn menu(&self) -> BuildMenu {
 		match self {
-			Self::Ground(_) => BuildMenu::Basics,
+			Self::Ground(_)
+			| Self::Fence(_)
+			| Self::Gate
+			| Self::SupplyPoint(_)
+			| Self::Conduit(_)
+			| Self::EntranceRoad => BuildMenu::Basics,
 			Self::PoolArea => BuildMenu::Pool,
 			Self::Pitch | Self::PitchType(_) => BuildMenu::Pitch,
+			Self::Facility(_) => BuildMenu::Facilities,
+			Self::Decoration(_) => BuildMenu::Decoration,
 		}
 	}
 
+	/// The usual footprint of the buildable, which the buildable manifest can change, see
+	/// [`BuildableRegistry::size`](buildables::BuildableRegistry::size).
 	pub fn size(&self) -> BoundingBox {
 		match self {
-			Self::Ground(_) => (1, 1).into(),
+			Self::Ground(_)
+			| Self::Fence(_)
+			| Self::Gate
+			| Self::SupplyPoint(_)
+			| Self::Conduit(_)
+			| Self::EntranceRoad => (1, 1).into(),
 			Self::Pitch | Self::PoolArea => (1, 1).into(),
 			Self::PitchType(kind) => kind.size(),
+			Self::Decoration(decoration) => decoration.size(),
+			Self::Facility(facility) => facility.size(),
 		}
 	}
 
+	/// How the player drags out a build of the buildable.
 	pub fn build_mode(&self) -> BuildMode {
 		match self {
-			Self::Ground(_) => BuildMode::Line,
+			Self::Ground(_) | Self::Conduit(_) => BuildMode::Line,
 			Self::Pitch | Self::PoolArea => BuildMode::Rect,
-			Self::PitchType(_) => BuildMode::Single,
+			Self::PitchType(_)
+			| Self::SupplyPoint(_)
+			| Self::Decoration(_)
+			| Self::Facility(_)
+			| Self::EntranceRoad => BuildMode::Single,
+			Self::Fence(_) | Self::Gate => BuildMode::Edge,
 		}
 	}
 }
 
+/// The name that the player gave their campground.
+#[derive(Resource, Reflect, Clone, Debug, Default, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct CampgroundName(pub String);
+
 /// A general-purpose metric with a specific range. Metrics are always natural numbers to simplify UI and corresponding
 /// game mechanics. Specific subsystems will define their own metric-derived types with specific ranges.
 #[derive(Clone, Copy, Debug, Eq, Ord, Deref, Reflect)]
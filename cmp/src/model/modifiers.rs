@@ -0,0 +1,139 @@
+//! Named modifiers that scale or disable simulation values, such as difficulty settings, sandbox options, scenario
+//! rules or mod tweaks.
+//!
+//! Modifiers stack predictably: all multipliers on the same target are multiplied together, so the order in which they
+//! were registered never matters, and a single disabling modifier turns the target off regardless of any multipliers.
+
+use bevy::prelude::*;
+
+/// A simulation value that can be influenced by modifiers.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierTarget {
+	/// Visitor demand for the campground.
+	Demand,
+	/// Running costs of buildings and staff.
+	Upkeep,
+	/// Random incidents, such as thefts or accidents.
+	Incidents,
+	/// How many visitors use the pools.
+	PoolUsage,
+	/// What demolishing builds refunds, such as on easier difficulties.
+	Refunds,
+}
+
+/// All modifier targets, in the order they are displayed in.
+pub const ALL_MODIFIER_TARGETS: [ModifierTarget; 5] = [
+	ModifierTarget::Demand,
+	ModifierTarget::Upkeep,
+	ModifierTarget::Incidents,
+	ModifierTarget::PoolUsage,
+	ModifierTarget::Refunds,
+];
+
+impl std::fmt::Display for ModifierTarget {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Demand => "Demand",
+			Self::Upkeep => "Upkeep",
+			Self::Incidents => "Incidents",
+			Self::PoolUsage => "Pool usage",
+			Self::Refunds => "Demolition refunds",
+		})
+	}
+}
+
+/// How a [`Modifier`] influences its target.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub enum ModifierEffect {
+	/// Multiplies the target value by this factor.
+	Multiply(f32),
+	/// Turns the target off entirely.
+	Disable,
+}
+
+impl std::fmt::Display for ModifierEffect {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Multiply(factor) => write!(f, "×{}", factor),
+			Self::Disable => write!(f, "off"),
+		}
+	}
+}
+
+/// A single named modifier. The name is shown to the player, so it should explain where the modifier comes from, e.g.
+/// "Relaxed difficulty".
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct Modifier {
+	/// Player-facing name of the modifier.
+	pub name:   String,
+	/// The value that this modifier influences.
+	pub target: ModifierTarget,
+	/// How the value is influenced.
+	pub effect: ModifierEffect,
+}
+
+impl Modifier {
+	/// Creates a modifier multiplying the target by the given factor.
+	pub fn multiply(name: impl Into<String>, target: ModifierTarget, factor: f32) -> Self {
+		Self { name: name.into(), target, effect: ModifierEffect::Multiply(factor) }
+	}
+
+	/// Creates a modifier turning off the target.
+	pub fn disable(name: impl Into<String>, target: ModifierTarget) -> Self {
+		Self { name: name.into(), target, effect: ModifierEffect::Disable }
+	}
+}
+
+/// All modifiers active in the current save. Systems should query [`Modifiers::factor`] for the value they compute
+/// instead of inspecting single modifiers.
+#[derive(Resource, Reflect, Clone, Debug, Default)]
+#[reflect(Resource)]
+pub struct Modifiers {
+	modifiers: Vec<Modifier>,
+}
+
+impl Modifiers {
+	/// Registers a modifier. A modifier with the same name and target replaces the existing one, so registering the
+	/// same modifier repeatedly (e.g. every time a scenario is loaded) doesn't stack it.
+	pub fn register(&mut self, modifier: Modifier) {
+		if let Some(existing) = self
+			.modifiers
+			.iter_mut()
+			.find(|existing| existing.name == modifier.name && existing.target == modifier.target)
+		{
+			*existing = modifier;
+		} else {
+			self.modifiers.push(modifier);
+		}
+	}
+
+	/// Removes all modifiers with the given name.
+	pub fn remove(&mut self, name: &str) {
+		self.modifiers.retain(|modifier| modifier.name != name);
+	}
+
+	/// All modifiers influencing the given target, in registration order.
+	pub fn for_target(&self, target: ModifierTarget) -> impl Iterator<Item = &Modifier> {
+		self.modifiers.iter().filter(move |modifier| modifier.target == target)
+	}
+
+	/// Whether no modifier turns off the given target.
+	pub fn is_enabled(&self, target: ModifierTarget) -> bool {
+		!self.for_target(target).any(|modifier| modifier.effect == ModifierEffect::Disable)
+	}
+
+	/// The combined factor of all modifiers on the given target. A disabled target has a factor of 0.
+	pub fn factor(&self, target: ModifierTarget) -> f32 {
+		self.for_target(target)
+			.map(|modifier| match modifier.effect {
+				ModifierEffect::Multiply(factor) => factor,
+				ModifierEffect::Disable => 0.,
+			})
+			.product()
+	}
+
+	/// Whether any modifiers are active.
+	pub fn is_empty(&self) -> bool {
+		self.modifiers.is_empty()
+	}
+}
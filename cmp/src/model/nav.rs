@@ -1,27 +1,31 @@
 //! Navigation and navmesh information.
 
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::f32::consts::PI;
-use std::marker::ConstParamTy;
 
-use bevy::color::palettes::css::{BLUE, RED};
+use bevy::color::palettes::css::{BLUE, GREEN, RED};
 use bevy::math::Vec3A;
 use bevy::prelude::*;
-use bevy::utils::Instant;
+use bevy::utils::{HashMap, HashSet, Instant};
 use petgraph::graphmap::DiGraphMap;
 
+use super::pathfinding::{finish_path_searches, start_path_searches, ClusterGraph};
 use super::{GridPosition, WorldPosition};
 use crate::config::GameSettings;
-use crate::gamemode::GameState;
-use crate::graphics::{engine_to_world_space, Sides, TRANSFORMATION_MATRIX};
+use crate::debug::{DebugVisualization, DebugVisualizations};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{Sides, TRANSFORMATION_MATRIX};
+use crate::input::picking::tile_under;
 use crate::input::MouseClick;
 
 /// The kinds of navigability, used by different groups of actors.
 /// Each kind has its own nav mesh.
 /// Note that many nav categories are subcategories of others, practically speaking. This is expressed with the
 /// category ordering; see [`PartialOrd`].
-#[derive(Reflect, Clone, Copy, Debug, Default, Eq, PartialEq, ConstParamTy)]
+#[derive(Reflect, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "nightly", derive(std::marker::ConstParamTy))]
+#[repr(u8)]
 pub enum NavCategory {
 	None,
 	#[default]
@@ -29,6 +33,37 @@ pub enum NavCategory {
 	Vehicles,
 }
 
+/// The type of const parameters that select a [`NavCategory`]. On nightly, this is the category itself; stable Rust
+/// only allows integer const parameters, so there the category's discriminant is used and looked up at runtime.
+#[cfg(feature = "nightly")]
+pub type NavCategoryParam = NavCategory;
+/// The type of const parameters that select a [`NavCategory`]. On nightly, this is the category itself; stable Rust
+/// only allows integer const parameters, so there the category's discriminant is used and looked up at runtime.
+#[cfg(not(feature = "nightly"))]
+pub type NavCategoryParam = u8;
+
+impl NavCategory {
+	/// The const parameter that selects this category.
+	pub const fn param(self) -> NavCategoryParam {
+		#[cfg(feature = "nightly")]
+		return self;
+		#[cfg(not(feature = "nightly"))]
+		return self as u8;
+	}
+
+	/// The category that a const parameter selects.
+	pub const fn from_param(param: NavCategoryParam) -> Self {
+		#[cfg(feature = "nightly")]
+		return param;
+		#[cfg(not(feature = "nightly"))]
+		return match param {
+			0 => Self::None,
+			1 => Self::People,
+			_ => Self::Vehicles,
+		};
+	}
+}
+
 impl PartialOrd for NavCategory {
 	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
 		if self == other {
@@ -44,6 +79,29 @@ impl PartialOrd for NavCategory {
 	}
 }
 
+/// The kinds of people that walk on the [`NavCategory::People`] nav mesh, which differ in their walking speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pedestrian {
+	/// A visitor on an errand.
+	Visitor,
+	/// An employee on their shift.
+	Staff,
+	/// The player in walk mode.
+	Player,
+}
+
+impl Pedestrian {
+	/// Walking speed relative to the ground's traversal speed. Visitors stroll a bit slower than staff, while the
+	/// player walks faster than both so that exploring the campground isn't tedious.
+	pub const fn speed_factor(self) -> f32 {
+		match self {
+			Self::Visitor => 0.7,
+			Self::Staff => 0.8,
+			Self::Player => 1.5,
+		}
+	}
+}
+
 /// A navigable vertex on the ground. The entities with these components make up the nav meshes in the world.
 #[derive(Component, Reflect, Clone, Copy, Debug)]
 #[reflect(Component)]
@@ -58,48 +116,31 @@ pub struct NavComponent {
 	pub navigability: NavCategory,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct NavVertex {
-	pub position: GridPosition,
-	pub speed:    u32,
-}
-
-impl PartialEq for NavVertex {
-	fn eq(&self, other: &Self) -> bool {
-		self.position == other.position
-	}
-}
-impl Eq for NavVertex {}
-impl std::hash::Hash for NavVertex {
-	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-		self.position.hash(state);
-	}
-}
-impl PartialOrd for NavVertex {
-	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-		Some(self.cmp(other))
-	}
-}
-impl Ord for NavVertex {
-	fn cmp(&self, other: &Self) -> Ordering {
-		self.position.cmp(&other.position)
-	}
-}
-
-impl From<(GridPosition, u32)> for NavVertex {
-	fn from(value: (GridPosition, u32)) -> Self {
-		Self { position: value.0, speed: value.1 }
-	}
-}
-
 /// A navigation mesh. This is not really a mesh, but it serves the same function as a 3D navmesh. Mathematically
 /// speaking, the navmesh is a directed weighted graph.
-#[derive(Resource, Debug, Default)]
-pub struct NavMesh<const N: NavCategory> {
-	/// Internal graph for the nav mesh.
-	graph: DiGraphMap<NavVertex, ()>,
+///
+/// Changes to the vertices are queued and applied in batches of at most [`VERTEX_UPDATE_BATCH`] per update, so that
+/// large ground edits are spread over several fixed updates instead of stalling one of them.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct NavMesh<const N: NavCategoryParam> {
+	/// Internal graph for the nav mesh. Edges are weighted with the speed of the vertex they start at.
+	graph:    DiGraphMap<GridPosition, u32>,
+	/// The vertices in the mesh, which decide whether their neighbors connect to them.
+	vertices: HashMap<GridPosition, NavComponent>,
+	/// Vertex changes that still need to be applied, with the latest state of every changed vertex.
+	pending:  BTreeMap<GridPosition, NavComponent>,
+	/// Abstract graph over the clusters of the nav mesh.
+	clusters: ClusterGraph,
 }
 
+/// How many vertex changes a nav mesh applies per fixed update at most.
+pub const VERTEX_UPDATE_BATCH: usize = 4096;
+
+/// The nav mesh of [`NavCategory::People`].
+pub type PeopleNavMesh = NavMesh<{ NavCategory::People.param() }>;
+/// The nav mesh of [`NavCategory::Vehicles`].
+pub type VehicleNavMesh = NavMesh<{ NavCategory::Vehicles.param() }>;
+
 #[derive(Debug, Default)]
 pub struct Path {
 	segments: VecDeque<GridPosition>,
@@ -113,183 +154,178 @@ impl Path {
 	pub fn end(&self) -> Option<&GridPosition> {
 		self.segments.back()
 	}
+
+	/// Removes and returns the first position of the path.
+	pub fn pop_start(&mut self) -> Option<GridPosition> {
+		self.segments.pop_front()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.segments.is_empty()
+	}
+
+	/// Number of tiles that are left on the path.
+	pub fn len(&self) -> usize {
+		self.segments.len()
+	}
 }
 
-impl<const N: NavCategory> NavMesh<N> {
-	fn update_vertex_impl(&mut self, position: &GridPosition, vertex: NavComponent) {
-		let belongs_in_mesh = N <= vertex.navigability;
-		// Vertex is being added to the mesh or modified within it.
-		if belongs_in_mesh {
-			self.graph.remove_node((*position, vertex.speed).into());
-			self.graph.add_node((*position, vertex.speed).into());
-			for neighbor in position.neighbors_for(vertex.exits) {
-				if self.graph.contains_node((neighbor, 0).into()) {
-					self.graph.add_edge((*position, vertex.speed).into(), (neighbor, vertex.speed).into(), ());
-					// TODO: We don’t really know whether the neighbor actually has a connection in this direction.
-					self.graph.add_edge((neighbor, vertex.speed).into(), (*position, vertex.speed).into(), ());
-				}
-			}
-		} else {
-			// Vertex is being removed from the mesh.
-			self.graph.remove_node((*position, 0).into());
+impl<const N: NavCategoryParam> NavMesh<N> {
+	fn update_vertex_impl(&mut self, position: GridPosition, vertex: NavComponent) {
+		// Dropping the node also drops all edges to and from it, including ones to neighbors that changed their exits.
+		self.graph.remove_node(position);
+		if NavCategory::from_param(N) > vertex.navigability {
+			self.vertices.remove(&position);
+			return;
+		}
+
+		self.vertices.insert(position, vertex);
+		self.graph.add_node(position);
+		for side in vertex.exits.iter() {
+			let Some(neighbor) = position.neighbors_for(side).next() else {
+				continue;
+			};
+			// Both vertices need an exit towards each other to be connected.
+			let Some(other) = self.vertices.get(&neighbor).filter(|other| other.exits.has_side(side.opposite())) else {
+				continue;
+			};
+			self.graph.add_edge(position, neighbor, vertex.speed);
+			self.graph.add_edge(neighbor, position, other.speed);
 		}
 	}
 
+	/// Whether the position is a vertex of this nav mesh.
+	pub fn contains(&self, position: GridPosition) -> bool {
+		self.graph.contains_node(position)
+	}
+
+	/// Queues changed vertices to be applied by [`Self::apply_pending`].
 	pub fn update_vertices<'a>(&mut self, vertices: impl IntoIterator<Item = (&'a GridPosition, &'a NavComponent)>) {
-		for (position, vertex) in vertices {
-			self.update_vertex_impl(position, *vertex);
-		}
+		self.pending.extend(vertices.into_iter().map(|(position, vertex)| (*position, *vertex)));
 	}
 
-	/// Pathfind via A* from start to end.
-	pub fn pathfind(&self, start: GridPosition, end: GridPosition) -> Option<Path> {
-		/// Manhattan distance between X and Y components of the grid position.
-		fn heuristic(from: GridPosition, to: GridPosition) -> u32 {
-			from.x.abs_diff(to.x) + from.y.abs_diff(to.y)
-		}
+	/// Whether there are vertex changes that haven't been applied yet.
+	pub fn has_pending(&self) -> bool {
+		!self.pending.is_empty()
+	}
 
-		#[derive(Clone, Copy, Debug, Default)]
-		struct OpenSetEntry {
-			position:    GridPosition,
-			// Total cost; used for ordering entries.
-			cost:        u32,
-			// Cost to this node.
-			g:           u32,
-			predecessor: GridPosition,
-		}
-		impl Eq for OpenSetEntry {}
-		impl PartialEq for OpenSetEntry {
-			fn eq(&self, other: &Self) -> bool {
-				self.position == other.position
-			}
-		}
-		impl std::hash::Hash for OpenSetEntry {
-			fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-				self.position.hash(state);
-			}
-		}
-		impl PartialOrd for OpenSetEntry {
-			fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-				Some(self.cmp(other))
-			}
-		}
-		impl Ord for OpenSetEntry {
-			fn cmp(&self, other: &Self) -> Ordering {
-				match self.position.cmp(&other.position) {
-					Ordering::Equal => Ordering::Equal,
-					position_ord => match self.cost.cmp(&other.cost) {
-						// never equal because of above comparison
-						Ordering::Equal => position_ord,
-						cost_ord => cost_ord,
-					},
-				}
-			}
-		}
-		impl From<GridPosition> for OpenSetEntry {
-			fn from(value: GridPosition) -> Self {
-				Self { position: value, cost: 0, g: 0, predecessor: value }
-			}
+	/// Applies up to the given number of queued vertex changes and rebuilds the clusters around them.
+	pub fn apply_pending(&mut self, limit: usize) {
+		let mut changed = Vec::new();
+		while changed.len() < limit {
+			let Some((position, vertex)) = self.pending.pop_first() else {
+				break;
+			};
+			self.update_vertex_impl(position, vertex);
+			changed.push(position);
 		}
+		self.clusters.update(&self.graph, changed);
+	}
 
-		let mut open_set = BTreeSet::new();
-		let mut closed_set: bevy::utils::HashSet<OpenSetEntry> = bevy::utils::HashSet::new();
-
-		open_set.insert(OpenSetEntry { position: start, cost: 0, g: 0, predecessor: start });
-		while let Some(current @ OpenSetEntry { position: current_position, g: current_g, .. }) = open_set.pop_first() {
-			closed_set.insert(current);
-			if current_position == end {
-				let mut backtrack = end;
-				let mut segments = VecDeque::new();
-				while let Some(backtrack_entry) = closed_set.get(&OpenSetEntry::from(backtrack)) {
-					segments.push_front(backtrack_entry.position);
-					if backtrack_entry.position == start {
-						break;
-					}
-					backtrack = backtrack_entry.predecessor;
-				}
-				return Some(Path { segments });
-			}
+	/// All vertices that can be reached from any of the start vertices. Starts that aren't in the mesh are ignored.
+	pub fn reachable_from(&self, starts: impl IntoIterator<Item = GridPosition>) -> HashSet<GridPosition> {
+		self.reachable_without(starts, &HashSet::new())
+	}
 
-			for neighbor in self
-				.graph
-				.neighbors((current_position, 0).into())
-				.filter(|neighbor| !closed_set.contains(&OpenSetEntry::from(neighbor.position)))
-			{
-				let edge_cost = neighbor.speed;
-				let g = current_g + edge_cost;
-				if let Some(neighbor_in_set) = open_set.get(&neighbor.position.into())
-					&& g >= neighbor_in_set.g
-				{
-					continue;
+	/// All vertices that could be reached from any of the start vertices if the excluded vertices were removed from the
+	/// mesh. Starts that aren't in the mesh or are excluded are ignored.
+	pub fn reachable_without(
+		&self,
+		starts: impl IntoIterator<Item = GridPosition>,
+		excluded: &HashSet<GridPosition>,
+	) -> HashSet<GridPosition> {
+		let mut reached = HashSet::new();
+		let mut queue = starts
+			.into_iter()
+			.filter(|start| self.contains(*start) && !excluded.contains(start))
+			.collect::<VecDeque<_>>();
+		reached.extend(queue.iter().copied());
+		while let Some(position) = queue.pop_front() {
+			for neighbor in self.graph.neighbors(position) {
+				if !excluded.contains(&neighbor) && reached.insert(neighbor) {
+					queue.push_back(neighbor);
 				}
-				let cost = g + heuristic(neighbor.position, end);
-				open_set.replace(OpenSetEntry { position: neighbor.position, cost, g, predecessor: current_position });
 			}
 		}
+		reached
+	}
 
-		None
+	/// Pathfind from start to end, using the [`ClusterGraph`] for hierarchical A*.
+	pub fn pathfind(&self, start: GridPosition, end: GridPosition) -> Option<Path> {
+		self.clusters.pathfind(&self.graph, start, end).map(|segments| Path { segments })
 	}
 }
 
-fn update_navmesh<const N: NavCategory>(
+fn update_navmesh<const N: NavCategoryParam>(
 	mut mesh: ResMut<NavMesh<N>>,
 	changed_navigables: Query<(&GridPosition, &NavComponent), Changed<NavComponent>>,
 ) {
-	if changed_navigables.is_empty() {
+	if changed_navigables.is_empty() && !mesh.has_pending() {
 		return;
 	}
 	let start = Instant::now();
 	mesh.update_vertices(&changed_navigables);
-	debug!("Navmesh {:?} update took {:?}", N, Instant::now() - start);
+	mesh.apply_pending(VERTEX_UPDATE_BATCH);
+	debug!("Navmesh {:?} update took {:?}", NavCategory::from_param(N), Instant::now() - start);
 }
 
-fn visualize_navmesh<const N: NavCategory>(mesh: Res<NavMesh<N>>, mut gizmos: Gizmos, settings: Res<GameSettings>) {
-	if !settings.show_debug {
+fn visualize_navmesh<const N: NavCategoryParam>(
+	mesh: Res<NavMesh<N>>,
+	mut gizmos: Gizmos,
+	settings: Res<GameSettings>,
+	visualizations: Res<DebugVisualizations>,
+) {
+	if !visualizations.shows(&settings, DebugVisualization::NavMesh) {
 		return;
 	}
 
 	let positive_angle = Vec2::from_angle(PI / 12.);
 	let negative_angle = Vec2::from_angle(-PI / 12.);
 
-	for (start_node, end_node, _) in mesh.graph.all_edges() {
-		let start = (*TRANSFORMATION_MATRIX.get().unwrap()
-			* (start_node.position.position() + Vec3A::new(0.5, 0.5, 0.)))
-		.truncate();
-		let end = (*TRANSFORMATION_MATRIX.get().unwrap() * (end_node.position.position() + Vec3A::new(0.5, 0.5, 0.)))
-			.truncate();
+	for (start_node, end_node, speed) in mesh.graph.all_edges() {
+		let start =
+			(*TRANSFORMATION_MATRIX.get().unwrap() * (start_node.position() + Vec3A::new(0.5, 0.5, 0.))).truncate();
+		let end = (*TRANSFORMATION_MATRIX.get().unwrap() * (end_node.position() + Vec3A::new(0.5, 0.5, 0.))).truncate();
 		let dir = end - start;
 		let tip1 = start + positive_angle.rotate(dir) * 0.7;
 		let tip2 = start + negative_angle.rotate(dir) * 0.7;
 
-		gizmos
-			.linestrip_2d([start, start + dir * 0.9, tip1, start + dir * 0.9, tip2], BLUE * (start_node.speed as f32));
+		gizmos.linestrip_2d([start, start + dir * 0.9, tip1, start + dir * 0.9, tip2], BLUE * (*speed as f32));
+	}
+
+	for (start, end) in mesh.clusters.connections() {
+		let [start, end] = [start, end].map(|position| {
+			(*TRANSFORMATION_MATRIX.get().unwrap() * (position.position() + Vec3A::new(0.5, 0.5, 0.))).truncate()
+		});
+		gizmos.line_2d(start, end, GREEN);
 	}
 }
 
-fn debug_pathfinding<const N: NavCategory>(
+fn debug_pathfinding<const N: NavCategoryParam>(
 	mesh: Res<NavMesh<N>>,
 	mut gizmos: Gizmos,
 	settings: Res<GameSettings>,
+	visualizations: Res<DebugVisualizations>,
 	mut path: Local<Path>,
 	mut clicks: EventReader<MouseClick>,
 ) {
-	if !settings.show_debug {
+	if !visualizations.shows(&settings, DebugVisualization::Pathfinding) {
 		return;
 	}
 
 	for click in clicks.read() {
-		let new_end = (engine_to_world_space(click.engine_position, 0.) - Vec3A::new(0.5, 0.5, 0.)).round();
+		let new_end = tile_under(click.engine_position);
 		let new_start = path.end();
 		if let Some(new_start) = new_start {
 			let start_time = Instant::now();
 			if let Some(new_path) = mesh.pathfind(*new_start, new_end) {
 				*path = new_path;
 			} else {
-				path.segments = VecDeque::from_iter(Some(new_end).into_iter());
+				path.segments = VecDeque::from_iter(Some(new_end));
 			}
 			debug!("Pathfind took {:?}", Instant::now() - start_time);
 		} else {
-			path.segments = VecDeque::from_iter(Some(new_end).into_iter());
+			path.segments = VecDeque::from_iter(Some(new_end));
 		}
 	}
 
@@ -311,19 +347,38 @@ pub struct NavManagement;
 
 impl Plugin for NavManagement {
 	fn build(&self, app: &mut App) {
-		app.init_resource::<NavMesh<{ NavCategory::People }>>()
-			.init_resource::<NavMesh<{ NavCategory::Vehicles }>>()
+		app.init_resource::<PeopleNavMesh>()
+			.init_resource::<VehicleNavMesh>()
 			.register_type::<NavComponent>()
 			.register_type::<NavCategory>()
 			.add_systems(
 				FixedUpdate,
-				(update_navmesh::<{ NavCategory::People }>, update_navmesh::<{ NavCategory::Vehicles }>)
-					.run_if(in_state(GameState::InGame)),
+				(
+					update_navmesh::<{ NavCategory::People.param() }>,
+					update_navmesh::<{ NavCategory::Vehicles.param() }>,
+				)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				(
+					start_path_searches::<{ NavCategory::People.param() }>,
+					start_path_searches::<{ NavCategory::Vehicles.param() }>,
+					finish_path_searches::<{ NavCategory::People.param() }>,
+					finish_path_searches::<{ NavCategory::Vehicles.param() }>,
+				)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
 			)
 			.add_systems(
 				Update,
-				(visualize_navmesh::<{ NavCategory::Vehicles }>, debug_pathfinding::<{ NavCategory::Vehicles }>)
-					.run_if(in_state(GameState::InGame)),
+				(
+					visualize_navmesh::<{ NavCategory::Vehicles.param() }>,
+					debug_pathfinding::<{ NavCategory::Vehicles.param() }>,
+				)
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
 			);
 	}
 }
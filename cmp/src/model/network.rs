@@ -0,0 +1,309 @@
+//! Supply networks that distribute a resource, such as water through pipes or electricity through cables.
+//!
+//! A network is a graph of nodes on the world grid, connected by edges. Sources feed the resource into the network up
+//! to their capacity, sinks draw it according to their demand, and conduits only pass it on. Every connected part of
+//! the graph is a [`NetworkComponent`] that shares its supply among all of its sinks.
+//!
+//! Networks are kept up to date incrementally: adding an edge merges two components, while removing a node or an edge
+//! only re-examines the component it belonged to. The graph itself knows nothing about the resource; each resource kind
+//! is a [`NetworkResource`] with its own [`Network`] resource, added by a [`NetworkPlugin`]. Additional resource kinds
+//! only need to add their own plugin.
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use super::GridPosition;
+
+/// A kind of resource distributed by a [`Network`].
+pub trait NetworkResource: Send + Sync + 'static {
+	/// Human-readable name of the resource, such as "Water".
+	const NAME: &'static str;
+}
+
+/// Adds the [`Network`] of a resource kind.
+pub struct NetworkPlugin<R: NetworkResource>(PhantomData<R>);
+
+impl<R: NetworkResource> Default for NetworkPlugin<R> {
+	fn default() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<R: NetworkResource> Plugin for NetworkPlugin<R> {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Network<R>>();
+	}
+}
+
+/// The role of a node in its network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkNode {
+	/// Feeds up to this many units of the resource into the network.
+	Source { capacity: u32 },
+	/// Draws this many units of the resource from the network.
+	Sink { demand: u32 },
+	/// Only connects other nodes.
+	Conduit,
+}
+
+impl NetworkNode {
+	const fn capacity(&self) -> u32 {
+		match self {
+			Self::Source { capacity } => *capacity,
+			_ => 0,
+		}
+	}
+
+	const fn demand(&self) -> u32 {
+		match self {
+			Self::Sink { demand } => *demand,
+			_ => 0,
+		}
+	}
+}
+
+/// Identifies a [`NetworkComponent`]. Identifiers are not reused, but merging and splitting components changes the
+/// identifiers of the nodes involved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(u32);
+
+/// A connected part of a network.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkComponent {
+	nodes:    HashSet<GridPosition>,
+	/// Total capacity of all sources.
+	capacity: u32,
+	/// Total demand of all sinks.
+	demand:   u32,
+}
+
+impl NetworkComponent {
+	/// All nodes in this component.
+	pub fn nodes(&self) -> impl Iterator<Item = &GridPosition> {
+		self.nodes.iter()
+	}
+
+	/// Total capacity of all sources.
+	pub const fn capacity(&self) -> u32 {
+		self.capacity
+	}
+
+	/// Total demand of all sinks.
+	pub const fn demand(&self) -> u32 {
+		self.demand
+	}
+
+	/// Fraction of the demand that the sources can satisfy, between 0 and 1. Without demand, this is 1.
+	pub fn supply_ratio(&self) -> f32 {
+		if self.demand == 0 {
+			1.
+		} else {
+			(self.capacity as f32 / self.demand as f32).min(1.)
+		}
+	}
+
+	/// Whether the sources can satisfy the entire demand.
+	pub const fn is_sufficient(&self) -> bool {
+		self.capacity >= self.demand
+	}
+
+	fn add(&mut self, position: GridPosition, node: NetworkNode) {
+		self.nodes.insert(position);
+		self.capacity += node.capacity();
+		self.demand += node.demand();
+	}
+}
+
+/// The network of one resource kind.
+#[derive(Resource)]
+pub struct Network<R: NetworkResource> {
+	nodes:             HashMap<GridPosition, NetworkNode>,
+	edges:             HashMap<GridPosition, HashSet<GridPosition>>,
+	node_components:   HashMap<GridPosition, ComponentId>,
+	components:        HashMap<ComponentId, NetworkComponent>,
+	next_component_id: u32,
+	_resource:         PhantomData<R>,
+}
+
+impl<R: NetworkResource> Default for Network<R> {
+	fn default() -> Self {
+		Self {
+			nodes:             HashMap::new(),
+			edges:             HashMap::new(),
+			node_components:   HashMap::new(),
+			components:        HashMap::new(),
+			next_component_id: 0,
+			_resource:         PhantomData,
+		}
+	}
+}
+
+impl<R: NetworkResource> Network<R> {
+	/// The node at the position, if any.
+	pub fn node(&self, position: GridPosition) -> Option<NetworkNode> {
+		self.nodes.get(&position).copied()
+	}
+
+	/// All nodes and their positions.
+	pub fn nodes(&self) -> impl Iterator<Item = (GridPosition, NetworkNode)> + '_ {
+		self.nodes.iter().map(|(position, node)| (*position, *node))
+	}
+
+	/// All nodes directly connected to the node at the position.
+	pub fn neighbors(&self, position: GridPosition) -> impl Iterator<Item = GridPosition> + '_ {
+		self.edges.get(&position).into_iter().flatten().copied()
+	}
+
+	/// The component that the node at the position belongs to.
+	pub fn component_of(&self, position: GridPosition) -> Option<&NetworkComponent> {
+		self.node_components.get(&position).and_then(|id| self.components.get(id))
+	}
+
+	/// All components of the network.
+	pub fn components(&self) -> impl Iterator<Item = (ComponentId, &NetworkComponent)> {
+		self.components.iter().map(|(id, component)| (*id, component))
+	}
+
+	/// Whether the resource can flow between the two nodes.
+	pub fn is_connected(&self, first: GridPosition, second: GridPosition) -> bool {
+		self.node_components.get(&first).is_some_and(|id| self.node_components.get(&second) == Some(id))
+	}
+
+	/// Whether the node at the position is connected to at least one source.
+	pub fn is_supplied(&self, position: GridPosition) -> bool {
+		self.component_of(position).is_some_and(|component| component.capacity > 0)
+	}
+
+	/// Removes all nodes and edges.
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+
+	/// Adds a node, or changes the role of an existing node.
+	pub fn insert_node(&mut self, position: GridPosition, node: NetworkNode) {
+		if let Some(previous) = self.nodes.insert(position, node) {
+			let component = self.component_mut(position);
+			component.capacity = component.capacity - previous.capacity() + node.capacity();
+			component.demand = component.demand - previous.demand() + node.demand();
+			return;
+		}
+		let id = self.new_component_id();
+		let mut component = NetworkComponent::default();
+		component.add(position, node);
+		self.components.insert(id, component);
+		self.node_components.insert(position, id);
+	}
+
+	/// Removes a node and all of its edges. The rest of its component may fall apart.
+	pub fn remove_node(&mut self, position: GridPosition) {
+		if self.nodes.remove(&position).is_none() {
+			return;
+		}
+		let neighbors = self.edges.remove(&position).unwrap_or_default();
+		for neighbor in &neighbors {
+			if let Some(neighbor_edges) = self.edges.get_mut(neighbor) {
+				neighbor_edges.remove(&position);
+			}
+		}
+		if let Some(id) = self.node_components.remove(&position) {
+			self.split_component(id);
+		}
+	}
+
+	/// Connects two existing nodes. Returns whether both nodes exist.
+	pub fn connect(&mut self, first: GridPosition, second: GridPosition) -> bool {
+		if first == second || !self.nodes.contains_key(&first) || !self.nodes.contains_key(&second) {
+			return false;
+		}
+		self.edges.entry(first).or_default().insert(second);
+		self.edges.entry(second).or_default().insert(first);
+		self.merge_components(first, second);
+		true
+	}
+
+	/// Connects the node at the position to all nodes on the directly neighboring tiles of the same level. This is how
+	/// networks laid out on the grid, like pipes, are connected.
+	pub fn connect_grid_neighbors(&mut self, position: GridPosition) {
+		for offset in [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y] {
+			self.connect(position, position + offset);
+		}
+	}
+
+	/// Removes the connection between two nodes. Their component may fall apart.
+	pub fn disconnect(&mut self, first: GridPosition, second: GridPosition) {
+		let removed = self.edges.get_mut(&first).is_some_and(|edges| edges.remove(&second));
+		if !removed {
+			return;
+		}
+		if let Some(edges) = self.edges.get_mut(&second) {
+			edges.remove(&first);
+		}
+		if let Some(id) = self.node_components.get(&first).copied() {
+			self.split_component(id);
+		}
+	}
+
+	fn new_component_id(&mut self) -> ComponentId {
+		let id = ComponentId(self.next_component_id);
+		self.next_component_id += 1;
+		id
+	}
+
+	fn component_mut(&mut self, position: GridPosition) -> &mut NetworkComponent {
+		let id = self.node_components[&position];
+		self.components.get_mut(&id).unwrap()
+	}
+
+	/// Moves all nodes of the smaller component into the larger one.
+	fn merge_components(&mut self, first: GridPosition, second: GridPosition) {
+		let (first_id, second_id) = (self.node_components[&first], self.node_components[&second]);
+		if first_id == second_id {
+			return;
+		}
+		let (larger_id, smaller_id) =
+			if self.components[&first_id].nodes.len() >= self.components[&second_id].nodes.len() {
+				(first_id, second_id)
+			} else {
+				(second_id, first_id)
+			};
+		let smaller = self.components.remove(&smaller_id).unwrap();
+		for position in &smaller.nodes {
+			self.node_components.insert(*position, larger_id);
+		}
+		let larger = self.components.get_mut(&larger_id).unwrap();
+		larger.nodes.extend(smaller.nodes);
+		larger.capacity += smaller.capacity;
+		larger.demand += smaller.demand;
+	}
+
+	/// Recomputes the connected parts of a component after nodes or edges were removed from it.
+	fn split_component(&mut self, id: ComponentId) {
+		let Some(old_component) = self.components.remove(&id) else {
+			return;
+		};
+		let mut unassigned = old_component
+			.nodes
+			.into_iter()
+			.filter(|position| self.nodes.contains_key(position))
+			.collect::<HashSet<_>>();
+		while let Some(&start) = unassigned.iter().next() {
+			unassigned.remove(&start);
+			let new_id = self.new_component_id();
+			let mut component = NetworkComponent::default();
+			let mut queue = VecDeque::from([start]);
+			while let Some(position) = queue.pop_front() {
+				component.add(position, self.nodes[&position]);
+				self.node_components.insert(position, new_id);
+				for neighbor in self.edges.get(&position).into_iter().flatten() {
+					if unassigned.remove(neighbor) {
+						queue.push_back(*neighbor);
+					}
+				}
+			}
+			self.components.insert(new_id, component);
+		}
+	}
+}
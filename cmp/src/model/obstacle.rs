@@ -0,0 +1,212 @@
+//! Natural obstacles: rocks and mature trees that stand in the way of building.
+//!
+//! New worlds are scattered with obstacles. Rocks lie around on their own, while mature trees grow in groves; the
+//! surroundings of the world origin stay clear, so that new campgrounds can start building right away. Obstacles take
+//! up the tiles of their [`GridBox`], so nothing can be built there, and nobody can walk through them.
+//!
+//! The player removes obstacles with the clearing tool, which charges the [`Balance::clearing_cost`] of every obstacle
+//! up front and marks it with [`Clearing`]. Clearing takes the [`Obstacle::clearing_hours`] of the obstacle, or no
+//! time at all with the `instant_construction` sandbox setting, after which the obstacle disappears.
+//!
+//! [`Balance::clearing_cost`]: super::balance::Balance::clearing_cost
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
+
+use super::burst::BurstPlacement;
+use super::construction::{spawn_progress_bar, update_progress_bars, WorkProgress};
+use super::rollup::in_game_hours;
+use super::{BoundingBox, GridBox, GridPosition};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, image_for_obstacle};
+use crate::graphics::ObjectPriority;
+use crate::save::LoadSave;
+use crate::ui::world_info::WorldInfoProperties;
+use crate::util::Tooltipable;
+
+pub struct ObstacleManagement;
+
+impl Plugin for ObstacleManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Obstacle>()
+			.register_type::<Clearing>()
+			// Loaded games bring their own obstacles.
+			.add_systems(OnEnter(GameState::Loading), generate_obstacles.run_if(not(resource_exists::<LoadSave>)))
+			.add_systems(FixedUpdate, advance_clearing.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				(add_obstacle_graphics, add_clearing_progress_bars, update_progress_bars::<Clearing>)
+					.chain()
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Obstacles are scattered within this many tiles of the world origin.
+const OBSTACLE_RADIUS: i32 = 60;
+/// No obstacles are within this many tiles of the world origin.
+const CLEAR_RADIUS: i32 = 12;
+/// Chance of a tile to hold a rock.
+const ROCK_CHANCE: f32 = 0.01;
+/// Number of groves of mature trees.
+const GROVE_COUNT: usize = 16;
+/// Largest distance of a grove's trees from its center, in tiles.
+const GROVE_RADIUS: i32 = 5;
+/// Chance of a tile within a grove to hold a mature tree.
+const GROVE_DENSITY: f32 = 0.35;
+
+/// A natural obstacle that has to be cleared before anything can be built on its tiles.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum Obstacle {
+	Rock,
+	MatureTree,
+}
+
+pub const ALL_OBSTACLES: [Obstacle; 2] = [Obstacle::Rock, Obstacle::MatureTree];
+
+impl Obstacle {
+	pub const fn size(&self) -> BoundingBox {
+		match self {
+			Self::Rock => BoundingBox::fixed::<1, 1, 1>(),
+			Self::MatureTree => BoundingBox::fixed::<1, 1, 2>(),
+		}
+	}
+
+	/// In-game hours that clearing the obstacle takes.
+	pub const fn clearing_hours(&self) -> f32 {
+		match self {
+			Self::Rock => 2.,
+			Self::MatureTree => 3.,
+		}
+	}
+
+	/// The tiles that the obstacle takes up when it lies at the position.
+	pub fn grid_box_at(&self, position: GridPosition) -> GridBox {
+		GridBox::around(position, self.size().flat())
+	}
+}
+
+impl std::fmt::Display for Obstacle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Rock => "Rock",
+			Self::MatureTree => "Mature Tree",
+		})
+	}
+}
+
+impl Tooltipable for Obstacle {
+	fn description(&self) -> &'static str {
+		match self {
+			Self::Rock => "A large boulder. Clear it with the clearing tool to build here.",
+			Self::MatureTree => "An old tree with deep roots. Clear it with the clearing tool to build here.",
+		}
+	}
+}
+
+/// An obstacle that is being cleared.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct Clearing {
+	/// Simulated time that was spent clearing so far.
+	pub elapsed:  Duration,
+	/// Simulated time that clearing takes.
+	pub duration: Duration,
+}
+
+impl Clearing {
+	/// Starts clearing the obstacle.
+	pub fn of(obstacle: Obstacle) -> Self {
+		Self { elapsed: Duration::ZERO, duration: in_game_hours(obstacle.clearing_hours()) }
+	}
+}
+
+impl WorkProgress for Clearing {
+	fn progress(&self) -> f32 {
+		if self.duration.is_zero() {
+			1.
+		} else {
+			(self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).clamp(0., 1.)
+		}
+	}
+}
+
+/// Scatters obstacles across a new world.
+fn generate_obstacles(mut burst: ResMut<BurstPlacement>) {
+	let is_clear = |position: GridPosition| position.x.abs() <= CLEAR_RADIUS && position.y.abs() <= CLEAR_RADIUS;
+	let mut obstacles = HashMap::<GridPosition, Obstacle>::new();
+
+	for _ in 0 .. GROVE_COUNT {
+		let center = IVec2::new(
+			fastrand::i32(-OBSTACLE_RADIUS ..= OBSTACLE_RADIUS),
+			fastrand::i32(-OBSTACLE_RADIUS ..= OBSTACLE_RADIUS),
+		);
+		for x in -GROVE_RADIUS ..= GROVE_RADIUS {
+			for y in -GROVE_RADIUS ..= GROVE_RADIUS {
+				// Groves thin out towards their edge.
+				let distance = IVec2::new(x, y).as_vec2().length() / GROVE_RADIUS as f32;
+				if distance <= 1. && fastrand::f32() < GROVE_DENSITY * (1. - distance * 0.5) {
+					obstacles.insert((center.x + x, center.y + y, 0).into(), Obstacle::MatureTree);
+				}
+			}
+		}
+	}
+	for x in -OBSTACLE_RADIUS ..= OBSTACLE_RADIUS {
+		for y in -OBSTACLE_RADIUS ..= OBSTACLE_RADIUS {
+			if fastrand::f32() < ROCK_CHANCE {
+				obstacles.entry((x, y, 0).into()).or_insert(Obstacle::Rock);
+			}
+		}
+	}
+
+	debug!("Generated {} obstacles", obstacles.len());
+	burst.queue_entities(
+		obstacles
+			.into_iter()
+			.filter(|(position, _)| !is_clear(*position))
+			.map(|(position, obstacle)| (obstacle, obstacle.grid_box_at(position), Save)),
+	);
+}
+
+fn advance_clearing(
+	time: Res<Time>,
+	settings: Res<GameSettings>,
+	mut obstacles: Query<(Entity, &mut Clearing)>,
+	mut commands: Commands,
+) {
+	for (entity, mut clearing) in &mut obstacles {
+		clearing.elapsed += time.delta();
+		if clearing.elapsed >= clearing.duration || settings.instant_construction {
+			commands.entity(entity).despawn_recursive();
+		}
+	}
+}
+
+/// Graphics are added separately, so that loaded obstacles receive them as well.
+fn add_obstacle_graphics(
+	obstacles: Query<(Entity, &Obstacle), Without<Sprite>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (entity, obstacle) in &obstacles {
+		let image = image_for_obstacle(*obstacle);
+		commands.entity(entity).insert((
+			Sprite { anchor: anchor_for_image(image), image: asset_server.load(image), ..default() },
+			ObjectPriority::Normal,
+			WorldInfoProperties::basic(obstacle.to_string(), obstacle.description().to_string()),
+		));
+	}
+}
+
+fn add_clearing_progress_bars(obstacles: Query<Entity, Added<Clearing>>, mut commands: Commands) {
+	for obstacle in &obstacles {
+		commands.entity(obstacle).with_children(spawn_progress_bar);
+	}
+}
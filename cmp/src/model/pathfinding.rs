@@ -0,0 +1,364 @@
+//! Hierarchical pathfinding and the asynchronous path queue.
+//!
+//! A* over the full [`NavMesh`] gets slow on large campgrounds with many actors. Every nav mesh therefore keeps a
+//! [`ClusterGraph`]: the map is cut into square clusters of [`CLUSTER_SIZE`] tiles, and an abstract graph connects the
+//! entrances between neighboring clusters with the cost of walking between them. Paths are first searched on the
+//! abstract graph and then refined cluster by cluster, so that a search only ever looks at a few clusters of the mesh
+//! at once. When nav mesh vertices change, only the clusters around them are rebuilt.
+//!
+//! Actors that can wait a few frames for their path add a [`PathRequest`] instead of calling [`NavMesh::pathfind`].
+//! The search then runs on the async compute task pool against a snapshot of the nav mesh, and the result replaces the
+//! request as a [`FoundPath`].
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future::{block_on, poll_once};
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::utils::{HashMap, HashSet};
+use itertools::Itertools;
+use petgraph::graphmap::DiGraphMap;
+use petgraph::Direction;
+
+use super::nav::{NavCategory, NavCategoryParam, NavMesh, Path};
+use super::GridPosition;
+
+/// Width and height of the clusters, in tiles.
+pub const CLUSTER_SIZE: i32 = 16;
+/// Entrances at least this wide get a transition at both of their ends instead of one in the middle, so that paths
+/// along a wide open border don't all funnel through its center.
+const WIDE_ENTRANCE: usize = 6;
+
+/// Index of a cluster, counted in clusters from the origin.
+type Cluster = IVec2;
+/// Two neighboring clusters, the one with the smaller coordinate first.
+type Border = (Cluster, Cluster);
+
+fn cluster_of(position: GridPosition) -> Cluster {
+	IVec2::new(position.x.div_euclid(CLUSTER_SIZE), position.y.div_euclid(CLUSTER_SIZE))
+}
+
+/// The borders of a cluster with its four neighbors.
+fn borders_of(cluster: Cluster) -> [Border; 4] {
+	[
+		(cluster - IVec2::X, cluster),
+		(cluster, cluster + IVec2::X),
+		(cluster - IVec2::Y, cluster),
+		(cluster, cluster + IVec2::Y),
+	]
+}
+
+/// Positions that can be walked to from the position in one step, with the cost of that step.
+fn steps(
+	graph: &DiGraphMap<GridPosition, u32>,
+	position: GridPosition,
+) -> impl Iterator<Item = (GridPosition, u32)> + '_ {
+	graph.edges(position).map(|(_, neighbor, cost)| (neighbor, *cost))
+}
+
+/// Like [`steps`], but never leaves the cluster. When going backwards, this follows the edges that lead to the
+/// position instead, still with the cost of walking them forwards.
+fn steps_within(
+	graph: &DiGraphMap<GridPosition, u32>,
+	position: GridPosition,
+	cluster: Cluster,
+	direction: Direction,
+) -> impl Iterator<Item = (GridPosition, u32)> + '_ {
+	graph
+		.edges_directed(position, direction)
+		.map(move |(from, to, cost)| (if from == position { to } else { from }, *cost))
+		.filter(move |(neighbor, _)| cluster_of(*neighbor) == cluster)
+}
+
+/// Best-first search from the start, returning the cost of and the predecessor on the cheapest way to every visited
+/// position. With a goal, this is A* with the Manhattan distance as the heuristic, and it stops once the goal is
+/// reached; without one, it is Dijkstra's algorithm and visits everything reachable.
+fn explore<I, F>(
+	start: GridPosition,
+	goal: Option<GridPosition>,
+	mut neighbors: F,
+) -> HashMap<GridPosition, (u32, GridPosition)>
+where
+	I: IntoIterator<Item = (GridPosition, u32)>,
+	F: FnMut(GridPosition) -> I,
+{
+	let heuristic =
+		|position: GridPosition| goal.map_or(0, |goal| position.x.abs_diff(goal.x) + position.y.abs_diff(goal.y));
+
+	let mut visited = HashMap::new();
+	let mut open = BinaryHeap::new();
+	visited.insert(start, (0, start));
+	open.push(Reverse((heuristic(start), 0, start)));
+	while let Some(Reverse((_, cost, position))) = open.pop() {
+		// A cheaper way to this position was found after this entry was queued.
+		if visited.get(&position).is_some_and(|(best, _)| *best < cost) {
+			continue;
+		}
+		if goal == Some(position) {
+			break;
+		}
+		for (neighbor, step) in neighbors(position) {
+			let neighbor_cost = cost + step;
+			if visited.get(&neighbor).is_some_and(|(best, _)| *best <= neighbor_cost) {
+				continue;
+			}
+			visited.insert(neighbor, (neighbor_cost, position));
+			open.push(Reverse((neighbor_cost + heuristic(neighbor), neighbor_cost, neighbor)));
+		}
+	}
+	visited
+}
+
+/// The cheapest path from start to end, including both.
+fn shortest_path<I, F>(start: GridPosition, end: GridPosition, neighbors: F) -> Option<VecDeque<GridPosition>>
+where
+	I: IntoIterator<Item = (GridPosition, u32)>,
+	F: FnMut(GridPosition) -> I,
+{
+	let visited = explore(start, Some(end), neighbors);
+	visited.get(&end)?;
+	let mut segments = VecDeque::from([end]);
+	let mut current = end;
+	while current != start {
+		current = visited[&current].1;
+		segments.push_front(current);
+	}
+	Some(segments)
+}
+
+/// The abstract graph of a nav mesh for hierarchical pathfinding.
+///
+/// Its vertices are the entrances of the clusters: pairs of tiles on both sides of a cluster border that the nav mesh
+/// connects. Every run of connected border tiles gets one or two such transitions. Entrances on both sides of a
+/// transition are connected directly, and entrances of the same cluster are connected with the cost of the cheapest
+/// path between them inside the cluster.
+#[derive(Clone, Debug, Default)]
+pub struct ClusterGraph {
+	graph:     DiGraphMap<GridPosition, u32>,
+	/// Transitions over each border, the tile in the first cluster first.
+	borders:   HashMap<Border, Vec<(GridPosition, GridPosition)>>,
+	/// Entrances of every cluster.
+	entrances: HashMap<Cluster, Vec<GridPosition>>,
+}
+
+impl ClusterGraph {
+	/// Rebuilds the clusters around the changed positions of the nav mesh graph.
+	pub(super) fn update(
+		&mut self,
+		graph: &DiGraphMap<GridPosition, u32>,
+		changed: impl IntoIterator<Item = GridPosition>,
+	) {
+		let dirty = changed.into_iter().map(cluster_of).collect::<HashSet<_>>();
+		if dirty.is_empty() {
+			return;
+		}
+
+		// Transitions over the borders of changed clusters may have appeared or disappeared, which changes the
+		// entrances of the clusters on both sides.
+		let dirty_borders = dirty.iter().flat_map(|cluster| borders_of(*cluster)).collect::<HashSet<_>>();
+		let mut crossings = HashMap::<Border, HashSet<(GridPosition, GridPosition)>>::new();
+		for vertex in graph.nodes() {
+			for (neighbor, _) in steps(graph, vertex) {
+				let (from, to) = (cluster_of(vertex), cluster_of(neighbor));
+				let crossing = if from.x < to.x || from.y < to.y { (vertex, neighbor) } else { (neighbor, vertex) };
+				let border = (cluster_of(crossing.0), cluster_of(crossing.1));
+				if from != to && dirty_borders.contains(&border) {
+					crossings.entry(border).or_default().insert(crossing);
+				}
+			}
+		}
+		for border in &dirty_borders {
+			match crossings.remove(border) {
+				Some(crossings) => self.borders.insert(*border, transitions(*border, crossings)),
+				None => self.borders.remove(border),
+			};
+		}
+
+		let affected = dirty_borders.iter().flat_map(|(first, second)| [*first, *second]).collect::<HashSet<_>>();
+		for cluster in &affected {
+			for entrance in self.entrances.remove(cluster).unwrap_or_default() {
+				self.graph.remove_node(entrance);
+			}
+		}
+		for cluster in &affected {
+			let entrances = borders_of(*cluster)
+				.iter()
+				.filter_map(|border| self.borders.get(border))
+				.flatten()
+				.flat_map(|(first, second)| [*first, *second])
+				.filter(|entrance| cluster_of(*entrance) == *cluster)
+				.sorted()
+				.dedup()
+				.collect::<Vec<_>>();
+			for entrance in &entrances {
+				self.graph.add_node(*entrance);
+			}
+			for entrance in &entrances {
+				let costs =
+					explore(*entrance, None, |position| steps_within(graph, position, *cluster, Direction::Outgoing));
+				for other in entrances.iter().filter(|other| *other != entrance) {
+					if let Some((cost, _)) = costs.get(other) {
+						self.graph.add_edge(*entrance, *other, *cost);
+					}
+				}
+			}
+			if !entrances.is_empty() {
+				self.entrances.insert(*cluster, entrances);
+			}
+		}
+
+		// Removing the entrances also removed the transitions to unaffected neighbors.
+		let affected_borders = affected.iter().flat_map(|cluster| borders_of(*cluster)).collect::<HashSet<_>>();
+		for (first, second) in affected_borders.iter().filter_map(|border| self.borders.get(border)).flatten() {
+			for (from, to) in [(*first, *second), (*second, *first)] {
+				if let Some((_, cost)) = steps(graph, from).find(|(neighbor, _)| *neighbor == to) {
+					self.graph.add_edge(from, to, cost);
+				}
+			}
+		}
+	}
+
+	/// Finds a path from start to end through the nav mesh graph, including both.
+	pub(super) fn pathfind(
+		&self,
+		graph: &DiGraphMap<GridPosition, u32>,
+		start: GridPosition,
+		end: GridPosition,
+	) -> Option<VecDeque<GridPosition>> {
+		let (start_cluster, end_cluster) = (cluster_of(start), cluster_of(end));
+		if start_cluster == end_cluster {
+			let local =
+				shortest_path(start, end, |position| steps_within(graph, position, start_cluster, Direction::Outgoing));
+			if local.is_some() {
+				return local;
+			}
+		}
+
+		// Connect start and end to the entrances of their clusters. Searching backwards from the end finds the costs of
+		// walking from each entrance to the end.
+		let entrance_costs = |from: GridPosition, cluster: Cluster, direction: Direction| {
+			let costs = explore(from, None, |position| steps_within(graph, position, cluster, direction));
+			self.entrances
+				.get(&cluster)
+				.into_iter()
+				.flatten()
+				.filter_map(|entrance| Some((*entrance, costs.get(entrance)?.0)))
+				.collect::<Vec<_>>()
+		};
+		let exits = entrance_costs(start, start_cluster, Direction::Outgoing);
+		let arrivals = entrance_costs(end, end_cluster, Direction::Incoming).into_iter().collect::<HashMap<_, _>>();
+		if exits.is_empty() || arrivals.is_empty() {
+			return None;
+		}
+
+		let abstract_path = shortest_path(start, end, |position| {
+			let mut next = self.graph.edges(position).map(|(_, to, cost)| (to, *cost)).collect::<Vec<_>>();
+			if position == start {
+				next.extend_from_slice(&exits);
+			}
+			if let Some(cost) = arrivals.get(&position) {
+				next.push((end, *cost));
+			}
+			next
+		})?;
+
+		let mut path = VecDeque::from([start]);
+		for (from, to) in abstract_path.into_iter().tuple_windows() {
+			let cluster = cluster_of(from);
+			if cluster == cluster_of(to) {
+				let leg =
+					shortest_path(from, to, |position| steps_within(graph, position, cluster, Direction::Outgoing))?;
+				path.extend(leg.into_iter().skip(1));
+			} else {
+				path.push_back(to);
+			}
+		}
+		Some(path)
+	}
+
+	/// Pairs of entrances connected in the abstract graph.
+	pub(super) fn connections(&self) -> impl Iterator<Item = (GridPosition, GridPosition)> + '_ {
+		self.graph.all_edges().map(|(from, to, _)| (from, to))
+	}
+}
+
+/// Picks the transitions among the connected tile pairs on a border.
+fn transitions(
+	(first, second): Border,
+	crossings: HashSet<(GridPosition, GridPosition)>,
+) -> Vec<(GridPosition, GridPosition)> {
+	let along = |(tile, _): &(GridPosition, GridPosition)| if first.x == second.x { tile.x } else { tile.y };
+	let sorted = crossings.into_iter().sorted_by_key(|crossing| (crossing.0.z, along(crossing))).collect::<Vec<_>>();
+
+	let mut transitions = Vec::new();
+	for (_, entrance) in
+		&sorted.iter().enumerate().group_by(|(index, crossing)| (crossing.0.z, along(crossing) - *index as i32))
+	{
+		let entrance = entrance.map(|(_, crossing)| *crossing).collect::<Vec<_>>();
+		if entrance.len() >= WIDE_ENTRANCE {
+			transitions.extend([entrance[0], entrance[entrance.len() - 1]]);
+		} else {
+			transitions.push(entrance[entrance.len() / 2]);
+		}
+	}
+	transitions
+}
+
+/// Requests a path from the start to the first of the destinations that can be reached. The search runs in the
+/// background, and once it finishes, this component is replaced with a [`FoundPath`].
+#[derive(Component)]
+pub struct PathRequest<const N: NavCategoryParam> {
+	start:        GridPosition,
+	destinations: Vec<GridPosition>,
+	search:       Option<Task<Option<Path>>>,
+}
+
+/// A path request over the nav mesh of [`NavCategory::People`].
+pub type PeoplePathRequest = PathRequest<{ NavCategory::People.param() }>;
+/// A path request over the nav mesh of [`NavCategory::Vehicles`].
+pub type VehiclePathRequest = PathRequest<{ NavCategory::Vehicles.param() }>;
+
+impl<const N: NavCategoryParam> PathRequest<N> {
+	pub fn new(start: GridPosition, destinations: impl IntoIterator<Item = GridPosition>) -> Self {
+		Self { start, destinations: destinations.into_iter().collect(), search: None }
+	}
+}
+
+/// The result of a [`PathRequest`], which is [`None`] if none of the destinations could be reached.
+#[derive(Component, Debug, Default)]
+pub struct FoundPath(pub Option<Path>);
+
+/// Starts searches for new requests on a snapshot of the nav mesh, which is only taken again once the mesh changed.
+pub(super) fn start_path_searches<const N: NavCategoryParam>(
+	mesh: Res<NavMesh<N>>,
+	mut snapshot: Local<Option<Arc<NavMesh<N>>>>,
+	mut requests: Query<&mut PathRequest<N>>,
+) {
+	if mesh.is_changed() {
+		*snapshot = None;
+	}
+	let pool = AsyncComputeTaskPool::get();
+	for mut request in requests.iter_mut().filter(|request| request.search.is_none()) {
+		let mesh = snapshot.get_or_insert_with(|| Arc::new(mesh.clone())).clone();
+		let start = request.start;
+		let destinations = std::mem::take(&mut request.destinations);
+		request.search =
+			Some(pool.spawn(async move {
+				destinations.into_iter().find_map(|destination| mesh.pathfind(start, destination))
+			}));
+	}
+}
+
+pub(super) fn finish_path_searches<const N: NavCategoryParam>(
+	mut requests: Query<(Entity, &mut PathRequest<N>)>,
+	mut commands: Commands,
+) {
+	for (entity, mut request) in &mut requests {
+		let Some(path) = request.search.as_mut().and_then(|search| block_on(poll_once(search))) else {
+			continue;
+		};
+		commands.entity(entity).remove::<PathRequest<N>>().insert(FoundPath(path));
+	}
+}
@@ -1,13 +1,11 @@
-use std::marker::ConstParamTy;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-
 use bevy::prelude::*;
 use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
 
 use super::area::{Area, AreaMarker, ImmutableArea, UpdateAreas};
+use super::utilities::Connected;
 use super::{BoundingBox, GridBox, GridPosition, GroundKind, GroundMap, Metric};
-use crate::gamemode::GameState;
+use crate::gamemode::{GameSet, GameState};
 use crate::graphics::library::{anchor_for_image, image_for_pitch};
 use crate::graphics::ObjectPriority;
 use crate::ui::world_info::{WorldInfoProperties, WorldInfoProperty};
@@ -15,7 +13,7 @@ use crate::util::Tooltipable;
 use crate::HashSet;
 
 /// The different available types of pitch.
-#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, ConstParamTy)]
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PitchType {
 	TentPitch,
 	PermanentTent,
@@ -24,6 +22,15 @@ pub enum PitchType {
 	Cottage,
 }
 
+/// All pitch types, from the simplest to the most luxurious.
+pub const ALL_PITCH_TYPES: [PitchType; 5] = [
+	PitchType::TentPitch,
+	PitchType::CaravanPitch,
+	PitchType::PermanentTent,
+	PitchType::MobileHome,
+	PitchType::Cottage,
+];
+
 pub type Comfort = Metric<0, 10>;
 
 impl PitchType {
@@ -57,6 +64,17 @@ impl PitchType {
 		.unwrap()
 	}
 
+	/// How many visitors can stay on one pitch of this type.
+	pub const fn guest_capacity(&self) -> u32 {
+		match self {
+			Self::TentPitch => 2,
+			Self::CaravanPitch => 3,
+			Self::MobileHome => 4,
+			Self::PermanentTent => 5,
+			Self::Cottage => 6,
+		}
+	}
+
 	/// Determines whether this pitch type is actually a building, so that when creating it an actual building
 	/// entity must be constructed.
 	pub const fn is_real_building(&self) -> bool {
@@ -111,17 +129,48 @@ impl Tooltipable for PitchType {
 	}
 }
 
-type AccommodationMultiplicity = Metric<1, 2>;
+pub type AccommodationMultiplicity = Metric<1, 2>;
 
-/// A proper pitch for guests; essentially an instance of [`PitchType`].
+/// A proper pitch for guests; essentially an instance of [`PitchType`]. Fields that are missing from older saves are
+/// filled in from the default pitch.
 #[derive(Component, Reflect, Default)]
-#[reflect(Component)]
+#[reflect(Component, Default)]
 pub struct Pitch {
 	/// When the kind is [`None`], the pitch type is unassigned and this pitch is not functional.
-	pub kind:         Option<PitchType>,
+	pub kind:          Option<PitchType>,
 	/// How many of the same pitch are available here. This value rarely goes beyond 1 except for specific
 	/// pitch types.
-	pub multiplicity: AccommodationMultiplicity,
+	pub multiplicity:  AccommodationMultiplicity,
+	/// What visitors pay for one night, per unit of multiplicity. Follows the price that the player set for the
+	/// pitch's type in the [`super::demand::PitchPricing`].
+	pub nightly_price: u64,
+	/// Whether visitors are staying here; follows the visitors, see [`super::booking`].
+	pub occupancy:     Occupancy,
+}
+
+/// The stages that a pitch goes through with every stay of visitors.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Occupancy {
+	/// Visitors can arrive.
+	#[default]
+	Free,
+	/// Visitors are on their way to the pitch.
+	Reserved,
+	/// Visitors are staying on the pitch.
+	Occupied,
+	/// Visitors left the pitch dirty, so it has to be cleaned before the next visitors can arrive.
+	NeedsCleaning,
+}
+
+impl std::fmt::Display for Occupancy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Free => "Free",
+			Self::Reserved => "Reserved",
+			Self::Occupied => "Occupied",
+			Self::NeedsCleaning => "Needs cleaning",
+		})
+	}
 }
 
 impl AreaMarker for Pitch {
@@ -141,6 +190,11 @@ impl Pitch {
 		self.kind.map(|kind| kind.required_area() * (*self.multiplicity as usize)).unwrap_or(0)
 	}
 
+	/// How many visitors can stay here. Pitches without a type can't house anyone.
+	pub fn guest_capacity(&self) -> u32 {
+		self.kind.map_or(0, |kind| kind.guest_capacity() * *self.multiplicity as u32)
+	}
+
 	pub fn apply_properties(&self, properties: &mut WorldInfoProperties, area: &Area) {
 		properties.clear();
 		properties.name = AccommodationBundle::info_base().name;
@@ -222,21 +276,21 @@ pub struct AccommodationBuildingBundle {
 }
 
 impl AccommodationBuildingBundle {
-	pub fn new(kind: PitchType, position: GridPosition, asset_server: &AssetServer) -> Option<Self> {
+	pub fn new(kind: PitchType, position: GridBox, asset_server: &AssetServer) -> Option<Self> {
 		if !kind.is_real_building() {
 			None
 		} else {
 			let image = image_for_pitch(kind);
 			Some(Self {
-				position: GridBox::around(position, kind.size().flat()),
-				sprite:   Sprite {
+				position,
+				sprite: Sprite {
 					anchor: anchor_for_image(image),
 					image: asset_server.load(image),
 					..Default::default()
 				},
-				marker:   AccommodationBuilding,
+				marker: AccommodationBuilding,
 				priority: ObjectPriority::Normal,
-				save:     Save,
+				save: Save,
 			})
 		}
 	}
@@ -248,24 +302,48 @@ impl Plugin for AccommodationManagement {
 		app.register_type::<AccommodationBuilding>()
 			.register_type::<PitchType>()
 			.register_type::<Pitch>()
+			.register_type::<Occupancy>()
 			.register_type::<Comfort>()
 			.register_type::<AccommodationMultiplicity>()
-			.add_systems(Update, add_pitch_graphics.run_if(in_state(GameState::InGame)))
-			.add_systems(FixedUpdate, update_built_pitches.run_if(in_state(GameState::InGame)))
-			.add_systems(
-				FixedUpdate,
-				update_pitch_world_info.after(update_built_pitches).run_if(in_state(GameState::InGame)),
-			);
+			.init_resource::<SiteCapacity>()
+			.add_systems(Update, update_site_capacity.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(Update, add_pitch_graphics.in_set(GameSet::Graphics).in_set(GameState::InGame))
+			.add_systems(FixedUpdate, update_built_pitches.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(FixedUpdate, update_pitch_world_info.in_set(GameSet::UI).in_set(GameState::InGame));
 	}
 }
 
+/// Total accommodation of the campground, counting only pitches with a type.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SiteCapacity {
+	/// Number of usable pitches, counting each pitch as often as its multiplicity.
+	pub pitches: u32,
+	/// Number of visitors that can stay at once.
+	pub guests:  u32,
+}
+
+/// Only pitches that are supplied with all utilities they require can take visitors.
+fn update_site_capacity(pitches: Query<(&Pitch, Option<&Connected>)>, mut capacity: ResMut<SiteCapacity>) {
+	let new_capacity = pitches
+		.iter()
+		.filter(|(pitch, connected)| {
+			pitch.kind.is_some_and(|kind| {
+				kind.required_utilities().is_empty() || connected.is_some_and(Connected::is_functional)
+			})
+		})
+		.fold(SiteCapacity::default(), |sum, (pitch, _)| SiteCapacity {
+			pitches: sum.pitches + *pitch.multiplicity as u32,
+			guests:  sum.guests + pitch.guest_capacity(),
+		});
+	capacity.set_if_neq(new_capacity);
+}
+
 fn update_built_pitches(
 	commands: ParallelCommands,
 	mut pitches: Query<(Entity, &mut Pitch, &Children, &mut ImmutableArea)>,
 	other_areas: Query<&Area>,
 	pitch_building_children: Query<&GridBox, With<AccommodationBuilding>>,
 	ground_map: Res<GroundMap>,
-	mut update: ResMut<Events<UpdateAreas>>,
 ) {
 	if ground_map.is_changed() {
 		let relevant_tiles = |tile: &'_ _| ground_map.kind_of(tile).is_some_and(|kind| kind == Pitch::GROUND_TYPE);
@@ -276,8 +354,6 @@ fn update_built_pitches(
 			.flat_map(|area| area.tiles_iter().filter(relevant_tiles).map(|x| (x, ())))
 			.collect::<HashSet<_>>();
 
-		let needs_update = Arc::new(AtomicBool::new(false));
-
 		pitches.par_iter_mut().for_each(|(entity, mut pitch, children, mut area)| {
 			area.retain_tiles(|tile| relevant_tiles(tile) && !foreign_area_tiles.contains_key(tile));
 			let mut should_destroy = false;
@@ -310,20 +386,17 @@ fn update_built_pitches(
 					entity_commands.remove::<ImmutableArea>();
 					entity_commands.insert(inner_area);
 					entity_commands.despawn_descendants();
+					// The area is mutable again, so it may merge with the areas around it.
+					commands.send_event(UpdateAreas::of(&area));
 				});
 				pitch.kind = None;
 				pitch.multiplicity = AccommodationMultiplicity::default();
-				needs_update.store(true, Ordering::Release);
 			}
 		});
-
-		if needs_update.load(Ordering::Acquire) {
-			update.send_default();
-		}
 	}
 }
 
-fn update_pitch_world_info(
+pub(super) fn update_pitch_world_info(
 	mut immutable_pitches: Query<(&mut WorldInfoProperties, Ref<Pitch>, Ref<ImmutableArea>), Without<Area>>,
 	mut pitches: Query<(&mut WorldInfoProperties, Ref<Pitch>, Ref<Area>), Without<ImmutableArea>>,
 ) {
@@ -342,16 +415,18 @@ fn add_pitch_graphics(
 	asset_server: Res<AssetServer>,
 ) {
 	for entity in &buildings {
-		let result: Option<()> = try {
-			let (parent_pitch, _) = pitches.iter().find(|(_, children)| children.contains(&entity))?;
-			let image = image_for_pitch(parent_pitch.kind?);
+		let image = pitches
+			.iter()
+			.find(|(_, children)| children.contains(&entity))
+			.and_then(|(parent_pitch, _)| parent_pitch.kind)
+			.map(image_for_pitch);
+		if let Some(image) = image {
 			commands.entity(entity).insert(Sprite {
 				anchor: anchor_for_image(image),
 				image: asset_server.load(image),
 				..Default::default()
 			});
-		};
-		if result.is_none() {
+		} else {
 			commands.entity(entity).despawn_recursive();
 		}
 	}
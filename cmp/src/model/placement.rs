@@ -0,0 +1,274 @@
+//! Declarative placement rules for buildables.
+//!
+//! Every [`Buildable`] has a list of [`PlacementRule`]s that a build must satisfy, such as requiring an empty pitch to
+//! build on. The rules are evaluated by [`Placement`] in the same way for the build preview, which is tinted when the
+//! build isn't allowed, and for the build itself, which is rejected with the first violated rule. Plugins can add
+//! their own rules with [`PlacementRuleExt::add_placement_rule`].
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use thiserror::Error;
+
+use super::area::Area;
+use super::buildables::BuildableRegistry;
+use super::fence::edge_path;
+use super::nav::VehicleNavMesh;
+use super::pitch::{AccommodationBuilding, Pitch};
+use super::reachability::EntranceConnections;
+use super::road::{entrances, EntranceRoad};
+use super::spatial::SpatialIndex;
+use super::{Buildable, GridBox, GridPosition, GroundKind, GroundMap, ALL_BUILDABLES};
+use crate::ui::build::BuildMode;
+use crate::ui::error::DisplayableError;
+
+/// A condition that the placement of a buildable has to satisfy.
+#[derive(Clone, Copy, Debug)]
+pub enum PlacementRule {
+	/// The build must start inside a pitch that has no pitch type yet.
+	InsideEmptyPitch,
+	/// The entire build must fit into the pitch that it starts in.
+	FitsIntoPitch,
+	/// The pitch that the build starts in must be large enough for the pitch type being built.
+	PitchLargeEnough,
+	/// All tiles of the build must be of one of these ground kinds.
+	OnGround(&'static [GroundKind]),
+	/// At least one tile next to the build must be a pathway.
+	NextToPath,
+	/// No tile of the build may have ground, so that it lies outside the campground.
+	OutsideCampground,
+	/// No building may be within this many tiles of the build.
+	DistanceToBuildings(u32),
+	/// The build must not collide with any placed object, such as buildings or decorations.
+	Unoccupied,
+	/// Vehicles must still be able to drive to every pathway that they can drive to from the entrance road now, even
+	/// though they can't drive on the build.
+	KeepsParkConnected,
+	/// A rule defined by a plugin.
+	Custom(CustomPlacementRule),
+}
+
+/// Checks a custom placement rule, given the tiles that the build covers.
+pub type CustomPlacementRule = fn(&Placement, Buildable, &[GridPosition]) -> Result<(), PlacementError>;
+
+impl Buildable {
+	/// The rules that every build of this buildable is subject to.
+	pub fn placement_rules(&self) -> &'static [PlacementRule] {
+		match self {
+			Self::PitchType(_) => &[
+				PlacementRule::InsideEmptyPitch,
+				PlacementRule::FitsIntoPitch,
+				PlacementRule::PitchLargeEnough,
+				PlacementRule::Unoccupied,
+			],
+			Self::SupplyPoint(_) =>
+				&[PlacementRule::OnGround(&[GroundKind::Grass, GroundKind::Pathway]), PlacementRule::Unoccupied],
+			Self::Decoration(_) => &[PlacementRule::OnGround(&[GroundKind::Grass]), PlacementRule::Unoccupied],
+			Self::Facility(_) =>
+				&[PlacementRule::OnGround(&[GroundKind::Grass]), PlacementRule::NextToPath, PlacementRule::Unoccupied],
+			Self::PoolArea | Self::Pitch => &[PlacementRule::Unoccupied, PlacementRule::KeepsParkConnected],
+			Self::Ground(_) => &[PlacementRule::Unoccupied],
+			Self::EntranceRoad =>
+				&[PlacementRule::OutsideCampground, PlacementRule::NextToPath, PlacementRule::Unoccupied],
+			Self::Fence(_) | Self::Gate | Self::Conduit(_) => &[],
+		}
+	}
+
+	/// All tiles covered by building this buildable from the start to the end position, with its usual footprint. Use
+	/// [`BuildableRegistry::footprint`] for the footprint that the player sees.
+	pub fn footprint(&self, start_position: GridPosition, end_position: GridPosition) -> Vec<GridPosition> {
+		match self.build_mode() {
+			BuildMode::Single => GridBox::around(start_position, self.size().flat()).floor_positions().collect(),
+			BuildMode::Line => start_position.line_to_2d(end_position).collect(),
+			BuildMode::Rect => Area::from_rect(start_position, end_position).tiles_iter().collect(),
+			BuildMode::Edge =>
+				edge_path(start_position, end_position).into_iter().map(|(position, _)| position).collect(),
+		}
+	}
+}
+
+/// Any reason that a buildable can't be placed somewhere; eventually propagated to the end-user.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+	#[error("There is no pitch to build on here.")]
+	NoAccommodationHere,
+	#[error("Building doesn’t have enough space to be built here.")]
+	NoSpace,
+	#[error(
+		"The pitch area is too small for this pitch type; {} tiles are required but there are only {} \
+		 tiles.", .required, .actual
+	)]
+	PitchTooSmall { required: usize, actual: usize },
+	#[error("This can only be built on {}.", .allowed)]
+	WrongGround { allowed: String },
+	#[error("This has to be built next to a pathway.")]
+	NoPathAccess,
+	#[error("This has to be built outside the campground.")]
+	InsideCampground,
+	#[error("This has to be at least {} tiles away from other buildings.", .distance)]
+	TooCloseToBuilding { distance: u32 },
+	#[error("Something is already standing here.")]
+	Occupied,
+	#[error("This would cut off pathways from the entrance road, so vehicles couldn’t drive there anymore.")]
+	DisconnectsPark,
+	#[error("{}", .0)]
+	Custom(String),
+}
+
+impl DisplayableError for PlacementError {
+	fn name(&self) -> &str {
+		"Build error"
+	}
+}
+
+/// The placement rules of all buildables, including those added by plugins.
+#[derive(Resource, Clone, Debug)]
+pub struct PlacementRules(Vec<(Buildable, PlacementRule)>);
+
+impl Default for PlacementRules {
+	fn default() -> Self {
+		Self(
+			ALL_BUILDABLES
+				.iter()
+				.flat_map(|buildable| buildable.placement_rules().iter().map(|rule| (*buildable, *rule)))
+				.collect(),
+		)
+	}
+}
+
+/// Extension point for restricting where buildables can be placed.
+pub trait PlacementRuleExt {
+	/// Adds a rule that all builds of the buildable have to satisfy, in addition to its existing rules.
+	fn add_placement_rule(&mut self, buildable: Buildable, rule: PlacementRule) -> &mut Self;
+}
+
+impl PlacementRuleExt for App {
+	fn add_placement_rule(&mut self, buildable: Buildable, rule: PlacementRule) -> &mut Self {
+		self.init_resource::<PlacementRules>();
+		self.world_mut().resource_mut::<PlacementRules>().0.push((buildable, rule));
+		self
+	}
+}
+
+/// Evaluates placement rules against the world.
+#[derive(SystemParam)]
+pub struct Placement<'w, 's> {
+	rules:             Res<'w, PlacementRules>,
+	/// Ground tiles of the world.
+	pub ground_map:    Res<'w, GroundMap>,
+	/// Pitches without a pitch type; pitches with a pitch type have an immutable area instead.
+	pub empty_pitches: Query<'w, 's, (&'static Area, &'static Pitch)>,
+	/// All buildings on pitches.
+	pub buildings:     Query<'w, 's, &'static GridBox, With<AccommodationBuilding>>,
+	/// All placed objects that take up space, including buildings.
+	pub objects:       Res<'w, SpatialIndex>,
+	/// Tiles that are connected to the entrance road.
+	pub connections:   Res<'w, EntranceConnections>,
+	pub vehicle_mesh:  Res<'w, VehicleNavMesh>,
+	/// Definitions of the buildables, for their footprints.
+	pub registry:      Res<'w, BuildableRegistry>,
+	pub roads:         Query<'w, 's, &'static EntranceRoad>,
+}
+
+impl Placement<'_, '_> {
+	/// Checks all rules of the buildable for a build from the start to the end position, and returns the first
+	/// violated rule's error.
+	pub fn check(
+		&self,
+		buildable: Buildable,
+		start_position: GridPosition,
+		end_position: GridPosition,
+	) -> Result<(), PlacementError> {
+		let footprint = self.registry.footprint(buildable, start_position, end_position);
+		self.rules
+			.0
+			.iter()
+			.filter(|(rule_buildable, _)| *rule_buildable == buildable)
+			.try_for_each(|(_, rule)| self.check_rule(*rule, buildable, start_position, &footprint))
+	}
+
+	fn check_rule(
+		&self,
+		rule: PlacementRule,
+		buildable: Buildable,
+		start_position: GridPosition,
+		footprint: &[GridPosition],
+	) -> Result<(), PlacementError> {
+		let pitch_area = || {
+			self.empty_pitches
+				.iter()
+				.map(|(area, _)| area)
+				.find(|area| area.contains(&start_position))
+				.ok_or(PlacementError::NoAccommodationHere)
+		};
+		match rule {
+			PlacementRule::InsideEmptyPitch => pitch_area().map(|_| ()),
+			PlacementRule::FitsIntoPitch => {
+				let area = pitch_area()?;
+				footprint.iter().all(|position| area.contains(position)).then_some(()).ok_or(PlacementError::NoSpace)
+			},
+			PlacementRule::PitchLargeEnough => {
+				let Buildable::PitchType(kind) = buildable else {
+					return Ok(());
+				};
+				let area = pitch_area()?;
+				if area.size() < kind.required_area() {
+					Err(PlacementError::PitchTooSmall { required: kind.required_area(), actual: area.size() })
+				} else {
+					Ok(())
+				}
+			},
+			PlacementRule::OnGround(allowed) => {
+				let on_allowed_ground = footprint
+					.iter()
+					.all(|position| self.ground_map.kind_of(position).is_some_and(|kind| allowed.contains(&kind)));
+				on_allowed_ground.then_some(()).ok_or_else(|| PlacementError::WrongGround {
+					allowed: allowed.iter().map(ToString::to_string).collect::<Vec<_>>().join(" or "),
+				})
+			},
+			PlacementRule::NextToPath => footprint
+				.iter()
+				.flat_map(GridPosition::neighbors)
+				.filter(|neighbor| !footprint.contains(neighbor))
+				.any(|neighbor| self.ground_map.kind_of(&neighbor) == Some(GroundKind::Pathway))
+				.then_some(())
+				.ok_or(PlacementError::NoPathAccess),
+			PlacementRule::OutsideCampground => footprint
+				.iter()
+				.all(|position| self.ground_map.kind_of(position).is_none())
+				.then_some(())
+				.ok_or(PlacementError::InsideCampground),
+			PlacementRule::DistanceToBuildings(distance) => {
+				let too_close = self.buildings.iter().flat_map(GridBox::floor_positions).any(|building_tile| {
+					footprint.iter().any(|position| {
+						let offset = (**position - *building_tile).abs();
+						offset.x.max(offset.y) <= distance as i32
+					})
+				});
+				(!too_close).then_some(()).ok_or(PlacementError::TooCloseToBuilding { distance })
+			},
+			PlacementRule::Unoccupied => {
+				let occupied = self.objects.is_occupied(footprint);
+				(!occupied).then_some(()).ok_or(PlacementError::Occupied)
+			},
+			PlacementRule::KeepsParkConnected => {
+				let removed = footprint
+					.iter()
+					.copied()
+					.filter(|position| self.connections.is_drivable(*position))
+					.collect::<HashSet<_>>();
+				if removed.is_empty() {
+					return Ok(());
+				}
+				let reached =
+					self.vehicle_mesh.reachable_without(entrances(&self.roads, &*self.vehicle_mesh), &removed);
+				let disconnects = self
+					.connections
+					.roads()
+					.any(|position| !removed.contains(&position) && !reached.contains(&position));
+				(!disconnects).then_some(()).ok_or(PlacementError::DisconnectsPark)
+			},
+			PlacementRule::Custom(check) => check(self, buildable, footprint),
+		}
+	}
+}
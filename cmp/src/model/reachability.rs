@@ -0,0 +1,215 @@
+//! Checks that visitors can walk from the park entrance to every pitch.
+//!
+//! The park entrance is the gate of the [`EntranceRoad`], where visitors and their vehicles enter the campground. A
+//! pitch is reachable if one of the tiles next to it is a pathway that the people [`NavMesh`] connects to an entrance.
+//! Pitches that aren't reachable are marked with a warning icon and an entry in their world info.
+//!
+//! The tiles connected to the entrance are flood-filled again whenever the nav mesh or the entrance road changes, which
+//! rechecks all pitches. Otherwise, only pitches near the regions of [`UpdateAreas`] events are rechecked, since those
+//! are the only ones whose tiles may have changed. The tiles that vehicles can drive to from the entrance are tracked
+//! as well; the campground only opens once vehicles can drive to a reception, and pitches and pools can't be built
+//! where they would cut off pathways from the entrance (see [`PlacementRule::KeepsParkConnected`]).
+//!
+//! [`NavMesh`]: super::nav::NavMesh
+//! [`PlacementRule::KeepsParkConnected`]: super::placement::PlacementRule::KeepsParkConnected
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use super::area::{clean_area_events, Area, ImmutableArea, UpdateAreas};
+use super::nav::{PeopleNavMesh, VehicleNavMesh};
+use super::pitch::update_pitch_world_info;
+use super::road::{entrances, EntranceRoad};
+use super::{GridBox, GridPosition, GroundKind, GroundMap, Pitch};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, WARNING_ICON};
+use crate::graphics::ObjectPriority;
+use crate::ui::world_info::{WorldInfoProperties, WorldInfoProperty};
+
+pub struct ReachabilityManagement;
+
+impl Plugin for ReachabilityManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<EntranceConnections>()
+			.add_systems(
+				FixedUpdate,
+				check_reachability.before(clean_area_events).in_set(GameSet::Simulation).in_set(GameState::InGame),
+			)
+			.add_systems(
+				FixedUpdate,
+				(update_reachability_world_info.after(update_pitch_world_info), show_unreachable_warnings)
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(GameState::Loading), reset_reachability)
+			.add_systems(OnEnter(GameState::MainMenu), reset_reachability);
+	}
+}
+
+/// Whether visitors can walk to a pitch from the park entrance.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reachability {
+	pub reachable: bool,
+}
+
+/// The nav mesh tiles that are connected to the park entrance.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct EntranceConnections {
+	/// Tiles of the people nav mesh.
+	tiles: HashSet<GridPosition>,
+	/// Tiles of the vehicle nav mesh.
+	roads: HashSet<GridPosition>,
+	/// Whether the connections were determined since the game was loaded.
+	ready: bool,
+}
+
+impl EntranceConnections {
+	/// Whether visitors can walk from the entrance to the tile.
+	pub fn contains(&self, tile: GridPosition) -> bool {
+		self.tiles.contains(&tile)
+	}
+
+	/// Whether vehicles can drive from the entrance to the tile.
+	pub fn is_drivable(&self, tile: GridPosition) -> bool {
+		self.roads.contains(&tile)
+	}
+
+	/// All tiles that vehicles can drive to from the entrance.
+	pub fn roads(&self) -> impl Iterator<Item = GridPosition> + '_ {
+		self.roads.iter().copied()
+	}
+
+	/// Whether the connections were determined since the game was loaded. Until then, nothing seems to be connected.
+	pub fn is_ready(&self) -> bool {
+		self.ready
+	}
+}
+
+/// Warning icon floating above an unreachable pitch.
+#[derive(Component, Clone, Copy, Debug)]
+struct UnreachableWarning;
+
+/// Whether one of the tiles next to the area is a pathway that is connected to the entrance.
+fn is_reachable(area: &Area, ground_map: &GroundMap, connections: &EntranceConnections) -> bool {
+	area.tiles_iter().flat_map(|tile| tile.neighbors()).any(|neighbor| {
+		!area.contains(&neighbor)
+			&& ground_map.kind_of(&neighbor) == Some(GroundKind::Pathway)
+			&& connections.contains(neighbor)
+	})
+}
+
+fn check_reachability(
+	ground_map: Res<GroundMap>,
+	mesh: Res<PeopleNavMesh>,
+	vehicle_mesh: Res<VehicleNavMesh>,
+	roads: Query<&EntranceRoad>,
+	changed_roads: Query<(), Changed<EntranceRoad>>,
+	area_updates: Res<Events<UpdateAreas>>,
+	mut connections: ResMut<EntranceConnections>,
+	mut pitches: Query<(Entity, Option<&Area>, Option<&ImmutableArea>, Option<&mut Reachability>), With<Pitch>>,
+	mut commands: Commands,
+) {
+	// While a nav mesh is still applying changes in batches, its connectivity is incomplete.
+	let roads_changed = !changed_roads.is_empty();
+	let mesh_changed = (mesh.is_changed() || roads_changed) && !mesh.has_pending();
+	if mesh_changed {
+		connections.tiles = mesh.reachable_from(entrances(&roads, &*mesh));
+		debug!("{} tiles are connected to the park entrance", connections.tiles.len());
+	}
+	if (vehicle_mesh.is_changed() || roads_changed) && !vehicle_mesh.has_pending() {
+		connections.roads = vehicle_mesh.reachable_from(entrances(&roads, &*vehicle_mesh));
+		connections.ready = true;
+		debug!("{} tiles can be driven to from the park entrance", connections.roads.len());
+	}
+
+	// Pitches touching the regions may have gained or lost tiles next to pathways.
+	let regions = area_updates
+		.iter_current_update_events()
+		.map(|update| {
+			GridBox::from_corners(
+				update.region.smallest() - IVec3::new(1, 1, 0),
+				update.region.largest() + IVec3::new(1, 1, 0),
+			)
+		})
+		.collect::<Vec<_>>();
+
+	for (entity, area, immutable_area, reachability) in &mut pitches {
+		let Some(area) = area.or(immutable_area.map(|area| &area.0)) else {
+			continue;
+		};
+		let needs_check =
+			mesh_changed || reachability.is_none() || regions.iter().any(|region| region.intersects_2d(area.aabb()));
+		if !needs_check {
+			continue;
+		}
+		let new_reachability = Reachability { reachable: is_reachable(area, &ground_map, &connections) };
+		match reachability {
+			Some(mut reachability) => {
+				reachability.set_if_neq(new_reachability);
+			},
+			None => {
+				commands.entity(entity).insert(new_reachability);
+			},
+		}
+	}
+}
+
+/// Adds or removes the [`WorldInfoProperty::Unreachable`] entry. Pitches rebuild their properties when their area
+/// changes, so this also runs whenever the properties changed.
+fn update_reachability_world_info(
+	mut pitches: Query<
+		(&Reachability, &mut WorldInfoProperties),
+		Or<(Changed<Reachability>, Changed<WorldInfoProperties>)>,
+	>,
+) {
+	for (reachability, mut properties) in &mut pitches {
+		let is_listed = properties.iter().any(|property| matches!(property, WorldInfoProperty::Unreachable));
+		// Only touch the properties when they need to change, or this would run again on the next update.
+		if reachability.reachable && is_listed {
+			properties.retain(|property| !matches!(property, WorldInfoProperty::Unreachable));
+		} else if !reachability.reachable && !is_listed {
+			properties.push(WorldInfoProperty::Unreachable);
+		}
+	}
+}
+
+/// Recreates all warning icons when the reachability or area of any pitch changed, or a pitch was removed.
+fn show_unreachable_warnings(
+	pitches: Query<(Ref<Reachability>, Option<&Area>, Option<&ImmutableArea>)>,
+	moved_pitches: Query<(), (With<Reachability>, Or<(Changed<Area>, Changed<ImmutableArea>)>)>,
+	warnings: Query<Entity, With<UnreachableWarning>>,
+	mut removed: RemovedComponents<Reachability>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let removed = removed.read().count() > 0;
+	if !removed && moved_pitches.is_empty() && !pitches.iter().any(|(reachability, ..)| reachability.is_changed()) {
+		return;
+	}
+
+	warnings.iter().for_each(|warning| commands.entity(warning).despawn());
+	for (reachability, area, immutable_area) in &pitches {
+		let Some(area) = area.or(immutable_area.map(|area| &area.0)) else {
+			continue;
+		};
+		if reachability.reachable {
+			continue;
+		}
+		commands.spawn((
+			UnreachableWarning,
+			// Float above the pitch's buildings.
+			area.aabb().center() + IVec3::new(0, 0, 3),
+			ObjectPriority::Overlay,
+			Sprite { image: asset_server.load(WARNING_ICON), anchor: anchor_for_image(WARNING_ICON), ..default() },
+		));
+	}
+}
+
+fn reset_reachability(
+	mut connections: ResMut<EntranceConnections>,
+	warnings: Query<Entity, With<UnreachableWarning>>,
+	mut commands: Commands,
+) {
+	*connections = EntranceConnections::default();
+	warnings.iter().for_each(|warning| commands.entity(warning).despawn());
+}
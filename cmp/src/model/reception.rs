@@ -0,0 +1,227 @@
+//! The reception, where visitors check in and out.
+//!
+//! Arriving vehicles first drive to a reception and queue at its desk to check in, and only then drive on to their
+//! pitch. After their stay, they queue at a reception again to check out before leaving the campground. Every
+//! [`Facility::Reception`] has a [`ReceptionDesk`] that serves its queue one vehicle at a time; its world info shows
+//! the arrival and departure queues.
+//!
+//! The campground only opens once it has a reception that vehicles can drive to from the entrance road. Until then, no
+//! visitors arrive, see [`CampgroundStatus`].
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use super::facility::{access_tile, Facility};
+use super::nav::VehicleNavMesh;
+use super::reachability::EntranceConnections;
+use super::vehicle::Vehicle;
+use super::GridBox;
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::error::{DisplayableError, ErrorBox};
+use crate::ui::notifications::NotificationEvent;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct ReceptionManagement;
+
+impl Plugin for ReceptionManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<CampgroundStatus>()
+			.add_systems(
+				FixedUpdate,
+				(add_reception_desks, update_campground_status, serve_reception_queues, count_reception_traffic)
+					.chain()
+					.in_set(GameSet::SimulationEarly)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(GameState::Loading), reset_campground_status)
+			.add_systems(OnEnter(GameState::MainMenu), reset_campground_status)
+			.add_world_info_section::<ReceptionDesk>("Arrivals and departures", |desk| {
+				[
+					("Arriving", desk.heading_in),
+					("Waiting to check in", desk.waiting(Visit::CheckIn)),
+					("Waiting to check out", desk.waiting(Visit::CheckOut)),
+					("Coming to check out", desk.heading_out),
+				]
+				.into_iter()
+				.map(|(name, count)| DialogWidget::Property { name: name.to_string(), value: count.to_string() })
+				.collect()
+			});
+	}
+}
+
+/// How long checking in or out takes for one vehicle.
+const SERVICE_TIME: Duration = Duration::from_secs(3);
+
+/// Why a vehicle comes to the reception.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visit {
+	CheckIn,
+	CheckOut,
+}
+
+/// The queue of vehicles at a reception.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ReceptionDesk {
+	/// Vehicles waiting at the reception, in the order they are served.
+	queue:       VecDeque<(Entity, Visit)>,
+	/// How long the first vehicle of the queue has been served.
+	served_for:  Duration,
+	/// Vehicles driving to the reception to check in.
+	heading_in:  usize,
+	/// Vehicles driving to the reception to check out.
+	heading_out: usize,
+}
+
+impl ReceptionDesk {
+	/// Adds the vehicle to the end of the queue.
+	pub fn join(&mut self, vehicle: Entity, visit: Visit) {
+		if !self.is_waiting(vehicle) {
+			self.queue.push_back((vehicle, visit));
+		}
+	}
+
+	/// Removes the vehicle from the queue without serving it.
+	pub fn leave(&mut self, vehicle: Entity) {
+		if self.queue.front().is_some_and(|(first, _)| *first == vehicle) {
+			self.served_for = Duration::ZERO;
+		}
+		self.queue.retain(|(other, _)| *other != vehicle);
+	}
+
+	/// Whether the vehicle is still in the queue. Vehicles leave the queue once they were served.
+	pub fn is_waiting(&self, vehicle: Entity) -> bool {
+		self.queue.iter().any(|(other, _)| *other == vehicle)
+	}
+
+	/// Number of vehicles in the queue for the visit.
+	pub fn waiting(&self, visit: Visit) -> usize {
+		self.queue.iter().filter(|(_, other)| *other == visit).count()
+	}
+}
+
+/// Whether visitors can come to the campground.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CampgroundStatus {
+	/// Not determined yet, since the game was just loaded.
+	#[default]
+	Unknown,
+	/// There is no reception, so visitors can't check in.
+	Closed,
+	/// No reception can be reached from the entrance road, so visitors can't get to one.
+	Disconnected,
+	Open,
+}
+
+/// Why the campground can't open; eventually propagated to the end-user.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OpeningError {
+	#[error(
+		"Your campground can’t open, since visitors can’t drive from the entrance road to any reception. Connect a \
+		 reception to the entrance road with pathways."
+	)]
+	NoRoadConnection,
+}
+
+impl DisplayableError for OpeningError {
+	fn name(&self) -> &str {
+		"Campground closed"
+	}
+}
+
+fn add_reception_desks(facilities: Query<(Entity, &Facility), Without<ReceptionDesk>>, mut commands: Commands) {
+	for (entity, facility) in &facilities {
+		if *facility == Facility::Reception {
+			commands.entity(entity).insert(ReceptionDesk::default());
+		}
+	}
+}
+
+/// Opens or closes the campground depending on whether it has a reception that vehicles can drive to from the entrance
+/// road, and tells the player about it.
+fn update_campground_status(
+	facilities: Query<(&Facility, &GridBox)>,
+	mesh: Res<VehicleNavMesh>,
+	connections: Res<EntranceConnections>,
+	mut status: ResMut<CampgroundStatus>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut errors: EventWriter<ErrorBox>,
+) {
+	let mut receptions = facilities.iter().filter(|(facility, _)| **facility == Facility::Reception).peekable();
+	let new_status = if receptions.peek().is_none() {
+		CampgroundStatus::Closed
+	} else if !connections.is_ready() {
+		// Whether the receptions are connected isn't known yet.
+		return;
+	} else if receptions
+		.any(|(_, grid_box)| access_tile(grid_box, &*mesh).is_some_and(|tile| connections.is_drivable(tile)))
+	{
+		CampgroundStatus::Open
+	} else {
+		CampgroundStatus::Disconnected
+	};
+	if *status == new_status {
+		return;
+	}
+	match (*status, new_status) {
+		(CampgroundStatus::Closed | CampgroundStatus::Disconnected, CampgroundStatus::Open) => {
+			notifications.send(NotificationEvent::info("Your campground has a reception and is open for visitors."));
+		},
+		(_, CampgroundStatus::Disconnected) => {
+			errors.send(OpeningError::NoRoadConnection.into());
+		},
+		(_, CampgroundStatus::Closed) => {
+			notifications.send(NotificationEvent::warning(
+				"Your campground has no reception and is closed. Build a reception so that visitors can check in.",
+			));
+		},
+		_ => {},
+	}
+	*status = new_status;
+}
+
+/// Serves the first vehicle of every queue, which leaves the queue once it is done.
+fn serve_reception_queues(time: Res<Time>, vehicles: Query<(), With<Vehicle>>, mut desks: Query<&mut ReceptionDesk>) {
+	for mut desk in &mut desks {
+		// Vehicles may have disappeared while waiting.
+		desk.queue.retain(|(vehicle, _)| vehicles.contains(*vehicle));
+		if desk.queue.is_empty() {
+			desk.served_for = Duration::ZERO;
+			continue;
+		}
+		desk.served_for += time.delta();
+		if desk.served_for >= SERVICE_TIME {
+			desk.served_for = Duration::ZERO;
+			desk.queue.pop_front();
+		}
+	}
+}
+
+fn count_reception_traffic(vehicles: Query<(Entity, &Vehicle)>, mut desks: Query<&mut ReceptionDesk>) {
+	for mut desk in &mut desks {
+		desk.heading_in = 0;
+		desk.heading_out = 0;
+	}
+	for (entity, vehicle) in &vehicles {
+		let Some((reception, visit)) = vehicle.reception() else {
+			continue;
+		};
+		let Ok(mut desk) = desks.get_mut(reception) else {
+			continue;
+		};
+		if desk.is_waiting(entity) {
+			continue;
+		}
+		match visit {
+			Visit::CheckIn => desk.heading_in += 1,
+			Visit::CheckOut => desk.heading_out += 1,
+		}
+	}
+}
+
+fn reset_campground_status(mut status: ResMut<CampgroundStatus>) {
+	*status = CampgroundStatus::Unknown;
+}
@@ -0,0 +1,112 @@
+//! The entrance road, which connects the campground to the outside world.
+//!
+//! Every campground has an [`EntranceRoad`] at the edge of the world. Arriving vehicles come in on it and departing
+//! vehicles leave on it, and new staff walk in from it. The road lies outside the campground and takes up its tile, so
+//! nothing can be built there. It joins the campground at its gate, a pathway tile next to the road; everything that
+//! the nav meshes connect to the gate is connected to the entrance road.
+//!
+//! New worlds get their road south of the world origin, where no obstacles are. Games that were saved before entrance
+//! roads existed get one where a pathway meets the edge of the world.
+//!
+//! The player can build more entrance roads outside the campground, next to a pathway that becomes their gate. Every
+//! road is a place where vehicles arrive and leave: arriving vehicles pick a road at random, and departing vehicles
+//! take the road closest to them, which spreads the traffic across the campground.
+
+use bevy::prelude::*;
+use moonshine_save::save::Save;
+
+use super::burst::BurstPlacement;
+use super::nav::{NavCategoryParam, NavMesh};
+use super::spatial::SpatialIndex;
+use super::{BoundingBox, GridBox, GridPosition, GroundKind, GroundMap};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, image_for_ground};
+use crate::graphics::ObjectPriority;
+use crate::ui::world_info::WorldInfoProperties;
+
+pub struct RoadManagement;
+
+impl Plugin for RoadManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<EntranceRoad>()
+			.add_systems(OnTransition { exited: GameState::Loading, entered: GameState::InGame }, place_entrance_road)
+			.add_systems(Update, add_road_graphics.in_set(GameSet::Graphics).in_set(GameState::InGame));
+	}
+}
+
+/// Where the entrance road of a new world lies.
+const NEW_WORLD_ROAD: GridPosition = GridPosition(IVec3::new(0, -10, 0));
+/// Tint of the entrance road, which is drawn as a darker pathway.
+const ROAD_TINT: Color = Color::srgb(0.55, 0.55, 0.6);
+
+/// The road that connects the campground to the outside world, on the tile of the entity's [`GridBox`].
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct EntranceRoad {
+	/// The tile next to the road where it joins the campground.
+	pub gate: GridPosition,
+}
+
+/// The gates of the entrance roads that are part of the nav mesh.
+pub fn entrances<const N: NavCategoryParam>(roads: &Query<&EntranceRoad>, mesh: &NavMesh<N>) -> Vec<GridPosition> {
+	roads.iter().map(|road| road.gate).filter(|gate| mesh.contains(*gate)).collect()
+}
+
+/// Gives campgrounds without an entrance road a new one, and makes its gate a pathway.
+fn place_entrance_road(
+	roads: Query<(), With<EntranceRoad>>,
+	ground_map: Res<GroundMap>,
+	objects: Res<SpatialIndex>,
+	mut burst: ResMut<BurstPlacement>,
+	mut commands: Commands,
+) {
+	if !roads.is_empty() {
+		return;
+	}
+	let is_free = |tile: &GridPosition| ground_map.kind_of(tile).is_none() && objects.at(*tile).next().is_none();
+	let (road, gate) = ground_map
+		.kinds()
+		.filter(|(_, kind)| *kind == GroundKind::Pathway)
+		.filter_map(|(gate, _)| Some((gate.neighbors().into_iter().find(is_free)?, gate)))
+		.min_by_key(|(_, gate)| (gate.x, gate.y, gate.z))
+		.unwrap_or((NEW_WORLD_ROAD, NEW_WORLD_ROAD + IVec3::Y));
+
+	if ground_map.kind_of(&gate) != Some(GroundKind::Pathway) {
+		burst.queue_tile(gate, GroundKind::Pathway);
+	}
+	debug!("Placed entrance road at {road:?} with gate {gate:?}");
+	spawn_entrance_road(road, EntranceRoad { gate }, &mut commands);
+}
+
+/// The gate that a road built on the tile would join the campground at, which is a neighboring pathway.
+pub fn gate_for(road: GridPosition, ground_map: &GroundMap) -> Option<GridPosition> {
+	road.neighbors()
+		.into_iter()
+		.filter(|neighbor| ground_map.kind_of(neighbor) == Some(GroundKind::Pathway))
+		.min_by_key(|gate| (gate.x, gate.y, gate.z))
+}
+
+/// Spawns an entrance road on the tile.
+pub fn spawn_entrance_road(road: GridPosition, entrance: EntranceRoad, commands: &mut Commands) {
+	commands.spawn((entrance, GridBox::around(road, BoundingBox::fixed::<1, 1, 1>().flat()), Save));
+}
+
+/// Graphics are added separately, so that loaded roads receive them as well.
+fn add_road_graphics(
+	roads: Query<Entity, (With<EntranceRoad>, Without<Sprite>)>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for road in &roads {
+		let image = image_for_ground(GroundKind::Pathway);
+		commands.entity(road).insert((
+			Sprite { anchor: anchor_for_image(image), image: asset_server.load(image), color: ROAD_TINT, ..default() },
+			ObjectPriority::Ground,
+			WorldInfoProperties::basic(
+				"Entrance Road".to_string(),
+				"The road to the outside world. Visitors arrive and leave here, and new staff come in from here."
+					.to_string(),
+			),
+		));
+	}
+}
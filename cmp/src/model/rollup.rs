@@ -0,0 +1,291 @@
+//! End-of-day rollup of the campground's statistics.
+//!
+//! Throughout the day, the simulation records income, expenses, visitor movements, satisfaction samples and incidents
+//! into the [`DailyTally`]. When a day ends, the rollup runs its steps in the fixed order of [`RollupSet`]: payments
+//! that are due at the end of the day are settled first, so that they count towards the day that is closing. Then the
+//! tally is closed into a [`DaySummary`] and appended to the [`DailyHistory`], and finally the player is notified.
+//!
+//! A day is a fixed amount of simulated time ([`DAY_LENGTH`]), which the [`super::time_of_day::TimeOfDay`] divides
+//! into hours. The [`super::calendar::Calendar`] counts the days into months and seasons. Staff wages are paid in
+//! [`RollupSet::Wages`], as are the operating costs of shops. Contracts don't exist yet; their end-of-day logic belongs
+//! in [`RollupSet::Deadlines`].
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::calendar::Calendar;
+use super::commerce::CommercialTally;
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::dialog::OpenDialog;
+
+/// Simulated time that makes up one day.
+pub const DAY_LENGTH: Duration = Duration::from_secs(5 * 60);
+
+/// Simulated time that the given number of in-game hours take.
+pub fn in_game_hours(hours: f32) -> Duration {
+	DAY_LENGTH.mul_f32(hours.max(0.) / 24.)
+}
+
+pub struct RollupManagement;
+
+impl Plugin for RollupManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<DayClock>()
+			.register_type::<DailyTally>()
+			.register_type::<DaySummary>()
+			.register_type::<Vec<DaySummary>>()
+			.register_type::<DailyHistory>()
+			.init_resource::<DayClock>()
+			.init_resource::<DailyTally>()
+			.init_resource::<DailyHistory>()
+			.add_event::<DayEnded>()
+			.configure_sets(
+				FixedUpdate,
+				(RollupSet::Deadlines, RollupSet::Wages, RollupSet::Aggregate, RollupSet::Notify)
+					.chain()
+					.run_if(on_event::<DayEnded>)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				FixedUpdate,
+				advance_day.before(RollupSet::Deadlines).in_set(GameSet::Simulation).in_set(GameState::InGame),
+			)
+			.add_systems(FixedUpdate, close_day.in_set(RollupSet::Aggregate))
+			.add_systems(FixedUpdate, notify_day_summary.in_set(RollupSet::Notify));
+	}
+}
+
+/// The steps of the end-of-day rollup, in the order they run. They only run in the fixed update in which a day ended.
+#[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RollupSet {
+	/// Checking deadlines that fall on the closing day, such as those of contracts.
+	Deadlines,
+	/// Paying wages and other recurring expenses into the closing day's tally.
+	Wages,
+	/// Closing the day's tally into the history.
+	Aggregate,
+	/// Telling the player about the closed day.
+	Notify,
+}
+
+/// Sent when a day has ended, before its rollup runs.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DayEnded {
+	/// The day that ended, starting at 1.
+	pub day: u32,
+}
+
+/// Keeps track of the current day.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Resource)]
+pub struct DayClock {
+	/// Number of days that have ended so far.
+	days_passed: u32,
+	/// Simulated time passed in the current day.
+	elapsed:     Duration,
+}
+
+impl DayClock {
+	/// The current day, starting at 1.
+	pub const fn day(&self) -> u32 {
+		self.days_passed + 1
+	}
+
+	/// How much of the current day has passed, between 0 and 1.
+	pub fn progress(&self) -> f32 {
+		self.elapsed.as_secs_f32() / DAY_LENGTH.as_secs_f32()
+	}
+}
+
+/// Running statistics of the current day.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Resource)]
+pub struct DailyTally {
+	income:               u64,
+	expenses:             u64,
+	arrivals:             u32,
+	departures:           u32,
+	satisfaction_total:   f32,
+	satisfaction_samples: u32,
+	incidents:            u32,
+	/// Missing from older saves.
+	#[reflect(default)]
+	refunds:              u64,
+	/// Missing from older saves.
+	#[reflect(default)]
+	turned_away:          u32,
+}
+
+impl DailyTally {
+	/// Records money earned, such as from visitors paying for their stay.
+	pub fn record_income(&mut self, amount: u64) {
+		self.income += amount;
+	}
+
+	/// Records money spent, such as on wages or upkeep.
+	pub fn record_expense(&mut self, amount: u64) {
+		self.expenses += amount;
+	}
+
+	/// Takes back money spent, such as on a build that was undone. Refunds of more than today's expenses, for builds
+	/// of an earlier day, count as income.
+	pub fn refund_expense(&mut self, amount: u64) {
+		let refunded = amount.min(self.expenses);
+		self.expenses -= refunded;
+		self.income += amount - refunded;
+	}
+
+	/// Records money refunded for demolished builds. Refunds are kept apart from income, so that selling off the
+	/// campground doesn't look like running it well.
+	pub fn record_refund(&mut self, amount: u64) {
+		self.refunds += amount;
+	}
+
+	/// Takes back a refund, such as when a demolition is undone. Taking back more than today's refunds, for a
+	/// demolition of an earlier day, counts as an expense.
+	pub fn revoke_refund(&mut self, amount: u64) {
+		let revoked = amount.min(self.refunds);
+		self.refunds -= revoked;
+		self.expenses += amount - revoked;
+	}
+
+	/// Records a visitor group arriving at the campground.
+	pub fn record_arrival(&mut self) {
+		self.arrivals += 1;
+	}
+
+	/// Records a visitor group leaving the campground.
+	pub fn record_departure(&mut self) {
+		self.departures += 1;
+	}
+
+	/// Records the satisfaction of a visitor group, between 0 and 1.
+	pub fn record_satisfaction(&mut self, satisfaction: f32) {
+		self.satisfaction_total += satisfaction.clamp(0., 1.);
+		self.satisfaction_samples += 1;
+	}
+
+	/// Records an incident, such as a theft or an accident.
+	pub fn record_incident(&mut self) {
+		self.incidents += 1;
+	}
+
+	/// Records a visitor group that wanted to book a night but was turned away because the campground was full.
+	pub fn record_turned_away(&mut self) {
+		self.turned_away += 1;
+	}
+
+	/// Number of visitor groups turned away today.
+	pub const fn turned_away(&self) -> u32 {
+		self.turned_away
+	}
+
+	fn close(self, day: u32) -> DaySummary {
+		DaySummary {
+			day,
+			income: self.income,
+			expenses: self.expenses,
+			arrivals: self.arrivals,
+			departures: self.departures,
+			average_satisfaction: (self.satisfaction_samples > 0)
+				.then(|| self.satisfaction_total / self.satisfaction_samples as f32),
+			incidents: self.incidents,
+			refunds: self.refunds,
+			turned_away: self.turned_away,
+		}
+	}
+}
+
+/// Statistics of a day that has ended.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct DaySummary {
+	/// The day, starting at 1.
+	pub day:                  u32,
+	/// Money earned.
+	pub income:               u64,
+	/// Money spent.
+	pub expenses:             u64,
+	/// Visitor groups that arrived.
+	pub arrivals:             u32,
+	/// Visitor groups that left.
+	pub departures:           u32,
+	/// Average satisfaction of all visitor groups that were asked, between 0 and 1. [`None`] if nobody was asked.
+	pub average_satisfaction: Option<f32>,
+	/// Incidents that happened.
+	pub incidents:            u32,
+	/// Money refunded for demolished builds. Missing from older saves.
+	#[reflect(default)]
+	pub refunds:              u64,
+	/// Visitor groups that were turned away because the campground was full. Missing from older saves.
+	#[reflect(default)]
+	pub turned_away:          u32,
+}
+
+impl DaySummary {
+	/// Income and refunds minus expenses.
+	pub fn profit(&self) -> i64 {
+		self.income as i64 + self.refunds as i64 - self.expenses as i64
+	}
+}
+
+/// Summaries of all days that have ended, oldest first.
+#[derive(Resource, Reflect, Clone, Debug, Default)]
+#[reflect(Resource)]
+pub struct DailyHistory(pub Vec<DaySummary>);
+
+impl DailyHistory {
+	/// The most recent day that has ended.
+	pub fn last(&self) -> Option<&DaySummary> {
+		self.0.last()
+	}
+}
+
+pub(super) fn advance_day(time: Res<Time>, mut clock: ResMut<DayClock>, mut day_ended: EventWriter<DayEnded>) {
+	clock.elapsed += time.delta();
+	// A single fixed update never spans more than one day, but catch up anyways to keep the clock consistent.
+	while clock.elapsed >= DAY_LENGTH {
+		clock.elapsed -= DAY_LENGTH;
+		day_ended.send(DayEnded { day: clock.day() });
+		clock.days_passed += 1;
+	}
+}
+
+fn close_day(mut day_ended: EventReader<DayEnded>, mut tally: ResMut<DailyTally>, mut history: ResMut<DailyHistory>) {
+	for DayEnded { day } in day_ended.read() {
+		let summary = std::mem::take(&mut *tally).close(*day);
+		debug!("Closed day {day}: {summary:?}");
+		history.0.push(summary);
+	}
+}
+
+fn notify_day_summary(
+	history: Res<DailyHistory>,
+	commercial_tally: Res<CommercialTally>,
+	mut dialogs: EventWriter<OpenDialog>,
+) {
+	let Some(summary) = history.last() else {
+		return;
+	};
+	info!("Day {} ended with a profit of {}", summary.day, summary.profit());
+	dialogs.send(
+		OpenDialog::new(format!("End of day {}, {}", summary.day, Calendar::for_day(summary.day)))
+			.property("Income", summary.income.to_string())
+			.property("Expenses", summary.expenses.to_string())
+			.property("Demolition refunds", summary.refunds.to_string())
+			.property("Profit", summary.profit().to_string())
+			.property("Shop revenue", commercial_tally.last_day.revenue.to_string())
+			.property("Shop operating costs", commercial_tally.last_day.operating_costs.to_string())
+			.property("Arrivals", summary.arrivals.to_string())
+			.property("Departures", summary.departures.to_string())
+			.property("Turned away", summary.turned_away.to_string())
+			.property(
+				"Satisfaction",
+				summary
+					.average_satisfaction
+					.map_or_else(|| "–".to_string(), |satisfaction| format!("{:.0}%", satisfaction * 100.)),
+			)
+			.property("Incidents", summary.incidents.to_string()),
+	);
+}
@@ -0,0 +1,92 @@
+//! Route finding for linear structures laid across the ground, such as pipes and cables.
+//!
+//! As opposed to actor navigation (see [`super::nav`]), routes are not restricted to navigable tiles. Instead, every
+//! tile has a construction cost, and the cheapest route to any of a set of targets is searched.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::utils::HashMap;
+
+use super::{GridPosition, GroundKind, GroundMap};
+use crate::HashSet;
+
+/// Construction cost of laying a route segment through a tile of the given ground kind. [`None`] means that no route
+/// can be laid there.
+pub fn route_cost(kind: GroundKind) -> Option<u32> {
+	match kind.behavior() {
+		GroundKind::Grass | GroundKind::Custom(_) => Some(1),
+		// Routes through pitches disturb visitors' space, so they should only be used when they're a big shortcut.
+		GroundKind::Pitch => Some(3),
+		// The pathway needs to be torn up and repaved.
+		GroundKind::Pathway => Some(4),
+		GroundKind::PoolPath => None,
+	}
+}
+
+/// A cheapest route found by [`route_to_nearest`].
+#[derive(Clone, Debug, Default)]
+pub struct Route {
+	/// All tiles of the route, from the start up to and including the reached target.
+	pub tiles: Vec<GridPosition>,
+	/// Total construction cost of the route, excluding the start tile.
+	pub cost:  u32,
+}
+
+/// Finds the cheapest route from `start` to whichever of the `targets` is cheapest to reach, using A* with the
+/// distance to the closest target as the heuristic. Tile costs are determined by `cost`, which returns [`None`] for
+/// tiles that can't be routed through. Tiles not present in the ground map are never routed through, which also
+/// bounds the search.
+pub fn route_to_nearest(
+	start: GridPosition,
+	targets: &HashSet<GridPosition>,
+	ground_map: &GroundMap,
+	cost: impl Fn(GridPosition, GroundKind) -> Option<u32>,
+) -> Option<Route> {
+	if targets.is_empty() {
+		return None;
+	}
+	// Every tile costs at least 1, so the Manhattan distance never overestimates.
+	let heuristic = |position: GridPosition| {
+		targets.keys().map(|target| position.x.abs_diff(target.x) + position.y.abs_diff(target.y)).min().unwrap_or(0)
+	};
+
+	let mut open_set = BinaryHeap::new();
+	let mut best_costs = HashMap::<GridPosition, u32>::new();
+	let mut predecessors = HashMap::<GridPosition, GridPosition>::new();
+
+	best_costs.insert(start, 0);
+	open_set.push(Reverse((heuristic(start), 0, start)));
+
+	while let Some(Reverse((_, current_cost, current))) = open_set.pop() {
+		if targets.contains_key(&current) {
+			let mut tiles = vec![current];
+			let mut backtrack = current;
+			while let Some(predecessor) = predecessors.get(&backtrack) {
+				tiles.push(*predecessor);
+				backtrack = *predecessor;
+			}
+			tiles.reverse();
+			return Some(Route { tiles, cost: current_cost });
+		}
+		// Stale entry; a cheaper way to this tile was already found.
+		if best_costs.get(&current).is_some_and(|best| *best < current_cost) {
+			continue;
+		}
+
+		for neighbor in current.neighbors() {
+			let Some(step_cost) = ground_map.kind_of(&neighbor).and_then(|kind| cost(neighbor, kind)) else {
+				continue;
+			};
+			let neighbor_cost = current_cost + step_cost.max(1);
+			if best_costs.get(&neighbor).is_some_and(|best| *best <= neighbor_cost) {
+				continue;
+			}
+			best_costs.insert(neighbor, neighbor_cost);
+			predecessors.insert(neighbor, current);
+			open_set.push(Reverse((neighbor_cost + heuristic(neighbor), neighbor_cost, neighbor)));
+		}
+	}
+
+	None
+}
@@ -0,0 +1,96 @@
+//! Spatial index of everything that takes up space on the grid.
+//!
+//! Finding the objects at a position by scanning every [`GridBox`] gets slow with many objects. The [`SpatialIndex`]
+//! instead keeps the entities of all grid boxes in a hash map keyed by the tiles that they cover on the xy plane. It
+//! follows the grid boxes through change detection in [`update_spatial_index`], so lookups see the boxes as of the
+//! last update.
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use super::{GridBox, GridPosition};
+use crate::gamemode::GameSet;
+
+pub struct SpatialManagement;
+
+impl Plugin for SpatialManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<SpatialIndex>()
+			.add_systems(PreUpdate, update_spatial_index.in_set(GameSet::SimulationEarly))
+			.add_systems(PostUpdate, update_spatial_index.in_set(GameSet::Graphics));
+	}
+}
+
+/// The entities with a [`GridBox`], keyed by the tiles that their boxes cover.
+#[derive(Resource, Debug, Default)]
+pub struct SpatialIndex {
+	tiles: HashMap<IVec2, Vec<Entity>>,
+	boxes: HashMap<Entity, GridBox>,
+}
+
+impl SpatialIndex {
+	/// The tiles that the box covers on the xy plane.
+	fn tiles_of(grid_box: &GridBox) -> impl Iterator<Item = IVec2> + '_ {
+		grid_box.floor_positions().map(|position| position.truncate())
+	}
+
+	fn insert(&mut self, entity: Entity, grid_box: GridBox) {
+		self.remove(entity);
+		for tile in Self::tiles_of(&grid_box) {
+			self.tiles.entry(tile).or_default().push(entity);
+		}
+		self.boxes.insert(entity, grid_box);
+	}
+
+	fn remove(&mut self, entity: Entity) {
+		let Some(grid_box) = self.boxes.remove(&entity) else {
+			return;
+		};
+		for tile in Self::tiles_of(&grid_box) {
+			if let Some(entities) = self.tiles.get_mut(&tile) {
+				entities.retain(|other| *other != entity);
+				if entities.is_empty() {
+					self.tiles.remove(&tile);
+				}
+			}
+		}
+	}
+
+	/// The boxes that cover the tile, ignoring height.
+	pub fn at(&self, position: GridPosition) -> impl Iterator<Item = (Entity, GridBox)> + '_ {
+		self.tiles
+			.get(&position.truncate())
+			.into_iter()
+			.flatten()
+			.filter_map(|entity| Some((*entity, *self.boxes.get(entity)?)))
+	}
+
+	/// The boxes that intersect the box on the xy plane, each only once.
+	pub fn intersecting(&self, grid_box: GridBox) -> impl Iterator<Item = (Entity, GridBox)> + '_ {
+		let mut seen = HashSet::new();
+		grid_box
+			.floor_positions()
+			.collect::<Vec<_>>()
+			.into_iter()
+			.flat_map(|position| self.at(position))
+			.filter(move |(entity, _)| seen.insert(*entity))
+	}
+
+	/// Whether any box covers one of the positions.
+	pub fn is_occupied(&self, positions: &[GridPosition]) -> bool {
+		positions.iter().any(|position| self.at(*position).next().is_some())
+	}
+}
+
+pub(crate) fn update_spatial_index(
+	mut index: ResMut<SpatialIndex>,
+	changed_boxes: Query<(Entity, &GridBox), Changed<GridBox>>,
+	mut removed_boxes: RemovedComponents<GridBox>,
+) {
+	for entity in removed_boxes.read() {
+		index.remove(entity);
+	}
+	for (entity, grid_box) in &changed_boxes {
+		index.insert(entity, *grid_box);
+	}
+}
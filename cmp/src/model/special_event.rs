@@ -0,0 +1,129 @@
+//! Special events, such as the summer festival, that take place on the same dates every year.
+//!
+//! Every [`SpecialEvent`] in the [`SpecialEventSchedule`] starts on a day of the [`Calendar`] and lasts for some days.
+//! While it takes place, it registers a [`ModifierTarget::Demand`] modifier named after the event, which draws more
+//! visitors. The player is told a few days ahead that an event is coming up, as well as when it begins and ends. The
+//! schedule is a resource, so that scenarios and mods can add their own events or replace the default ones.
+
+use bevy::prelude::*;
+
+use super::calendar::{Calendar, DAYS_PER_MONTH};
+use super::modifiers::{Modifier, ModifierTarget, Modifiers};
+use crate::gamemode::{GameSet, GameState};
+use crate::ui::notifications::NotificationEvent;
+
+pub struct SpecialEventManagement;
+
+impl Plugin for SpecialEventManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<SpecialEventSchedule>().add_systems(
+			Update,
+			apply_special_events
+				.run_if(resource_changed::<Calendar>.or(resource_changed::<SpecialEventSchedule>))
+				.in_set(GameSet::Simulation)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// How many days ahead the player is told about an upcoming event.
+const ANNOUNCEMENT_DAYS: u32 = 3;
+/// Number of days in every year.
+const DAYS_PER_YEAR: u32 = 12 * DAYS_PER_MONTH;
+
+/// An event that takes place every year on the same dates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecialEvent {
+	/// Player-facing name of the event, which is also the name of its modifier.
+	pub name:          String,
+	/// The month that the event begins in, counted from 0 for January.
+	pub month:         u32,
+	/// The day of the month that the event begins on, starting at 1.
+	pub first_day:     u32,
+	/// How many days the event lasts.
+	pub days:          u32,
+	/// How much the event multiplies visitor demand while it takes place.
+	pub demand_factor: f32,
+}
+
+impl SpecialEvent {
+	/// The day of the year that the event begins on, starting at 0.
+	const fn first_day_of_year(&self) -> u32 {
+		self.month * DAYS_PER_MONTH + self.first_day.saturating_sub(1)
+	}
+
+	/// Days from the date until the event begins next, which is 0 on its first day.
+	pub const fn days_until(&self, calendar: &Calendar) -> u32 {
+		(self.first_day_of_year() + DAYS_PER_YEAR - calendar.day_of_year()) % DAYS_PER_YEAR
+	}
+
+	/// Whether the event takes place on the date.
+	pub const fn is_taking_place(&self, calendar: &Calendar) -> bool {
+		let days_since_start = (DAYS_PER_YEAR - self.days_until(calendar)) % DAYS_PER_YEAR;
+		days_since_start < self.days
+	}
+
+	/// The modifier that the event registers while it takes place.
+	pub fn modifier(&self) -> Modifier {
+		Modifier::multiply(self.name.clone(), ModifierTarget::Demand, self.demand_factor)
+	}
+}
+
+/// All special events of the year.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct SpecialEventSchedule(pub Vec<SpecialEvent>);
+
+impl Default for SpecialEventSchedule {
+	fn default() -> Self {
+		Self(vec![
+			SpecialEvent {
+				name:          "Summer festival".to_string(),
+				month:         6,
+				first_day:     1,
+				days:          5,
+				demand_factor: 1.5,
+			},
+			SpecialEvent {
+				name:          "Holiday lights".to_string(),
+				month:         11,
+				first_day:     1,
+				days:          DAYS_PER_MONTH,
+				demand_factor: 1.3,
+			},
+		])
+	}
+}
+
+/// Keeps the event modifiers in sync with the date. Since the modifiers are saved, events that were already taking
+/// place before loading a save aren't announced again.
+fn apply_special_events(
+	calendar: Res<Calendar>,
+	schedule: Res<SpecialEventSchedule>,
+	mut modifiers: ResMut<Modifiers>,
+	mut notifications: EventWriter<NotificationEvent>,
+) {
+	for event in &schedule.0 {
+		let was_taking_place = modifiers.for_target(ModifierTarget::Demand).any(|modifier| modifier.name == event.name);
+		match (was_taking_place, event.is_taking_place(&calendar)) {
+			(false, true) => {
+				modifiers.register(event.modifier());
+				notifications.send(NotificationEvent::info(format!(
+					"The {} begins today and draws more visitors for {} days.",
+					event.name.to_lowercase(),
+					event.days
+				)));
+			},
+			(true, false) => {
+				modifiers.remove(&event.name);
+				notifications.send(NotificationEvent::info(format!("The {} is over.", event.name.to_lowercase())));
+			},
+			(false, false) if calendar.is_changed() && event.days_until(&calendar) == ANNOUNCEMENT_DAYS => {
+				notifications.send(NotificationEvent::info(format!(
+					"The {} begins in {ANNOUNCEMENT_DAYS} days.",
+					event.name.to_lowercase()
+				)));
+			},
+			_ => {},
+		}
+	}
+}
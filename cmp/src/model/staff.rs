@@ -0,0 +1,474 @@
+//! Staff that keeps the campground in shape.
+//!
+//! Pitches wear down over time: they get dirty while visitors stay on them, and their facilities slowly lose their
+//! condition. The [`PitchUpkeep`] of a pitch influences how satisfied its visitors are when they leave. The player
+//! hires [`Employee`]s to counter this: cleaners restore cleanliness, and maintenance workers restore condition. Staff
+//! walk over the people [`NavMesh`] to the pitch that needs them most, service it for a while, and patrol the
+//! campground when there's nothing to do. Every employee costs a daily wage, which is paid in the end-of-day rollup.
+
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParamItem;
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::{HashMap, HashSet};
+use moonshine_save::save::Save;
+
+use super::area::ImmutableArea;
+use super::decoration::LocalBeauty;
+use super::modifiers::{ModifierTarget, Modifiers};
+use super::nav::{Path, Pedestrian, PeopleNavMesh};
+use super::pathfinding::{FoundPath, PeoplePathRequest};
+use super::road::{entrances, EntranceRoad};
+use super::rollup::{DailyTally, RollupSet, DAY_LENGTH};
+use super::vehicle::Vehicle;
+use super::{ActorPosition, GridPosition, GroundMap, Pitch};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::{ObjectPriority, OverlayLayer, OverlayLayerExt};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct StaffManagement;
+
+impl Plugin for StaffManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<StaffRole>()
+			.register_type::<Employee>()
+			.register_type::<PitchUpkeep>()
+			.add_event::<HireStaff>()
+			.add_event::<DismissStaff>()
+			.add_overlay_layer::<PitchSatisfactionLayer>()
+			.add_world_info_section::<PitchUpkeep>("Upkeep", |upkeep| {
+				vec![
+					DialogWidget::Property {
+						name:  "Cleanliness".into(),
+						value: format!("{:.0}%", upkeep.cleanliness * 100.),
+					},
+					DialogWidget::Property {
+						name:  "Condition".into(),
+						value: format!("{:.0}%", upkeep.condition * 100.),
+					},
+				]
+			})
+			.add_systems(
+				Update,
+				(hire_staff, dismiss_staff, add_employee_shifts, add_pitch_upkeep)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				FixedUpdate,
+				(decay_upkeep, (service_pitches, start_walking, assign_staff).chain())
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				walk_staff.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			)
+			.add_systems(FixedUpdate, pay_wages.in_set(RollupSet::Wages));
+	}
+}
+
+/// How long it takes a fully clean pitch to get completely dirty while visitors stay on it.
+const CLEANLINESS_LIFETIME: Duration = Duration::from_secs(DAY_LENGTH.as_secs() * 2);
+/// How long it takes the facilities of a pitch to go from perfect to broken.
+const CONDITION_LIFETIME: Duration = Duration::from_secs(DAY_LENGTH.as_secs() * 8);
+/// Pitches are serviced once their cleanliness or condition falls below this.
+const SERVICE_THRESHOLD: f32 = 0.8;
+/// How long it takes to service a pitch.
+const SERVICE_TIME: Duration = Duration::from_secs(10);
+/// How many random tiles are tried when looking for a patrol destination.
+const PATROL_ATTEMPTS: usize = 8;
+
+/// The jobs that staff can be hired for.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StaffRole {
+	/// Keeps occupied pitches clean.
+	Cleaner,
+	/// Repairs the facilities of pitches.
+	Maintenance,
+}
+
+/// All staff roles, in the order they are displayed in.
+pub const ALL_STAFF_ROLES: [StaffRole; 2] = [StaffRole::Cleaner, StaffRole::Maintenance];
+
+impl std::fmt::Display for StaffRole {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Cleaner => "Cleaner",
+			Self::Maintenance => "Maintenance worker",
+		})
+	}
+}
+
+impl StaffRole {
+	/// Wage paid at the end of every day, before upkeep modifiers.
+	pub const fn daily_wage(&self) -> u64 {
+		match self {
+			Self::Cleaner => 40,
+			Self::Maintenance => 60,
+		}
+	}
+
+	/// The part of the pitch upkeep that this role takes care of.
+	const fn upkeep_value(&self, upkeep: &PitchUpkeep) -> f32 {
+		match self {
+			Self::Cleaner => upkeep.cleanliness,
+			Self::Maintenance => upkeep.condition,
+		}
+	}
+
+	fn restore(&self, upkeep: &mut PitchUpkeep) {
+		match self {
+			Self::Cleaner => upkeep.cleanliness = 1.,
+			Self::Maintenance => upkeep.condition = 1.,
+		}
+	}
+
+	const fn activity(&self) -> &'static str {
+		match self {
+			Self::Cleaner => "Cleaning",
+			Self::Maintenance => "Repairing",
+		}
+	}
+
+	/// There are no staff graphics yet, so employees are drawn as colored rectangles.
+	fn sprite(&self) -> Sprite {
+		let color = match self {
+			Self::Cleaner => Color::srgb(0.3, 0.7, 0.9),
+			Self::Maintenance => Color::srgb(0.95, 0.6, 0.15),
+		};
+		Sprite { anchor: Anchor::BottomCenter, ..Sprite::from_color(color, Vec2::new(3., 6.)) }
+	}
+}
+
+/// A hired member of staff.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Employee {
+	pub role: StaffRole,
+}
+
+/// What an employee is currently doing. Shifts aren't saved; loaded employees start out idle.
+#[derive(Component, Debug, Default)]
+pub struct Shift {
+	task: Task,
+	/// Remaining tiles to walk through.
+	path: Path,
+	/// While planning, the pitches that the employee looks for a way to, with the tile to service each one from.
+	jobs: Vec<(Entity, GridPosition)>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Task {
+	/// Waiting for a new task.
+	#[default]
+	Idle,
+	/// Waiting for the way to a pitch or patrol destination.
+	Planning,
+	/// Walking to the given pitch, or patrolling if there is none.
+	Walking { pitch: Option<Entity> },
+	/// Servicing the given pitch for the remaining time.
+	Servicing { pitch: Entity, remaining: Duration },
+}
+
+impl Shift {
+	/// The pitch that the employee is assigned to, if any.
+	pub fn assignment(&self) -> Option<Entity> {
+		match self.task {
+			Task::Walking { pitch } => pitch,
+			Task::Servicing { pitch, .. } => Some(pitch),
+			Task::Idle | Task::Planning => None,
+		}
+	}
+
+	/// Player-facing description of the current task, given the pitch type of the assigned pitch.
+	pub fn describe(&self, role: StaffRole, pitch: Option<&Pitch>) -> String {
+		let pitch_name =
+			pitch.and_then(|pitch| pitch.kind).map_or_else(|| "a pitch".to_string(), |kind| kind.to_string());
+		match self.task {
+			Task::Idle => "Idle".into(),
+			Task::Planning => "Looking for work".into(),
+			Task::Walking { pitch: None } => "Patrolling".into(),
+			Task::Walking { pitch: Some(_) } => format!("On the way to {pitch_name}"),
+			Task::Servicing { .. } => format!("{} {pitch_name}", role.activity()),
+		}
+	}
+}
+
+/// How well a pitch with a pitch type is kept. Both values are between 0 (worst) and 1 (best).
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct PitchUpkeep {
+	pub cleanliness: f32,
+	pub condition:   f32,
+}
+
+impl Default for PitchUpkeep {
+	fn default() -> Self {
+		Self { cleanliness: 1., condition: 1. }
+	}
+}
+
+impl PitchUpkeep {
+	/// Whether the pitch is dirty enough for cleaners to clean it.
+	pub fn needs_cleaning(&self) -> bool {
+		self.cleanliness < SERVICE_THRESHOLD
+	}
+
+	/// How satisfied visitors are with the pitch's upkeep, between 0 and 1.
+	pub fn satisfaction(&self) -> f32 {
+		(self.cleanliness + self.condition) / 2.
+	}
+
+	/// How satisfied visitors are with the pitch as a whole: how well it is kept, and how beautiful its surroundings
+	/// are. Visitors' errands aren't included, since they depend on the visitors rather than the pitch.
+	pub fn pitch_satisfaction(&self, beauty: Option<&LocalBeauty>) -> f32 {
+		(self.satisfaction() + beauty.map_or(0., LocalBeauty::satisfaction_bonus)).min(1.)
+	}
+}
+
+/// How satisfied visitors would be with every pitch if they left now. Tiles without a pitch have no value.
+pub struct PitchSatisfactionLayer;
+
+impl OverlayLayer for PitchSatisfactionLayer {
+	type Param = Query<'static, 'static, (&'static PitchUpkeep, &'static ImmutableArea, Option<&'static LocalBeauty>)>;
+
+	const LEGEND: [&'static str; 2] = ["Unhappy", "Happy"];
+	const NAME: &'static str = "Pitch satisfaction";
+
+	fn values(pitches: SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		pitches
+			.iter()
+			.flat_map(|(upkeep, area, beauty)| {
+				let satisfaction = upkeep.pitch_satisfaction(beauty);
+				area.0.tiles_iter().map(move |tile| (tile, satisfaction))
+			})
+			.collect()
+	}
+}
+
+/// Requests hiring a new employee for the role.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HireStaff(pub StaffRole);
+
+/// Requests dismissing the employee.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DismissStaff(pub Entity);
+
+pub(super) fn tile_center(tile: GridPosition) -> Vec3A {
+	tile.0.as_vec3a() + Vec3A::new(0.5, 0.5, 0.)
+}
+
+/// A walkable tile next to the pitch, from which it can be serviced.
+pub(super) fn service_tile(area: &ImmutableArea, mesh: &PeopleNavMesh) -> Option<GridPosition> {
+	area.0.tiles_iter().flat_map(|tile| tile.neighbors()).find(|neighbor| mesh.contains(*neighbor))
+}
+
+fn hire_staff(
+	mut requests: EventReader<HireStaff>,
+	ground_map: Res<GroundMap>,
+	mesh: Res<PeopleNavMesh>,
+	roads: Query<&EntranceRoad>,
+	mut commands: Commands,
+) {
+	for HireStaff(role) in requests.read() {
+		// New staff walk in through the gate of the entrance road; if it can't be walked on, anywhere walkable will do.
+		let mut gates = entrances(&roads, &mesh);
+		if gates.is_empty() {
+			gates = ground_map.iter().map(|(position, ..)| position).filter(|tile| mesh.contains(*tile)).collect();
+		}
+		let Some(entrance) = fastrand::choice(gates) else {
+			warn!("Can’t hire a {role} on a campground without walkable ground");
+			continue;
+		};
+		debug!("Hired a {role} arriving at {entrance:?}");
+		commands.spawn((Employee { role: *role }, ActorPosition(tile_center(entrance)), ObjectPriority::Normal, Save));
+	}
+}
+
+fn dismiss_staff(
+	mut requests: EventReader<DismissStaff>,
+	employees: Query<(), With<Employee>>,
+	mut commands: Commands,
+) {
+	for DismissStaff(employee) in requests.read() {
+		if employees.contains(*employee) {
+			commands.entity(*employee).despawn_recursive();
+		}
+	}
+}
+
+/// Also gives loaded employees their sprites.
+fn add_employee_shifts(employees: Query<(Entity, &Employee), Without<Shift>>, mut commands: Commands) {
+	for (entity, employee) in &employees {
+		commands.entity(entity).insert((Shift::default(), employee.role.sprite()));
+	}
+}
+
+fn add_pitch_upkeep(pitches: Query<(Entity, &Pitch), Without<PitchUpkeep>>, mut commands: Commands) {
+	for (entity, pitch) in &pitches {
+		if pitch.kind.is_some() {
+			commands.entity(entity).insert(PitchUpkeep::default());
+		}
+	}
+}
+
+/// Pitches get dirty while vehicles are parked on them, and their facilities wear down as long as they have a type.
+fn decay_upkeep(time: Res<Time>, vehicles: Query<&Vehicle>, mut pitches: Query<(Entity, &Pitch, &mut PitchUpkeep)>) {
+	let occupied_pitches =
+		vehicles.iter().filter(|vehicle| vehicle.is_parked()).map(|vehicle| vehicle.pitch).collect::<HashSet<_>>();
+	let delta = time.delta_secs();
+	for (entity, pitch, mut upkeep) in &mut pitches {
+		if pitch.kind.is_none() {
+			continue;
+		}
+		if occupied_pitches.contains(&entity) {
+			upkeep.cleanliness = (upkeep.cleanliness - delta / CLEANLINESS_LIFETIME.as_secs_f32()).max(0.);
+		}
+		upkeep.condition = (upkeep.condition - delta / CONDITION_LIFETIME.as_secs_f32()).max(0.);
+	}
+}
+
+fn service_pitches(
+	time: Res<Time>,
+	mut employees: Query<(&Employee, &mut Shift)>,
+	mut pitches: Query<&mut PitchUpkeep>,
+) {
+	for (employee, mut shift) in &mut employees {
+		let Task::Servicing { pitch, remaining } = &mut shift.task else {
+			continue;
+		};
+		let Ok(mut upkeep) = pitches.get_mut(*pitch) else {
+			shift.task = Task::Idle;
+			continue;
+		};
+		*remaining = remaining.saturating_sub(time.delta());
+		if remaining.is_zero() {
+			employee.role.restore(&mut upkeep);
+			shift.task = Task::Idle;
+		}
+	}
+}
+
+/// Sends idle staff to the pitch most in need of their role, or on patrol. Finding the way there takes a while, so
+/// this only requests the paths, and [`start_walking`] picks up the result.
+fn assign_staff(
+	ground_map: Res<GroundMap>,
+	mesh: Res<PeopleNavMesh>,
+	pitches: Query<(Entity, &ImmutableArea, &PitchUpkeep)>,
+	mut employees: Query<(Entity, &Employee, &mut Shift, &ActorPosition)>,
+	mut commands: Commands,
+) {
+	let taken = employees
+		.iter()
+		.filter_map(|(_, employee, shift, _)| Some((employee.role, shift.assignment()?)))
+		.collect::<HashSet<_>>();
+	let tiles = ground_map.iter().map(|(position, ..)| position).collect::<Vec<_>>();
+
+	for (entity, employee, mut shift, position) in &mut employees {
+		if shift.task != Task::Idle {
+			continue;
+		}
+		let role = employee.role;
+
+		let mut candidates = pitches
+			.iter()
+			.filter(|(entity, ..)| !taken.contains(&(role, *entity)))
+			.map(|(entity, area, upkeep)| (entity, area, role.upkeep_value(upkeep)))
+			.filter(|(.., value)| *value < SERVICE_THRESHOLD)
+			.collect::<Vec<_>>();
+		candidates.sort_by(|(.., a), (.., b)| a.total_cmp(b));
+		let jobs = candidates
+			.into_iter()
+			.filter_map(|(entity, area, _)| Some((entity, service_tile(area, &mesh)?)))
+			.collect::<Vec<_>>();
+		// Without a reachable job, the employee patrols to the first random tile that can be reached.
+		let patrol = (0 .. PATROL_ATTEMPTS).filter_map(|_| fastrand::choice(&tiles).copied()).collect::<Vec<_>>();
+
+		let destinations = jobs.iter().map(|(_, tile)| *tile).chain(patrol).collect::<Vec<_>>();
+		commands.entity(entity).insert(PeoplePathRequest::new(position.tile(), destinations));
+		*shift = Shift { task: Task::Planning, jobs, ..default() };
+	}
+}
+
+/// Starts walking along the paths found for planning employees.
+fn start_walking(
+	mut employees: Query<(Entity, &Employee, &mut Shift, Option<&mut FoundPath>)>,
+	mut commands: Commands,
+) {
+	let mut taken = employees
+		.iter()
+		.filter_map(|(_, employee, shift, _)| Some((employee.role, shift.assignment()?)))
+		.collect::<HashSet<_>>();
+
+	for (entity, employee, mut shift, found_path) in &mut employees {
+		if shift.task != Task::Planning {
+			continue;
+		}
+		let Some(mut found_path) = found_path else {
+			continue;
+		};
+		commands.entity(entity).remove::<FoundPath>();
+
+		let jobs = std::mem::take(&mut shift.jobs);
+		let Some(path) = found_path.0.take() else {
+			shift.task = Task::Idle;
+			continue;
+		};
+		let pitch = jobs.into_iter().find(|(_, tile)| path.end() == Some(tile)).map(|(pitch, _)| pitch);
+		// Someone else took the job while the way there was searched, so the employee looks for another one.
+		if pitch.is_some_and(|pitch| !taken.insert((employee.role, pitch))) {
+			shift.task = Task::Idle;
+			continue;
+		}
+		*shift = Shift { task: Task::Walking { pitch }, path, ..default() };
+	}
+}
+
+fn walk_staff(
+	time: Res<Time>,
+	ground_map: Res<GroundMap>,
+	mesh: Res<PeopleNavMesh>,
+	mut employees: Query<(&mut Shift, &mut ActorPosition)>,
+) {
+	for (mut shift, mut position) in &mut employees {
+		let Task::Walking { pitch } = shift.task else {
+			continue;
+		};
+		let Some(target) = shift.path.start().copied() else {
+			shift.task = match pitch {
+				Some(pitch) => Task::Servicing { pitch, remaining: SERVICE_TIME },
+				None => Task::Idle,
+			};
+			continue;
+		};
+		// The way was built over, so the employee looks for something new to do from here.
+		if !mesh.contains(target) {
+			*shift = Shift::default();
+			continue;
+		}
+
+		let speed = ground_map.kind_of(&position.tile()).map_or(1, |kind| kind.traversal_speed()) as f32
+			* Pedestrian::Staff.speed_factor();
+		let step = speed * time.delta_secs();
+		let target_position = tile_center(target);
+		let offset = target_position - position.0;
+		if offset.length() <= step {
+			position.0 = target_position;
+			shift.path.pop_start();
+		} else {
+			position.0 += offset.normalize() * step;
+		}
+	}
+}
+
+fn pay_wages(employees: Query<&Employee>, modifiers: Res<Modifiers>, mut tally: ResMut<DailyTally>) {
+	let wages = employees.iter().map(|employee| employee.role.daily_wage()).sum::<u64>();
+	let wages = (wages as f32 * modifiers.factor(ModifierTarget::Upkeep)).round() as u64;
+	if wages > 0 {
+		tally.record_expense(wages);
+	}
+}
@@ -1,14 +1,16 @@
-use std::marker::ConstParamTy;
-
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
 
+use super::area::{Area, ImmutableArea};
+use super::burst::{clear_bursts, place_bursts, BurstPlacement};
 use super::nav::{NavCategory, NavComponent};
 use super::GridPosition;
-use crate::gamemode::GameState;
+use crate::gamemode::{GameSet, GameState};
 use crate::graphics::library::{anchor_for_image, image_for_ground};
 use crate::graphics::{BorderKind, ObjectPriority, Sides};
+use crate::mods::custom_ground_kind;
 use crate::ui::world_info::WorldInfoProperties;
 use crate::util::Tooltipable;
 
@@ -17,35 +19,56 @@ pub struct TileManagement;
 impl Plugin for TileManagement {
 	fn build(&self, app: &mut App) {
 		app.register_type::<GroundKind>()
+			.register_type::<Option<GroundKind>>()
+			.register_type::<Vec<Option<GroundKind>>>()
+			.register_type::<GroundChunk>()
+			.register_type::<HashMap<IVec3, GroundChunk>>()
+			.register_type::<GroundMap>()
 			.insert_resource(GroundMap::new())
-			.add_systems(PreUpdate, update_map_from_world.run_if(in_state(GameState::InGame)))
+			.init_resource::<BurstPlacement>()
+			.add_systems(
+				Update,
+				place_bursts
+					.in_set(GameSet::SimulationEarly)
+					.run_if(in_state(GameState::Loading).or(in_state(GameState::InGame))),
+			)
+			.add_systems(OnEnter(GameState::MainMenu), clear_bursts)
+			.add_systems(
+				PreUpdate,
+				(adopt_legacy_tiles, spawn_loaded_tiles)
+					.in_set(GameSet::SimulationEarly)
+					.run_if(in_state(GameState::Loading).or(in_state(GameState::InGame))),
+			)
 			.add_systems(
 				PostUpdate,
-				(update_ground_textures, add_ground_textures, add_world_info).run_if(in_state(GameState::InGame)),
+				(update_ground_textures, add_ground_textures).in_set(GameSet::Graphics).in_set(GameState::InGame),
 			)
+			.add_systems(PostUpdate, add_world_info.in_set(GameSet::UI).in_set(GameState::InGame))
 			// .add_systems(Update, resize_tiles)
 			.add_systems(
 				FixedUpdate,
 				(add_navigability.after(update_navigability_properties), update_navigability_properties)
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::SimulationEarly)
+					.in_set(GameState::InGame),
 			);
 	}
 }
 
 /// The kinds of ground that exist; most have their own graphics.
-#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, ConstParamTy)]
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[reflect(Component)]
 pub enum GroundKind {
+	/// The ground that the world starts out with.
+	#[default]
 	Grass,
+	/// Paved ground, which vehicles drive on.
 	Pathway,
+	/// The floor of pools.
 	PoolPath,
+	/// The ground of pitches.
 	Pitch,
-}
-
-impl Default for GroundKind {
-	fn default() -> Self {
-		Self::Grass
-	}
+	/// A ground kind that a mod added, see [`crate::mods::CustomGroundKind`].
+	Custom(u8),
 }
 
 impl std::fmt::Display for GroundKind {
@@ -55,6 +78,7 @@ impl std::fmt::Display for GroundKind {
 			Self::Pathway => "Pathway",
 			Self::PoolPath => "Pool Path",
 			Self::Pitch => "Pitch",
+			Self::Custom(number) => custom_ground_kind(*number).map_or("Unknown Ground", |kind| kind.name),
 		})
 	}
 }
@@ -70,30 +94,45 @@ impl Tooltipable for GroundKind {
 			Self::Pitch =>
 				"Pitch ground looks like grass, but behaves very differently, since it defines where a pitch is \
 				 situated.",
+			Self::Custom(number) => custom_ground_kind(*number)
+				.map_or("Ground from a mod that isn’t installed anymore, which behaves like grass.", |kind| {
+					kind.description
+				}),
 		}
 	}
 }
 
 impl GroundKind {
+	/// The built-in ground kind whose behavior this ground kind shares, which is itself for built-in ground kinds.
+	/// Ground kinds of mods that aren't installed behave like grass.
+	pub fn behavior(&self) -> Self {
+		match self {
+			Self::Custom(number) => custom_ground_kind(*number).map_or(Self::Grass, |kind| kind.behaves_like),
+			_ => *self,
+		}
+	}
+
+	/// The border drawn around areas of this ground type, if any.
 	pub const fn border_kind(&self) -> Option<BorderKind> {
 		match self {
 			Self::Pitch => Some(BorderKind::Pitch),
-			Self::Grass | Self::Pathway | Self::PoolPath => None,
+			Self::Grass | Self::Pathway | Self::PoolPath | Self::Custom(_) => None,
 		}
 	}
 
-	pub const fn navigability(&self) -> NavCategory {
-		match self {
-			Self::Grass | Self::PoolPath => NavCategory::People,
+	/// Who can move across this ground type.
+	pub fn navigability(&self) -> NavCategory {
+		match self.behavior() {
+			Self::Grass | Self::PoolPath | Self::Custom(_) => NavCategory::People,
 			Self::Pathway => NavCategory::Vehicles,
 			Self::Pitch => NavCategory::None,
 		}
 	}
 
 	/// The traversal speed needed for this ground type. The speed is given in tiles/second (i/s²) for a person.
-	pub const fn traversal_speed(&self) -> u32 {
-		match self {
-			Self::Grass | Self::Pitch => 1,
+	pub fn traversal_speed(&self) -> u32 {
+		match self.behavior() {
+			Self::Grass | Self::Pitch | Self::Custom(_) => 1,
 			Self::Pathway => 2,
 			Self::PoolPath => 1,
 		}
@@ -109,7 +148,6 @@ pub struct GroundTile {
 	kind:       GroundKind,
 	world_info: WorldInfoProperties,
 	navigable:  NavComponent,
-	save:       Save,
 }
 
 fn sprite_object_for_image(image: &str, asset_server: &AssetServer) -> Sprite {
@@ -132,10 +170,18 @@ fn sprite_object_for_image(image: &str, asset_server: &AssetServer) -> Sprite {
 
 impl GroundTile {
 	pub fn new(kind: GroundKind, position: GridPosition, asset_server: &AssetServer) -> Self {
-		let image = image_for_ground(kind);
+		Self::with_sprite(kind, position, Self::sprite_for(kind, asset_server))
+	}
+
+	/// The sprite of a tile of this kind, which can be shared between many tiles.
+	pub(super) fn sprite_for(kind: GroundKind, asset_server: &AssetServer) -> Sprite {
+		sprite_object_for_image(image_for_ground(kind), asset_server)
+	}
+
+	pub(super) fn with_sprite(kind: GroundKind, position: GridPosition, sprite: Sprite) -> Self {
 		GroundTile {
 			position,
-			sprite: sprite_object_for_image(image, asset_server),
+			sprite,
 			priority: ObjectPriority::Ground,
 			kind,
 			world_info: WorldInfoProperties::basic(kind.to_string(), kind.description().to_string()),
@@ -144,22 +190,84 @@ impl GroundTile {
 				speed:        kind.traversal_speed(),
 				navigability: kind.navigability(),
 			},
-			save: Save,
 		}
 	}
 }
 
+/// Edge length of the chunks of the [`GroundMap`], in tiles.
+pub const GROUND_CHUNK_SIZE: i32 = 32;
+
+/// The ground tiles of a square of [`GROUND_CHUNK_SIZE`]² positions on one level, stored row by row.
+#[derive(Reflect, Clone, Debug)]
+pub struct GroundChunk {
+	kinds:    Vec<Option<GroundKind>>,
+	/// The tile entities, in the same layout as the kinds. They aren't saved; after loading, this is empty until the
+	/// tiles are spawned from their kinds.
+	#[reflect(ignore)]
+	entities: Vec<Option<Entity>>,
+}
+
+impl GroundChunk {
+	const TILES: usize = (GROUND_CHUNK_SIZE * GROUND_CHUNK_SIZE) as usize;
+
+	fn new() -> Self {
+		Self { kinds: vec![None; Self::TILES], entities: vec![None; Self::TILES] }
+	}
+
+	fn entity(&self, index: usize) -> Option<Entity> {
+		self.entities.get(index).copied().flatten()
+	}
+
+	fn is_empty(&self) -> bool {
+		self.kinds.iter().all(Option::is_none)
+	}
+}
+
 /// A map of all ground tiles for fast access.
-#[derive(Resource)]
+///
+/// Tiles are stored in chunks of [`GROUND_CHUNK_SIZE`]² tiles, so that iterating over large maps visits tiles that are
+/// close to each other in order. The map is saved directly with the tile kinds of every chunk, while the tile entities
+/// are not saved at all. After a map was loaded, the tile entities are spawned from it through [`BurstPlacement`].
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
 pub struct GroundMap {
-	map: HashMap<GridPosition, (Entity, GroundKind)>,
+	/// Chunks by their chunk coordinates on the xy plane and the level of their tiles.
+	chunks:  HashMap<IVec3, GroundChunk>,
+	/// Whether the tile entities of all tiles have been spawned. A loaded map is deserialized without its entities, so
+	/// this starts out false.
+	#[reflect(ignore)]
+	spawned: bool,
+}
+
+impl Default for GroundMap {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl GroundMap {
 	pub fn new() -> Self {
-		Self { map: HashMap::new() }
+		Self { chunks: HashMap::new(), spawned: true }
 	}
 
+	/// The chunk containing the position, and the index of the position within the chunk.
+	fn chunk_index(position: &GridPosition) -> (IVec3, usize) {
+		let chunk = position.truncate().div_euclid(IVec2::splat(GROUND_CHUNK_SIZE));
+		let offset = position.truncate().rem_euclid(IVec2::splat(GROUND_CHUNK_SIZE));
+		(chunk.extend(position.z), (offset.x + offset.y * GROUND_CHUNK_SIZE) as usize)
+	}
+
+	/// The position at the index within the chunk; the inverse of [`Self::chunk_index`].
+	fn position_of(chunk: IVec3, index: usize) -> GridPosition {
+		let index = index as i32;
+		GridPosition(IVec3::new(
+			chunk.x * GROUND_CHUNK_SIZE + index % GROUND_CHUNK_SIZE,
+			chunk.y * GROUND_CHUNK_SIZE + index / GROUND_CHUNK_SIZE,
+			chunk.z,
+		))
+	}
+
+	/// Places or changes a single tile. For building large parts of the world at once, use [`BurstPlacement`] instead.
 	pub fn set(
 		&mut self,
 		position: GridPosition,
@@ -179,15 +287,15 @@ impl GroundMap {
 		commands: &mut Commands,
 		asset_server: &AssetServer,
 	) {
-		if let Some((responsible_entity, old_kind)) = self.map.get_mut(&position) {
-			let (_, _, mut in_world_kind, mut world_info) = tile_query.get_mut(*responsible_entity).unwrap();
+		if let Some((responsible_entity, _)) = self.get(&position) {
+			let (_, _, mut in_world_kind, mut world_info) = tile_query.get_mut(responsible_entity).unwrap();
 			// Avoid mutation if there is no change, reducing the pressure on update_ground_textures
 			in_world_kind.set_if_neq(kind);
 			*world_info = WorldInfoProperties::basic(kind.to_string(), kind.description().to_string());
-			*old_kind = kind;
+			self.update_with_existing_tile(responsible_entity, position, kind);
 		} else {
 			let new_entity = commands.spawn(GroundTile::new(kind, position, asset_server)).id();
-			self.map.entry(position).insert((new_entity, kind));
+			self.update_with_existing_tile(new_entity, position, kind);
 		}
 	}
 
@@ -210,44 +318,127 @@ impl GroundMap {
 		}
 	}
 
+	/// Removes the tile at the position from the world, if there is one.
+	pub fn remove(&mut self, position: &GridPosition, commands: &mut Commands) {
+		let (chunk_position, index) = Self::chunk_index(position);
+		let Some(chunk) = self.chunks.get_mut(&chunk_position) else {
+			return;
+		};
+		let entity = chunk.entities.get_mut(index).and_then(Option::take);
+		chunk.kinds[index] = None;
+		if chunk.is_empty() {
+			self.chunks.remove(&chunk_position);
+		}
+		if let Some(entity) = entity {
+			commands.entity(entity).despawn_recursive();
+		}
+	}
+
 	pub fn kind_of(&self, position: &GridPosition) -> Option<GroundKind> {
-		self.map.get(position).map(|(_, kind)| *kind)
+		let (chunk, index) = Self::chunk_index(position);
+		self.chunks.get(&chunk).and_then(|chunk| chunk.kinds[index])
 	}
 
+	/// The entity and kind of the tile at the position. Tiles of a freshly loaded map that haven't been spawned yet
+	/// are not returned.
 	pub fn get(&self, position: &GridPosition) -> Option<(Entity, GroundKind)> {
-		self.map.get(position).cloned()
+		let (chunk, index) = Self::chunk_index(position);
+		let chunk = self.chunks.get(&chunk)?;
+		Some((chunk.entity(index)?, chunk.kinds[index]?))
+	}
+
+	/// Iterates over all spawned tiles in the map, chunk by chunk.
+	pub fn iter(&self) -> impl Iterator<Item = (GridPosition, Entity, GroundKind)> + '_ {
+		self.chunks.iter().flat_map(|(chunk_position, chunk)| {
+			chunk.kinds.iter().enumerate().filter_map(|(index, kind)| {
+				Some((Self::position_of(*chunk_position, index), chunk.entity(index)?, (*kind)?))
+			})
+		})
+	}
+
+	/// Iterates over the kinds of all tiles in the map, chunk by chunk, including tiles that haven't been spawned yet.
+	pub fn kinds(&self) -> impl Iterator<Item = (GridPosition, GroundKind)> + '_ {
+		self.chunks.iter().flat_map(|(chunk_position, chunk)| {
+			chunk
+				.kinds
+				.iter()
+				.enumerate()
+				.filter_map(|(index, kind)| Some((Self::position_of(*chunk_position, index), (*kind)?)))
+		})
 	}
 
 	/// Enter an existing tile into the ground map. This is only to be used with already correctly set up tiles (from a
-	/// game load), and not for entering tile changes and additions into the map.
+	/// game load or burst placement), and not for entering tile changes and additions into the map.
 	pub(super) fn update_with_existing_tile(&mut self, entity: Entity, position: GridPosition, kind: GroundKind) {
-		self.map.insert(position, (entity, kind));
+		let (chunk, index) = Self::chunk_index(&position);
+		let chunk = self.chunks.entry(chunk).or_insert_with(GroundChunk::new);
+		// Loaded chunks don't have their entities yet.
+		chunk.entities.resize(GroundChunk::TILES, None);
+		chunk.kinds[index] = Some(kind);
+		chunk.entities[index] = Some(entity);
 	}
 }
 
-fn update_map_from_world(
-	new_entries: Query<(Entity, &GridPosition, &GroundKind), Added<GroundKind>>,
+/// Spawns the tile entities of a freshly loaded ground map through [`BurstPlacement`]. The tiles of the previous world
+/// aren't saved, so they are removed first. Areas were loaded before their tiles existed, so they are marked as changed
+/// once the tiles are placed, which rebuilds their borders and world info on the tiles.
+fn spawn_loaded_tiles(
 	mut map: ResMut<GroundMap>,
+	old_tiles: Query<Entity, (With<GroundKind>, Without<Save>)>,
+	mut burst: ResMut<BurstPlacement>,
+	mut commands: Commands,
 ) {
-	for (entity, position, kind) in &new_entries {
+	if map.spawned {
+		return;
+	}
+	for tile in &old_tiles {
+		commands.entity(tile).despawn_recursive();
+	}
+	map.spawned = true;
+	let tiles = map.kinds().collect::<Vec<_>>();
+	debug!("Spawning {} loaded ground tiles", tiles.len());
+	burst.queue_tiles(tiles);
+	burst.queue_task(|world| {
+		for mut area in world.query::<&mut Area>().iter_mut(world) {
+			area.set_changed();
+		}
+		for mut area in world.query::<&mut ImmutableArea>().iter_mut(world) {
+			area.set_changed();
+		}
+	});
+}
+
+/// Saves from before the ground map was saved directly store every tile as a saved entity. Such a save replaces the
+/// whole ground, so the previous tiles are removed and the loaded tiles are entered into an empty map. They are no
+/// longer saved as entities afterwards.
+fn adopt_legacy_tiles(
+	legacy_tiles: Query<(Entity, &GridPosition, &GroundKind), With<Save>>,
+	old_tiles: Query<Entity, (With<GroundKind>, Without<Save>)>,
+	mut map: ResMut<GroundMap>,
+	mut commands: Commands,
+) {
+	if legacy_tiles.is_empty() {
+		return;
+	}
+	for tile in &old_tiles {
+		commands.entity(tile).despawn_recursive();
+	}
+	*map = GroundMap::new();
+	for (entity, position, kind) in &legacy_tiles {
 		map.update_with_existing_tile(entity, *position, *kind);
+		commands.entity(entity).remove::<Save>();
 	}
 }
 
 // For testing purposes:
 
-pub fn spawn_test_tiles(
-	mut commands: Commands,
-	mut tile_query: Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
-	mut map: ResMut<GroundMap>,
-	asset_server: Res<AssetServer>,
-) {
-	for x in -100i32 .. 100 {
-		for y in -100i32 .. 100 {
+pub fn spawn_test_tiles(mut burst: ResMut<BurstPlacement>) {
+	burst.queue_tiles((-100i32 .. 100).flat_map(|x| {
+		(-100i32 .. 100).map(move |y| {
 			let kind = if x.abs() < 2 || y.abs() < 2 { GroundKind::Pathway } else { GroundKind::Grass };
-			map.set((x, y, 0).into(), kind, &mut tile_query, &mut commands, &asset_server);
-		}
-	}
+			((x, y, 0).into(), kind)
+		})
+	}));
 }
 
 pub fn update_ground_textures(
@@ -285,10 +476,12 @@ fn add_navigability(mut ground_vertices: Query<(Entity, &GroundKind), Without<Na
 	}
 }
 
-fn update_navigability_properties(mut ground_vertices: Query<(&GroundKind, &mut NavComponent), Changed<GroundKind>>) {
+pub(super) fn update_navigability_properties(
+	mut ground_vertices: Query<(&GroundKind, &mut NavComponent), Changed<GroundKind>>,
+) {
 	for (kind, mut vertex) in &mut ground_vertices {
 		vertex.navigability = kind.navigability();
-		// TODO: Check border objects in another system and remove sides with borders.
+		// Fenced sides are removed again by fence::block_fenced_exits.
 		vertex.exits = Sides::all();
 		vertex.speed = kind.traversal_speed();
 	}
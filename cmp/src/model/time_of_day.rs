@@ -0,0 +1,150 @@
+//! Time of day and the day/night cycle.
+//!
+//! The [`TimeOfDay`] follows the [`DayClock`], so that a full cycle of dawn, day, dusk and night takes one
+//! [`DAY_LENGTH`]. The campground's days run from morning to morning like check-in days, so every day (and the game)
+//! starts at [`DAY_START_HOUR`], and the end-of-day rollup happens at that time as well.
+//!
+//! Systems that depend on the time of day, such as visitors going to sleep or lamps turning on, can read the
+//! [`TimeOfDay`] resource, run only [`during`] some [`DayPhase`], or react to [`PhaseChanged`] events. The canvas is
+//! graded according to the current phase, see [`crate::graphics::lut`].
+//!
+//! [`DAY_LENGTH`]: super::rollup::DAY_LENGTH
+
+use bevy::prelude::*;
+
+use super::rollup::{advance_day, DayClock};
+use crate::gamemode::{GameSet, GameState};
+
+/// Hour at which every day starts.
+pub const DAY_START_HOUR: f32 = 6.;
+
+pub struct TimeOfDayManagement;
+
+impl Plugin for TimeOfDayManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<TimeOfDay>().add_event::<PhaseChanged>().add_systems(
+			FixedUpdate,
+			advance_time_of_day.after(advance_day).in_set(GameSet::Simulation).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// The parts of a day, which look and behave differently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DayPhase {
+	/// The sun rises.
+	Dawn,
+	/// Broad daylight.
+	Day,
+	/// The sun sets.
+	Dusk,
+	/// Dark; most visitors are asleep.
+	Night,
+}
+
+impl DayPhase {
+	/// The phases in the order they occur, starting after midnight.
+	pub const ALL: [Self; 4] = [Self::Dawn, Self::Day, Self::Dusk, Self::Night];
+
+	/// The hour at which this phase starts.
+	pub const fn start_hour(&self) -> f32 {
+		match self {
+			Self::Dawn => 5.,
+			Self::Day => 7.,
+			Self::Dusk => 19.,
+			Self::Night => 21.,
+		}
+	}
+
+	/// The phase at the given hour since midnight.
+	pub fn at(hours: f32) -> Self {
+		Self::ALL.into_iter().rev().find(|phase| hours >= phase.start_hour()).unwrap_or(Self::Night)
+	}
+}
+
+impl std::fmt::Display for DayPhase {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Dawn => "Dawn",
+			Self::Day => "Day",
+			Self::Dusk => "Dusk",
+			Self::Night => "Night",
+		})
+	}
+}
+
+/// The in-game clock time.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct TimeOfDay {
+	/// Hours since midnight, between 0 and 24.
+	hours: f32,
+}
+
+impl Default for TimeOfDay {
+	fn default() -> Self {
+		Self { hours: DAY_START_HOUR }
+	}
+}
+
+impl TimeOfDay {
+	/// The time of day at the given progress through the day.
+	pub fn at_progress(progress: f32) -> Self {
+		Self { hours: (DAY_START_HOUR + progress * 24.).rem_euclid(24.) }
+	}
+
+	/// Hours since midnight, including the fraction of the current hour.
+	pub const fn hours(&self) -> f32 {
+		self.hours
+	}
+
+	/// The current full hour, between 0 and 23.
+	pub fn hour(&self) -> u32 {
+		self.hours as u32
+	}
+
+	/// The current minute within the hour.
+	pub fn minute(&self) -> u32 {
+		(self.hours.fract() * 60.) as u32
+	}
+
+	/// The current phase of the day.
+	pub fn phase(&self) -> DayPhase {
+		DayPhase::at(self.hours)
+	}
+
+	/// Whether it's dark outside.
+	pub fn is_night(&self) -> bool {
+		self.phase() == DayPhase::Night
+	}
+}
+
+impl std::fmt::Display for TimeOfDay {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:02}:{:02}", self.hour(), self.minute())
+	}
+}
+
+/// Sent when a new [`DayPhase`] begins.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PhaseChanged {
+	pub phase: DayPhase,
+}
+
+/// Run condition for systems that only run during the given phase of the day.
+pub fn during(phase: DayPhase) -> impl FnMut(Res<TimeOfDay>) -> bool + Clone {
+	move |time_of_day| time_of_day.phase() == phase
+}
+
+fn advance_time_of_day(
+	clock: Res<DayClock>,
+	mut time_of_day: ResMut<TimeOfDay>,
+	mut phase_changed: EventWriter<PhaseChanged>,
+) {
+	let old_phase = time_of_day.phase();
+	time_of_day.set_if_neq(TimeOfDay::at_progress(clock.progress()));
+	let phase = time_of_day.phase();
+	if phase != old_phase {
+		debug!("{phase} begins at {}", *time_of_day);
+		phase_changed.send(PhaseChanged { phase });
+	}
+}
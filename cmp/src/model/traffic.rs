@@ -0,0 +1,88 @@
+//! Foot traffic: how many people walk across each tile.
+//!
+//! Every simulation step, each person on foot (visitors on their [`super::errand`]s and staff) leaves a step on the
+//! tile they are on. Steps fade away over [`TRAFFIC_HALF_LIFE`], so that [`FootTraffic`] shows where people walk these
+//! days rather than where they once walked. The foot traffic overlay shows it on the ground, which reveals busy
+//! pathways and the shortcuts that people take. Busy grass tiles wear down, see [`super::grass`].
+
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::vehicle::Vehicle;
+use super::{ActorPosition, GridPosition};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{OverlayLayer, OverlayLayerExt};
+
+pub struct TrafficManagement;
+
+impl Plugin for TrafficManagement {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<FootTraffic>()
+			.add_systems(FixedUpdate, record_foot_traffic.in_set(GameSet::Simulation).in_set(GameState::InGame))
+			.add_systems(OnEnter(GameState::Loading), reset_foot_traffic)
+			.add_systems(OnEnter(GameState::MainMenu), reset_foot_traffic)
+			.add_overlay_layer::<FootTrafficLayer>();
+	}
+}
+
+/// Time after which half of the steps on a tile have faded away.
+const TRAFFIC_HALF_LIFE: Duration = Duration::from_secs(120);
+/// Tiles with fewer steps than this are forgotten.
+const MIN_STEPS: f32 = 0.05;
+
+/// The faded step count of every tile that people walked across recently.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct FootTraffic {
+	steps: HashMap<GridPosition, f32>,
+}
+
+impl FootTraffic {
+	/// The faded step counts of all tiles that people walked across recently.
+	pub fn iter(&self) -> impl Iterator<Item = (GridPosition, f32)> + '_ {
+		self.steps.iter().map(|(tile, steps)| (*tile, *steps))
+	}
+
+	/// The highest faded step count of any tile.
+	pub fn busiest(&self) -> f32 {
+		self.steps.values().copied().fold(0., f32::max)
+	}
+}
+
+fn record_foot_traffic(
+	time: Res<Time>,
+	people: Query<&ActorPosition, Without<Vehicle>>,
+	mut traffic: ResMut<FootTraffic>,
+) {
+	let fade = 0.5f32.powf(time.delta_secs() / TRAFFIC_HALF_LIFE.as_secs_f32());
+	traffic.steps.retain(|_, steps| {
+		*steps *= fade;
+		*steps >= MIN_STEPS
+	});
+	for position in &people {
+		*traffic.steps.entry(position.tile()).or_default() += 1.;
+	}
+}
+
+fn reset_foot_traffic(mut traffic: ResMut<FootTraffic>) {
+	traffic.steps.clear();
+}
+
+/// Where people walk, relative to the busiest tile.
+pub struct FootTrafficLayer;
+
+impl OverlayLayer for FootTrafficLayer {
+	type Param = Res<'static, FootTraffic>;
+
+	/// From cold blue to hot red, like a heat map.
+	const HUES: [f32; 2] = [240., 0.];
+	const LEGEND: [&'static str; 2] = ["Quiet", "Busy"];
+	const NAME: &'static str = "Foot traffic";
+
+	fn values(traffic: SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		let busiest = traffic.busiest().max(1.);
+		traffic.steps.iter().map(|(tile, steps)| (*tile, steps / busiest)).collect()
+	}
+}
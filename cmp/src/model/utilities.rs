@@ -0,0 +1,438 @@
+//! Water and electricity supply of pitches.
+//!
+//! Utilities are distributed from supply points through underground pipes and cables, which are only shown while
+//! building or demolishing. Pitches connect to any pipe or cable that touches one of their tiles, and pass the
+//! utility on to the rest of their own tiles, but not to neighboring pitches. Each utility has its own [`Network`],
+//! which is rebuilt from the world whenever a utility part or pitch changes. A pitch is [`Connected`] to a utility if
+//! its network has a supply point and enough capacity for the demand of all pitches on it. Pitches whose pitch type
+//! requires a utility that they aren't connected to can't take visitors.
+
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use moonshine_save::save::Save;
+use serde::{Deserialize, Serialize};
+
+use super::area::{Area, ImmutableArea};
+use super::network::{Network, NetworkNode, NetworkPlugin, NetworkResource};
+use super::{Buildable, GridPosition, Pitch, PitchType};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, image_for_utility};
+use crate::graphics::{ObjectPriority, OverlayLayer, OverlayLayerExt};
+use crate::input::InputState;
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::{WorldInfoProperties, WorldInfoSectionExt};
+use crate::util::Tooltipable;
+
+pub struct UtilityManagement;
+
+impl Plugin for UtilityManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Utility>()
+			.register_type::<UtilityPartKind>()
+			.register_type::<UtilityPart>()
+			.add_plugins((NetworkPlugin::<Water>::default(), NetworkPlugin::<Electricity>::default()))
+			.add_systems(
+				FixedUpdate,
+				(resolve_utility_network::<Water>, resolve_utility_network::<Electricity>, update_connections)
+					.chain()
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(Update, add_utility_graphics.in_set(GameSet::Graphics).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				show_utility_conduits
+					.run_if(state_changed::<InputState>)
+					.in_set(GameSet::Graphics)
+					.in_set(GameState::InGame),
+			)
+			.add_overlay_layer::<UtilityCoverageLayer>()
+			.add_world_info_section::<Connected>("Utilities", |connected| {
+				ALL_UTILITIES
+					.into_iter()
+					.map(|utility| DialogWidget::Property {
+						name:  utility.to_string(),
+						value: match (connected.has(utility), connected.required.contains(&utility)) {
+							(true, _) => "Connected",
+							(false, true) => "Not connected (required)",
+							(false, false) => "Not connected",
+						}
+						.to_string(),
+					})
+					.collect()
+			});
+	}
+}
+
+/// A resource that is supplied to pitches through a network.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Utility {
+	Water,
+	Electricity,
+}
+
+/// All utilities, in the order they are displayed in.
+pub const ALL_UTILITIES: [Utility; 2] = [Utility::Water, Utility::Electricity];
+
+impl std::fmt::Display for Utility {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Water => "Water",
+			Self::Electricity => "Electricity",
+		})
+	}
+}
+
+impl Utility {
+	/// Number of guests that a single supply point can serve.
+	pub const fn supply_capacity(&self) -> u32 {
+		match self {
+			Self::Water => 40,
+			Self::Electricity => 60,
+		}
+	}
+
+	/// Tint of the utility's supply points and conduits, until they have their own graphics.
+	pub fn color(&self) -> Color {
+		match self {
+			Self::Water => Color::srgb(0.3, 0.6, 1.),
+			Self::Electricity => Color::srgb(1., 0.85, 0.2),
+		}
+	}
+}
+
+/// The [`NetworkResource`] of a [`Utility`].
+pub trait UtilityResource: NetworkResource {
+	/// The utility distributed by this network.
+	const UTILITY: Utility;
+}
+
+/// Marker for the water network.
+pub struct Water;
+
+impl NetworkResource for Water {
+	const NAME: &'static str = "Water";
+}
+
+impl UtilityResource for Water {
+	const UTILITY: Utility = Utility::Water;
+}
+
+/// Marker for the electricity network.
+pub struct Electricity;
+
+impl NetworkResource for Electricity {
+	const NAME: &'static str = "Electricity";
+}
+
+impl UtilityResource for Electricity {
+	const UTILITY: Utility = Utility::Electricity;
+}
+
+/// The role of a [`UtilityPart`] in its network.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UtilityPartKind {
+	/// Feeds the utility into the network, such as a well or a grid connection.
+	SupplyPoint,
+	/// An underground pipe or cable.
+	Conduit,
+}
+
+/// A part of a utility network on a single tile.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct UtilityPart {
+	/// The utility that this part distributes.
+	pub utility: Utility,
+	/// What this part does in the network.
+	pub kind:    UtilityPartKind,
+}
+
+impl UtilityPart {
+	/// The part built by the buildable, if it is a utility part.
+	pub const fn from_buildable(buildable: Buildable) -> Option<Self> {
+		match buildable {
+			Buildable::SupplyPoint(utility) => Some(Self { utility, kind: UtilityPartKind::SupplyPoint }),
+			Buildable::Conduit(utility) => Some(Self { utility, kind: UtilityPartKind::Conduit }),
+			_ => None,
+		}
+	}
+
+	/// The buildable that builds the part.
+	pub const fn buildable(&self) -> Buildable {
+		match self.kind {
+			UtilityPartKind::SupplyPoint => Buildable::SupplyPoint(self.utility),
+			UtilityPartKind::Conduit => Buildable::Conduit(self.utility),
+		}
+	}
+
+	/// Player-facing name of the part.
+	pub const fn name(&self) -> &'static str {
+		match (self.utility, self.kind) {
+			(Utility::Water, UtilityPartKind::SupplyPoint) => "Water Supply",
+			(Utility::Water, UtilityPartKind::Conduit) => "Water Pipe",
+			(Utility::Electricity, UtilityPartKind::SupplyPoint) => "Power Supply",
+			(Utility::Electricity, UtilityPartKind::Conduit) => "Power Cable",
+		}
+	}
+}
+
+impl std::fmt::Display for UtilityPart {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl Tooltipable for UtilityPart {
+	fn description(&self) -> &'static str {
+		match (self.utility, self.kind) {
+			(Utility::Water, UtilityPartKind::SupplyPoint) =>
+				"A connection to the water main. Every water supply serves a limited number of guests; pitches \
+				 connected to it through pipes are supplied with water.",
+			(Utility::Water, UtilityPartKind::Conduit) =>
+				"An underground water pipe. Pipes connect water supplies to pitches, and are only shown while building \
+				 or demolishing.",
+			(Utility::Electricity, UtilityPartKind::SupplyPoint) =>
+				"A connection to the power grid. Every power supply serves a limited number of guests; pitches \
+				 connected to it through cables are supplied with electricity.",
+			(Utility::Electricity, UtilityPartKind::Conduit) =>
+				"An underground power cable. Cables connect power supplies to pitches, and are only shown while \
+				 building or demolishing.",
+		}
+	}
+}
+
+impl PitchType {
+	/// Utilities that pitches of this type need to be able to take guests.
+	pub const fn required_utilities(&self) -> &'static [Utility] {
+		match self {
+			Self::TentPitch | Self::PermanentTent => &[],
+			Self::CaravanPitch | Self::MobileHome | Self::Cottage => &[Utility::Water, Utility::Electricity],
+		}
+	}
+}
+
+/// The utilities that reach a pitch.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Connected {
+	/// Whether the pitch is supplied with water.
+	pub water:       bool,
+	/// Whether the pitch is supplied with electricity.
+	pub electricity: bool,
+	/// Utilities that the pitch's type requires.
+	pub required:    &'static [Utility],
+}
+
+impl Connected {
+	/// Whether the pitch is supplied with the utility.
+	pub const fn has(&self, utility: Utility) -> bool {
+		match utility {
+			Utility::Water => self.water,
+			Utility::Electricity => self.electricity,
+		}
+	}
+
+	/// Whether the pitch is supplied with all utilities that its type requires.
+	pub fn is_functional(&self) -> bool {
+		self.required.iter().all(|utility| self.has(*utility))
+	}
+}
+
+/// The pitch tile that represents the whole pitch in the utility networks.
+fn connection_tile(area: &Area) -> Option<GridPosition> {
+	area.tiles_iter().min_by_key(|tile| (tile.x, tile.y, tile.z))
+}
+
+/// What a tile belongs to in a network; tiles only connect to neighbors that belong to the same pitch, or if one of
+/// them is a utility part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TileOwner {
+	UtilityPart,
+	Pitch(Entity),
+}
+
+/// Rebuilds the network of a utility from the world after utility parts or pitches changed.
+fn resolve_utility_network<R: UtilityResource>(
+	mut network: ResMut<Network<R>>,
+	parts: Query<(Ref<UtilityPart>, Ref<GridPosition>)>,
+	pitches: Query<(Entity, Ref<Pitch>, Option<Ref<Area>>, Option<Ref<ImmutableArea>>)>,
+	mut removed_parts: RemovedComponents<UtilityPart>,
+	mut removed_pitches: RemovedComponents<Pitch>,
+	mut removed_areas: RemovedComponents<Area>,
+) {
+	let parts_changed = parts.iter().any(|(part, position)| part.is_changed() || position.is_changed());
+	let pitches_changed = pitches.iter().any(|(_, pitch, area, immutable_area)| {
+		pitch.is_changed()
+			|| area.is_some_and(|area| area.is_changed())
+			|| immutable_area.is_some_and(|area| area.is_changed())
+	});
+	let removed = removed_parts.read().count() + removed_pitches.read().count() + removed_areas.read().count() > 0;
+	if !parts_changed && !pitches_changed && !removed {
+		return;
+	}
+
+	network.clear();
+	let mut owners = HashMap::new();
+	for (part, position) in parts.iter().filter(|(part, _)| part.utility == R::UTILITY) {
+		if part.kind == UtilityPartKind::Conduit {
+			network.insert_node(*position, NetworkNode::Conduit);
+			owners.insert(*position, TileOwner::UtilityPart);
+		}
+	}
+	for (entity, pitch, area, immutable_area) in &pitches {
+		let Some(area) = area.as_deref().or(immutable_area.as_deref().map(|area| &area.0)) else {
+			continue;
+		};
+		let demand = match pitch.kind {
+			Some(kind) if kind.required_utilities().contains(&R::UTILITY) => pitch.guest_capacity(),
+			_ => 0,
+		};
+		let sink_tile = connection_tile(area);
+		for tile in area.tiles_iter() {
+			// A conduit below a pitch tile becomes part of the pitch.
+			let node = if Some(tile) == sink_tile { NetworkNode::Sink { demand } } else { NetworkNode::Conduit };
+			network.insert_node(tile, node);
+			owners.insert(tile, TileOwner::Pitch(entity));
+		}
+	}
+	for (part, position) in parts.iter().filter(|(part, _)| part.utility == R::UTILITY) {
+		if part.kind == UtilityPartKind::SupplyPoint {
+			network.insert_node(*position, NetworkNode::Source { capacity: R::UTILITY.supply_capacity() });
+			owners.insert(*position, TileOwner::UtilityPart);
+		}
+	}
+
+	for (position, owner) in &owners {
+		for neighbor in position.neighbors() {
+			let Some(neighbor_owner) = owners.get(&neighbor) else {
+				continue;
+			};
+			if owner == neighbor_owner || *owner == TileOwner::UtilityPart || *neighbor_owner == TileOwner::UtilityPart
+			{
+				network.connect(*position, neighbor);
+			}
+		}
+	}
+	debug!("{} network has {} components", R::NAME, network.components().count());
+}
+
+fn is_supplied<R: UtilityResource>(network: &Network<R>, tile: Option<GridPosition>) -> bool {
+	tile.and_then(|tile| network.component_of(tile))
+		.is_some_and(|component| component.capacity() > 0 && component.is_sufficient())
+}
+
+fn update_connections(
+	water: Res<Network<Water>>,
+	electricity: Res<Network<Electricity>>,
+	mut pitches: Query<(Entity, &Pitch, Option<&Area>, Option<&ImmutableArea>, Option<&mut Connected>)>,
+	mut commands: Commands,
+) {
+	if !water.is_changed() && !electricity.is_changed() {
+		return;
+	}
+	for (entity, pitch, area, immutable_area, connected) in &mut pitches {
+		let tile = area.or(immutable_area.map(|area| &area.0)).and_then(connection_tile);
+		let new_connected = Connected {
+			water:       is_supplied(&water, tile),
+			electricity: is_supplied(&electricity, tile),
+			required:    pitch.kind.map_or(&[], |kind| kind.required_utilities()),
+		};
+		match connected {
+			Some(mut connected) => {
+				connected.set_if_neq(new_connected);
+			},
+			None => {
+				commands.entity(entity).insert(new_connected);
+			},
+		}
+	}
+}
+
+/// How well the tiles of a network are supplied: 0 without any supply point, otherwise the fraction of the demand
+/// that the supply points can satisfy.
+fn supply_ratios<R: UtilityResource>(network: &Network<R>) -> impl Iterator<Item = (GridPosition, f32)> + '_ {
+	network.nodes().map(|(tile, _)| {
+		let ratio = network.component_of(tile).filter(|component| component.capacity() > 0);
+		(tile, ratio.map_or(0., |component| component.supply_ratio()))
+	})
+}
+
+/// How well pitches, pipes and cables are supplied, averaged over both utilities where a tile belongs to both
+/// networks. Tiles outside of the networks have no value.
+pub struct UtilityCoverageLayer;
+
+impl OverlayLayer for UtilityCoverageLayer {
+	type Param = (Res<'static, Network<Water>>, Res<'static, Network<Electricity>>);
+
+	const LEGEND: [&'static str; 2] = ["Unsupplied", "Supplied"];
+	const NAME: &'static str = "Utility coverage";
+
+	fn values((water, electricity): SystemParamItem<Self::Param>) -> HashMap<GridPosition, f32> {
+		let mut coverage = HashMap::<GridPosition, (f32, u32)>::new();
+		for (tile, ratio) in supply_ratios(&water).chain(supply_ratios(&electricity)) {
+			let (total, count) = coverage.entry(tile).or_default();
+			*total += ratio;
+			*count += 1;
+		}
+		coverage.into_iter().map(|(tile, (total, count))| (tile, total / count as f32)).collect()
+	}
+}
+
+/// Spawns a new utility part. Its graphics are added separately, so that loaded parts receive them as well.
+pub fn spawn_utility_part(part: UtilityPart, position: GridPosition, commands: &mut Commands) {
+	commands.spawn((part, position, Save));
+}
+
+fn add_utility_graphics(
+	parts: Query<(Entity, &UtilityPart), Without<Sprite>>,
+	input_state: Res<State<InputState>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (entity, part) in &parts {
+		let image = image_for_utility(part.kind);
+		let priority = match part.kind {
+			UtilityPartKind::SupplyPoint => ObjectPriority::Normal,
+			UtilityPartKind::Conduit => ObjectPriority::Decal,
+		};
+		commands.entity(entity).insert((
+			Sprite {
+				color: utility_part_color(part, input_state.get()),
+				anchor: anchor_for_image(image),
+				image: asset_server.load(image),
+				..default()
+			},
+			priority,
+			WorldInfoProperties::basic(part.name().to_string(), part.description().to_string()),
+		));
+	}
+}
+
+/// Conduits are underground, so they are only shown while building or demolishing. They are hidden by making them
+/// transparent, since their visibility is managed by culling.
+fn utility_part_color(part: &UtilityPart, input_state: &InputState) -> Color {
+	let alpha = match (part.kind, input_state) {
+		(UtilityPartKind::SupplyPoint, _) => 1.,
+		(
+			UtilityPartKind::Conduit,
+			InputState::Building
+			| InputState::Demolish
+			| InputState::CaptureBlueprint
+			| InputState::StampBlueprint
+			| InputState::Selecting
+			| InputState::AutoRoute,
+		) => 0.6,
+		(UtilityPartKind::Conduit, _) => 0.,
+	};
+	part.utility.color().with_alpha(alpha)
+}
+
+fn show_utility_conduits(input_state: Res<State<InputState>>, mut parts: Query<(&UtilityPart, &mut Sprite)>) {
+	for (part, mut sprite) in &mut parts {
+		let color = utility_part_color(part, input_state.get());
+		if sprite.color != color {
+			sprite.color = color;
+		}
+	}
+}
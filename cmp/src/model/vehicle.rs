@@ -0,0 +1,551 @@
+//! Vehicles that bring visitors to their pitches.
+//!
+//! Caravans and camper vans arrive at caravan pitches once the campground is open, on the days that the pitches were
+//! booked for (see [`super::booking`]) and once the pitch is free. They come in on the [`EntranceRoad`], drive over the
+//! vehicle [`NavMesh`] to a reception to check in, and then on to the pitch to park on it. After their stay, they check
+//! out at a reception, drive back to the gate of the entrance road and leave. Vehicles that lose their pitch or their
+//! way turn around and leave early.
+//!
+//! Vehicles on the road carry their visitors' luggage on the roof. Once parked, the visitors unpack it during the first
+//! [`UNPACK_TIME`] of their stay and put up an awning next to their vehicle, and they pack up again during the last
+//! [`UNPACK_TIME`] before they leave.
+//!
+//! Every vehicle carries visitors of the [`VisitorSegment`] that made the booking, who judge their stay by the
+//! expectations of their segment when they leave.
+//!
+//! Vehicles aren't saved; after loading, pitches receive new arrivals instead.
+//!
+//! [`NavMesh`]: super::nav::NavMesh
+
+use std::time::Duration;
+
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::HashSet;
+
+use super::area::{ImmutableArea, Pool};
+use super::balance::Balance;
+use super::booking::Bookings;
+use super::decoration::LocalBeauty;
+use super::errand::VisitorNeeds;
+use super::facility::access_tile;
+use super::nav::{Path, VehicleNavMesh};
+use super::reception::{CampgroundStatus, ReceptionDesk, Visit};
+use super::road::{entrances, EntranceRoad};
+use super::rollup::{DailyTally, DAY_LENGTH};
+use super::staff::PitchUpkeep;
+use super::utilities::Connected;
+use super::visitor::VisitorSegment;
+use super::{ActorPosition, GridBox, GridPosition, GroundMap, Occupancy, Pitch, PitchType};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::ObjectPriority;
+use crate::ui::notifications::NotificationEvent;
+
+pub struct VehicleManagement;
+
+impl Plugin for VehicleManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<VehicleKind>()
+			.add_systems(
+				FixedUpdate,
+				(send_arriving_vehicles, leave_receptions, end_stays)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				drive_vehicles.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			)
+			.add_systems(Update, show_unpacking.in_set(GameSet::Graphics).in_set(GameState::InGame))
+			.add_systems(OnEnter(GameState::Loading), remove_vehicles)
+			.add_systems(OnEnter(GameState::MainMenu), remove_vehicles);
+	}
+}
+
+/// How long visitors stay at their pitch.
+const STAY_LENGTH: Duration = DAY_LENGTH;
+/// Driving speed relative to the ground's traversal speed.
+const DRIVE_SPEED_FACTOR: f32 = 1.5;
+/// How long visitors take to unpack after parking, and to pack up again before leaving.
+const UNPACK_TIME: Duration = Duration::from_secs(10);
+
+/// The kinds of vehicles that visitors arrive in.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VehicleKind {
+	/// A car towing a caravan.
+	Caravan,
+	CamperVan,
+}
+
+impl std::fmt::Display for VehicleKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Caravan => "Caravan",
+			Self::CamperVan => "Camper Van",
+		})
+	}
+}
+
+impl VehicleKind {
+	/// There are no vehicle graphics yet, so vehicles are drawn as colored rectangles.
+	fn sprite(&self) -> Sprite {
+		let (color, size) = match self {
+			Self::Caravan => (Color::srgb(0.93, 0.91, 0.85), Vec2::new(8., 6.)),
+			Self::CamperVan => (Color::srgb(0.35, 0.55, 0.8), Vec2::new(7., 7.)),
+		};
+		Sprite { anchor: Anchor::BottomCenter, ..Sprite::from_color(color, size) }
+	}
+
+	/// The luggage on the roof and the awning next to the vehicle, relative to the vehicle.
+	fn props(&self) -> [(Sprite, Transform); 2] {
+		let (roof_height, awning_color) = match self {
+			Self::Caravan => (6., Color::srgb(0.85, 0.45, 0.2)),
+			Self::CamperVan => (7., Color::srgb(0.3, 0.6, 0.35)),
+		};
+		[
+			(
+				Sprite {
+					anchor: Anchor::BottomCenter,
+					..Sprite::from_color(Color::srgb(0.3, 0.25, 0.2), Vec2::new(4., 2.))
+				},
+				Transform::from_xyz(0., roof_height, 0.01),
+			),
+			(
+				Sprite { anchor: Anchor::BottomCenter, ..Sprite::from_color(awning_color, Vec2::new(6., 5.)) },
+				Transform::from_xyz(-7., 0., -0.01),
+			),
+		]
+	}
+}
+
+/// Luggage that a vehicle carries on its roof while its visitors aren't unpacked.
+#[derive(Component, Clone, Copy, Debug)]
+struct Luggage;
+
+/// The awning that visitors put up next to their parked vehicle.
+#[derive(Component, Clone, Copy, Debug)]
+struct Awning;
+
+/// A vehicle of visitors staying at a pitch.
+#[derive(Component, Debug)]
+pub struct Vehicle {
+	pub kind:    VehicleKind,
+	/// The pitch that the visitors stay at.
+	pub pitch:   Entity,
+	/// The segment of the visitors, taken from their booking.
+	pub segment: VisitorSegment,
+	state:       VehicleState,
+	/// Remaining tiles to drive through.
+	path:        Path,
+}
+
+impl Vehicle {
+	/// Whether the vehicle is parked on its pitch, with its visitors staying there.
+	pub fn is_parked(&self) -> bool {
+		matches!(self.state, VehicleState::Parked { .. })
+	}
+
+	/// Whether the vehicle is on its way to its pitch, which includes checking in.
+	pub fn is_arriving(&self) -> bool {
+		matches!(self.state, VehicleState::CheckingIn { .. } | VehicleState::Arriving { .. })
+	}
+
+	/// The reception that the vehicle drives to or waits at, and why.
+	pub fn reception(&self) -> Option<(Entity, Visit)> {
+		self.state.reception().map(|(reception, visit, _)| (reception, visit))
+	}
+
+	/// How far the visitors have unpacked, between 0 while they are on the road and 1 once they are settled in.
+	/// Visitors unpack during the first [`UNPACK_TIME`] of their stay, and pack up during the last.
+	pub fn unpacked(&self) -> f32 {
+		match self.state {
+			VehicleState::Parked { remaining } => {
+				let since_parking = STAY_LENGTH.saturating_sub(remaining);
+				(since_parking.min(remaining).as_secs_f32() / UNPACK_TIME.as_secs_f32()).min(1.)
+			},
+			_ => 0.,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VehicleState {
+	/// Driving to the reception to check in, or waiting in its queue once the vehicle got there.
+	CheckingIn { reception: Entity, queued: bool },
+	/// Driving to the pitch, where the vehicle parks on the given tile after the end of its path.
+	Arriving { parking: GridPosition },
+	/// Parked on the pitch for the remaining duration of the stay.
+	Parked { remaining: Duration },
+	/// Driving to the reception to check out, or waiting in its queue once the vehicle got there.
+	CheckingOut { reception: Entity, queued: bool },
+	/// Driving to the entrance road, where the vehicle leaves at the end of its path. Vehicles that turned around
+	/// before parking haven't stayed at the campground.
+	Departing { stayed: bool },
+}
+
+impl VehicleState {
+	/// The reception that the vehicle drives to or waits at, why, and whether it is waiting in the queue.
+	const fn reception(&self) -> Option<(Entity, Visit, bool)> {
+		match *self {
+			Self::CheckingIn { reception, queued } => Some((reception, Visit::CheckIn, queued)),
+			Self::CheckingOut { reception, queued } => Some((reception, Visit::CheckOut, queued)),
+			_ => None,
+		}
+	}
+}
+
+/// The shortest path from the start to the gate of a reachable entrance road, where the vehicle leaves.
+fn route_to_exit(start: GridPosition, roads: &Query<&EntranceRoad>, mesh: &VehicleNavMesh) -> Option<Path> {
+	entrances(roads, mesh).into_iter().filter_map(|exit| mesh.pathfind(start, exit)).min_by_key(|path| path.len())
+}
+
+/// The reception with the shortest path from the start, the tile that vehicles wait on at its desk, and the path.
+fn route_to_reception(
+	start: GridPosition,
+	receptions: &Query<(Entity, &GridBox), With<ReceptionDesk>>,
+	mesh: &VehicleNavMesh,
+) -> Option<(Entity, GridPosition, Path)> {
+	receptions
+		.iter()
+		.filter_map(|(reception, grid_box)| {
+			let desk_tile = access_tile(grid_box, mesh)?;
+			Some((reception, desk_tile, mesh.pathfind(start, desk_tile)?))
+		})
+		.min_by_key(|(.., path)| path.len())
+}
+
+/// The tile of the pitch that a vehicle parks on, which has to be next to the vehicle nav mesh, and the neighboring
+/// nav mesh tile that the vehicle drives to and from.
+fn parking_tile(area: &ImmutableArea, mesh: &VehicleNavMesh) -> Option<(GridPosition, GridPosition)> {
+	area.0
+		.tiles_iter()
+		.filter_map(|tile| Some((tile, tile.neighbors().into_iter().find(|neighbor| mesh.contains(*neighbor))?)))
+		.min_by_key(|(tile, _)| (tile.x, tile.y, tile.z))
+}
+
+fn send_arriving_vehicles(
+	mesh: Res<VehicleNavMesh>,
+	roads: Query<(&EntranceRoad, &GridBox)>,
+	status: Res<CampgroundStatus>,
+	mut pitches: Query<(Entity, &Pitch, &ImmutableArea, &mut Bookings, Option<&Connected>)>,
+	receptions: Query<(Entity, &GridBox), With<ReceptionDesk>>,
+	vehicles: Query<&Vehicle>,
+	mut commands: Commands,
+) {
+	if *status != CampgroundStatus::Open {
+		return;
+	}
+	let occupied_pitches = vehicles.iter().map(|vehicle| vehicle.pitch).collect::<HashSet<_>>();
+	let mut arrival_roads = None;
+	for (entity, pitch, area, mut bookings, connected) in &mut pitches {
+		let Some(booking) = bookings.expected_arrival().copied() else {
+			continue;
+		};
+		let is_expected = pitch.kind == Some(PitchType::CaravanPitch)
+			&& pitch.occupancy == Occupancy::Free
+			&& connected.is_some_and(Connected::is_functional)
+			&& !occupied_pitches.contains(&entity);
+		if !is_expected {
+			continue;
+		}
+		let Some((_, access)) = parking_tile(area, &mesh) else {
+			continue;
+		};
+		// Visitors check in at the reception closest to their pitch.
+		let Some((reception, desk_tile, _)) = route_to_reception(access, &receptions, &mesh) else {
+			continue;
+		};
+		// Vehicles come in on an entrance road and drive through its gate.
+		let arrival_roads = arrival_roads.get_or_insert_with(|| {
+			roads
+				.iter()
+				.filter(|(road, _)| mesh.contains(road.gate))
+				.map(|(road, grid_box)| (grid_box.smallest(), road.gate))
+				.collect::<Vec<_>>()
+		});
+		fastrand::shuffle(arrival_roads);
+		let Some((entrance, path)) =
+			arrival_roads.iter().find_map(|(road, gate)| Some((*road, mesh.pathfind(*gate, desk_tile)?)))
+		else {
+			continue;
+		};
+
+		bookings.record_arrival();
+		let kind = if fastrand::bool() { VehicleKind::Caravan } else { VehicleKind::CamperVan };
+		debug!("{kind} of {} arriving at {entrance:?} for pitch {entity}", booking.segment);
+		let [luggage, awning] = kind.props();
+		commands
+			.spawn((
+				Vehicle {
+					kind,
+					pitch: entity,
+					segment: booking.segment,
+					state: VehicleState::CheckingIn { reception, queued: false },
+					path,
+				},
+				ActorPosition(entrance.0.as_vec3a() + Vec3A::new(0.5, 0.5, 0.)),
+				ObjectPriority::Normal,
+				kind.sprite(),
+			))
+			.with_children(|vehicle| {
+				vehicle.spawn((Luggage, luggage));
+				vehicle.spawn((Awning, awning, Visibility::Hidden));
+			});
+	}
+}
+
+/// Sends vehicles away whose stay is over or whose pitch doesn't exist anymore.
+fn end_stays(
+	time: Res<Time>,
+	mesh: Res<VehicleNavMesh>,
+	roads: Query<&EntranceRoad>,
+	pitches: Query<(&Pitch, &ImmutableArea, Option<&PitchUpkeep>, Option<&LocalBeauty>)>,
+	pools: Query<(), With<Pool>>,
+	receptions: Query<(Entity, &GridBox), With<ReceptionDesk>>,
+	mut desks: Query<&mut ReceptionDesk>,
+	mut vehicles: Query<(Entity, &mut Vehicle, &ActorPosition, Option<&VisitorNeeds>)>,
+	balance: Res<Balance>,
+	mut tally: ResMut<DailyTally>,
+	mut commands: Commands,
+) {
+	for (entity, mut vehicle, position, needs) in &mut vehicles {
+		let pitch = pitches.get(vehicle.pitch).ok().filter(|(pitch, ..)| pitch.kind == Some(PitchType::CaravanPitch));
+		let start = match (&mut vehicle.state, pitch) {
+			(VehicleState::Parked { remaining }, Some((pitch, area, upkeep, beauty))) => {
+				*remaining = remaining.saturating_sub(time.delta());
+				if !remaining.is_zero() {
+					continue;
+				}
+				// A stay lasts one night, which the visitors pay when they leave.
+				let price = balance.nightly_price(pitch);
+				tally.record_income(price);
+				// Visitors judge the pitch by how well it was kept during their stay, and enjoy beautiful surroundings.
+				// Long walks and queues on their errands spoil their stay, and so does a pitch that falls short of
+				// what their segment expects for the price.
+				if let Some(upkeep) = upkeep {
+					let errands = needs.map_or(1., VisitorNeeds::satisfaction);
+					let expectations =
+						pitch.kind.map_or(1., |kind| vehicle.segment.stay_satisfaction(kind, price, !pools.is_empty()));
+					tally.record_satisfaction(upkeep.pitch_satisfaction(beauty) * errands * expectations);
+				}
+				let access = parking_tile(area, &mesh).map(|(_, access)| access);
+				// Visitors check out before they leave, unless there is no reception to check out at.
+				if let Some((reception, _, path)) =
+					access.and_then(|access| route_to_reception(access, &receptions, &mesh))
+				{
+					vehicle.state = VehicleState::CheckingOut { reception, queued: false };
+					vehicle.path = path;
+					continue;
+				}
+				access
+			},
+			// The pitch is gone, so the parked vehicle has to find its way from wherever it is.
+			(VehicleState::Parked { .. }, None) =>
+				Some(position.tile()).into_iter().chain(position.tile().neighbors()).find(|tile| mesh.contains(*tile)),
+			(VehicleState::CheckingIn { reception, .. }, None) => {
+				if let Ok(mut desk) = desks.get_mut(*reception) {
+					desk.leave(entity);
+				}
+				Some(position.tile())
+			},
+			(VehicleState::Arriving { .. }, None) => Some(position.tile()),
+			(VehicleState::CheckingIn { .. } | VehicleState::Arriving { .. }, Some(_))
+			| (VehicleState::CheckingOut { .. } | VehicleState::Departing { .. }, _) => continue,
+		};
+
+		let stayed = matches!(vehicle.state, VehicleState::Parked { .. });
+		match start.and_then(|start| route_to_exit(start, &roads, &mesh)) {
+			Some(path) => {
+				vehicle.state = VehicleState::Departing { stayed };
+				vehicle.path = path;
+			},
+			None => {
+				debug!("{} {entity} can’t find a way out and leaves immediately", vehicle.kind);
+				if stayed {
+					tally.record_departure();
+				}
+				commands.entity(entity).despawn_recursive();
+			},
+		}
+	}
+}
+
+/// Sends vehicles on once the reception has served them: to their pitch after checking in, and out of the campground
+/// after checking out. Vehicles whose reception was removed don't wait any longer.
+fn leave_receptions(
+	mesh: Res<VehicleNavMesh>,
+	roads: Query<&EntranceRoad>,
+	pitches: Query<&ImmutableArea, With<Pitch>>,
+	desks: Query<&ReceptionDesk>,
+	mut vehicles: Query<(Entity, &mut Vehicle, &ActorPosition)>,
+	mut tally: ResMut<DailyTally>,
+	mut commands: Commands,
+) {
+	for (entity, mut vehicle, position) in &mut vehicles {
+		let Some((reception, visit, true)) = vehicle.state.reception() else {
+			continue;
+		};
+		if desks.get(reception).is_ok_and(|desk| desk.is_waiting(entity)) {
+			continue;
+		}
+
+		let start = position.tile();
+		if visit == Visit::CheckIn {
+			let route = pitches
+				.get(vehicle.pitch)
+				.ok()
+				.and_then(|area| parking_tile(area, &mesh))
+				.and_then(|(parking, access)| Some((parking, mesh.pathfind(start, access)?)));
+			if let Some((parking, path)) = route {
+				debug!("{} {entity} checked in", vehicle.kind);
+				vehicle.state = VehicleState::Arriving { parking };
+				vehicle.path = path;
+				continue;
+			}
+		}
+
+		let stayed = visit == Visit::CheckOut;
+		match route_to_exit(start, &roads, &mesh) {
+			Some(path) => {
+				vehicle.state = VehicleState::Departing { stayed };
+				vehicle.path = path;
+			},
+			None => {
+				debug!("{} {entity} can’t find a way out and leaves immediately", vehicle.kind);
+				if stayed {
+					tally.record_departure();
+				}
+				commands.entity(entity).despawn_recursive();
+			},
+		}
+	}
+}
+
+fn drive_vehicles(
+	time: Res<Time>,
+	ground_map: Res<GroundMap>,
+	mesh: Res<VehicleNavMesh>,
+	roads: Query<&EntranceRoad>,
+	mut desks: Query<&mut ReceptionDesk>,
+	mut vehicles: Query<(Entity, &mut Vehicle, &mut ActorPosition)>,
+	mut tally: ResMut<DailyTally>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut commands: Commands,
+) {
+	let mut arrivals = 0;
+	for (entity, mut vehicle, mut position) in &mut vehicles {
+		let Vehicle { state, path, kind, .. } = &mut *vehicle;
+		let target = match (path.start().copied(), *state) {
+			(_, VehicleState::Parked { .. }) => continue,
+			(Some(tile), _) => {
+				// The pathway was torn up in front of the vehicle, so it gives up and turns around.
+				if !mesh.contains(tile) {
+					let stayed =
+						matches!(state, VehicleState::Departing { stayed: true } | VehicleState::CheckingOut { .. });
+					match route_to_exit(position.tile(), &roads, &mesh) {
+						Some(new_path) => {
+							*state = VehicleState::Departing { stayed };
+							*path = new_path;
+						},
+						None => {
+							if stayed {
+								tally.record_departure();
+							}
+							commands.entity(entity).despawn_recursive();
+						},
+					}
+					continue;
+				}
+				tile
+			},
+			(None, VehicleState::CheckingIn { .. } | VehicleState::CheckingOut { .. }) => {
+				// Arrived at the reception, where the vehicle waits until it was served.
+				if let Some((reception, visit, false)) = state.reception() {
+					if let Ok(mut desk) = desks.get_mut(reception) {
+						desk.join(entity, visit);
+					}
+					if let VehicleState::CheckingIn { queued, .. } | VehicleState::CheckingOut { queued, .. } = state {
+						*queued = true;
+					}
+				}
+				continue;
+			},
+			(None, VehicleState::Arriving { parking }) => {
+				if position.tile() == parking {
+					debug!("{kind} {entity} parked at {parking:?}");
+					*state = VehicleState::Parked { remaining: STAY_LENGTH };
+					tally.record_arrival();
+					arrivals += 1;
+					continue;
+				}
+				parking
+			},
+			(None, VehicleState::Departing { stayed }) => {
+				debug!("{kind} {entity} left the campground");
+				if stayed {
+					tally.record_departure();
+				}
+				commands.entity(entity).despawn_recursive();
+				continue;
+			},
+		};
+
+		let speed =
+			ground_map.kind_of(&position.tile()).map_or(1, |kind| kind.traversal_speed()) as f32 * DRIVE_SPEED_FACTOR;
+		let step = speed * time.delta_secs();
+		let target_position = target.0.as_vec3a() + Vec3A::new(0.5, 0.5, 0.);
+		let offset = target_position - position.0;
+		if offset.length() <= step {
+			position.0 = target_position;
+			if path.start() == Some(&target) {
+				path.pop_start();
+			}
+		} else {
+			position.0 += offset.normalize() * step;
+		}
+	}
+
+	match arrivals {
+		0 => {},
+		1 => {
+			notifications.send(NotificationEvent::info("New visitors arrived at their pitch."));
+		},
+		_ => {
+			notifications
+				.send(NotificationEvent::info(format!("{arrivals} new visitor groups arrived at their pitches.")));
+		},
+	}
+}
+
+/// The awning grows out of the ground while the visitors unpack, and the luggage disappears from the roof once they
+/// started. With reduced effects, the awning appears at once.
+fn show_unpacking(
+	settings: Res<GameSettings>,
+	vehicles: Query<(&Vehicle, &Children)>,
+	mut luggage: Query<&mut Visibility, (With<Luggage>, Without<Awning>)>,
+	mut awnings: Query<(&mut Transform, &mut Visibility), With<Awning>>,
+) {
+	for (vehicle, children) in &vehicles {
+		let mut unpacked = vehicle.unpacked();
+		if settings.reduced_effects {
+			unpacked = unpacked.ceil();
+		}
+		let visibility = |visible: bool| if visible { Visibility::Inherited } else { Visibility::Hidden };
+		for child in children {
+			if let Ok(mut luggage) = luggage.get_mut(*child) {
+				luggage.set_if_neq(visibility(unpacked == 0.));
+			} else if let Ok((mut transform, mut awning)) = awnings.get_mut(*child) {
+				awning.set_if_neq(visibility(unpacked > 0.));
+				transform.scale.y = unpacked;
+			}
+		}
+	}
+}
+
+fn remove_vehicles(vehicles: Query<Entity, With<Vehicle>>, mut commands: Commands) {
+	for vehicle in &vehicles {
+		commands.entity(vehicle).despawn_recursive();
+	}
+}
@@ -0,0 +1,215 @@
+//! Visitor segments: groups of visitors with distinct expectations of the campground.
+//!
+//! Segments differ in the comfort they expect, what they are willing to pay, which pitches they prefer and when they
+//! travel. They make a campground's offering matter: a site full of tent pitches draws backpackers in summer, but
+//! little else. The [`super::demand`] for a pitch follows the travel seasons of the segments that it appeals to, and
+//! pitches show which segments they appeal to.
+//!
+//! Every booking is made by visitors of one segment, drawn by how much the segments travel in the current season and
+//! how well the pitch suits them. Visitors don't book pitches that cost more than their budget, and they arrive in
+//! their vehicle as the segment of their booking. When they leave, they judge their stay by whether the pitch lived
+//! up to the comfort they expect, how much of their budget it took, and whether the campground has the pool that
+//! families look for.
+
+use bevy::prelude::*;
+
+use super::calendar::{Season, ALL_SEASONS};
+use super::pitch::{Comfort, Pitch, PitchType};
+use crate::ui::dialog::DialogWidget;
+use crate::ui::world_info::WorldInfoSectionExt;
+
+pub struct VisitorManagement;
+
+impl Plugin for VisitorManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<VisitorSegment>().add_world_info_section::<Pitch>("Visitor appeal", |pitch| {
+			let Some(kind) = pitch.kind else {
+				return Vec::new();
+			};
+			ALL_VISITOR_SEGMENTS
+				.into_iter()
+				.map(|segment| DialogWidget::Property {
+					name:  segment.to_string(),
+					value: segment.appeal(kind).to_string(),
+				})
+				.collect()
+		});
+	}
+}
+
+/// A group of visitors with shared expectations.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VisitorSegment {
+	/// Young travellers on a budget, who are happy with a patch of grass for their tent.
+	Backpackers,
+	/// Families with children, who value space and a pool.
+	#[default]
+	Families,
+	/// Visitors who expect hotel-like comfort and are willing to pay for it.
+	LuxuryCampers,
+}
+
+/// All visitor segments, in the order they are displayed in.
+pub const ALL_VISITOR_SEGMENTS: [VisitorSegment; 3] =
+	[VisitorSegment::Backpackers, VisitorSegment::Families, VisitorSegment::LuxuryCampers];
+
+impl std::fmt::Display for VisitorSegment {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Backpackers => "Backpackers",
+			Self::Families => "Families",
+			Self::LuxuryCampers => "Luxury campers",
+		})
+	}
+}
+
+/// How well a pitch suits a visitor segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Appeal {
+	/// The segment avoids this pitch.
+	Poor,
+	/// The segment stays here if nothing better is available.
+	Acceptable,
+	/// The segment seeks out this pitch.
+	Good,
+}
+
+impl Appeal {
+	/// How much a segment counts towards the visitors of a pitch with this appeal. Segments that seek out the pitch
+	/// count fully, segments that merely accept it half.
+	pub const fn weight(&self) -> f32 {
+		match self {
+			Self::Good => 1.,
+			Self::Acceptable => 0.5,
+			Self::Poor => 0.,
+		}
+	}
+}
+
+impl std::fmt::Display for Appeal {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Poor => "Poor",
+			Self::Acceptable => "Acceptable",
+			Self::Good => "Good",
+		})
+	}
+}
+
+impl VisitorSegment {
+	/// The least comfort that this segment accepts.
+	pub fn expected_comfort(&self) -> Comfort {
+		match self {
+			Self::Backpackers => 1,
+			Self::Families => 3,
+			Self::LuxuryCampers => 6,
+		}
+		.try_into()
+		.unwrap()
+	}
+
+	/// The most that a visitor group of this segment pays per night.
+	pub const fn nightly_budget(&self) -> u32 {
+		match self {
+			Self::Backpackers => 20,
+			Self::Families => 60,
+			Self::LuxuryCampers => 150,
+		}
+	}
+
+	/// Pitch types that this segment prefers over all others.
+	pub const fn preferred_pitch_types(&self) -> &'static [PitchType] {
+		match self {
+			Self::Backpackers => &[PitchType::TentPitch],
+			Self::Families => &[PitchType::CaravanPitch, PitchType::MobileHome],
+			Self::LuxuryCampers => &[PitchType::Cottage],
+		}
+	}
+
+	/// Whether this segment prefers campgrounds with a pool.
+	pub const fn wants_pool(&self) -> bool {
+		matches!(self, Self::Families)
+	}
+
+	/// Share of this segment's yearly visits that fall into the given season. The shares of all seasons add up to 1.
+	pub const fn seasonal_share(&self, season: Season) -> f32 {
+		match (self, season) {
+			(Self::Backpackers, Season::Summer) => 0.6,
+			(Self::Backpackers, Season::Spring | Season::Autumn) => 0.2,
+			(Self::Families, Season::Summer) => 0.7,
+			(Self::Families, Season::Spring) => 0.15,
+			(Self::Families, Season::Autumn) => 0.1,
+			(Self::Families, Season::Winter) => 0.05,
+			(Self::LuxuryCampers, Season::Summer) => 0.4,
+			(Self::LuxuryCampers, Season::Spring | Season::Autumn) => 0.25,
+			(Self::LuxuryCampers, Season::Winter) => 0.1,
+			(Self::Backpackers, Season::Winter) => 0.,
+		}
+	}
+
+	/// How well a pitch of the given type suits this segment.
+	pub fn appeal(&self, pitch_type: PitchType) -> Appeal {
+		if self.preferred_pitch_types().contains(&pitch_type) {
+			Appeal::Good
+		} else if pitch_type.comfort() >= self.expected_comfort() {
+			Appeal::Acceptable
+		} else {
+			Appeal::Poor
+		}
+	}
+
+	/// Draws the segment of visitors that book a pitch of the given type in the season, weighted by how much each
+	/// segment travels in the season and how well the pitch suits it. [`None`] if the pitch suits no segment that
+	/// travels in the season.
+	pub fn draw(pitch_type: PitchType, season: Season) -> Option<Self> {
+		let weight = |segment: &Self| segment.appeal(pitch_type).weight() * segment.seasonal_share(season);
+		let total = ALL_VISITOR_SEGMENTS.iter().map(weight).sum::<f32>();
+		let mut remaining = fastrand::f32() * total;
+		let mut candidates = ALL_VISITOR_SEGMENTS.into_iter().filter(|segment| weight(segment) > 0.);
+		// Rounding may leave a tiny remainder after the last candidate, which belongs to it.
+		let last = candidates.clone().next_back();
+		candidates
+			.find(|segment| {
+				remaining -= weight(segment);
+				remaining < 0.
+			})
+			.or(last)
+	}
+
+	/// Whether visitors of this segment are willing to pay the nightly price.
+	pub fn accepts_price(&self, nightly_price: u64) -> bool {
+		nightly_price <= u64::from(self.nightly_budget())
+	}
+
+	/// How satisfied visitors of this segment are with a night on a pitch of the given type at the given price,
+	/// between 0 and 1, before the pitch's upkeep and their errands are considered. Pitches below the expected
+	/// comfort disappoint, prices above half the budget are felt, and families miss a pool if the campground has none.
+	pub fn stay_satisfaction(&self, pitch_type: PitchType, nightly_price: u64, has_pool: bool) -> f32 {
+		let comfort = match self.appeal(pitch_type) {
+			Appeal::Good => 1.,
+			Appeal::Acceptable => 0.9,
+			Appeal::Poor => 0.6,
+		};
+		let budget_share = nightly_price as f32 / self.nightly_budget() as f32;
+		let price = 1. - 0.4 * (budget_share - 0.5).clamp(0., 0.5);
+		let pool = if self.wants_pool() && !has_pool { 0.85 } else { 1. };
+		comfort * price * pool
+	}
+
+	/// Player-facing summary of this segment's expectations.
+	pub fn summary(&self) -> String {
+		let preferred = self.preferred_pitch_types().iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+		let seasons = ALL_SEASONS
+			.into_iter()
+			.filter(|season| self.seasonal_share(*season) > 0.)
+			.map(|season| format!("{season} {:.0}%", self.seasonal_share(season) * 100.))
+			.collect::<Vec<_>>()
+			.join(", ");
+		format!(
+			"Expects comfort {} or more and pays up to {} per night. Prefers {preferred}{}. Visits: {seasons}.",
+			self.expected_comfort(),
+			self.nightly_budget(),
+			if self.wants_pool() { ", and a pool" } else { "" },
+		)
+	}
+}
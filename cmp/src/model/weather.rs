@@ -0,0 +1,152 @@
+//! Weather, which changes once per day and influences the visitors.
+//!
+//! At the end of every day, the next day's [`Weather`] is drawn from the transition chances of the current weather, so
+//! that rainy days tend to come in streaks and storms never last for two days. Bad weather registers [`Modifier`]s:
+//! rain keeps away new arrivals and empties the pools, and storms do so even more. Storms also damage tent pitches at
+//! the start of the day, which then need repairs by maintenance staff.
+
+use bevy::prelude::*;
+
+use super::modifiers::{Modifier, ModifierTarget, Modifiers};
+use super::pitch::{Pitch, PitchType};
+use super::rollup::{DailyTally, DayEnded, RollupSet};
+use super::staff::PitchUpkeep;
+use crate::gamemode::{GameSet, GameState};
+use crate::util::Tooltipable;
+
+pub struct WeatherManagement;
+
+impl Plugin for WeatherManagement {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Weather>()
+			.init_resource::<Weather>()
+			.add_systems(
+				FixedUpdate,
+				(change_weather, damage_tent_pitches)
+					.chain()
+					.after(RollupSet::Notify)
+					.run_if(on_event::<DayEnded>)
+					.in_set(GameSet::Simulation)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(Update, apply_weather_modifiers.in_set(GameSet::Simulation).in_set(GameState::InGame));
+	}
+}
+
+/// Chance that a storm damages a tent pitch.
+const STORM_DAMAGE_CHANCE: f32 = 0.3;
+/// How much of its condition a tent pitch loses when damaged by a storm.
+const STORM_DAMAGE: f32 = 0.5;
+
+/// The weather of the current day.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[reflect(Resource)]
+pub enum Weather {
+	#[default]
+	Sunny,
+	Rain,
+	Storm,
+}
+
+/// All kinds of weather, in the order of [`Weather::transition_chances`].
+pub const ALL_WEATHER: [Weather; 3] = [Weather::Sunny, Weather::Rain, Weather::Storm];
+
+impl Weather {
+	const MODIFIER_NAME: &str = "Bad weather";
+
+	/// Chances for the next day's weather after a day of this weather, in the order of [`ALL_WEATHER`].
+	pub const fn transition_chances(&self) -> [f32; 3] {
+		match self {
+			Self::Sunny => [0.7, 0.25, 0.05],
+			Self::Rain => [0.5, 0.35, 0.15],
+			Self::Storm => [0.4, 0.6, 0.],
+		}
+	}
+
+	/// Randomly picks the next day's weather.
+	pub fn next(&self) -> Self {
+		let mut roll = fastrand::f32();
+		for (weather, chance) in ALL_WEATHER.into_iter().zip(self.transition_chances()) {
+			if roll < chance {
+				return weather;
+			}
+			roll -= chance;
+		}
+		Self::Sunny
+	}
+
+	/// Modifiers that this weather registers. Their names are shared between all weather, so that changing the
+	/// weather replaces them.
+	pub fn modifiers(&self) -> Vec<Modifier> {
+		match self {
+			Self::Sunny => Vec::new(),
+			Self::Rain => vec![
+				Modifier::multiply(Self::MODIFIER_NAME, ModifierTarget::Demand, 0.6),
+				Modifier::multiply(Self::MODIFIER_NAME, ModifierTarget::PoolUsage, 0.3),
+			],
+			Self::Storm => vec![
+				Modifier::multiply(Self::MODIFIER_NAME, ModifierTarget::Demand, 0.3),
+				Modifier::disable(Self::MODIFIER_NAME, ModifierTarget::PoolUsage),
+			],
+		}
+	}
+}
+
+impl std::fmt::Display for Weather {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Sunny => "Sunny",
+			Self::Rain => "Rain",
+			Self::Storm => "Storm",
+		})
+	}
+}
+
+impl Tooltipable for Weather {
+	fn description(&self) -> &'static str {
+		match self {
+			Self::Sunny => "Clear skies. Visitors arrive and use the pools as usual.",
+			Self::Rain => "Fewer visitors arrive, and the pools stay mostly empty.",
+			Self::Storm =>
+				"Hardly anyone arrives, the pools are closed, and tent pitches may have been damaged. Maintenance \
+				 staff repair them.",
+		}
+	}
+}
+
+fn change_weather(mut weather: ResMut<Weather>) {
+	let next = weather.next();
+	if next != *weather {
+		info!("The weather changes to {next}");
+		*weather = next;
+	}
+}
+
+fn damage_tent_pitches(
+	weather: Res<Weather>,
+	modifiers: Res<Modifiers>,
+	mut pitches: Query<(&Pitch, &mut PitchUpkeep)>,
+	mut tally: ResMut<DailyTally>,
+) {
+	if *weather != Weather::Storm {
+		return;
+	}
+	let chance = STORM_DAMAGE_CHANCE * modifiers.factor(ModifierTarget::Incidents);
+	for (_, mut upkeep) in pitches.iter_mut().filter(|(pitch, _)| pitch.kind == Some(PitchType::TentPitch)) {
+		if fastrand::f32() < chance {
+			upkeep.condition = (upkeep.condition - STORM_DAMAGE).max(0.);
+			tally.record_incident();
+		}
+	}
+}
+
+/// Keeps the weather modifiers in sync with the weather, including after loading a save.
+fn apply_weather_modifiers(weather: Res<Weather>, mut modifiers: ResMut<Modifiers>) {
+	if !weather.is_changed() {
+		return;
+	}
+	modifiers.remove(Weather::MODIFIER_NAME);
+	for modifier in weather.modifiers() {
+		modifiers.register(modifier);
+	}
+}
@@ -0,0 +1,292 @@
+//! Code mods, which are loaded from dynamic libraries at startup.
+//!
+//! Mods are dynamic libraries (`.so`, `.dll` or `.dylib`, depending on the system) in the `mods` folder of the data
+//! directory, next to the saves. A mod declares its entry point with [`cmp_mod!`](crate::cmp_mod), which is called
+//! with a [`CmpModApi`] while the game starts up. Through it, the mod registers its content: buildables offered in the
+//! build menus, new ground kinds, new build menus, world info sections, dialogs, and systems. Anything else can be
+//! changed through the [`App`] directly. The mod's systems can open dialogs as well, by sending [`OpenDialog`] events.
+//!
+//! Rust has no stable ABI, so a mod only works with the exact game version that it was built against, built with the
+//! same compiler. Every mod records the [`MOD_API_VERSION`] and the game [`VERSION`] that it was built against, and
+//! both are checked before its entry point is called; mods for any other version are skipped with a warning. The
+//! libraries stay loaded until the game exits, since their systems and strings are used until then.
+//!
+//! Mods are only loaded with the `mods` feature. The game and its mods have to share one copy of the engine, since
+//! Bevy keeps global state such as its schedule labels and hash seeds in statics: the game links the engine through the
+//! `cmp-dylib` crate with the client's `mods` feature, and mods are `dylib` crates that depend on `cmp-dylib` as well.
+//!
+//! Custom ground kinds and build menus are numbered in the order that they are registered, and mods are loaded in the
+//! order of their file names. Saves that use custom ground kinds need the same mods to be installed; without them,
+//! unknown ground looks and behaves like grass.
+
+use std::ffi::CStr;
+#[cfg(feature = "mods")]
+use std::path::Path;
+#[cfg(feature = "mods")]
+use std::sync::Mutex;
+use std::sync::OnceLock;
+
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+#[cfg(feature = "mods")]
+use libloading::{Library, Symbol};
+
+pub use crate::gamemode::{GameSet, GameState};
+pub use crate::model::buildables::BuildableDefinition;
+#[cfg(feature = "mods")]
+use crate::model::buildables::BuildableRegistry;
+pub use crate::model::{Buildable, GroundKind};
+#[cfg(feature = "mods")]
+use crate::save::save_directory;
+pub use crate::ui::controls::BuildMenu;
+pub use crate::ui::dialog::{DialogKind, DialogWidget, OpenDialog};
+use crate::ui::world_info::WorldInfoSectionExt;
+pub use crate::VERSION;
+
+/// Version of the [`CmpModApi`], which changes whenever the API changes in a way that requires mods to be rebuilt.
+pub const MOD_API_VERSION: u32 = 1;
+/// The game [`VERSION`] as a C string, which mods record when they are built.
+pub const GAME_VERSION: &CStr = match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+	Ok(version) => version,
+	Err(_) => panic!("the game version contains a null byte"),
+};
+
+/// Name of the symbol that a mod exports its [`MOD_API_VERSION`] with.
+const API_VERSION_SYMBOL: &[u8] = b"_cmp_mod_api_version";
+/// Name of the symbol that a mod exports its game [`VERSION`] with.
+const GAME_VERSION_SYMBOL: &[u8] = b"_cmp_mod_game_version";
+/// Name of the symbol of a mod's entry point.
+const CREATE_SYMBOL: &[u8] = b"_cmp_create_mod";
+
+/// Declares the entry point of a mod, which is called with the [`CmpModApi`] to register the mod's content. Along with
+/// it, the mod records the API and game version that it is built against.
+///
+/// ```ignore
+/// fn create(api: &mut CmpModApi) {
+///     api.register_buildable(definition);
+/// }
+///
+/// cmp::cmp_mod!(create);
+/// ```
+#[macro_export]
+macro_rules! cmp_mod {
+	($create:path) => {
+		#[no_mangle]
+		pub extern "C" fn _cmp_mod_api_version() -> u32 {
+			$crate::mods::MOD_API_VERSION
+		}
+
+		#[no_mangle]
+		pub extern "C" fn _cmp_mod_game_version() -> *const ::std::ffi::c_char {
+			$crate::mods::GAME_VERSION.as_ptr()
+		}
+
+		#[no_mangle]
+		pub fn _cmp_create_mod(api: &mut $crate::mods::CmpModApi) {
+			$create(api)
+		}
+	};
+}
+
+/// A ground kind that a mod adds. It has its own name and look, but behaves like one of the built-in ground kinds:
+/// who can walk or drive on it and how fast, and what routing conduits through it costs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CustomGroundKind {
+	/// Player-facing name.
+	pub name:         &'static str,
+	/// Player-facing description.
+	pub description:  &'static str,
+	/// Asset path of the tile image.
+	pub image:        &'static str,
+	/// Average color of the image, which is used where tiles are too small to make out, such as on the minimap.
+	pub color:        Color,
+	/// The built-in ground kind that this ground kind behaves like.
+	pub behaves_like: GroundKind,
+}
+
+/// A build menu that a mod adds, which appears after the built-in menus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CustomMenu {
+	/// Player-facing name.
+	pub name:        &'static str,
+	/// Player-facing description.
+	pub description: &'static str,
+	/// Asset path of the menu's logo.
+	pub logo:        &'static str,
+}
+
+/// All ground kinds that mods added, indexed by the number of their [`GroundKind::Custom`].
+static CUSTOM_GROUND_KINDS: OnceLock<Vec<CustomGroundKind>> = OnceLock::new();
+/// All build menus that mods added, indexed by the number of their [`BuildMenu::Custom`].
+static CUSTOM_MENUS: OnceLock<Vec<CustomMenu>> = OnceLock::new();
+/// The loaded mod libraries. They are never unloaded, since statics aren't dropped.
+#[cfg(feature = "mods")]
+static LIBRARIES: Mutex<Vec<Library>> = Mutex::new(Vec::new());
+
+/// The custom ground kind with the number, if a mod registered it.
+pub(crate) fn custom_ground_kind(number: u8) -> Option<&'static CustomGroundKind> {
+	CUSTOM_GROUND_KINDS.get()?.get(usize::from(number))
+}
+
+/// All custom ground kinds, in the order that they were registered.
+pub(crate) fn custom_ground_kinds() -> impl Iterator<Item = GroundKind> {
+	let count = CUSTOM_GROUND_KINDS.get().map_or(0, Vec::len);
+	(0 ..= u8::MAX).take(count).map(GroundKind::Custom)
+}
+
+/// All custom build menus, in the order that they were registered.
+pub(crate) fn custom_menus() -> &'static [CustomMenu] {
+	CUSTOM_MENUS.get().map_or(&[], Vec::as_slice)
+}
+
+/// What mods register, which is applied to the game once all mods are loaded.
+#[derive(Default)]
+struct ModContent {
+	buildables:   Vec<BuildableDefinition>,
+	ground_kinds: Vec<CustomGroundKind>,
+	menus:        Vec<CustomMenu>,
+}
+
+/// The interface that a mod registers its content through, see the [module documentation](self).
+pub struct CmpModApi<'a> {
+	app:     &'a mut App,
+	name:    &'a str,
+	content: &'a mut ModContent,
+}
+
+impl CmpModApi<'_> {
+	/// The name of the mod, which is the file name of its library without the system's prefix and extension.
+	pub fn mod_name(&self) -> &str {
+		self.name
+	}
+
+	/// The game, for anything that the API has no dedicated method for, such as resources and events.
+	pub fn app(&mut self) -> &mut App {
+		self.app
+	}
+
+	/// Offers a buildable to the player. A definition for a buildable that is already offered replaces that one,
+	/// including definitions from the buildable manifest.
+	pub fn register_buildable(&mut self, definition: BuildableDefinition) -> &mut Self {
+		self.content.buildables.push(definition);
+		self
+	}
+
+	/// Adds a new ground kind, and returns the [`GroundKind`] that refers to it, such as for offering it as a
+	/// buildable. There can be at most 256 custom ground kinds; further ground kinds are replaced by grass.
+	pub fn register_ground_kind(&mut self, mut ground_kind: CustomGroundKind) -> GroundKind {
+		let Ok(number) = u8::try_from(self.content.ground_kinds.len()) else {
+			error!("{} adds too many ground kinds, {} is replaced by grass", self.name, ground_kind.name);
+			return GroundKind::Grass;
+		};
+		if let GroundKind::Custom(_) = ground_kind.behaves_like {
+			warn!("{} can only behave like a built-in ground kind, it behaves like grass instead", ground_kind.name);
+			ground_kind.behaves_like = GroundKind::Grass;
+		}
+		self.content.ground_kinds.push(ground_kind);
+		GroundKind::Custom(number)
+	}
+
+	/// Adds a new build menu, and returns the [`BuildMenu`] that refers to it, such as for offering buildables in it.
+	/// There can be at most 256 custom build menus; further menus are replaced by the basics menu.
+	pub fn register_menu(&mut self, menu: CustomMenu) -> BuildMenu {
+		let Ok(number) = u8::try_from(self.content.menus.len()) else {
+			error!("{} adds too many build menus, {} is replaced by the basics menu", self.name, menu.name);
+			return BuildMenu::Basics;
+		};
+		self.content.menus.push(menu);
+		BuildMenu::Custom(number)
+	}
+
+	/// Adds a section to the world info of all entities with the component `C`, like the game's own sections. The
+	/// section is shown below the entity's regular properties, with the heading and the widgets returned by `build`.
+	pub fn add_world_info_section<C: Component>(
+		&mut self,
+		heading: impl Into<String>,
+		build: impl Fn(&C) -> Vec<DialogWidget> + Send + Sync + 'static,
+	) -> &mut Self {
+		self.app.add_world_info_section(heading, build);
+		self
+	}
+
+	/// Opens a dialog once the game is running. Dialogs are shown one after another, so the dialog waits until the
+	/// player closed any dialogs opened before it.
+	pub fn open_dialog(&mut self, dialog: OpenDialog) -> &mut Self {
+		self.app.world_mut().send_event(dialog);
+		self
+	}
+
+	/// Adds systems to the schedule, like [`App::add_systems`]. Use [`GameSet`] and [`GameState`] to run them
+	/// alongside the game's own systems.
+	pub fn add_systems<M>(&mut self, schedule: impl ScheduleLabel, systems: impl IntoSystemConfigs<M>) -> &mut Self {
+		self.app.add_systems(schedule, systems);
+		self
+	}
+}
+
+/// Loads all mods from the `mods` folder of the data directory. This has to happen after all of the game's plugins
+/// were added, so that mods can change their content.
+#[cfg(feature = "mods")]
+pub(crate) fn load_mods(app: &mut App) {
+	let Some(directory) = save_directory().map(|directory| directory.join("mods")) else {
+		warn!("There is no data directory to load mods from");
+		return;
+	};
+	if let Err(why) = std::fs::create_dir_all(&directory) {
+		warn!("Couldn’t create the mods folder {directory:?}: {why}");
+		return;
+	}
+	let mut paths = match std::fs::read_dir(&directory) {
+		Ok(entries) => entries
+			.filter_map(|entry| Some(entry.ok()?.path()))
+			.filter(|path| path.extension().is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION))
+			.collect::<Vec<_>>(),
+		Err(why) => {
+			warn!("Couldn’t read the mods folder {directory:?}: {why}");
+			return;
+		},
+	};
+	paths.sort();
+	info!("Loading {} mods from {directory:?}", paths.len());
+
+	let mut content = ModContent::default();
+	for path in paths {
+		// SAFETY: Loading a library runs its initialization code, which mods are trusted with, like all of their code.
+		match unsafe { load_mod(app, &path, &mut content) } {
+			Ok(library) => LIBRARIES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(library),
+			Err(why) => warn!("Skipping mod {path:?}: {why}"),
+		}
+	}
+
+	let _ = CUSTOM_GROUND_KINDS.set(content.ground_kinds);
+	let _ = CUSTOM_MENUS.set(content.menus);
+	if !content.buildables.is_empty() {
+		app.world_mut().resource_mut::<BuildableRegistry>().add_mod_definitions(content.buildables);
+	}
+}
+
+/// Loads one mod, checks that it was built for this game version, and calls its entry point.
+///
+/// # Safety
+/// The library's initialization code and entry point run with no guarantees, and the exported symbols have to have
+/// the types that [`cmp_mod!`](crate::cmp_mod) gives them.
+#[cfg(feature = "mods")]
+unsafe fn load_mod(app: &mut App, path: &Path, content: &mut ModContent) -> anyhow::Result<Library> {
+	let library = Library::new(path)?;
+	let api_version: Symbol<extern "C" fn() -> u32> = library.get(API_VERSION_SYMBOL)?;
+	let game_version: Symbol<extern "C" fn() -> *const std::ffi::c_char> = library.get(GAME_VERSION_SYMBOL)?;
+	let api_version = api_version();
+	let game_version = CStr::from_ptr(game_version()).to_string_lossy();
+	anyhow::ensure!(
+		api_version == MOD_API_VERSION && game_version == VERSION,
+		"it was built for game version {game_version} with mod API version {api_version}, not for game version \
+		 {VERSION} with mod API version {MOD_API_VERSION}"
+	);
+
+	let create: Symbol<fn(&mut CmpModApi)> = library.get(CREATE_SYMBOL)?;
+	let file_name = path.file_stem().map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+	let name = file_name.strip_prefix(std::env::consts::DLL_PREFIX).unwrap_or(&file_name);
+	info!("Loading mod {name}");
+	create(&mut CmpModApi { app, name, content });
+	Ok(library)
+}
@@ -0,0 +1,401 @@
+//! Multiplayer state replication.
+//!
+//! One game hosts a session with `--host <port>`, typically a headless server, and other games join it with
+//! `--join <address>`. Games talk over TCP in frames of a length followed by a [`NetMessage`] in the [wire
+//! format](crate::wire). After both sides said hello, the host sends a snapshot of its whole world, which is a save
+//! written into memory, and the joining game loads it like a save. From then on, every build and demolition that a
+//! game performs is sent to the other games and performed there as well. The host is the authority: it performs the
+//! actions that players send it, and only forwards them to the other players if they succeed. Builds that the host
+//! can't perform, because another player built in the same place in the meantime, are sent back to their player as
+//! rejected, and that player's game reverts them.
+//!
+//! Only the built world is replicated. Visitors, staff and the economy are simulated by every game on its own, and
+//! undoing a build and clearing obstacles are not replicated yet.
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use parking_lot::Mutex;
+
+use crate::config::CLIResource;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::save::{LoadSave, StoreSnapshot};
+use crate::ui::build::{BuildCompleted, BuildRequests, RevertBuild};
+use crate::ui::demolish::PerformDemolish;
+use crate::ui::notifications::NotificationEvent;
+use crate::ui::rect_tool::TileRect;
+use crate::wire::{from_bytes, to_bytes, BuildCommand, DemolishCommand, NetMessage, Wire, WireError};
+use crate::VERSION;
+
+pub(crate) struct NetPlugin;
+
+impl Plugin for NetPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Session>()
+			.add_systems(Startup, start_session)
+			.add_systems(
+				Update,
+				(
+					accept_peers,
+					receive_messages,
+					request_snapshot.in_set(GameState::InGame),
+					send_snapshot,
+					// Remote actions are only performed once a joined world is loaded, or they would be overwritten.
+					perform_remote_actions.in_set(SimulationActive).run_if(not(resource_exists::<LoadSave>)),
+				)
+					.chain()
+					.in_set(GameSet::Input),
+			)
+			.add_systems(PostUpdate, (replicate_actions, send_messages).chain());
+	}
+}
+
+/// How long joining waits for the host to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest message that is accepted, which bounds the memory that a broken peer can make us allocate.
+const MAX_MESSAGE_LENGTH: u64 = 256 * 1024 * 1024;
+
+/// How far a peer has gotten into the session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PeerState {
+	/// The peer hasn't said hello yet.
+	Connected,
+	/// The peer said hello and waits for the host's world.
+	AwaitingSnapshot,
+	/// The peer has the same world as we do, and receives all actions.
+	Synchronized,
+}
+
+/// Another game connected to this one. The host has one peer per player; a joined game has the host as its only peer.
+#[derive(Debug)]
+struct Peer {
+	id:       u64,
+	address:  SocketAddr,
+	stream:   TcpStream,
+	state:    PeerState,
+	/// Received bytes that don't form a complete message yet.
+	incoming: Vec<u8>,
+	/// Encoded messages that couldn't be sent yet.
+	outgoing: Vec<u8>,
+	/// Whether the peer closed the connection. Messages that it sent before are still handled.
+	closed:   bool,
+}
+
+impl Peer {
+	fn new(id: u64, address: SocketAddr, stream: TcpStream) -> std::io::Result<Self> {
+		stream.set_nonblocking(true)?;
+		stream.set_nodelay(true)?;
+		let mut peer = Self {
+			id,
+			address,
+			stream,
+			state: PeerState::Connected,
+			incoming: Vec::new(),
+			outgoing: Vec::new(),
+			closed: false,
+		};
+		peer.send(&NetMessage::Hello(VERSION.to_string()));
+		Ok(peer)
+	}
+
+	fn send(&mut self, message: &NetMessage) {
+		let message = to_bytes(message);
+		(message.len() as u64).encode(&mut self.outgoing);
+		self.outgoing.extend_from_slice(&message);
+	}
+
+	/// Writes as much of the outgoing data as the connection accepts without blocking.
+	fn flush(&mut self) -> std::io::Result<()> {
+		while !self.outgoing.is_empty() {
+			match self.stream.write(&self.outgoing) {
+				Ok(0) => return Err(ErrorKind::WriteZero.into()),
+				Ok(written) => drop(self.outgoing.drain(.. written)),
+				Err(why) if why.kind() == ErrorKind::WouldBlock => break,
+				Err(why) => return Err(why),
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads everything that arrived and returns all complete messages. If the peer closed the connection, the
+	/// messages that it sent before are still returned, and [`Self::closed`] is set.
+	fn receive(&mut self) -> anyhow::Result<Vec<NetMessage>> {
+		let mut buffer = [0; 16 * 1024];
+		while !self.closed {
+			match self.stream.read(&mut buffer) {
+				Ok(0) => self.closed = true,
+				Ok(read) => self.incoming.extend_from_slice(&buffer[.. read]),
+				Err(why) if why.kind() == ErrorKind::WouldBlock => break,
+				Err(why) => return Err(why.into()),
+			}
+		}
+
+		let mut messages = Vec::new();
+		loop {
+			let mut input = self.incoming.as_slice();
+			let length = match u64::decode(&mut input) {
+				Ok(length) => length,
+				Err(WireError::UnexpectedEnd) => break,
+				Err(why) => return Err(why.into()),
+			};
+			anyhow::ensure!(length <= MAX_MESSAGE_LENGTH, "a message of {length} bytes is too large");
+			let Some(message) = input.get(.. length as usize) else {
+				break;
+			};
+			messages.push(from_bytes(message)?);
+			let consumed = self.incoming.len() - input.len() + length as usize;
+			self.incoming.drain(.. consumed);
+		}
+		Ok(messages)
+	}
+}
+
+/// The multiplayer session that this game takes part in, if any.
+#[derive(Resource, Default)]
+struct Session {
+	/// Accepts new players while hosting.
+	listener:  Option<TcpListener>,
+	peers:     Vec<Peer>,
+	next_id:   u64,
+	/// Where the snapshot for waiting players is written to, while it is being taken.
+	snapshot:  Option<Arc<Mutex<Option<Vec<u8>>>>>,
+	/// Actions received from peers that are performed next.
+	received:  Vec<(u64, NetMessage)>,
+	/// Actions that were performed for a peer this frame, so that they aren't sent back to it.
+	performed: Vec<(u64, NetMessage)>,
+}
+
+impl Session {
+	fn is_host(&self) -> bool {
+		self.listener.is_some()
+	}
+
+	fn add_peer(&mut self, address: SocketAddr, stream: TcpStream) -> std::io::Result<()> {
+		self.next_id += 1;
+		self.peers.push(Peer::new(self.next_id, address, stream)?);
+		Ok(())
+	}
+}
+
+fn start_session(cli_arguments: Res<CLIResource>, mut session: ResMut<Session>) {
+	if let Some(port) = cli_arguments.host {
+		match TcpListener::bind(("0.0.0.0", port)).and_then(|listener| {
+			listener.set_nonblocking(true)?;
+			Ok(listener)
+		}) {
+			Ok(listener) => {
+				info!("Hosting a multiplayer session on port {port}");
+				session.listener = Some(listener);
+			},
+			Err(why) => error!("Couldn’t host on port {port}: {why}"),
+		}
+	} else if let Some(host) = &cli_arguments.join {
+		let result = host
+			.to_socket_addrs()
+			.and_then(|mut addresses| addresses.next().ok_or_else(|| ErrorKind::AddrNotAvailable.into()))
+			.and_then(|address| {
+				let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+				session.add_peer(address, stream)
+			});
+		match result {
+			Ok(()) => info!("Joining the multiplayer session of {host}"),
+			Err(why) => error!("Couldn’t join {host}: {why}"),
+		}
+	}
+}
+
+fn accept_peers(mut session: ResMut<Session>, mut notifications: EventWriter<NotificationEvent>) {
+	loop {
+		let Some(accepted) = session.listener.as_ref().map(TcpListener::accept) else {
+			return;
+		};
+		match accepted {
+			Ok((stream, address)) => match session.add_peer(address, stream) {
+				Ok(()) => notifications.send(NotificationEvent::info(format!("A player joins from {address}."))),
+				Err(why) => notifications.send(NotificationEvent::warning(format!("{address} couldn’t join: {why}"))),
+			},
+			Err(why) if why.kind() == ErrorKind::WouldBlock => return,
+			Err(why) => {
+				error!("Couldn’t accept a player: {why}");
+				return;
+			},
+		};
+	}
+}
+
+fn receive_messages(
+	mut session: ResMut<Session>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut commands: Commands,
+) {
+	let is_host = session.is_host();
+	let Session { peers, received, .. } = &mut *session;
+	peers.retain_mut(|peer| {
+		let result = peer.receive().and_then(|messages| {
+			for message in messages {
+				match (message, peer.state) {
+					(NetMessage::Hello(game_version), PeerState::Connected) => {
+						anyhow::ensure!(game_version == VERSION, "it runs version {game_version}, not {VERSION}");
+						peer.state = if is_host { PeerState::AwaitingSnapshot } else { PeerState::Synchronized };
+					},
+					(NetMessage::Snapshot(snapshot), _) if !is_host => {
+						commands.insert_resource(LoadSave::from_snapshot(peer.address.to_string(), &snapshot)?);
+						next_state.set(GameState::Loading);
+						notifications.send(NotificationEvent::info(format!("Joined the game of {}.", peer.address)));
+					},
+					(message @ (NetMessage::Build(_) | NetMessage::Demolish(_)), PeerState::Synchronized) =>
+						received.push((peer.id, message)),
+					(message @ NetMessage::Rejected { .. }, PeerState::Synchronized) if !is_host =>
+						received.push((peer.id, message)),
+					(message, state) => anyhow::bail!("unexpected {message:?} while {state:?}"),
+				}
+			}
+			anyhow::ensure!(!peer.closed, "the connection was closed");
+			Ok(())
+		});
+		match result {
+			Ok(()) => true,
+			Err(why) => {
+				let message = if is_host {
+					format!("The player from {} left: {why}", peer.address)
+				} else {
+					format!("Lost the connection to {}: {why}", peer.address)
+				};
+				notifications.send(NotificationEvent::warning(message));
+				false
+			},
+		}
+	});
+}
+
+/// Takes a snapshot of the world for players that wait for one.
+fn request_snapshot(mut session: ResMut<Session>, mut commands: Commands) {
+	if session.snapshot.is_some() || !session.peers.iter().any(|peer| peer.state == PeerState::AwaitingSnapshot) {
+		return;
+	}
+	let snapshot = StoreSnapshot::default();
+	session.snapshot = Some(snapshot.0.clone());
+	commands.insert_resource(snapshot);
+}
+
+/// Sends the snapshot once it was taken. Snapshots are taken before any actions of a frame are performed, so all
+/// actions of this and later frames are sent to the waiting players after the snapshot. If the snapshot couldn't be
+/// taken, the waiting players are disconnected, since they can't join without the world.
+fn send_snapshot(
+	mut session: ResMut<Session>,
+	request: Option<Res<StoreSnapshot>>,
+	mut notifications: EventWriter<NotificationEvent>,
+) {
+	let Some(slot) = &session.snapshot else {
+		return;
+	};
+	let Some(snapshot) = slot.lock().take() else {
+		// The save pipeline fills the slot before it removes the request.
+		if request.is_none() {
+			session.snapshot = None;
+			session.peers.retain(|peer| {
+				let waiting = peer.state == PeerState::AwaitingSnapshot;
+				if waiting {
+					notifications.send(NotificationEvent::error(format!(
+						"The player from {} couldn’t join, since the campground couldn’t be sent.",
+						peer.address
+					)));
+				}
+				!waiting
+			});
+		}
+		return;
+	};
+	session.snapshot = None;
+	let message = NetMessage::Snapshot(snapshot);
+	for peer in session.peers.iter_mut().filter(|peer| peer.state == PeerState::AwaitingSnapshot) {
+		peer.send(&message);
+		peer.state = PeerState::Synchronized;
+	}
+}
+
+fn perform_remote_actions(
+	mut session: ResMut<Session>,
+	mut build_requests: BuildRequests,
+	mut demolitions: EventWriter<PerformDemolish>,
+	mut reverts: EventWriter<RevertBuild>,
+	mut notifications: EventWriter<NotificationEvent>,
+) {
+	let is_host = session.is_host();
+	let Session { peers, received, performed, .. } = &mut *session;
+	for (peer, message) in received.drain(..) {
+		match message {
+			NetMessage::Build(build @ BuildCommand { buildable, start, end }) => {
+				// Other players may have built in the same place in the meantime.
+				if let Err(why) = build_requests.check(buildable, start, end) {
+					if is_host {
+						if let Some(peer) = peers.iter_mut().find(|other| other.id == peer) {
+							peer.send(&NetMessage::Rejected { build, reason: why.to_string() });
+						}
+					} else {
+						// The host already performed the build, so our world differs from the host's.
+						warn!("Couldn’t perform {buildable} from {start} to {end} for another player: {why}");
+					}
+					continue;
+				}
+				build_requests.send(buildable, start, end);
+			},
+			NetMessage::Demolish(DemolishCommand { start, end }) => {
+				demolitions.send(PerformDemolish(TileRect::new(start, end)));
+			},
+			NetMessage::Rejected { build: BuildCommand { buildable, start, end }, reason } => {
+				reverts.send(RevertBuild(BuildCompleted { buildable, start_position: start, end_position: end }));
+				notifications
+					.send(NotificationEvent::warning(format!("The host couldn’t build {buildable}: {reason}")));
+				continue;
+			},
+			_ => continue,
+		}
+		performed.push((peer, message));
+	}
+}
+
+/// Sends all actions that were performed this frame to the synchronized peers, except to the peer they came from.
+fn replicate_actions(
+	mut session: ResMut<Session>,
+	mut builds: EventReader<BuildCompleted>,
+	mut demolitions: EventReader<PerformDemolish>,
+) {
+	let builds = builds.read().map(|build| {
+		NetMessage::Build(BuildCommand {
+			buildable: build.buildable,
+			start:     build.start_position,
+			end:       build.end_position,
+		})
+	});
+	let demolitions = demolitions.read().map(|demolition| {
+		NetMessage::Demolish(DemolishCommand { start: demolition.start_position, end: demolition.end_position })
+	});
+	let actions = builds.chain(demolitions).collect::<Vec<_>>();
+	let Session { peers, performed, .. } = &mut *session;
+	for action in actions {
+		let origin = performed
+			.iter()
+			.position(|(_, performed)| *performed == action)
+			.map(|index| performed.swap_remove(index).0);
+		for peer in peers.iter_mut().filter(|peer| peer.state == PeerState::Synchronized && Some(peer.id) != origin) {
+			peer.send(&action);
+		}
+	}
+	// Remote actions that failed never complete.
+	performed.clear();
+}
+
+fn send_messages(mut session: ResMut<Session>) {
+	session.peers.retain_mut(|peer| match peer.flush() {
+		Ok(()) => true,
+		Err(why) => {
+			warn!("Couldn’t send to {}: {why}", peer.address);
+			false
+		},
+	});
+}
@@ -0,0 +1,108 @@
+//! Crash-safe writing of save files.
+//!
+//! A save is never written into its save file directly, since a crash or power loss during saving would leave a
+//! truncated file behind and lose the campground. Instead, [`SaveFileWriter`] writes into a temporary file next to the
+//! save file. Once all data is written and the writer is committed, the temporary file is synced to disk and renamed to
+//! the save file, which replaces the previous save in one step. Writing either succeeds completely, or the previous
+//! save stays untouched.
+//!
+//! Before the previous save is replaced, it is copied to the first of a number of rotating backups:
+//! `<slot>.cmpsave.1.bak` is the most recent backup, and older backups move up by one number until they drop out. The
+//! number of backups is controlled by the `save_backups` setting; with 0, no backups are kept.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Writes a save file through a temporary file, which only replaces the save file once it is committed. Writers that
+/// are dropped without committing leave the previous save untouched.
+pub struct SaveFileWriter {
+	file:           File,
+	/// The save file that is written.
+	path:           PathBuf,
+	/// The temporary file next to the save file that receives the data first.
+	temporary_path: PathBuf,
+	/// How many backups of the previous save are kept.
+	backups:        usize,
+	/// Whether any write failed, in which case the previous save is kept.
+	failed:         bool,
+	/// Whether the temporary file replaced the save file.
+	committed:      bool,
+}
+
+impl SaveFileWriter {
+	/// Starts writing the save file at the path.
+	pub fn create(path: PathBuf, backups: usize) -> std::io::Result<Self> {
+		let temporary_path = with_suffix(&path, ".tmp");
+		let file = File::options().write(true).truncate(true).create(true).open(&temporary_path)?;
+		Ok(Self { file, path, temporary_path, backups, failed: false, committed: false })
+	}
+
+	/// Syncs the temporary file to disk, keeps a backup of the previous save and replaces it.
+	pub fn commit(mut self) -> std::io::Result<()> {
+		if self.failed {
+			return Err(std::io::Error::other("writing the save data failed"));
+		}
+		self.file.flush()?;
+		self.file.sync_all()?;
+		if self.path.exists() {
+			rotate_backups(&self.path, self.backups)?;
+		}
+		std::fs::rename(&self.temporary_path, &self.path)?;
+		// The rename itself is only durable once the directory that contains the save is synced. Directories can't be
+		// opened as files on Windows, where renames are durable without this.
+		#[cfg(unix)]
+		if let Some(directory) = self.path.parent() {
+			File::open(directory)?.sync_all()?;
+		}
+		self.committed = true;
+		Ok(())
+	}
+}
+
+impl Write for SaveFileWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.file.write(buf).inspect_err(|_| self.failed = true)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		self.file.flush().inspect_err(|_| self.failed = true)
+	}
+}
+
+impl Drop for SaveFileWriter {
+	fn drop(&mut self) {
+		if !self.committed {
+			let _ = std::fs::remove_file(&self.temporary_path);
+		}
+	}
+}
+
+/// Path of the numbered backup of a save file; backup 1 is the most recent one.
+pub fn backup_path(path: &Path, number: usize) -> PathBuf {
+	with_suffix(path, &format!(".{number}.bak"))
+}
+
+/// Moves all backups of the save file up by one number, dropping the oldest one, and copies the save file to the first
+/// backup.
+fn rotate_backups(path: &Path, backups: usize) -> std::io::Result<()> {
+	if backups == 0 {
+		return Ok(());
+	}
+	for number in (1 .. backups).rev() {
+		let backup = backup_path(path, number);
+		if backup.exists() {
+			std::fs::rename(&backup, backup_path(path, number + 1))?;
+		}
+	}
+	// The save file is copied instead of moved, so that there is a save file at all times.
+	std::fs::copy(path, backup_path(path, 1))?;
+	Ok(())
+}
+
+/// Appends the suffix to the file name, keeping the original extension.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	path.into()
+}
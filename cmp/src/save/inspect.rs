@@ -0,0 +1,194 @@
+//! Inspection of save files without loading them, started with the `--inspect-save` command-line argument.
+//!
+//! An inspection reads the metadata block of a save file and decompresses its world data, then prints the metadata and
+//! statistics about the scene: how many entities it contains, how often every component occurs, and which resources
+//! it stores. Strings in the scene that look like asset paths are checked against the asset folder, and missing
+//! assets are listed. With `--extract-scene`, the raw scene RON is written to a file as well, even if the scene can't
+//! be deserialized; this is the starting point for debugging corrupted saves by hand.
+//!
+//! Inspections run without a window, and the game exits right after. The exit code is nonzero if the save is
+//! unreadable, its scene can't be deserialized, or assets are missing.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::scene::ron;
+use bevy::scene::serde::SceneDeserializer;
+use serde::de::DeserializeSeed;
+
+use super::metadata::SaveMetadata;
+use super::{migration, BUFFER_SIZE};
+use crate::config::CLIResource;
+
+/// File extensions of assets that saves may refer to.
+const ASSET_EXTENSIONS: [&str; 6] = ["png", "qoi", "ogg", "ron", "ttf", "wgsl"];
+
+/// A save file as it is stored on disk, with decompressed but not yet migrated world data.
+#[derive(Clone, Debug)]
+pub struct SaveFile {
+	pub metadata:   SaveMetadata,
+	/// The serialized scene in the format version of the save.
+	pub world_data: String,
+}
+
+impl SaveFile {
+	/// Reads the metadata and decompresses the world data of the save file at the path.
+	pub fn read(path: &Path) -> anyhow::Result<Self> {
+		let mut file = std::fs::File::options().read(true).open(path)?;
+		let metadata = SaveMetadata::read_from(&mut file)?;
+		let mut world_data = String::new();
+		brotli::Decompressor::new(file, BUFFER_SIZE).read_to_string(&mut world_data)?;
+		Ok(Self { metadata, world_data })
+	}
+
+	/// Migrates the world data to the current format, and collects statistics about its scene.
+	pub fn statistics(&self, type_registry: &TypeRegistry) -> anyhow::Result<SceneStatistics> {
+		let world_data = migration::migrate(self.world_data.clone(), self.metadata.format_version)?;
+		let mut deserializer = ron::Deserializer::from_str(&world_data)?;
+		let scene = SceneDeserializer { type_registry }.deserialize(&mut deserializer)?;
+
+		let mut statistics = SceneStatistics { entities: scene.entities.len(), ..default() };
+		for component in scene.entities.iter().flat_map(|entity| &entity.components) {
+			*statistics.components.entry(component.reflect_type_path().to_string()).or_default() += 1;
+		}
+		statistics.resources =
+			scene.resources.iter().map(|resource| resource.reflect_type_path().to_string()).collect();
+		statistics.resources.sort_unstable();
+		Ok(statistics)
+	}
+
+	/// All strings in the world data that look like asset paths.
+	pub fn asset_references(&self) -> BTreeSet<String> {
+		string_literals(&self.world_data)
+			.into_iter()
+			.filter(|string| {
+				Path::new(string)
+					.extension()
+					.is_some_and(|extension| ASSET_EXTENSIONS.iter().any(|known| extension.eq_ignore_ascii_case(known)))
+			})
+			.collect()
+	}
+}
+
+/// What the scene of a save contains.
+#[derive(Clone, Debug, Default)]
+pub struct SceneStatistics {
+	pub entities:   usize,
+	/// Number of entities with each component, by type path.
+	pub components: BTreeMap<String, usize>,
+	/// Type paths of the saved resources.
+	pub resources:  Vec<String>,
+}
+
+/// Collects the contents of all string literals in RON data. Escape sequences are kept as they are, which is good
+/// enough for paths.
+fn string_literals(ron: &str) -> Vec<String> {
+	let mut literals = Vec::new();
+	let mut chars = ron.chars();
+	while let Some(char) = chars.next() {
+		match char {
+			'"' => {
+				let mut literal = String::new();
+				while let Some(char) = chars.next() {
+					match char {
+						'"' => break,
+						'\\' => literal.extend([char].into_iter().chain(chars.next())),
+						_ => literal.push(char),
+					}
+				}
+				literals.push(literal);
+			},
+			// Character literals may contain quotes, which must not start a string.
+			'\'' => {
+				if chars.next() == Some('\\') {
+					chars.next();
+				}
+				chars.next();
+			},
+			_ => {},
+		}
+	}
+	literals
+}
+
+/// Inspects the save file given on the command line, prints the results, then exits.
+pub(super) fn inspect_save(
+	cli_arguments: Res<CLIResource>,
+	type_registry: Res<AppTypeRegistry>,
+	mut exit: EventWriter<AppExit>,
+) {
+	let Some(path) = &cli_arguments.inspect_save else {
+		return;
+	};
+	let is_healthy = print_inspection(path, cli_arguments.extract_scene.as_deref(), &type_registry.read());
+	exit.send(if is_healthy { AppExit::Success } else { AppExit::error() });
+}
+
+/// Prints the inspection of the save file, and returns whether the save is healthy.
+fn print_inspection(path: &Path, extract_to: Option<&Path>, type_registry: &TypeRegistry) -> bool {
+	let save = match SaveFile::read(path) {
+		Ok(save) => save,
+		Err(why) => {
+			error!("Couldn’t read save file {path:?}: {why}");
+			return false;
+		},
+	};
+	let mut is_healthy = true;
+
+	let metadata = &save.metadata;
+	println!("save file:           {path:?}");
+	println!("campground:          {}", metadata.name.as_deref().unwrap_or("(unknown)"));
+	println!(
+		"game version:        {}",
+		metadata.schema.as_ref().map_or("(unknown)", |schema| schema.game_version.as_str())
+	);
+	println!("format version:      {}", metadata.format_version);
+	match metadata.saved_at.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+		Some(saved_at) => println!("saved at:            {} s since the Unix epoch", saved_at.as_secs()),
+		None => println!("saved at:            (unknown)"),
+	}
+	println!("thumbnail:           {} bytes", metadata.thumbnail.as_ref().map_or(0, Vec::len));
+	println!("world data:          {} bytes", save.world_data.len());
+
+	if let Some(extract_to) = extract_to {
+		match std::fs::write(extract_to, &save.world_data) {
+			Ok(()) => println!("scene extracted to:  {extract_to:?}"),
+			Err(why) => {
+				error!("Couldn’t extract scene to {extract_to:?}: {why}");
+				is_healthy = false;
+			},
+		}
+	}
+
+	match save.statistics(type_registry) {
+		Ok(statistics) => {
+			println!("\n{} entities with components:", statistics.entities);
+			let width = statistics.components.keys().map(String::len).max().unwrap_or_default();
+			for (component, count) in &statistics.components {
+				println!("  {component:<width$}  {count:>6}");
+			}
+			println!("\n{} resources:", statistics.resources.len());
+			for resource in &statistics.resources {
+				println!("  {resource}");
+			}
+		},
+		Err(why) => {
+			error!("Couldn’t deserialize the scene of {path:?}: {why}");
+			is_healthy = false;
+		},
+	}
+
+	let asset_root = FileAssetReader::new("assets").root_path().clone();
+	let references = save.asset_references();
+	let missing = references.iter().filter(|asset| !asset_root.join(asset).is_file()).collect::<Vec<_>>();
+	println!("\n{} referenced assets, {} missing from {asset_root:?}", references.len(), missing.len());
+	for asset in &missing {
+		println!("  missing: {asset}");
+	}
+	is_healthy && missing.is_empty()
+}
@@ -0,0 +1,176 @@
+//! The uncompressed metadata block at the start of every save file.
+//!
+//! The metadata block allows the game to display information about a save, such as its thumbnail, without
+//! decompressing and deserializing the entire world. Its layout is:
+//!
+//! | Field            | Size              | Contents                                         |
+//! |------------------|-------------------|--------------------------------------------------|
+//! | magic            | 8 bytes           | [`SAVE_MAGIC`]                                   |
+//! | version          | u16 little-endian | [`METADATA_VERSION`]                             |
+//! | thumbnail length | u32 little-endian | Length of the thumbnail data; 0 if there is none |
+//! | thumbnail        | variable          | QOI-encoded thumbnail image                      |
+//! | schema length    | u32 little-endian | Length of the schema data; 0 if there is none    |
+//! | schema           | variable          | JSON-encoded [`SaveSchema`]                      |
+//! | name length      | u32 little-endian | Length of the name; 0 if there is none           |
+//! | name             | variable          | UTF-8 encoded campground name                    |
+//! | format version   | u16 little-endian | [`SAVE_FORMAT_VERSION`] of the world data        |
+//! | saved at         | u64 little-endian | Seconds since the Unix epoch; 0 if unknown       |
+//!
+//! The game version that wrote the save is part of the schema. The brotli-compressed world data directly follows the
+//! metadata block. Version 1 of the metadata block ends after the thumbnail, version 2 ends after the schema, and
+//! version 3 ends after the name; saves from before version 4 have world data format version 0.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::renderer::RenderDevice;
+
+use super::migration::SAVE_FORMAT_VERSION;
+use super::schema::SaveSchema;
+
+/// Magic bytes identifying a CMP save file.
+pub const SAVE_MAGIC: [u8; 8] = *b"CMPSAVE\0";
+/// Current version of the metadata block layout.
+pub const METADATA_VERSION: u16 = 4;
+
+/// Thumbnails are downscaled from the canvas by this factor.
+const THUMBNAIL_DOWNSCALE: u32 = 2;
+
+/// Metadata stored in front of the compressed world data in a save file.
+#[derive(Clone, Debug, Default)]
+pub struct SaveMetadata {
+	/// QOI-encoded thumbnail of the world at the time of saving.
+	pub thumbnail:      Option<Vec<u8>>,
+	/// Schema of the saved types, used to detect incompatible saves before loading them.
+	pub schema:         Option<SaveSchema>,
+	/// Name of the saved campground.
+	pub name:           Option<String>,
+	/// Version of the world data format; see [`SAVE_FORMAT_VERSION`].
+	pub format_version: u16,
+	/// When the save was written.
+	pub saved_at:       Option<SystemTime>,
+}
+
+impl SaveMetadata {
+	pub fn write_to(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+		let thumbnail = self.thumbnail.as_deref().unwrap_or_default();
+		writer.write_all(&SAVE_MAGIC)?;
+		writer.write_all(&METADATA_VERSION.to_le_bytes())?;
+		writer.write_all(&u32::try_from(thumbnail.len())?.to_le_bytes())?;
+		writer.write_all(thumbnail)?;
+		let schema = self.schema.as_ref().map(serde_json::to_vec).transpose()?.unwrap_or_default();
+		writer.write_all(&u32::try_from(schema.len())?.to_le_bytes())?;
+		writer.write_all(&schema)?;
+		let name = self.name.as_deref().unwrap_or_default().as_bytes();
+		writer.write_all(&u32::try_from(name.len())?.to_le_bytes())?;
+		writer.write_all(name)?;
+		writer.write_all(&self.format_version.to_le_bytes())?;
+		let saved_at = self.saved_at.and_then(|time| time.duration_since(UNIX_EPOCH).ok()).unwrap_or_default();
+		writer.write_all(&saved_at.as_secs().to_le_bytes())?;
+		Ok(())
+	}
+
+	/// Reads the metadata block, leaving the reader at the start of the world data. Saves from before the metadata
+	/// block existed are handled by rewinding the reader and returning empty metadata.
+	pub fn read_from(reader: &mut (impl Read + Seek)) -> anyhow::Result<Self> {
+		let mut magic = [0; SAVE_MAGIC.len()];
+		if reader.read_exact(&mut magic).is_err() || magic != SAVE_MAGIC {
+			reader.seek(SeekFrom::Start(0))?;
+			return Ok(Self::default());
+		}
+
+		let mut version = [0; size_of::<u16>()];
+		reader.read_exact(&mut version)?;
+		let version = u16::from_le_bytes(version);
+		if version > METADATA_VERSION {
+			anyhow::bail!("save metadata version {version} is newer than the supported version {METADATA_VERSION}");
+		}
+
+		let thumbnail = read_block(reader)?;
+		let schema = if version >= 2 {
+			read_block(reader)?.map(|schema| serde_json::from_slice(&schema)).transpose()?
+		} else {
+			None
+		};
+		let name = if version >= 3 { read_block(reader)?.map(String::from_utf8).transpose()? } else { None };
+		let (format_version, saved_at) = if version >= 4 {
+			let mut format_version = [0; size_of::<u16>()];
+			reader.read_exact(&mut format_version)?;
+			let mut saved_at = [0; size_of::<u64>()];
+			reader.read_exact(&mut saved_at)?;
+			let saved_at = u64::from_le_bytes(saved_at);
+			(u16::from_le_bytes(format_version), (saved_at > 0).then(|| UNIX_EPOCH + Duration::from_secs(saved_at)))
+		} else {
+			(0, None)
+		};
+
+		Ok(Self { thumbnail, schema, name, format_version, saved_at })
+	}
+
+	/// Creates the metadata for a save of the current game.
+	pub fn current(thumbnail: Option<Vec<u8>>, schema: SaveSchema, name: String) -> Self {
+		Self {
+			thumbnail,
+			schema: Some(schema),
+			name: Some(name),
+			format_version: SAVE_FORMAT_VERSION,
+			saved_at: Some(SystemTime::now()),
+		}
+	}
+
+	/// Decodes the thumbnail into an image that can be displayed in the UI.
+	pub fn thumbnail_image(&self) -> Option<Image> {
+		let thumbnail = self.thumbnail.as_ref()?;
+		bevy_qoi::decode_image(thumbnail).inspect_err(|why| warn!("Couldn’t decode save thumbnail: {why}")).ok()
+	}
+}
+
+/// Reads a length-prefixed block of data; [`None`] if the block is empty.
+fn read_block(reader: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+	let mut length = [0; size_of::<u32>()];
+	reader.read_exact(&mut length)?;
+	let length = u32::from_le_bytes(length) as usize;
+	if length == 0 {
+		return Ok(None);
+	}
+	let mut data = vec![0; length];
+	reader.read_exact(&mut data)?;
+	Ok(Some(data))
+}
+
+/// Creates a QOI-encoded thumbnail from the raw texture data of the canvas as read back from the GPU. Texture rows in
+/// the readback data are padded to the GPU's row alignment; see [`RenderDevice::align_copy_bytes_per_row`].
+pub fn encode_thumbnail(canvas_data: &[u8], width: u32, height: u32, format: TextureFormat) -> Option<Vec<u8>> {
+	const PIXEL_SIZE: usize = 4;
+	let is_bgra = match format {
+		TextureFormat::Bgra8UnormSrgb | TextureFormat::Bgra8Unorm => true,
+		TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => false,
+		_ => {
+			warn!("Can’t create a thumbnail from texture format {format:?}");
+			return None;
+		},
+	};
+	let padded_row_size = RenderDevice::align_copy_bytes_per_row(width as usize * PIXEL_SIZE);
+
+	let (thumbnail_width, thumbnail_height) = (width / THUMBNAIL_DOWNSCALE, height / THUMBNAIL_DOWNSCALE);
+	let mut thumbnail = Vec::with_capacity(thumbnail_width as usize * thumbnail_height as usize * PIXEL_SIZE);
+	// Nearest-neighbor downscaling keeps the thumbnail as crisp as the pixel art itself.
+	for y in 0 .. thumbnail_height {
+		for x in 0 .. thumbnail_width {
+			let offset =
+				(y * THUMBNAIL_DOWNSCALE) as usize * padded_row_size + (x * THUMBNAIL_DOWNSCALE) as usize * PIXEL_SIZE;
+			let pixel = canvas_data.get(offset .. offset + PIXEL_SIZE)?;
+			if is_bgra {
+				thumbnail.extend_from_slice(&[pixel[2], pixel[1], pixel[0], u8::MAX]);
+			} else {
+				thumbnail.extend_from_slice(&[pixel[0], pixel[1], pixel[2], u8::MAX]);
+			}
+		}
+	}
+
+	qoi::encode_to_vec(&thumbnail, thumbnail_width, thumbnail_height)
+		.inspect_err(|why| warn!("Couldn’t encode save thumbnail: {why}"))
+		.ok()
+}
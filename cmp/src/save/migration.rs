@@ -0,0 +1,88 @@
+//! Upgrades of the world data of older saves.
+//!
+//! The world data is a serialized scene, whose format changes whenever savable types change. Every save records the
+//! [`SAVE_FORMAT_VERSION`] that it was written with. When loading an older save, all migrations from its version up to
+//! the current version are applied in order to the serialized scene before it is deserialized. Saves without a chain
+//! of migrations to the current version, as well as saves from newer game versions, are rejected.
+//!
+//! When changing a savable type in an incompatible way, increase [`SAVE_FORMAT_VERSION`] and register a migration
+//! from the previous version in [`MIGRATIONS`].
+
+use bevy::prelude::*;
+
+use super::LoadError;
+
+/// Current version of the world data format.
+pub const SAVE_FORMAT_VERSION: u16 = 8;
+
+/// Upgrades serialized world data from one format version to the next.
+pub struct Migration {
+	/// The format version that this migration upgrades from; it produces version `from + 1`.
+	pub from:        u16,
+	/// What changed in the format.
+	pub description: &'static str,
+	/// Transforms the serialized scene.
+	pub migrate:     fn(String) -> anyhow::Result<String>,
+}
+
+/// All migrations, ordered by the version they upgrade from.
+const MIGRATIONS: &[Migration] = &[
+	Migration {
+		from:        0,
+		description: "Format versions are recorded; the world data itself is unchanged.",
+		migrate:     Ok,
+	},
+	Migration {
+		from:        1,
+		description: "The ground map is saved directly; tiles saved as entities are entered into it after loading.",
+		migrate:     Ok,
+	},
+	Migration {
+		from:        2,
+		description: "Pitches store their nightly price; it is filled in from the price of their type after loading.",
+		migrate:     Ok,
+	},
+	Migration {
+		from:        3,
+		description: "Pitches store their occupancy; it starts out free and follows the visitors after loading.",
+		migrate:     Ok,
+	},
+	Migration {
+		from:        4,
+		description: "The daily tally and day summaries record demolition refunds; older days refunded nothing.",
+		migrate:     Ok,
+	},
+	Migration { from: 5, description: "Fences store their style; older fences are wooden.", migrate: Ok },
+	Migration {
+		from:        6,
+		description: "The daily tally and day summaries count turned away visitors; older days turned nobody away.",
+		migrate:     Ok,
+	},
+	Migration {
+		from:        7,
+		description: "Bookings store the segment of their visitors; older bookings were made by families.",
+		migrate:     Ok,
+	},
+];
+
+/// Checks whether world data of the given format version can be loaded.
+pub fn check_format_version(version: u16) -> Result<(), LoadError> {
+	if version > SAVE_FORMAT_VERSION {
+		return Err(LoadError::NewerFormat { version });
+	}
+	match (version .. SAVE_FORMAT_VERSION).find(|from| !MIGRATIONS.iter().any(|migration| migration.from == *from)) {
+		Some(missing) => Err(LoadError::NoMigration { from: missing }),
+		None => Ok(()),
+	}
+}
+
+/// Upgrades world data of the given format version to the current format.
+pub fn migrate(mut world_data: String, version: u16) -> Result<String, LoadError> {
+	check_format_version(version)?;
+	for migration in MIGRATIONS.iter().filter(|migration| migration.from >= version) {
+		info!("Migrating save from format version {}: {}", migration.from, migration.description);
+		world_data = (migration.migrate)(world_data)
+			.map_err(|why| LoadError::MigrationFailed { from: migration.from, reason: why.to_string() })?;
+	}
+	Ok(world_data)
+}
@@ -0,0 +1,532 @@
+//! Saving and loading.
+
+use std::any::TypeId;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bevy::ecs::schedule::SystemConfigs;
+use bevy::prelude::*;
+use bevy::reflect::{TypeRegistration, TypeRegistry};
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::primitives::Aabb;
+use bevy::render::view::RenderLayers;
+use bevy::tasks::futures_lite::future::{block_on, poll_once};
+use bevy::tasks::{IoTaskPool, Task};
+use brotli::enc::BrotliEncoderParams;
+use directories::ProjectDirs;
+use file::SaveFileWriter;
+use inspect::SaveFile;
+use metadata::{encode_thumbnail, SaveMetadata};
+use migration::SAVE_FORMAT_VERSION;
+use moonshine_save::prelude::*;
+use moonshine_save::save::SavePipeline;
+use moonshine_save::{stream_from_resource, GetStream, Pipeline, StreamFromResource};
+use parking_lot::Mutex;
+use schema::SaveSchema;
+use thiserror::Error;
+
+use crate::config::{CLIResource, GameSettings, APP_NAME};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::Canvas;
+use crate::input::{Action, ActionInput};
+use crate::model::commerce::CommercialTally;
+use crate::model::demand::PitchPricing;
+use crate::model::modifiers::Modifiers;
+use crate::model::nav::NavComponent;
+use crate::model::rollup::{DailyHistory, DailyTally, DayClock};
+use crate::model::weather::Weather;
+use crate::model::{CampgroundName, GroundMap};
+use crate::ui::error::{DisplayableError, ErrorBox};
+use crate::ui::notifications::NotificationEvent;
+use crate::ui::world_info::WorldInfoProperties;
+
+pub mod file;
+pub mod inspect;
+pub mod metadata;
+pub mod migration;
+pub mod recent;
+pub mod schema;
+
+/// Requests loading a save. The save is read and migrated when the request is created, so that unreadable saves are
+/// reported right away instead of failing during loading.
+#[derive(Resource, Event, Debug, Clone)]
+pub struct LoadSave {
+	save_name:  String,
+	/// The decompressed and migrated world data.
+	world_data: Arc<[u8]>,
+	/// The schema of a save in the current format, which is checked before loading starts.
+	schema:     Option<SaveSchema>,
+}
+
+/// Requests writing the world into memory instead of a save file, for example to send it to another game. Once the
+/// world is written, its compressed data is placed into the shared slot. If the world couldn't be written, the slot
+/// stays empty after the request is removed.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct StoreSnapshot(pub Arc<Mutex<Option<Vec<u8>>>>);
+
+/// Since event requests are broken in moonshine_save, we instead use a resource request that is just a clone of the
+/// event.
+///
+/// The world is only serialized into memory on the main thread. Compressing it and writing the save file takes much
+/// longer for large worlds, so it happens on the IO task pool afterwards, and a [`SaveFinished`] event is sent once the
+/// save file is written. Only one save is written at a time; saves requested in the meantime are rejected.
+#[derive(Resource, Event, Debug, Clone)]
+pub struct StoreSave {
+	save_name:  String,
+	metadata:   SaveMetadata,
+	/// How many backups of the previous save in the slot are kept.
+	backups:    usize,
+	/// Receives the uncompressed world data once the world is serialized.
+	world_data: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+/// Sent once a save was written to its save file, or couldn't be written.
+#[derive(Event, Debug, Clone)]
+pub struct SaveFinished {
+	pub save_name: String,
+	/// Why the save couldn't be written, if it failed.
+	pub result:    Result<(), String>,
+}
+
+/// A save whose world data is being serialized, compressed and written to its save file.
+#[derive(Resource)]
+struct SaveInProgress {
+	request: StoreSave,
+	/// Compresses and writes the world data once it is serialized.
+	task:    Option<Task<anyhow::Result<()>>>,
+}
+
+const BUFFER_SIZE: usize = 10 * 1024;
+
+impl StoreSave {
+	pub fn new(save_name: String, metadata: SaveMetadata, backups: usize) -> Self {
+		Self { save_name, metadata, backups, world_data: default() }
+	}
+
+	/// Writes the metadata block and the compressed world data to the save file. This blocks until the save file is
+	/// written, so it should run on the IO task pool.
+	fn write_save_file(&self, world_data: &[u8]) -> anyhow::Result<()> {
+		let output_path = path_for_slot(&self.save_name).ok_or(anyhow::anyhow!("couldn’t get project directory"))?;
+		debug!("initiated save to {output_path:?}");
+		let mut file = SaveFileWriter::create(output_path, self.backups)?;
+		self.metadata.write_to(&mut file)?;
+		let mut compressor = brotli::CompressorWriter::with_params(file, BUFFER_SIZE, &Self::brotli_params());
+		compressor.write_all(world_data)?;
+		// Taking the file out of the compressor finishes the compressed stream.
+		compressor.into_inner().commit()?;
+		Ok(())
+	}
+
+	fn brotli_params() -> BrotliEncoderParams {
+		BrotliEncoderParams { quality: 9, lgwin: 20, ..Default::default() }
+	}
+}
+
+impl LoadSave {
+	/// Reads, decompresses and migrates the save in the slot.
+	pub fn new(save_name: String) -> Result<Self, LoadError> {
+		let unreadable = |why: &dyn std::fmt::Display| LoadError::Unreadable { reason: why.to_string() };
+		let input_path = path_for_slot(&save_name).ok_or_else(|| unreadable(&"couldn’t get project directory"))?;
+		debug!("initiated load from {input_path:?}");
+		let save = SaveFile::read(&input_path).map_err(|why| unreadable(&why))?;
+		let format_version = save.metadata.format_version;
+		let world_data = migration::migrate(save.world_data, format_version)?;
+		// The schema stored in older saves describes their world data before migration, which is expected to differ
+		// from the current schema. Migrations are responsible for those saves instead.
+		let schema = save.metadata.schema.filter(|_| format_version == SAVE_FORMAT_VERSION);
+		Ok(Self { save_name, world_data: world_data.into_bytes().into(), schema })
+	}
+
+	/// Loads the compressed world data of a [`StoreSnapshot`] from a game of the same version.
+	pub fn from_snapshot(save_name: String, snapshot: &[u8]) -> Result<Self, LoadError> {
+		let mut world_data = Vec::new();
+		brotli::Decompressor::new(snapshot, BUFFER_SIZE)
+			.read_to_end(&mut world_data)
+			.map_err(|why| LoadError::Unreadable { reason: why.to_string() })?;
+		Ok(Self { save_name, world_data: world_data.into(), schema: None })
+	}
+
+	/// The decompressed world data in the current format.
+	pub(crate) fn world_data(&self) -> &[u8] {
+		&self.world_data
+	}
+}
+
+/// Reads only the metadata of a save, without loading the world.
+pub fn read_metadata(save_name: &str) -> anyhow::Result<SaveMetadata> {
+	let path = path_for_slot(save_name).ok_or(anyhow::anyhow!("couldn’t get project directory"))?;
+	let mut file = std::fs::File::options().read(true).open(path)?;
+	SaveMetadata::read_from(&mut file)
+}
+
+/// A request whose world data is placed into a slot in memory, see [`IntoSlot`].
+trait WorldDataSlot: Resource {
+	/// Receives the world data once the world is serialized.
+	fn slot(&self) -> &Arc<Mutex<Option<Vec<u8>>>>;
+
+	/// Converts the serialized world into the data that is placed into the slot.
+	fn encode(&self, world_data: &[u8]) -> std::io::Result<Vec<u8>>;
+}
+
+impl WorldDataSlot for StoreSave {
+	fn slot(&self) -> &Arc<Mutex<Option<Vec<u8>>>> {
+		&self.world_data
+	}
+
+	fn encode(&self, world_data: &[u8]) -> std::io::Result<Vec<u8>> {
+		Ok(world_data.to_vec())
+	}
+}
+
+impl WorldDataSlot for StoreSnapshot {
+	fn slot(&self) -> &Arc<Mutex<Option<Vec<u8>>>> {
+		&self.0
+	}
+
+	fn encode(&self, world_data: &[u8]) -> std::io::Result<Vec<u8>> {
+		let mut compressor =
+			brotli::CompressorWriter::with_params(Vec::new(), BUFFER_SIZE, &StoreSave::brotli_params());
+		compressor.write_all(world_data)?;
+		Ok(compressor.into_inner())
+	}
+}
+
+/// Save pipeline that places the world data into the slot of a [`StoreSave`] or [`StoreSnapshot`] request, and removes
+/// the request afterwards. The slot is only filled once the world was serialized, so that a failed save leaves it empty
+/// instead of replacing the save file or snapshot with an empty world.
+struct IntoSlot<R: Resource>(StreamFromResource<R>);
+
+impl<R: WorldDataSlot> Pipeline for IntoSlot<R> {
+	fn finish(&self, pipeline: impl System<In = (), Out = ()>) -> SystemConfigs {
+		self.0.finish(pipeline)
+	}
+}
+
+impl<R: WorldDataSlot> SavePipeline for IntoSlot<R> {
+	fn save(&self, system: impl System<In = (), Out = Saved>) -> impl System<In = (), Out = Result<Saved, SaveError>> {
+		IntoSystem::into_system(system.pipe(write_into_slot::<R>))
+	}
+}
+
+fn write_into_slot<R: WorldDataSlot>(
+	In(saved): In<Saved>,
+	request: Res<R>,
+	type_registry: Res<AppTypeRegistry>,
+) -> Result<Saved, SaveError> {
+	let world_data = saved.scene.serialize(&type_registry.read())?;
+	if world_data.is_empty() {
+		return Err(SaveError::Io(std::io::Error::other("the world data is empty")));
+	}
+	*request.slot().lock() = Some(request.encode(world_data.as_bytes())?);
+	Ok(saved)
+}
+
+impl GetStream for LoadSave {
+	type Stream = Cursor<Vec<u8>>;
+
+	fn stream(&self) -> Self::Stream {
+		Cursor::new(self.world_data.to_vec())
+	}
+}
+
+/// Any reason that a save could not be loaded; eventually propagated to the end-user.
+#[derive(Error, Debug)]
+pub enum LoadError {
+	#[error("This save was created by an incompatible game version ({}):\n{}", .game_version, .problems.join("\n"))]
+	IncompatibleSchema { game_version: String, problems: Vec<String> },
+	#[error("This save was created by a newer version of the game (save format version {}).", .version)]
+	NewerFormat { version: u16 },
+	#[error("Saves with format version {} can no longer be upgraded to the current format.", .from)]
+	NoMigration { from: u16 },
+	#[error("Upgrading this save from format version {} failed: {}", .from, .reason)]
+	MigrationFailed { from: u16, reason: String },
+	#[error("This save can’t be read: {}", .reason)]
+	Unreadable { reason: String },
+}
+
+impl DisplayableError for LoadError {
+	fn name(&self) -> &str {
+		"Load error"
+	}
+}
+
+/// Components that are never stored in saves, which both the save pipeline and the save schema leave out.
+fn excluded_components() -> [TypeId; 10] {
+	[
+		TypeId::of::<Sprite>(),
+		TypeId::of::<Transform>(),
+		TypeId::of::<GlobalTransform>(),
+		TypeId::of::<Visibility>(),
+		TypeId::of::<InheritedVisibility>(),
+		TypeId::of::<ViewVisibility>(),
+		TypeId::of::<Aabb>(),
+		TypeId::of::<NavComponent>(),
+		TypeId::of::<WorldInfoProperties>(),
+		TypeId::of::<RenderLayers>(),
+	]
+}
+
+/// Whether the registered type is one of the components that saves are checked for. Saved entities also carry some
+/// engine components, such as their hierarchy, but those change with engine updates instead of game updates, and
+/// saves should not be rejected for engine components that never occur on saved entities, such as those of the UI.
+/// Therefore, only the game's own components are part of the save schema.
+fn is_saved_component(registration: &TypeRegistration) -> bool {
+	registration.type_info().type_path().starts_with(concat!(env!("CARGO_CRATE_NAME"), "::"))
+		&& !excluded_components().contains(&registration.type_id())
+		&& registration.data::<ReflectComponent>().is_some()
+}
+
+/// Resources that are stored in saves, which both the save pipeline and the save schema include.
+fn included_resources() -> [TypeId; 9] {
+	[
+		TypeId::of::<GroundMap>(),
+		TypeId::of::<Modifiers>(),
+		TypeId::of::<CampgroundName>(),
+		TypeId::of::<DayClock>(),
+		TypeId::of::<DailyTally>(),
+		TypeId::of::<DailyHistory>(),
+		TypeId::of::<CommercialTally>(),
+		TypeId::of::<Weather>(),
+		TypeId::of::<PitchPricing>(),
+	]
+}
+
+/// Return the directory that contains all saves.
+pub(crate) fn save_directory() -> Option<PathBuf> {
+	let project = ProjectDirs::from("rs", "", APP_NAME)?;
+	let data_path = project.data_dir();
+	std::fs::create_dir_all(data_path).ok()?;
+	Some(data_path.to_owned())
+}
+
+/// Return the file system path for the numbered save slot.
+fn path_for_slot(save_name: &str) -> Option<PathBuf> {
+	Some(save_directory()?.join(format!("{}.cmpsave", save_name)))
+}
+
+pub struct Saving;
+
+impl Plugin for Saving {
+	fn build(&self, app: &mut App) {
+		app.add_plugins((SavePlugin, LoadPlugin))
+			.add_event::<StoreSave>()
+			.add_event::<LoadSave>()
+			.add_event::<SaveFinished>();
+
+		// TODO: Disable this line when debugging loading.
+		// app.add_systems(Startup, crate::model::spawn_test_tiles);
+		// TODO: Enable this line when debugging loading.
+
+		app.add_systems(FixedPreUpdate, (save_pipeline::<StoreSave>(), load(stream_from_resource::<LoadSave>())))
+			// Fixed time stands still in the pause menu, so saves requested from there are written right away.
+			.add_systems(PreUpdate, save_pipeline::<StoreSave>().after(start_save).in_set(GameState::Paused))
+			// Snapshots are needed right away, even while the simulation is paused.
+			.add_systems(PreUpdate, save_pipeline::<StoreSnapshot>());
+
+		app.add_systems(First, (cause_test_save, cause_test_load).in_set(GameSet::Input).in_set(GameState::InGame))
+			// Loading happens in the fixed schedules, so incompatible saves must be rejected before those run.
+			.add_systems(PreUpdate, check_load.run_if(resource_added::<LoadSave>))
+			// Saves must be accepted or rejected before they are serialized.
+			.add_systems(PreUpdate, start_save.run_if(resource_added::<StoreSave>))
+			.add_systems(
+				Update,
+				(
+					write_save_in_background.run_if(resource_exists::<SaveInProgress>),
+					(notify_saved, recent::record_recent_saves),
+				)
+					.chain(),
+			)
+			.add_systems(PostStartup, (dump_save_schema, inspect::inspect_save));
+	}
+}
+
+/// Saves the world once a [`StoreSave`] or [`StoreSnapshot`] is requested.
+fn save_pipeline<R: WorldDataSlot>() -> SystemConfigs {
+	let pipeline = excluded_components()
+		.into_iter()
+		.fold(save_default(), |pipeline, component| pipeline.exclude_component_by_id(component));
+	included_resources()
+		.into_iter()
+		.fold(pipeline, |pipeline, resource| pipeline.include_resource_by_id(resource))
+		.into(IntoSlot(stream_from_resource::<R>()))
+}
+
+/// Checks that the save that is about to be loaded is compatible with the running game. Saves from newer game versions
+/// were already rejected, and older saves migrated to the current format, when the [`LoadSave`] was created. The schema
+/// of saves in the current format is compared against the current schema, and saves with incompatible schemas are
+/// rejected. Saves without a schema are loaded without checking the schema.
+fn check_load(
+	load: Res<LoadSave>,
+	type_registry: Res<AppTypeRegistry>,
+	mut errors: EventWriter<ErrorBox>,
+	mut commands: Commands,
+) {
+	let Some(saved_schema) = &load.schema else {
+		return;
+	};
+	if let Err(why) = check_save_schema(saved_schema, &type_registry.read()) {
+		commands.remove_resource::<LoadSave>();
+		errors.send(why.into());
+	}
+}
+
+/// Compares the schema stored in the save against the current schema.
+fn check_save_schema(saved_schema: &SaveSchema, type_registry: &TypeRegistry) -> Result<(), LoadError> {
+	let problems = saved_schema.incompatibilities(&SaveSchema::from_registry(type_registry));
+	if problems.is_empty() {
+		Ok(())
+	} else {
+		Err(LoadError::IncompatibleSchema { game_version: saved_schema.game_version.clone(), problems })
+	}
+}
+
+/// Writes the current save schema as JSON to the file given on the command line, then exits.
+fn dump_save_schema(
+	cli_arguments: Res<CLIResource>,
+	type_registry: Res<AppTypeRegistry>,
+	mut exit: EventWriter<AppExit>,
+) {
+	let Some(path) = &cli_arguments.dump_save_schema else {
+		return;
+	};
+	let schema = SaveSchema::from_registry(&type_registry.read());
+	let result = serde_json::to_string_pretty(&schema)
+		.map_err(anyhow::Error::from)
+		.and_then(|json| std::fs::write(path, json).map_err(anyhow::Error::from));
+	match result {
+		Ok(()) => {
+			info!("Wrote schema of {} savable types to {path:?}", schema.types.len());
+			exit.send(AppExit::Success);
+		},
+		Err(why) => {
+			error!("Couldn’t write save schema to {path:?}: {why}");
+			exit.send(AppExit::error());
+		},
+	}
+}
+
+fn cause_test_save(
+	actions: ActionInput,
+	canvas: Query<&Canvas>,
+	type_registry: Res<AppTypeRegistry>,
+	name: Res<CampgroundName>,
+	settings: Res<GameSettings>,
+	mut commands: Commands,
+) {
+	if actions.just_pressed(Action::QuickSave) {
+		let schema = SaveSchema::from_registry(&type_registry.read());
+		request_save("Test".to_string(), schema, name.0.clone(), settings.save_backup_count(), &canvas, &mut commands);
+	}
+}
+
+/// Starts saving to the given slot. Since the thumbnail has to be read back from the GPU first, which takes a couple of
+/// frames, the save itself is only performed once the readback completes.
+pub fn request_save(
+	save_name: String,
+	schema: SaveSchema,
+	campground_name: String,
+	backups: usize,
+	canvas: &Query<&Canvas>,
+	commands: &mut Commands,
+) {
+	let Ok(canvas) = canvas.get_single() else {
+		// Without a canvas (e.g. when running headless), there is nothing to take a thumbnail of.
+		commands.insert_resource(StoreSave::new(
+			save_name,
+			SaveMetadata::current(None, schema, campground_name),
+			backups,
+		));
+		return;
+	};
+
+	let canvas_image = canvas.image.clone();
+	commands.spawn(Readback::texture(canvas_image.clone())).observe(
+		move |trigger: Trigger<ReadbackComplete>, images: Res<Assets<Image>>, mut commands: Commands| {
+			let thumbnail = images.get(&canvas_image).and_then(|image| {
+				encode_thumbnail(&trigger.event().0, image.width(), image.height(), image.texture_descriptor.format)
+			});
+			commands.insert_resource(StoreSave::new(
+				save_name.clone(),
+				SaveMetadata::current(thumbnail, schema.clone(), campground_name.clone()),
+				backups,
+			));
+			// Readbacks are repeated every frame until the component is removed.
+			commands.entity(trigger.entity()).despawn();
+		},
+	);
+}
+
+/// Accepts a newly requested save, unless another save is still being written.
+fn start_save(
+	store: Res<StoreSave>,
+	in_progress: Option<Res<SaveInProgress>>,
+	mut notifications: EventWriter<NotificationEvent>,
+	mut commands: Commands,
+) {
+	if let Some(in_progress) = in_progress {
+		warn!("Not saving to {} while still saving to {}", store.save_name, in_progress.request.save_name);
+		notifications.send(NotificationEvent::warning("The campground is still being saved; try again in a moment."));
+		commands.remove_resource::<StoreSave>();
+		return;
+	}
+	notifications.send(NotificationEvent::info("Saving…"));
+	commands.insert_resource(SaveInProgress { request: store.clone(), task: None });
+}
+
+/// Hands the serialized world data to the IO task pool, and sends a [`SaveFinished`] event once the save file is
+/// written.
+fn write_save_in_background(
+	mut in_progress: ResMut<SaveInProgress>,
+	store: Option<Res<StoreSave>>,
+	mut finished: EventWriter<SaveFinished>,
+	mut commands: Commands,
+) {
+	let save_name = in_progress.request.save_name.clone();
+	let result = match in_progress.task.as_mut() {
+		Some(task) => match block_on(poll_once(task)) {
+			Some(result) => result,
+			None => return,
+		},
+		None => {
+			let world_data = in_progress.request.world_data.lock().take();
+			match world_data {
+				Some(world_data) => {
+					let request = in_progress.request.clone();
+					in_progress.task =
+						Some(IoTaskPool::get().spawn(async move { request.write_save_file(&world_data) }));
+					return;
+				},
+				// The save pipeline places the world data into the slot before it removes the request.
+				None if store.is_some() => return,
+				None => Err(anyhow::anyhow!("the world couldn’t be serialized")),
+			}
+		},
+	};
+	commands.remove_resource::<SaveInProgress>();
+	finished.send(SaveFinished { save_name, result: result.map_err(|why| why.to_string()) });
+}
+
+fn notify_saved(mut finished: EventReader<SaveFinished>, mut notifications: EventWriter<NotificationEvent>) {
+	for SaveFinished { save_name, result } in finished.read() {
+		let notification = match result {
+			Ok(()) => NotificationEvent::info(format!("Saved the campground as “{save_name}”.")),
+			Err(why) => {
+				error!("Couldn’t save to {save_name}: {why}");
+				NotificationEvent::error(format!("The campground couldn’t be saved: {why}"))
+			},
+		};
+		notifications.send(notification);
+	}
+}
+
+fn cause_test_load(actions: ActionInput, mut errors: EventWriter<ErrorBox>, mut commands: Commands) {
+	if actions.just_pressed(Action::QuickLoad) {
+		match LoadSave::new("Test".to_string()) {
+			Ok(load) => commands.insert_resource(load),
+			Err(why) => {
+				errors.send(why.into());
+			},
+		}
+	}
+}
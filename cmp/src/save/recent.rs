@@ -0,0 +1,136 @@
+//! List of recently saved campgrounds.
+//!
+//! The list belongs to the player's profile rather than to any save, so it is stored by [`confy`] next to the game
+//! settings. It lets the main menu offer the most recent saves across all slots without reading every save file.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use super::{read_metadata, save_directory, SaveFinished};
+use crate::config::APP_NAME;
+use crate::save::metadata::SaveMetadata;
+
+const RECENTS_NAME: &str = "recent-saves";
+/// The list never grows longer than this.
+const MAX_RECENT_SAVES: usize = 5;
+
+/// Recently saved slots, newest first.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct RecentSaves {
+	#[serde(default)]
+	saves: Vec<RecentSave>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct RecentSave {
+	save_name: String,
+	/// Seconds since the Unix epoch.
+	saved_at:  u64,
+}
+
+impl RecentSaves {
+	fn load() -> Self {
+		confy::load(APP_NAME, RECENTS_NAME)
+			.inspect_err(|why| warn!("Couldn’t load recent saves: {why}"))
+			.unwrap_or_default()
+	}
+
+	fn store(&self) {
+		if let Err(why) = confy::store(APP_NAME, RECENTS_NAME, self) {
+			error!("Couldn’t store recent saves: {why}");
+		}
+	}
+
+	/// Moves the slot to the front of the list.
+	fn record(&mut self, save_name: &str, saved_at: SystemTime) {
+		self.saves.retain(|save| save.save_name != save_name);
+		self.saves.insert(0, RecentSave {
+			save_name: save_name.to_owned(),
+			saved_at:  saved_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+		});
+		self.saves.truncate(MAX_RECENT_SAVES);
+	}
+}
+
+/// A recently saved slot, with everything the main menu needs to show it.
+#[derive(Clone, Debug)]
+pub struct RecentSaveInfo {
+	pub save_name: String,
+	pub metadata:  SaveMetadata,
+	pub saved_at:  SystemTime,
+}
+
+impl RecentSaveInfo {
+	/// Name of the campground in the save, which older saves don't store.
+	pub fn campground_name(&self) -> &str {
+		self.metadata.name.as_deref().unwrap_or(&self.save_name)
+	}
+
+	/// Rough time since the save, such as "3 hours ago".
+	pub fn age(&self) -> String {
+		let seconds = SystemTime::now().duration_since(self.saved_at).unwrap_or_default().as_secs();
+		let (amount, unit) = match seconds {
+			.. 60 => return "just now".into(),
+			60 .. 3600 => (seconds / 60, "minute"),
+			3600 .. 86400 => (seconds / 3600, "hour"),
+			_ => (seconds / 86400, "day"),
+		};
+		format!("{amount} {unit}{} ago", if amount == 1 { "" } else { "s" })
+	}
+}
+
+/// The most recent saves, newest first. Slots whose save file was deleted or can't be read are skipped. Saves made
+/// before the list existed are found by their modification time instead.
+pub fn recent_saves() -> Vec<RecentSaveInfo> {
+	let recents = RecentSaves::load();
+	let candidates = if recents.saves.is_empty() {
+		saves_by_modification_time()
+	} else {
+		recents
+			.saves
+			.into_iter()
+			.map(|save| (save.save_name, UNIX_EPOCH + Duration::from_secs(save.saved_at)))
+			.collect()
+	};
+	with_metadata(candidates).take(MAX_RECENT_SAVES).collect()
+}
+
+/// All save slots, most recently modified first. Slots that can't be read are skipped.
+pub fn all_saves() -> Vec<RecentSaveInfo> {
+	with_metadata(saves_by_modification_time()).collect()
+}
+
+fn with_metadata(saves: Vec<(String, SystemTime)>) -> impl Iterator<Item = RecentSaveInfo> {
+	saves.into_iter().filter_map(|(save_name, saved_at)| {
+		let metadata = read_metadata(&save_name)
+			.inspect_err(|why| warn!("Couldn’t read metadata of save {save_name}: {why}"))
+			.ok()?;
+		Some(RecentSaveInfo { save_name, metadata, saved_at })
+	})
+}
+
+fn saves_by_modification_time() -> Vec<(String, SystemTime)> {
+	let Some(Ok(entries)) = save_directory().map(std::fs::read_dir) else {
+		return Vec::new();
+	};
+	let mut saves = entries
+		.filter_map(Result::ok)
+		.filter(|entry| entry.path().extension().is_some_and(|extension| extension == "cmpsave"))
+		.filter_map(|entry| {
+			Some((entry.path().file_stem()?.to_str()?.to_string(), entry.metadata().ok()?.modified().ok()?))
+		})
+		.collect::<Vec<_>>();
+	saves.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+	saves
+}
+
+/// Records every save in the recents list once it was written.
+pub(super) fn record_recent_saves(mut finished: EventReader<SaveFinished>) {
+	for SaveFinished { save_name, .. } in finished.read().filter(|finished| finished.result.is_ok()) {
+		let mut recents = RecentSaves::load();
+		recents.record(save_name, SystemTime::now());
+		recents.store();
+	}
+}
@@ -0,0 +1,207 @@
+//! Description of the data stored in saves, derived from the type registry.
+//!
+//! Every save stores the schema it was written with in its metadata block. Before loading, the stored schema is
+//! compared against the schema of the running game, so that saves from incompatible game versions are rejected with a
+//! list of the exact types and fields that changed, instead of failing deep inside world deserialization. The schema
+//! can also be dumped to JSON with the `--dump-save-schema` command-line argument to document the save format.
+//!
+//! The schema contains the game's own components that may be stored on saved entities, as well as the resources that
+//! are stored in saves. Fields that were added since a save was written are only compatible if their type reflects
+//! [`Default`] with `#[reflect(Default)]`, which fills in the missing fields when the save is loaded.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy::reflect::{TypeInfo, TypeRegistry};
+use serde_derive::{Deserialize, Serialize};
+
+use super::{included_resources, is_saved_component};
+
+/// Current version of the schema description format itself.
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// All types that can be stored in a save, keyed by their full type path.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SaveSchema {
+	/// Version of the schema description format; see [`SCHEMA_VERSION`].
+	pub schema_version: u16,
+	/// Version of the game that created this schema.
+	pub game_version:   String,
+	/// Savable components and resources.
+	pub types:          BTreeMap<String, TypeSchema>,
+}
+
+/// A single savable type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TypeSchema {
+	/// Whether the type is stored as a component or a resource.
+	pub kind:        SavedKind,
+	/// The serialized structure of the type.
+	pub shape:       Shape,
+	/// Whether the type reflects [`Default`], so that fields missing from a save are filled with their default values.
+	#[serde(default)]
+	pub has_default: bool,
+}
+
+/// How a type is stored in a save.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedKind {
+	/// The type is stored as a component on saved entities.
+	Component,
+	/// The type is stored as a world resource.
+	Resource,
+}
+
+impl std::fmt::Display for SavedKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Component => "component",
+			Self::Resource => "resource",
+		})
+	}
+}
+
+/// Serialized structure of a type; only the top level of a type is described, since nested types are described by
+/// their type paths.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Shape {
+	/// A struct with named fields.
+	Struct {
+		/// Fields in declaration order.
+		fields: Vec<Field>,
+	},
+	/// A tuple struct; the type paths of all fields in order.
+	TupleStruct {
+		/// Type paths of the fields.
+		fields: Vec<String>,
+	},
+	/// An enum; only the variant names are described.
+	Enum {
+		/// Names of all variants.
+		variants: Vec<String>,
+	},
+	/// A type without reflected structure, such as a primitive or a list.
+	Opaque,
+}
+
+impl Shape {
+	fn describe(&self) -> &'static str {
+		match self {
+			Self::Struct { .. } => "struct",
+			Self::TupleStruct { .. } => "tuple struct",
+			Self::Enum { .. } => "enum",
+			Self::Opaque => "opaque value",
+		}
+	}
+}
+
+/// A named struct field.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+	/// Name of the field.
+	pub name:      String,
+	/// Full type path of the field's type.
+	pub type_path: String,
+}
+
+impl SaveSchema {
+	/// Creates the schema of all savable types in the registry.
+	pub fn from_registry(registry: &TypeRegistry) -> Self {
+		let included_resources = included_resources();
+		let types = registry
+			.iter()
+			.filter_map(|registration| {
+				let kind = if included_resources.contains(&registration.type_id())
+					&& registration.data::<ReflectResource>().is_some()
+				{
+					SavedKind::Resource
+				} else if is_saved_component(registration) {
+					SavedKind::Component
+				} else {
+					return None;
+				};
+				let type_info = registration.type_info();
+				let has_default = registration.data::<ReflectDefault>().is_some();
+				Some((type_info.type_path().to_string(), TypeSchema { kind, shape: shape_of(type_info), has_default }))
+			})
+			.collect();
+		Self { schema_version: SCHEMA_VERSION, game_version: crate::VERSION.to_string(), types }
+	}
+
+	/// Lists all reasons why a save with this schema can't be loaded by a game with the current schema. The list is
+	/// empty if the save is compatible.
+	pub fn incompatibilities(&self, current: &Self) -> Vec<String> {
+		let mut problems = Vec::new();
+		for (name, saved) in &self.types {
+			let Some(current_type) = current.types.get(name) else {
+				problems.push(format!("The {} `{name}` no longer exists.", saved.kind));
+				continue;
+			};
+			if saved.kind != current_type.kind {
+				problems.push(format!("`{name}` was saved as a {} but is now a {}.", saved.kind, current_type.kind));
+			}
+			match (&saved.shape, &current_type.shape) {
+				(Shape::Struct { fields: saved_fields }, Shape::Struct { fields: current_fields }) => {
+					for field in saved_fields {
+						match current_fields.iter().find(|current_field| current_field.name == field.name) {
+							None => problems.push(format!("Field `{}` of `{name}` was removed.", field.name)),
+							Some(current_field) if current_field.type_path != field.type_path =>
+								problems.push(format!(
+									"Field `{}` of `{name}` changed type from `{}` to `{}`.",
+									field.name, field.type_path, current_field.type_path
+								)),
+							Some(_) => {},
+						}
+					}
+					// Added fields are filled in from the default value of the type, if it has one.
+					for field in current_fields.iter().filter(|current_field| {
+						!current_type.has_default && !saved_fields.iter().any(|field| field.name == current_field.name)
+					}) {
+						problems.push(format!(
+							"Field `{}` of `{name}` was added and is missing from the save.",
+							field.name
+						));
+					}
+				},
+				(Shape::TupleStruct { fields: saved_fields }, Shape::TupleStruct { fields: current_fields }) =>
+					if saved_fields != current_fields {
+						problems.push(format!(
+							"Fields of `{name}` changed from ({}) to ({}).",
+							saved_fields.join(", "),
+							current_fields.join(", ")
+						));
+					},
+				// New variants don't affect existing saves.
+				(Shape::Enum { variants: saved_variants }, Shape::Enum { variants: current_variants }) =>
+					for variant in saved_variants.iter().filter(|variant| !current_variants.contains(variant)) {
+						problems.push(format!("Variant `{variant}` of `{name}` was removed."));
+					},
+				(Shape::Opaque, Shape::Opaque) => {},
+				(saved_shape, current_shape) => problems.push(format!(
+					"`{name}` changed from a {} to a {}.",
+					saved_shape.describe(),
+					current_shape.describe()
+				)),
+			}
+		}
+		problems
+	}
+}
+
+fn shape_of(type_info: &TypeInfo) -> Shape {
+	match type_info {
+		TypeInfo::Struct(struct_info) => Shape::Struct {
+			fields: struct_info
+				.iter()
+				.map(|field| Field { name: field.name().to_string(), type_path: field.type_path().to_string() })
+				.collect(),
+		},
+		TypeInfo::TupleStruct(tuple_struct_info) =>
+			Shape::TupleStruct { fields: tuple_struct_info.iter().map(|field| field.type_path().to_string()).collect() },
+		TypeInfo::Enum(enum_info) =>
+			Shape::Enum { variants: enum_info.variant_names().iter().map(ToString::to_string).collect() },
+		_ => Shape::Opaque,
+	}
+}
@@ -0,0 +1,101 @@
+//! Accessibility options that adapt the UI to the player.
+//!
+//! The UI is laid out for a 1920 × 1080 window, which is hard to read on high-density displays and doesn't fit into
+//! smaller windows. The `ui_scale` setting scales all UI nodes through Bevy's [`UiScale`], on top of the scale factor
+//! of the window. The `text_size` setting additionally enlarges all UI text by one of the [`TextSize`] presets: every
+//! text remembers the font size that it was spawned with, and is resized from it whenever the setting changes. Both
+//! settings apply right away. The colorblind-safe colors of overlays and borders are part of the
+//! [`crate::graphics::palette`].
+
+use bevy::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::GameSettings;
+use crate::gamemode::GameSet;
+
+pub(super) struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+	fn build(&self, app: &mut App) {
+		// Settings can be changed in all game states.
+		app.add_systems(Update, (apply_ui_scale, scale_text).in_set(GameSet::UI));
+	}
+}
+
+/// Smallest and largest UI scale factor.
+pub const UI_SCALE_RANGE: [f32; 2] = [0.5, 2.];
+
+/// Presets for the size of UI text, relative to the size that the UI was laid out with.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextSize {
+	#[default]
+	Normal,
+	Large,
+	Largest,
+}
+
+impl TextSize {
+	pub const ALL: [Self; 3] = [Self::Normal, Self::Large, Self::Largest];
+
+	/// The next preset in [`Self::ALL`], wrapping around at the end.
+	pub fn next(&self) -> Self {
+		let index = Self::ALL.iter().position(|size| size == self).unwrap_or_default();
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+
+	/// Factor that font sizes are multiplied with.
+	pub const fn factor(&self) -> f32 {
+		match self {
+			Self::Normal => 1.,
+			Self::Large => 1.25,
+			Self::Largest => 1.5,
+		}
+	}
+}
+
+impl std::fmt::Display for TextSize {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Self::Normal => "Normal",
+			Self::Large => "Large",
+			Self::Largest => "Largest",
+		})
+	}
+}
+
+/// The font size that a UI text was spawned with, before the text size setting was applied.
+#[derive(Component, Clone, Copy, Debug)]
+struct BaseFontSize(f32);
+
+fn apply_ui_scale(settings: Res<GameSettings>, mut ui_scale: ResMut<UiScale>) {
+	let [min, max] = UI_SCALE_RANGE;
+	let scale = settings.ui_scale.clamp(min, max);
+	if settings.is_changed() && ui_scale.0 != scale {
+		ui_scale.0 = scale;
+	}
+}
+
+/// New texts are resized as soon as they appear, and all texts whenever the setting changes.
+fn scale_text(
+	settings: Res<GameSettings>,
+	mut new_texts: Query<(Entity, &mut TextFont), (With<Text>, Without<BaseFontSize>)>,
+	mut texts: Query<(&mut TextFont, &BaseFontSize)>,
+	mut commands: Commands,
+) {
+	let factor = settings.text_size.factor();
+	for (entity, mut font) in &mut new_texts {
+		commands.entity(entity).insert(BaseFontSize(font.font_size));
+		if factor != 1. {
+			font.font_size *= factor;
+		}
+	}
+	if !settings.is_changed() {
+		return;
+	}
+	for (mut font, BaseFontSize(base_size)) in &mut texts {
+		let font_size = base_size * factor;
+		if font.font_size != font_size {
+			font.font_size = font_size;
+		}
+	}
+}
@@ -3,8 +3,11 @@
 use std::marker::PhantomData;
 use std::time::Duration;
 
+use bevy::audio::Volume;
 use bevy::prelude::*;
 
+use crate::config::GameSettings;
+use crate::gamemode::GameSet;
 use crate::util::physics_ease::MassDamperSystem;
 use crate::util::Lerpable;
 
@@ -32,8 +35,16 @@ impl AnimatedProperty<Node, Val> for StyleHeight {
 	}
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StyleLeft;
+impl AnimatedProperty<Node, Val> for StyleLeft {
+	fn set_data(component: &mut Node, data: Val) {
+		component.left = data;
+	}
+}
+
 /// Defines the three end targets for an animation, in the logical sense.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Reflect, Clone, Copy, Debug, Default)]
 pub struct AnimationTargets {
 	pub start:        f32,
 	pub when_hovered: f32,
@@ -60,7 +71,7 @@ impl AnimationTargets {
 	}
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Reflect, Clone, Copy, Debug, Default)]
 pub struct TransitionTimes {
 	pub to_start:   Duration,
 	pub to_hovered: Duration,
@@ -157,11 +168,16 @@ impl<D: Lerpable + Sync + Send + 'static, C: Component, P: AnimatedProperty<C, D
 		self.system.set_target(self.target_values.logical_position_of(target));
 	}
 
-	/// Runs the regular update of the animation.
-	pub fn update(&mut self, time: &Time, component: &mut C) {
-		let normalized_delta =
-			time.delta().as_secs_f32() / self.transition_times.transition_time_to(self.target).as_secs_f32();
-		self.system.simulate(normalized_delta);
+	/// Runs the regular update of the animation. If the user asked for reduced motion, the animation jumps to its
+	/// target immediately instead of easing there.
+	pub fn update(&mut self, time: &Time<Real>, component: &mut C, settings: &GameSettings) {
+		if settings.reduce_motion {
+			self.system.settle();
+		} else {
+			let normalized_delta =
+				time.delta().as_secs_f32() / self.transition_times.transition_time_to(self.target).as_secs_f32();
+			self.system.simulate(normalized_delta);
+		}
 
 		let current_value = self.start_position.lerp(&self.end_position, self.system.position());
 		P::set_data(component, current_value);
@@ -186,6 +202,24 @@ impl<D: Lerpable + Sync + Send + 'static, C: Component, P: AnimatedProperty<C, D
 	// }
 }
 
+/// The standard button press feedback: the background darkens while the button is pressed.
+pub fn darken_when_pressed(
+	background_color: BackgroundColor,
+) -> UIAnimation<BackgroundColor, BackgroundColor, BackgroundColor> {
+	UIAnimation::new(
+		background_color,
+		BackgroundColor({
+			let Hsla { hue, saturation, mut lightness, alpha } = background_color.0.into();
+			lightness = (lightness - 0.3).clamp(0., 1.);
+			Color::hsla(hue, saturation, lightness, alpha)
+		}),
+		AnimationTargets::at_press(),
+		4.,
+		4.,
+		TransitionTimes::uniform(Duration::from_millis(100)),
+	)
+}
+
 pub fn transition_animation<
 	D: Lerpable + Send + Sync + 'static,
 	C: Component,
@@ -203,11 +237,53 @@ pub fn update_animation<
 	C: Component,
 	P: AnimatedProperty<C, D> + Send + Sync + 'static,
 >(
-	time: Res<Time>,
+	// UI keeps animating while the game is paused or in menus, where virtual time stands still.
+	time: Res<Time<Real>>,
+	settings: Res<GameSettings>,
 	mut buttons: Query<(&mut UIAnimation<D, C, P>, &mut C)>,
 ) {
 	for (mut animations, mut component) in &mut buttons {
-		animations.update(&time, &mut component);
+		animations.update(&time, &mut component, &settings);
+	}
+}
+
+/// Sounds played when the user interacts with a UI element. Both sounds are optional, so that a widget can opt into
+/// only the feedback it needs.
+#[derive(Component, Clone, Debug, Default)]
+pub struct InteractionSounds {
+	/// Short tick played when the cursor starts hovering over the element.
+	pub hover:        Option<Handle<AudioSource>>,
+	/// Click played when the element is pressed.
+	pub press:        Option<Handle<AudioSource>>,
+	/// Needed to distinguish a new hover from the cursor releasing a press, which both end up as
+	/// [`Interaction::Hovered`].
+	last_interaction: Interaction,
+}
+
+impl InteractionSounds {
+	pub fn new(hover: Option<Handle<AudioSource>>, press: Option<Handle<AudioSource>>) -> Self {
+		Self { hover, press, last_interaction: Interaction::None }
+	}
+}
+
+pub fn play_interaction_sounds(
+	mut interacted: Query<(&Interaction, &mut InteractionSounds), Changed<Interaction>>,
+	settings: Res<GameSettings>,
+	mut commands: Commands,
+) {
+	for (interaction, mut sounds) in &mut interacted {
+		let sound = match (sounds.last_interaction, interaction) {
+			(Interaction::None, Interaction::Hovered) => sounds.hover.clone(),
+			(_, Interaction::Pressed) => sounds.press.clone(),
+			_ => None,
+		};
+		sounds.last_interaction = *interaction;
+		if let Some(sound) = sound {
+			commands.spawn((
+				AudioPlayer::new(sound),
+				PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.ui_volume)),
+			));
+		}
 	}
 }
 
@@ -220,14 +296,18 @@ impl Plugin for AnimationPlugin {
 			(
 				transition_animation::<Val, Node, StyleHeight>,
 				transition_animation::<BackgroundColor, BackgroundColor, BackgroundColor>,
-			),
+			)
+				.in_set(GameSet::UI),
 		)
 		.add_systems(
 			Update,
 			(
 				update_animation::<Val, Node, StyleHeight>,
+				update_animation::<Val, Node, StyleLeft>,
 				update_animation::<BackgroundColor, BackgroundColor, BackgroundColor>,
-			),
-		);
+			)
+				.in_set(GameSet::UI),
+		)
+		.add_systems(Update, play_interaction_sounds.in_set(GameSet::UI));
 	}
 }
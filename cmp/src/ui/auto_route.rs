@@ -0,0 +1,290 @@
+//! Automatic routing of utility conduits.
+//!
+//! Laying pipes and cables tile by tile is tedious, so the auto-route tool, toggled with U, connects a pitch for the
+//! player: clicking a pitch finds the cheapest conduit route from it to the nearest part of each utility network that
+//! has a supply point, using the route costs of the ground (see [`crate::model::routing`]). Only the utilities that the
+//! pitch's type requires and lacks are routed, or all missing utilities for pitch types without requirements. The
+//! routes are shown as ghost conduits together with their cost, and are built on confirmation through the regular build
+//! requests, so they cost, replicate and undo just like conduits built by hand. The tool stays active until Escape or
+//! right click.
+
+use bevy::color::palettes::css::DARK_GRAY;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use super::build::BuildRequests;
+use super::notifications::NotificationEvent;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{anchor_for_image, font_for, image_for_utility, FontStyle, FontWeight};
+use crate::graphics::ObjectPriority;
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::area::{Area, ImmutableArea};
+use crate::model::buildables::BuildableRegistry;
+use crate::model::network::Network;
+use crate::model::routing::{route_cost, route_to_nearest};
+use crate::model::utilities::{
+	Connected, Electricity, Utility, UtilityPart, UtilityPartKind, UtilityResource, Water, ALL_UTILITIES,
+};
+use crate::model::{GridPosition, GroundMap, Pitch};
+use crate::util::Tooltip;
+use crate::HashSet;
+
+pub(super) struct AutoRoutePlugin;
+
+impl Plugin for AutoRoutePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<RouteSuggestion>()
+			.add_systems(Update, toggle_auto_routing.in_set(GameSet::UI).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				(
+					suggest_route,
+					build_suggested_route.in_set(SimulationActive),
+					dismiss_suggested_route,
+					show_suggested_route,
+				)
+					.chain()
+					.run_if(in_state(InputState::AutoRoute))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnExit(InputState::AutoRoute), clear_suggested_route);
+	}
+}
+
+/// Opacity of the ghost conduits, which are drawn in the color of their utility.
+const GHOST_ALPHA: f32 = 0.5;
+
+/// The currently suggested conduit routes, if any.
+#[derive(Resource, Default)]
+struct RouteSuggestion(Option<SuggestedRoute>);
+
+#[derive(Clone, Debug)]
+struct SuggestedRoute {
+	/// The conduits to build for each missing utility, from the pitch to the utility's network.
+	conduits: Vec<(UtilityPart, Vec<GridPosition>)>,
+	/// What building all conduits costs.
+	cost:     u64,
+}
+
+/// Marks ghost conduits of the suggested route.
+#[derive(Component, Clone, Copy, Debug)]
+struct RouteGhost;
+
+/// Marks the panel that offers building the suggested route.
+#[derive(Component, Clone, Copy, Debug)]
+struct RoutePanel;
+
+/// Marks the button that builds the suggested route.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildRouteButton;
+
+/// Marks the button that discards the suggested route.
+#[derive(Component, Clone, Copy, Debug)]
+struct DismissRouteButton;
+
+fn toggle_auto_routing(
+	actions: ActionInput,
+	mouse: Res<ButtonInput<MouseButton>>,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	match state.get() {
+		InputState::Idle if actions.just_pressed(Action::AutoRoute) => next_state.set(InputState::AutoRoute),
+		InputState::AutoRoute
+			if actions.any_just_pressed([Action::AutoRoute, Action::Cancel])
+				|| mouse.just_pressed(MouseButton::Right) =>
+			next_state.set(InputState::Idle),
+		_ => {},
+	}
+}
+
+/// The network tiles of the utility that are connected to a supply point.
+fn supplied_tiles<R: UtilityResource>(network: &Network<R>) -> Vec<GridPosition> {
+	network
+		.nodes()
+		.map(|(tile, _)| tile)
+		.filter(|tile| network.component_of(*tile).is_some_and(|component| component.capacity() > 0))
+		.collect()
+}
+
+/// The conduits that connect the pitch to the nearest of the supplied tiles, without the tiles of the pitch itself and
+/// the supplied tile. Returns [`None`] if no supplied tile can be reached.
+fn route_conduits(pitch: &Area, supplied: Vec<GridPosition>, ground_map: &GroundMap) -> Option<Vec<GridPosition>> {
+	let targets: HashSet<GridPosition> =
+		supplied.into_iter().filter(|tile| !pitch.contains(tile)).map(|tile| (tile, ())).collect();
+	// Start on the side of the pitch that faces the network, so that the route leaves the pitch right away.
+	let start = pitch
+		.tiles_iter()
+		.min_by_key(|tile| targets.keys().map(|target| tile.x.abs_diff(target.x) + tile.y.abs_diff(target.y)).min())?;
+	let route = route_to_nearest(start, &targets, ground_map, |_, kind| route_cost(kind))?;
+	let (_, tiles) = route.tiles.split_last()?;
+	Some(tiles.iter().copied().filter(|tile| !pitch.contains(tile)).collect())
+}
+
+fn suggest_route(
+	mouse: Res<ButtonInput<MouseButton>>,
+	picking: Picking,
+	pitches: Query<(&Pitch, AnyOf<(&Area, &ImmutableArea)>, Option<&Connected>)>,
+	water: Res<Network<Water>>,
+	electricity: Res<Network<Electricity>>,
+	ground_map: Res<GroundMap>,
+	registry: Res<BuildableRegistry>,
+	mut suggestion: ResMut<RouteSuggestion>,
+	mut notifications: EventWriter<NotificationEvent>,
+) {
+	let Some(pick) = picking.under_cursor() else {
+		return;
+	};
+	if !mouse.just_pressed(MouseButton::Left) || pick.ui_blocked {
+		return;
+	}
+	let Some((pitch, area, connected)) = pitches.iter().find_map(|(pitch, (area, immutable_area), connected)| {
+		let area = area.or(immutable_area.map(|area| &area.0))?;
+		area.contains(&pick.tile).then_some((pitch, area, connected))
+	}) else {
+		notifications.send(NotificationEvent::warning("Click a pitch to connect it to the utilities."));
+		return;
+	};
+
+	let required = pitch.kind.map_or(&[][..], |kind| kind.required_utilities());
+	let wanted = if required.is_empty() { &ALL_UTILITIES[..] } else { required };
+	let missing = wanted.iter().filter(|utility| !connected.is_some_and(|connected| connected.has(**utility)));
+	let mut conduits = Vec::new();
+	for utility in missing {
+		let supplied = match utility {
+			Utility::Water => supplied_tiles(&water),
+			Utility::Electricity => supplied_tiles(&electricity),
+		};
+		let part = UtilityPart { utility: *utility, kind: UtilityPartKind::Conduit };
+		match route_conduits(area, supplied, &ground_map) {
+			Some(tiles) => conduits.push((part, tiles)),
+			None => {
+				notifications.send(NotificationEvent::warning(format!(
+					"There is no {} network with a supply point that a {} could reach this pitch from.",
+					utility.to_string().to_lowercase(),
+					part.name().to_lowercase()
+				)));
+			},
+		}
+	}
+	if conduits.is_empty() {
+		suggestion.0 = None;
+		return;
+	}
+	let cost =
+		conduits.iter().map(|(part, tiles)| registry.definition(part.buildable()).cost * tiles.len() as u64).sum();
+	suggestion.0 = Some(SuggestedRoute { conduits, cost });
+}
+
+fn build_suggested_route(
+	button: Query<&Interaction, (Changed<Interaction>, With<BuildRouteButton>)>,
+	actions: ActionInput,
+	mut suggestion: ResMut<RouteSuggestion>,
+	mut build_requests: BuildRequests,
+) {
+	if !button.iter().any(|interaction| interaction == &Interaction::Pressed) && !actions.just_pressed(Action::Confirm)
+	{
+		return;
+	}
+	if let Some(route) = suggestion.0.take() {
+		for (part, tiles) in route.conduits {
+			for tile in tiles {
+				build_requests.send(part.buildable(), tile, tile);
+			}
+		}
+	}
+}
+
+fn dismiss_suggested_route(
+	button: Query<&Interaction, (Changed<Interaction>, With<DismissRouteButton>)>,
+	mut suggestion: ResMut<RouteSuggestion>,
+) {
+	if button.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		suggestion.0 = None;
+	}
+}
+
+fn clear_suggested_route(
+	mut suggestion: ResMut<RouteSuggestion>,
+	old_entities: Query<Entity, Or<(With<RouteGhost>, With<RoutePanel>)>>,
+	mut commands: Commands,
+) {
+	suggestion.0 = None;
+	for entity in &old_entities {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+fn show_suggested_route(
+	suggestion: Res<RouteSuggestion>,
+	old_entities: Query<Entity, Or<(With<RouteGhost>, With<RoutePanel>)>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !suggestion.is_changed() {
+		return;
+	}
+	for entity in &old_entities {
+		commands.entity(entity).despawn_recursive();
+	}
+	let Some(route) = &suggestion.0 else {
+		return;
+	};
+
+	let image = image_for_utility(UtilityPartKind::Conduit);
+	for (part, tiles) in &route.conduits {
+		for tile in tiles {
+			commands.spawn((RouteGhost, ObjectPriority::Overlay, *tile, Sprite {
+				color: part.utility.color().with_alpha(GHOST_ALPHA),
+				anchor: anchor_for_image(image),
+				image: asset_server.load(image),
+				..Default::default()
+			}));
+		}
+	}
+
+	let font = |weight| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size: 20.,
+		..Default::default()
+	};
+	let button_node = Node { padding: UiRect::all(Val::Px(5.)), ..Default::default() };
+	let parts = route.conduits.iter().map(|(part, tiles)| format!("{} × {}", tiles.len(), part)).collect::<Vec<_>>();
+	// The outer node only centers the panel and lets clicks pass through to the world.
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				top: Val::Percent(7.),
+				width: Val::Percent(100.),
+				justify_content: JustifyContent::Center,
+				..Default::default()
+			},
+			RoutePanel,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Node { column_gap: Val::Px(5.), padding: UiRect::all(Val::Px(5.)), ..Default::default() },
+					BackgroundColor(DARK_GRAY.into()),
+					FocusPolicy::Block,
+					Interaction::default(),
+				))
+				.with_children(|panel| {
+					panel
+						.spawn((Button, button_node.clone(), BuildRouteButton, Tooltip {
+							title: "Connect utilities".into(),
+							body:  format!("Build {}, so that the pitch is supplied.", parts.join(" and ")),
+						}))
+						.with_child((Text(format!("Build route (cost {})", route.cost)), font(FontWeight::Bold)));
+					panel
+						.spawn((Button, button_node, DismissRouteButton, Tooltip {
+							title: "Dismiss".into(),
+							body:  "Discard the suggested route.".into(),
+						}))
+						.with_child((Text("Dismiss".into()), font(FontWeight::Regular)));
+				});
+		});
+}
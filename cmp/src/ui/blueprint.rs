@@ -0,0 +1,524 @@
+//! Blueprints: reusable groups of builds that the player captured from their campground.
+//!
+//! The capture button in the blueprint build menu starts the capture tool. Dragging selects a rectangle, and everything
+//! in it that can be rebuilt is stored as a [`Blueprint`]: the builds that recreate it, relative to the rectangle's
+//! corner. Pitches and pools are only captured if they lie completely inside the rectangle and are rectangular
+//! themselves. Blueprints are stored as RON files in the `blueprints` directory next to the saves, and listed in the
+//! blueprint build menu.
+//!
+//! Stamping a blueprint previews its builds at the cursor and performs them through the regular [`BuildRequests`], so
+//! they follow the same placement rules and can be undone like any other build. Nothing is built if any of them would
+//! be rejected. Pitch types are only assigned once the pitches they belong to exist, which takes a few frames.
+
+use std::path::PathBuf;
+
+use bevy::color::palettes::css::DARK_GRAY;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::build::{BuildRequests, PreviewChild, INVALID_PREVIEW_TINT, PREVIEW_TINT};
+use super::build_menu::BLUEPRINT_COLUMNS;
+use super::controls::{BuildMenu, BuildMenuContainer};
+use super::dialog::OpenDialog;
+use super::rect_tool::{RectToolPlugin, TileRect};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{anchor_for_image, font_for, preview_image_for_buildable, FontStyle, FontWeight};
+use crate::graphics::{ObjectPriority, Sides};
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::area::{Area, ImmutableArea, Pool};
+use crate::model::buildables::BuildableRegistry;
+use crate::model::decoration::Decoration;
+use crate::model::facility::Facility;
+use crate::model::fence::{Fence, Gate};
+use crate::model::pitch::AccommodationBuilding;
+use crate::model::placement::PlacementError;
+use crate::model::utilities::{UtilityPart, UtilityPartKind};
+use crate::model::{Buildable, GridBox, GridPosition, GroundKind, GroundMap, Pitch};
+use crate::save::save_directory;
+use crate::ui::build::BuildMode;
+use crate::util::Tooltip;
+
+pub(super) struct BlueprintPlugin;
+
+impl Plugin for BlueprintPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(RectToolPlugin::<CaptureBlueprint>::new(InputState::CaptureBlueprint, SELECTION_TINT))
+			.init_resource::<Blueprints>()
+			.init_resource::<StampedBlueprint>()
+			.init_resource::<PendingPitchTypes>()
+			.add_systems(Startup, load_blueprints)
+			.add_systems(
+				Update,
+				(update_blueprint_menu, press_blueprint_buttons).in_set(GameSet::UI).in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				capture_blueprint
+					.run_if(in_state(InputState::CaptureBlueprint))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				(update_stamp_preview, stamp_blueprint.in_set(SimulationActive))
+					.chain()
+					.run_if(in_state(InputState::StampBlueprint))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				stop_stamping
+					.run_if(in_state(InputState::StampBlueprint))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				assign_pending_pitch_types.in_set(GameSet::UI).in_set(SimulationActive).in_set(GameState::InGame),
+			)
+			.add_systems(OnExit(InputState::StampBlueprint), clear_stamp_preview);
+	}
+}
+
+/// Tint of the tiles selected for capturing.
+const SELECTION_TINT: Color = Color::srgba(0.3, 0.5, 1., 0.7);
+/// How many frames a stamped pitch type waits for its pitch before it is given up.
+const PITCH_TYPE_ATTEMPTS: u32 = 10;
+
+/// One build of a blueprint.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BlueprintBuild {
+	pub buildable: Buildable,
+	pub start:     GridPosition,
+	pub end:       GridPosition,
+}
+
+/// A reusable group of builds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blueprint {
+	pub name:   String,
+	/// The builds relative to the blueprint's corner, in the order that they have to be performed in.
+	pub builds: Vec<BlueprintBuild>,
+}
+
+impl Blueprint {
+	/// The builds of the blueprint when it is stamped with its corner at the position.
+	pub fn builds_at(&self, corner: GridPosition) -> impl Iterator<Item = BlueprintBuild> + '_ {
+		self.builds.iter().map(move |build| BlueprintBuild {
+			buildable: build.buildable,
+			start:     build.start + corner,
+			end:       build.end + corner,
+		})
+	}
+
+	fn file_name(&self) -> String {
+		let name = self.name.to_lowercase().split_whitespace().join("-");
+		format!("{name}.ron")
+	}
+}
+
+/// All blueprints of the player, loaded from the blueprint directory on startup.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Blueprints(pub Vec<Blueprint>);
+
+/// Requests capturing everything in the rectangle as a new blueprint.
+#[derive(Event, Clone, Copy, Debug, Deref)]
+struct CaptureBlueprint(TileRect);
+
+impl From<TileRect> for CaptureBlueprint {
+	fn from(rect: TileRect) -> Self {
+		Self(rect)
+	}
+}
+
+/// The blueprint that is being stamped, and the corner that its preview was last shown at.
+#[derive(Resource, Clone, Debug, Default)]
+struct StampedBlueprint {
+	blueprint:      Option<Blueprint>,
+	preview_corner: Option<GridPosition>,
+}
+
+/// Pitch types of stamped blueprints that wait for their pitch to exist, with the remaining attempts.
+#[derive(Resource, Clone, Debug, Default)]
+struct PendingPitchTypes(Vec<(BlueprintBuild, u32)>);
+
+/// Marks the button that starts the capture tool.
+#[derive(Component, Clone, Copy, Debug)]
+struct CaptureBlueprintButton;
+
+/// Marks a button that starts stamping the blueprint with the index in [`Blueprints`].
+#[derive(Component, Clone, Copy, Debug)]
+struct StampBlueprintButton(usize);
+
+/// Marks the sprites that preview the stamped blueprint.
+#[derive(Component, Clone, Copy, Debug)]
+struct StampPreview;
+
+fn blueprint_directory() -> Option<PathBuf> {
+	let directory = save_directory()?.join("blueprints");
+	std::fs::create_dir_all(&directory).ok()?;
+	Some(directory)
+}
+
+fn load_blueprints(mut blueprints: ResMut<Blueprints>) {
+	let Some(entries) = blueprint_directory().and_then(|directory| std::fs::read_dir(directory).ok()) else {
+		return;
+	};
+	let paths = entries
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+		.sorted();
+	for path in paths {
+		match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|text| Ok(ron::from_str(&text)?)) {
+			Ok(blueprint) => blueprints.0.push(blueprint),
+			Err(why) => warn!("Couldn’t load blueprint {}: {why}", path.display()),
+		}
+	}
+}
+
+fn store_blueprint(blueprint: &Blueprint) -> anyhow::Result<()> {
+	let directory = blueprint_directory().ok_or_else(|| anyhow::anyhow!("There is no blueprint directory."))?;
+	let text = ron::ser::to_string_pretty(blueprint, ron::ser::PrettyConfig::default())?;
+	std::fs::write(directory.join(blueprint.file_name()), text)?;
+	Ok(())
+}
+
+/// Everything in the world that blueprints capture.
+#[derive(SystemParam)]
+struct CaptureSources<'w, 's> {
+	ground_map:    Res<'w, GroundMap>,
+	pitches: Query<
+		'w,
+		's,
+		(AnyOf<(&'static Area, &'static ImmutableArea)>, &'static Pitch, Option<&'static Children>),
+		Without<Pool>,
+	>,
+	pools:         Query<'w, 's, &'static Area, With<Pool>>,
+	buildings:     Query<'w, 's, &'static GridBox, With<AccommodationBuilding>>,
+	fences:        Query<'w, 's, (&'static GridPosition, &'static Fence)>,
+	gates:         Query<'w, 's, (&'static GridPosition, &'static Gate)>,
+	utility_parts: Query<'w, 's, (&'static GridPosition, &'static UtilityPart)>,
+	decorations:   Query<'w, 's, (&'static GridBox, &'static Decoration)>,
+	facilities:    Query<'w, 's, (&'static GridBox, &'static Facility)>,
+}
+
+impl CaptureSources<'_, '_> {
+	/// Creates a blueprint of everything in the rectangle between the corners.
+	fn capture(&self, name: String, first_corner: GridPosition, second_corner: GridPosition) -> Blueprint {
+		let corner = first_corner.component_wise_min(second_corner);
+		let inside = Area::from_rect(first_corner, second_corner);
+		let single = |buildable, position| BlueprintBuild { buildable, start: position, end: position };
+		let mut builds = Vec::new();
+
+		builds.extend(
+			self.ground_map
+				.iter()
+				.filter(|(position, _, kind)| *kind == GroundKind::Pathway && inside.contains(position))
+				.sorted_by_key(|(position, ..)| *position)
+				.map(|(position, _, kind)| single(Buildable::Ground(kind), position)),
+		);
+		builds.extend(
+			self.pools.iter().filter_map(|area| rect_inside(area, &inside)).map(|(start, end)| BlueprintBuild {
+				buildable: Buildable::PoolArea,
+				start,
+				end,
+			}),
+		);
+		let mut pitch_types = Vec::new();
+		for ((area, immutable_area), pitch, children) in &self.pitches {
+			let Some((start, end)) =
+				area.or(immutable_area.map(|area| &area.0)).and_then(|area| rect_inside(area, &inside))
+			else {
+				continue;
+			};
+			builds.push(BlueprintBuild { buildable: Buildable::Pitch, start, end });
+			let building = children.and_then(|children| self.buildings.iter_many(children).next());
+			if let (Some(kind), Some(building)) = (pitch.kind, building) {
+				let position = building.center();
+				pitch_types.push(single(Buildable::PitchType(kind), position));
+			}
+		}
+		let edge_objects = self
+			.fences
+			.iter()
+			.map(|(position, fence)| (Buildable::Fence(fence.style), position, fence.sides))
+			.chain(self.gates.iter().map(|(position, gate)| (Buildable::Gate, position, gate.sides)));
+		for (buildable, position, sides) in edge_objects.filter(|(_, position, _)| inside.contains(position)) {
+			// Edge objects are built along the bottom and left edges of tiles, which is where the edges of the other
+			// sides start as well.
+			for side in sides.iter() {
+				let (start, direction) = match side {
+					Sides::Bottom => (*position, IVec3::X),
+					Sides::Left => (*position, IVec3::Y),
+					Sides::Top => (*position + IVec3::Y, IVec3::X),
+					_ => (*position + IVec3::X, IVec3::Y),
+				};
+				builds.push(BlueprintBuild { buildable, start, end: start + direction });
+			}
+		}
+		for (position, part) in self.utility_parts.iter().filter(|(position, _)| inside.contains(position)) {
+			let buildable = match part.kind {
+				UtilityPartKind::SupplyPoint => Buildable::SupplyPoint(part.utility),
+				UtilityPartKind::Conduit => Buildable::Conduit(part.utility),
+			};
+			builds.push(single(buildable, *position));
+		}
+		for (grid_box, decoration) in &self.decorations {
+			if grid_box.floor_positions().all(|position| inside.contains(&position)) {
+				let position = grid_box.center();
+				builds.push(single(Buildable::Decoration(*decoration), position));
+			}
+		}
+		for (grid_box, facility) in &self.facilities {
+			if grid_box.floor_positions().all(|position| inside.contains(&position)) {
+				let position = grid_box.center();
+				builds.push(single(Buildable::Facility(*facility), position));
+			}
+		}
+		// Pitch types need their pitch, so they are built last.
+		builds.extend(pitch_types);
+
+		for build in &mut builds {
+			build.start = build.start - corner;
+			build.end = build.end - corner;
+		}
+		Blueprint { name, builds }
+	}
+}
+
+/// The corners of the area if it is a rectangle that lies completely inside the other area.
+fn rect_inside(area: &Area, inside: &Area) -> Option<(GridPosition, GridPosition)> {
+	let (smallest_x, largest_x) = area.tiles_iter().map(|tile| tile.x).minmax().into_option()?;
+	let (smallest_y, largest_y) = area.tiles_iter().map(|tile| tile.y).minmax().into_option()?;
+	let corners = (GridPosition::from((smallest_x, smallest_y)), GridPosition::from((largest_x, largest_y)));
+	let is_rect = Area::from_rect(corners.0, corners.1).size() == area.size();
+	(is_rect && area.tiles_iter().all(|tile| inside.contains(&tile))).then_some(corners)
+}
+
+fn update_blueprint_menu(
+	blueprints: Res<Blueprints>,
+	containers: Query<(Entity, Ref<BuildMenuContainer>)>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let Some((container, _)) = containers
+		.iter()
+		.find(|(_, container)| container.0 == BuildMenu::Blueprints)
+		.filter(|(_, container)| container.is_added() || blueprints.is_changed())
+	else {
+		return;
+	};
+	let font = TextFont {
+		font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+		font_size: 20.,
+		..default()
+	};
+	let node = Node {
+		justify_content: JustifyContent::Center,
+		align_items: AlignItems::Center,
+		grid_column: GridPlacement::span(BLUEPRINT_COLUMNS),
+		padding: UiRect::horizontal(Val::Px(10.)),
+		..default()
+	};
+	commands.entity(container).despawn_descendants().with_children(|menu| {
+		menu.spawn((Button, node.clone(), BackgroundColor(DARK_GRAY.into()), CaptureBlueprintButton, Tooltip {
+			title: "Capture".into(),
+			body:  "Drag to store everything in an area as a new blueprint. Right click or Escape to stop.".into(),
+		}))
+		.with_child((Text("Capture…".into()), font.clone()));
+		for (index, blueprint) in blueprints.0.iter().enumerate() {
+			menu.spawn((
+				Button,
+				node.clone(),
+				BackgroundColor(DARK_GRAY.into()),
+				StampBlueprintButton(index),
+				Tooltip {
+					title: blueprint.name.clone(),
+					body:  format!(
+						"Place copies of this blueprint, with {} builds. Right click or Escape to stop.",
+						blueprint.builds.len()
+					),
+				},
+			))
+			.with_child((Text(blueprint.name.clone()), font.clone()));
+		}
+	});
+}
+
+fn press_blueprint_buttons(
+	capture_buttons: Query<&Interaction, (Changed<Interaction>, With<CaptureBlueprintButton>)>,
+	stamp_buttons: Query<(&Interaction, &StampBlueprintButton), Changed<Interaction>>,
+	blueprints: Res<Blueprints>,
+	mut stamped: ResMut<StampedBlueprint>,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	if ![InputState::Idle, InputState::Building, InputState::StampBlueprint].contains(state.get()) {
+		return;
+	}
+	if capture_buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		next_state.set(InputState::CaptureBlueprint);
+	}
+	let pressed = stamp_buttons.iter().find(|(interaction, _)| *interaction == &Interaction::Pressed);
+	if let Some(blueprint) = pressed.and_then(|(_, button)| blueprints.0.get(button.0)) {
+		*stamped = StampedBlueprint { blueprint: Some(blueprint.clone()), preview_corner: None };
+		next_state.set(InputState::StampBlueprint);
+	}
+}
+
+fn stop_stamping(
+	actions: ActionInput,
+	mouse: Res<ButtonInput<MouseButton>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	if actions.just_pressed(Action::Cancel) || mouse.just_pressed(MouseButton::Right) {
+		next_state.set(InputState::Idle);
+	}
+}
+
+fn capture_blueprint(
+	mut captures: EventReader<CaptureBlueprint>,
+	sources: CaptureSources,
+	mut blueprints: ResMut<Blueprints>,
+	mut dialogs: EventWriter<OpenDialog>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	for capture in captures.read() {
+		let name = (blueprints.0.len() + 1 ..)
+			.map(|number| format!("Blueprint {number}"))
+			.find(|name| blueprints.0.iter().all(|blueprint| blueprint.name != *name))
+			.unwrap_or_default();
+		let blueprint = sources.capture(name, capture.start_position, capture.end_position);
+		if blueprint.builds.is_empty() {
+			dialogs.send(OpenDialog::error("Empty blueprint").paragraph("There is nothing to capture in this area."));
+			continue;
+		}
+		if let Err(why) = store_blueprint(&blueprint) {
+			error!("Couldn’t store blueprint: {why}");
+			dialogs.send(OpenDialog::error("Couldn’t store blueprint").paragraph(why.to_string()));
+			continue;
+		}
+		info!("Captured {} with {} builds", blueprint.name, blueprint.builds.len());
+		blueprints.0.push(blueprint);
+		next_state.set(InputState::Idle);
+	}
+}
+
+/// The tiles and images that preview a build; fences are not previewed.
+fn preview_sprites(build: BlueprintBuild, registry: &BuildableRegistry) -> Vec<(GridPosition, &'static str)> {
+	let image = preview_image_for_buildable(build.buildable);
+	match build.buildable.build_mode() {
+		BuildMode::Single => vec![(registry.grid_box_at(build.buildable, build.start).smallest(), image)],
+		BuildMode::Line | BuildMode::Rect => registry
+			.footprint(build.buildable, build.start, build.end)
+			.into_iter()
+			.map(|position| (position, image))
+			.collect(),
+		BuildMode::Edge => Vec::new(),
+	}
+}
+
+/// Checks all builds of the stamp except pitch types, whose pitches don't exist yet.
+fn check_stamp(builds: &[BlueprintBuild], build_requests: &BuildRequests) -> Result<(), PlacementError> {
+	builds
+		.iter()
+		.filter(|build| !matches!(build.buildable, Buildable::PitchType(_)))
+		.try_for_each(|build| build_requests.check(build.buildable, build.start, build.end))
+}
+
+fn update_stamp_preview(
+	picking: Picking,
+	mut stamped: ResMut<StampedBlueprint>,
+	previews: Query<Entity, With<StampPreview>>,
+	build_requests: BuildRequests,
+	registry: Res<BuildableRegistry>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let Some(pick) = picking.under_cursor() else {
+		return;
+	};
+	if stamped.preview_corner == Some(pick.tile) {
+		return;
+	}
+	let Some(blueprint) = &stamped.blueprint else {
+		return;
+	};
+	for preview in &previews {
+		commands.entity(preview).despawn_recursive();
+	}
+	let builds = blueprint.builds_at(pick.tile).collect::<Vec<_>>();
+	let tint = if check_stamp(&builds, &build_requests).is_ok() { PREVIEW_TINT } else { INVALID_PREVIEW_TINT };
+	for (position, image) in builds.into_iter().flat_map(|build| preview_sprites(build, &registry)) {
+		commands.spawn((StampPreview, PreviewChild, ObjectPriority::Overlay, position, Sprite {
+			color: tint,
+			anchor: anchor_for_image(image),
+			image: asset_server.load(image),
+			..default()
+		}));
+	}
+	stamped.preview_corner = Some(pick.tile);
+}
+
+fn stamp_blueprint(
+	mouse: Res<ButtonInput<MouseButton>>,
+	picking: Picking,
+	mut stamped: ResMut<StampedBlueprint>,
+	mut build_requests: BuildRequests,
+	mut pending: ResMut<PendingPitchTypes>,
+) {
+	let Some(pick) = picking.under_cursor().filter(|pick| !pick.ui_blocked) else {
+		return;
+	};
+	if !mouse.just_released(MouseButton::Left) {
+		return;
+	}
+	let Some(blueprint) = &stamped.blueprint else {
+		return;
+	};
+	let builds = blueprint.builds_at(pick.tile).collect::<Vec<_>>();
+	if let Err(why) = check_stamp(&builds, &build_requests) {
+		build_requests.reject(why);
+		return;
+	}
+	for build in builds {
+		if matches!(build.buildable, Buildable::PitchType(_)) {
+			pending.0.push((build, PITCH_TYPE_ATTEMPTS));
+		} else {
+			build_requests.send(build.buildable, build.start, build.end);
+		}
+	}
+	// The stamped builds change what the preview has to check against.
+	stamped.preview_corner = None;
+}
+
+fn assign_pending_pitch_types(mut pending: ResMut<PendingPitchTypes>, mut build_requests: BuildRequests) {
+	if pending.0.is_empty() {
+		return;
+	}
+	pending.0.retain_mut(|(build, attempts)| {
+		*attempts -= 1;
+		// Once out of attempts, the build is sent anyways so that its error is shown.
+		if build_requests.check(build.buildable, build.start, build.end).is_ok() || *attempts == 0 {
+			build_requests.send(build.buildable, build.start, build.end);
+			false
+		} else {
+			true
+		}
+	});
+}
+
+fn clear_stamp_preview(
+	mut stamped: ResMut<StampedBlueprint>,
+	previews: Query<Entity, With<StampPreview>>,
+	mut commands: Commands,
+) {
+	*stamped = StampedBlueprint::default();
+	for preview in &previews {
+		commands.entity(preview).despawn_recursive();
+	}
+}
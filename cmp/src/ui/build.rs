@@ -1,57 +1,106 @@
 use std::sync::OnceLock;
 
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::utils::HashMap;
 use itertools::{EitherOrBoth, Itertools};
-use thiserror::Error;
+use moonshine_save::save::Save;
 
-use super::error::{DisplayableError, ErrorBox};
+use super::error::ErrorBox;
 use super::on_start_build_preview;
 use super::world_info::WorldInfoProperties;
-use crate::gamemode::GameState;
-use crate::graphics::library::{anchor_for_image, preview_image_for_buildable};
-use crate::graphics::{engine_to_world_space, InGameCamera, ObjectPriority};
-use crate::input::{camera_to_world, InputState};
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::{
+	build_dust, BorderKind, BorderTextures, BuildPop, ObjectPriority, Overlay, OverlayLayer, Sides, WalkingSpeedLayer,
+};
+use crate::input::keyboard::VirtualCursor;
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
 use crate::model::area::{Area, ImmutableArea, Pool, UpdateAreas};
+use crate::model::balance::Balance;
+use crate::model::buildables::BuildableRegistry;
+use crate::model::construction::{start_construction, UnderConstruction};
+use crate::model::decoration::{spawn_decoration, Decoration};
+use crate::model::facility::{spawn_facility, Facility};
+use crate::model::fence::{edge_path, EdgeObject, Fence, Gate};
+use crate::model::nav::NavCategory;
 use crate::model::pitch::Pitch;
+use crate::model::placement::{Placement, PlacementError, PlacementRules};
+use crate::model::road::{gate_for, spawn_entrance_road, EntranceRoad};
+use crate::model::rollup::DailyTally;
+use crate::model::utilities::{spawn_utility_part, UtilityPart};
 use crate::model::{
-	AccommodationBuildingBundle, AccommodationBundle, Buildable, BuildableType, GridBox, GridPosition, GroundKind,
-	GroundMap,
+	AccommodationBuildingBundle, AccommodationBundle, Buildable, BuildableType, BuildableTypeParam, GridBox,
+	GridPosition, GroundKind, GroundMap, PitchType,
 };
+use crate::save::LoadSave;
 
 pub struct BuildPlugin;
 
 impl Plugin for BuildPlugin {
 	fn build(&self, app: &mut App) {
 		app.add_event::<StartBuildPreview>()
-			.add_event::<PerformBuild<{ BuildableType::Ground }>>()
-			.add_event::<PerformBuild<{ BuildableType::Pitch }>>()
-			.add_event::<PerformBuild<{ BuildableType::PitchType }>>()
-			.add_event::<PerformBuild<{ BuildableType::PoolArea }>>()
-			.add_event::<BuildError>()
+			.add_event::<PerformBuild<{ BuildableType::Ground.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Pitch.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::PitchType.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::PoolArea.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Fence.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Utility.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Decoration.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Facility.param() }>>()
+			.add_event::<PerformBuild<{ BuildableType::Road.param() }>>()
+			.add_event::<BuildCompleted>()
+			.add_event::<RevertBuild>()
+			.init_resource::<BuildHistory>()
+			.init_resource::<PlacementRules>()
 			.add_systems(
 				Update,
 				update_building_preview
 					.after(create_building_preview)
 					.after(on_start_build_preview)
 					.run_if(in_state(InputState::Building))
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
 			.add_systems(
 				Update,
-				(handle_build_interactions, set_building_preview_start, end_building)
+				(handle_build_interactions.in_set(SimulationActive), set_building_preview_start, end_building)
 					.run_if(in_state(InputState::Building))
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
-			.add_systems(Update, create_building_preview.run_if(in_state(GameState::InGame)))
+			.add_systems(Update, create_building_preview.in_set(GameSet::UI).in_set(GameState::InGame))
 			.add_systems(
-				OnExit(InputState::Building),
-				destroy_building_preview.after(update_building_preview).run_if(in_state(GameState::InGame)),
+				Update,
+				(tint_preview, update_build_summary)
+					.after(update_building_preview)
+					.run_if(in_state(InputState::Building))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
+			.add_systems(OnExit(InputState::Building), destroy_building_preview.in_set(GameState::InGame))
+			.add_systems(PreUpdate, clear_build_history.run_if(resource_added::<LoadSave>))
 			.add_systems(
 				Update,
-				(perform_pitch_build, perform_pitch_type_build, perform_ground_build, perform_pool_area_build)
-					.run_if(in_state(GameState::InGame)),
+				(
+					perform_pitch_build,
+					perform_pitch_type_build,
+					perform_ground_build,
+					perform_pool_area_build,
+					perform_fence_build,
+					perform_utility_build,
+					perform_decoration_build,
+					perform_facility_build,
+					perform_road_build,
+					undo_redo_builds,
+					revert_builds,
+				)
+					.in_set(GameSet::Simulation)
+					.in_set(SimulationActive)
+					.in_set(GameState::InGame),
 			);
 	}
 }
@@ -64,29 +113,27 @@ pub struct StartBuildPreview {
 /// The [`BuildableType`] is a static parameter on the build event so that we can determine the correct receiver system
 /// via the type system and bevy's system parameters.
 #[derive(Event)]
-struct PerformBuild<const BUILDABLE: BuildableType> {
+struct PerformBuild<const BUILDABLE: BuildableTypeParam> {
 	start_position: GridPosition,
 	end_position:   GridPosition,
 	buildable:      Buildable,
 }
 
-/// Any reason that the build could not be completed; eventually propagated to the end-user.
-#[derive(Event, Error, Debug)]
-pub(super) enum BuildError {
-	#[error("There is no pitch to build on here.")]
-	NoAccommodationHere,
-	#[error("Building doesn’t have enough space to be built here.")]
-	NoSpace,
-	#[error(
-		"The pitch area is too small for this pitch type; {} tiles are required but there are only {} \
-		 tiles.", .required, .actual
-	)]
-	PitchTooSmall { required: usize, actual: usize },
+/// Sent after a build was successfully performed, with the same parameters that it was requested with.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BuildCompleted {
+	pub buildable:      Buildable,
+	pub start_position: GridPosition,
+	pub end_position:   GridPosition,
 }
 
-impl DisplayableError for BuildError {
-	fn name(&self) -> &str {
-		"Build error"
+impl<const BUILDABLE: BuildableTypeParam> From<&PerformBuild<BUILDABLE>> for BuildCompleted {
+	fn from(event: &PerformBuild<BUILDABLE>) -> Self {
+		Self {
+			buildable:      event.buildable,
+			start_position: event.start_position,
+			end_position:   event.end_position,
+		}
 	}
 }
 
@@ -108,10 +155,21 @@ impl PreviewParent {
 	}
 }
 
+/// Tint of all preview sprites.
+pub(super) const PREVIEW_TINT: Color = Color::hsla(0., 0.5, 1., 0.7);
+/// Tint of preview sprites of builds that violate a placement rule.
+pub(super) const INVALID_PREVIEW_TINT: Color = Color::hsla(0., 0.9, 0.5, 0.7);
+
+/// Label next to the cursor that summarizes how many tiles the previewed build covers, what it costs, and why it would
+/// be rejected.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct BuildSummary;
+
 /// Marker component for anything that's part of a building preview.
 #[derive(Component, Reflect)]
 #[reflect(Component)]
-struct PreviewChild;
+pub(super) struct PreviewChild;
 
 /// The way the user performs building, and the way the building is previewed.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -122,32 +180,36 @@ pub enum BuildMode {
 	Line,
 	/// A rectangle with opposite corners at click start and end will be built.
 	Rect,
+	/// Tile edges from click start to end will be built, with up to one automatically placed corner.
+	Edge,
 }
 
 impl BuildMode {
 	fn update_preview<'a>(
 		&self,
 		PreviewParent { previewed, start_position, current_position }: PreviewParent,
-		mut current_children: impl Iterator<Item = (Entity, Mut<'a, GridPosition>)>,
+		mut current_children: impl Iterator<Item = (Entity, Mut<'a, GridPosition>, Mut<'a, Sprite>)>,
 		parent_entity: Entity,
 		commands: &mut Commands,
 		asset_server: &AssetServer,
+		texture_atlases: &mut Assets<TextureAtlasLayout>,
+		border_textures: &mut BorderTextures,
+		registry: &BuildableRegistry,
 	) {
-		const PREVIEW_TINT: Color = Color::hsla(0., 0.5, 1., 0.7);
-
+		let definition = registry.definition(previewed);
+		let (image, anchor) = (definition.preview.as_str(), definition.preview_anchor());
 		match self {
 			Self::Single => {
 				// Using start_position has the effect of "locking" the building where the click started.
-				let preview_position = GridBox::around(start_position, previewed.size().flat()).smallest();
+				let preview_position = registry.grid_box_at(previewed, start_position).smallest();
 				let any_child = current_children.next();
-				if let Some((_, mut existing_child)) = any_child {
+				if let Some((_, mut existing_child, _)) = any_child {
 					*existing_child = preview_position;
 				} else {
-					let image = preview_image_for_buildable(previewed);
 					commands.entity(parent_entity).with_children(|parent| {
 						parent.spawn((PreviewChild, preview_position, ObjectPriority::Overlay, Sprite {
 							color: PREVIEW_TINT,
-							anchor: anchor_for_image(image),
+							anchor,
 							image: asset_server.load(image),
 							..Default::default()
 						}));
@@ -158,21 +220,20 @@ impl BuildMode {
 				let required_positions = start_position.line_to_2d(current_position);
 				for element in required_positions.zip_longest(current_children) {
 					match element {
-						EitherOrBoth::Both(position, (_, mut child)) => *child = position,
+						EitherOrBoth::Both(position, (_, mut child, _)) => *child = position,
 						// Create new child.
 						EitherOrBoth::Left(position) => {
-							let image = preview_image_for_buildable(previewed);
 							commands.entity(parent_entity).with_children(|parent| {
 								parent.spawn((PreviewChild, ObjectPriority::Overlay, position, Sprite {
 									color: PREVIEW_TINT,
-									anchor: anchor_for_image(image),
+									anchor,
 									image: asset_server.load(image),
 									..Default::default()
 								}));
 							});
 						},
 						// Destroy not needed child.
-						EitherOrBoth::Right((child, _)) => {
+						EitherOrBoth::Right((child, ..)) => {
 							commands.entity(child).despawn_recursive();
 						},
 					}
@@ -183,11 +244,10 @@ impl BuildMode {
 				let larger_corner = start_position.component_wise_max(current_position);
 
 				let mut parent = commands.entity(parent_entity);
-				let image = preview_image_for_buildable(previewed);
 
 				for x in smaller_corner.x ..= larger_corner.x {
 					for y in smaller_corner.y ..= larger_corner.y {
-						if let Some((_, mut old_child_position)) = current_children.next() {
+						if let Some((_, mut old_child_position, _)) = current_children.next() {
 							old_child_position.x = x;
 							old_child_position.y = y;
 						} else {
@@ -198,7 +258,7 @@ impl BuildMode {
 									GridPosition::from((x, y, start_position.z)),
 									Sprite {
 										color: PREVIEW_TINT,
-										anchor: anchor_for_image(image),
+										anchor,
 										image: asset_server.load(image),
 										..Default::default()
 									},
@@ -209,37 +269,51 @@ impl BuildMode {
 				}
 
 				// Despawn all superfluous old children.
-				for (superfluous_child, _) in current_children {
+				for (superfluous_child, ..) in current_children {
 					commands.entity(superfluous_child).despawn_recursive();
 				}
 			},
+			Self::Edge => {
+				let kind = if previewed == Buildable::Gate { BorderKind::Gate } else { BorderKind::Fence };
+				let (layout, image) = border_textures.get(kind, texture_atlases, asset_server);
+				for element in edge_path(start_position, current_position).into_iter().zip_longest(current_children) {
+					match element {
+						EitherOrBoth::Both((position, side), (_, mut child, mut sprite)) => {
+							*child = position;
+							sprite.anchor = side.anchor_at_tile();
+							if let Some(atlas) = sprite.texture_atlas.as_mut() {
+								atlas.index = side.to_sprite_index();
+							}
+						},
+						EitherOrBoth::Left((position, side)) => {
+							let mut sprite = Sprite::from_atlas_image(image.clone(), TextureAtlas {
+								layout: layout.clone(),
+								index:  side.to_sprite_index(),
+							});
+							sprite.color = PREVIEW_TINT;
+							sprite.anchor = side.anchor_at_tile();
+							commands.entity(parent_entity).with_children(|parent| {
+								parent.spawn((PreviewChild, ObjectPriority::Overlay, position, sprite));
+							});
+						},
+						EitherOrBoth::Right((child, ..)) => {
+							commands.entity(child).despawn_recursive();
+						},
+					}
+				}
+			},
 		}
 	}
 }
 
 /// This always sets the building preview's current point to the mouse cursor. [`handle_build_interactions`] copies this
 /// into the start point when needed.
-fn set_building_preview_start(
-	windows: Query<&Window, With<PrimaryWindow>>,
-	camera_q: Query<(&Camera, &GlobalTransform), With<InGameCamera>>,
-	mut preview: Query<&mut PreviewParent>,
-) {
-	let (camera, camera_transform) = camera_q.single();
-	let window = windows.single();
-
-	let cursor_position =
-		window.cursor_position().and_then(|cursor| camera_to_world(cursor, window, camera, camera_transform));
-	if cursor_position.is_none() {
+fn set_building_preview_start(picking: Picking, mut preview: Query<&mut PreviewParent>) {
+	let Some(pick) = picking.under_cursor() else {
 		return;
-	}
-	// Since the anchors are on the lower left corner of the sprite, we need to offset the cursor half a tile.
-	let cursor_position = cursor_position.unwrap();
-	// FIXME: Use ray casting + structure data to figure out the elevation under the cursor.
-	let fake_z = 0.;
-	// Since we measure positions from corners, offset the cursor half a tile so we move the preview around its center.
-	let world_position = (engine_to_world_space(cursor_position, fake_z) - Vec3::new(0.5, 0.5, 0.)).round();
+	};
 	for mut preview_data in &mut preview {
-		preview_data.current_position = world_position;
+		preview_data.current_position = pick.tile;
 	}
 }
 
@@ -247,15 +321,18 @@ fn update_building_preview(
 	mouse: Res<ButtonInput<MouseButton>>,
 	mut commands: Commands,
 	mut preview: Query<(Entity, Option<&mut Children>, &PreviewParent, &mut Visibility)>,
-	preview_children: Query<&mut GridPosition, With<PreviewChild>>,
+	preview_children: Query<(&mut GridPosition, &mut Sprite), With<PreviewChild>>,
 	asset_server: Res<AssetServer>,
+	mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+	mut border_textures: ResMut<BorderTextures>,
+	registry: Res<BuildableRegistry>,
 ) {
 	for (parent_entity, children, preview_data, mut visibility) in &mut preview {
 		// SAFETY: We never obtain the same component twice, since the entity IDs in the iterator are distinct.
 		// Therefore, we do not alias a mutable pointer to the same component.
 		let children = children.iter().flatten().flat_map(|entity| {
-			if let Ok(child) = unsafe { preview_children.get_unchecked(*entity) } {
-				Some((*entity, child))
+			if let Ok((position, sprite)) = unsafe { preview_children.get_unchecked(*entity) } {
+				Some((*entity, position, sprite))
 			} else {
 				None
 			}
@@ -266,6 +343,9 @@ fn update_building_preview(
 			parent_entity,
 			&mut commands,
 			&asset_server,
+			&mut texture_atlases,
+			&mut border_textures,
+			&registry,
 		);
 		// Make sure to delay displaying the preview until after the user releases the mouse after clicking the button.
 		// On second click, since we never set the building to invisible again, it doesn't matter.
@@ -275,10 +355,127 @@ fn update_building_preview(
 	}
 }
 
+/// Previews of builds that violate a placement rule are tinted red. Otherwise, while the [`WalkingSpeedLayer`] is
+/// shown, ground previews show how the build would change traversal speed: tiles that become faster are tinted green,
+/// tiles that become slower are tinted red, or in the colors of the overlay's ramp with the colorblind-safe palette.
+fn tint_preview(
+	overlay: Res<Overlay>,
+	settings: Res<GameSettings>,
+	placement: Placement,
+	previews: Query<(&PreviewParent, &Children)>,
+	mut preview_children: Query<(&GridPosition, &mut Sprite), With<PreviewChild>>,
+) {
+	for (preview, children) in &previews {
+		let is_valid = placement.check(preview.previewed, preview.start_position, preview.current_position).is_ok();
+		let new_speed = match preview.previewed {
+			Buildable::Ground(new_kind) => Some(ground_speed(new_kind)),
+			_ => None,
+		};
+		let mut children = preview_children.iter_many_mut(children);
+		while let Some((position, mut sprite)) = children.fetch_next() {
+			let old_speed = placement.ground_map.kind_of(position).and_then(ground_speed);
+			let tint = if !is_valid {
+				INVALID_PREVIEW_TINT
+			} else if let Some(new_speed) =
+				new_speed.filter(|new_speed| overlay.shows::<WalkingSpeedLayer>() && old_speed != *new_speed)
+			{
+				// Note that untraversable tiles are slower than any traversable tile.
+				let value = if new_speed > old_speed { 1. } else { 0. };
+				settings.palette().ramp(WalkingSpeedLayer::HUES, value).with_alpha(PREVIEW_TINT.alpha())
+			} else {
+				PREVIEW_TINT
+			};
+			if sprite.color != tint {
+				sprite.color = tint;
+			}
+		}
+	}
+}
+
+/// The summary follows the virtual cursor while it is active, like the preview does.
+fn update_build_summary(
+	mouse: Res<ButtonInput<MouseButton>>,
+	windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+	virtual_cursor: Res<VirtualCursor>,
+	placement: Placement,
+	balance: Res<Balance>,
+	registry: Res<BuildableRegistry>,
+	previews: Query<(&PreviewParent, &Visibility)>,
+	mut summaries: Query<
+		(&mut Node, &mut Text, &mut TextColor, &mut Visibility),
+		(With<BuildSummary>, Without<PreviewParent>),
+	>,
+) {
+	let (Ok((preview, preview_visibility)), Ok((mut node, mut text, mut color, mut visibility))) =
+		(previews.get_single(), summaries.get_single_mut())
+	else {
+		return;
+	};
+	let cursor_position = windows
+		.get_single()
+		.ok()
+		.and_then(|window| virtual_cursor.pointer_position(window).map(|cursor| (window, cursor)));
+	let PreviewParent { previewed, start_position, current_position } = *preview;
+	let cost = build_cost(previewed, start_position, current_position, &placement, &balance, &registry);
+	let dragging =
+		mouse.pressed(MouseButton::Left) && matches!(previewed.build_mode(), BuildMode::Line | BuildMode::Rect);
+	let Some((window, cursor_position)) =
+		cursor_position.filter(|_| *preview_visibility != Visibility::Hidden && (dragging || cost > 0))
+	else {
+		visibility.set_if_neq(Visibility::Hidden);
+		return;
+	};
+	visibility.set_if_neq(Visibility::Visible);
+	node.bottom = Val::Px(-cursor_position.y + window.height() + 10.);
+	node.left = Val::Px(cursor_position.x + 10.);
+
+	let tiles = placement.registry.footprint(previewed, start_position, current_position).len();
+	let mut summary = if tiles == 1 { "1 tile".to_string() } else { format!("{tiles} tiles") };
+	if cost > 0 {
+		summary += &format!("\nCost: {cost}");
+	}
+	let check = placement.check(previewed, start_position, current_position);
+	if let Err(why) = &check {
+		summary += &format!("\n{why}");
+	}
+	if text.0 != summary {
+		text.0 = summary;
+	}
+	let new_color = if check.is_ok() { WHITE.into() } else { INVALID_PREVIEW_TINT.with_alpha(1.) };
+	if color.0 != new_color {
+		color.0 = new_color;
+	}
+}
+
+/// What performing the build would cost, as charged by the build systems.
+fn build_cost(
+	buildable: Buildable,
+	start_position: GridPosition,
+	end_position: GridPosition,
+	placement: &Placement,
+	balance: &Balance,
+	registry: &BuildableRegistry,
+) -> u64 {
+	match buildable {
+		Buildable::PitchType(kind) =>
+			placement.empty_pitches.iter().find(|(area, _)| area.contains(&start_position)).map_or(0, |(_, pitch)| {
+				balance.build_cost(&Pitch { kind: Some(kind), multiplicity: pitch.multiplicity, ..Default::default() })
+			}),
+		_ =>
+			registry.definition(buildable).cost
+				* registry.footprint(buildable, start_position, end_position).len() as u64,
+	}
+}
+
+fn ground_speed(kind: GroundKind) -> Option<u32> {
+	(kind.navigability() != NavCategory::None).then_some(kind.traversal_speed())
+}
+
 fn create_building_preview(
 	mut commands: Commands,
-	current_preview: Query<Entity, With<PreviewParent>>,
+	current_preview: Query<Entity, Or<(With<PreviewParent>, With<BuildSummary>)>>,
 	mut events: EventReader<StartBuildPreview>,
+	asset_server: Res<AssetServer>,
 ) {
 	if !events.is_empty() {
 		for old_preview in &current_preview {
@@ -296,84 +493,130 @@ fn create_building_preview(
 			InheritedVisibility::default(),
 			ViewVisibility::default(),
 		));
+		commands.spawn((
+			BuildSummary,
+			Node { position_type: PositionType::Absolute, padding: UiRect::all(Val::Px(5.)), ..default() },
+			BackgroundColor(DARK_GRAY.with_alpha(0.8).into()),
+			Text::default(),
+			TextFont {
+				font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+				font_size: 16.,
+				..default()
+			},
+			TextColor(WHITE.into()),
+			Visibility::Hidden,
+		));
 	}
 }
 
 fn perform_ground_build(
-	mut event: EventReader<PerformBuild<{ BuildableType::Ground }>>,
+	mut event: EventReader<PerformBuild<{ BuildableType::Ground.param() }>>,
 	mut commands: Commands,
 	asset_server: Res<AssetServer>,
 	mut ground_map: ResMut<GroundMap>,
 	mut tile_query: Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
 	mut area_update_event: EventWriter<UpdateAreas>,
+	mut builds: BuildRecorder,
 ) {
 	for event in event.read() {
 		let kind = match event.buildable {
 			Buildable::Ground(kind) => kind,
 			_ => unreachable!(),
 		};
-		for line_element in event.start_position.line_to_2d(event.end_position) {
-			ground_map.set(line_element, kind, &mut tile_query, &mut commands, &asset_server);
-		}
+		let changes = set_ground(
+			event.start_position.line_to_2d(event.end_position),
+			kind,
+			&mut ground_map,
+			&mut tile_query,
+			&mut commands,
+			&asset_server,
+		);
 		// Either we or the tiles we overwrote might be part of areas.
-		area_update_event.send_default();
+		if let Some(update) = UpdateAreas::around(changes.iter().map(|change| change.position)) {
+			area_update_event.send(update);
+		}
+		builds.finish(event.into(), BuildCommand::Ground(changes));
 	}
 	event.clear();
 }
 
 fn perform_pitch_build(
-	mut event: EventReader<PerformBuild<{ BuildableType::Pitch }>>,
+	mut event: EventReader<PerformBuild<{ BuildableType::Pitch.param() }>>,
 	mut commands: Commands,
 	asset_server: Res<AssetServer>,
 	mut ground_map: ResMut<GroundMap>,
 	mut tile_query: Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
 	mut area_update_event: EventWriter<UpdateAreas>,
+	mut builds: BuildRecorder,
 ) {
 	for event in event.read() {
-		ground_map.fill_rect(
-			event.start_position,
-			event.end_position,
+		let changes = set_ground(
+			Area::from_rect(event.start_position, event.end_position).tiles_iter(),
 			GroundKind::Pitch,
+			&mut ground_map,
 			&mut tile_query,
 			&mut commands,
 			&asset_server,
 		);
 		commands.spawn(AccommodationBundle::new(event.start_position, event.end_position));
-		area_update_event.send_default();
+		builds.finish(
+			event.into(),
+			BuildCommand::Batch(vec![BuildCommand::Ground(changes), BuildCommand::Area {
+				buildable:      event.buildable,
+				start_position: event.start_position,
+				end_position:   event.end_position,
+				created:        true,
+			}]),
+		);
+		area_update_event.send(UpdateAreas::rect(event.start_position, event.end_position));
 	}
 	event.clear();
 }
 
 fn perform_pool_area_build(
-	mut event: EventReader<PerformBuild<{ BuildableType::PoolArea }>>,
+	mut event: EventReader<PerformBuild<{ BuildableType::PoolArea.param() }>>,
 	mut commands: Commands,
 	asset_server: Res<AssetServer>,
 	mut ground_map: ResMut<GroundMap>,
 	mut tile_query: Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
 	mut area_update_event: EventWriter<UpdateAreas>,
+	mut builds: BuildRecorder,
 ) {
 	for event in event.read() {
-		ground_map.fill_rect(
-			event.start_position,
-			event.end_position,
+		let changes = set_ground(
+			Area::from_rect(event.start_position, event.end_position).tiles_iter(),
 			GroundKind::PoolPath,
+			&mut ground_map,
 			&mut tile_query,
 			&mut commands,
 			&asset_server,
 		);
 		commands.spawn((Area::from_rect(event.start_position, event.end_position), Pool));
-		area_update_event.send_default();
+		builds.finish(
+			event.into(),
+			BuildCommand::Batch(vec![BuildCommand::Ground(changes), BuildCommand::Area {
+				buildable:      event.buildable,
+				start_position: event.start_position,
+				end_position:   event.end_position,
+				created:        true,
+			}]),
+		);
+		area_update_event.send(UpdateAreas::rect(event.start_position, event.end_position));
 	}
 	event.clear();
 }
 
 fn perform_pitch_type_build(
-	mut event: EventReader<PerformBuild<{ BuildableType::PitchType }>>,
+	mut event: EventReader<PerformBuild<{ BuildableType::PitchType.param() }>>,
 	mut commands: Commands,
 	asset_server: Res<AssetServer>,
 	mut pitches: Query<(Entity, &Area, &mut Pitch)>,
+	settings: Res<GameSettings>,
 	mut build_error: EventWriter<ErrorBox>,
 	mut area_update_event: EventWriter<UpdateAreas>,
+	mut builds: BuildRecorder,
+	balance: Res<Balance>,
+	registry: Res<BuildableRegistry>,
 ) {
 	for event in event.read() {
 		let kind = match event.buildable {
@@ -389,45 +632,768 @@ fn perform_pitch_type_build(
 			}
 		});
 
-		if pitch.get().is_none() {
-			build_error.send(BuildError::NoAccommodationHere.into());
-			return;
+		// The placement rules were checked when the build was requested, but the pitch may have been assigned by
+		// another build in the meantime.
+		let Some((pitch_entity, area, pitch)) = pitch.get_mut() else {
+			build_error.send(PlacementError::NoAccommodationHere.into());
+			continue;
+		};
+
+		let building = registry.grid_box_at(event.buildable, start_position);
+		assign_pitch_type(*pitch_entity, area, pitch, kind, building, &settings, &asset_server, &mut commands);
+		builds.finish_with_cost(event.into(), balance.build_cost(pitch), BuildCommand::PitchType {
+			position: start_position,
+			kind,
+			assigned: true,
+		});
+		area_update_event.send(UpdateAreas::rect(start_position, start_position));
+	}
+	event.clear();
+}
+
+/// Finalizes the pitch with the pitch type, placing its building on the tiles.
+fn assign_pitch_type(
+	pitch_entity: Entity,
+	area: &Area,
+	pitch: &mut Pitch,
+	kind: PitchType,
+	building: GridBox,
+	settings: &GameSettings,
+	asset_server: &AssetServer,
+	commands: &mut Commands,
+) {
+	pitch.kind = Some(kind);
+	if let Some(bundle) = AccommodationBuildingBundle::new(kind, building, asset_server) {
+		commands.entity(pitch_entity).with_children(|parent| {
+			let mut building = parent.spawn((bundle, BuildPop::default()));
+			if !settings.reduced_effects {
+				// Slightly in front of the building, so the dust isn't hidden behind it.
+				building.with_child((build_dust(), Transform::from_xyz(0., 0., 0.5)));
+			}
+		});
+	}
+
+	commands.entity(pitch_entity).remove::<Area>().insert(ImmutableArea(area.clone()));
+}
+
+/// Builds fences or gates along the edge path. Fences and gates replace each other where they overlap, and so do fences
+/// of different styles.
+fn perform_fence_build(
+	mut event: EventReader<PerformBuild<{ BuildableType::Fence.param() }>>,
+	mut fences: Query<(Entity, &GridPosition, &mut Fence)>,
+	mut gates: Query<(Entity, &GridPosition, &mut Gate)>,
+	mut commands: Commands,
+	mut builds: BuildRecorder,
+) {
+	for event in event.read() {
+		let mut new_sides = HashMap::<GridPosition, Sides>::new();
+		for (position, side) in edge_path(event.start_position, event.end_position) {
+			*new_sides.entry(position).or_insert(side) |= side;
 		}
-		let (pitch_entity, area, pitch) = pitch.get_mut().unwrap();
-		let pitch_box = GridBox::around(start_position, kind.size().flat());
-		if !area.fits(&pitch_box) {
-			build_error.send(BuildError::NoSpace.into());
-			return;
+		let command = if let Buildable::Fence(style) = event.buildable {
+			let replaced_gates = replaced_edge_objects(&new_sides, &gates, |_| true);
+			let replaced_fences = replaced_edge_objects(&new_sides, &fences, |fence| fence.style != style);
+			let removed_gates = remove_edge_objects(&replaced_gates, &mut gates, &mut commands);
+			let removed_fences = remove_edge_objects(&replaced_fences, &mut fences, &mut commands);
+			let new_fences = new_sides.into_iter().map(|(position, sides)| (position, Fence { sides, style }));
+			let added = add_edge_objects(new_fences, &mut fences, &mut commands);
+			BuildCommand::Batch(vec![
+				BuildCommand::Gates { added: Vec::new(), removed: removed_gates },
+				BuildCommand::Fences { added, removed: removed_fences },
+			])
+		} else {
+			let replaced = replaced_edge_objects(&new_sides, &fences, |_| true);
+			let removed = remove_edge_objects(&replaced, &mut fences, &mut commands);
+			let new_gates = new_sides.into_iter().map(|(position, sides)| (position, Gate { sides }));
+			let added = add_edge_objects(new_gates, &mut gates, &mut commands);
+			BuildCommand::Batch(vec![BuildCommand::Fences { added: Vec::new(), removed }, BuildCommand::Gates {
+				added,
+				removed: Vec::new(),
+			}])
+		};
+		builds.finish(event.into(), command);
+	}
+	event.clear();
+}
+
+fn perform_utility_build(
+	mut event: EventReader<PerformBuild<{ BuildableType::Utility.param() }>>,
+	parts: Query<(Entity, &GridPosition, &UtilityPart)>,
+	registry: Res<BuildableRegistry>,
+	mut commands: Commands,
+	mut builds: BuildRecorder,
+) {
+	for event in event.read() {
+		let Some(part) = UtilityPart::from_buildable(event.buildable) else { unreachable!() };
+		let new_parts = registry
+			.footprint(event.buildable, event.start_position, event.end_position)
+			.into_iter()
+			.map(|position| (position, part));
+		let added = add_utility_parts(new_parts, &parts, &mut commands);
+		builds.finish(event.into(), BuildCommand::Utilities { added, removed: Vec::new() });
+	}
+	event.clear();
+}
+
+fn perform_decoration_build(
+	mut event: EventReader<PerformBuild<{ BuildableType::Decoration.param() }>>,
+	registry: Res<BuildableRegistry>,
+	mut commands: Commands,
+	mut builds: BuildRecorder,
+) {
+	for event in event.read() {
+		let Buildable::Decoration(decoration) = event.buildable else { unreachable!() };
+		let grid_box = registry.grid_box_at(event.buildable, event.start_position);
+		start_construction(
+			event.buildable,
+			grid_box,
+			registry.definition(event.buildable).construction_time(),
+			&mut commands,
+		);
+		builds.finish(event.into(), BuildCommand::Decorations {
+			added:   vec![(grid_box, decoration)],
+			removed: Vec::new(),
+		});
+	}
+	event.clear();
+}
+
+fn perform_facility_build(
+	mut event: EventReader<PerformBuild<{ BuildableType::Facility.param() }>>,
+	registry: Res<BuildableRegistry>,
+	mut commands: Commands,
+	mut builds: BuildRecorder,
+) {
+	for event in event.read() {
+		let Buildable::Facility(facility) = event.buildable else { unreachable!() };
+		let grid_box = registry.grid_box_at(event.buildable, event.start_position);
+		start_construction(
+			event.buildable,
+			grid_box,
+			registry.definition(event.buildable).construction_time(),
+			&mut commands,
+		);
+		builds.finish(event.into(), BuildCommand::Facilities {
+			added:   vec![(grid_box, facility)],
+			removed: Vec::new(),
+		});
+	}
+	event.clear();
+}
+
+fn perform_road_build(
+	mut event: EventReader<PerformBuild<{ BuildableType::Road.param() }>>,
+	ground_map: Res<GroundMap>,
+	mut commands: Commands,
+	mut builds: BuildRecorder,
+) {
+	for event in event.read() {
+		// The placement rules ensure that there is a pathway next to the road.
+		let Some(gate) = gate_for(event.start_position, &ground_map) else { unreachable!() };
+		let road = EntranceRoad { gate };
+		spawn_entrance_road(event.start_position, road, &mut commands);
+		builds.finish(event.into(), BuildCommand::Roads {
+			added:   vec![(event.start_position, road)],
+			removed: Vec::new(),
+		});
+	}
+	event.clear();
+}
+
+/// Sets the ground of all positions to the kind, and returns the changes for the [`BuildHistory`].
+pub(crate) fn set_ground(
+	positions: impl IntoIterator<Item = GridPosition>,
+	kind: GroundKind,
+	ground_map: &mut GroundMap,
+	tile_query: &mut Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
+	commands: &mut Commands,
+	asset_server: &AssetServer,
+) -> Vec<GroundChange> {
+	positions
+		.into_iter()
+		.filter_map(|position| {
+			let before = ground_map.kind_of(&position);
+			ground_map.set(position, kind, tile_query, commands, asset_server);
+			(before != Some(kind)).then_some(GroundChange { position, before, after: Some(kind) })
+		})
+		.collect()
+}
+
+/// Adds edge objects to tiles, merging them into alike objects on the same tile and spawning objects where there are
+/// none yet. Returns the sides that weren't occupied before.
+fn add_edge_objects<T: EdgeObject + Copy>(
+	new_objects: impl IntoIterator<Item = (GridPosition, T)>,
+	objects: &mut Query<(Entity, &GridPosition, &mut T)>,
+	commands: &mut Commands,
+) -> Vec<(GridPosition, T)> {
+	let mut new_objects = new_objects.into_iter().collect::<Vec<_>>();
+	let mut added = Vec::new();
+	for (_, position, mut object) in objects.iter_mut() {
+		let Some(index) =
+			new_objects.iter().position(|(new_position, new)| new_position == position && new.is_alike(&object))
+		else {
+			continue;
+		};
+		let (_, new) = new_objects.swap_remove(index);
+		let new_sides = new.sides() ^ (new.sides() & object.sides());
+		if new_sides.iter().next().is_some() {
+			added.push((*position, new.with_sides(new_sides)));
+			let all_sides = object.sides() | new.sides();
+			object.set_sides(all_sides);
+		}
+	}
+	for (position, new) in new_objects {
+		commands.spawn((new, position, Save));
+		added.push((position, new));
+	}
+	added
+}
+
+/// The parts of existing objects that lie on the new sides and satisfy the predicate, which are replaced by building on
+/// those sides.
+fn replaced_edge_objects<T: EdgeObject + Copy>(
+	new_sides: &HashMap<GridPosition, Sides>,
+	objects: &Query<(Entity, &GridPosition, &mut T)>,
+	is_replaced: impl Fn(&T) -> bool,
+) -> Vec<(GridPosition, T)> {
+	objects
+		.iter()
+		.filter(|(.., object)| is_replaced(object))
+		.filter_map(|(_, position, object)| Some((*position, object.with_sides(*new_sides.get(position)?))))
+		.collect()
+}
+
+/// Removes edge object sides from alike objects on the tiles, despawning objects that have no sides left. Returns the
+/// sides that were actually occupied.
+fn remove_edge_objects<T: EdgeObject + Copy>(
+	removed: &[(GridPosition, T)],
+	objects: &mut Query<(Entity, &GridPosition, &mut T)>,
+	commands: &mut Commands,
+) -> Vec<(GridPosition, T)> {
+	let mut actually_removed = Vec::new();
+	for (entity, position, mut object) in objects.iter_mut() {
+		let Some((_, removed)) = removed
+			.iter()
+			.find(|(removed_position, removed)| removed_position == position && removed.is_alike(&object))
+		else {
+			continue;
+		};
+		let occupied = object.sides() & removed.sides();
+		if occupied.iter().next().is_none() {
+			continue;
+		}
+		actually_removed.push((*position, object.with_sides(occupied)));
+		let remaining = object.sides() ^ occupied;
+		if remaining.iter().next().is_none() {
+			commands.entity(entity).despawn_recursive();
+		} else {
+			object.set_sides(remaining);
+		}
+	}
+	actually_removed
+}
+
+/// Spawns utility parts where there is no identical part yet. Returns the parts that were spawned.
+fn add_utility_parts(
+	new_parts: impl IntoIterator<Item = (GridPosition, UtilityPart)>,
+	parts: &Query<(Entity, &GridPosition, &UtilityPart)>,
+	commands: &mut Commands,
+) -> Vec<(GridPosition, UtilityPart)> {
+	let mut added = Vec::new();
+	for (position, part) in new_parts {
+		let exists = parts.iter().any(|(_, other_position, other)| *other_position == position && *other == part)
+			|| added.contains(&(position, part));
+		if !exists {
+			spawn_utility_part(part, position, commands);
+			added.push((position, part));
+		}
+	}
+	added
+}
+
+/// Despawns the utility parts.
+fn remove_utility_parts(
+	removed: &[(GridPosition, UtilityPart)],
+	parts: &Query<(Entity, &GridPosition, &UtilityPart)>,
+	commands: &mut Commands,
+) {
+	for (entity, ..) in parts.iter().filter(|(_, position, part)| removed.contains(&(**position, **part))) {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+/// Despawns the decorations.
+fn remove_decorations(
+	removed: &[(GridBox, Decoration)],
+	decorations: &Query<(Entity, &GridBox, &Decoration)>,
+	commands: &mut Commands,
+) {
+	for (entity, ..) in
+		decorations.iter().filter(|(_, grid_box, decoration)| removed.contains(&(**grid_box, **decoration)))
+	{
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+/// Despawns the facilities.
+fn remove_facilities(
+	removed: &[(GridBox, Facility)],
+	facilities: &Query<(Entity, &GridBox, &Facility)>,
+	commands: &mut Commands,
+) {
+	for (entity, ..) in facilities.iter().filter(|(_, grid_box, facility)| removed.contains(&(**grid_box, **facility)))
+	{
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+/// Despawns the construction sites of the buildables, so that undoing a build also stops its construction.
+fn remove_construction_sites(
+	removed: impl IntoIterator<Item = (GridBox, Buildable)>,
+	sites: &Query<(Entity, &GridBox, &UnderConstruction)>,
+	commands: &mut Commands,
+) {
+	let removed = removed.into_iter().collect::<Vec<_>>();
+	for (entity, ..) in sites.iter().filter(|(_, grid_box, site)| removed.contains(&(**grid_box, site.buildable))) {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+/// A tile whose ground kind was changed by a build action.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GroundChange {
+	pub position: GridPosition,
+	/// [`None`] if there was no tile.
+	pub before:   Option<GroundKind>,
+	/// [`None`] if the tile was removed.
+	pub after:    Option<GroundKind>,
+}
+
+/// An invertible record of a build action, as stored in the [`BuildHistory`].
+#[derive(Clone, Debug)]
+pub(crate) enum BuildCommand {
+	/// Tiles changed their ground kind.
+	Ground(Vec<GroundChange>),
+	/// A pitch or pool area was created on the rectangle. Since areas follow their ground, removing the area needs no
+	/// work besides reverting the ground, but a new area has to be spawned explicitly.
+	Area { buildable: Buildable, start_position: GridPosition, end_position: GridPosition, created: bool },
+	/// A pitch type was assigned to, or removed from, the pitch at the position.
+	PitchType { position: GridPosition, kind: PitchType, assigned: bool },
+	/// Fence sides were added to and removed from tiles.
+	Fences { added: Vec<(GridPosition, Fence)>, removed: Vec<(GridPosition, Fence)> },
+	/// Gate sides were added to and removed from tiles.
+	Gates { added: Vec<(GridPosition, Gate)>, removed: Vec<(GridPosition, Gate)> },
+	/// Utility parts were added and removed.
+	Utilities { added: Vec<(GridPosition, UtilityPart)>, removed: Vec<(GridPosition, UtilityPart)> },
+	/// Decorations were added and removed.
+	Decorations { added: Vec<(GridBox, Decoration)>, removed: Vec<(GridBox, Decoration)> },
+	/// Facilities were added and removed.
+	Facilities { added: Vec<(GridBox, Facility)>, removed: Vec<(GridBox, Facility)> },
+	/// Entrance roads were added to and removed from tiles.
+	Roads { added: Vec<(GridPosition, EntranceRoad)>, removed: Vec<(GridPosition, EntranceRoad)> },
+	/// Several commands that make up one action.
+	Batch(Vec<BuildCommand>),
+}
+
+impl BuildCommand {
+	/// The command that reverts this command.
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Ground(changes) => Self::Ground(
+				changes
+					.iter()
+					.map(|change| GroundChange {
+						position: change.position,
+						before:   change.after,
+						after:    change.before,
+					})
+					.collect(),
+			),
+			Self::Area { buildable, start_position, end_position, created } => Self::Area {
+				buildable:      *buildable,
+				start_position: *start_position,
+				end_position:   *end_position,
+				created:        !created,
+			},
+			Self::PitchType { position, kind, assigned } =>
+				Self::PitchType { position: *position, kind: *kind, assigned: !assigned },
+			Self::Fences { added, removed } => Self::Fences { added: removed.clone(), removed: added.clone() },
+			Self::Gates { added, removed } => Self::Gates { added: removed.clone(), removed: added.clone() },
+			Self::Utilities { added, removed } => Self::Utilities { added: removed.clone(), removed: added.clone() },
+			Self::Decorations { added, removed } =>
+				Self::Decorations { added: removed.clone(), removed: added.clone() },
+			Self::Facilities { added, removed } =>
+				Self::Facilities { added: removed.clone(), removed: added.clone() },
+			Self::Roads { added, removed } => Self::Roads { added: removed.clone(), removed: added.clone() },
+			Self::Batch(commands) => Self::Batch(commands.iter().rev().map(Self::inverse).collect()),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		match self {
+			Self::Ground(changes) => changes.is_empty(),
+			Self::Area { .. } | Self::PitchType { .. } => false,
+			Self::Fences { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Gates { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Utilities { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Decorations { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Facilities { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Roads { added, removed } => added.is_empty() && removed.is_empty(),
+			Self::Batch(commands) => commands.iter().all(Self::is_empty),
 		}
-		if area.size() < kind.required_area() {
-			build_error.send(BuildError::PitchTooSmall { required: kind.required_area(), actual: area.size() }.into());
+	}
+}
+
+/// Maximum number of actions that can be undone.
+const MAX_HISTORY_LENGTH: usize = 100;
+
+/// Undo and redo stacks of build actions. Every build and demolish records a [`BuildCommand`]; Ctrl+Z reverts the most
+/// recent one, and Ctrl+Y or Ctrl+Shift+Z reapplies it. Indirect consequences of an action, such as a pitch losing
+/// its type because its ground was rebuilt, are not part of the command and therefore aren't restored. Undoing a build
+/// also removes its construction site, while redone buildings are finished right away. Undoing a build refunds what it
+/// cost, and redoing it charges the cost again, as long as the build still satisfies its placement rules. Likewise,
+/// undoing a demolition takes back what it refunded.
+#[derive(Resource, Default)]
+pub(crate) struct BuildHistory {
+	undo: Vec<RecordedAction>,
+	redo: Vec<RecordedAction>,
+}
+
+/// A build or demolish action in the [`BuildHistory`].
+#[derive(Clone, Debug)]
+struct RecordedAction {
+	command: BuildCommand,
+	/// The build that performed the command, which must still be allowed when it is redone. [`None`] for demolish
+	/// actions.
+	build:   Option<BuildCompleted>,
+	/// What the action cost.
+	cost:    u64,
+	/// What the action refunded, see [`super::demolish`].
+	refund:  u64,
+}
+
+impl BuildHistory {
+	/// Records a newly performed demolish action, which refunded the given amount. Since the actions that can be redone
+	/// were based on the previous state of the world, they are discarded.
+	pub fn record(&mut self, command: BuildCommand, refund: u64) {
+		self.push(RecordedAction { command, build: None, cost: 0, refund });
+	}
+
+	fn push(&mut self, action: RecordedAction) {
+		if action.command.is_empty() {
 			return;
 		}
+		self.redo.clear();
+		self.undo.push(action);
+		if self.undo.len() > MAX_HISTORY_LENGTH {
+			self.undo.remove(0);
+		}
+	}
+}
 
-		pitch.kind = Some(kind);
-		if let Some(bundle) = AccommodationBuildingBundle::new(kind, start_position, &asset_server) {
-			commands.entity(*pitch_entity).with_children(|parent| {
-				parent.spawn(bundle);
-			});
+/// Finishes performed builds by charging their cost, recording them in the [`BuildHistory`] and sending
+/// [`BuildCompleted`].
+#[derive(SystemParam)]
+struct BuildRecorder<'w> {
+	registry:  Res<'w, BuildableRegistry>,
+	tally:     ResMut<'w, DailyTally>,
+	history:   ResMut<'w, BuildHistory>,
+	completed: EventWriter<'w, BuildCompleted>,
+}
+
+impl BuildRecorder<'_> {
+	/// Finishes a build that costs the buildable's cost for every tile that it covers.
+	fn finish(&mut self, build: BuildCompleted, command: BuildCommand) {
+		let cost = self.registry.definition(build.buildable).cost
+			* self.registry.footprint(build.buildable, build.start_position, build.end_position).len() as u64;
+		self.finish_with_cost(build, cost, command);
+	}
+
+	/// Finishes a build with a cost of its own, such as a pitch type that is paid per unit of multiplicity.
+	fn finish_with_cost(&mut self, build: BuildCompleted, cost: u64, command: BuildCommand) {
+		self.tally.record_expense(cost);
+		self.history.push(RecordedAction { command, build: Some(build), cost, refund: 0 });
+		self.completed.send(build);
+	}
+}
+
+/// Everything that build commands modify, for undoing and redoing them.
+#[derive(SystemParam)]
+struct BuildCommandTarget<'w, 's> {
+	ground_map:         ResMut<'w, GroundMap>,
+	tile_query:
+		Query<'w, 's, (Entity, &'static GridPosition, &'static mut GroundKind, &'static mut WorldInfoProperties)>,
+	fences:             Query<'w, 's, (Entity, &'static GridPosition, &'static mut Fence)>,
+	gates:              Query<'w, 's, (Entity, &'static GridPosition, &'static mut Gate)>,
+	utility_parts:      Query<'w, 's, (Entity, &'static GridPosition, &'static UtilityPart)>,
+	decorations:        Query<'w, 's, (Entity, &'static GridBox, &'static Decoration)>,
+	facilities:         Query<'w, 's, (Entity, &'static GridBox, &'static Facility)>,
+	construction_sites: Query<'w, 's, (Entity, &'static GridBox, &'static UnderConstruction)>,
+	roads:              Query<'w, 's, (Entity, &'static GridBox, &'static EntranceRoad)>,
+	pitches:            Query<'w, 's, (Entity, AnyOf<(&'static Area, &'static ImmutableArea)>, &'static mut Pitch)>,
+	settings:           Res<'w, GameSettings>,
+	asset_server:       Res<'w, AssetServer>,
+	registry:           Res<'w, BuildableRegistry>,
+	commands:           Commands<'w, 's>,
+	area_update_event:  EventWriter<'w, UpdateAreas>,
+}
+
+impl BuildCommandTarget<'_, '_> {
+	fn apply(&mut self, command: &BuildCommand) {
+		match command {
+			BuildCommand::Ground(changes) => {
+				for change in changes {
+					match change.after {
+						Some(kind) => self.ground_map.set(
+							change.position,
+							kind,
+							&mut self.tile_query,
+							&mut self.commands,
+							&self.asset_server,
+						),
+						None => self.ground_map.remove(&change.position, &mut self.commands),
+					}
+				}
+				if let Some(update) = UpdateAreas::around(changes.iter().map(|change| change.position)) {
+					self.area_update_event.send(update);
+				}
+			},
+			BuildCommand::Area { buildable, start_position, end_position, created: true } => {
+				match buildable {
+					Buildable::Pitch => {
+						self.commands.spawn(AccommodationBundle::new(*start_position, *end_position));
+					},
+					Buildable::PoolArea => {
+						self.commands.spawn((Area::from_rect(*start_position, *end_position), Pool));
+					},
+					_ => {},
+				}
+				self.area_update_event.send(UpdateAreas::rect(*start_position, *end_position));
+			},
+			BuildCommand::Area { created: false, .. } => {},
+			BuildCommand::PitchType { position, kind, assigned: true } => {
+				if let Some((entity, (Some(area), _), mut pitch)) =
+					self.pitches.iter_mut().find(|(_, (area, _), _)| area.is_some_and(|area| area.contains(position)))
+				{
+					assign_pitch_type(
+						entity,
+						area,
+						&mut pitch,
+						*kind,
+						self.registry.grid_box_at(Buildable::PitchType(*kind), *position),
+						&self.settings,
+						&self.asset_server,
+						&mut self.commands,
+					);
+				}
+			},
+			BuildCommand::PitchType { position, assigned: false, .. } => {
+				if let Some((entity, (_, Some(area)), mut pitch)) =
+					self.pitches.iter_mut().find(|(_, (_, area), _)| area.is_some_and(|area| area.0.contains(position)))
+				{
+					// Same as when the pitch loses its type because its area changed.
+					pitch.kind = None;
+					pitch.multiplicity = Default::default();
+					self.commands.entity(entity).remove::<ImmutableArea>().insert(area.0.clone()).despawn_descendants();
+					self.area_update_event.send(UpdateAreas::of(area));
+				}
+			},
+			BuildCommand::Fences { added, removed } => {
+				remove_edge_objects(removed, &mut self.fences, &mut self.commands);
+				add_edge_objects(added.iter().copied(), &mut self.fences, &mut self.commands);
+			},
+			BuildCommand::Gates { added, removed } => {
+				remove_edge_objects(removed, &mut self.gates, &mut self.commands);
+				add_edge_objects(added.iter().copied(), &mut self.gates, &mut self.commands);
+			},
+			BuildCommand::Utilities { added, removed } => {
+				remove_utility_parts(removed, &self.utility_parts, &mut self.commands);
+				add_utility_parts(added.iter().copied(), &self.utility_parts, &mut self.commands);
+			},
+			BuildCommand::Decorations { added, removed } => {
+				remove_decorations(removed, &self.decorations, &mut self.commands);
+				remove_construction_sites(
+					removed.iter().map(|(grid_box, decoration)| (*grid_box, Buildable::Decoration(*decoration))),
+					&self.construction_sites,
+					&mut self.commands,
+				);
+				for (grid_box, decoration) in added {
+					spawn_decoration(*decoration, *grid_box, &mut self.commands);
+				}
+			},
+			BuildCommand::Facilities { added, removed } => {
+				remove_facilities(removed, &self.facilities, &mut self.commands);
+				remove_construction_sites(
+					removed.iter().map(|(grid_box, facility)| (*grid_box, Buildable::Facility(*facility))),
+					&self.construction_sites,
+					&mut self.commands,
+				);
+				for (grid_box, facility) in added {
+					spawn_facility(*facility, *grid_box, &mut self.commands);
+				}
+			},
+			BuildCommand::Roads { added, removed } => {
+				for (entity, ..) in
+					self.roads.iter().filter(|(_, grid_box, road)| removed.contains(&(grid_box.smallest(), **road)))
+				{
+					self.commands.entity(entity).despawn_recursive();
+				}
+				for (position, road) in added {
+					spawn_entrance_road(*position, *road, &mut self.commands);
+				}
+			},
+			BuildCommand::Batch(commands) =>
+				for command in commands {
+					self.apply(command);
+				},
 		}
+	}
+}
 
-		commands.entity(*pitch_entity).remove::<Area>().insert(ImmutableArea((*area).clone()));
-		area_update_event.send_default();
+/// Redoing a build checks its placement rules first, since the world may have changed since it was undone.
+fn undo_redo_builds(
+	actions: ActionInput,
+	mut history: ResMut<BuildHistory>,
+	mut tally: ResMut<DailyTally>,
+	mut world: ParamSet<(BuildRequests, BuildCommandTarget)>,
+) {
+	if actions.just_pressed(Action::Redo) {
+		let Some(action) = history.redo.pop() else {
+			return;
+		};
+		if let Some(build) = action.build {
+			let mut requests = world.p0();
+			if let Err(why) = requests.check(build.buildable, build.start_position, build.end_position) {
+				requests.reject(why);
+				history.redo.push(action);
+				return;
+			}
+		}
+		world.p1().apply(&action.command);
+		tally.record_expense(action.cost);
+		tally.record_refund(action.refund);
+		history.undo.push(action);
+	} else if actions.just_pressed(Action::Undo) {
+		let Some(action) = history.undo.pop() else {
+			return;
+		};
+		world.p1().apply(&action.command.inverse());
+		tally.refund_expense(action.cost);
+		tally.revoke_refund(action.refund);
+		history.redo.push(action);
+	}
+}
+
+/// Reverts a build that was performed but then turned out to be invalid, such as a build that the multiplayer host
+/// rejected. Like undoing it, this refunds its cost, but the build can't be redone.
+#[derive(Event, Clone, Copy, Debug)]
+pub(crate) struct RevertBuild(pub BuildCompleted);
+
+fn revert_builds(
+	mut reverts: EventReader<RevertBuild>,
+	mut history: ResMut<BuildHistory>,
+	mut tally: ResMut<DailyTally>,
+	mut target: BuildCommandTarget,
+) {
+	for RevertBuild(build) in reverts.read() {
+		let Some(index) = history.undo.iter().rposition(|action| action.build.as_ref() == Some(build)) else {
+			warn!(
+				"Couldn’t revert {} from {} to {}, it isn’t recorded",
+				build.buildable, build.start_position, build.end_position
+			);
+			continue;
+		};
+		let action = history.undo.remove(index);
+		target.apply(&action.command.inverse());
+		tally.refund_expense(action.cost);
+	}
+}
+
+/// The history refers to the world before loading, so it can't be applied to the loaded world.
+fn clear_build_history(mut history: ResMut<BuildHistory>) {
+	*history = BuildHistory::default();
+}
+
+/// Requests building any [`Buildable`], dispatching to the build system responsible for its [`BuildableType`]. Builds
+/// that violate a placement rule are rejected with an error.
+#[derive(SystemParam)]
+pub(crate) struct BuildRequests<'w, 's> {
+	placement:  Placement<'w, 's>,
+	errors:     EventWriter<'w, ErrorBox>,
+	ground:     EventWriter<'w, PerformBuild<{ BuildableType::Ground.param() }>>,
+	pool_area:  EventWriter<'w, PerformBuild<{ BuildableType::PoolArea.param() }>>,
+	pitch:      EventWriter<'w, PerformBuild<{ BuildableType::Pitch.param() }>>,
+	pitch_type: EventWriter<'w, PerformBuild<{ BuildableType::PitchType.param() }>>,
+	fence:      EventWriter<'w, PerformBuild<{ BuildableType::Fence.param() }>>,
+	utility:    EventWriter<'w, PerformBuild<{ BuildableType::Utility.param() }>>,
+	decoration: EventWriter<'w, PerformBuild<{ BuildableType::Decoration.param() }>>,
+	facility:   EventWriter<'w, PerformBuild<{ BuildableType::Facility.param() }>>,
+	road:       EventWriter<'w, PerformBuild<{ BuildableType::Road.param() }>>,
+}
+
+impl BuildRequests<'_, '_> {
+	/// Checks whether the build would be allowed, without performing it.
+	pub fn check(
+		&self,
+		buildable: Buildable,
+		start_position: GridPosition,
+		end_position: GridPosition,
+	) -> Result<(), PlacementError> {
+		self.placement.check(buildable, start_position, end_position)
+	}
+
+	/// Rejects a build that can't be performed, showing the error to the player.
+	pub fn reject(&mut self, why: PlacementError) {
+		self.errors.send(why.into());
+	}
+
+	/// Builds the buildable from the start to the end position, as if the player had dragged between them.
+	pub fn send(&mut self, buildable: Buildable, start_position: GridPosition, end_position: GridPosition) {
+		if let Err(why) = self.placement.check(buildable, start_position, end_position) {
+			self.errors.send(why.into());
+			return;
+		}
+		// Transform a "dynamic" PerformBuild instantiation into a static one.
+		match BuildableType::from(buildable) {
+			BuildableType::Ground => {
+				self.ground.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::PoolArea => {
+				self.pool_area.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Pitch => {
+				self.pitch.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::PitchType => {
+				self.pitch_type.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Fence => {
+				self.fence.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Utility => {
+				self.utility.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Decoration => {
+				self.decoration.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Facility => {
+				self.facility.send(PerformBuild { start_position, end_position, buildable });
+			},
+			BuildableType::Road => {
+				self.road.send(PerformBuild { start_position, end_position, buildable });
+			},
+		}
 	}
-	event.clear();
 }
 
 fn handle_build_interactions(
 	mouse: Res<ButtonInput<MouseButton>>,
 	mut state: ResMut<NextState<InputState>>,
 	mut preview: Query<&mut PreviewParent>,
-	all_interacted: Query<&Interaction, (With<Node>, Changed<Interaction>)>,
-	mut pitch_type_build_event: EventWriter<PerformBuild<{ BuildableType::PitchType }>>,
-	mut ground_build_event: EventWriter<PerformBuild<{ BuildableType::Ground }>>,
-	mut pitch_build_event: EventWriter<PerformBuild<{ BuildableType::Pitch }>>,
-	mut pool_build_event: EventWriter<PerformBuild<{ BuildableType::PoolArea }>>,
+	picking: Picking,
+	mut build_requests: BuildRequests,
 ) {
-	let any_ui_active = all_interacted.iter().any(|interaction| interaction != &Interaction::None);
+	let any_ui_active = picking.is_ui_blocked();
 
 	for mut preview_data in &mut preview {
 		// Probably before the user released the mouse from clicking the build button.
@@ -438,37 +1404,7 @@ fn handle_build_interactions(
 
 		if mouse.just_released(MouseButton::Left) {
 			state.set(InputState::Idle);
-			// Transform a "dynamic" PerformBuild instantiation into a static one.
-			match BuildableType::from(preview_data.previewed) {
-				BuildableType::Ground => {
-					ground_build_event.send(PerformBuild {
-						start_position: preview_data.start_position,
-						end_position:   preview_data.current_position,
-						buildable:      preview_data.previewed,
-					});
-				},
-				BuildableType::PoolArea => {
-					pool_build_event.send(PerformBuild {
-						start_position: preview_data.start_position,
-						end_position:   preview_data.current_position,
-						buildable:      preview_data.previewed,
-					});
-				},
-				BuildableType::Pitch => {
-					pitch_build_event.send(PerformBuild {
-						start_position: preview_data.start_position,
-						end_position:   preview_data.current_position,
-						buildable:      preview_data.previewed,
-					});
-				},
-				BuildableType::PitchType => {
-					pitch_type_build_event.send(PerformBuild {
-						start_position: preview_data.start_position,
-						end_position:   preview_data.current_position,
-						buildable:      preview_data.previewed,
-					});
-				},
-			}
+			build_requests.send(preview_data.previewed, preview_data.start_position, preview_data.current_position);
 		}
 		// Keep start and current identical as long as the mouse is not pressed.
 		// This has the effect that it establishes the building's start corner once the user starts clicking.
@@ -478,14 +1414,17 @@ fn handle_build_interactions(
 	}
 }
 
-fn destroy_building_preview(mut commands: Commands, preview: Query<Entity, With<PreviewParent>>) {
+fn destroy_building_preview(
+	mut commands: Commands,
+	preview: Query<Entity, Or<(With<PreviewParent>, With<BuildSummary>)>>,
+) {
 	for entity in &preview {
 		commands.get_entity(entity).unwrap().despawn_recursive();
 	}
 }
 
-fn end_building(keys: Res<ButtonInput<KeyCode>>, mut state: ResMut<NextState<InputState>>) {
-	if keys.just_pressed(KeyCode::Escape) {
+fn end_building(actions: ActionInput, mut state: ResMut<NextState<InputState>>) {
+	if actions.just_pressed(Action::Cancel) {
 		state.set(InputState::Idle);
 	}
 }
@@ -0,0 +1,432 @@
+//! The build menus, from which the player picks what to build.
+//!
+//! All build menus share one panel above the build bar, with a tab for each menu and a search field. Each menu is a
+//! grid of the buildables that the [`BuildableRegistry`] offers in it, which scrolls once it has more rows than fit
+//! into the panel. While the search field has any text,
+//! the panel shows the matching buildables of all menus instead of the open menu. Pressing Enter in the search field
+//! starts building the first match.
+//!
+//! With the keyboard, B focuses the search field and Escape leaves it again. Tab moves through the tabs and the
+//! buildables, and the grid scrolls to keep the focused buildable visible.
+
+use bevy::color::palettes::css::{DARK_GRAY, GRAY, ORANGE};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use super::animate::darken_when_pressed;
+use super::build::StartBuildPreview;
+use super::controls::{
+	all_build_menus, BuildMenu, BuildMenuContainer, CloseBuildMenus, OpenBuildMenu, StartBuildButton, ALL_BUILD_MENUS,
+};
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::input::keyboard::KeyboardFocus;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::buildables::{BuildableDefinition, BuildableRegistry};
+use crate::util::text_input::{TextInput, TextInputSubmitted};
+use crate::util::Tooltip;
+
+pub(super) struct BuildMenuPlugin;
+
+impl Plugin for BuildMenuPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<OpenedBuildMenu>().add_systems(
+			Update,
+			(
+				fill_build_menus,
+				(update_build_menu_state, press_build_menu_tabs, focus_build_menu_search, leave_build_menu_search),
+				build_first_search_match.in_set(SimulationActive),
+				update_build_menu_display,
+				scroll_build_menu,
+				scroll_to_focused_buildable,
+			)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Size of a buildable's button in the grid.
+const ENTRY_SIZE: f32 = 50.;
+/// Space between the grid cells, the same as [`BUTTON_SPACING`].
+const ENTRY_SPACING: f32 = 5.;
+/// Number of grid columns.
+const COLUMNS: u16 = 10;
+/// Number of grid rows that are visible without scrolling.
+const VISIBLE_ROWS: f32 = 3.;
+/// Number of grid columns taken by a blueprint button, which shows the blueprint's name instead of a logo.
+pub(super) const BLUEPRINT_COLUMNS: u16 = 4;
+/// Maximum number of characters in the search field.
+const MAX_SEARCH_LENGTH: usize = 32;
+/// Logical pixels scrolled per line of mouse wheel scrolling.
+const SCROLL_LINE_HEIGHT: f32 = ENTRY_SIZE / 2.;
+
+/// The build menu that is currently open, if any.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct OpenedBuildMenu(Option<BuildMenu>);
+
+/// The panel containing the tabs, the search field and all build menus.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildMenuPanel;
+
+/// A tab that switches to a build menu.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildMenuTab(BuildMenu);
+
+/// The search field, a button that focuses its text input when pressed.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildMenuSearch;
+
+/// The scrolling part of the panel, which contains the build menus.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildMenuScrollArea;
+
+/// Whether the buildable matches the (lowercase) search text by name or description.
+fn matches_search(definition: &BuildableDefinition, search: &str) -> bool {
+	definition.name.to_lowercase().contains(search) || definition.description.to_lowercase().contains(search)
+}
+
+/// The search text that buildables are filtered by, or [`None`] if the player isn't searching.
+fn search_text(input: &TextInput) -> Option<String> {
+	let search = input.value.trim().to_lowercase();
+	(!search.is_empty()).then_some(search)
+}
+
+/// Spawns the build menu panel into the bottom part of the in-game UI grid.
+pub(super) fn spawn_build_menu_panel(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+	let font = |weight, font_size| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..default()
+	};
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	parent
+		.spawn((
+			Node {
+				grid_row: GridPlacement::start(1),
+				display: Display::None,
+				flex_direction: FlexDirection::Column,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::all(BUTTON_SPACING),
+				width: Val::Px(COLUMNS as f32 * (ENTRY_SIZE + ENTRY_SPACING) + ENTRY_SPACING),
+				..default()
+			},
+			BackgroundColor(GRAY.into()),
+			FocusPolicy::Block,
+			Interaction::default(),
+			BuildMenuPanel,
+		))
+		.with_children(|panel| {
+			panel
+				.spawn(Node { column_gap: BUTTON_SPACING, align_items: AlignItems::Center, ..default() })
+				.with_children(|header| {
+					for menu in all_build_menus() {
+						header
+							.spawn((
+								Button,
+								Node { padding: UiRect::horizontal(BUTTON_SPACING), ..default() },
+								background_color,
+								darken_when_pressed(background_color),
+								Tooltip::from(&menu),
+								BuildMenuTab(menu),
+							))
+							.with_child((Text(menu.to_string()), font(FontWeight::Bold, 20.)));
+					}
+					header.spawn((Text("Search:".into()), font(FontWeight::Regular, 20.)));
+					header.spawn((
+						Button,
+						TextInput { max_length: MAX_SEARCH_LENGTH, ..default() },
+						Node { flex_grow: 1., padding: UiRect::horizontal(BUTTON_SPACING), ..default() },
+						BackgroundColor(Color::BLACK),
+						font(FontWeight::Regular, 20.),
+						Tooltip {
+							title: "Search".into(),
+							body:  "Find buildables in all build menus by name or description. Press Enter to build \
+							        the first match, or Escape to stop searching."
+								.into(),
+						},
+						BuildMenuSearch,
+					));
+				});
+			panel
+				.spawn((
+					Node {
+						flex_direction: FlexDirection::Column,
+						row_gap: Val::Px(ENTRY_SPACING),
+						max_height: Val::Px(VISIBLE_ROWS * (ENTRY_SIZE + ENTRY_SPACING) - ENTRY_SPACING),
+						overflow: Overflow::scroll_y(),
+						..default()
+					},
+					Interaction::default(),
+					BuildMenuScrollArea,
+				))
+				.with_children(|scroll_area| {
+					// The buildables are filled in by fill_build_menus.
+					for menu_type in all_build_menus() {
+						scroll_area.spawn((
+							Node {
+								display: Display::None,
+								grid_template_columns: vec![RepeatedGridTrack::px(COLUMNS, ENTRY_SIZE)],
+								grid_auto_rows: vec![GridTrack::px(ENTRY_SIZE)],
+								row_gap: Val::Px(ENTRY_SPACING),
+								column_gap: Val::Px(ENTRY_SPACING),
+								..default()
+							},
+							BuildMenuContainer(menu_type),
+						));
+					}
+				});
+		});
+}
+
+/// Fills the build menus with the buildables from the registry, again whenever the registry changes. The blueprints
+/// menu is filled by the blueprint module instead.
+fn fill_build_menus(
+	registry: Res<BuildableRegistry>,
+	containers: Query<(Entity, Ref<BuildMenuContainer>)>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	for (container, menu) in &containers {
+		if menu.0 == BuildMenu::Blueprints || (!menu.is_added() && !registry.is_changed()) {
+			continue;
+		}
+		commands.entity(container).despawn_descendants().with_children(|build_menu| {
+			for definition in registry.in_menu(menu.0) {
+				build_menu
+					.spawn((
+						Button,
+						Node { justify_content: JustifyContent::Center, align_items: AlignItems::Center, ..default() },
+						background_color,
+						darken_when_pressed(background_color),
+						Tooltip::from(definition),
+						StartBuildButton(definition.buildable),
+					))
+					.with_child((ImageNode { image: asset_server.load(&definition.logo), ..default() }, Node {
+						width: Val::Percent(90.),
+						..default()
+					}));
+			}
+		});
+	}
+}
+
+fn update_build_menu_state(
+	mut opened: ResMut<OpenedBuildMenu>,
+	mut open_menu_event: EventReader<OpenBuildMenu>,
+	mut close_menu_event: EventReader<CloseBuildMenus>,
+	mut search: Query<&mut TextInput, With<BuildMenuSearch>>,
+) {
+	for OpenBuildMenu(kind) in open_menu_event.read() {
+		// Opening the open menu again closes it.
+		opened.0 = if opened.0 == Some(*kind) { None } else { Some(*kind) };
+	}
+	if close_menu_event.read().count() > 0 {
+		opened.0 = None;
+	}
+	if opened.is_changed() && opened.0.is_none() {
+		for mut input in &mut search {
+			input.value.clear();
+			input.focused = false;
+		}
+	}
+}
+
+/// Unlike the build bar buttons, the tabs never close the panel.
+fn press_build_menu_tabs(
+	tabs: Query<(&Interaction, &BuildMenuTab), Changed<Interaction>>,
+	mut opened: ResMut<OpenedBuildMenu>,
+	mut search: Query<&mut TextInput, With<BuildMenuSearch>>,
+) {
+	for (_, BuildMenuTab(menu)) in tabs.iter().filter(|(interaction, _)| **interaction == Interaction::Pressed) {
+		opened.set_if_neq(OpenedBuildMenu(Some(*menu)));
+		// Switching menus ends the search, since it would hide the menu.
+		for mut input in &mut search {
+			input.value.clear();
+			input.focused = false;
+		}
+	}
+}
+
+fn focus_build_menu_search(
+	actions: ActionInput,
+	mouse: Res<ButtonInput<MouseButton>>,
+	fields: Query<&Interaction, (Changed<Interaction>, With<BuildMenuSearch>)>,
+	mut search: Query<&mut TextInput, With<BuildMenuSearch>>,
+	mut opened: ResMut<OpenedBuildMenu>,
+) {
+	let field_pressed = fields.iter().any(|interaction| *interaction == Interaction::Pressed);
+	if !field_pressed && !actions.just_pressed(Action::SearchBuildables) {
+		// Clicking anywhere else leaves the search field, but keeps the search.
+		if mouse.just_pressed(MouseButton::Left) {
+			for mut input in search.iter_mut().filter(|input| input.focused) {
+				input.focused = false;
+			}
+		}
+		return;
+	}
+	if opened.0.is_none() {
+		opened.0 = Some(ALL_BUILD_MENUS[0]);
+	}
+	for mut input in &mut search {
+		input.focused = true;
+	}
+}
+
+/// The focused search field swallows all keys, so Escape has to be read from the raw keyboard events.
+fn leave_build_menu_search(
+	mut keyboard_events: EventReader<KeyboardInput>,
+	mut search: Query<&mut TextInput, With<BuildMenuSearch>>,
+) {
+	let escape_pressed =
+		keyboard_events.read().any(|event| event.state == ButtonState::Pressed && event.logical_key == Key::Escape);
+	if !escape_pressed {
+		return;
+	}
+	for mut input in search.iter_mut().filter(|input| input.focused) {
+		input.value.clear();
+		input.focused = false;
+	}
+}
+
+fn build_first_search_match(
+	mut submitted: EventReader<TextInputSubmitted>,
+	mut search: Query<&mut TextInput, With<BuildMenuSearch>>,
+	opened: Res<OpenedBuildMenu>,
+	registry: Res<BuildableRegistry>,
+	mut start_preview_event: EventWriter<StartBuildPreview>,
+	current_state: Res<State<InputState>>,
+	mut state: ResMut<NextState<InputState>>,
+) {
+	for event in submitted.read() {
+		let Ok(mut input) = search.get_mut(event.entity) else {
+			continue;
+		};
+		input.focused = false;
+		if ![InputState::Building, InputState::Idle].contains(current_state.get()) {
+			continue;
+		}
+		let first_match = match search_text(&input) {
+			Some(search) => registry.iter().find(|definition| matches_search(definition, &search)),
+			None => registry.iter().find(|definition| Some(definition.menu) == opened.0),
+		};
+		if let Some(definition) = first_match {
+			start_preview_event.send(StartBuildPreview { buildable: definition.buildable });
+			state.set(InputState::Building);
+		}
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_build_menu_display(
+	opened: Res<OpenedBuildMenu>,
+	registry: Res<BuildableRegistry>,
+	search: Query<Ref<TextInput>, With<BuildMenuSearch>>,
+	added_buttons: Query<(), Added<StartBuildButton>>,
+	mut panels: Query<&mut Node, With<BuildMenuPanel>>,
+	mut containers: Query<(&BuildMenuContainer, &mut Node), Without<BuildMenuPanel>>,
+	mut buttons: Query<(&StartBuildButton, &mut Node), (Without<BuildMenuPanel>, Without<BuildMenuContainer>)>,
+	tabs: Query<(&BuildMenuTab, &Children)>,
+	mut labels: Query<&mut TextColor>,
+	mut scroll_areas: Query<&mut ScrollPosition, With<BuildMenuScrollArea>>,
+) {
+	let Ok(search) = search.get_single() else {
+		return;
+	};
+	if !opened.is_changed() && !search.is_changed() && added_buttons.is_empty() {
+		return;
+	}
+	let search_text = search_text(&search);
+
+	for mut node in &mut panels {
+		node.display = if opened.0.is_some() { Display::Flex } else { Display::None };
+	}
+	let mut matching_menus = Vec::new();
+	for (StartBuildButton(buildable), mut node) in &mut buttons {
+		let Some(definition) = registry.get(*buildable) else {
+			continue;
+		};
+		let matches = search_text.as_ref().is_none_or(|search| matches_search(definition, search));
+		node.display = if matches { Display::Flex } else { Display::None };
+		if matches {
+			matching_menus.push(definition.menu);
+		}
+	}
+	for (BuildMenuContainer(menu), mut node) in &mut containers {
+		let shown = match search_text {
+			Some(_) => matching_menus.contains(menu),
+			None => opened.0 == Some(*menu),
+		};
+		node.display = if shown { Display::Grid } else { Display::None };
+	}
+	for (BuildMenuTab(menu), children) in &tabs {
+		let color = if search_text.is_none() && opened.0 == Some(*menu) { ORANGE.into() } else { Color::WHITE };
+		let mut labels = labels.iter_many_mut(children);
+		while let Some(mut label) = labels.fetch_next() {
+			label.0 = color;
+		}
+	}
+	for mut scroll_position in &mut scroll_areas {
+		scroll_position.offset_y = 0.;
+	}
+}
+
+/// The UI clamps the scroll position to the content size by itself.
+fn scroll_build_menu(
+	mut scroll_events: EventReader<MouseWheel>,
+	mut scroll_areas: Query<(&Interaction, &mut ScrollPosition), With<BuildMenuScrollArea>>,
+) {
+	let amount = scroll_events
+		.read()
+		.map(|scroll| match scroll.unit {
+			MouseScrollUnit::Line => scroll.y * SCROLL_LINE_HEIGHT,
+			MouseScrollUnit::Pixel => scroll.y,
+		})
+		.sum::<f32>();
+	if amount == 0. {
+		return;
+	}
+	for (_, mut scroll_position) in
+		scroll_areas.iter_mut().filter(|(interaction, _)| **interaction != Interaction::None)
+	{
+		scroll_position.offset_y = (scroll_position.offset_y - amount).max(0.);
+	}
+}
+
+fn scroll_to_focused_buildable(
+	focus: Res<KeyboardFocus>,
+	buttons: Query<(&ComputedNode, &GlobalTransform), With<StartBuildButton>>,
+	mut scroll_areas: Query<(Entity, &ComputedNode, &GlobalTransform, &mut ScrollPosition), With<BuildMenuScrollArea>>,
+	parents: Query<&Parent>,
+) {
+	if !focus.is_changed() {
+		return;
+	}
+	let Some((focused, (button_node, button_transform))) =
+		focus.get().and_then(|focused| Some((focused, buttons.get(focused).ok()?)))
+	else {
+		return;
+	};
+	for (area, area_node, area_transform, mut scroll_position) in &mut scroll_areas {
+		if !parents.iter_ancestors(focused).any(|ancestor| ancestor == area) {
+			continue;
+		}
+		// Node sizes and positions are in physical pixels, but the scroll position is in logical pixels.
+		let button_top = button_transform.translation().y - button_node.size().y / 2.;
+		let button_bottom = button_top + button_node.size().y;
+		let area_top = area_transform.translation().y - area_node.size().y / 2.;
+		let area_bottom = area_top + area_node.size().y;
+		let scale = area_node.inverse_scale_factor();
+		if button_top < area_top {
+			scroll_position.offset_y -= (area_top - button_top) * scale;
+		} else if button_bottom > area_bottom {
+			scroll_position.offset_y += (button_bottom - area_bottom) * scale;
+		}
+	}
+}
@@ -0,0 +1,211 @@
+//! Planning calculator for pitches.
+//!
+//! The panel is toggled with C. The player picks a pitch type and multiplicity, and the panel shows the area that
+//! such a pitch needs, what it costs, the income it can be expected to bring per night and how long it takes to pay
+//! for itself. All numbers come from the [`Balance`], which the simulation uses as well, and the income from the price
+//! that the player set in the [`PitchPricing`].
+
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::prelude::*;
+
+use super::animate::darken_when_pressed;
+use super::controls::InGameUi;
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::balance::Balance;
+use crate::model::demand::PitchPricing;
+use crate::model::pitch::{AccommodationMultiplicity, ALL_PITCH_TYPES};
+use crate::model::PitchType;
+
+pub(super) struct CalculatorPlugin;
+
+impl Plugin for CalculatorPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(toggle_calculator, press_calculator_buttons, update_calculator)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Root of the calculator panel, holding the pitch that is being planned.
+#[derive(Component, Clone, Copy, Debug)]
+pub(super) struct CalculatorPanel {
+	kind:         PitchType,
+	multiplicity: AccommodationMultiplicity,
+}
+
+/// Text showing the results of the calculation.
+#[derive(Component, Clone, Copy, Debug)]
+struct CalculatorResults;
+
+#[derive(Component, Clone, Copy, Debug)]
+enum CalculatorButton {
+	PitchType(PitchType),
+	/// Changes the multiplicity by the given amount.
+	Multiplicity(i64),
+	Close,
+}
+
+fn font(asset_server: &AssetServer, weight: FontWeight) -> TextFont {
+	TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size: 24., ..Default::default() }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: String, button: CalculatorButton, font: TextFont) {
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	parent
+		.spawn((
+			Button,
+			Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+			background_color,
+			darken_when_pressed(background_color),
+			button,
+		))
+		.with_child((Text(label), TextColor(WHITE.into()), font));
+}
+
+fn toggle_calculator(
+	actions: ActionInput,
+	input_state: Res<State<InputState>>,
+	panels: Query<Entity, With<CalculatorPanel>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !panels.is_empty() {
+		if actions.any_just_pressed([Action::Calculator, Action::Cancel]) {
+			for panel in &panels {
+				commands.entity(panel).despawn_recursive();
+			}
+		}
+		return;
+	}
+	if !actions.just_pressed(Action::Calculator) || input_state.get() != &InputState::Idle {
+		return;
+	}
+
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				right: Val::Percent(1.),
+				top: Val::Percent(10.),
+				flex_direction: FlexDirection::Column,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::all(BUTTON_SPACING * 2.),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.85)),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			CalculatorPanel { kind: PitchType::TentPitch, multiplicity: AccommodationMultiplicity::default() },
+			InGameUi,
+		))
+		.with_children(|panel| {
+			panel.spawn((Text("Pitch planner".into()), TextColor(WHITE.into()), TextFont {
+				font_size: 40.,
+				..font(&asset_server, FontWeight::Bold)
+			}));
+			panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+				for kind in ALL_PITCH_TYPES {
+					spawn_button(
+						row,
+						kind.to_string(),
+						CalculatorButton::PitchType(kind),
+						font(&asset_server, FontWeight::Regular),
+					);
+				}
+			});
+			panel
+				.spawn(Node { column_gap: BUTTON_SPACING, align_items: AlignItems::Center, ..Default::default() })
+				.with_children(|row| {
+					row.spawn((
+						Text("Multiplicity".into()),
+						TextColor(WHITE.into()),
+						font(&asset_server, FontWeight::Regular),
+					));
+					spawn_button(
+						row,
+						"-".into(),
+						CalculatorButton::Multiplicity(-1),
+						font(&asset_server, FontWeight::Bold),
+					);
+					spawn_button(
+						row,
+						"+".into(),
+						CalculatorButton::Multiplicity(1),
+						font(&asset_server, FontWeight::Bold),
+					);
+				});
+			panel.spawn((
+				Text::default(),
+				TextColor(WHITE.into()),
+				font(&asset_server, FontWeight::Regular),
+				CalculatorResults,
+			));
+			spawn_button(panel, "Close".into(), CalculatorButton::Close, font(&asset_server, FontWeight::Bold));
+		});
+}
+
+fn press_calculator_buttons(
+	buttons: Query<(&Interaction, &CalculatorButton), Changed<Interaction>>,
+	mut panels: Query<(Entity, &mut CalculatorPanel)>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		for (entity, mut panel) in &mut panels {
+			match button {
+				CalculatorButton::PitchType(kind) => panel.kind = *kind,
+				CalculatorButton::Multiplicity(change) => {
+					// Multiplicities outside of the allowed range are ignored.
+					if let Ok(multiplicity) = (*panel.multiplicity).saturating_add_signed(*change).try_into() {
+						panel.multiplicity = multiplicity;
+					}
+				},
+				CalculatorButton::Close => commands.entity(entity).despawn_recursive(),
+			}
+		}
+	}
+}
+
+fn update_calculator(
+	balance: Res<Balance>,
+	pricing: Res<PitchPricing>,
+	panels: Query<Ref<CalculatorPanel>>,
+	mut results: Query<&mut Text, With<CalculatorResults>>,
+) {
+	let Ok(panel) = panels.get_single() else {
+		return;
+	};
+	if !panel.is_changed() && !balance.is_changed() && !pricing.is_changed() {
+		return;
+	}
+
+	let plan = balance.plan(panel.kind, panel.multiplicity, pricing.nightly_price(panel.kind));
+	let payback = match plan.payback_nights {
+		Some(1) => "1 night".to_string(),
+		Some(nights) => format!("{nights} nights"),
+		None => "never".to_string(),
+	};
+	for mut text in &mut results {
+		text.0 = format!(
+			"{} × {}\nRequired area: {}i²\nBuild cost: {}\nExpected income: {:.0} per night at {:.0}% occupancy\nPays \
+			 for itself after: {}",
+			panel.multiplicity,
+			panel.kind,
+			plan.required_area,
+			plan.build_cost,
+			plan.expected_nightly_income,
+			balance.expected_occupancy * 100.,
+			payback,
+		);
+	}
+}
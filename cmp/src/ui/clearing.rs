@@ -0,0 +1,62 @@
+//! Clearing tool for removing natural obstacles.
+//!
+//! The tool is started with the axe button in the build bar. Dragging selects a rectangle of tiles; on release, the
+//! clearing of all obstacles in it starts, and their clearing cost is charged right away. Obstacles that are already
+//! being cleared aren't charged again. Clearing can't be undone. The tool stays active until Escape or right click.
+
+use bevy::prelude::*;
+
+use super::rect_tool::{start_rect_tool, RectToolPlugin, TileRect};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::input::InputState;
+use crate::model::balance::Balance;
+use crate::model::obstacle::{Clearing, Obstacle};
+use crate::model::rollup::DailyTally;
+use crate::model::GridBox;
+use crate::ui::controls::ClearingButton;
+
+pub(super) struct ClearingPlugin;
+
+impl Plugin for ClearingPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(RectToolPlugin::<PerformClearing>::new(InputState::Clearing, SELECTION_TINT))
+			.add_systems(
+				Update,
+				start_rect_tool::<PerformClearing, ClearingButton>.in_set(GameSet::UI).in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				perform_clearing.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Tint of the tiles selected for clearing.
+const SELECTION_TINT: Color = Color::srgba(1., 0.8, 0.2, 0.6);
+
+/// Requests clearing all obstacles in the rectangle.
+#[derive(Event, Clone, Copy, Debug, Deref)]
+pub struct PerformClearing(pub TileRect);
+
+impl From<TileRect> for PerformClearing {
+	fn from(rect: TileRect) -> Self {
+		Self(rect)
+	}
+}
+
+fn perform_clearing(
+	mut events: EventReader<PerformClearing>,
+	obstacles: Query<(Entity, &GridBox, &Obstacle), Without<Clearing>>,
+	balance: Res<Balance>,
+	mut tally: ResMut<DailyTally>,
+	mut commands: Commands,
+) {
+	for event in events.read() {
+		for (entity, _, obstacle) in
+			obstacles.iter().filter(|(_, grid_box, _)| grid_box.floor_positions().any(|tile| event.contains(&tile)))
+		{
+			tally.record_expense(balance.clearing_cost(*obstacle));
+			commands.entity(entity).insert(Clearing::of(*obstacle));
+		}
+	}
+}
@@ -0,0 +1,219 @@
+//! Demolish tool for removing anything the player has built.
+//!
+//! The tool is started with the bulldozer button in the build bar. Dragging selects a rectangle of tiles; on release,
+//! all tiles in it revert to grass and all fences, utility parts, decorations, facilities, construction sites and
+//! decals on them are removed, as well as entrance roads unless that would remove the last one. Areas, pitch buildings
+//! and borders are cleaned up by the regular area update, which considers only tiles of the area's ground kind. The
+//! tool stays active until Escape or right click.
+//!
+//! Demolishing refunds part of what the demolished builds cost: ground refunds its whole material cost, everything else
+//! [`Balance::demolition_refund`] of its build cost. Refunds are scaled by the [`ModifierTarget::Refunds`] modifiers,
+//! such as those of the difficulty, and recorded as their own category in the [`DailyTally`].
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::rect_tool::{start_rect_tool, RectToolPlugin, TileRect};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::input::InputState;
+use crate::model::area::UpdateAreas;
+use crate::model::balance::Balance;
+use crate::model::buildables::BuildableRegistry;
+use crate::model::construction::UnderConstruction;
+use crate::model::decal::Decal;
+use crate::model::decoration::Decoration;
+use crate::model::facility::Facility;
+use crate::model::fence::{Fence, Gate};
+use crate::model::modifiers::{ModifierTarget, Modifiers};
+use crate::model::road::EntranceRoad;
+use crate::model::rollup::DailyTally;
+use crate::model::utilities::UtilityPart;
+use crate::model::{Buildable, GridBox, GridPosition, GroundKind, GroundMap};
+use crate::ui::build::{set_ground, BuildCommand, BuildHistory};
+use crate::ui::controls::DemolishButton;
+use crate::ui::world_info::WorldInfoProperties;
+
+pub(super) struct DemolishPlugin;
+
+impl Plugin for DemolishPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(RectToolPlugin::<PerformDemolish>::new(InputState::Demolish, SELECTION_TINT))
+			.add_systems(
+				Update,
+				start_rect_tool::<PerformDemolish, DemolishButton>.in_set(GameSet::UI).in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				perform_demolish.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Tint of the tiles selected for demolishing.
+const SELECTION_TINT: Color = Color::srgba(1., 0.3, 0.2, 0.7);
+
+/// Requests demolishing everything in the rectangle.
+#[derive(Event, Clone, Copy, Debug, Deref)]
+pub struct PerformDemolish(pub TileRect);
+
+impl From<TileRect> for PerformDemolish {
+	fn from(rect: TileRect) -> Self {
+		Self(rect)
+	}
+}
+
+fn perform_demolish(
+	mut events: EventReader<PerformDemolish>,
+	mut ground_map: ResMut<GroundMap>,
+	mut tile_query: Query<(Entity, &GridPosition, &mut GroundKind, &mut WorldInfoProperties)>,
+	fences: Query<(Entity, &GridPosition, &Fence)>,
+	gates: Query<(Entity, &GridPosition, &Gate)>,
+	utility_parts: Query<(Entity, &GridPosition, &UtilityPart)>,
+	decorations: Query<(Entity, &GridBox, &Decoration)>,
+	facilities: Query<(Entity, &GridBox, &Facility)>,
+	construction_sites: Query<(Entity, &GridBox), With<UnderConstruction>>,
+	decals: Query<(Entity, &GridPosition), With<Decal>>,
+	roads: Query<(Entity, &GridBox, &EntranceRoad)>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+	mut area_update_event: EventWriter<UpdateAreas>,
+	mut history: ResMut<BuildHistory>,
+	mut refunds: Refunds,
+) {
+	for event in events.read() {
+		// Removing the ground entirely would leave holes in the world, so demolished ground is grass again.
+		let demolished_tiles = ground_map
+			.iter()
+			.filter(|(position, _, kind)| *kind != GroundKind::Grass && event.contains(position))
+			.map(|(position, ..)| position)
+			.collect::<Vec<_>>();
+		let changes = set_ground(
+			demolished_tiles,
+			GroundKind::Grass,
+			&mut ground_map,
+			&mut tile_query,
+			&mut commands,
+			&asset_server,
+		);
+		let mut removed_fences = Vec::new();
+		for (entity, position, fence) in fences.iter().filter(|(_, position, _)| event.contains(position)) {
+			removed_fences.push((*position, *fence));
+			commands.entity(entity).despawn_recursive();
+		}
+		let mut removed_gates = Vec::new();
+		for (entity, position, gate) in gates.iter().filter(|(_, position, _)| event.contains(position)) {
+			removed_gates.push((*position, *gate));
+			commands.entity(entity).despawn_recursive();
+		}
+		let mut removed_utility_parts = Vec::new();
+		for (entity, position, part) in utility_parts.iter().filter(|(_, position, _)| event.contains(position)) {
+			removed_utility_parts.push((*position, *part));
+			commands.entity(entity).despawn_recursive();
+		}
+		let mut removed_decorations = Vec::new();
+		for (entity, grid_box, decoration) in
+			decorations.iter().filter(|(_, grid_box, _)| grid_box.floor_positions().any(|tile| event.contains(&tile)))
+		{
+			removed_decorations.push((*grid_box, *decoration));
+			commands.entity(entity).despawn_recursive();
+		}
+		let mut removed_facilities = Vec::new();
+		for (entity, grid_box, facility) in
+			facilities.iter().filter(|(_, grid_box, _)| grid_box.floor_positions().any(|tile| event.contains(&tile)))
+		{
+			removed_facilities.push((*grid_box, *facility));
+			commands.entity(entity).despawn_recursive();
+		}
+		// The campground always keeps one entrance road, so that visitors can still come.
+		let (removed_roads, kept_roads): (Vec<_>, Vec<_>) =
+			roads.iter().partition(|(_, grid_box, _)| event.contains(&grid_box.smallest()));
+		let removed_roads = if kept_roads.is_empty() { Vec::new() } else { removed_roads };
+		for (entity, ..) in &removed_roads {
+			commands.entity(*entity).despawn_recursive();
+		}
+		// Unfinished buildings are given up and not restored when undoing.
+		for (entity, _) in construction_sites
+			.iter()
+			.filter(|(_, grid_box)| grid_box.floor_positions().any(|tile| event.contains(&tile)))
+		{
+			commands.entity(entity).despawn_recursive();
+		}
+		// Decals are cosmetic and not restored when undoing.
+		for (entity, _) in decals.iter().filter(|(_, position)| event.contains(position)) {
+			commands.entity(entity).despawn_recursive();
+		}
+		let command = BuildCommand::Batch(vec![
+			BuildCommand::Ground(changes),
+			BuildCommand::Fences { added: Vec::new(), removed: removed_fences },
+			BuildCommand::Gates { added: Vec::new(), removed: removed_gates },
+			BuildCommand::Utilities { added: Vec::new(), removed: removed_utility_parts },
+			BuildCommand::Decorations { added: Vec::new(), removed: removed_decorations },
+			BuildCommand::Facilities { added: Vec::new(), removed: removed_facilities },
+			BuildCommand::Roads {
+				added:   Vec::new(),
+				removed: removed_roads.into_iter().map(|(_, grid_box, road)| (grid_box.smallest(), *road)).collect(),
+			},
+		]);
+		let refund = refunds.pay(&command);
+		history.record(command, refund);
+		area_update_event.send(UpdateAreas::rect(event.start_position, event.end_position));
+	}
+}
+
+/// Pays refunds for demolished builds.
+#[derive(SystemParam)]
+struct Refunds<'w> {
+	registry:  Res<'w, BuildableRegistry>,
+	balance:   Res<'w, Balance>,
+	modifiers: Res<'w, Modifiers>,
+	tally:     ResMut<'w, DailyTally>,
+}
+
+impl Refunds<'_> {
+	/// Pays the refund for everything that the command removed, and returns it.
+	fn pay(&mut self, command: &BuildCommand) -> u64 {
+		let refund =
+			refund(command, &self.registry, &self.balance) as f32 * self.modifiers.factor(ModifierTarget::Refunds);
+		self.tally.record_refund(refund as u64);
+		refund as u64
+	}
+}
+
+/// What demolishing refunds for everything that the command removed, before modifiers. Unfinished buildings aren't part
+/// of the command and therefore refund nothing.
+fn refund(command: &BuildCommand, registry: &BuildableRegistry, balance: &Balance) -> u64 {
+	let cost_of = |buildable: Buildable, tiles: usize| registry.definition(buildable).cost * tiles as u64;
+	let building_refund = |cost: u64| (cost as f32 * balance.demolition_refund) as u64;
+	match command {
+		BuildCommand::Ground(changes) => changes
+			.iter()
+			.filter(|change| change.before != change.after)
+			.filter_map(|change| change.before)
+			.map(|kind| cost_of(Buildable::Ground(kind), 1))
+			.sum(),
+		BuildCommand::Fences { removed, .. } => removed
+			.iter()
+			.map(|(_, fence)| building_refund(cost_of(Buildable::Fence(fence.style), fence.sides.iter().count())))
+			.sum(),
+		BuildCommand::Gates { removed, .. } =>
+			removed.iter().map(|(_, gate)| building_refund(cost_of(Buildable::Gate, gate.sides.iter().count()))).sum(),
+		BuildCommand::Roads { removed, .. } =>
+			removed.iter().map(|_| building_refund(cost_of(Buildable::EntranceRoad, 1))).sum(),
+		BuildCommand::Utilities { removed, .. } =>
+			removed.iter().map(|(_, part)| building_refund(cost_of(part.buildable(), 1))).sum(),
+		BuildCommand::Decorations { removed, .. } => removed
+			.iter()
+			.map(|(grid_box, decoration)| {
+				building_refund(cost_of(Buildable::Decoration(*decoration), grid_box.floor_positions().count()))
+			})
+			.sum(),
+		BuildCommand::Facilities { removed, .. } => removed
+			.iter()
+			.map(|(grid_box, facility)| {
+				building_refund(cost_of(Buildable::Facility(*facility), grid_box.floor_positions().count()))
+			})
+			.sum(),
+		BuildCommand::Batch(commands) => commands.iter().map(|command| refund(command, registry, balance)).sum(),
+		BuildCommand::Area { .. } | BuildCommand::PitchType { .. } => 0,
+	}
+}
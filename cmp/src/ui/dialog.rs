@@ -0,0 +1,182 @@
+//! Shared modal dialog API.
+//!
+//! Dialogs are opened by sending an [`OpenDialog`] event. Their contents are built from a small set of
+//! [`DialogWidget`]s instead of arbitrary UI nodes, so that every dialog, whether opened by the game itself or by an
+//! extension, matches the game's style. Only one dialog is shown at a time; a dialog that the player hasn't closed yet
+//! is never replaced. Dialogs requested in the meantime, or before the game is running, are queued and shown in the
+//! order they were requested.
+
+use std::collections::VecDeque;
+
+use bevy::color::palettes::css::{ANTIQUE_WHITE, ORANGE, WHITE};
+use bevy::prelude::*;
+use bevy::text::LineBreak;
+
+use super::controls::{DialogBox, DialogContainer, DialogContents, DialogTitle};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+
+/// A building block of dialog and world info contents.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogWidget {
+	/// A bold heading introducing the following widgets.
+	Heading(String),
+	/// Running text.
+	Paragraph(String),
+	/// A named value, with the name and value in separate columns.
+	Property {
+		/// Name of the property.
+		name:  String,
+		/// Formatted value of the property.
+		value: String,
+	},
+}
+
+/// Visual style of a dialog.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DialogKind {
+	/// Neutral information requested by the player.
+	#[default]
+	Information,
+	/// An error or warning the player needs to be made aware of.
+	Error,
+}
+
+/// Requests opening a modal dialog.
+#[derive(Event, Clone, Debug, Default)]
+pub struct OpenDialog {
+	/// Dialog title.
+	pub title:   String,
+	/// Visual style of the dialog.
+	pub kind:    DialogKind,
+	/// Dialog contents, from top to bottom.
+	pub widgets: Vec<DialogWidget>,
+}
+
+impl OpenDialog {
+	/// Creates an empty informational dialog.
+	pub fn new(title: impl Into<String>) -> Self {
+		Self { title: title.into(), ..Default::default() }
+	}
+
+	/// Creates an empty error dialog.
+	pub fn error(title: impl Into<String>) -> Self {
+		Self { title: title.into(), kind: DialogKind::Error, ..Default::default() }
+	}
+
+	/// Appends a heading.
+	pub fn heading(mut self, text: impl Into<String>) -> Self {
+		self.widgets.push(DialogWidget::Heading(text.into()));
+		self
+	}
+
+	/// Appends a paragraph of text.
+	pub fn paragraph(mut self, text: impl Into<String>) -> Self {
+		self.widgets.push(DialogWidget::Paragraph(text.into()));
+		self
+	}
+
+	/// Appends a named value.
+	pub fn property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+		self.widgets.push(DialogWidget::Property { name: name.into(), value: value.into() });
+		self
+	}
+}
+
+impl DialogWidget {
+	/// Spawns this widget's UI nodes. The widget is laid out for a two-column grid: properties fill one cell per
+	/// column, while all other widgets span both columns.
+	pub fn spawn(
+		&self,
+		parent: &mut ChildBuilder,
+		font_size: f32,
+		asset_server: &AssetServer,
+		marker: impl Bundle + Clone,
+	) {
+		let text = |text: &str, weight, color: Srgba| {
+			(
+				Text(text.to_string()),
+				TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size, ..default() },
+				TextColor(color.into()),
+				TextLayout { linebreak: LineBreak::WordBoundary, ..default() },
+			)
+		};
+		let full_width = Node { grid_column: GridPlacement::span(2), ..default() };
+		match self {
+			Self::Heading(heading) => {
+				parent.spawn((text(heading, FontWeight::Bold, WHITE), full_width, marker));
+			},
+			Self::Paragraph(paragraph) => {
+				parent.spawn((text(paragraph, FontWeight::Regular, WHITE), full_width, marker));
+			},
+			Self::Property { name, value } => {
+				parent.spawn((text(name, FontWeight::Regular, WHITE), marker.clone()));
+				parent.spawn((
+					text(value, FontWeight::Regular, ANTIQUE_WHITE),
+					Node { align_self: AlignSelf::End, ..default() },
+					marker,
+				));
+			},
+		}
+	}
+}
+
+/// Dialogs that were requested, but aren't shown yet.
+#[derive(Resource, Default)]
+pub(super) struct DialogQueue(VecDeque<OpenDialog>);
+
+/// Keeps all dialog requests until they can be shown, since events expire after a couple of frames.
+pub(super) fn queue_dialogs(mut requests: EventReader<OpenDialog>, mut queue: ResMut<DialogQueue>) {
+	queue.0.extend(requests.read().cloned());
+}
+
+pub(super) fn show_dialogs(
+	mut queue: ResMut<DialogQueue>,
+	mut dialog_container: Query<&mut Visibility, With<DialogContainer>>,
+	dialog_box: Query<Entity, With<DialogBox>>,
+	mut dialog_title: Query<(&mut Text, &mut TextColor), With<DialogTitle>>,
+	dialog_contents: Query<Entity, With<DialogContents>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let mut dialog_container = dialog_container.single_mut();
+	// Don't replace a dialog that the player hasn't closed yet.
+	if dialog_container.as_ref() == Visibility::Visible {
+		return;
+	}
+	let Some(request) = queue.0.pop_front() else {
+		return;
+	};
+
+	let (mut title, mut title_color) = dialog_title.single_mut();
+	*title = Text(request.title.clone());
+	*title_color = TextColor(match request.kind {
+		DialogKind::Information => WHITE.into(),
+		DialogKind::Error => ORANGE.into(),
+	});
+
+	dialog_contents.iter().for_each(|entity| commands.entity(entity).despawn_recursive());
+	commands.entity(dialog_box.single()).with_children(|parent| {
+		parent
+			.spawn((
+				Node {
+					grid_column: GridPlacement::span(2),
+					display: Display::Grid,
+					grid_template_columns: vec![
+						RepeatedGridTrack::auto(1),
+						RepeatedGridTrack::fit_content_percent(1, 40.),
+					],
+					row_gap: Val::Px(8.),
+					column_gap: Val::Px(16.),
+					..default()
+				},
+				DialogContents,
+			))
+			.with_children(|contents| {
+				for widget in &request.widgets {
+					widget.spawn(contents, 24., &asset_server, ());
+				}
+			});
+	});
+
+	dialog_container.set_if_neq(Visibility::Visible);
+}
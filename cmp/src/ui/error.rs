@@ -1,9 +1,7 @@
 //! Error display in the UI.
-use bevy::color::palettes::css::{ORANGE, WHITE};
 use bevy::prelude::*;
 
-use super::controls::{DialogBox, DialogContainer, DialogContents, DialogTitle};
-use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use super::dialog::OpenDialog;
 
 /// A kind of error event that can be displayed in the UI.
 pub trait DisplayableError: std::error::Error {
@@ -28,47 +26,9 @@ impl<T: DisplayableError + Send + Sync + 'static> From<T> for ErrorBox {
 	}
 }
 
-pub(super) fn show_errors(
-	mut errors: EventReader<ErrorBox>,
-	mut dialog_container: Query<&mut Visibility, With<DialogContainer>>,
-	dialog_box: Query<Entity, With<DialogBox>>,
-	mut dialog_title: Query<(&mut Text, &mut TextColor), With<DialogTitle>>,
-	mut dialog_contents: Query<Entity, With<DialogContents>>,
-	asset_server: Res<AssetServer>,
-	mut commands: Commands,
-) {
-	let mut dialog_container = dialog_container.single_mut();
-	// Don't show another error while the dialog box is still open.
-	if dialog_container.as_ref() == Visibility::Visible {
-		return;
-	}
-
-	if let Some(ErrorBox(error)) = errors.read().next() {
-		let title = error.name();
-		let text = error.to_string();
-
-		let (mut dialog_title, mut dialog_title_color) = dialog_title.single_mut();
-		let dialog_box = dialog_box.single();
-
-		dialog_contents.iter_mut().for_each(|entity| commands.entity(entity).despawn_recursive());
-
-		*dialog_title = Text(title.into());
-		*dialog_title_color = TextColor(ORANGE.into());
-
-		commands.entity(dialog_box).with_children(|dialog_content_commands| {
-			dialog_content_commands.spawn((
-				Text(text),
-				TextFont {
-					font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
-					font_size: 24.,
-					..Default::default()
-				},
-				TextColor(WHITE.into()),
-				DialogContents,
-			));
-		});
-
-		dialog_container.set_if_neq(Visibility::Visible);
+pub(super) fn show_errors(mut errors: EventReader<ErrorBox>, mut dialogs: EventWriter<OpenDialog>) {
+	for ErrorBox(error) in errors.read() {
+		dialogs.send(OpenDialog::error(error.name()).paragraph(error.to_string()));
 	}
 }
 
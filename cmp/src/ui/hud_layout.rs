@@ -0,0 +1,383 @@
+//! Player-defined positions of the major HUD panels.
+//!
+//! Pressing L starts editing the layout: every [`HudPanel`] gets an outlined handle that can be dragged anywhere in
+//! the window. Panels snap to the window edges and center lines when dragged close to them, and a guide line shows the
+//! edge they snapped to. Positions are stored in the [`HudLayout`] as fractions of the window size, so that they
+//! survive resizing, and are persisted in the game settings. Panels without a stored position stay at their default
+//! place in the UI grid.
+
+use bevy::color::palettes::css::{DARK_GRAY, ORANGE, WHITE};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use super::animate::darken_when_pressed;
+use super::controls::InGameUi;
+use super::BUTTON_SPACING;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::input::{Action, ActionInput, InputState};
+use crate::util::Tooltip;
+
+pub(super) struct HudLayoutPlugin;
+
+impl Plugin for HudLayoutPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<HudLayout>()
+			.add_systems(Startup, load_hud_layout)
+			.add_systems(
+				Update,
+				(load_hud_layout.run_if(resource_changed::<GameSettings>), store_hud_layout, apply_hud_layout)
+					.chain()
+					.in_set(GameSet::UI),
+			)
+			.add_systems(
+				Update,
+				(toggle_layout_editor, drag_hud_panels, reset_hud_layout)
+					.chain()
+					.before(apply_hud_layout)
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnExit(GameState::InGame), close_layout_editor);
+	}
+}
+
+/// Distance in logical pixels within which dragged panels snap to an edge.
+const SNAP_DISTANCE: f32 = 16.;
+/// Thickness of the snapping guide lines in logical pixels.
+const GUIDE_THICKNESS: f32 = 2.;
+
+/// The major panels of the in-game UI, which the player can move.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HudPanel {
+	/// Campground statistics, such as the capacity.
+	Statistics,
+	/// Pause and game speed buttons.
+	GameSpeed,
+	/// Overview map of the campground.
+	Minimap,
+}
+
+impl HudPanel {
+	/// Where the panel is placed in the in-game UI grid when it hasn't been moved.
+	fn default_grid_area(&self) -> (GridPlacement, GridPlacement) {
+		match self {
+			Self::Statistics | Self::GameSpeed => (GridPlacement::start(1), GridPlacement::start(2)),
+			Self::Minimap => (GridPlacement::start(2), GridPlacement::start(2)),
+		}
+	}
+}
+
+/// Position of a moved panel's upper left corner, as a fraction of the window size.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PanelPosition {
+	pub x: f32,
+	pub y: f32,
+}
+
+/// Positions of all panels that the player moved. The in-game UI applies this layout when it is created, and whenever
+/// it changes.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct HudLayout {
+	#[serde(default)]
+	statistics: Option<PanelPosition>,
+	#[serde(default)]
+	game_speed: Option<PanelPosition>,
+	#[serde(default)]
+	minimap:    Option<PanelPosition>,
+}
+
+impl HudLayout {
+	pub fn position(&self, panel: HudPanel) -> Option<PanelPosition> {
+		match panel {
+			HudPanel::Statistics => self.statistics,
+			HudPanel::GameSpeed => self.game_speed,
+			HudPanel::Minimap => self.minimap,
+		}
+	}
+
+	fn position_mut(&mut self, panel: HudPanel) -> &mut Option<PanelPosition> {
+		match panel {
+			HudPanel::Statistics => &mut self.statistics,
+			HudPanel::GameSpeed => &mut self.game_speed,
+			HudPanel::Minimap => &mut self.minimap,
+		}
+	}
+
+	/// Places the panel's node according to this layout.
+	pub fn apply(&self, panel: HudPanel, node: &mut Node) {
+		match self.position(panel) {
+			Some(position) => {
+				// Absolutely positioned grid items would be positioned within their grid area instead of the window.
+				node.grid_row = GridPlacement::default();
+				node.grid_column = GridPlacement::default();
+				node.position_type = PositionType::Absolute;
+				node.left = Val::Percent(position.x * 100.);
+				node.top = Val::Percent(position.y * 100.);
+			},
+			None => {
+				(node.grid_row, node.grid_column) = panel.default_grid_area();
+				node.position_type = PositionType::Relative;
+				node.left = Val::Auto;
+				node.top = Val::Auto;
+			},
+		}
+	}
+}
+
+/// Root of the layout editing overlay.
+#[derive(Component, Clone, Copy, Debug)]
+pub(super) struct LayoutEditor;
+
+/// Drag handle covering a panel while editing the layout. It also keeps the panel's own buttons from being pressed.
+#[derive(Component, Clone, Copy, Debug)]
+struct PanelHandle(HudPanel);
+
+#[derive(Component, Clone, Copy, Debug)]
+struct ResetLayoutButton;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct FinishLayoutButton;
+
+/// Window edges and center lines that panels snap to.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum Guide {
+	Left,
+	Right,
+	Top,
+	Bottom,
+	CenterX,
+	CenterY,
+}
+
+impl Guide {
+	const ALL: [Self; 6] = [Self::Left, Self::Right, Self::Top, Self::Bottom, Self::CenterX, Self::CenterY];
+
+	fn node(&self) -> Node {
+		let full = Val::Percent(100.);
+		let thickness = Val::Px(GUIDE_THICKNESS);
+		let (left, right, top, bottom, width, height) = match self {
+			Self::Left => (Val::Px(0.), Val::Auto, Val::Px(0.), Val::Auto, thickness, full),
+			Self::Right => (Val::Auto, Val::Px(0.), Val::Px(0.), Val::Auto, thickness, full),
+			Self::Top => (Val::Px(0.), Val::Auto, Val::Px(0.), Val::Auto, full, thickness),
+			Self::Bottom => (Val::Px(0.), Val::Auto, Val::Auto, Val::Px(0.), full, thickness),
+			Self::CenterX => (Val::Percent(50.), Val::Auto, Val::Px(0.), Val::Auto, thickness, full),
+			Self::CenterY => (Val::Px(0.), Val::Auto, Val::Percent(50.), Val::Auto, full, thickness),
+		};
+		Node {
+			position_type: PositionType::Absolute,
+			left,
+			right,
+			top,
+			bottom,
+			width,
+			height,
+			display: Display::None,
+			..default()
+		}
+	}
+}
+
+/// Snaps one axis of a panel's upper left corner to the window's edges and center line, returning the snapped
+/// coordinate and the guide that it snapped to.
+fn snap(start: f32, size: f32, window_size: f32, guides: [Guide; 3]) -> (f32, Option<Guide>) {
+	let [start_guide, end_guide, center_guide] = guides;
+	if start.abs() < SNAP_DISTANCE {
+		(0., Some(start_guide))
+	} else if (window_size - start - size).abs() < SNAP_DISTANCE {
+		(window_size - size, Some(end_guide))
+	} else if (start + size / 2. - window_size / 2.).abs() < SNAP_DISTANCE {
+		((window_size - size) / 2., Some(center_guide))
+	} else {
+		(start.clamp(0., (window_size - size).max(0.)), None)
+	}
+}
+
+fn load_hud_layout(settings: Res<GameSettings>, mut layout: ResMut<HudLayout>) {
+	layout.set_if_neq(settings.hud_layout);
+}
+
+fn store_hud_layout(layout: Res<HudLayout>, mut settings: ResMut<GameSettings>) {
+	if layout.is_changed() && settings.hud_layout != *layout {
+		settings.hud_layout = *layout;
+	}
+}
+
+fn apply_hud_layout(
+	layout: Res<HudLayout>,
+	mut panels: Query<(&HudPanel, &mut Node)>,
+	added: Query<(), Added<HudPanel>>,
+) {
+	if !layout.is_changed() && added.is_empty() {
+		return;
+	}
+	for (panel, mut node) in &mut panels {
+		layout.apply(*panel, &mut node);
+	}
+}
+
+fn toggle_layout_editor(
+	actions: ActionInput,
+	input_state: Res<State<InputState>>,
+	finish_buttons: Query<&Interaction, (Changed<Interaction>, With<FinishLayoutButton>)>,
+	editors: Query<Entity, With<LayoutEditor>>,
+	panels: Query<(Entity, &HudPanel)>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let finish_pressed = finish_buttons.iter().any(|interaction| interaction == &Interaction::Pressed);
+	if !editors.is_empty() {
+		if finish_pressed || actions.any_just_pressed([Action::EditLayout, Action::Cancel]) {
+			for editor in &editors {
+				commands.entity(editor).despawn_recursive();
+			}
+		}
+		return;
+	}
+	if !actions.just_pressed(Action::EditLayout) || input_state.get() != &InputState::Idle {
+		return;
+	}
+
+	for (entity, panel) in &panels {
+		commands.entity(entity).with_child((
+			Node {
+				position_type: PositionType::Absolute,
+				width: Val::Percent(100.),
+				height: Val::Percent(100.),
+				border: UiRect::all(Val::Px(GUIDE_THICKNESS)),
+				..default()
+			},
+			BorderColor(ORANGE.into()),
+			BackgroundColor(Color::Srgba(ORANGE).with_alpha(0.2)),
+			GlobalZIndex(10),
+			Interaction::default(),
+			PanelHandle(*panel),
+			LayoutEditor,
+		));
+	}
+
+	let font = |weight| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size: 24.,
+		..default()
+	};
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.),
+				height: Val::Percent(100.),
+				position_type: PositionType::Absolute,
+				flex_direction: FlexDirection::Column,
+				align_items: AlignItems::Center,
+				justify_content: JustifyContent::Center,
+				row_gap: BUTTON_SPACING,
+				..default()
+			},
+			HIGH_RES_LAYERS,
+			LayoutEditor,
+			InGameUi,
+		))
+		.with_children(|parent| {
+			parent.spawn((
+				Text("Drag the highlighted panels to move them. Press L or Escape when you're done.".into()),
+				font(FontWeight::Regular),
+				TextColor(WHITE.into()),
+			));
+			for (label, tooltip) in [
+				("Reset layout", "Move all panels back to their default places."),
+				("Done", "Stop editing the layout. (L)"),
+			] {
+				let mut button = parent.spawn((
+					Button,
+					Node { padding: UiRect::axes(BUTTON_SPACING, BUTTON_SPACING / 2.), ..default() },
+					background_color,
+					darken_when_pressed(background_color),
+					Tooltip { title: label.into(), body: tooltip.into() },
+				));
+				button.with_child((Text(label.into()), font(FontWeight::Bold)));
+				if label == "Done" {
+					button.insert(FinishLayoutButton);
+				} else {
+					button.insert(ResetLayoutButton);
+				}
+			}
+			for guide in Guide::ALL {
+				parent.spawn((guide.node(), BackgroundColor(ORANGE.into()), guide));
+			}
+		});
+}
+
+fn drag_hud_panels(
+	mouse: Res<ButtonInput<MouseButton>>,
+	window: Query<&Window, With<PrimaryWindow>>,
+	handles: Query<(&Interaction, &PanelHandle)>,
+	panels: Query<(&HudPanel, &ComputedNode, &GlobalTransform)>,
+	mut guides: Query<(&mut Node, &Guide)>,
+	mut layout: ResMut<HudLayout>,
+	// The dragged panel and the cursor position relative to its upper left corner.
+	mut dragged: Local<Option<(HudPanel, Vec2)>>,
+) {
+	let (Ok(window), Some(cursor)) = (window.get_single(), window.get_single().ok().and_then(Window::cursor_position))
+	else {
+		return;
+	};
+	let panel_rect = |panel: HudPanel| {
+		panels.iter().find(|(other, ..)| **other == panel).map(|(_, computed, transform)| {
+			let size = computed.size() * computed.inverse_scale_factor();
+			let center = transform.translation().truncate() * computed.inverse_scale_factor();
+			(center - size / 2., size)
+		})
+	};
+
+	if mouse.just_pressed(MouseButton::Left) {
+		if let Some((top_left, panel)) = handles
+			.iter()
+			.find(|(interaction, _)| **interaction == Interaction::Pressed)
+			.and_then(|(_, PanelHandle(panel))| Some((panel_rect(*panel)?.0, *panel)))
+		{
+			*dragged = Some((panel, cursor - top_left));
+		}
+	}
+	let mut snapped_guides = Vec::new();
+	if let Some((panel, grab_offset)) = *dragged {
+		if let Some((_, size)) = panel_rect(panel).filter(|_| mouse.pressed(MouseButton::Left)) {
+			let top_left = cursor - grab_offset;
+			let window_size = window.size();
+			let (x, x_guide) = snap(top_left.x, size.x, window_size.x, [Guide::Left, Guide::Right, Guide::CenterX]);
+			let (y, y_guide) = snap(top_left.y, size.y, window_size.y, [Guide::Top, Guide::Bottom, Guide::CenterY]);
+			snapped_guides.extend(x_guide.into_iter().chain(y_guide));
+			let position = Some(PanelPosition { x: x / window_size.x, y: y / window_size.y });
+			if *layout.bypass_change_detection().position_mut(panel) != position {
+				*layout.position_mut(panel) = position;
+			}
+		} else {
+			*dragged = None;
+		}
+	}
+
+	for (mut node, guide) in &mut guides {
+		let display = if snapped_guides.contains(guide) { Display::Flex } else { Display::None };
+		if node.display != display {
+			node.display = display;
+		}
+	}
+}
+
+fn reset_hud_layout(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<ResetLayoutButton>)>,
+	mut layout: ResMut<HudLayout>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		*layout = HudLayout::default();
+	}
+}
+
+fn close_layout_editor(editors: Query<Entity, With<LayoutEditor>>, mut commands: Commands) {
+	for editor in &editors {
+		commands.entity(editor).despawn_recursive();
+	}
+}
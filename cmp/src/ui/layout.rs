@@ -0,0 +1,114 @@
+//! Responsive layout of the in-game UI.
+//!
+//! The UI is laid out for large windows by default. Below [`COMPACT_WIDTH`] × [`COMPACT_HEIGHT`] logical pixels, the
+//! fixed-size build buttons and dialogs don't fit anymore, so the UI switches to a compact layout: the build bar
+//! collapses into a single button that expands it, and dialogs use the full window width.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::controls::{BuildBarToggle, BuildMenuButton, ClearingButton, CloseBuildMenus, DemolishButton, DialogBox};
+use crate::gamemode::{GameSet, GameState};
+
+/// Windows narrower than this many logical pixels use the compact layout.
+const COMPACT_WIDTH: f32 = 1280.;
+/// Windows lower than this many logical pixels use the compact layout.
+const COMPACT_HEIGHT: f32 = 720.;
+
+pub(super) struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<UILayout>().init_resource::<BuildBarExpanded>().add_systems(
+			Update,
+			(select_layout, toggle_build_bar, apply_layout).chain().in_set(GameSet::UI).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// The layout breakpoint that the UI currently uses.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UILayout {
+	/// All controls are shown at their full size.
+	#[default]
+	Regular,
+	/// Controls are collapsed or stretched to fit small windows.
+	Compact,
+}
+
+impl UILayout {
+	/// The layout for a window of the given logical size.
+	pub fn for_window_size(width: f32, height: f32) -> Self {
+		if width < COMPACT_WIDTH || height < COMPACT_HEIGHT {
+			Self::Compact
+		} else {
+			Self::Regular
+		}
+	}
+}
+
+/// Whether the collapsed build bar of the compact layout has been expanded.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct BuildBarExpanded(bool);
+
+fn select_layout(window: Query<&Window, With<PrimaryWindow>>, mut layout: ResMut<UILayout>) {
+	let Ok(window) = window.get_single() else {
+		return;
+	};
+	layout.set_if_neq(UILayout::for_window_size(window.width(), window.height()));
+}
+
+fn toggle_build_bar(
+	toggle: Query<&Interaction, (Changed<Interaction>, With<BuildBarToggle>)>,
+	mut expanded: ResMut<BuildBarExpanded>,
+	mut close_menus: EventWriter<CloseBuildMenus>,
+) {
+	if toggle.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		expanded.0 = !expanded.0;
+		if !expanded.0 {
+			close_menus.send(CloseBuildMenus);
+		}
+	}
+}
+
+fn apply_layout(
+	layout: Res<UILayout>,
+	mut expanded: ResMut<BuildBarExpanded>,
+	mut build_menu_buttons: Query<
+		&mut Node,
+		(
+			Or<(With<BuildMenuButton>, With<DemolishButton>, With<ClearingButton>)>,
+			Without<BuildBarToggle>,
+			Without<DialogBox>,
+		),
+	>,
+	mut toggle: Query<&mut Node, (With<BuildBarToggle>, Without<DialogBox>)>,
+	mut dialog_box: Query<
+		&mut Node,
+		(With<DialogBox>, Without<BuildMenuButton>, Without<DemolishButton>, Without<ClearingButton>),
+	>,
+	added_toggle: Query<(), Added<BuildBarToggle>>,
+	mut close_menus: EventWriter<CloseBuildMenus>,
+) {
+	// The UI is recreated whenever a game is started, and needs the current layout applied as well.
+	if !layout.is_changed() && !expanded.is_changed() && added_toggle.is_empty() {
+		return;
+	}
+	if layout.is_changed() && *layout == UILayout::Compact {
+		// Start out collapsed after switching, since the player most likely just made the window smaller.
+		expanded.0 = false;
+		close_menus.send(CloseBuildMenus);
+	}
+
+	let compact = *layout == UILayout::Compact;
+	let visible = |is_visible: bool| if is_visible { Display::Flex } else { Display::None };
+	for mut node in &mut build_menu_buttons {
+		node.display = visible(!compact || expanded.0);
+	}
+	for mut node in &mut toggle {
+		node.display = visible(compact);
+	}
+	for mut node in &mut dialog_box {
+		node.grid_column = if compact { GridPlacement::start_span(1, 3) } else { GridPlacement::start(2) };
+	}
+}
@@ -0,0 +1,164 @@
+//! Loading screen that preloads commonly used assets before entering the game.
+//!
+//! Without preloading, sprites and fonts are loaded on demand when they are first displayed, which causes noticeable
+//! hitches and pop-in during the first seconds in-game. The loading screen also waits for worldgen and scenario
+//! loading to place their tiles and entities through [`BurstPlacement`].
+
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::color::palettes::css::{DARK_GRAY, ORANGE, WHITE};
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, preload_fonts, preload_images, FontStyle, FontWeight};
+use crate::graphics::{BorderKind, BorderTextures, InGameCamera, HIGH_RES_LAYERS};
+use crate::model::buildables::BuildableRegistry;
+use crate::model::burst::BurstPlacement;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PreloadedAssets>()
+			.add_systems(
+				OnEnter(GameState::Loading),
+				(start_preload, setup_loading_screen, set_world_camera_active::<false>),
+			)
+			.add_systems(Update, update_preload_progress.in_set(GameSet::UI).in_set(GameState::Loading))
+			.add_systems(
+				OnExit(GameState::Loading),
+				(warm_texture_atlases, destroy_loading_screen, set_world_camera_active::<true>),
+			);
+	}
+}
+
+/// Strong handles to all preloaded assets. These keep the assets loaded for the lifetime of the game, even when nothing
+/// in the world uses them for a while.
+#[derive(Resource, Default)]
+pub struct PreloadedAssets {
+	handles: Vec<UntypedHandle>,
+}
+
+impl PreloadedAssets {
+	/// Fraction of preloaded assets that have finished loading, successfully or not, between 0 and 1.
+	pub fn progress(&self, asset_server: &AssetServer) -> f32 {
+		if self.handles.is_empty() {
+			return 1.;
+		}
+		let finished = self
+			.handles
+			.iter()
+			.filter(|handle| {
+				matches!(
+					asset_server.recursive_dependency_load_state(handle.id()),
+					RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed(_)
+				)
+			})
+			.count();
+		finished as f32 / self.handles.len() as f32
+	}
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBarFill;
+
+fn start_preload(
+	mut preloaded: ResMut<PreloadedAssets>,
+	registry: Res<BuildableRegistry>,
+	asset_server: Res<AssetServer>,
+) {
+	// The game should start with the buildable manifest applied, so it is preloaded as well.
+	preloaded.handles = preload_images()
+		.map(String::from)
+		.chain(registry.iter().flat_map(|definition| [definition.logo.clone(), definition.preview.clone()]))
+		.unique()
+		.map(|path| asset_server.load::<Image>(path).untyped())
+		.chain(preload_fonts().map(|path| asset_server.load::<Font>(path).untyped()))
+		.chain(registry.manifest().map(|manifest| manifest.clone().untyped()))
+		.collect();
+	debug!("preloading {} assets", preloaded.handles.len());
+}
+
+fn update_preload_progress(
+	preloaded: Res<PreloadedAssets>,
+	burst: Res<BurstPlacement>,
+	asset_server: Res<AssetServer>,
+	mut bar_fill: Query<&mut Node, With<LoadingBarFill>>,
+	mut next_state: ResMut<NextState<GameState>>,
+) {
+	let progress = preloaded.progress(&asset_server).min(burst.progress());
+	for mut node in &mut bar_fill {
+		node.width = Val::Percent(progress * 100.);
+	}
+	if progress >= 1. && burst.is_empty() {
+		next_state.set(GameState::InGame);
+	}
+}
+
+/// Creates the texture atlas layouts of area borders, so that the first area doesn't need to create them.
+fn warm_texture_atlases(
+	asset_server: Res<AssetServer>,
+	mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+	mut border_textures: ResMut<BorderTextures>,
+) {
+	border_textures.get(BorderKind::Pitch, &mut texture_atlases, &asset_server);
+}
+
+/// The world is only rendered once loading is done, so that sprites don't pop in one by one.
+fn set_world_camera_active<const ACTIVE: bool>(mut cameras: Query<&mut Camera, With<InGameCamera>>) {
+	for mut camera in &mut cameras {
+		camera.is_active = ACTIVE;
+	}
+}
+
+fn setup_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.),
+				height: Val::Percent(100.),
+				position_type: PositionType::Absolute,
+				flex_direction: FlexDirection::Column,
+				justify_content: JustifyContent::Center,
+				align_items: AlignItems::Center,
+				row_gap: Val::Px(20.),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK),
+			HIGH_RES_LAYERS,
+			LoadingScreen,
+		))
+		.with_children(|parent| {
+			parent.spawn((Text("Loading…".into()), TextColor(WHITE.into()), TextFont {
+				font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
+				font_size: 40.,
+				..Default::default()
+			}));
+			parent
+				.spawn((
+					Node {
+						width: Val::Percent(40.),
+						height: Val::Px(16.),
+						border: UiRect::all(Val::Px(2.)),
+						..Default::default()
+					},
+					BorderColor(DARK_GRAY.into()),
+				))
+				.with_children(|parent| {
+					parent.spawn((
+						Node { width: Val::Percent(0.), height: Val::Percent(100.), ..Default::default() },
+						BackgroundColor(ORANGE.into()),
+						LoadingBarFill,
+					));
+				});
+		});
+}
+
+fn destroy_loading_screen(mut commands: Commands, loading_screens: Query<Entity, With<LoadingScreen>>) {
+	for loading_screen in &loading_screens {
+		commands.entity(loading_screen).despawn_recursive();
+	}
+}
@@ -1,19 +1,85 @@
-use bevy::color::palettes::css::WHITE;
+use bevy::app::AppExit;
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
 use bevy::prelude::*;
 
+use super::animate::darken_when_pressed;
+use super::error::ErrorBox;
+use super::settings::OpenSettings;
 use super::{BUTTON_SPACING, COLUMN_TEMPLATE};
+use crate::gamemode::{GameSet, GameState};
 use crate::graphics::library::{font_for, FontStyle, FontWeight};
 use crate::graphics::HIGH_RES_LAYERS;
+use crate::save::recent::{all_saves, recent_saves};
+use crate::save::LoadSave;
+use crate::util::Tooltip;
 
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(Startup, setup_main_menu);
+		app.add_systems(OnEnter(GameState::MainMenu), (setup_main_menu, show_recent_saves))
+			.add_systems(OnExit(GameState::MainMenu), (destroy_main_menu, hide_recent_saves))
+			.add_systems(
+				Update,
+				(toggle_recent_saves_list, continue_recent_save, press_main_menu_buttons)
+					.in_set(GameSet::UI)
+					.in_set(GameState::MainMenu),
+			);
 	}
 }
 
+/// Root of the main menu's title and buttons.
+#[derive(Component, Clone, Copy, Debug)]
+struct MainMenu;
+
+/// The main menu's buttons.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum MainMenuButton {
+	NewGame,
+	Load,
+	Settings,
+	Quit,
+}
+
+impl MainMenuButton {
+	const ALL: [Self; 4] = [Self::NewGame, Self::Load, Self::Settings, Self::Quit];
+
+	const fn label(&self) -> &'static str {
+		match self {
+			Self::NewGame => "New Game",
+			Self::Load => "Load",
+			Self::Settings => "Settings",
+			Self::Quit => "Quit",
+		}
+	}
+
+	const fn description(&self) -> &'static str {
+		match self {
+			Self::NewGame => "Start a new campground.",
+			Self::Load => "Continue playing a saved campground.",
+			Self::Settings => "Change the game settings.",
+			Self::Quit => "Close the game.",
+		}
+	}
+}
+
+/// Contains one button for each save slot; shown with the load button.
+#[derive(Component, Clone, Copy, Debug)]
+struct LoadList;
+
+/// Button that continues playing a saved campground.
+#[derive(Component, Clone, Debug)]
+struct ContinueButton {
+	save_name: String,
+}
+
 pub fn setup_main_menu(mut commands: Commands, assets: Res<AssetServer>) {
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	let font = |weight, font_size| TextFont {
+		font: assets.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..Default::default()
+	};
 	commands
 		.spawn((
 			Node {
@@ -30,6 +96,7 @@ pub fn setup_main_menu(mut commands: Commands, assets: Res<AssetServer>) {
 				..Default::default()
 			},
 			HIGH_RES_LAYERS,
+			MainMenu,
 		))
 		.with_children(|parent| {
 			parent
@@ -44,16 +111,266 @@ pub fn setup_main_menu(mut commands: Commands, assets: Res<AssetServer>) {
 					TextColor(WHITE.into()),
 				))
 				.with_children(|parent| {
-					parent.spawn((TextSpan("CMP".into()), TextFont {
-						font: assets.load(font_for(FontWeight::Bold, FontStyle::Regular)),
-						font_size: 120.,
+					parent.spawn((TextSpan("CMP".into()), font(FontWeight::Bold, 120.)));
+					parent.spawn((TextSpan("\nThe Camping Madness Project".into()), font(FontWeight::Bold, 40.)));
+				});
+			parent
+				.spawn(Node {
+					grid_row: GridPlacement::start(3),
+					grid_column: GridPlacement::start(2),
+					flex_direction: FlexDirection::Column,
+					align_items: AlignItems::Center,
+					row_gap: BUTTON_SPACING,
+					..Default::default()
+				})
+				.with_children(|parent| {
+					for button in MainMenuButton::ALL {
+						parent
+							.spawn((
+								Button,
+								Node {
+									width: Val::Px(240.),
+									justify_content: JustifyContent::Center,
+									padding: UiRect::all(BUTTON_SPACING),
+									..Default::default()
+								},
+								background_color,
+								darken_when_pressed(background_color),
+								Tooltip { title: button.label().into(), body: button.description().into() },
+								button,
+							))
+							.with_child((
+								Text(button.label().into()),
+								TextColor(WHITE.into()),
+								font(FontWeight::Bold, 32.),
+							));
+					}
+				});
+			parent
+				.spawn((
+					Node {
+						grid_row: GridPlacement::start(4),
+						grid_column: GridPlacement::start(2),
+						display: Display::None,
+						flex_direction: FlexDirection::Column,
+						align_items: AlignItems::Center,
+						row_gap: BUTTON_SPACING,
+						..Default::default()
+					},
+					LoadList,
+				))
+				.with_children(|list| {
+					let saves = all_saves();
+					if saves.is_empty() {
+						list.spawn((
+							Text("No saved campgrounds yet.".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 20.),
+						));
+					}
+					for save in saves {
+						list.spawn((
+							Button,
+							Node { padding: UiRect::all(BUTTON_SPACING), ..Default::default() },
+							background_color,
+							darken_when_pressed(background_color),
+							ContinueButton { save_name: save.save_name.clone() },
+						))
+						.with_child((
+							Text(format!("{} ({}, saved {})", save.campground_name(), save.save_name, save.age())),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 20.),
+						));
+					}
+				});
+		});
+}
+
+fn destroy_main_menu(mut commands: Commands, menus: Query<Entity, With<MainMenu>>) {
+	for menu in &menus {
+		commands.entity(menu).despawn_recursive();
+	}
+}
+
+fn press_main_menu_buttons(
+	buttons: Query<(&Interaction, &MainMenuButton), Changed<Interaction>>,
+	mut load_lists: Query<&mut Node, With<LoadList>>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut open_settings: EventWriter<OpenSettings>,
+	mut exit: EventWriter<AppExit>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		match button {
+			MainMenuButton::NewGame => {
+				commands.remove_resource::<LoadSave>();
+				next_state.set(GameState::Loading);
+			},
+			MainMenuButton::Load =>
+				for mut list in &mut load_lists {
+					list.display = if list.display == Display::None { Display::Flex } else { Display::None };
+				},
+			MainMenuButton::Settings => {
+				open_settings.send_default();
+			},
+			MainMenuButton::Quit => {
+				exit.send(AppExit::Success);
+			},
+		}
+	}
+}
+
+/// Root of the continue card and the list of recent saves.
+#[derive(Component, Clone, Copy, Debug)]
+struct RecentSavesMenu;
+
+/// Button that shows or hides the list of recent saves.
+#[derive(Component, Clone, Copy, Debug)]
+struct RecentSavesToggle;
+
+/// Contains one button for each recent save.
+#[derive(Component, Clone, Copy, Debug)]
+struct RecentSavesList;
+
+/// Shows the name and thumbnail of the most recently saved campground, if there is any, as well as a list of the other
+/// recent saves.
+fn show_recent_saves(mut commands: Commands, assets: Res<AssetServer>, mut images: ResMut<Assets<Image>>) {
+	let recents = recent_saves();
+	let Some(most_recent) = recents.first() else {
+		return;
+	};
+	let thumbnail = most_recent.metadata.thumbnail_image().map(|image| images.add(image));
+	let campground_name = most_recent.campground_name().to_owned();
+	let font = |weight, font_size| TextFont {
+		font: assets.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..Default::default()
+	};
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.),
+				height: Val::Percent(100.),
+				position_type: PositionType::Absolute,
+				flex_direction: FlexDirection::Column,
+				justify_content: JustifyContent::End,
+				align_items: AlignItems::Center,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::bottom(Val::Percent(10.)),
+				..Default::default()
+			},
+			HIGH_RES_LAYERS,
+			RecentSavesMenu,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Button,
+					Node {
+						flex_direction: FlexDirection::Column,
+						align_items: AlignItems::Center,
+						row_gap: BUTTON_SPACING,
+						padding: UiRect::all(BUTTON_SPACING),
 						..Default::default()
-					}));
-					parent.spawn((TextSpan("\nThe Camping Madness Project".into()), TextFont {
-						font: assets.load(font_for(FontWeight::Bold, FontStyle::Regular)),
-						font_size: 40.,
+					},
+					BackgroundColor(DARK_GRAY.into()),
+					Tooltip { title: "Continue".into(), body: format!("Continue playing {campground_name}.") },
+					ContinueButton { save_name: most_recent.save_name.clone() },
+				))
+				.with_children(|button| {
+					if let Some(thumbnail) = thumbnail {
+						button.spawn((ImageNode::new(thumbnail), Node { width: Val::Px(320.), ..Default::default() }));
+					}
+					button.spawn((
+						Text(format!("Continue {campground_name}")),
+						TextColor(WHITE.into()),
+						font(FontWeight::Bold, 32.),
+					));
+				});
+
+			if recents.len() < 2 {
+				return;
+			}
+			parent
+				.spawn((
+					Button,
+					Node { padding: UiRect::all(BUTTON_SPACING), ..Default::default() },
+					BackgroundColor(DARK_GRAY.into()),
+					Tooltip { title: "Recent saves".into(), body: "Show the campgrounds you saved recently.".into() },
+					RecentSavesToggle,
+				))
+				.with_child((Text("Recent saves".into()), TextColor(WHITE.into()), font(FontWeight::Regular, 24.)));
+			parent
+				.spawn((
+					Node {
+						display: Display::None,
+						flex_direction: FlexDirection::Column,
+						row_gap: BUTTON_SPACING,
 						..Default::default()
-					}));
+					},
+					RecentSavesList,
+				))
+				.with_children(|list| {
+					for recent in &recents {
+						list.spawn((
+							Button,
+							Node { padding: UiRect::all(BUTTON_SPACING), ..Default::default() },
+							BackgroundColor(DARK_GRAY.into()),
+							ContinueButton { save_name: recent.save_name.clone() },
+						))
+						.with_child((
+							Text(format!(
+								"{} ({}, saved {})",
+								recent.campground_name(),
+								recent.save_name,
+								recent.age()
+							)),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 20.),
+						));
+					}
 				});
 		});
 }
+
+fn hide_recent_saves(mut commands: Commands, menus: Query<Entity, With<RecentSavesMenu>>) {
+	for menu in &menus {
+		commands.entity(menu).despawn_recursive();
+	}
+}
+
+fn toggle_recent_saves_list(
+	toggles: Query<&Interaction, (Changed<Interaction>, With<RecentSavesToggle>)>,
+	mut lists: Query<&mut Node, With<RecentSavesList>>,
+) {
+	if toggles.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		for mut list in &mut lists {
+			list.display = if list.display == Display::None { Display::Flex } else { Display::None };
+		}
+	}
+}
+
+fn continue_recent_save(
+	buttons: Query<(&Interaction, &ContinueButton), Changed<Interaction>>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut errors: EventWriter<ErrorBox>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction == &Interaction::Pressed {
+			match LoadSave::new(button.save_name.clone()) {
+				// The save is loaded once the game has started.
+				Ok(load) => {
+					commands.insert_resource(load);
+					next_state.set(GameState::Loading);
+				},
+				Err(why) => {
+					errors.send(why.into());
+				},
+			}
+		}
+	}
+}
@@ -0,0 +1,263 @@
+//! Minimap of the whole campground.
+//!
+//! The minimap shows the ground of the campground from the same angle as the camera, with one color per ground kind,
+//! so that the camera's view is a plain rectangle on it. The map is drawn into an image on the CPU by looking up the
+//! tile under every minimap pixel; since that is too slow to do every frame, it is only redrawn a short while after
+//! the ground changed. Clicking or dragging on the minimap moves the camera there.
+
+use std::time::Duration;
+
+use bevy::color::palettes::css::WHITE;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::ui::{FocusPolicy, RelativeCursorPosition};
+
+use super::hud_layout::{HudLayout, HudPanel};
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::{world_to_engine_space, InGameCamera};
+use crate::input::picking::tile_under;
+use crate::model::{GroundKind, GroundMap};
+use crate::mods::custom_ground_kind;
+use crate::util::Tooltip;
+
+pub(super) struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Minimap>().add_systems(
+			Update,
+			(draw_minimap, jump_to_minimap_position, update_minimap_viewport)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Size of the minimap image, in pixels.
+const MINIMAP_SIZE: UVec2 = UVec2::new(256, 160);
+/// Minimum time between redraws of the minimap.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+/// Thickness of the minimap frame and the viewport rectangle in logical pixels.
+const FRAME_THICKNESS: f32 = 2.;
+
+/// The minimap image and the part of the engine space that it shows.
+#[derive(Resource)]
+pub(super) struct Minimap {
+	image:      Handle<Image>,
+	/// The engine-space area shown on the minimap, which has the same aspect ratio as the minimap.
+	view:       Rect,
+	/// Whether the ground changed since the minimap was last drawn.
+	dirty:      bool,
+	last_drawn: Duration,
+}
+
+impl FromWorld for Minimap {
+	fn from_world(world: &mut World) -> Self {
+		let image = Image::new_fill(
+			Extent3d {
+				width:                 MINIMAP_SIZE.x,
+				height:                MINIMAP_SIZE.y,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			&[0, 0, 0, 0],
+			TextureFormat::Rgba8UnormSrgb,
+			RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+		);
+		Self {
+			image:      world.resource_mut::<Assets<Image>>().add(image),
+			view:       Rect::default(),
+			dirty:      true,
+			last_drawn: Duration::ZERO,
+		}
+	}
+}
+
+impl Minimap {
+	/// Engine-space units per minimap pixel.
+	fn scale(&self) -> f32 {
+		self.view.width() / MINIMAP_SIZE.x as f32
+	}
+
+	/// The engine-space position at a position on the minimap, measured in pixels from its upper left corner.
+	fn to_engine(&self, pixel: Vec2) -> Vec2 {
+		Vec2::new(self.view.min.x + pixel.x * self.scale(), self.view.max.y - pixel.y * self.scale())
+	}
+
+	/// The position on the minimap, in pixels from its upper left corner, of an engine-space position.
+	fn to_pixel(&self, engine_position: Vec2) -> Vec2 {
+		Vec2::new(engine_position.x - self.view.min.x, self.view.max.y - engine_position.y) / self.scale()
+	}
+
+	/// Fits the view around all ground tiles, keeping the minimap's aspect ratio.
+	fn fit_view(&mut self, ground_map: &GroundMap) {
+		let Some((smallest, largest)) = ground_map.iter().map(|(position, ..)| position.truncate()).fold(
+			None,
+			|bounds: Option<(IVec2, IVec2)>, tile| {
+				let (smallest, largest) = bounds.unwrap_or((tile, tile));
+				Some((smallest.min(tile), largest.max(tile)))
+			},
+		) else {
+			return;
+		};
+		let corners =
+			[smallest, IVec2::new(largest.x + 1, smallest.y), IVec2::new(smallest.x, largest.y + 1), largest + 1]
+				.map(|corner| world_to_engine_space(corner.extend(0).as_vec3a()));
+		let bounds = corners
+			.iter()
+			.fold(Rect::from_corners(corners[0], corners[0]), |bounds, corner| bounds.union_point(*corner));
+		let aspect_ratio = MINIMAP_SIZE.x as f32 / MINIMAP_SIZE.y as f32;
+		let size = if bounds.width() / bounds.height() > aspect_ratio {
+			Vec2::new(bounds.width(), bounds.width() / aspect_ratio)
+		} else {
+			Vec2::new(bounds.height() * aspect_ratio, bounds.height())
+		};
+		self.view = Rect::from_center_size(bounds.center(), size.max(Vec2::ONE));
+	}
+}
+
+/// Color of a ground kind on the minimap.
+fn minimap_color(kind: GroundKind) -> [u8; 4] {
+	match kind {
+		GroundKind::Grass => [86, 138, 58, 255],
+		GroundKind::Pathway => [168, 156, 136, 255],
+		GroundKind::PoolPath => [82, 164, 222, 255],
+		GroundKind::Pitch => [204, 172, 92, 255],
+		GroundKind::Custom(number) =>
+			custom_ground_kind(number).map_or([86, 138, 58, 255], |kind| kind.color.to_srgba().to_u8_array()),
+	}
+}
+
+/// The minimap image, which moves the camera when clicked.
+#[derive(Component, Clone, Copy, Debug)]
+struct MinimapImage;
+
+/// Rectangle on the minimap showing what the camera sees.
+#[derive(Component, Clone, Copy, Debug)]
+struct MinimapViewport;
+
+/// Spawns the minimap panel into the in-game UI grid.
+pub(super) fn spawn_minimap(parent: &mut ChildBuilder, minimap: &Minimap, layout: &HudLayout) {
+	let mut node = Node {
+		justify_self: JustifySelf::End,
+		align_self: AlignSelf::End,
+		border: UiRect::all(Val::Px(FRAME_THICKNESS)),
+		..default()
+	};
+	layout.apply(HudPanel::Minimap, &mut node);
+	parent
+		.spawn((
+			node,
+			HudPanel::Minimap,
+			BorderColor(Color::BLACK),
+			BackgroundColor(Color::BLACK.with_alpha(0.85)),
+			FocusPolicy::Block,
+			Interaction::default(),
+		))
+		.with_children(|panel| {
+			panel
+				.spawn((
+					ImageNode::new(minimap.image.clone()),
+					Node {
+						width: Val::Px(MINIMAP_SIZE.x as f32),
+						height: Val::Px(MINIMAP_SIZE.y as f32),
+						overflow: Overflow::clip(),
+						..default()
+					},
+					Interaction::default(),
+					RelativeCursorPosition::default(),
+					Tooltip { title: "Minimap".into(), body: "Click or drag to move the camera there.".into() },
+					MinimapImage,
+				))
+				.with_child((
+					Node {
+						position_type: PositionType::Absolute,
+						border: UiRect::all(Val::Px(FRAME_THICKNESS)),
+						..default()
+					},
+					BorderColor(WHITE.into()),
+					FocusPolicy::Pass,
+					MinimapViewport,
+				));
+		});
+}
+
+fn draw_minimap(
+	ground_map: Res<GroundMap>,
+	mut minimap: ResMut<Minimap>,
+	mut images: ResMut<Assets<Image>>,
+	time: Res<Time<Real>>,
+) {
+	if ground_map.is_changed() {
+		minimap.dirty = true;
+	}
+	if !minimap.dirty || time.elapsed().saturating_sub(minimap.last_drawn) < REDRAW_INTERVAL {
+		return;
+	}
+	minimap.dirty = false;
+	minimap.last_drawn = time.elapsed();
+	minimap.fit_view(&ground_map);
+
+	let Some(image) = images.get_mut(&minimap.image) else {
+		return;
+	};
+	for (index, pixel) in image.data.chunks_exact_mut(4).enumerate() {
+		let position = UVec2::new(index as u32 % MINIMAP_SIZE.x, index as u32 / MINIMAP_SIZE.x);
+		let tile = tile_under(minimap.to_engine(position.as_vec2() + 0.5));
+		pixel.copy_from_slice(&ground_map.kind_of(&tile).map_or([0; 4], minimap_color));
+	}
+}
+
+fn jump_to_minimap_position(
+	mouse: Res<ButtonInput<MouseButton>>,
+	minimap: Res<Minimap>,
+	images: Query<(&Interaction, &RelativeCursorPosition), With<MinimapImage>>,
+	mut camera: Query<(&mut Transform, &OrthographicProjection), With<InGameCamera>>,
+) {
+	// Camera dragging also reacts to the mouse, but runs earlier, so the minimap has the last word.
+	if !mouse.pressed(MouseButton::Left) || minimap.view.width() <= 0. {
+		return;
+	}
+	let Some(cursor) = images
+		.iter()
+		.find(|(interaction, _)| **interaction == Interaction::Pressed)
+		.and_then(|(_, cursor)| cursor.normalized)
+	else {
+		return;
+	};
+	let Ok((mut transform, projection)) = camera.get_single_mut() else {
+		return;
+	};
+	let target = minimap.to_engine(cursor.clamp(Vec2::ZERO, Vec2::ONE) * MINIMAP_SIZE.as_vec2());
+	let translation = (target - projection.area.center()).round();
+	transform.translation = translation.extend(transform.translation.z);
+}
+
+fn update_minimap_viewport(
+	minimap: Res<Minimap>,
+	camera: Query<(&GlobalTransform, &OrthographicProjection), With<InGameCamera>>,
+	mut viewports: Query<&mut Node, With<MinimapViewport>>,
+) {
+	// Nothing is drawn on the minimap yet.
+	if minimap.view.width() <= 0. {
+		return;
+	}
+	let Ok((camera_transform, projection)) = camera.get_single() else {
+		return;
+	};
+	let view = Rect::from_center_size(
+		camera_transform.translation().truncate() + projection.area.center(),
+		projection.area.size(),
+	);
+	// The y axis flips between engine and minimap space, so the engine's upper left corner is the minimum here.
+	let top_left = minimap.to_pixel(Vec2::new(view.min.x, view.max.y));
+	let size = view.size() / minimap.scale();
+	for mut node in &mut viewports {
+		node.left = Val::Px(top_left.x);
+		node.top = Val::Px(top_left.y);
+		node.width = Val::Px(size.x);
+		node.height = Val::Px(size.y);
+	}
+}
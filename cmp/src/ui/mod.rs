@@ -1,76 +1,173 @@
 use std::sync::LazyLock;
 use std::time::Duration;
 
-use bevy::color::palettes::css::{DARK_GRAY, GRAY, ORANGE};
+use bevy::color::palettes::css::{DARK_GRAY, ORANGE};
 use bevy::prelude::*;
 use bevy::text::LineBreak;
 use bevy::ui::FocusPolicy;
 use build::BuildPlugin;
+use loading::LoadingPlugin;
 use main_menu::MainMenuPlugin;
 
-use self::animate::{AnimationPlugin, AnimationTargets, UIAnimation};
-use self::controls::{BuildMenuContainer, ALL_BUILD_MENUS};
-use crate::gamemode::GameState;
-use crate::graphics::library::{font_for, logo_for_build_menu, logo_for_buildable, FontStyle, FontWeight};
+use self::animate::{darken_when_pressed, AnimationPlugin, AnimationTargets, UIAnimation};
+use self::hud_layout::{HudLayout, HudPanel};
+use crate::gamemode::{GameSet, GameSpeed, GameState, SimulationActive};
+use crate::graphics::library::{font_for, logo_for_build_menu, FontStyle, FontWeight, CLEARING_LOGO, DEMOLISH_LOGO};
 use crate::graphics::HIGH_RES_LAYERS;
 use crate::input::InputState;
-use crate::model::ALL_BUILDABLES;
+use crate::model::calendar::{Calendar, DAYS_PER_MONTH};
+use crate::model::rollup::DailyTally;
+use crate::model::weather::Weather;
+use crate::model::SiteCapacity;
 use crate::ui::animate::{StyleHeight, TransitionTimes};
+use crate::util::text_input::TextInputPlugin;
 use crate::util::{Tooltip, TooltipPlugin};
 
+pub(crate) mod accessibility;
 pub(crate) mod animate;
+mod auto_route;
+pub(crate) mod blueprint;
 pub(crate) mod build;
+mod build_menu;
+pub(crate) mod calculator;
+pub(crate) mod clearing;
+pub(crate) mod demolish;
+pub mod dialog;
 pub mod error;
+pub(crate) mod hud_layout;
+pub(crate) mod layout;
+pub(crate) mod loading;
 pub(crate) mod main_menu;
+pub(crate) mod minimap;
+pub(crate) mod modifiers;
+pub(crate) mod naming;
+pub mod notifications;
+pub(crate) mod overlay_legend;
+pub(crate) mod paint;
+pub(crate) mod path_suggestion;
+pub(crate) mod pause;
+pub(crate) mod pricing;
+pub(crate) mod rect_tool;
+pub(crate) mod selection;
+pub(crate) mod settings;
+pub(crate) mod staff;
+pub(crate) mod tutorial;
+pub(crate) mod visitors;
 pub(crate) mod world_info;
 
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_plugins((BuildPlugin, TooltipPlugin, AnimationPlugin, MainMenuPlugin))
+		app.add_plugins(notifications::NotificationPlugin)
+			.add_plugins((
+				BuildPlugin,
+				TooltipPlugin,
+				AnimationPlugin,
+				MainMenuPlugin,
+				LoadingPlugin,
+				layout::LayoutPlugin,
+				paint::PaintPlugin,
+				path_suggestion::PathSuggestionPlugin,
+				naming::NamingPlugin,
+				// Tools besides building.
+				(
+					demolish::DemolishPlugin,
+					clearing::ClearingPlugin,
+					blueprint::BlueprintPlugin,
+					selection::SelectionPlugin,
+					auto_route::AutoRoutePlugin,
+				),
+				settings::SettingsPlugin,
+				accessibility::AccessibilityPlugin,
+				pause::PausePlugin,
+				TextInputPlugin,
+				// In-game panels.
+				(
+					hud_layout::HudLayoutPlugin,
+					staff::StaffPanelPlugin,
+					calculator::CalculatorPlugin,
+					pricing::PricingPlugin,
+					minimap::MinimapPlugin,
+					overlay_legend::OverlayLegendPlugin,
+					build_menu::BuildMenuPlugin,
+					tutorial::TutorialPlugin,
+				),
+			))
 			.add_event::<controls::OpenBuildMenu>()
 			.add_event::<controls::CloseBuildMenus>()
 			.add_event::<error::ErrorBox>()
+			.add_event::<dialog::OpenDialog>()
+			.init_resource::<dialog::DialogQueue>()
+			.init_resource::<world_info::WorldInfoSections>()
+			.init_resource::<world_info::WorldInfoStack>()
 			.add_systems(
-				OnEnter(GameState::InGame),
+				// Not when resuming from the pause menu, where the in-game UI still exists.
+				OnTransition { exited: GameState::Loading, entered: GameState::InGame },
 				(initialize_ingame_ui, initialize_dialogs, world_info::setup_world_info),
 			)
 			.add_systems(
 				Update,
-				(world_info::reassign_world_info, world_info::update_world_info)
+				(
+					world_info::reassign_world_info,
+					world_info::update_world_info,
+					world_info::update_world_info_sections.after(world_info::update_world_info),
+				)
 					.run_if(in_state(InputState::Idle))
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
 			.add_systems(
 				Update,
-				(world_info::move_world_info, world_info::hide_world_info)
+				(
+					world_info::move_world_info,
+					world_info::hide_world_info,
+					world_info::raise_world_info,
+					world_info::pin_or_close_world_info,
+				)
 					.before(world_info::update_world_info)
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
 			.add_systems(
 				Update,
 				(
-					update_build_menu_state,
 					on_build_menu_button_press,
-					on_start_build_preview.after(on_build_menu_button_press),
+					on_start_build_preview.after(on_build_menu_button_press).in_set(SimulationActive),
 					close_dialog,
+					modifiers::show_modifier_panel,
+					visitors::show_visitor_panel,
+					update_capacity_label,
+					update_weather_label,
+					update_date_label,
+					press_game_speed_buttons,
+					update_game_speed_buttons.after(press_game_speed_buttons),
 				)
-					.run_if(in_state(GameState::InGame)),
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
 			)
-			.add_systems(PostUpdate, (error::show_errors, error::print_errors).run_if(in_state(GameState::InGame)));
+			.add_systems(
+				PostUpdate,
+				(error::show_errors, error::print_errors, dialog::show_dialogs.after(dialog::queue_dialogs))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			// Dialogs can be requested at any time, but are only shown in the game.
+			.add_systems(PostUpdate, dialog::queue_dialogs.after(error::show_errors));
 	}
 }
 
 /// Components used for marking and identifying various UI controls.
 pub mod controls {
 	use bevy::prelude::*;
+	use serde::Deserialize;
 
 	use crate::model::Buildable;
+	use crate::mods::custom_menus;
 	use crate::util::Tooltipable;
 
 	/// The possible build menus.
-	#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+	#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect, Deserialize)]
 	pub enum BuildMenu {
 		/// Basic objects, like fences and pathways.
 		Basics,
@@ -78,6 +175,14 @@ pub mod controls {
 		Pitch,
 		/// All pool-related objects.
 		Pool,
+		/// Buildings that serve visitors, like the reception.
+		Facilities,
+		/// Trees, benches and other objects that make the campground more beautiful.
+		Decoration,
+		/// Blueprints that the player captured from their campground.
+		Blueprints,
+		/// A build menu that a mod added, see [`crate::mods::CustomMenu`].
+		Custom(u8),
 	}
 
 	impl Tooltipable for BuildMenu {
@@ -86,6 +191,13 @@ pub mod controls {
 				Self::Basics => "Fundamental buildings and objects.",
 				Self::Pitch => "Pitches housing visitors, such as tent pitches, caravans or mobile homes.",
 				Self::Pool => "Everything for swimming pools.",
+				Self::Facilities => "Buildings that serve your visitors, such as the reception, toilets and shops.",
+				Self::Decoration =>
+					"Trees, flowers and furniture that make the surroundings of pitches more beautiful.",
+				Self::Blueprints =>
+					"Capture parts of your campground as blueprints, and build copies of them elsewhere.",
+				Self::Custom(number) =>
+					custom_menus().get(usize::from(*number)).map_or("Buildables of a mod.", |menu| menu.description),
 			}
 		}
 	}
@@ -96,21 +208,84 @@ pub mod controls {
 				Self::Pitch => "Pitches",
 				Self::Basics => "The Basics",
 				Self::Pool => "Swimming Pools",
+				Self::Facilities => "Facilities",
+				Self::Decoration => "Decorations",
+				Self::Blueprints => "Blueprints",
+				Self::Custom(number) => custom_menus().get(usize::from(*number)).map_or("Mod", |menu| menu.name),
 			})
 		}
 	}
 
-	pub(super) const ALL_BUILD_MENUS: [BuildMenu; 3] = [BuildMenu::Basics, BuildMenu::Pitch, BuildMenu::Pool];
+	pub(crate) const ALL_BUILD_MENUS: [BuildMenu; 6] = [
+		BuildMenu::Basics,
+		BuildMenu::Pitch,
+		BuildMenu::Pool,
+		BuildMenu::Facilities,
+		BuildMenu::Decoration,
+		BuildMenu::Blueprints,
+	];
+
+	/// The built-in build menus, followed by those that mods added.
+	pub(crate) fn all_build_menus() -> impl Iterator<Item = BuildMenu> {
+		let custom_menus = (0 ..= u8::MAX).take(custom_menus().len()).map(BuildMenu::Custom);
+		ALL_BUILD_MENUS.into_iter().chain(custom_menus)
+	}
 
 	/// Marks a button that opens one of the several build menus.
 	#[derive(Component, Reflect)]
 	#[reflect(Component)]
 	pub struct BuildMenuButton(pub BuildMenu);
 
+	/// Marks the grid of buttons of one build menu.
 	#[derive(Component, Reflect)]
 	#[reflect(Component)]
 	pub struct BuildMenuContainer(pub BuildMenu);
 
+	/// Marks the button that expands the collapsed build bar in the compact layout.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct BuildBarToggle;
+
+	/// Marks the top bar text that shows the campground's name.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct CampgroundNameLabel;
+
+	/// Marks the top bar text that shows the campground's capacity.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct CapacityLabel;
+
+	/// Marks the top bar text that shows the current date.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct DateLabel;
+
+	/// Marks the top bar text that shows the current weather.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct WeatherLabel;
+
+	/// Marks a top bar button that changes the game speed to the given multiplier, or pauses for [`None`].
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct GameSpeedButton(pub Option<f32>);
+
+	/// Marks the root of a UI layer that only exists while a game is running.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct InGameUi;
+
+	/// Marks the button that starts the demolish tool.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct DemolishButton;
+
+	/// Marks the button that starts the clearing tool.
+	#[derive(Component, Reflect)]
+	#[reflect(Component)]
+	pub struct ClearingButton;
+
 	/// Marks a button that starts the build process for a specific [`Buildable`].
 	#[derive(Component, Reflect)]
 	#[reflect(Component)]
@@ -154,7 +329,17 @@ static COLUMN_TEMPLATE: LazyLock<Vec<RepeatedGridTrack>> = LazyLock::new(|| {
 	]
 });
 
-fn initialize_ingame_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn initialize_ingame_ui(
+	mut commands: Commands,
+	asset_server: Res<AssetServer>,
+	layout: Res<HudLayout>,
+	minimap: Res<minimap::Minimap>,
+) {
+	// Movable panels are placed by the player's layout instead of in the grid.
+	let panel_node = |panel, mut node: Node| {
+		layout.apply(panel, &mut node);
+		(node, panel)
+	};
 	commands
 		.spawn((
 			Node {
@@ -179,8 +364,106 @@ fn initialize_ingame_ui(mut commands: Commands, asset_server: Res<AssetServer>)
 				..Default::default()
 			},
 			HIGH_RES_LAYERS,
+			controls::InGameUi,
 		))
 		.with_children(|parent| {
+			parent.spawn((
+				Node {
+					grid_row: GridPlacement::start(1),
+					grid_column: GridPlacement::start(2),
+					justify_self: JustifySelf::Center,
+					align_self: AlignSelf::Center,
+					..Default::default()
+				},
+				Text::default(),
+				TextFont {
+					font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
+					font_size: 32.,
+					..Default::default()
+				},
+				controls::CampgroundNameLabel,
+			));
+			parent
+				.spawn(panel_node(HudPanel::Statistics, Node {
+					justify_self: JustifySelf::End,
+					align_self: AlignSelf::Center,
+					column_gap: BUTTON_SPACING * 2.,
+					..Default::default()
+				}))
+				.with_children(|panel| {
+					let font = TextFont {
+						font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+						font_size: 24.,
+						..Default::default()
+					};
+					panel.spawn((
+						Text::default(),
+						font.clone(),
+						Tooltip {
+							title: "Capacity".into(),
+							body:  "How many visitors can stay at once, and on how many pitches. Only pitches with a \
+							        pitch type count. Visitors that want to book when no pitch is free are turned \
+							        away."
+								.into(),
+						},
+						Interaction::default(),
+						controls::CapacityLabel,
+					));
+					panel.spawn((
+						Text::default(),
+						font.clone(),
+						Tooltip { title: String::new(), body: String::new() },
+						Interaction::default(),
+						controls::WeatherLabel,
+					));
+					panel.spawn((
+						Text::default(),
+						font,
+						Tooltip { title: String::new(), body: String::new() },
+						Interaction::default(),
+						controls::DateLabel,
+					));
+				});
+			parent
+				.spawn(panel_node(HudPanel::GameSpeed, Node {
+					justify_self: JustifySelf::Start,
+					align_self: AlignSelf::Center,
+					column_gap: BUTTON_SPACING,
+					..Default::default()
+				}))
+				.with_children(|parent| {
+					let background_color = BackgroundColor(DARK_GRAY.into());
+					let speeds = [None].into_iter().chain(GameSpeed::MULTIPLIERS.map(Some));
+					for speed in speeds {
+						let (label, tooltip) = match speed {
+							None => ("Pause".to_string(), Tooltip {
+								title: "Pause".into(),
+								body:  "Pause or resume the simulation. (Space)".into(),
+							}),
+							Some(multiplier) => (format!("{multiplier}x"), Tooltip {
+								title: format!("Speed {multiplier}x"),
+								body:  format!(
+									"Run the simulation at {multiplier} times the normal speed. ({multiplier})"
+								),
+							}),
+						};
+						parent
+							.spawn((
+								Button,
+								Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+								background_color,
+								darken_when_pressed(background_color),
+								tooltip,
+								controls::GameSpeedButton(speed),
+							))
+							.with_child((Text(label), TextFont {
+								font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
+								font_size: 24.,
+								..Default::default()
+							}));
+					}
+				});
+			minimap::spawn_minimap(parent, &minimap, &layout);
 			parent
 				.spawn(Node {
 					grid_row: GridPlacement::start(3),
@@ -210,18 +493,7 @@ fn initialize_ingame_ui(mut commands: Commands, asset_server: Res<AssetServer>)
 						20.,
 						TRANSITION_TIMES,
 					);
-					let press_animation = UIAnimation::<_, _, BackgroundColor>::new(
-						background_color,
-						BackgroundColor({
-							let Hsla { hue, saturation, mut lightness, alpha } = background_color.0.into();
-							lightness = (lightness - 0.3).clamp(0., 1.);
-							Color::hsla(hue, saturation, lightness, alpha)
-						}),
-						AnimationTargets::at_press(),
-						4.,
-						4.,
-						TransitionTimes::uniform(Duration::from_millis(100)),
-					);
+					let press_animation = darken_when_pressed(background_color);
 					parent
 						.spawn((
 							Node {
@@ -236,8 +508,33 @@ fn initialize_ingame_ui(mut commands: Commands, asset_server: Res<AssetServer>)
 							Interaction::default(),
 						))
 						.with_children(|parent| {
-							// TODO: Use iter_variants to dynamically access all variants.
-							for menu_type in controls::ALL_BUILD_MENUS {
+							// Only shown in the compact layout, where it replaces the build menu buttons.
+							parent
+								.spawn((
+									Button,
+									height_animation.clone(),
+									press_animation.clone(),
+									Node {
+										justify_content: JustifyContent::Center,
+										align_items: AlignItems::Center,
+										display: Display::None,
+										min_width: Val::Px(PIXEL_SIZE),
+										height: Val::Px(PIXEL_SIZE),
+										padding: UiRect::horizontal(BUTTON_SPACING),
+										..Default::default()
+									},
+									background_color,
+									controls::BuildBarToggle,
+									Tooltip { title: "Build".into(), body: "Show or hide the build menus.".into() },
+								))
+								.with_children(|button| {
+									button.spawn((Text("Build".into()), TextFont {
+										font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
+										font_size: 24.,
+										..Default::default()
+									}));
+								});
+							for menu_type in controls::all_build_menus() {
 								let node = Node {
 									justify_content: JustifyContent::Center,
 									align_items: AlignItems::Center,
@@ -265,62 +562,61 @@ fn initialize_ingame_ui(mut commands: Commands, asset_server: Res<AssetServer>)
 										));
 									});
 							}
-						});
-					// All build menus.
-					for menu_type in ALL_BUILD_MENUS {
-						parent
-							.spawn((
-								Node {
-									grid_row: GridPlacement::start(1),
-									display: Display::None,
-									flex_direction: FlexDirection::Row,
-									align_items: AlignItems::Baseline,
-									align_self: AlignSelf::Baseline,
-									column_gap: BUTTON_SPACING,
-									padding: UiRect::all(BUTTON_SPACING),
-									min_height: Val::Px(50.),
-									..Default::default()
-								},
-								BackgroundColor(GRAY.into()),
-								FocusPolicy::Block,
-								BuildMenuContainer(menu_type),
-								Interaction::default(),
-							))
-							.with_children(|build_menu| {
-								// May be a little slow to iterate all buildable types each time, but we only do it once
-								// on startup anyways.
-								for buildable in ALL_BUILDABLES.iter().filter(|buildable| buildable.menu() == menu_type)
-								{
-									let background_color = BackgroundColor(DARK_GRAY.into());
-									let node = Node {
+							parent
+								.spawn((
+									Button,
+									height_animation.clone(),
+									press_animation.clone(),
+									Node {
 										justify_content: JustifyContent::Center,
 										align_items: AlignItems::Center,
-										width: Val::Px(50.),
-										height: Val::Px(50.),
+										width: Val::Px(PIXEL_SIZE),
+										height: Val::Px(PIXEL_SIZE),
 										..Default::default()
-									};
-									build_menu
-										.spawn((
-											Button,
-											height_animation.clone(),
-											press_animation.clone(),
-											node,
-											background_color,
-											Tooltip::from(buildable),
-											controls::StartBuildButton(*buildable),
-										))
-										.with_children(|button| {
-											button.spawn((
-												ImageNode {
-													image: asset_server.load(logo_for_buildable(*buildable)),
-													..Default::default()
-												},
-												Node { width: Val::Percent(90.), ..Default::default() },
-											));
-										});
-								}
-							});
-					}
+									},
+									background_color,
+									controls::DemolishButton,
+									Tooltip {
+										title: "Demolish".into(),
+										body:  "Drag to remove everything in an area. Right click or Escape to stop."
+											.into(),
+									},
+								))
+								.with_children(|button| {
+									button.spawn((
+										ImageNode { image: asset_server.load(DEMOLISH_LOGO), ..Default::default() },
+										Node { width: Val::Percent(90.), ..Default::default() },
+									));
+								});
+							parent
+								.spawn((
+									Button,
+									height_animation.clone(),
+									press_animation.clone(),
+									Node {
+										justify_content: JustifyContent::Center,
+										align_items: AlignItems::Center,
+										width: Val::Px(PIXEL_SIZE),
+										height: Val::Px(PIXEL_SIZE),
+										..Default::default()
+									},
+									background_color,
+									controls::ClearingButton,
+									Tooltip {
+										title: "Clear obstacles".into(),
+										body:  "Drag to clear rocks and old trees in an area. Clearing costs money \
+										        and takes a while. Right click or Escape to stop."
+											.into(),
+									},
+								))
+								.with_children(|button| {
+									button.spawn((
+										ImageNode { image: asset_server.load(CLEARING_LOGO), ..Default::default() },
+										Node { width: Val::Percent(90.), ..Default::default() },
+									));
+								});
+						});
+					build_menu::spawn_build_menu_panel(parent, &asset_server);
 				});
 		});
 }
@@ -354,6 +650,7 @@ fn initialize_dialogs(mut commands: Commands, asset_server: Res<AssetServer>) {
 			Visibility::Hidden,
 			BackgroundColor(Color::Srgba(DARK_GRAY).with_alpha(0.5)),
 			controls::DialogContainer,
+			controls::InGameUi,
 		))
 		.with_children(|parent| {
 			parent
@@ -447,22 +744,86 @@ fn on_start_build_preview(
 	}
 }
 
-fn update_build_menu_state(
-	mut build_menus: Query<(&controls::BuildMenuContainer, &mut Node)>,
-	mut open_menu_event: EventReader<controls::OpenBuildMenu>,
-	mut close_menu_event: EventReader<controls::CloseBuildMenus>,
+fn press_game_speed_buttons(
+	buttons: Query<(&Interaction, &controls::GameSpeedButton), Changed<Interaction>>,
+	mut speed: ResMut<GameSpeed>,
+) {
+	for (interaction, controls::GameSpeedButton(multiplier)) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		match multiplier {
+			None => speed.paused = !speed.paused,
+			Some(multiplier) => *speed = GameSpeed { paused: false, multiplier: *multiplier },
+		}
+	}
+}
+
+/// Highlights the button of the current speed.
+fn update_game_speed_buttons(
+	speed: Res<GameSpeed>,
+	buttons: Query<(&controls::GameSpeedButton, &Children)>,
+	mut labels: Query<&mut TextColor>,
+) {
+	for (controls::GameSpeedButton(multiplier), children) in &buttons {
+		let active = match multiplier {
+			None => speed.paused,
+			Some(multiplier) => !speed.paused && speed.multiplier == *multiplier,
+		};
+		let mut labels = labels.iter_many_mut(children);
+		while let Some(mut label) = labels.fetch_next() {
+			let color = if active { ORANGE.into() } else { Color::WHITE };
+			if label.0 != color {
+				label.0 = color;
+			}
+		}
+	}
+}
+
+fn update_weather_label(
+	weather: Res<Weather>,
+	mut labels: Query<(&mut Text, &mut Tooltip), With<controls::WeatherLabel>>,
+	added_labels: Query<(), Added<controls::WeatherLabel>>,
+) {
+	if weather.is_changed() || !added_labels.is_empty() {
+		for (mut label, mut tooltip) in &mut labels {
+			label.0 = weather.to_string();
+			*tooltip = (&*weather).into();
+		}
+	}
+}
+
+fn update_date_label(
+	calendar: Res<Calendar>,
+	mut labels: Query<(&mut Text, &mut Tooltip), With<controls::DateLabel>>,
+	added_labels: Query<(), Added<controls::DateLabel>>,
 ) {
-	for open_event in open_menu_event.read() {
-		let kind = open_event.0;
-		for (container_type, mut node) in &mut build_menus {
-			// The second check will also close any currently-open menu on second click.
-			node.display =
-				if container_type.0 == kind && node.display == Display::None { Display::Flex } else { Display::None };
+	if calendar.is_changed() || !added_labels.is_empty() {
+		for (mut label, mut tooltip) in &mut labels {
+			label.0 = calendar.to_string();
+			*tooltip = Tooltip {
+				title: calendar.season().to_string(),
+				body:  format!("Months have {DAYS_PER_MONTH} days. The season changes how visitors travel."),
+			};
 		}
 	}
-	for _ in close_menu_event.read() {
-		for (_, mut node) in &mut build_menus {
-			node.display = Display::None;
+}
+
+fn update_capacity_label(
+	capacity: Res<SiteCapacity>,
+	tally: Res<DailyTally>,
+	mut labels: Query<&mut Text, With<controls::CapacityLabel>>,
+) {
+	if !capacity.is_changed() && !tally.is_changed() {
+		return;
+	}
+	let mut text = format!("{} guests on {} pitches", capacity.guests, capacity.pitches);
+	if tally.turned_away() > 0 {
+		text += &format!(", {} turned away today", tally.turned_away());
+	}
+	for mut label in &mut labels {
+		if label.0 != text {
+			label.0.clone_from(&text);
 		}
 	}
 }
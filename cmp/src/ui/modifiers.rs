@@ -0,0 +1,41 @@
+//! Panel explaining the active [`Modifiers`] of the current save.
+
+use bevy::prelude::*;
+
+use super::dialog::OpenDialog;
+use crate::input::{Action, ActionInput};
+use crate::model::modifiers::{ModifierEffect, Modifiers, ALL_MODIFIER_TARGETS};
+
+/// Creates the player-facing breakdown of all active modifiers, grouped by the value they influence.
+pub fn modifier_breakdown(modifiers: &Modifiers) -> String {
+	if modifiers.is_empty() {
+		return "No modifiers are active; all values are at their defaults.".into();
+	}
+	ALL_MODIFIER_TARGETS
+		.into_iter()
+		.filter(|target| modifiers.for_target(*target).next().is_some())
+		.map(|target| {
+			let total = if modifiers.is_enabled(target) {
+				format!("×{:.2}", modifiers.factor(target))
+			} else {
+				ModifierEffect::Disable.to_string()
+			};
+			let single_modifiers: String = modifiers
+				.for_target(target)
+				.map(|modifier| format!("\n    {}: {}", modifier.name, modifier.effect))
+				.collect();
+			format!("{target} {total}{single_modifiers}")
+		})
+		.collect::<Vec<_>>()
+		.join("\n\n")
+}
+
+pub(super) fn show_modifier_panel(
+	actions: ActionInput,
+	modifiers: Res<Modifiers>,
+	mut dialogs: EventWriter<OpenDialog>,
+) {
+	if actions.just_pressed(Action::Modifiers) {
+		dialogs.send(OpenDialog::new("Modifiers").paragraph(modifier_breakdown(&modifiers)));
+	}
+}
@@ -0,0 +1,141 @@
+//! Naming the campground.
+//!
+//! A new game starts by asking the player for the name of their campground. The name is shown in the top bar, and
+//! stored both in the world and in the save metadata, so that menus can show it without loading the save.
+
+use bevy::color::palettes::css::{DARK_GRAY, ORANGE, WHITE};
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use super::controls::{CampgroundNameLabel, InGameUi};
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::model::CampgroundName;
+use crate::util::text_input::{TextInput, TextInputSubmitted};
+
+/// Campground names are limited to this many characters, so that they fit into the top bar.
+const MAX_NAME_LENGTH: usize = 32;
+/// Name used when the player doesn't enter one.
+const DEFAULT_NAME: &str = "Camping Madness";
+
+pub(super) struct NamingPlugin;
+
+impl Plugin for NamingPlugin {
+	fn build(&self, app: &mut App) {
+		// The prompt is opened after the in-game UI exists, so that it's on top.
+		app.add_systems(
+			OnTransition { exited: GameState::Loading, entered: GameState::InGame },
+			open_naming_prompt.after(super::initialize_ingame_ui),
+		)
+		.add_systems(
+			Update,
+			(submit_name, close_naming_prompt, update_name_label).chain().in_set(GameSet::UI).in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Marks the root of the naming prompt.
+#[derive(Component, Clone, Copy, Debug)]
+struct NamingPrompt;
+
+/// Marks the text input of the naming prompt.
+#[derive(Component, Clone, Copy, Debug)]
+struct NameInput;
+
+/// Only new games are unnamed; loaded games get their name from the save.
+fn open_naming_prompt(name: Res<CampgroundName>, asset_server: Res<AssetServer>, mut commands: Commands) {
+	if !name.0.is_empty() {
+		return;
+	}
+	let font = |weight, font_size| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..Default::default()
+	};
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.),
+				height: Val::Percent(100.),
+				position_type: PositionType::Absolute,
+				justify_content: JustifyContent::Center,
+				align_items: AlignItems::Center,
+				..Default::default()
+			},
+			BackgroundColor(Color::Srgba(DARK_GRAY).with_alpha(0.5)),
+			// Keep the player from interacting with the world until the campground has a name.
+			FocusPolicy::Block,
+			Interaction::default(),
+			GlobalZIndex(2),
+			HIGH_RES_LAYERS,
+			NamingPrompt,
+			InGameUi,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Node {
+						flex_direction: FlexDirection::Column,
+						row_gap: BUTTON_SPACING,
+						padding: UiRect::all(BUTTON_SPACING),
+						min_width: Val::Percent(30.),
+						..Default::default()
+					},
+					BackgroundColor(DARK_GRAY.into()),
+				))
+				.with_children(|prompt| {
+					prompt.spawn((
+						Text("Name your campground".into()),
+						font(FontWeight::Bold, 32.),
+						TextColor(ORANGE.into()),
+					));
+					prompt.spawn((
+						TextInput::focused(MAX_NAME_LENGTH),
+						NameInput,
+						font(FontWeight::Regular, 28.),
+						TextColor(WHITE.into()),
+						Node { padding: UiRect::all(BUTTON_SPACING), ..Default::default() },
+						BackgroundColor(Color::BLACK),
+					));
+					prompt.spawn((
+						Text(format!(
+							"Press Enter to open your campground. Without a name, it is called {DEFAULT_NAME}."
+						)),
+						font(FontWeight::Regular, 20.),
+						TextColor(WHITE.into()),
+					));
+				});
+		});
+}
+
+fn submit_name(
+	mut submitted: EventReader<TextInputSubmitted>,
+	name_input: Query<(), With<NameInput>>,
+	mut name: ResMut<CampgroundName>,
+) {
+	for event in submitted.read() {
+		if name_input.contains(event.entity) {
+			let trimmed = event.value.trim();
+			name.0 = if trimmed.is_empty() { DEFAULT_NAME.to_string() } else { trimmed.to_string() };
+		}
+	}
+}
+
+/// The prompt also closes when a save is loaded while it is open.
+fn close_naming_prompt(name: Res<CampgroundName>, prompts: Query<Entity, With<NamingPrompt>>, mut commands: Commands) {
+	if name.is_changed() && !name.0.is_empty() {
+		for prompt in &prompts {
+			commands.entity(prompt).despawn_recursive();
+		}
+	}
+}
+
+fn update_name_label(name: Res<CampgroundName>, mut labels: Query<&mut Text, With<CampgroundNameLabel>>) {
+	if name.is_changed() {
+		for mut label in &mut labels {
+			label.0.clone_from(&name.0);
+		}
+	}
+}
@@ -0,0 +1,248 @@
+//! Non-blocking notifications ("toasts").
+//!
+//! Any system can show a short message to the player by sending a [`NotificationEvent`]. Unlike dialogs, notifications
+//! don't interrupt the player: they slide in at the top right of the window, stack below each other and disappear on
+//! their own after a while, or when clicked. Hovering a notification keeps it open. If too many notifications arrive at
+//! once, the rest wait in a queue until there is room.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::color::palettes::css::{DARK_GRAY, LIGHT_SKY_BLUE, ORANGE, RED};
+use bevy::prelude::*;
+use bevy::text::LineBreak;
+use bevy::ui::FocusPolicy;
+
+use super::animate::{AnimationTargets, StyleLeft, TransitionTimes, UIAnimation};
+use super::BUTTON_SPACING;
+use crate::config::GameSettings;
+use crate::gamemode::GameSet;
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::palette::{Palette, SAFE_VERMILLION, SAFE_YELLOW};
+use crate::graphics::HIGH_RES_LAYERS;
+
+pub(super) struct NotificationPlugin;
+
+impl Plugin for NotificationPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<NotificationEvent>()
+			.init_resource::<NotificationQueue>()
+			.add_systems(Startup, spawn_notification_area)
+			.add_systems(
+				Update,
+				(queue_notifications, dismiss_notifications, show_notifications).chain().in_set(GameSet::UI),
+			);
+	}
+}
+
+/// Width of a notification in logical pixels.
+const NOTIFICATION_WIDTH: f32 = 320.;
+/// Maximum number of notifications shown at once.
+const MAX_VISIBLE: usize = 4;
+/// Time a notification takes to slide in or out.
+const SLIDE_TIME: Duration = Duration::from_millis(300);
+
+/// How important a notification is, which determines its color and how long it stays open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Severity {
+	/// Something happened that the player may want to know about.
+	#[default]
+	Info,
+	/// Something went wrong that the player should look into.
+	Warning,
+	/// Something failed, but not badly enough for an error dialog.
+	Error,
+}
+
+impl Severity {
+	fn color(self, palette: Palette) -> Color {
+		match (self, palette) {
+			(Self::Info, _) => LIGHT_SKY_BLUE.into(),
+			(Self::Warning, Palette::Standard) => ORANGE.into(),
+			(Self::Warning, Palette::ColorblindSafe) => SAFE_YELLOW,
+			(Self::Error, Palette::Standard) => RED.into(),
+			(Self::Error, Palette::ColorblindSafe) => SAFE_VERMILLION,
+		}
+	}
+
+	/// How long a notification stays open if the player doesn't dismiss it.
+	fn display_time(self) -> Duration {
+		match self {
+			Self::Info => Duration::from_secs(4),
+			Self::Warning => Duration::from_secs(6),
+			Self::Error => Duration::from_secs(8),
+		}
+	}
+}
+
+/// Requests showing a notification.
+#[derive(Event, Clone, Debug, Default)]
+pub struct NotificationEvent {
+	/// How important the notification is.
+	pub severity: Severity,
+	/// The message shown to the player.
+	pub message:  String,
+}
+
+impl NotificationEvent {
+	/// Creates an informational notification.
+	pub fn info(message: impl Into<String>) -> Self {
+		Self { severity: Severity::Info, message: message.into() }
+	}
+
+	/// Creates a warning notification.
+	pub fn warning(message: impl Into<String>) -> Self {
+		Self { severity: Severity::Warning, message: message.into() }
+	}
+
+	/// Creates an error notification.
+	pub fn error(message: impl Into<String>) -> Self {
+		Self { severity: Severity::Error, message: message.into() }
+	}
+}
+
+/// Notifications waiting to be shown.
+#[derive(Resource, Clone, Debug, Default)]
+struct NotificationQueue(VecDeque<NotificationEvent>);
+
+/// The container that notifications are stacked in.
+#[derive(Component, Clone, Copy, Debug)]
+struct NotificationArea;
+
+/// A shown notification. The timer first counts the time until the notification is dismissed, and then the time it
+/// takes to slide out.
+#[derive(Component, Clone, Debug)]
+struct Notification {
+	timer:      Timer,
+	dismissing: bool,
+}
+
+/// The part of a notification that slides in and out.
+type SlideAnimation = UIAnimation<Val, Node, StyleLeft>;
+
+fn spawn_notification_area(mut commands: Commands) {
+	commands.spawn((
+		Node {
+			position_type: PositionType::Absolute,
+			top: Val::Px(60.),
+			right: BUTTON_SPACING,
+			width: Val::Px(NOTIFICATION_WIDTH),
+			flex_direction: FlexDirection::Column,
+			row_gap: BUTTON_SPACING,
+			..default()
+		},
+		// Above all panels, but below the settings.
+		GlobalZIndex(20),
+		HIGH_RES_LAYERS,
+		NotificationArea,
+	));
+}
+
+fn queue_notifications(mut events: EventReader<NotificationEvent>, mut queue: ResMut<NotificationQueue>) {
+	for event in events.read() {
+		match event.severity {
+			Severity::Info => info!("{}", event.message),
+			Severity::Warning => warn!("{}", event.message),
+			Severity::Error => error!("{}", event.message),
+		}
+		queue.0.push_back(event.clone());
+	}
+}
+
+fn show_notifications(
+	mut queue: ResMut<NotificationQueue>,
+	areas: Query<Entity, With<NotificationArea>>,
+	notifications: Query<&Notification>,
+	settings: Res<GameSettings>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let Ok(area) = areas.get_single() else {
+		return;
+	};
+	let mut visible = notifications.iter().filter(|notification| !notification.dismissing).count();
+	while visible < MAX_VISIBLE {
+		let Some(event) = queue.0.pop_front() else {
+			break;
+		};
+		visible += 1;
+
+		// Notifications have no interaction target of their own, so "hovered" stands for "slid in".
+		let mut slide = SlideAnimation::new(
+			Val::Px(NOTIFICATION_WIDTH),
+			Val::Px(0.),
+			AnimationTargets::at_hover(),
+			16.,
+			20.,
+			TransitionTimes::uniform(SLIDE_TIME),
+		);
+		slide.start_transition_to(Interaction::Hovered);
+		commands.entity(area).with_children(|area| {
+			area.spawn((
+				Node { width: Val::Percent(100.), overflow: Overflow::clip(), ..default() },
+				FocusPolicy::Block,
+				Interaction::default(),
+				Notification {
+					timer:      Timer::new(event.severity.display_time(), TimerMode::Once),
+					dismissing: false,
+				},
+			))
+			.with_children(|notification| {
+				notification
+					.spawn((
+						Node {
+							width: Val::Percent(100.),
+							left: Val::Px(NOTIFICATION_WIDTH),
+							padding: UiRect::all(BUTTON_SPACING * 2.),
+							border: UiRect::left(BUTTON_SPACING),
+							..default()
+						},
+						slide,
+						BackgroundColor(DARK_GRAY.with_alpha(0.9).into()),
+						BorderColor(event.severity.color(settings.palette())),
+					))
+					.with_child((
+						Text(event.message),
+						TextFont {
+							font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+							font_size: 20.,
+							..default()
+						},
+						TextLayout { linebreak: LineBreak::WordBoundary, ..default() },
+					));
+			});
+		});
+	}
+}
+
+/// Notifications keep running while the game is paused, so they use real time.
+fn dismiss_notifications(
+	time: Res<Time<Real>>,
+	mut notifications: Query<(Entity, &mut Notification, &Interaction, &Children)>,
+	mut slides: Query<&mut SlideAnimation>,
+	mut commands: Commands,
+) {
+	for (entity, mut notification, interaction, children) in &mut notifications {
+		if notification.dismissing {
+			if notification.timer.tick(time.delta()).finished() {
+				commands.entity(entity).despawn_recursive();
+			}
+			continue;
+		}
+		match interaction {
+			Interaction::Pressed => {},
+			// Hovering keeps the notification open, so that it can be read in peace.
+			Interaction::Hovered => continue,
+			Interaction::None =>
+				if !notification.timer.tick(time.delta()).finished() {
+					continue;
+				},
+		}
+		notification.dismissing = true;
+		notification.timer = Timer::new(SLIDE_TIME, TimerMode::Once);
+		let mut slides = slides.iter_many_mut(children);
+		while let Some(mut slide) = slides.fetch_next() {
+			slide.start_transition_to(Interaction::None);
+		}
+	}
+}
@@ -0,0 +1,105 @@
+//! Legend of the shown overlay.
+//!
+//! While an overlay is shown, a small panel in the lower left corner names its layer and explains its colors: a ramp
+//! from the lowest to the highest value, and the gray of tiles without data.
+
+use bevy::color::palettes::css::WHITE;
+use bevy::prelude::*;
+
+use super::controls::InGameUi;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::{Overlay, HIGH_RES_LAYERS, NO_DATA_COLOR};
+use crate::input::{Action, InputMap, KeyChord};
+
+pub(super) struct OverlayLegendPlugin;
+
+impl Plugin for OverlayLegendPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Update, update_overlay_legend.in_set(GameSet::UI).in_set(GameState::InGame));
+	}
+}
+
+/// Number of color swatches on the legend's ramp.
+const RAMP_STEPS: usize = 6;
+/// Size of a color swatch in logical pixels.
+const SWATCH_SIZE: f32 = 14.;
+
+/// Marks the root of the overlay legend.
+#[derive(Component, Clone, Copy, Debug)]
+struct OverlayLegend;
+
+/// Recreates the legend whenever another layer is shown or the palette may have changed, and removes it once no overlay
+/// is shown. The in-game UI is recreated after loading, so the legend is also recreated when it is missing.
+fn update_overlay_legend(
+	overlay: Res<Overlay>,
+	input_map: Res<InputMap>,
+	settings: Res<GameSettings>,
+	legends: Query<Entity, With<OverlayLegend>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !(overlay.is_changed() || settings.is_changed() || (legends.is_empty() && overlay.is_active())) {
+		return;
+	}
+	for legend in &legends {
+		commands.entity(legend).despawn_recursive();
+	}
+	let Some(layer) = overlay.shown() else {
+		return;
+	};
+
+	let font = |weight, font_size| {
+		(
+			TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size, ..Default::default() },
+			TextColor(WHITE.into()),
+		)
+	};
+	let swatch = |color: Color| {
+		(
+			Node { width: Val::Px(SWATCH_SIZE), height: Val::Px(SWATCH_SIZE), ..Default::default() },
+			BackgroundColor(color),
+		)
+	};
+	let row = Node { column_gap: Val::Px(4.), align_items: AlignItems::Center, ..Default::default() };
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				left: Val::Px(10.),
+				bottom: Val::Px(10.),
+				flex_direction: FlexDirection::Column,
+				row_gap: Val::Px(4.),
+				padding: UiRect::all(Val::Px(8.)),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.5)),
+			HIGH_RES_LAYERS,
+			OverlayLegend,
+			InGameUi,
+		))
+		.with_children(|parent| {
+			parent.spawn((Text(layer.name.to_string()), font(FontWeight::Bold, 18.)));
+			parent.spawn(row.clone()).with_children(|ramp| {
+				ramp.spawn((Text(layer.legend[0].to_string()), font(FontWeight::Regular, 14.)));
+				for step in 0 .. RAMP_STEPS {
+					ramp.spawn(swatch(layer.color(Some(step as f32 / (RAMP_STEPS - 1) as f32), settings.palette())));
+				}
+				ramp.spawn((Text(layer.legend[1].to_string()), font(FontWeight::Regular, 14.)));
+			});
+			parent.spawn(row).with_children(|no_data| {
+				no_data.spawn(swatch(NO_DATA_COLOR));
+				no_data.spawn((Text("No data".to_string()), font(FontWeight::Regular, 14.)));
+			});
+			let key = |action| input_map.chords(action).first().map_or_else(|| "Unbound".to_string(), KeyChord::label);
+			parent.spawn((
+				Text(format!(
+					"{}: next overlay, {}: previous overlay",
+					key(Action::NextOverlay),
+					key(Action::PreviousOverlay)
+				)),
+				font(FontWeight::Regular, 12.),
+			));
+		});
+}
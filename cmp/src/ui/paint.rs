@@ -0,0 +1,123 @@
+//! Paint tool for stamping [`Decal`]s onto the ground.
+//!
+//! The paint tool is toggled with P. Digits select what to paint: 1 for numbers, 2 for arrows and 3 for lines; R
+//! rotates arrows and lines. Left click paints on the tile under the cursor, right click erases its decal. Numbers
+//! count up with every stamp, so that a row of pitches can be numbered with consecutive clicks.
+
+use bevy::prelude::*;
+
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::Sides;
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::decal::{Decal, DecalKind};
+use crate::model::{GridPosition, GroundMap};
+
+/// Highest number that fits on a tile.
+const MAX_NUMBER: u16 = 999;
+
+pub(super) struct PaintPlugin;
+
+impl Plugin for PaintPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PaintTool>()
+			.add_systems(Update, toggle_paint_tool.in_set(GameSet::Input).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				(select_decal, paint.in_set(SimulationActive))
+					.chain()
+					.run_if(in_state(InputState::Painting))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Current settings of the paint tool.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PaintTool {
+	/// The decal that is painted next.
+	pub kind: DecalKind,
+}
+
+impl Default for PaintTool {
+	fn default() -> Self {
+		Self { kind: DecalKind::Number(1) }
+	}
+}
+
+fn toggle_paint_tool(
+	actions: ActionInput,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	match state.get() {
+		InputState::Idle if actions.just_pressed(Action::PaintMode) => next_state.set(InputState::Painting),
+		InputState::Painting if actions.any_just_pressed([Action::PaintMode, Action::Cancel]) =>
+			next_state.set(InputState::Idle),
+		_ => {},
+	}
+}
+
+/// The next side in clockwise order.
+fn rotate(side: Sides) -> Sides {
+	match side {
+		Sides::Top => Sides::Right,
+		Sides::Right => Sides::Bottom,
+		Sides::Bottom => Sides::Left,
+		_ => Sides::Top,
+	}
+}
+
+fn select_decal(actions: ActionInput, mut tool: ResMut<PaintTool>) {
+	let side = match tool.kind {
+		DecalKind::Arrow(side) | DecalKind::Line(side) => side,
+		DecalKind::Number(_) => Sides::Top,
+	};
+	if actions.just_pressed(Action::PaintNumber) {
+		tool.kind = DecalKind::Number(1);
+	}
+	if actions.just_pressed(Action::PaintArrow) {
+		tool.kind = DecalKind::Arrow(side);
+	}
+	if actions.just_pressed(Action::PaintLine) {
+		tool.kind = DecalKind::Line(side);
+	}
+	if actions.just_pressed(Action::RotateMarking) {
+		tool.kind = match tool.kind {
+			DecalKind::Arrow(side) => DecalKind::Arrow(rotate(side)),
+			DecalKind::Line(side) => DecalKind::Line(rotate(side)),
+			number => number,
+		};
+	}
+}
+
+fn paint(
+	mouse: Res<ButtonInput<MouseButton>>,
+	picking: Picking,
+	ground_map: Res<GroundMap>,
+	existing_decals: Query<(Entity, &GridPosition), With<Decal>>,
+	mut tool: ResMut<PaintTool>,
+	mut commands: Commands,
+) {
+	let paints = mouse.just_pressed(MouseButton::Left);
+	let erases = mouse.just_pressed(MouseButton::Right);
+	if !paints && !erases {
+		return;
+	}
+	let Some(pick) = picking.under_cursor() else {
+		return;
+	};
+	if pick.ui_blocked || ground_map.kind_of(&pick.tile).is_none() {
+		return;
+	}
+
+	if erases {
+		Decal::erase(pick.tile, &existing_decals, &mut commands);
+		return;
+	}
+	Decal::paint(tool.kind, pick.tile, &existing_decals, &mut commands);
+	if let DecalKind::Number(number) = tool.kind {
+		tool.kind = DecalKind::Number(if number >= MAX_NUMBER { 1 } else { number + 1 });
+	}
+}
@@ -0,0 +1,234 @@
+//! Pathway connection suggestions.
+//!
+//! Visitors reach their pitches over pathways, so a pitch without any adjacent pathway is useless. When the player
+//! places such a pitch, the cheapest pathway route to the existing pathway network is suggested: the route is shown as
+//! ghost tiles, and a button builds it in one click. The suggestion disappears once the pitch is connected in any
+//! way, including by pathways the player builds themselves.
+
+use bevy::color::palettes::css::DARK_GRAY;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use super::build::{BuildCompleted, BuildRequests};
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{anchor_for_image, font_for, preview_image_for_buildable, FontStyle, FontWeight};
+use crate::graphics::ObjectPriority;
+use crate::model::area::{Area, ImmutableArea};
+use crate::model::routing::{route_cost, route_to_nearest};
+use crate::model::{Buildable, GridPosition, GroundKind, GroundMap, Pitch};
+use crate::util::Tooltip;
+use crate::HashSet;
+
+pub(super) struct PathSuggestionPlugin;
+
+impl Plugin for PathSuggestionPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PathSuggestion>().add_systems(
+			Update,
+			(
+				suggest_path,
+				clear_connected_suggestion,
+				build_suggested_path.in_set(SimulationActive),
+				dismiss_suggested_path,
+				show_suggested_path,
+			)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Tint of the ghost tiles of a suggested path.
+const GHOST_TINT: Color = Color::srgba(1., 1., 1., 0.5);
+
+/// The currently suggested pathway connection, if any.
+#[derive(Resource, Default)]
+struct PathSuggestion(Option<SuggestedPath>);
+
+#[derive(Clone, Debug)]
+struct SuggestedPath {
+	/// The pitch that is to be connected.
+	pitch: Area,
+	/// All tiles that need to become pathways, from the pitch to the existing pathway network.
+	tiles: Vec<GridPosition>,
+	/// Total construction cost of the path.
+	cost:  u32,
+}
+
+impl SuggestedPath {
+	/// Finds the cheapest path from the pitch to any existing pathway. Returns [`None`] if the pitch is already
+	/// connected, or if no pathway can be reached. Paths never lead through pitches, not even the one to be connected.
+	fn find(pitch: Area, ground_map: &GroundMap) -> Option<Self> {
+		if is_connected(&pitch, ground_map) {
+			return None;
+		}
+		let pathways: HashSet<GridPosition> = ground_map
+			.iter()
+			.filter(|(.., kind)| *kind == GroundKind::Pathway)
+			.map(|(position, ..)| (position, ()))
+			.collect();
+		// Start on the side of the pitch that faces the network, so that the path leaves the pitch right away.
+		let start = pitch.tiles_iter().min_by_key(|tile| {
+			pathways.keys().map(|pathway| tile.x.abs_diff(pathway.x) + tile.y.abs_diff(pathway.y)).min()
+		})?;
+		let route = route_to_nearest(start, &pathways, ground_map, |_, kind| {
+			(kind != GroundKind::Pitch).then(|| route_cost(kind)).flatten()
+		})?;
+		// The route starts on the pitch and ends on the pathway; neither needs to be built.
+		let tiles = route.tiles[1 .. route.tiles.len() - 1].to_vec();
+		let cost = tiles.iter().filter_map(|tile| ground_map.kind_of(tile).and_then(route_cost)).sum();
+		Some(Self { pitch, tiles, cost })
+	}
+}
+
+/// Whether any tile next to the pitch is a pathway.
+fn is_connected(pitch: &Area, ground_map: &GroundMap) -> bool {
+	pitch
+		.tiles_iter()
+		.flat_map(|tile| tile.neighbors())
+		.any(|neighbor| ground_map.kind_of(&neighbor) == Some(GroundKind::Pathway))
+}
+
+/// Marks ghost tiles of the suggested path.
+#[derive(Component, Clone, Copy, Debug)]
+struct SuggestionGhost;
+
+/// Marks the panel that offers building the suggested path.
+#[derive(Component, Clone, Copy, Debug)]
+struct SuggestionPanel;
+
+/// Marks the button that builds the suggested path.
+#[derive(Component, Clone, Copy, Debug)]
+struct BuildSuggestionButton;
+
+/// Marks the button that discards the suggested path.
+#[derive(Component, Clone, Copy, Debug)]
+struct DismissSuggestionButton;
+
+fn suggest_path(
+	mut completed: EventReader<BuildCompleted>,
+	pitches: Query<AnyOf<(&Area, &ImmutableArea)>, With<Pitch>>,
+	ground_map: Res<GroundMap>,
+	mut suggestion: ResMut<PathSuggestion>,
+) {
+	for event in completed.read() {
+		let pitch = match event.buildable {
+			Buildable::Pitch => Area::from_rect(event.start_position, event.end_position),
+			// Buildings are placed on existing pitches, which may not have been connected before.
+			Buildable::PitchType(_) => {
+				let Some(area) = pitches
+					.iter()
+					.map(|(area, immutable_area)| area.or(immutable_area.map(|area| &area.0)).unwrap())
+					.find(|area| area.contains(&event.start_position))
+				else {
+					continue;
+				};
+				area.clone()
+			},
+			_ => continue,
+		};
+		if let Some(path) = SuggestedPath::find(pitch, &ground_map) {
+			suggestion.0 = Some(path);
+		}
+	}
+}
+
+fn clear_connected_suggestion(ground_map: Res<GroundMap>, mut suggestion: ResMut<PathSuggestion>) {
+	if ground_map.is_changed() && suggestion.0.as_ref().is_some_and(|path| is_connected(&path.pitch, &ground_map)) {
+		suggestion.0 = None;
+	}
+}
+
+fn build_suggested_path(
+	button: Query<&Interaction, (Changed<Interaction>, With<BuildSuggestionButton>)>,
+	mut suggestion: ResMut<PathSuggestion>,
+	mut build_requests: BuildRequests,
+) {
+	if !button.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		return;
+	}
+	if let Some(path) = suggestion.0.take() {
+		for tile in path.tiles {
+			build_requests.send(Buildable::Ground(GroundKind::Pathway), tile, tile);
+		}
+	}
+}
+
+fn dismiss_suggested_path(
+	button: Query<&Interaction, (Changed<Interaction>, With<DismissSuggestionButton>)>,
+	mut suggestion: ResMut<PathSuggestion>,
+) {
+	if button.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		suggestion.0 = None;
+	}
+}
+
+fn show_suggested_path(
+	suggestion: Res<PathSuggestion>,
+	old_entities: Query<Entity, Or<(With<SuggestionGhost>, With<SuggestionPanel>)>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !suggestion.is_changed() {
+		return;
+	}
+	for entity in &old_entities {
+		commands.entity(entity).despawn_recursive();
+	}
+	let Some(path) = &suggestion.0 else {
+		return;
+	};
+
+	let image = preview_image_for_buildable(Buildable::Ground(GroundKind::Pathway));
+	for tile in &path.tiles {
+		commands.spawn((SuggestionGhost, ObjectPriority::Overlay, *tile, Sprite {
+			color: GHOST_TINT,
+			anchor: anchor_for_image(image),
+			image: asset_server.load(image),
+			..Default::default()
+		}));
+	}
+
+	let font = |weight| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size: 20.,
+		..Default::default()
+	};
+	let button_node = Node { padding: UiRect::all(Val::Px(5.)), ..Default::default() };
+	// The outer node only centers the panel and lets clicks pass through to the world.
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				top: Val::Percent(7.),
+				width: Val::Percent(100.),
+				justify_content: JustifyContent::Center,
+				..Default::default()
+			},
+			SuggestionPanel,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Node { column_gap: Val::Px(5.), padding: UiRect::all(Val::Px(5.)), ..Default::default() },
+					BackgroundColor(DARK_GRAY.into()),
+					FocusPolicy::Block,
+					Interaction::default(),
+				))
+				.with_children(|panel| {
+					panel
+						.spawn((Button, button_node.clone(), BuildSuggestionButton, Tooltip {
+							title: "Connect pitch".into(),
+							body:  "Build the suggested pathway, so that visitors can reach the new pitch.".into(),
+						}))
+						.with_child((Text(format!("Build path (cost {})", path.cost)), font(FontWeight::Bold)));
+					panel
+						.spawn((Button, button_node, DismissSuggestionButton, Tooltip {
+							title: "Dismiss".into(),
+							body:  "Discard the suggested pathway.".into(),
+						}))
+						.with_child((Text("Dismiss".into()), font(FontWeight::Regular)));
+				});
+		});
+}
@@ -0,0 +1,276 @@
+//! Pause menu, opened with Escape while nothing else is going on.
+//!
+//! While the menu is open, the game is in [`GameState::Paused`], so neither the simulation nor the in-game controls
+//! run. The menu offers resuming, the settings panel, skipping the music track, saving and returning to the main menu.
+//! Leaving for the main menu removes the world, so that the next game starts from scratch.
+
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::prelude::*;
+use moonshine_save::save::Save;
+
+use super::animate::darken_when_pressed;
+use super::calculator::CalculatorPanel;
+use super::controls::{DialogContainer, InGameUi};
+use super::hud_layout::LayoutEditor;
+use super::pricing::PricingPanel;
+use super::settings::{OpenSettings, SettingsPanel};
+use super::staff::StaffPanel;
+use super::world_info::WorldInfoUI;
+use super::BUTTON_SPACING;
+use crate::audio::music::SkipTrack;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameSpeed, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::{Canvas, HIGH_RES_LAYERS};
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::calendar::Calendar;
+use crate::model::commerce::CommercialTally;
+use crate::model::demand::PitchPricing;
+use crate::model::modifiers::Modifiers;
+use crate::model::nav::{PeopleNavMesh, VehicleNavMesh};
+use crate::model::rollup::{DailyHistory, DailyTally, DayClock};
+use crate::model::time_of_day::TimeOfDay;
+use crate::model::weather::Weather;
+use crate::model::{CampgroundName, GroundKind, GroundMap};
+use crate::save::request_save;
+use crate::save::schema::SaveSchema;
+use crate::ui::build::BuildHistory;
+use crate::util::Tooltip;
+
+/// Slot name for campgrounds whose name doesn't contain any usable characters.
+const DEFAULT_SAVE_SLOT: &str = "Campground";
+
+pub(super) struct PausePlugin;
+
+impl Plugin for PausePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			open_pause_menu.run_if(in_state(InputState::Idle)).in_set(GameSet::Input).in_set(GameState::InGame),
+		)
+		.add_systems(Update, resume_with_escape.in_set(GameSet::Input).in_set(GameState::Paused))
+		.add_systems(Update, press_pause_menu_buttons.in_set(GameSet::UI).in_set(GameState::Paused))
+		.add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+		.add_systems(OnExit(GameState::Paused), destroy_pause_menu)
+		.add_systems(OnTransition { exited: GameState::Paused, entered: GameState::MainMenu }, leave_game);
+	}
+}
+
+/// The pause menu's buttons.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum PauseMenuButton {
+	Resume,
+	Settings,
+	NextTrack,
+	Save,
+	QuitToMenu,
+}
+
+impl PauseMenuButton {
+	const ALL: [Self; 5] = [Self::Resume, Self::Settings, Self::NextTrack, Self::Save, Self::QuitToMenu];
+
+	const fn label(&self) -> &'static str {
+		match self {
+			Self::Resume => "Resume",
+			Self::Settings => "Settings",
+			Self::NextTrack => "Next Track",
+			Self::Save => "Save",
+			Self::QuitToMenu => "Quit to Menu",
+		}
+	}
+
+	const fn description(&self) -> &'static str {
+		match self {
+			Self::Resume => "Continue playing. (Escape)",
+			Self::Settings => "Change the game settings.",
+			Self::NextTrack => "Fade over to another music track.",
+			Self::Save => "Save the campground to a slot named after it.",
+			Self::QuitToMenu => "Return to the main menu. Unsaved progress is lost.",
+		}
+	}
+}
+
+/// Root of the pause menu.
+#[derive(Component, Clone, Copy, Debug)]
+struct PauseMenu;
+
+/// Text below the buttons that reports saving.
+#[derive(Component, Clone, Copy, Debug)]
+struct PauseStatus;
+
+/// Cancelling only pauses if it isn't needed to close something else first.
+fn open_pause_menu(
+	actions: ActionInput,
+	name: Res<CampgroundName>,
+	world_info: Query<&WorldInfoUI>,
+	dialog_container: Query<&Visibility, With<DialogContainer>>,
+	settings_panels: Query<(), With<SettingsPanel>>,
+	layout_editors: Query<(), With<LayoutEditor>>,
+	staff_panels: Query<(), With<StaffPanel>>,
+	calculators: Query<(), With<CalculatorPanel>>,
+	pricing_panels: Query<(), With<PricingPanel>>,
+	mut next_state: ResMut<NextState<GameState>>,
+) {
+	let busy = name.0.is_empty()
+		|| world_info.iter().any(|world_info| world_info.is_open() && !world_info.is_pinned())
+		|| dialog_container.iter().any(|visibility| visibility == Visibility::Visible)
+		|| !settings_panels.is_empty()
+		|| !layout_editors.is_empty()
+		|| !staff_panels.is_empty()
+		|| !calculators.is_empty()
+		|| !pricing_panels.is_empty();
+	if actions.just_pressed(Action::Cancel) && !busy {
+		next_state.set(GameState::Paused);
+	}
+}
+
+fn resume_with_escape(
+	actions: ActionInput,
+	settings_panels: Query<(), With<SettingsPanel>>,
+	mut next_state: ResMut<NextState<GameState>>,
+) {
+	// Cancelling closes the settings panel first.
+	if actions.just_pressed(Action::Cancel) && settings_panels.is_empty() {
+		next_state.set(GameState::InGame);
+	}
+}
+
+fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+	let font = |weight, font_size| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..Default::default()
+	};
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	commands
+		.spawn((
+			Node {
+				width: Val::Vw(100.),
+				height: Val::Vh(100.),
+				position_type: PositionType::Absolute,
+				flex_direction: FlexDirection::Column,
+				justify_content: JustifyContent::Center,
+				align_items: AlignItems::Center,
+				row_gap: BUTTON_SPACING,
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.5)),
+			// Above the in-game UI and dialogs.
+			GlobalZIndex(3),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			PauseMenu,
+		))
+		.with_children(|parent| {
+			parent.spawn((Text("Paused".into()), TextColor(WHITE.into()), font(FontWeight::Bold, 48.)));
+			for button in PauseMenuButton::ALL {
+				parent
+					.spawn((
+						Button,
+						Node {
+							width: Val::Px(240.),
+							justify_content: JustifyContent::Center,
+							padding: UiRect::all(BUTTON_SPACING),
+							..Default::default()
+						},
+						background_color,
+						darken_when_pressed(background_color),
+						Tooltip { title: button.label().into(), body: button.description().into() },
+						button,
+					))
+					.with_child((Text(button.label().into()), TextColor(WHITE.into()), font(FontWeight::Bold, 32.)));
+			}
+			parent.spawn((Text::default(), TextColor(WHITE.into()), font(FontWeight::Regular, 20.), PauseStatus));
+		});
+}
+
+fn destroy_pause_menu(mut commands: Commands, menus: Query<Entity, With<PauseMenu>>) {
+	for menu in &menus {
+		commands.entity(menu).despawn_recursive();
+	}
+}
+
+/// Save slots are named after the campground, without characters that aren't allowed in file names.
+fn save_slot_for(campground_name: &str) -> String {
+	let slot = campground_name
+		.chars()
+		.filter(|character| character.is_alphanumeric() || [' ', '-', '_'].contains(character))
+		.collect::<String>();
+	let slot = slot.trim();
+	if slot.is_empty() {
+		DEFAULT_SAVE_SLOT.into()
+	} else {
+		slot.into()
+	}
+}
+
+fn press_pause_menu_buttons(
+	buttons: Query<(&Interaction, &PauseMenuButton), Changed<Interaction>>,
+	mut status: Query<&mut Text, With<PauseStatus>>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut open_settings: EventWriter<OpenSettings>,
+	mut skip_track: EventWriter<SkipTrack>,
+	name: Res<CampgroundName>,
+	canvas: Query<&Canvas>,
+	type_registry: Res<AppTypeRegistry>,
+	settings: Res<GameSettings>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		match button {
+			PauseMenuButton::Resume => next_state.set(GameState::InGame),
+			PauseMenuButton::Settings => {
+				open_settings.send_default();
+			},
+			PauseMenuButton::NextTrack => {
+				skip_track.send_default();
+			},
+			PauseMenuButton::Save => {
+				let slot = save_slot_for(&name.0);
+				let schema = SaveSchema::from_registry(&type_registry.read());
+				request_save(
+					slot.clone(),
+					schema,
+					name.0.clone(),
+					settings.save_backup_count(),
+					&canvas,
+					&mut commands,
+				);
+				for mut status in &mut status {
+					status.0 = format!("Saving to slot “{slot}”…");
+				}
+			},
+			PauseMenuButton::QuitToMenu => next_state.set(GameState::MainMenu),
+		}
+	}
+}
+
+/// Removes everything belonging to the game that was left, including its UI and ground tiles, and resets the world's
+/// resources.
+fn leave_game(
+	roots: Query<Entity, (Or<(With<Save>, With<InGameUi>, With<GroundKind>)>, Without<Parent>)>,
+	mut speed: ResMut<GameSpeed>,
+	mut commands: Commands,
+) {
+	for root in &roots {
+		commands.entity(root).despawn_recursive();
+	}
+	commands.insert_resource(GroundMap::new());
+	commands.insert_resource(PeopleNavMesh::default());
+	commands.insert_resource(VehicleNavMesh::default());
+	commands.insert_resource(BuildHistory::default());
+	commands.insert_resource(CampgroundName::default());
+	commands.insert_resource(Modifiers::default());
+	commands.insert_resource(DayClock::default());
+	commands.insert_resource(TimeOfDay::default());
+	commands.insert_resource(Calendar::default());
+	commands.insert_resource(Weather::default());
+	commands.insert_resource(DailyTally::default());
+	commands.insert_resource(DailyHistory::default());
+	commands.insert_resource(CommercialTally::default());
+	commands.insert_resource(PitchPricing::default());
+	*speed = GameSpeed::default();
+}
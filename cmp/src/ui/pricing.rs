@@ -0,0 +1,220 @@
+//! Pricing panel for the nightly prices of pitches.
+//!
+//! The panel is toggled with T. It lists every pitch type with the nightly price that the player set for it in the
+//! [`PitchPricing`], buttons to lower and raise it, and the demand that the price leads to right now. Below, it shows
+//! the factors that affect the demand for all pitches: the season, the campground's rating, and the weather and other
+//! modifiers. Price changes apply to all pitches of the type right away.
+
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::prelude::*;
+
+use super::animate::darken_when_pressed;
+use super::controls::InGameUi;
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::balance::Balance;
+use crate::model::calendar::Calendar;
+use crate::model::demand::{DemandModel, PitchPricing, RATING_DAYS};
+use crate::model::pitch::ALL_PITCH_TYPES;
+use crate::model::PitchType;
+
+pub(super) struct PricingPlugin;
+
+impl Plugin for PricingPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(toggle_pricing_panel, press_pricing_buttons, update_pricing_panel)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Prices change by this fraction of the usual price per button press.
+const PRICE_STEP_FRACTION: u64 = 20;
+
+/// Root of the pricing panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub(super) struct PricingPanel;
+
+/// Text showing the price and demand of a pitch type.
+#[derive(Component, Clone, Copy, Debug)]
+struct PriceLabel(PitchType);
+
+/// Text showing the demand factors that apply to all pitch types.
+#[derive(Component, Clone, Copy, Debug)]
+struct DemandFactorsLabel;
+
+#[derive(Component, Clone, Copy, Debug)]
+enum PricingButton {
+	/// Lowers (-1) or raises (1) the price of the pitch type by one step.
+	Change(PitchType, i64),
+	/// Goes back to the usual prices.
+	Reset,
+	Close,
+}
+
+fn font(asset_server: &AssetServer, weight: FontWeight) -> TextFont {
+	TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size: 24., ..Default::default() }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: String, button: PricingButton, font: TextFont) {
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	parent
+		.spawn((
+			Button,
+			Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+			background_color,
+			darken_when_pressed(background_color),
+			button,
+		))
+		.with_child((Text(label), TextColor(WHITE.into()), font));
+}
+
+fn toggle_pricing_panel(
+	actions: ActionInput,
+	input_state: Res<State<InputState>>,
+	panels: Query<Entity, With<PricingPanel>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !panels.is_empty() {
+		if actions.any_just_pressed([Action::Pricing, Action::Cancel]) {
+			for panel in &panels {
+				commands.entity(panel).despawn_recursive();
+			}
+		}
+		return;
+	}
+	if !actions.just_pressed(Action::Pricing) || input_state.get() != &InputState::Idle {
+		return;
+	}
+
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				right: Val::Percent(1.),
+				top: Val::Percent(10.),
+				flex_direction: FlexDirection::Column,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::all(BUTTON_SPACING * 2.),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.85)),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			PricingPanel,
+			InGameUi,
+		))
+		.with_children(|panel| {
+			panel.spawn((Text("Pitch prices".into()), TextColor(WHITE.into()), TextFont {
+				font_size: 40.,
+				..font(&asset_server, FontWeight::Bold)
+			}));
+			for kind in ALL_PITCH_TYPES {
+				panel
+					.spawn(Node { column_gap: BUTTON_SPACING, align_items: AlignItems::Center, ..Default::default() })
+					.with_children(|row| {
+						spawn_button(
+							row,
+							"-".into(),
+							PricingButton::Change(kind, -1),
+							font(&asset_server, FontWeight::Bold),
+						);
+						spawn_button(
+							row,
+							"+".into(),
+							PricingButton::Change(kind, 1),
+							font(&asset_server, FontWeight::Bold),
+						);
+						row.spawn((
+							Text::default(),
+							TextColor(WHITE.into()),
+							font(&asset_server, FontWeight::Regular),
+							PriceLabel(kind),
+						));
+					});
+			}
+			panel.spawn((
+				Text::default(),
+				TextColor(WHITE.into()),
+				font(&asset_server, FontWeight::Regular),
+				DemandFactorsLabel,
+			));
+			panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+				spawn_button(row, "Usual prices".into(), PricingButton::Reset, font(&asset_server, FontWeight::Bold));
+				spawn_button(row, "Close".into(), PricingButton::Close, font(&asset_server, FontWeight::Bold));
+			});
+		});
+}
+
+fn press_pricing_buttons(
+	buttons: Query<(&Interaction, &PricingButton), Changed<Interaction>>,
+	panels: Query<Entity, With<PricingPanel>>,
+	balance: Res<Balance>,
+	mut pricing: ResMut<PitchPricing>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		match button {
+			PricingButton::Change(kind, direction) => {
+				let step = (balance.pitch_prices(*kind).nightly_price / PRICE_STEP_FRACTION).max(1);
+				let price = pricing.nightly_price(*kind).saturating_add_signed(direction * step as i64);
+				pricing.set_nightly_price(*kind, price);
+			},
+			PricingButton::Reset => *pricing = PitchPricing::default(),
+			PricingButton::Close =>
+				for panel in &panels {
+					commands.entity(panel).despawn_recursive();
+				},
+		}
+	}
+}
+
+fn update_pricing_panel(
+	demand: DemandModel,
+	pricing: Res<PitchPricing>,
+	calendar: Res<Calendar>,
+	panels: Query<Ref<PricingPanel>>,
+	mut price_labels: Query<(&mut Text, &PriceLabel), Without<DemandFactorsLabel>>,
+	mut factor_labels: Query<&mut Text, With<DemandFactorsLabel>>,
+) {
+	let Ok(panel) = panels.get_single() else {
+		return;
+	};
+	if !panel.is_added() && !pricing.is_changed() && !demand.is_changed() {
+		return;
+	}
+
+	for (mut text, PriceLabel(kind)) in &mut price_labels {
+		let price = pricing.nightly_price(*kind);
+		let demand = demand.demand(*kind, price);
+		text.0 = format!(
+			"{kind}: {price} per night, demand {:.0}% (season ×{:.2}, price ×{:.2})",
+			demand.total() * 100.,
+			demand.season,
+			demand.price,
+		);
+	}
+	// The rating and modifiers are the same for every pitch type and price.
+	let factors = demand.demand(PitchType::TentPitch, 0);
+	for mut text in &mut factor_labels {
+		text.0 = format!(
+			"Season: {}\nRating over the last {RATING_DAYS} days: {:.0}% (demand ×{:.2})\nWeather and modifiers: \
+			 demand ×{:.2}",
+			calendar.season(),
+			demand.rating() * 100.,
+			factors.rating,
+			factors.modifiers,
+		);
+	}
+}
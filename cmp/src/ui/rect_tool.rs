@@ -0,0 +1,186 @@
+//! Rectangle dragging for the tools that act on a rectangle of tiles, such as demolishing and clearing.
+//!
+//! A [`RectToolPlugin`] runs while its tool's [`InputState`] is active: dragging with the left mouse button selects a
+//! rectangle of tiles, which is highlighted with the tool's tint, and releasing the button sends the rectangle as the
+//! tool's event. The tool stays active until Escape or right click.
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{anchor_for_image, image_for_ground};
+use crate::graphics::ObjectPriority;
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::{GridPosition, GroundKind};
+
+/// Input states that a tool started from a button can be switched to from.
+const SWITCHABLE_STATES: [InputState; 4] =
+	[InputState::Idle, InputState::Building, InputState::Demolish, InputState::Clearing];
+
+/// A rectangle of tiles between two corner tiles, which may be given in any order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileRect {
+	pub start_position: GridPosition,
+	pub end_position:   GridPosition,
+}
+
+impl TileRect {
+	pub fn new(start_position: GridPosition, end_position: GridPosition) -> Self {
+		Self { start_position, end_position }
+	}
+
+	/// All tiles in the rectangle, on the height of its smaller corner.
+	pub fn positions(&self) -> impl Iterator<Item = GridPosition> {
+		let smaller_corner = self.start_position.component_wise_min(self.end_position);
+		let larger_corner = self.start_position.component_wise_max(self.end_position);
+		(smaller_corner.x ..= larger_corner.x)
+			.flat_map(move |x| (smaller_corner.y ..= larger_corner.y).map(move |y| (x, y, smaller_corner.z).into()))
+	}
+
+	/// Whether the position lies in the rectangle, regardless of its height.
+	pub fn contains(&self, position: &GridPosition) -> bool {
+		let smaller_corner = self.start_position.component_wise_min(self.end_position);
+		let larger_corner = self.start_position.component_wise_max(self.end_position);
+		(smaller_corner.x ..= larger_corner.x).contains(&position.x)
+			&& (smaller_corner.y ..= larger_corner.y).contains(&position.y)
+	}
+}
+
+/// Lets the player drag rectangles while the tool's input state is active, and sends each finished rectangle as an
+/// event of type `E`.
+pub struct RectToolPlugin<E> {
+	tool:   InputState,
+	tint:   Color,
+	_event: PhantomData<fn() -> E>,
+}
+
+impl<E> RectToolPlugin<E> {
+	/// A tool that is active in the input state, and highlights the dragged tiles with the tint.
+	pub fn new(tool: InputState, tint: Color) -> Self {
+		Self { tool, tint, _event: PhantomData }
+	}
+}
+
+impl<E: Event + From<TileRect>> Plugin for RectToolPlugin<E> {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(RectDrag::<E> { tool: self.tool, tint: self.tint, rect: None, _event: PhantomData })
+			.add_event::<E>()
+			.add_systems(
+				Update,
+				(drag_rect::<E>.in_set(SimulationActive), show_rect_drag::<E>, stop_rect_tool)
+					.chain()
+					.run_if(in_state(self.tool))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(OnExit(self.tool), clear_rect_drag::<E>);
+	}
+}
+
+/// The rectangle that the player is currently dragging with the tool that sends `E`.
+#[derive(Resource)]
+pub struct RectDrag<E> {
+	tool:   InputState,
+	tint:   Color,
+	rect:   Option<TileRect>,
+	_event: PhantomData<fn() -> E>,
+}
+
+/// Marks the sprites that show the rectangle of the tool that sends `E`.
+#[derive(Component)]
+struct RectDragTile<E>(PhantomData<fn() -> E>);
+
+/// Starts the tool that sends `E` when a button marked with `B` is pressed.
+pub fn start_rect_tool<E: Event, B: Component>(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<B>)>,
+	drag: Res<RectDrag<E>>,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed)
+		&& state.get() != &drag.tool
+		&& SWITCHABLE_STATES.contains(state.get())
+	{
+		next_state.set(drag.tool);
+	}
+}
+
+fn stop_rect_tool(
+	actions: ActionInput,
+	mouse: Res<ButtonInput<MouseButton>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	if actions.just_pressed(Action::Cancel) || mouse.just_pressed(MouseButton::Right) {
+		next_state.set(InputState::Idle);
+	}
+}
+
+fn drag_rect<E: Event + From<TileRect>>(
+	mouse: Res<ButtonInput<MouseButton>>,
+	picking: Picking,
+	mut drag: ResMut<RectDrag<E>>,
+	mut finished: EventWriter<E>,
+) {
+	let Some(pick) = picking.under_cursor() else {
+		return;
+	};
+	// Don't start a rectangle when clicking on UI, such as the button of the tool itself.
+	if mouse.just_pressed(MouseButton::Left) && !pick.ui_blocked {
+		drag.rect = Some(TileRect::new(pick.tile, pick.tile));
+	}
+	let Some(mut rect) = drag.rect else {
+		return;
+	};
+	// Only change the drag when it moves, so that the highlight isn't rebuilt every frame.
+	if rect.end_position != pick.tile {
+		rect.end_position = pick.tile;
+		drag.rect = Some(rect);
+	}
+	if mouse.just_released(MouseButton::Left) {
+		finished.send(rect.into());
+		drag.rect = None;
+	}
+}
+
+fn show_rect_drag<E: Event>(
+	drag: Res<RectDrag<E>>,
+	mut tiles: Query<(Entity, &mut GridPosition), With<RectDragTile<E>>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !drag.is_changed() {
+		return;
+	}
+	let positions = drag.rect.map_or(Vec::new(), |rect| rect.positions().collect());
+
+	let mut existing_tiles = tiles.iter_mut();
+	for position in positions {
+		if let Some((_, mut tile_position)) = existing_tiles.next() {
+			*tile_position = position;
+		} else {
+			let image = image_for_ground(GroundKind::Grass);
+			commands.spawn((RectDragTile::<E>(PhantomData), ObjectPriority::Overlay, position, Sprite {
+				color: drag.tint,
+				anchor: anchor_for_image(image),
+				image: asset_server.load(image),
+				..Default::default()
+			}));
+		}
+	}
+	for (superfluous_tile, _) in existing_tiles {
+		commands.entity(superfluous_tile).despawn_recursive();
+	}
+}
+
+fn clear_rect_drag<E: Event>(
+	mut drag: ResMut<RectDrag<E>>,
+	tiles: Query<Entity, With<RectDragTile<E>>>,
+	mut commands: Commands,
+) {
+	drag.rect = None;
+	for tile in &tiles {
+		commands.entity(tile).despawn_recursive();
+	}
+}
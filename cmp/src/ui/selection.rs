@@ -0,0 +1,409 @@
+//! Selection tool for applying actions to many tiles at once.
+//!
+//! The tool is toggled with E. Dragging selects a rectangle of tiles, replacing the selection; holding Shift adds the
+//! rectangle to the selection instead, and holding Ctrl removes it. Clicking an object selects all tiles it covers.
+//! The [`Selection`] is highlighted on the canvas and can be demolished with Delete or the panel at the bottom, or
+//! rebuilt with another kind of ground. Bulk actions go through the regular build and demolish requests, row by row,
+//! so they cost, replicate and undo just like building by hand. The tool stays active until Escape or right click.
+
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use super::animate::darken_when_pressed;
+use super::build::BuildRequests;
+use super::controls::InGameUi;
+use super::demolish::PerformDemolish;
+use super::rect_tool::TileRect;
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState, SimulationActive};
+use crate::graphics::library::{anchor_for_image, font_for, image_for_ground, FontStyle, FontWeight};
+use crate::graphics::{ObjectPriority, HIGH_RES_LAYERS};
+use crate::input::picking::Picking;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::spatial::SpatialIndex;
+use crate::model::{Buildable, GridBox, GridPosition, GroundKind};
+use crate::util::{Tooltip, Tooltipable};
+
+pub(super) struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Selection>()
+			.init_resource::<SelectionDrag>()
+			.add_event::<BulkAction>()
+			.add_systems(Update, toggle_selecting.in_set(GameSet::UI).in_set(GameState::InGame))
+			.add_systems(
+				Update,
+				(select_tiles, press_bulk_action_buttons, show_selection, update_selection_summary)
+					.chain()
+					.run_if(in_state(InputState::Selecting))
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			)
+			.add_systems(
+				Update,
+				perform_bulk_actions.in_set(GameSet::Simulation).in_set(SimulationActive).in_set(GameState::InGame),
+			)
+			.add_systems(OnEnter(InputState::Selecting), spawn_selection_panel)
+			.add_systems(OnExit(InputState::Selecting), clear_selection);
+	}
+}
+
+/// Tint of the selected tiles.
+const SELECTION_TINT: Color = Color::srgba(0.3, 0.6, 1., 0.6);
+
+/// Buildables that the selection can be rebuilt with, since they only change the ground.
+const GROUND_BUILDABLES: [Buildable; 4] = [
+	Buildable::Ground(GroundKind::Grass),
+	Buildable::Ground(GroundKind::Pathway),
+	Buildable::PoolArea,
+	Buildable::Pitch,
+];
+
+/// The tiles that the player selected with the selection tool. The selection is empty while the tool isn't active.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Selection {
+	tiles: HashSet<GridPosition>,
+}
+
+impl Selection {
+	pub fn tiles(&self) -> impl Iterator<Item = GridPosition> + '_ {
+		self.tiles.iter().copied()
+	}
+
+	pub fn contains(&self, position: &GridPosition) -> bool {
+		self.tiles.contains(position)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tiles.is_empty()
+	}
+
+	pub fn len(&self) -> usize {
+		self.tiles.len()
+	}
+
+	pub fn clear(&mut self) {
+		self.tiles.clear();
+	}
+
+	pub fn extend(&mut self, positions: impl IntoIterator<Item = GridPosition>) {
+		self.tiles.extend(positions);
+	}
+
+	pub fn remove(&mut self, positions: impl IntoIterator<Item = GridPosition>) {
+		for position in positions {
+			self.tiles.remove(&position);
+		}
+	}
+
+	/// The objects on the selected tiles, each only once.
+	pub fn entities<'a>(&'a self, index: &'a SpatialIndex) -> impl Iterator<Item = Entity> + 'a {
+		let mut seen = HashSet::new();
+		self.tiles
+			.iter()
+			.flat_map(|tile| index.at(*tile))
+			.map(|(entity, _)| entity)
+			.filter(move |entity| seen.insert(*entity))
+	}
+
+	/// Splits the selection into runs of neighboring tiles along the x axis, given as their start and end tile. The
+	/// runs don't overlap and together cover exactly the selected tiles.
+	pub fn runs(&self) -> Vec<(GridPosition, GridPosition)> {
+		let mut tiles = self.tiles.iter().copied().collect::<Vec<_>>();
+		tiles.sort_by_key(|tile| (tile.z, tile.y, tile.x));
+		let mut runs: Vec<(GridPosition, GridPosition)> = Vec::new();
+		for tile in tiles {
+			match runs.last_mut() {
+				Some((_, end)) if end.z == tile.z && end.y == tile.y && end.x + 1 == tile.x => *end = tile,
+				_ => runs.push((tile, tile)),
+			}
+		}
+		runs
+	}
+}
+
+/// How a dragged rectangle changes the selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SelectionMode {
+	Replace,
+	Add,
+	Remove,
+}
+
+/// The rectangle that the player is currently dragging.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+struct SelectionDrag(Option<(TileRect, SelectionMode)>);
+
+/// Something done to all selected tiles.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BulkAction {
+	Demolish,
+	/// Builds the buildable on all selected tiles.
+	Build(Buildable),
+}
+
+/// Marks the sprites that show the selection.
+#[derive(Component, Clone, Copy, Debug)]
+struct SelectionTile;
+
+/// Root of the panel with the bulk actions.
+#[derive(Component, Clone, Copy, Debug)]
+struct SelectionPanel;
+
+/// Text showing how much is selected.
+#[derive(Component, Clone, Copy, Debug)]
+struct SelectionSummary;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct BulkActionButton(BulkAction);
+
+fn toggle_selecting(
+	actions: ActionInput,
+	mouse: Res<ButtonInput<MouseButton>>,
+	state: Res<State<InputState>>,
+	mut next_state: ResMut<NextState<InputState>>,
+) {
+	match state.get() {
+		InputState::Idle if actions.just_pressed(Action::SelectMode) => next_state.set(InputState::Selecting),
+		InputState::Selecting
+			if actions.any_just_pressed([Action::SelectMode, Action::Cancel])
+				|| mouse.just_pressed(MouseButton::Right) =>
+			next_state.set(InputState::Idle),
+		_ => {},
+	}
+}
+
+fn select_tiles(
+	mouse: Res<ButtonInput<MouseButton>>,
+	keys: Res<ButtonInput<KeyCode>>,
+	picking: Picking,
+	boxes: Query<&GridBox>,
+	mut drag: ResMut<SelectionDrag>,
+	mut selection: ResMut<Selection>,
+) {
+	let Some(pick) = picking.under_cursor() else {
+		return;
+	};
+	// Don't start a selection when clicking on UI, such as the bulk action buttons.
+	if mouse.just_pressed(MouseButton::Left) && !pick.ui_blocked {
+		let mode = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+			SelectionMode::Add
+		} else if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+			SelectionMode::Remove
+		} else {
+			SelectionMode::Replace
+		};
+		drag.0 = Some((TileRect::new(pick.tile, pick.tile), mode));
+	}
+	let Some((mut rect, mode)) = drag.0 else {
+		return;
+	};
+	// Only change the drag when it moves, so that the highlight isn't rebuilt every frame.
+	if rect.end_position != pick.tile {
+		rect.end_position = pick.tile;
+		drag.0 = Some((rect, mode));
+	}
+	if !mouse.just_released(MouseButton::Left) {
+		return;
+	}
+
+	drag.0 = None;
+	// Clicking without dragging selects the whole object under the cursor, if there is one.
+	let clicked_box =
+		pick.entity.filter(|_| rect.start_position == rect.end_position).and_then(|entity| boxes.get(entity).ok());
+	let tiles = match clicked_box {
+		Some(grid_box) => grid_box.floor_positions().collect::<Vec<_>>(),
+		None => rect.positions().collect(),
+	};
+	match mode {
+		SelectionMode::Replace => {
+			selection.clear();
+			selection.extend(tiles);
+		},
+		SelectionMode::Add => selection.extend(tiles),
+		SelectionMode::Remove => selection.remove(tiles),
+	}
+}
+
+fn show_selection(
+	selection: Res<Selection>,
+	drag: Res<SelectionDrag>,
+	mut tiles: Query<(Entity, &mut GridPosition), With<SelectionTile>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !selection.is_changed() && !drag.is_changed() {
+		return;
+	}
+	// Show the selection as it will be once the drag ends.
+	let mut shown = selection.clone();
+	if let Some((rect, mode)) = drag.0 {
+		match mode {
+			SelectionMode::Replace => {
+				shown.clear();
+				shown.extend(rect.positions());
+			},
+			SelectionMode::Add => shown.extend(rect.positions()),
+			SelectionMode::Remove => shown.remove(rect.positions()),
+		}
+	}
+
+	let mut existing_tiles = tiles.iter_mut();
+	for position in shown.tiles() {
+		if let Some((_, mut tile_position)) = existing_tiles.next() {
+			*tile_position = position;
+		} else {
+			let image = image_for_ground(GroundKind::Grass);
+			commands.spawn((SelectionTile, ObjectPriority::Overlay, position, Sprite {
+				color: SELECTION_TINT,
+				anchor: anchor_for_image(image),
+				image: asset_server.load(image),
+				..Default::default()
+			}));
+		}
+	}
+	for (superfluous_tile, _) in existing_tiles {
+		commands.entity(superfluous_tile).despawn_recursive();
+	}
+}
+
+fn clear_selection(
+	mut selection: ResMut<Selection>,
+	mut drag: ResMut<SelectionDrag>,
+	shown: Query<Entity, Or<(With<SelectionTile>, With<SelectionPanel>)>>,
+	mut commands: Commands,
+) {
+	selection.clear();
+	drag.0 = None;
+	for entity in &shown {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+fn spawn_selection_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+	let font = |weight| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size: 24.,
+		..Default::default()
+	};
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				bottom: Val::Percent(12.),
+				width: Val::Percent(100.),
+				justify_content: JustifyContent::Center,
+				..Default::default()
+			},
+			HIGH_RES_LAYERS,
+			SelectionPanel,
+			InGameUi,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Node {
+						flex_direction: FlexDirection::Column,
+						align_items: AlignItems::Center,
+						row_gap: BUTTON_SPACING,
+						padding: UiRect::all(BUTTON_SPACING * 2.),
+						..Default::default()
+					},
+					BackgroundColor(Color::BLACK.with_alpha(0.85)),
+					Interaction::default(),
+				))
+				.with_children(|panel| {
+					panel.spawn((
+						Text::default(),
+						TextColor(WHITE.into()),
+						font(FontWeight::Regular),
+						SelectionSummary,
+					));
+					panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+						let demolish = (
+							BulkAction::Demolish,
+							"Demolish".to_string(),
+							"Remove everything on the selected tiles. (Delete)",
+						);
+						let actions = [demolish].into_iter().chain(GROUND_BUILDABLES.map(|buildable| {
+							(BulkAction::Build(buildable), buildable.to_string(), buildable.description())
+						}));
+						for (action, label, description) in actions {
+							row.spawn((
+								Button,
+								Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+								background_color,
+								darken_when_pressed(background_color),
+								Tooltip { title: label.clone(), body: description.into() },
+								BulkActionButton(action),
+							))
+							.with_child((Text(label), TextColor(WHITE.into()), font(FontWeight::Bold)));
+						}
+					});
+				});
+		});
+}
+
+fn update_selection_summary(
+	selection: Res<Selection>,
+	index: Res<SpatialIndex>,
+	mut summaries: Query<&mut Text, With<SelectionSummary>>,
+) {
+	if !selection.is_changed() && !index.is_changed() {
+		return;
+	}
+	let summary = if selection.is_empty() {
+		"Drag to select tiles. Shift adds to the selection, Ctrl removes from it.".to_string()
+	} else {
+		format!("{} tiles and {} objects selected", selection.len(), selection.entities(&index).count())
+	};
+	for mut text in &mut summaries {
+		text.0.clone_from(&summary);
+	}
+}
+
+fn press_bulk_action_buttons(
+	buttons: Query<(&Interaction, &BulkActionButton), Changed<Interaction>>,
+	actions: ActionInput,
+	mut bulk_actions: EventWriter<BulkAction>,
+) {
+	for (interaction, BulkActionButton(action)) in &buttons {
+		if interaction == &Interaction::Pressed {
+			bulk_actions.send(*action);
+		}
+	}
+	if actions.just_pressed(Action::DemolishSelection) {
+		bulk_actions.send(BulkAction::Demolish);
+	}
+}
+
+/// Builds are only performed if every row of the selection can be built, so that the selection isn't left half-built.
+fn perform_bulk_actions(
+	mut bulk_actions: EventReader<BulkAction>,
+	selection: Res<Selection>,
+	mut build_requests: BuildRequests,
+	mut demolitions: EventWriter<PerformDemolish>,
+) {
+	for action in bulk_actions.read() {
+		let runs = selection.runs();
+		match action {
+			BulkAction::Demolish =>
+				for (start_position, end_position) in runs {
+					demolitions.send(PerformDemolish(TileRect::new(start_position, end_position)));
+				},
+			BulkAction::Build(buildable) => {
+				let error = runs.iter().find_map(|(start, end)| build_requests.check(*buildable, *start, *end).err());
+				if let Some(why) = error {
+					build_requests.reject(why);
+					continue;
+				}
+				for (start, end) in runs {
+					build_requests.send(*buildable, start, end);
+				}
+			},
+		}
+	}
+}
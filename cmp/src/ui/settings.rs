@@ -0,0 +1,855 @@
+//! Settings panel for changing the [`GameSettings`] without editing the settings file.
+//!
+//! The panel is opened by sending an [`OpenSettings`] event, from the main menu or the pause menu. It shows a toggle
+//! for every on/off setting, a slider for every numeric setting and selectors for the text size and the debug color
+//! grading. Changes apply immediately, and the config plugin stores them as soon as they are made. The panel is closed
+//! with its close button or Escape.
+//!
+//! A second page of the panel lists the key chords of every [`Action`] in the [`InputMap`]. Clicking an action's
+//! button captures the next key chord the player presses and binds it to the action instead of its current chords.
+//! While capturing, keys are taken out of the keyboard input before any other system sees them, so that binding a key
+//! doesn't also trigger it.
+
+use bevy::color::palettes::css::{DARK_GRAY, LIGHT_GRAY, ORANGE, WHITE};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+use super::accessibility::{TextSize, UI_SCALE_RANGE};
+use super::animate::darken_when_pressed;
+use super::BUTTON_SPACING;
+use crate::config::{GameSettings, MAX_SAVE_BACKUPS};
+use crate::gamemode::GameSet;
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::lut::ColorLut;
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::input::{Action, ActionInput, InputMap, KeyChord};
+use crate::util::text_input::edit_text_inputs;
+use crate::util::Tooltip;
+
+pub(super) struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+	fn build(&self, app: &mut App) {
+		// The panel is available in all game states.
+		app.add_event::<OpenSettings>()
+			.init_resource::<BindingCapture>()
+			.add_systems(PreUpdate, capture_key_binding.after(InputSystem).before(edit_text_inputs))
+			.add_systems(
+				Update,
+				(
+					open_settings_panel,
+					toggle_settings,
+					drag_setting_sliders,
+					cycle_text_size,
+					cycle_color_lut,
+					reset_settings,
+					update_setting_labels,
+					switch_settings_page,
+					start_binding_capture,
+					reset_key_bindings,
+					update_binding_labels,
+					close_settings_panel,
+				)
+					.chain()
+					.in_set(GameSet::UI),
+			);
+	}
+}
+
+/// Requests opening the settings panel.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct OpenSettings;
+
+/// A setting that is either on or off.
+struct SettingToggle {
+	name:        &'static str,
+	description: &'static str,
+	value:       fn(&mut GameSettings) -> &mut bool,
+}
+
+/// All settings shown in the panel, in display order.
+const TOGGLES: [SettingToggle; 12] = [
+	SettingToggle {
+		name:        "VSync",
+		description: "Synchronize frames with the display to avoid tearing.",
+		value:       |settings| &mut settings.use_vsync,
+	},
+	SettingToggle {
+		name:        "FPS display",
+		description: "Show frame timing in the upper left corner.",
+		value:       |settings| &mut settings.show_fps,
+	},
+	SettingToggle {
+		name:        "Debug information",
+		description: "Show debugging information in the world and enable the inspector.",
+		value:       |settings| &mut settings.show_debug,
+	},
+	SettingToggle {
+		name:        "Reduce motion",
+		description: "Replace springy UI animations with instant transitions.",
+		value:       |settings| &mut settings.reduce_motion,
+	},
+	SettingToggle {
+		name:        "Reduce effects",
+		description: "Skip purely cosmetic world effects, such as buildings popping into place.",
+		value:       |settings| &mut settings.reduced_effects,
+	},
+	SettingToggle {
+		name:        "Colorblind-safe colors",
+		description: "Use colors that don't rely on telling red from green in overlays and notifications.",
+		value:       |settings| &mut settings.colorblind_palette,
+	},
+	SettingToggle {
+		name:        "Ambient grading",
+		description: "Let areas like pools and pitches subtly tint everything within them.",
+		value:       |settings| &mut settings.ambient_grading,
+	},
+	SettingToggle {
+		name:        "Day and night",
+		description: "Tint the world through dawn, day, dusk and night.",
+		value:       |settings| &mut settings.day_night_cycle,
+	},
+	SettingToggle {
+		name:        "System cursor",
+		description: "Use the system's mouse cursor instead of the game's own.",
+		value:       |settings| &mut settings.system_cursor,
+	},
+	SettingToggle {
+		name:        "Music",
+		description: "Play background music. Turning it off pauses the current track.",
+		value:       |settings| &mut settings.play_music,
+	},
+	SettingToggle {
+		name:        "Tutorial",
+		description: "Start new games with a short tutorial that shows how to build a pitch.",
+		value:       |settings| &mut settings.tutorial,
+	},
+	SettingToggle {
+		name:        "Instant construction",
+		description: "Sandbox: buildings are finished as soon as they are placed, and obstacles are cleared right \
+		              away.",
+		value:       |settings| &mut settings.instant_construction,
+	},
+];
+
+/// Where a slider's setting is stored.
+#[derive(Clone, Copy)]
+enum SliderValue {
+	/// A setting that can take any value within the range.
+	Float(fn(&mut GameSettings) -> &mut f32),
+	/// A whole number, which the slider changes in whole steps.
+	Count(fn(&mut GameSettings) -> &mut u8),
+}
+
+impl SliderValue {
+	fn get(self, settings: &mut GameSettings) -> f32 {
+		match self {
+			Self::Float(value) => *value(settings),
+			Self::Count(value) => f32::from(*value(settings)),
+		}
+	}
+
+	fn set(self, settings: &mut GameSettings, new_value: f32) {
+		match self {
+			Self::Float(value) => *value(settings) = new_value,
+			Self::Count(value) => *value(settings) = new_value.round() as u8,
+		}
+	}
+}
+
+/// A numeric setting within a range.
+struct SettingSlider {
+	name:        &'static str,
+	description: &'static str,
+	value:       SliderValue,
+	min:         f32,
+	max:         f32,
+	/// Values snap to multiples of this.
+	step:        f32,
+	format:      fn(f32) -> String,
+}
+
+impl SettingSlider {
+	/// Position of the value along the slider, between 0 and 1.
+	fn fraction(&self, value: f32) -> f32 {
+		((value - self.min) / (self.max - self.min)).clamp(0., 1.)
+	}
+
+	fn value_at(&self, fraction: f32) -> f32 {
+		let value = self.min + fraction.clamp(0., 1.) * (self.max - self.min);
+		((value / self.step).round() * self.step).clamp(self.min, self.max)
+	}
+}
+
+/// All numeric settings shown in the panel, in display order.
+const SLIDERS: [SettingSlider; 8] = [
+	SettingSlider {
+		name:        "Music volume",
+		description: "Volume of the background music.",
+		value:       SliderValue::Float(|settings| &mut settings.music_volume),
+		min:         0.,
+		max:         1.,
+		step:        0.05,
+		format:      |value| format!("{:.0}%", value * 100.),
+	},
+	SettingSlider {
+		name:        "Interface volume",
+		description: "Volume of the sounds played by buttons.",
+		value:       SliderValue::Float(|settings| &mut settings.ui_volume),
+		min:         0.,
+		max:         1.,
+		step:        0.05,
+		format:      |value| format!("{:.0}%", value * 100.),
+	},
+	SettingSlider {
+		name:        "Effects volume",
+		description: "Volume of sounds in the world, such as building and demolishing.",
+		value:       SliderValue::Float(|settings| &mut settings.effects_volume),
+		min:         0.,
+		max:         1.,
+		step:        0.05,
+		format:      |value| format!("{:.0}%", value * 100.),
+	},
+	SettingSlider {
+		name:        "Ambience volume",
+		description: "Volume of birds, crickets, rain and wind.",
+		value:       SliderValue::Float(|settings| &mut settings.ambient_volume),
+		min:         0.,
+		max:         1.,
+		step:        0.05,
+		format:      |value| format!("{:.0}%", value * 100.),
+	},
+	SettingSlider {
+		name:        "UI scale",
+		description: "Size of all menus, panels and text.",
+		value:       SliderValue::Float(|settings| &mut settings.ui_scale),
+		min:         UI_SCALE_RANGE[0],
+		max:         UI_SCALE_RANGE[1],
+		step:        0.25,
+		format:      |value| format!("{value}x"),
+	},
+	SettingSlider {
+		name:        "Cursor size",
+		description: "Size of the game's own cursor.",
+		value:       SliderValue::Float(|settings| &mut settings.cursor_scale),
+		min:         0.5,
+		max:         2.,
+		step:        0.25,
+		format:      |value| format!("{value}x"),
+	},
+	SettingSlider {
+		name:        "Time-lapse interval",
+		description: "In-game hours between two pictures of a time-lapse.",
+		value:       SliderValue::Float(|settings| &mut settings.time_lapse_hours),
+		min:         1.,
+		max:         24.,
+		step:        1.,
+		format:      |value| format!("{value} h"),
+	},
+	SettingSlider {
+		name:        "Save backups",
+		description: "Number of previous versions that are kept as backups of every save.",
+		value:       SliderValue::Count(|settings| &mut settings.save_backups),
+		min:         0.,
+		max:         MAX_SAVE_BACKUPS as f32,
+		step:        1.,
+		format:      |value| format!("{value}"),
+	},
+];
+
+/// Width of the slider tracks in logical pixels.
+const SLIDER_WIDTH: f32 = 240.;
+/// Width of an action's row on the key bindings page in logical pixels.
+const BINDING_ROW_WIDTH: f32 = 420.;
+
+/// Root of the settings panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct SettingsPanel;
+
+/// Button that flips the setting with this index in [`TOGGLES`].
+#[derive(Component, Clone, Copy, Debug)]
+struct ToggleButton(usize);
+
+/// Text showing the state of the setting with this index in [`TOGGLES`].
+#[derive(Component, Clone, Copy, Debug)]
+struct ToggleLabel(usize);
+
+/// Track of the slider for the setting with this index in [`SLIDERS`]. Pressing or dragging on the track sets the
+/// value.
+#[derive(Component, Clone, Copy, Debug)]
+struct SliderTrack(usize);
+
+/// Filled part of the track of the slider with this index in [`SLIDERS`].
+#[derive(Component, Clone, Copy, Debug)]
+struct SliderFill(usize);
+
+/// Text showing the value of the slider with this index in [`SLIDERS`].
+#[derive(Component, Clone, Copy, Debug)]
+struct SliderLabel(usize);
+
+/// Button that switches the text size to the next preset.
+#[derive(Component, Clone, Copy, Debug)]
+struct TextSizeButton;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct TextSizeLabel;
+
+/// Button that switches the forced color lookup table to the next one.
+#[derive(Component, Clone, Copy, Debug)]
+struct ColorLutButton;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct ColorLutLabel;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct ResetSettingsButton;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct CloseSettingsButton;
+
+/// A page of the settings panel. Only one page is displayed at a time.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingsPage {
+	Main,
+	KeyBindings,
+}
+
+/// Button that shows the page with this target.
+#[derive(Component, Clone, Copy, Debug)]
+struct ShowPageButton(SettingsPage);
+
+/// Button that captures a new key chord for the action.
+#[derive(Component, Clone, Copy, Debug)]
+struct BindingButton(Action);
+
+/// Text showing the key chords of the action.
+#[derive(Component, Clone, Copy, Debug)]
+struct BindingLabel(Action);
+
+/// Text below the key bindings that reports conflicts of the last binding, or else all keys that are bound to several
+/// actions.
+#[derive(Component, Clone, Copy, Debug)]
+struct BindingStatus;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct ResetBindingsButton;
+
+/// The action whose new key chord is being captured, if any.
+#[derive(Resource, Clone, Debug, Default)]
+struct BindingCapture {
+	action: Option<Action>,
+	/// Message about the last binding that was made.
+	status: String,
+}
+
+fn toggle_label(toggle: &SettingToggle, settings: &mut GameSettings) -> String {
+	format!("{}: {}", toggle.name, if *(toggle.value)(settings) { "On" } else { "Off" })
+}
+
+fn slider_label(slider: &SettingSlider, settings: &mut GameSettings) -> String {
+	format!("{}: {}", slider.name, (slider.format)(slider.value.get(settings)))
+}
+
+fn binding_label(action: Action, input_map: &InputMap, capture: &BindingCapture) -> String {
+	if capture.action == Some(action) {
+		return "Press a key…".into();
+	}
+	let chords = input_map.chords(action);
+	if chords.is_empty() {
+		"Unbound".into()
+	} else {
+		chords.iter().map(KeyChord::label).collect::<Vec<_>>().join(" / ")
+	}
+}
+
+/// Actions that share a key chord with another action are highlighted.
+fn binding_color(action: Action, input_map: &InputMap) -> Color {
+	let is_duplicate =
+		input_map.chords(action).iter().any(|chord| input_map.conflicts(action, *chord).next().is_some());
+	if is_duplicate {
+		ORANGE.into()
+	} else {
+		WHITE.into()
+	}
+}
+
+fn binding_status(input_map: &InputMap, capture: &BindingCapture) -> String {
+	if !capture.status.is_empty() {
+		return capture.status.clone();
+	}
+	let duplicates = input_map.duplicates();
+	if duplicates.is_empty() {
+		return String::new();
+	}
+	let duplicates = duplicates
+		.iter()
+		.map(|(chord, actions)| {
+			let actions = actions.iter().copied().map(Action::description).collect::<Vec<_>>();
+			format!("{} ({})", chord.label(), actions.join(", "))
+		})
+		.collect::<Vec<_>>();
+	format!("Keys bound to several actions: {}", duplicates.join("; "))
+}
+
+fn text_size_label(size: TextSize) -> String {
+	format!("Text size: {size}")
+}
+
+fn color_lut_label(lut: ColorLut) -> String {
+	match lut {
+		ColorLut::Neutral => "Color grading: Game".into(),
+		lut => format!("Color grading: {lut}"),
+	}
+}
+
+fn open_settings_panel(
+	mut requests: EventReader<OpenSettings>,
+	panels: Query<(), With<SettingsPanel>>,
+	settings: Res<GameSettings>,
+	input_map: Res<InputMap>,
+	capture: Res<BindingCapture>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if requests.read().count() == 0 || !panels.is_empty() {
+		return;
+	}
+	let mut settings = *settings;
+	let font = |weight, font_size| TextFont {
+		font: asset_server.load(font_for(weight, FontStyle::Regular)),
+		font_size,
+		..Default::default()
+	};
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	let button = || {
+		(
+			Button,
+			Node { padding: UiRect::all(BUTTON_SPACING), ..Default::default() },
+			background_color,
+			darken_when_pressed(background_color),
+		)
+	};
+
+	commands
+		.spawn((
+			Node {
+				width: Val::Vw(100.),
+				height: Val::Vh(100.),
+				position_type: PositionType::Absolute,
+				justify_content: JustifyContent::Center,
+				align_items: AlignItems::Center,
+				..Default::default()
+			},
+			// Above menus and dialogs, but below the cursor.
+			GlobalZIndex(i32::MAX - 1),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			SettingsPanel,
+		))
+		.with_children(|parent| {
+			parent
+				.spawn((
+					Node {
+						flex_direction: FlexDirection::Column,
+						row_gap: BUTTON_SPACING,
+						padding: UiRect::all(BUTTON_SPACING * 2.),
+						..Default::default()
+					},
+					BackgroundColor(Color::BLACK.with_alpha(0.85)),
+					SettingsPage::Main,
+				))
+				.with_children(|panel| {
+					panel.spawn((Text("Settings".into()), TextColor(WHITE.into()), font(FontWeight::Bold, 40.)));
+					for (index, toggle) in TOGGLES.iter().enumerate() {
+						panel
+							.spawn((button(), ToggleButton(index), Tooltip {
+								title: toggle.name.into(),
+								body:  toggle.description.into(),
+							}))
+							.with_child((
+								Text(toggle_label(toggle, &mut settings)),
+								TextColor(WHITE.into()),
+								font(FontWeight::Regular, 24.),
+								ToggleLabel(index),
+							));
+					}
+					for (index, slider) in SLIDERS.iter().enumerate() {
+						let fraction = slider.fraction(slider.value.get(&mut settings));
+						panel
+							.spawn((
+								Node {
+									flex_direction: FlexDirection::Column,
+									row_gap: BUTTON_SPACING,
+									..Default::default()
+								},
+								Interaction::default(),
+								Tooltip { title: slider.name.into(), body: slider.description.into() },
+							))
+							.with_children(|row| {
+								row.spawn((
+									Text(slider_label(slider, &mut settings)),
+									TextColor(WHITE.into()),
+									font(FontWeight::Regular, 24.),
+									SliderLabel(index),
+								));
+								row.spawn((
+									Node { width: Val::Px(SLIDER_WIDTH), height: Val::Px(16.), ..Default::default() },
+									BackgroundColor(background_color.0),
+									Interaction::default(),
+									RelativeCursorPosition::default(),
+									SliderTrack(index),
+								))
+								.with_child((
+									Node {
+										width: Val::Percent(fraction * 100.),
+										height: Val::Percent(100.),
+										..Default::default()
+									},
+									BackgroundColor(ORANGE.into()),
+									SliderFill(index),
+								));
+							});
+					}
+					panel
+						.spawn((button(), TextSizeButton, Tooltip {
+							title: "Text size".into(),
+							body:  "Make all text larger than normal.".into(),
+						}))
+						.with_child((
+							Text(text_size_label(settings.text_size)),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 24.),
+							TextSizeLabel,
+						));
+					panel
+						.spawn((button(), ColorLutButton, Tooltip {
+							title: "Color grading".into(),
+							body:  "Debugging: force a color lookup table over the whole picture, instead of the one \
+							        chosen by the game."
+								.into(),
+						}))
+						.with_child((
+							Text(color_lut_label(settings.color_lut)),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 24.),
+							ColorLutLabel,
+						));
+					panel
+						.spawn((button(), ShowPageButton(SettingsPage::KeyBindings), Tooltip {
+							title: "Key bindings".into(),
+							body:  "Change the keys for hotkeys and tools.".into(),
+						}))
+						.with_child((
+							Text("Key bindings".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 24.),
+						));
+					panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+						row.spawn((button(), ResetSettingsButton, Tooltip {
+							title: "Reset".into(),
+							body:  "Restore the default settings.".into(),
+						}))
+						.with_child((
+							Text("Reset".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 24.),
+						));
+						row.spawn((button(), CloseSettingsButton)).with_child((
+							Text("Close".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Bold, 24.),
+						));
+					});
+				});
+
+			parent
+				.spawn((
+					Node {
+						display: Display::None,
+						flex_direction: FlexDirection::Column,
+						row_gap: BUTTON_SPACING,
+						padding: UiRect::all(BUTTON_SPACING * 2.),
+						..Default::default()
+					},
+					BackgroundColor(Color::BLACK.with_alpha(0.85)),
+					SettingsPage::KeyBindings,
+				))
+				.with_children(|panel| {
+					panel.spawn((Text("Key bindings".into()), TextColor(WHITE.into()), font(FontWeight::Bold, 40.)));
+					// Two columns, since all actions don't fit below each other.
+					panel
+						.spawn(Node {
+							flex_direction: FlexDirection::Column,
+							flex_wrap: FlexWrap::Wrap,
+							max_height: Val::Vh(70.),
+							row_gap: BUTTON_SPACING,
+							column_gap: BUTTON_SPACING * 4.,
+							..Default::default()
+						})
+						.with_children(|list| {
+							for action in Action::ALL {
+								list.spawn(Node {
+									width: Val::Px(BINDING_ROW_WIDTH),
+									justify_content: JustifyContent::SpaceBetween,
+									align_items: AlignItems::Center,
+									..Default::default()
+								})
+								.with_children(|row| {
+									row.spawn((
+										Text(action.description().into()),
+										TextColor(WHITE.into()),
+										font(FontWeight::Regular, 18.),
+									));
+									row.spawn((button(), BindingButton(action), Tooltip {
+										title: action.description().into(),
+										body:  "Click, then press the new key together with any modifiers. Click \
+										        again to keep the current keys."
+											.into(),
+									}))
+									.with_child((
+										Text(binding_label(action, &input_map, &capture)),
+										TextColor(binding_color(action, &input_map)),
+										font(FontWeight::Regular, 18.),
+										BindingLabel(action),
+									));
+								});
+							}
+						});
+					panel.spawn((
+						Text(binding_status(&input_map, &capture)),
+						TextColor(LIGHT_GRAY.into()),
+						font(FontWeight::Regular, 18.),
+						BindingStatus,
+					));
+					panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+						row.spawn((button(), ResetBindingsButton, Tooltip {
+							title: "Reset".into(),
+							body:  "Restore the default key bindings.".into(),
+						}))
+						.with_child((
+							Text("Reset".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Regular, 24.),
+						));
+						row.spawn((button(), ShowPageButton(SettingsPage::Main))).with_child((
+							Text("Back".into()),
+							TextColor(WHITE.into()),
+							font(FontWeight::Bold, 24.),
+						));
+					});
+				});
+		});
+}
+
+fn toggle_settings(
+	buttons: Query<(&Interaction, &ToggleButton), Changed<Interaction>>,
+	mut settings: ResMut<GameSettings>,
+) {
+	for (interaction, ToggleButton(index)) in &buttons {
+		if interaction == &Interaction::Pressed {
+			let value = (TOGGLES[*index].value)(&mut settings);
+			*value = !*value;
+		}
+	}
+}
+
+/// Sliders follow the cursor for as long as they are held.
+fn drag_setting_sliders(
+	tracks: Query<(&Interaction, &RelativeCursorPosition, &SliderTrack)>,
+	mut settings: ResMut<GameSettings>,
+) {
+	for (interaction, cursor, SliderTrack(index)) in &tracks {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		let Some(cursor) = cursor.normalized else {
+			continue;
+		};
+		let slider = &SLIDERS[*index];
+		let new_value = slider.value_at(cursor.x);
+		// Avoid storing the settings file every frame while the slider is held still.
+		if slider.value.get(settings.bypass_change_detection()) != new_value {
+			slider.value.set(&mut settings, new_value);
+		}
+	}
+}
+
+fn cycle_text_size(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<TextSizeButton>)>,
+	mut settings: ResMut<GameSettings>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		settings.text_size = settings.text_size.next();
+	}
+}
+
+fn cycle_color_lut(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<ColorLutButton>)>,
+	mut settings: ResMut<GameSettings>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		settings.color_lut = settings.color_lut.next();
+	}
+}
+
+fn reset_settings(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<ResetSettingsButton>)>,
+	mut settings: ResMut<GameSettings>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		*settings = GameSettings::default();
+	}
+}
+
+/// Settings may also change through hotkeys while the panel is open.
+fn update_setting_labels(
+	settings: Res<GameSettings>,
+	mut toggle_labels: Query<
+		(&mut Text, &ToggleLabel),
+		(Without<SliderLabel>, Without<ColorLutLabel>, Without<TextSizeLabel>),
+	>,
+	mut slider_labels: Query<
+		(&mut Text, &SliderLabel),
+		(Without<ToggleLabel>, Without<ColorLutLabel>, Without<TextSizeLabel>),
+	>,
+	mut color_lut_labels: Query<
+		&mut Text,
+		(With<ColorLutLabel>, Without<ToggleLabel>, Without<SliderLabel>, Without<TextSizeLabel>),
+	>,
+	mut text_size_labels: Query<
+		&mut Text,
+		(With<TextSizeLabel>, Without<ToggleLabel>, Without<SliderLabel>, Without<ColorLutLabel>),
+	>,
+	mut slider_fills: Query<(&mut Node, &SliderFill)>,
+) {
+	if !settings.is_changed() {
+		return;
+	}
+	let mut settings = *settings;
+	for (mut text, ToggleLabel(index)) in &mut toggle_labels {
+		text.0 = toggle_label(&TOGGLES[*index], &mut settings);
+	}
+	for (mut text, SliderLabel(index)) in &mut slider_labels {
+		text.0 = slider_label(&SLIDERS[*index], &mut settings);
+	}
+	for mut text in &mut color_lut_labels {
+		text.0 = color_lut_label(settings.color_lut);
+	}
+	for mut text in &mut text_size_labels {
+		text.0 = text_size_label(settings.text_size);
+	}
+	for (mut node, SliderFill(index)) in &mut slider_fills {
+		let slider = &SLIDERS[*index];
+		node.width = Val::Percent(slider.fraction(slider.value.get(&mut settings)) * 100.);
+	}
+}
+
+fn show_page(page: SettingsPage, pages: &mut Query<(&mut Node, &SettingsPage)>) {
+	for (mut node, other) in pages {
+		node.display = if *other == page { Display::Flex } else { Display::None };
+	}
+}
+
+fn switch_settings_page(
+	buttons: Query<(&Interaction, &ShowPageButton), Changed<Interaction>>,
+	mut pages: Query<(&mut Node, &SettingsPage)>,
+	mut capture: ResMut<BindingCapture>,
+) {
+	for (interaction, ShowPageButton(page)) in &buttons {
+		if interaction == &Interaction::Pressed {
+			show_page(*page, &mut pages);
+			*capture = BindingCapture::default();
+		}
+	}
+}
+
+fn start_binding_capture(
+	buttons: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+	mut capture: ResMut<BindingCapture>,
+) {
+	for (interaction, BindingButton(action)) in &buttons {
+		if interaction == &Interaction::Pressed {
+			// Clicking the button of the action being captured again keeps its chords.
+			capture.action = if capture.action == Some(*action) { None } else { Some(*action) };
+			capture.status.clear();
+		}
+	}
+}
+
+/// Binds the first key chord pressed while capturing. The pressed key is removed from the keyboard input before the
+/// keyboard focus and other systems see it, so that for example binding Escape doesn't also close the panel.
+fn capture_key_binding(
+	mut capture: ResMut<BindingCapture>,
+	mut keys: ResMut<ButtonInput<KeyCode>>,
+	mut input_map: ResMut<InputMap>,
+) {
+	let Some(action) = capture.action else {
+		return;
+	};
+	let Some(chord) = keys.get_just_pressed().find_map(|key| KeyChord::held_with(*key, &keys)) else {
+		return;
+	};
+	keys.reset(chord.key);
+	input_map.bind(action, chord);
+	let conflicts = input_map.conflicts(action, chord).map(Action::description).collect::<Vec<_>>();
+	capture.action = None;
+	capture.status = if conflicts.is_empty() {
+		String::new()
+	} else {
+		format!("{} is also bound to: {}", chord.label(), conflicts.join(", "))
+	};
+}
+
+fn reset_key_bindings(
+	buttons: Query<&Interaction, (Changed<Interaction>, With<ResetBindingsButton>)>,
+	mut input_map: ResMut<InputMap>,
+	mut capture: ResMut<BindingCapture>,
+) {
+	if buttons.iter().any(|interaction| interaction == &Interaction::Pressed) {
+		*input_map = InputMap::default();
+		*capture = BindingCapture::default();
+	}
+}
+
+fn update_binding_labels(
+	input_map: Res<InputMap>,
+	capture: Res<BindingCapture>,
+	mut labels: Query<(&mut Text, &mut TextColor, &BindingLabel), Without<BindingStatus>>,
+	mut status: Query<&mut Text, (With<BindingStatus>, Without<BindingLabel>)>,
+) {
+	if !input_map.is_changed() && !capture.is_changed() {
+		return;
+	}
+	for (mut text, mut color, BindingLabel(action)) in &mut labels {
+		text.0 = binding_label(*action, &input_map, &capture);
+		color.0 = binding_color(*action, &input_map);
+	}
+	for mut text in &mut status {
+		text.0 = binding_status(&input_map, &capture);
+	}
+}
+
+/// Cancelling on the key bindings page returns to the main page, and closes the panel from there.
+fn close_settings_panel(
+	actions: ActionInput,
+	buttons: Query<&Interaction, (Changed<Interaction>, With<CloseSettingsButton>)>,
+	panels: Query<Entity, With<SettingsPanel>>,
+	mut pages: Query<(&mut Node, &SettingsPage)>,
+	mut capture: ResMut<BindingCapture>,
+	mut commands: Commands,
+) {
+	let on_key_bindings =
+		pages.iter().any(|(node, page)| page == &SettingsPage::KeyBindings && node.display != Display::None);
+	if actions.just_pressed(Action::Cancel) && on_key_bindings {
+		show_page(SettingsPage::Main, &mut pages);
+		*capture = BindingCapture::default();
+	} else if actions.just_pressed(Action::Cancel)
+		|| buttons.iter().any(|interaction| interaction == &Interaction::Pressed)
+	{
+		for panel in &panels {
+			commands.entity(panel).despawn_recursive();
+		}
+		*capture = BindingCapture::default();
+	}
+}
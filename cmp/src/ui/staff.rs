@@ -0,0 +1,222 @@
+//! Staff management panel, listing all employees and their current assignments.
+//!
+//! The panel is toggled with H. It has a hire button for every staff role and a dismiss button for every employee.
+//! The employee list is rebuilt whenever an assignment changes.
+
+use bevy::color::palettes::css::{DARK_GRAY, WHITE};
+use bevy::prelude::*;
+
+use super::animate::darken_when_pressed;
+use super::controls::InGameUi;
+use super::BUTTON_SPACING;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{font_for, FontStyle, FontWeight};
+use crate::graphics::HIGH_RES_LAYERS;
+use crate::input::{Action, ActionInput, InputState};
+use crate::model::modifiers::{ModifierTarget, Modifiers};
+use crate::model::staff::{DismissStaff, Employee, HireStaff, Shift, StaffRole, ALL_STAFF_ROLES};
+use crate::model::Pitch;
+use crate::util::Tooltip;
+
+pub(super) struct StaffPanelPlugin;
+
+impl Plugin for StaffPanelPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(toggle_staff_panel, press_staff_buttons, update_staff_list)
+				.chain()
+				.in_set(GameSet::UI)
+				.in_set(GameState::InGame),
+		);
+	}
+}
+
+/// Root of the staff panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub(super) struct StaffPanel;
+
+/// Container of the employee rows.
+#[derive(Component, Clone, Copy, Debug)]
+struct StaffList;
+
+#[derive(Component, Clone, Copy, Debug)]
+struct WagesLabel;
+
+#[derive(Component, Clone, Copy, Debug)]
+enum StaffButton {
+	Hire(StaffRole),
+	Dismiss(Entity),
+	Close,
+}
+
+fn font(asset_server: &AssetServer, weight: FontWeight) -> TextFont {
+	TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size: 24., ..Default::default() }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: String, button: impl Bundle, font: TextFont) {
+	let background_color = BackgroundColor(DARK_GRAY.into());
+	parent
+		.spawn((
+			Button,
+			Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+			background_color,
+			darken_when_pressed(background_color),
+			button,
+		))
+		.with_child((Text(label), TextColor(WHITE.into()), font));
+}
+
+fn toggle_staff_panel(
+	actions: ActionInput,
+	input_state: Res<State<InputState>>,
+	panels: Query<Entity, With<StaffPanel>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !panels.is_empty() {
+		if actions.any_just_pressed([Action::Staff, Action::Cancel]) {
+			for panel in &panels {
+				commands.entity(panel).despawn_recursive();
+			}
+		}
+		return;
+	}
+	if !actions.just_pressed(Action::Staff) || input_state.get() != &InputState::Idle {
+		return;
+	}
+
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				right: Val::Percent(1.),
+				top: Val::Percent(10.),
+				flex_direction: FlexDirection::Column,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::all(BUTTON_SPACING * 2.),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.85)),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			StaffPanel,
+			InGameUi,
+		))
+		.with_children(|panel| {
+			panel.spawn((Text("Staff".into()), TextColor(WHITE.into()), TextFont {
+				font_size: 40.,
+				..font(&asset_server, FontWeight::Bold)
+			}));
+			panel.spawn(Node { column_gap: BUTTON_SPACING, ..Default::default() }).with_children(|row| {
+				for role in ALL_STAFF_ROLES {
+					spawn_button(
+						row,
+						format!("Hire {role}"),
+						(StaffButton::Hire(role), Tooltip {
+							title: format!("Hire {role}"),
+							body:  format!("Costs a wage of {} per day.", role.daily_wage()),
+						}),
+						font(&asset_server, FontWeight::Regular),
+					);
+				}
+			});
+			panel.spawn((
+				Node { flex_direction: FlexDirection::Column, row_gap: BUTTON_SPACING, ..Default::default() },
+				StaffList,
+			));
+			panel.spawn((
+				Text::default(),
+				TextColor(WHITE.into()),
+				font(&asset_server, FontWeight::Regular),
+				WagesLabel,
+			));
+			spawn_button(panel, "Close".into(), StaffButton::Close, font(&asset_server, FontWeight::Bold));
+		});
+}
+
+fn press_staff_buttons(
+	buttons: Query<(&Interaction, &StaffButton), Changed<Interaction>>,
+	panels: Query<Entity, With<StaffPanel>>,
+	mut hire: EventWriter<HireStaff>,
+	mut dismiss: EventWriter<DismissStaff>,
+	mut commands: Commands,
+) {
+	for (interaction, button) in &buttons {
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		match button {
+			StaffButton::Hire(role) => {
+				hire.send(HireStaff(*role));
+			},
+			StaffButton::Dismiss(employee) => {
+				dismiss.send(DismissStaff(*employee));
+			},
+			StaffButton::Close =>
+				for panel in &panels {
+					commands.entity(panel).despawn_recursive();
+				},
+		}
+	}
+}
+
+fn update_staff_list(
+	employees: Query<(Entity, &Employee, &Shift)>,
+	pitches: Query<&Pitch>,
+	modifiers: Res<Modifiers>,
+	lists: Query<Entity, With<StaffList>>,
+	mut wages_labels: Query<&mut Text, With<WagesLabel>>,
+	asset_server: Res<AssetServer>,
+	mut shown_rows: Local<Option<Vec<(Entity, String)>>>,
+	mut commands: Commands,
+) {
+	let Ok(list) = lists.get_single() else {
+		*shown_rows = None;
+		return;
+	};
+	let mut rows = employees
+		.iter()
+		.map(|(entity, employee, shift)| {
+			let pitch = shift.assignment().and_then(|pitch| pitches.get(pitch).ok());
+			(entity, format!("{}: {}", employee.role, shift.describe(employee.role, pitch)))
+		})
+		.collect::<Vec<_>>();
+	rows.sort_by_key(|(entity, _)| *entity);
+	if shown_rows.as_ref() == Some(&rows) {
+		return;
+	}
+
+	let wages = employees.iter().map(|(_, employee, _)| employee.role.daily_wage()).sum::<u64>() as f32
+		* modifiers.factor(ModifierTarget::Upkeep);
+	for mut label in &mut wages_labels {
+		label.0 = format!("{} employees, wages {:.0} per day", rows.len(), wages);
+	}
+
+	commands.entity(list).despawn_descendants().with_children(|list| {
+		if rows.is_empty() {
+			list.spawn((
+				Text("Nobody works here yet.".into()),
+				TextColor(WHITE.into()),
+				font(&asset_server, FontWeight::Regular),
+			));
+		}
+		for (employee, description) in &rows {
+			list.spawn(Node { column_gap: BUTTON_SPACING, align_items: AlignItems::Center, ..Default::default() })
+				.with_children(|row| {
+					row.spawn((
+						Text(description.clone()),
+						TextColor(WHITE.into()),
+						font(&asset_server, FontWeight::Regular),
+					));
+					spawn_button(
+						row,
+						"Dismiss".into(),
+						StaffButton::Dismiss(*employee),
+						font(&asset_server, FontWeight::Regular),
+					);
+				});
+		}
+	});
+	*shown_rows = Some(rows);
+}
@@ -0,0 +1,390 @@
+//! Tutorial for new players.
+//!
+//! A new game starts with a short tutorial that walks the player through building their first pitch: building a
+//! pathway, placing a pitch and assigning a pitch type to it. The tutorial is a sequence of [`TutorialStep`]s, which
+//! only advance through [`TutorialEvent`]s; steps with an objective advance once a matching build completes, all other
+//! steps once the player continues. While a step is active, a panel explains it, a frame highlights the build menu
+//! button that the player needs next, and a marker in the world points at the pitch that the objective is about.
+//!
+//! The tutorial can be skipped at any time, and turned off entirely with the `tutorial` setting.
+
+use bevy::color::palettes::css::{DARK_GRAY, ORANGE, WHITE};
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use super::animate::darken_when_pressed;
+use super::build::BuildCompleted;
+use super::controls::{BuildMenu, BuildMenuButton, InGameUi, StartBuildButton};
+use super::BUTTON_SPACING;
+use crate::config::GameSettings;
+use crate::gamemode::{GameSet, GameState};
+use crate::graphics::library::{anchor_for_image, font_for, FontStyle, FontWeight, WARNING_ICON};
+use crate::graphics::{ObjectPriority, HIGH_RES_LAYERS};
+use crate::model::area::{Area, ImmutableArea};
+use crate::model::pitch::Pitch;
+use crate::model::{Buildable, CampgroundName, GridPosition, GroundKind};
+
+pub(super) struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Tutorial>()
+			.add_event::<TutorialEvent>()
+			.add_systems(
+				OnTransition { exited: GameState::Loading, entered: GameState::InGame },
+				start_tutorial.after(super::initialize_ingame_ui),
+			)
+			.add_systems(OnEnter(GameState::Loading), reset_tutorial)
+			.add_systems(OnEnter(GameState::MainMenu), reset_tutorial)
+			.add_systems(
+				Update,
+				(
+					forward_build_events,
+					press_tutorial_buttons,
+					advance_tutorial,
+					update_tutorial_panel,
+					update_tutorial_highlight,
+					update_tutorial_marker,
+				)
+					.chain()
+					.in_set(GameSet::UI)
+					.in_set(GameState::InGame),
+			);
+	}
+}
+
+/// Distance between the highlight frame and the highlighted button, in logical pixels.
+const HIGHLIGHT_MARGIN: f32 = 4.;
+/// How many times per second the world marker pulses.
+const MARKER_PULSE_FREQUENCY: f32 = 1.5;
+
+/// The steps of the tutorial, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TutorialStep {
+	Welcome,
+	BuildPath,
+	PlacePitch,
+	AssignPitchType,
+	Done,
+}
+
+impl TutorialStep {
+	fn instructions(&self) -> &'static str {
+		match self {
+			Self::Welcome =>
+				"Welcome to your new campground! Let’s get your first visitors a place to stay. This short tutorial \
+				 shows you how to build a pitch.",
+			Self::BuildPath =>
+				"Visitors need pathways to walk and drive on. Open the basics menu, pick the pathway and drag it \
+				 across the ground.",
+			Self::PlacePitch =>
+				"Now mark out a pitch next to your pathway. Open the pitch menu, pick the pitch area and drag it \
+				 across the ground.",
+			Self::AssignPitchType =>
+				"A pitch needs a type before visitors can stay on it. Pick a pitch type from the pitch menu and click \
+				 your new pitch.",
+			Self::Done =>
+				"Well done! Your pitch is ready for visitors. Build a reception next to a pathway to open your \
+				 campground.",
+		}
+	}
+
+	/// Whether the build completes the objective of this step. Steps without an objective never complete this way.
+	fn is_completed_by(&self, buildable: Buildable) -> bool {
+		match self {
+			Self::BuildPath => buildable == Buildable::Ground(GroundKind::Pathway),
+			Self::PlacePitch => buildable == Buildable::Pitch,
+			Self::AssignPitchType => matches!(buildable, Buildable::PitchType(_)),
+			Self::Welcome | Self::Done => false,
+		}
+	}
+
+	/// Whether the player continues this step with a button instead of completing an objective.
+	fn is_continued_by_player(&self) -> bool {
+		matches!(self, Self::Welcome | Self::Done)
+	}
+
+	/// The build menu that contains the buildables of this step's objective.
+	fn menu(&self) -> Option<BuildMenu> {
+		match self {
+			Self::BuildPath => Some(BuildMenu::Basics),
+			Self::PlacePitch | Self::AssignPitchType => Some(BuildMenu::Pitch),
+			Self::Welcome | Self::Done => None,
+		}
+	}
+
+	fn next(&self) -> Option<Self> {
+		match self {
+			Self::Welcome => Some(Self::BuildPath),
+			Self::BuildPath => Some(Self::PlacePitch),
+			Self::PlacePitch => Some(Self::AssignPitchType),
+			Self::AssignPitchType => Some(Self::Done),
+			Self::Done => None,
+		}
+	}
+}
+
+/// The current step of the tutorial, or [`None`] if the tutorial isn't running.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct Tutorial(pub Option<TutorialStep>);
+
+/// Everything that can advance the tutorial.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TutorialEvent {
+	/// The player continues a step without an objective.
+	Continue,
+	/// The player ends the tutorial.
+	Skip,
+	/// A build was completed.
+	Built(Buildable),
+}
+
+/// Marks the root of the tutorial panel.
+#[derive(Component, Clone, Copy, Debug)]
+struct TutorialPanel;
+
+/// A button of the tutorial panel, which sends its event when pressed.
+#[derive(Component, Clone, Copy, Debug)]
+struct TutorialButton(TutorialEvent);
+
+/// Marks the frame that highlights a build menu button.
+#[derive(Component, Clone, Copy, Debug)]
+struct TutorialHighlight;
+
+/// Marks the marker that floats above a pitch in the world.
+#[derive(Component, Clone, Copy, Debug)]
+struct TutorialMarker;
+
+/// Only new games, which are still unnamed, start with the tutorial.
+fn start_tutorial(name: Res<CampgroundName>, settings: Res<GameSettings>, mut tutorial: ResMut<Tutorial>) {
+	if name.0.is_empty() && settings.tutorial {
+		tutorial.0 = Some(TutorialStep::Welcome);
+	}
+}
+
+/// The tutorial's UI is removed with the rest of the in-game UI, but its world marker isn't saved and has to be removed
+/// here.
+fn reset_tutorial(
+	mut tutorial: ResMut<Tutorial>,
+	markers: Query<Entity, With<TutorialMarker>>,
+	mut commands: Commands,
+) {
+	tutorial.0 = None;
+	for marker in &markers {
+		commands.entity(marker).despawn();
+	}
+}
+
+fn forward_build_events(
+	tutorial: Res<Tutorial>,
+	mut completed: EventReader<BuildCompleted>,
+	mut events: EventWriter<TutorialEvent>,
+) {
+	for BuildCompleted { buildable, .. } in completed.read() {
+		if tutorial.0.is_some() {
+			events.send(TutorialEvent::Built(*buildable));
+		}
+	}
+}
+
+fn press_tutorial_buttons(
+	buttons: Query<(&Interaction, &TutorialButton), Changed<Interaction>>,
+	mut events: EventWriter<TutorialEvent>,
+) {
+	for (interaction, button) in &buttons {
+		if interaction == &Interaction::Pressed {
+			events.send(button.0);
+		}
+	}
+}
+
+fn advance_tutorial(mut events: EventReader<TutorialEvent>, mut tutorial: ResMut<Tutorial>) {
+	for event in events.read() {
+		let Some(step) = tutorial.0 else {
+			continue;
+		};
+		let advances = match event {
+			TutorialEvent::Continue => step.is_continued_by_player(),
+			TutorialEvent::Skip => {
+				tutorial.0 = None;
+				continue;
+			},
+			TutorialEvent::Built(buildable) => step.is_completed_by(*buildable),
+		};
+		if advances {
+			tutorial.0 = step.next();
+		}
+	}
+}
+
+/// Recreates the panel and the highlight frame whenever the step changes.
+fn update_tutorial_panel(
+	tutorial: Res<Tutorial>,
+	panels: Query<Entity, Or<(With<TutorialPanel>, With<TutorialHighlight>)>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	if !tutorial.is_changed() {
+		return;
+	}
+	for panel in &panels {
+		commands.entity(panel).despawn_recursive();
+	}
+	let Some(step) = tutorial.0 else {
+		return;
+	};
+
+	let font = |weight, font_size| {
+		(
+			TextFont { font: asset_server.load(font_for(weight, FontStyle::Regular)), font_size, ..Default::default() },
+			TextColor(WHITE.into()),
+		)
+	};
+	let spawn_button = |parent: &mut ChildBuilder, label: &str, event| {
+		let background_color = BackgroundColor(DARK_GRAY.into());
+		parent
+			.spawn((
+				Button,
+				Node { padding: UiRect::horizontal(BUTTON_SPACING), ..Default::default() },
+				background_color,
+				darken_when_pressed(background_color),
+				TutorialButton(event),
+			))
+			.with_child((Text(label.to_string()), font(FontWeight::Bold, 20.)));
+	};
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				left: Val::Percent(30.),
+				right: Val::Percent(30.),
+				top: Val::Percent(12.),
+				flex_direction: FlexDirection::Column,
+				row_gap: BUTTON_SPACING,
+				padding: UiRect::all(BUTTON_SPACING * 2.),
+				..Default::default()
+			},
+			BackgroundColor(Color::BLACK.with_alpha(0.85)),
+			Interaction::default(),
+			HIGH_RES_LAYERS,
+			TutorialPanel,
+			InGameUi,
+		))
+		.with_children(|panel| {
+			panel.spawn((Text("Tutorial".into()), font(FontWeight::Bold, 28.)));
+			panel.spawn((Text(step.instructions().into()), font(FontWeight::Regular, 20.)));
+			panel
+				.spawn(Node { column_gap: BUTTON_SPACING, justify_content: JustifyContent::End, ..Default::default() })
+				.with_children(|row| {
+					if step.is_continued_by_player() {
+						let label = if step.next().is_some() { "Continue" } else { "Finish" };
+						spawn_button(row, label, TutorialEvent::Continue);
+					}
+					if step.next().is_some() {
+						spawn_button(row, "Skip tutorial", TutorialEvent::Skip);
+					}
+				});
+		});
+
+	if step.menu().is_some() {
+		commands
+			.spawn((
+				Node { position_type: PositionType::Absolute, display: Display::None, ..Default::default() },
+				Outline::new(Val::Px(3.), Val::ZERO, ORANGE.into()),
+				// The frame only points at the button, clicks go through to it.
+				FocusPolicy::Pass,
+				GlobalZIndex(1),
+				HIGH_RES_LAYERS,
+				TutorialHighlight,
+				InGameUi,
+			))
+			.with_child((Text("Click here".into()), font(FontWeight::Bold, 16.), TextColor(ORANGE.into()), Node {
+				position_type: PositionType::Absolute,
+				bottom: Val::Percent(100.),
+				..Default::default()
+			}));
+	}
+}
+
+/// Moves the highlight frame over the button that the player needs next: the button of a buildable that completes the
+/// objective if its build menu is open, and the button of the build menu otherwise.
+fn update_tutorial_highlight(
+	tutorial: Res<Tutorial>,
+	build_buttons: Query<(&StartBuildButton, &ComputedNode, &GlobalTransform)>,
+	menu_buttons: Query<(&BuildMenuButton, &ComputedNode, &GlobalTransform)>,
+	mut highlights: Query<&mut Node, With<TutorialHighlight>>,
+) {
+	let Some(step) = tutorial.0 else {
+		return;
+	};
+	// Buttons of closed build menus aren't laid out and have no size.
+	let target = build_buttons
+		.iter()
+		.find(|(button, node, _)| step.is_completed_by(button.0) && node.size() != Vec2::ZERO)
+		.map(|(_, node, transform)| (node, transform))
+		.or_else(|| {
+			menu_buttons
+				.iter()
+				.find(|(button, ..)| Some(button.0) == step.menu())
+				.map(|(_, node, transform)| (node, transform))
+		});
+
+	for mut highlight in &mut highlights {
+		let Some((node, transform)) = target else {
+			highlight.display = Display::None;
+			continue;
+		};
+		// Node sizes and positions are in physical pixels, but UI positions are in logical pixels.
+		let size = node.size() * node.inverse_scale_factor();
+		let top_left = transform.translation().truncate() * node.inverse_scale_factor() - size / 2.;
+		highlight.display = Display::Flex;
+		highlight.left = Val::Px(top_left.x - HIGHLIGHT_MARGIN);
+		highlight.top = Val::Px(top_left.y - HIGHLIGHT_MARGIN);
+		highlight.width = Val::Px(size.x + HIGHLIGHT_MARGIN * 2.);
+		highlight.height = Val::Px(size.y + HIGHLIGHT_MARGIN * 2.);
+	}
+}
+
+/// While a pitch type is to be assigned, a pulsing marker floats above the first pitch without one.
+fn update_tutorial_marker(
+	tutorial: Res<Tutorial>,
+	time: Res<Time>,
+	pitches: Query<(&Pitch, Option<&Area>, Option<&ImmutableArea>)>,
+	mut markers: Query<(Entity, &mut GridPosition, &mut Sprite), With<TutorialMarker>>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	let target = if tutorial.0 == Some(TutorialStep::AssignPitchType) {
+		pitches
+			.iter()
+			.filter(|(pitch, ..)| pitch.kind.is_none())
+			.find_map(|(_, area, immutable_area)| area.or(immutable_area.map(|area| &area.0)))
+			// Float above the pitch's buildings.
+			.map(|area| area.aabb().center() + IVec3::new(0, 0, 3))
+	} else {
+		None
+	};
+
+	let Some(target) = target else {
+		for (marker, ..) in &markers {
+			commands.entity(marker).despawn();
+		}
+		return;
+	};
+	let alpha = 0.6 + 0.4 * (time.elapsed_secs() * MARKER_PULSE_FREQUENCY * std::f32::consts::TAU).sin();
+	let color = Color::from(ORANGE).with_alpha(alpha);
+	if markers.is_empty() {
+		commands.spawn((TutorialMarker, target, ObjectPriority::Overlay, Sprite {
+			image: asset_server.load(WARNING_ICON),
+			anchor: anchor_for_image(WARNING_ICON),
+			color,
+			..default()
+		}));
+	}
+	for (_, mut position, mut sprite) in &mut markers {
+		if *position != target {
+			*position = target;
+		}
+		sprite.color = color;
+	}
+}
@@ -0,0 +1,16 @@
+//! Panel describing the visitor segments and what each of them expects.
+
+use bevy::prelude::*;
+
+use super::dialog::OpenDialog;
+use crate::input::{Action, ActionInput};
+use crate::model::visitor::ALL_VISITOR_SEGMENTS;
+
+pub(super) fn show_visitor_panel(actions: ActionInput, mut dialogs: EventWriter<OpenDialog>) {
+	if actions.just_pressed(Action::Visitors) {
+		let dialog = ALL_VISITOR_SEGMENTS.into_iter().fold(OpenDialog::new("Visitors"), |dialog, segment| {
+			dialog.heading(segment.to_string()).paragraph(segment.summary())
+		});
+		dialogs.send(dialog);
+	}
+}
@@ -1,29 +1,69 @@
+//! Floating panels showing the [`WorldInfoProperties`] of entities in the world.
+//!
+//! Clicking an entity attaches the unpinned world info panel to it. Pinning the panel keeps it attached to its entity,
+//! and a new unpinned panel takes over the following clicks, so that any number of pinned panels can be open at once.
+//! Pinned panels are only closed with their close button; Escape closes the unpinned panel. Clicking a panel brings
+//! it in front of the others.
+
 use std::ops::DerefMut;
-use std::sync::Arc;
 
 use bevy::color::palettes::css::{ANTIQUE_WHITE, DARK_GRAY, WHITE};
-use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::text::LineBreak;
 use bevy::ui::FocusPolicy;
-use bevy::utils::Instant;
-use parking_lot::Mutex;
 
+use super::animate::darken_when_pressed;
+use super::controls::InGameUi;
+use super::dialog::DialogWidget;
 use crate::graphics::library::{font_for, FontStyle, FontWeight};
-use crate::graphics::{InGameCamera, TILE_HEIGHT, TILE_WIDTH};
-use crate::input::{world_to_camera, MouseClick};
+use crate::graphics::{InGameCamera, TILE_HEIGHT};
+use crate::input::picking::Picking;
+use crate::input::{world_to_camera, Action, ActionInput, MouseClick};
 use crate::model::{Comfort, PitchType};
+use crate::util::Tooltip;
 
 #[derive(Component, Reflect, Default)]
 pub struct WorldInfoUI {
 	attached_entity: Option<Entity>,
+	/// Whether the panel stays attached to its entity when another entity is clicked.
+	pinned:          bool,
+}
+
+impl WorldInfoUI {
+	/// Whether the world info is shown for some entity.
+	pub fn is_open(&self) -> bool {
+		self.attached_entity.is_some()
+	}
+
+	/// Whether the panel is pinned, so that it is not closed by cancelling.
+	pub fn is_pinned(&self) -> bool {
+		self.pinned
+	}
 }
+/// Title text of the world info panel.
 #[derive(Component, Reflect)]
-pub struct WorldInfoTitle;
+pub struct WorldInfoTitle(Entity);
+/// Description text of the world info panel.
 #[derive(Component, Reflect)]
-pub struct WorldInfoBody;
+pub struct WorldInfoBody(Entity);
+/// Button that pins the world info panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WorldInfoPinButton(Entity);
+/// Button that closes the world info panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WorldInfoCloseButton(Entity);
+/// Z index of the world info panel that was raised last. Panels are raised when clicked or opened.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct WorldInfoStack(i32);
+
+impl WorldInfoStack {
+	fn raise(&mut self) -> ZIndex {
+		self.0 += 1;
+		ZIndex(self.0)
+	}
+}
 /// Used for all property columns.
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Clone, Copy)]
 pub enum WorldInfoPropertyDisplay {
 	Description,
 	Value,
@@ -46,6 +86,14 @@ pub enum WorldInfoProperty {
 	PitchType(PitchType),
 	/// Various properties called "multiplicity".
 	Multiplicity(u64),
+	/// A [`crate::model::Pitch`] that can't be reached from the park entrance.
+	Unreachable,
+	/// Money that visitors spent at a shop.
+	Revenue(u64),
+	/// Money spent on running a shop.
+	OperatingCosts(u64),
+	/// Revenue minus operating costs of a shop.
+	Profit(i64),
 }
 
 impl WorldInfoProperty {
@@ -57,6 +105,10 @@ impl WorldInfoProperty {
 			Self::Comfort(_) => "Comfort",
 			Self::PitchType(_) => "Type",
 			Self::Multiplicity(_) => "Multiplicity",
+			Self::Unreachable => "Access",
+			Self::Revenue(_) => "Revenue",
+			Self::OperatingCosts(_) => "Operating costs",
+			Self::Profit(_) => "Profit",
 		}
 		.to_string()
 	}
@@ -68,6 +120,9 @@ impl WorldInfoProperty {
 			Self::Comfort(comfort) => format!("{}", comfort),
 			Self::PitchType(kind) => kind.to_string(),
 			Self::Multiplicity(multiplicity) => format!("{}", multiplicity),
+			Self::Unreachable => "No path from the entrance".to_string(),
+			Self::Revenue(amount) | Self::OperatingCosts(amount) => amount.to_string(),
+			Self::Profit(amount) => amount.to_string(),
 		}
 	}
 }
@@ -100,8 +155,75 @@ impl WorldInfoProperties {
 	}
 }
 
+/// Shows the world info of another entity in place of this entity's own, such as that of the area a tile belongs to.
+/// If the owner no longer exists, the entity's own world info is shown again.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldInfoOwner(pub Entity);
+
+/// The entity whose world info is shown for the given entity.
+fn world_info_source(entity: EntityRef, world: &World) -> Entity {
+	entity
+		.get::<WorldInfoOwner>()
+		.map(|owner| owner.0)
+		.filter(|owner| world.get::<WorldInfoProperties>(*owner).is_some())
+		.unwrap_or(entity.id())
+}
+
+/// Builds the widgets of a world info section for an entity, or [`None`] if the section doesn't apply to it.
+type SectionBuilder = Box<dyn Fn(EntityRef) -> Option<Vec<DialogWidget>> + Send + Sync>;
+
+/// Additional world info sections, in registration order. Sections are registered with
+/// [`WorldInfoSectionExt::add_world_info_section`].
+#[derive(Resource, Default)]
+pub struct WorldInfoSections(Vec<(String, SectionBuilder)>);
+
+/// Extension point for showing additional information about entities in the world info UI.
+pub trait WorldInfoSectionExt {
+	/// Registers a world info section for all entities with the component `C`. The section is shown below the
+	/// entity's regular properties, introduced by the given heading and filled with the widgets returned by `build`.
+	fn add_world_info_section<C: Component>(
+		&mut self,
+		heading: impl Into<String>,
+		build: impl Fn(&C) -> Vec<DialogWidget> + Send + Sync + 'static,
+	) -> &mut Self;
+}
+
+impl WorldInfoSectionExt for App {
+	fn add_world_info_section<C: Component>(
+		&mut self,
+		heading: impl Into<String>,
+		build: impl Fn(&C) -> Vec<DialogWidget> + Send + Sync + 'static,
+	) -> &mut Self {
+		self.init_resource::<WorldInfoSections>();
+		self.world_mut()
+			.resource_mut::<WorldInfoSections>()
+			.0
+			.push((heading.into(), Box::new(move |entity| entity.get::<C>().map(&build))));
+		self
+	}
+}
+
 pub fn setup_world_info(mut commands: Commands, asset_server: Res<AssetServer>) {
-	commands
+	spawn_world_info_panel(&mut commands, &asset_server);
+}
+
+/// Spawns a hidden, unpinned world info panel.
+fn spawn_world_info_panel(commands: &mut Commands, asset_server: &AssetServer) {
+	let small_font = TextFont {
+		font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+		font_size: 14.,
+		..Default::default()
+	};
+	let header_button = || {
+		let background_color = BackgroundColor(Color::BLACK.with_alpha(0.5));
+		(
+			Button,
+			Node { padding: UiRect::axes(Val::Px(5.), Val::Px(2.)), ..Default::default() },
+			background_color,
+			darken_when_pressed(background_color),
+		)
+	};
+	let panel = commands
 		.spawn((
 			Node {
 				position_type: PositionType::Absolute,
@@ -123,37 +245,60 @@ pub fn setup_world_info(mut commands: Commands, asset_server: Res<AssetServer>)
 			},
 			BackgroundColor(DARK_GRAY.into()),
 			FocusPolicy::Block,
+			// Panels are ordered among each other by their ZIndex.
 			GlobalZIndex(1),
 			Visibility::Hidden,
 			Interaction::default(),
 			WorldInfoUI::default(),
+			InGameUi,
 		))
-		.with_children(|parent| {
-			parent.spawn((
-				WorldInfoTitle,
-				Text::default(),
-				TextFont {
-					font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
-					font_size: 24.,
-					..Default::default()
-				},
-				TextColor(WHITE.into()),
-				TextLayout { linebreak: LineBreak::WordBoundary, ..Default::default() },
-				Node { grid_column: GridPlacement::start_span(1, 2), ..Default::default() },
-			));
-			parent.spawn((
-				WorldInfoBody,
-				Text::default(),
-				TextFont {
-					font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
-					font_size: 16.,
-					..Default::default()
-				},
-				TextColor(WHITE.into()),
-				TextLayout { linebreak: LineBreak::WordBoundary, ..Default::default() },
-				Node { grid_column: GridPlacement::start_span(1, 2), ..Default::default() },
-			));
-		});
+		.id();
+	commands.entity(panel).with_children(|parent| {
+		parent
+			.spawn(Node {
+				grid_column: GridPlacement::start_span(1, 2),
+				column_gap: Val::Px(5.),
+				align_items: AlignItems::Start,
+				..Default::default()
+			})
+			.with_children(|header| {
+				header.spawn((
+					WorldInfoTitle(panel),
+					Text::default(),
+					TextFont {
+						font: asset_server.load(font_for(FontWeight::Bold, FontStyle::Regular)),
+						font_size: 24.,
+						..Default::default()
+					},
+					TextColor(WHITE.into()),
+					TextLayout { linebreak: LineBreak::WordBoundary, ..Default::default() },
+					Node { flex_grow: 1., ..Default::default() },
+				));
+				header
+					.spawn((header_button(), WorldInfoPinButton(panel), Tooltip {
+						title: "Pin".into(),
+						body:  "Keep this panel open while inspecting something else.".into(),
+					}))
+					.with_child((Text("Pin".into()), small_font.clone(), TextColor(WHITE.into())));
+				header.spawn((header_button(), WorldInfoCloseButton(panel))).with_child((
+					Text("Close".into()),
+					small_font.clone(),
+					TextColor(WHITE.into()),
+				));
+			});
+		parent.spawn((
+			WorldInfoBody(panel),
+			Text::default(),
+			TextFont {
+				font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+				font_size: 16.,
+				..Default::default()
+			},
+			TextColor(WHITE.into()),
+			TextLayout { linebreak: LineBreak::WordBoundary, ..Default::default() },
+			Node { grid_column: GridPlacement::start_span(1, 2), ..Default::default() },
+		));
+	});
 }
 
 pub fn move_world_info(
@@ -172,83 +317,117 @@ pub fn move_world_info(
 	if cursor_position.is_none() {
 		return;
 	}
-	let (mut world_info_style, mut world_info_visibility, world_info_ui) = world_info.single_mut();
-
-	if let Some(Ok(attached_transform)) =
-		world_info_ui.attached_entity.map(|attached_entity| interactable_world_info_entities.get(attached_entity))
-	{
-		world_info_visibility.set_if_neq(Visibility::Visible);
-		let bevy_world_position = attached_transform.translation() + Vec3::from((0., TILE_HEIGHT / 2., 0.));
-		if let Some(screen_position) = world_to_camera(bevy_world_position, window, camera, camera_transform) {
-			world_info_style.bottom = Val::Px(-screen_position.y + window.height());
-			world_info_style.left = Val::Px(screen_position.x);
+
+	for (mut world_info_style, mut world_info_visibility, world_info_ui) in &mut world_info {
+		if let Some(Ok(attached_transform)) =
+			world_info_ui.attached_entity.map(|attached_entity| interactable_world_info_entities.get(attached_entity))
+		{
+			world_info_visibility.set_if_neq(Visibility::Visible);
+			let bevy_world_position = attached_transform.translation() + Vec3::from((0., TILE_HEIGHT / 2., 0.));
+			if let Some(screen_position) = world_to_camera(bevy_world_position, window, camera, camera_transform) {
+				world_info_style.bottom = Val::Px(-screen_position.y + window.height());
+				world_info_style.left = Val::Px(screen_position.x);
+			}
+		} else {
+			world_info_visibility.set_if_neq(Visibility::Hidden);
 		}
-	} else {
-		world_info_visibility.set_if_neq(Visibility::Hidden);
 	}
 }
 
-pub fn hide_world_info(mut world_info: Query<&mut WorldInfoUI>, input: Res<ButtonInput<KeyCode>>) {
-	let mut world_info_ui = world_info.single_mut();
-	if input.just_pressed(KeyCode::Escape) {
-		world_info_ui.attached_entity = None;
+/// Cancelling only closes the unpinned panel.
+pub fn hide_world_info(mut world_info: Query<&mut WorldInfoUI>, actions: ActionInput) {
+	if actions.just_pressed(Action::Cancel) {
+		for mut world_info_ui in world_info.iter_mut().filter(|world_info_ui| !world_info_ui.pinned) {
+			world_info_ui.attached_entity = None;
+		}
 	}
 }
 
+/// Clicking an entity that a pinned panel already shows raises that panel instead of showing the entity twice.
 pub fn reassign_world_info(
-	blocking_ui_elements: Query<(&FocusPolicy, &Interaction)>,
-	mut interactable_world_info_entities: Query<(Entity, &GlobalTransform, &mut WorldInfoProperties)>,
-	mut world_info: Query<&mut WorldInfoUI>,
+	picking: Picking,
+	mut world_info: Query<(&mut WorldInfoUI, &mut ZIndex)>,
+	mut stack: ResMut<WorldInfoStack>,
 	mut mouse_click: EventReader<MouseClick>,
 ) {
-	for MouseClick { engine_position: world_position, .. } in mouse_click.read() {
-		if !blocking_ui_elements
-			.iter()
-			.any(|(policy, interaction)| *policy == FocusPolicy::Block && *interaction != Interaction::None)
+	for click in mouse_click.read() {
+		let Some(pick) = picking.pick(click.screen_position) else {
+			continue;
+		};
+		if pick.ui_blocked {
+			continue;
+		}
+		let Some(entity) = pick.entity else {
+			continue;
+		};
+		let pinned_panel = world_info
+			.iter_mut()
+			.find(|(world_info_ui, _)| world_info_ui.pinned && world_info_ui.attached_entity == Some(entity));
+		if let Some((_, mut z_index)) = pinned_panel {
+			*z_index = stack.raise();
+			continue;
+		}
+		if let Some((mut world_info_ui, mut z_index)) =
+			world_info.iter_mut().find(|(world_info_ui, _)| !world_info_ui.pinned)
 		{
-			let start = Instant::now();
-
-			let mut world_info_data = world_info.single_mut();
-			let cursor_position = Vec3A::from((*world_position, 0.)) - Vec3A::from((0., TILE_HEIGHT / 2., 0.));
-
-			let node_under_cursor: Arc<Mutex<Option<_>>> = Arc::default();
-			// PERFORMANCE: Run distance checks in parallel, only locking the current-best node once we have something
-			// that's within the click tolerance anyways.
-			interactable_world_info_entities.par_iter_mut().for_each(|(entity, node_position, mut properties)| {
-				let mut node_position = node_position.translation_vec3a();
-				node_position.z = 0.;
-				let distance_to_cursor = node_position.distance(cursor_position).abs();
-
-				if distance_to_cursor < 2. * TILE_WIDTH {
-					let mut node_under_cursor = node_under_cursor.lock();
-					if let Some((old_entity, distance)) = node_under_cursor.as_mut() {
-						if *distance > distance_to_cursor {
-							// Set this world info to changed so that update_world_info definitely runs the next time.
-							properties.set_changed();
-							*old_entity = entity;
-							*distance = distance_to_cursor;
-						}
-					} else {
-						*node_under_cursor = Some((entity, distance_to_cursor));
-					}
-				}
-			});
+			world_info_ui.attached_entity = Some(entity);
+			*z_index = stack.raise();
+		}
+	}
+}
 
-			if let Some((entity, _)) = &*node_under_cursor.lock() {
-				world_info_data.attached_entity = Some(*entity);
-			}
+/// Brings pressed panels in front of the others.
+pub fn raise_world_info(
+	mut world_info: Query<(&Interaction, &mut ZIndex), (Changed<Interaction>, With<WorldInfoUI>)>,
+	mut stack: ResMut<WorldInfoStack>,
+) {
+	for (interaction, mut z_index) in &mut world_info {
+		if interaction == &Interaction::Pressed {
+			*z_index = stack.raise();
+		}
+	}
+}
 
-			let duration = Instant::now() - start;
-			debug!("Regenerating world info took {:?}", duration);
+/// Pinning a panel replaces it with a new unpinned panel for the following clicks. Closing a pinned panel removes it.
+pub fn pin_or_close_world_info(
+	pin_buttons: Query<(&Interaction, &WorldInfoPinButton, Entity), Changed<Interaction>>,
+	close_buttons: Query<(&Interaction, &WorldInfoCloseButton), Changed<Interaction>>,
+	mut world_info: Query<&mut WorldInfoUI>,
+	asset_server: Res<AssetServer>,
+	mut commands: Commands,
+) {
+	for (interaction, WorldInfoPinButton(panel), button) in &pin_buttons {
+		let Ok(mut world_info_ui) = world_info.get_mut(*panel) else {
+			continue;
+		};
+		if interaction != &Interaction::Pressed || world_info_ui.pinned || !world_info_ui.is_open() {
+			continue;
+		}
+		world_info_ui.pinned = true;
+		commands.entity(button).despawn_recursive();
+		spawn_world_info_panel(&mut commands, &asset_server);
+	}
+	for (interaction, WorldInfoCloseButton(panel)) in &close_buttons {
+		let Ok(mut world_info_ui) = world_info.get_mut(*panel) else {
+			continue;
+		};
+		if interaction != &Interaction::Pressed {
+			continue;
+		}
+		if world_info_ui.pinned {
+			commands.entity(*panel).despawn_recursive();
+		} else {
+			world_info_ui.attached_entity = None;
 		}
 	}
 }
 
 pub fn update_world_info(
 	interactable_world_info_entities: Query<&WorldInfoProperties>,
-	mut world_info: Query<(Entity, &mut WorldInfoUI)>,
-	mut world_info_header: Query<&mut Text, (With<WorldInfoTitle>, Without<WorldInfoBody>)>,
-	mut world_info_body: Query<&mut Text, (With<WorldInfoBody>, Without<WorldInfoTitle>)>,
+	owners: Query<&WorldInfoOwner>,
+	mut world_info: Query<(Entity, &mut WorldInfoUI, &Children)>,
+	mut world_info_header: Query<(&mut Text, &WorldInfoTitle), Without<WorldInfoBody>>,
+	mut world_info_body: Query<(&mut Text, &WorldInfoBody), Without<WorldInfoTitle>>,
 	asset_server: Res<AssetServer>,
 	property_displays: Query<
 		Entity,
@@ -256,51 +435,106 @@ pub fn update_world_info(
 	>,
 	mut commands: Commands,
 ) {
-	let (world_info_style, mut world_info_ui) = world_info.single_mut();
-
-	let mut world_info_header = world_info_header.single_mut();
-	let mut world_info_body = world_info_body.single_mut();
-	if let Some(Ok(node_under_cursor)) =
-		world_info_ui.attached_entity.map(|attached_entity| interactable_world_info_entities.get(attached_entity))
-	{
-		for entity in property_displays.into_iter() {
-			commands.entity(entity).despawn_recursive();
+	for (world_info_style, mut world_info_ui, children) in &mut world_info {
+		let mut world_info_header = world_info_header.iter_mut().find(|(_, title)| title.0 == world_info_style);
+		let mut world_info_body = world_info_body.iter_mut().find(|(_, body)| body.0 == world_info_style);
+		if let Some(Ok(node_under_cursor)) = world_info_ui.attached_entity.map(|attached_entity| {
+			owners
+				.get(attached_entity)
+				.and_then(|owner| interactable_world_info_entities.get(owner.0))
+				.or_else(|_| interactable_world_info_entities.get(attached_entity))
+		}) {
+			for entity in property_displays.iter_many(children) {
+				commands.entity(entity).despawn_recursive();
+			}
+
+			if let Some((text, _)) = &mut world_info_header {
+				text.0.clone_from(&node_under_cursor.name);
+			}
+			if let Some((text, _)) = &mut world_info_body {
+				text.0.clone_from(&node_under_cursor.description);
+			}
+
+			let mut info_ui = commands.entity(world_info_style);
+			info_ui.with_children(|parent| {
+				for property in node_under_cursor.iter() {
+					let property_name = property.property_name();
+					let property_value = property.property_value();
+					parent.spawn((
+						Text(property_name),
+						TextFont {
+							font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+							font_size: 18.,
+							..Default::default()
+						},
+						TextColor(WHITE.into()),
+						WorldInfoPropertyDisplay::Description,
+					));
+					parent.spawn((
+						Node { align_self: AlignSelf::End, ..Default::default() },
+						Text(property_value),
+						TextFont {
+							font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
+							font_size: 18.,
+							..Default::default()
+						},
+						TextColor(ANTIQUE_WHITE.into()),
+						WorldInfoPropertyDisplay::Value,
+					));
+				}
+			});
+		} else if world_info_ui.pinned {
+			// The entity of a pinned panel is gone, so the panel has nothing left to show.
+			commands.entity(world_info_style).despawn_recursive();
+		} else {
+			world_info_ui.attached_entity = None;
+			if let Some((text, _)) = &mut world_info_header {
+				text.0.clear();
+			}
+			if let Some((text, _)) = &mut world_info_body {
+				text.0.clear();
+			}
 		}
+	}
+}
 
-		**world_info_header = node_under_cursor.name.clone();
-		**world_info_body = node_under_cursor.description.clone();
+/// Appends all registered [`WorldInfoSections`] that apply to the attached entity. Like the regular properties, the
+/// sections are rebuilt by [`update_world_info`] every time it runs.
+pub fn update_world_info_sections(world: &mut World) {
+	let mut world_info = world.query::<(Entity, &WorldInfoUI)>();
+	let attached = world_info
+		.iter(world)
+		.filter_map(|(world_info_entity, world_info_ui)| Some((world_info_entity, world_info_ui.attached_entity?)))
+		.collect::<Vec<_>>();
+	let asset_server = world.resource::<AssetServer>().clone();
 
-		let mut info_ui = commands.entity(world_info_style);
-		info_ui.with_children(|parent| {
-			for property in node_under_cursor.iter() {
-				let property_name = property.property_name();
-				let property_value = property.property_value();
-				parent.spawn((
-					Text(property_name),
-					TextFont {
-						font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
-						font_size: 18.,
-						..Default::default()
-					},
-					TextColor(WHITE.into()),
-					WorldInfoPropertyDisplay::Description,
-				));
-				parent.spawn((
-					Node { align_self: AlignSelf::End, ..Default::default() },
-					Text(property_value),
-					TextFont {
-						font: asset_server.load(font_for(FontWeight::Regular, FontStyle::Regular)),
-						font_size: 18.,
-						..Default::default()
-					},
-					TextColor(ANTIQUE_WHITE.into()),
-					WorldInfoPropertyDisplay::Value,
-				));
+	for (world_info_entity, attached_entity) in attached {
+		let Ok(attached_entity) = world.get_entity(attached_entity) else {
+			continue;
+		};
+		let Ok(attached_entity) = world.get_entity(world_info_source(attached_entity, world)) else {
+			continue;
+		};
+
+		let widgets = world
+			.resource::<WorldInfoSections>()
+			.0
+			.iter()
+			.filter_map(|(heading, build)| {
+				let widgets = build(attached_entity)?;
+				Some(std::iter::once(DialogWidget::Heading(heading.clone())).chain(widgets))
+			})
+			.flatten()
+			.collect::<Vec<_>>();
+		if widgets.is_empty() {
+			continue;
+		}
+
+		world.commands().entity(world_info_entity).with_children(|parent| {
+			for widget in &widgets {
+				widget.spawn(parent, 18., &asset_server, WorldInfoPropertyDisplay::Value);
 			}
 		});
-	} else {
-		world_info_ui.attached_entity = None;
-		world_info_header.clear();
-		world_info_body.clear();
 	}
+	world.flush();
 }
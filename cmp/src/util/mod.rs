@@ -4,9 +4,11 @@ use bevy::color::palettes::css::DARK_GRAY;
 use bevy::prelude::*;
 use bevy::text::LineBreak;
 
+use crate::gamemode::GameSet;
 use crate::graphics::library::{font_for, FontStyle, FontWeight};
 
 pub mod physics_ease;
+pub mod text_input;
 
 /// Any property which can be linerarly interpolated with itself. Linear interpolation is a useful tool for many things
 /// in games, like animations and transitions.
@@ -91,7 +93,7 @@ pub struct TooltipPlugin;
 impl Plugin for TooltipPlugin {
 	fn build(&self, app: &mut App) {
 		app.add_systems(Startup, setup_tooltip)
-			.add_systems(Update, (move_tooltip_to_mouse, show_tooltip, update_tooltip));
+			.add_systems(Update, (move_tooltip_to_mouse, show_tooltip, update_tooltip).in_set(GameSet::UI));
 	}
 }
 
@@ -158,7 +160,10 @@ fn move_tooltip_to_mouse(
 	windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
 	mut tooltip: Query<&mut Node, With<TooltipUI>>,
 ) {
-	let window = windows.single();
+	// There is no window when running headless.
+	let Ok(window) = windows.get_single() else {
+		return;
+	};
 	let mut tooltip_style = tooltip.single_mut();
 	if let Some(cursor_position) = window.cursor_position() {
 		// Some hacks to translate screen coordinates to UI behavior...
@@ -85,6 +85,12 @@ impl MassDamperSystem {
 		}
 	}
 
+	/// Immediately puts the system into its resting state at the target position, as if it had been simulated for an
+	/// infinite amount of time. In the resting state, `ẋ = 0` and `ẍ = 0`, so `x = m/k_P w`.
+	pub fn settle(&mut self) {
+		self.state = Vec2::new(self.target * self.mass / self.spring_force, 0.);
+	}
+
 	/// Returns the state derivation transfer matrix A.
 	pub const fn a(&self) -> Mat2 {
 		Mat2::from_cols_array_2d(&[[0., -self.spring_force / self.mass], [1., -self.damper_force / self.mass]])
@@ -108,6 +114,7 @@ impl MassDamperSystem {
 	}
 }
 
+#[cfg(feature = "nightly")]
 mod test {
 	#[bench]
 	fn bench_mass_spring_damper_system(bench: &mut test::Bencher) {
@@ -0,0 +1,89 @@
+//! Single-line text input fields.
+//!
+//! A [`TextInput`] displays its value in its [`Text`] component. While a text input is focused, it receives all
+//! keyboard input: keys are removed from [`ButtonInput<KeyCode>`] before any other system sees them, so that typing
+//! doesn't trigger keybinds.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+
+/// Plugin for editing [`TextInput`] fields.
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<TextInputSubmitted>()
+			.add_systems(PreUpdate, edit_text_inputs.after(InputSystem))
+			.add_systems(Update, display_text_inputs);
+	}
+}
+
+/// A single-line text input field. Requires a [`Text`] component on the same entity, which displays the value.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Text)]
+pub struct TextInput {
+	/// Current contents of the field.
+	pub value:      String,
+	/// Maximum number of characters of the value.
+	pub max_length: usize,
+	/// Whether the field receives keyboard input.
+	pub focused:    bool,
+}
+
+impl TextInput {
+	/// Creates a focused, empty text input.
+	pub fn focused(max_length: usize) -> Self {
+		Self { value: String::new(), max_length, focused: true }
+	}
+}
+
+/// Sent when the player presses Enter in a focused text input.
+#[derive(Event, Clone, Debug)]
+pub struct TextInputSubmitted {
+	/// The text input entity.
+	pub entity: Entity,
+	/// Value of the text input at the time of submission.
+	pub value:  String,
+}
+
+pub(crate) fn edit_text_inputs(
+	mut keyboard_events: EventReader<KeyboardInput>,
+	mut keys: ResMut<ButtonInput<KeyCode>>,
+	mut inputs: Query<(Entity, &mut TextInput)>,
+	mut submitted: EventWriter<TextInputSubmitted>,
+) {
+	let Some((entity, mut input)) = inputs.iter_mut().find(|(_, input)| input.focused) else {
+		return;
+	};
+	for event in keyboard_events.read() {
+		if event.state != ButtonState::Pressed {
+			continue;
+		}
+		match &event.logical_key {
+			Key::Enter => {
+				submitted.send(TextInputSubmitted { entity, value: input.value.clone() });
+			},
+			Key::Backspace => {
+				input.value.pop();
+			},
+			Key::Space if input.value.chars().count() < input.max_length => input.value.push(' '),
+			Key::Character(characters) => {
+				for character in characters.chars().filter(|character| !character.is_control()) {
+					if input.value.chars().count() < input.max_length {
+						input.value.push(character);
+					}
+				}
+			},
+			_ => {},
+		}
+	}
+	keys.reset_all();
+}
+
+fn display_text_inputs(mut inputs: Query<(&TextInput, &mut Text), Changed<TextInput>>) {
+	for (input, mut text) in &mut inputs {
+		// The trailing underscore acts as a caret.
+		**text = if input.focused { format!("{}_", input.value) } else { input.value.clone() };
+	}
+}
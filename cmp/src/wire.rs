@@ -0,0 +1,913 @@
+//! Compact binary wire format for multiplayer and replays.
+//!
+//! Saves use bevy reflection, which ties their format to the engine version and to the exact layout of every
+//! component. Network messages and replays must stay readable across engine upgrades and across game versions that add
+//! fields, so they use this separate, hand-specified encoding instead:
+//!
+//! - Integers are LEB128 variable-length encoded; signed integers are zigzag-encoded first, so that small negative
+//!   coordinates stay small.
+//! - Floats are stored as little-endian IEEE 754 bit patterns.
+//! - Enum variants are identified by explicit, stable tags that never change once assigned.
+//! - Structs are a sequence of explicitly tagged, length-prefixed fields, terminated by tag 0. Decoders skip fields
+//!   with unknown tags, so new fields can be added without breaking older readers. Fields must never be renumbered.
+//!
+//! Every top-level message starts with the [`WIRE_VERSION`]; see [`to_bytes`] and [`from_bytes`].
+
+use bevy::math::{IVec3, Vec3A};
+use thiserror::Error;
+
+use crate::model::decoration::Decoration;
+use crate::model::facility::Facility;
+use crate::model::fence::FenceStyle;
+use crate::model::utilities::Utility;
+use crate::model::{ActorPosition, Buildable, GridPosition, GroundKind, PitchType};
+
+/// Version of the wire format. Increment this whenever an existing encoding changes incompatibly; adding struct fields
+/// does not require a new version.
+pub const WIRE_VERSION: u8 = 1;
+
+/// Any reason that wire data could not be decoded.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+	#[error("The data ended unexpectedly.")]
+	UnexpectedEnd,
+	#[error("The data uses wire format version {0}, but only version {WIRE_VERSION} is supported.")]
+	UnsupportedVersion(u8),
+	#[error("A number is too large for its type.")]
+	Overflow,
+	#[error("Unknown tag {tag} for {type_name}.")]
+	UnknownTag { type_name: &'static str, tag: u32 },
+	#[error("Required field {field} of {type_name} is missing.")]
+	MissingField { type_name: &'static str, field: &'static str },
+	#[error("There are {0} unused bytes after the message.")]
+	TrailingData(usize),
+	#[error("The text is not valid UTF-8.")]
+	InvalidText,
+}
+
+/// A type with a stable binary encoding.
+pub trait Wire: Sized {
+	/// Appends the encoding of this value to the output.
+	fn encode(&self, output: &mut Vec<u8>);
+	/// Decodes a value from the start of the input, advancing the input past it.
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError>;
+}
+
+/// Encodes a complete message, including the wire format version.
+pub fn to_bytes<T: Wire>(value: &T) -> Vec<u8> {
+	let mut output = vec![WIRE_VERSION];
+	value.encode(&mut output);
+	output
+}
+
+/// Decodes a complete message that was encoded with [`to_bytes`].
+pub fn from_bytes<T: Wire>(mut input: &[u8]) -> Result<T, WireError> {
+	let version = u8::decode(&mut input)?;
+	if version != WIRE_VERSION {
+		return Err(WireError::UnsupportedVersion(version));
+	}
+	let value = T::decode(&mut input)?;
+	if !input.is_empty() {
+		return Err(WireError::TrailingData(input.len()));
+	}
+	Ok(value)
+}
+
+fn take<'a>(input: &mut &'a [u8], length: usize) -> Result<&'a [u8], WireError> {
+	if input.len() < length {
+		return Err(WireError::UnexpectedEnd);
+	}
+	let (taken, rest) = input.split_at(length);
+	*input = rest;
+	Ok(taken)
+}
+
+impl Wire for u8 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		output.push(*self);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		Ok(take(input, 1)?[0])
+	}
+}
+
+impl Wire for u64 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		let mut value = *self;
+		loop {
+			let byte = (value & 0x7f) as u8;
+			value >>= 7;
+			if value == 0 {
+				output.push(byte);
+				return;
+			}
+			output.push(byte | 0x80);
+		}
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		let mut value = 0u64;
+		for shift in (0 .. 64).step_by(7) {
+			let byte = u8::decode(input)?;
+			let bits = (byte & 0x7f) as u64;
+			if shift == 63 && bits > 1 {
+				return Err(WireError::Overflow);
+			}
+			value |= bits << shift;
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+		Err(WireError::Overflow)
+	}
+}
+
+impl Wire for u32 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		(*self as u64).encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		u64::decode(input)?.try_into().map_err(|_| WireError::Overflow)
+	}
+}
+
+impl Wire for i32 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		(((*self << 1) ^ (*self >> 31)) as u32).encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		let zigzag = u32::decode(input)?;
+		Ok(((zigzag >> 1) as i32) ^ -((zigzag & 1) as i32))
+	}
+}
+
+impl Wire for f32 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		output.extend_from_slice(&self.to_le_bytes());
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		Ok(f32::from_le_bytes(take(input, 4)?.try_into().unwrap()))
+	}
+}
+
+impl<T: Wire> Wire for Vec<T> {
+	fn encode(&self, output: &mut Vec<u8>) {
+		(self.len() as u64).encode(output);
+		for element in self {
+			element.encode(output);
+		}
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		let length = u64::decode(input)?;
+		// Every element takes at least one byte, which bounds the allocation for corrupt lengths.
+		if length > input.len() as u64 {
+			return Err(WireError::UnexpectedEnd);
+		}
+		(0 .. length).map(|_| T::decode(input)).collect()
+	}
+}
+
+impl Wire for String {
+	fn encode(&self, output: &mut Vec<u8>) {
+		(self.len() as u64).encode(output);
+		output.extend_from_slice(self.as_bytes());
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		let length = u64::decode(input)?.try_into().map_err(|_| WireError::Overflow)?;
+		String::from_utf8(take(input, length)?.to_vec()).map_err(|_| WireError::InvalidText)
+	}
+}
+
+impl Wire for IVec3 {
+	fn encode(&self, output: &mut Vec<u8>) {
+		self.x.encode(output);
+		self.y.encode(output);
+		self.z.encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		Ok(Self::new(i32::decode(input)?, i32::decode(input)?, i32::decode(input)?))
+	}
+}
+
+impl Wire for Vec3A {
+	fn encode(&self, output: &mut Vec<u8>) {
+		self.x.encode(output);
+		self.y.encode(output);
+		self.z.encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		Ok(Self::new(f32::decode(input)?, f32::decode(input)?, f32::decode(input)?))
+	}
+}
+
+impl Wire for GridPosition {
+	fn encode(&self, output: &mut Vec<u8>) {
+		self.0.encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		IVec3::decode(input).map(Self)
+	}
+}
+
+impl Wire for ActorPosition {
+	fn encode(&self, output: &mut Vec<u8>) {
+		self.0.encode(output);
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		Vec3A::decode(input).map(Self)
+	}
+}
+
+/// Implements [`Wire`] for a fieldless enum, with an explicit tag for every variant. Tag 0 is reserved.
+macro_rules! wire_enum {
+	($type:ident { $($tag:literal => $variant:ident),* $(,)? }) => {
+		impl Wire for $type {
+			fn encode(&self, output: &mut Vec<u8>) {
+				let tag: u32 = match self {
+					$(Self::$variant => $tag,)*
+				};
+				tag.encode(output);
+			}
+
+			fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+				match u32::decode(input)? {
+					$($tag => Ok(Self::$variant),)*
+					tag => Err(WireError::UnknownTag { type_name: stringify!($type), tag }),
+				}
+			}
+		}
+	};
+}
+
+/// Implements [`Wire`] for a struct with named fields, with an explicit tag for every field. Tag 0 is reserved as the
+/// end marker. All listed fields are required when decoding.
+macro_rules! wire_struct {
+	($type:ident { $($tag:literal => $field:ident),* $(,)? }) => {
+		impl Wire for $type {
+			fn encode(&self, output: &mut Vec<u8>) {
+				$(
+					($tag as u32).encode(output);
+					let mut field = Vec::new();
+					self.$field.encode(&mut field);
+					(field.len() as u64).encode(output);
+					output.extend_from_slice(&field);
+				)*
+				0u32.encode(output);
+			}
+
+			fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+				$(let mut $field = None;)*
+				loop {
+					let tag = u32::decode(input)?;
+					if tag == 0 {
+						break;
+					}
+					let length = u64::decode(input)?.try_into().map_err(|_| WireError::Overflow)?;
+					let mut field = take(input, length)?;
+					match tag {
+						$($tag => $field = Some(Wire::decode(&mut field)?),)*
+						// Fields from newer versions of the game.
+						_ => {},
+					}
+				}
+				Ok(Self {
+					$($field: $field.ok_or(WireError::MissingField {
+						type_name: stringify!($type),
+						field: stringify!($field),
+					})?,)*
+				})
+			}
+		}
+	};
+}
+
+impl Wire for GroundKind {
+	fn encode(&self, output: &mut Vec<u8>) {
+		match self {
+			Self::Grass => 1u32.encode(output),
+			Self::Pathway => 2u32.encode(output),
+			Self::PoolPath => 3u32.encode(output),
+			Self::Pitch => 4u32.encode(output),
+			Self::Custom(number) => {
+				5u32.encode(output);
+				number.encode(output);
+			},
+		}
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		match u32::decode(input)? {
+			1 => Ok(Self::Grass),
+			2 => Ok(Self::Pathway),
+			3 => Ok(Self::PoolPath),
+			4 => Ok(Self::Pitch),
+			5 => u8::decode(input).map(Self::Custom),
+			tag => Err(WireError::UnknownTag { type_name: "GroundKind", tag }),
+		}
+	}
+}
+
+wire_enum!(PitchType {
+	1 => TentPitch,
+	2 => PermanentTent,
+	3 => CaravanPitch,
+	4 => MobileHome,
+	5 => Cottage,
+});
+
+wire_enum!(Utility {
+	1 => Water,
+	2 => Electricity,
+});
+
+wire_enum!(Decoration {
+	1 => Tree,
+	2 => Bench,
+	3 => Lamp,
+	4 => FlowerBed,
+});
+
+wire_enum!(FenceStyle {
+	1 => Wooden,
+	2 => Hedge,
+	3 => StoneWall,
+});
+
+wire_enum!(Facility {
+	1 => Reception,
+	2 => Toilets,
+	3 => Showers,
+	4 => Kiosk,
+	5 => Restaurant,
+});
+
+impl Wire for Buildable {
+	fn encode(&self, output: &mut Vec<u8>) {
+		match self {
+			Self::Ground(kind) => {
+				1u32.encode(output);
+				kind.encode(output);
+			},
+			Self::PoolArea => 2u32.encode(output),
+			Self::Pitch => 3u32.encode(output),
+			Self::PitchType(kind) => {
+				4u32.encode(output);
+				kind.encode(output);
+			},
+			Self::SupplyPoint(utility) => {
+				6u32.encode(output);
+				utility.encode(output);
+			},
+			Self::Conduit(utility) => {
+				7u32.encode(output);
+				utility.encode(output);
+			},
+			Self::Decoration(decoration) => {
+				8u32.encode(output);
+				decoration.encode(output);
+			},
+			Self::Gate => 9u32.encode(output),
+			Self::Facility(facility) => {
+				10u32.encode(output);
+				facility.encode(output);
+			},
+			Self::Fence(style) => {
+				11u32.encode(output);
+				style.encode(output);
+			},
+			Self::EntranceRoad => 12u32.encode(output),
+		}
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		match u32::decode(input)? {
+			1 => GroundKind::decode(input).map(Self::Ground),
+			2 => Ok(Self::PoolArea),
+			3 => Ok(Self::Pitch),
+			4 => PitchType::decode(input).map(Self::PitchType),
+			// Written before fences had styles.
+			5 => Ok(Self::Fence(FenceStyle::Wooden)),
+			6 => Utility::decode(input).map(Self::SupplyPoint),
+			7 => Utility::decode(input).map(Self::Conduit),
+			8 => Decoration::decode(input).map(Self::Decoration),
+			9 => Ok(Self::Gate),
+			10 => Facility::decode(input).map(Self::Facility),
+			11 => FenceStyle::decode(input).map(Self::Fence),
+			12 => Ok(Self::EntranceRoad),
+			tag => Err(WireError::UnknownTag { type_name: "Buildable", tag }),
+		}
+	}
+}
+
+/// A build action of a player, as it is sent to other players and recorded in replays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildCommand {
+	/// What is built.
+	pub buildable: Buildable,
+	/// Where the player started dragging.
+	pub start:     GridPosition,
+	/// Where the player stopped dragging.
+	pub end:       GridPosition,
+}
+
+wire_struct!(BuildCommand {
+	1 => buildable,
+	2 => start,
+	3 => end,
+});
+
+/// The state of a single moving actor at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActorSnapshot {
+	/// Identifier of the actor that is stable across all peers.
+	pub id:       u64,
+	/// Where the actor currently is.
+	pub position: ActorPosition,
+}
+
+wire_struct!(ActorSnapshot {
+	1 => id,
+	2 => position,
+});
+
+/// Snapshots of all moving actors at one simulation tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActorSnapshots {
+	/// Simulation tick the snapshots were taken at.
+	pub tick:   u64,
+	/// Snapshots of all actors.
+	pub actors: Vec<ActorSnapshot>,
+}
+
+wire_struct!(ActorSnapshots {
+	1 => tick,
+	2 => actors,
+});
+
+/// Demolition of everything in the rectangle between two corner tiles, as it is sent to other players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DemolishCommand {
+	/// One corner of the demolished rectangle.
+	pub start: GridPosition,
+	/// The opposite corner of the demolished rectangle.
+	pub end:   GridPosition,
+}
+
+wire_struct!(DemolishCommand {
+	1 => start,
+	2 => end,
+});
+
+/// A message between two games of a multiplayer session; see the `net` module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetMessage {
+	/// Sent by both sides right after connecting, with the game version, so that incompatible games don't play
+	/// together.
+	Hello(String),
+	/// The complete world of the host as compressed save data, sent to a joining player.
+	Snapshot(Vec<u8>),
+	/// A build performed by a player.
+	Build(BuildCommand),
+	/// A demolition performed by a player.
+	Demolish(DemolishCommand),
+	/// Sent by the host to a player whose build it couldn't perform, with the reason, so that the player reverts it.
+	Rejected {
+		/// The build as the player sent it.
+		build:  BuildCommand,
+		/// Why the host couldn't perform the build.
+		reason: String,
+	},
+}
+
+impl Wire for NetMessage {
+	fn encode(&self, output: &mut Vec<u8>) {
+		match self {
+			Self::Hello(game_version) => {
+				1u32.encode(output);
+				game_version.encode(output);
+			},
+			Self::Snapshot(world_data) => {
+				2u32.encode(output);
+				world_data.encode(output);
+			},
+			Self::Build(command) => {
+				3u32.encode(output);
+				command.encode(output);
+			},
+			Self::Demolish(command) => {
+				4u32.encode(output);
+				command.encode(output);
+			},
+			Self::Rejected { build, reason } => {
+				5u32.encode(output);
+				build.encode(output);
+				reason.encode(output);
+			},
+		}
+	}
+
+	fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+		match u32::decode(input)? {
+			1 => String::decode(input).map(Self::Hello),
+			2 => Vec::decode(input).map(Self::Snapshot),
+			3 => BuildCommand::decode(input).map(Self::Build),
+			4 => DemolishCommand::decode(input).map(Self::Demolish),
+			5 => Ok(Self::Rejected { build: BuildCommand::decode(input)?, reason: String::decode(input)? }),
+			tag => Err(WireError::UnknownTag { type_name: "NetMessage", tag }),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::fmt::Debug;
+
+	use bevy::math::{IVec3, Vec3A};
+	use fastrand::Rng;
+
+	use super::{
+		from_bytes, to_bytes, ActorSnapshot, ActorSnapshots, BuildCommand, DemolishCommand, NetMessage, Wire,
+		WireError, WIRE_VERSION,
+	};
+	use crate::model::decoration::ALL_DECORATIONS;
+	use crate::model::facility::ALL_FACILITIES;
+	use crate::model::fence::{FenceStyle, ALL_FENCE_STYLES};
+	use crate::model::utilities::ALL_UTILITIES;
+	use crate::model::{ActorPosition, Buildable, GridPosition, GroundKind, ALL_PITCH_TYPES};
+
+	const GROUND_KINDS: [GroundKind; 5] =
+		[GroundKind::Grass, GroundKind::Pathway, GroundKind::PoolPath, GroundKind::Pitch, GroundKind::Custom(7)];
+	/// Seed of the random values in fuzz tests, so that failures can be reproduced.
+	const FUZZ_SEED: u64 = 0x636d_7077_6972_6500;
+	/// Number of random values or inputs per fuzz test.
+	const FUZZ_ITERATIONS: usize = 2000;
+
+	fn assert_round_trip<T: Wire + PartialEq + Debug>(value: T) {
+		let bytes = to_bytes(&value);
+		assert_eq!(from_bytes::<T>(&bytes), Ok(value), "encoded as {bytes:x?}");
+	}
+
+	fn grid_position(x: i32, y: i32, z: i32) -> GridPosition {
+		GridPosition(IVec3::new(x, y, z))
+	}
+
+	fn all_buildables() -> Vec<Buildable> {
+		let mut buildables = vec![Buildable::PoolArea, Buildable::Pitch, Buildable::Gate, Buildable::EntranceRoad];
+		buildables.extend(ALL_FENCE_STYLES.map(Buildable::Fence));
+		buildables.extend(GROUND_KINDS.map(Buildable::Ground));
+		buildables.extend(ALL_PITCH_TYPES.map(Buildable::PitchType));
+		buildables.extend(ALL_UTILITIES.map(Buildable::SupplyPoint));
+		buildables.extend(ALL_UTILITIES.map(Buildable::Conduit));
+		buildables.extend(ALL_DECORATIONS.map(Buildable::Decoration));
+		buildables.extend(ALL_FACILITIES.map(Buildable::Facility));
+		buildables
+	}
+
+	fn build_command() -> BuildCommand {
+		BuildCommand {
+			buildable: Buildable::Ground(GroundKind::Pathway),
+			start:     grid_position(-3, 7, 0),
+			end:       grid_position(i32::MAX, i32::MIN, 1),
+		}
+	}
+
+	fn random_position(rng: &mut Rng) -> GridPosition {
+		grid_position(rng.i32(..), rng.i32(..), rng.i32(..))
+	}
+
+	fn random_buildable(rng: &mut Rng, buildables: &[Buildable]) -> Buildable {
+		if rng.u8(.. 8) == 0 {
+			Buildable::Ground(GroundKind::Custom(rng.u8(..)))
+		} else {
+			buildables[rng.usize(.. buildables.len())]
+		}
+	}
+
+	fn random_build_command(rng: &mut Rng, buildables: &[Buildable]) -> BuildCommand {
+		BuildCommand {
+			buildable: random_buildable(rng, buildables),
+			start:     random_position(rng),
+			end:       random_position(rng),
+		}
+	}
+
+	/// Any float except NaN, which never compares equal to itself.
+	fn random_float(rng: &mut Rng) -> f32 {
+		loop {
+			let value = f32::from_bits(rng.u32(..));
+			if !value.is_nan() {
+				return value;
+			}
+		}
+	}
+
+	fn random_snapshots(rng: &mut Rng) -> ActorSnapshots {
+		let actors = (0 .. rng.usize(.. 16))
+			.map(|_| ActorSnapshot {
+				id:       rng.u64(..),
+				position: ActorPosition(Vec3A::new(random_float(rng), random_float(rng), random_float(rng))),
+			})
+			.collect();
+		ActorSnapshots { tick: rng.u64(..), actors }
+	}
+
+	fn random_text(rng: &mut Rng) -> String {
+		(0 .. rng.usize(.. 32)).map(|_| rng.char(..)).collect()
+	}
+
+	fn random_message(rng: &mut Rng, buildables: &[Buildable]) -> NetMessage {
+		match rng.u8(.. 5) {
+			0 => NetMessage::Hello(random_text(rng)),
+			1 => NetMessage::Snapshot((0 .. rng.usize(.. 64)).map(|_| rng.u8(..)).collect()),
+			2 => NetMessage::Build(random_build_command(rng, buildables)),
+			3 => NetMessage::Demolish(DemolishCommand { start: random_position(rng), end: random_position(rng) }),
+			_ => NetMessage::Rejected { build: random_build_command(rng, buildables), reason: random_text(rng) },
+		}
+	}
+
+	/// Decodes untrusted input as a message, which may fail but must not panic. Anything that is accepted has to be a
+	/// valid message, which survives another round trip.
+	fn decode_untrusted(bytes: &[u8]) -> Result<NetMessage, WireError> {
+		let result = from_bytes::<NetMessage>(bytes);
+		if let Ok(message) = &result {
+			assert_eq!(from_bytes::<NetMessage>(&to_bytes(message)).as_ref(), Ok(message), "decoded from {bytes:x?}");
+		}
+		result
+	}
+
+	/// Encodes a single field of a struct in the same way as the struct encoding.
+	fn encode_field(tag: u32, value: &impl Wire, output: &mut Vec<u8>) {
+		let mut field = Vec::new();
+		value.encode(&mut field);
+		tag.encode(output);
+		(field.len() as u64).encode(output);
+		output.extend_from_slice(&field);
+	}
+
+	#[test]
+	fn round_trip_integers() {
+		for value in [0, 1, 0x7f, 0x80, u8::MAX] {
+			assert_round_trip(value);
+		}
+		for value in [0, 1, 0x7f, 0x80, 0x3fff, 0x4000, u32::MAX - 1, u32::MAX] {
+			assert_round_trip(value);
+		}
+		for value in [0, 1, 0x7f, 0x80, u32::MAX as u64 + 1, 1 << 63, u64::MAX - 1, u64::MAX] {
+			assert_round_trip(value);
+		}
+		for value in [0, 1, -1, 63, -64, 64, -65, 1000, -1000, i32::MAX, i32::MIN, i32::MIN + 1] {
+			assert_round_trip(value);
+		}
+	}
+
+	#[test]
+	fn small_negative_integers_stay_small() {
+		assert_eq!(to_bytes(&-1i32), [WIRE_VERSION, 1]);
+		assert_eq!(to_bytes(&-64i32), [WIRE_VERSION, 127]);
+	}
+
+	#[test]
+	fn round_trip_floats() {
+		for value in [0., -0., 1.5, -1.5, f32::MIN, f32::MAX, f32::MIN_POSITIVE, f32::EPSILON, f32::INFINITY] {
+			assert_eq!(from_bytes::<f32>(&to_bytes(&value)).map(f32::to_bits), Ok(value.to_bits()));
+		}
+		assert!(from_bytes::<f32>(&to_bytes(&f32::NAN)).unwrap().is_nan());
+	}
+
+	#[test]
+	fn round_trip_collections() {
+		assert_round_trip(Vec::<u8>::new());
+		assert_round_trip(vec![0u8, 1, 2, u8::MAX]);
+		assert_round_trip(vec![u64::MAX, 0, 1 << 40]);
+		assert_round_trip(vec![i32::MIN, -1, 0, i32::MAX]);
+		assert_round_trip(String::new());
+		assert_round_trip("Camping Madness".to_string());
+		assert_round_trip("Zeltplatz „Am Bärensee“ ⛺".to_string());
+	}
+
+	#[test]
+	fn round_trip_positions() {
+		for position in [grid_position(0, 0, 0), grid_position(-1, 1, -1), grid_position(i32::MIN, i32::MAX, i32::MIN)]
+		{
+			assert_round_trip(position);
+		}
+		assert_round_trip(IVec3::new(i32::MAX, i32::MIN, 0));
+		assert_round_trip(Vec3A::new(-0.5, 1e9, f32::MIN_POSITIVE));
+		assert_round_trip(ActorPosition(Vec3A::new(12.25, -7.75, 0.)));
+	}
+
+	#[test]
+	fn round_trip_enums() {
+		for kind in GROUND_KINDS {
+			assert_round_trip(kind);
+		}
+		for kind in ALL_PITCH_TYPES {
+			assert_round_trip(kind);
+		}
+		for utility in ALL_UTILITIES {
+			assert_round_trip(utility);
+		}
+		for decoration in ALL_DECORATIONS {
+			assert_round_trip(decoration);
+		}
+		for facility in ALL_FACILITIES {
+			assert_round_trip(facility);
+		}
+		for buildable in all_buildables() {
+			assert_round_trip(buildable);
+		}
+	}
+
+	#[test]
+	fn round_trip_messages() {
+		assert_round_trip(build_command());
+		assert_round_trip(DemolishCommand { start: grid_position(i32::MIN, 0, 0), end: grid_position(5, -5, 0) });
+		assert_round_trip(ActorSnapshots { tick: 0, actors: Vec::new() });
+		assert_round_trip(ActorSnapshots {
+			tick:   u64::MAX,
+			actors: vec![ActorSnapshot { id: 0, position: ActorPosition(Vec3A::ZERO) }, ActorSnapshot {
+				id:       u64::MAX,
+				position: ActorPosition(Vec3A::new(-1., 2.5, f32::MAX)),
+			}],
+		});
+		assert_round_trip(NetMessage::Hello(crate::VERSION.to_string()));
+		assert_round_trip(NetMessage::Snapshot(vec![0, 0xff, 0x80, 0x7f]));
+		for buildable in all_buildables() {
+			assert_round_trip(NetMessage::Build(BuildCommand { buildable, ..build_command() }));
+		}
+		assert_round_trip(NetMessage::Demolish(DemolishCommand {
+			start: grid_position(-10, -10, 0),
+			end:   grid_position(10, 10, 0),
+		}));
+		assert_round_trip(NetMessage::Rejected { build: build_command(), reason: "Tiles are occupied".to_string() });
+	}
+
+	#[test]
+	fn fuzz_round_trip() {
+		let mut rng = Rng::with_seed(FUZZ_SEED);
+		let buildables = all_buildables();
+		for _ in 0 .. FUZZ_ITERATIONS {
+			assert_round_trip(random_position(&mut rng));
+			assert_round_trip(random_buildable(&mut rng, &buildables));
+			assert_round_trip(random_build_command(&mut rng, &buildables));
+			assert_round_trip(random_snapshots(&mut rng));
+			assert_round_trip(random_message(&mut rng, &buildables));
+		}
+	}
+
+	#[test]
+	fn fuzz_random_input() {
+		let mut rng = Rng::with_seed(FUZZ_SEED);
+		for _ in 0 .. FUZZ_ITERATIONS {
+			let mut bytes = (0 .. rng.usize(.. 64)).map(|_| rng.u8(..)).collect::<Vec<_>>();
+			// Most inputs get past the version check, so that the message decoding is exercised.
+			if let Some(version) = bytes.first_mut().filter(|_| rng.u8(.. 8) != 0) {
+				*version = WIRE_VERSION;
+			}
+			let _ = decode_untrusted(&bytes);
+		}
+	}
+
+	#[test]
+	fn fuzz_mutated_messages() {
+		let mut rng = Rng::with_seed(FUZZ_SEED);
+		let buildables = all_buildables();
+		for _ in 0 .. FUZZ_ITERATIONS {
+			let bytes = to_bytes(&random_message(&mut rng, &buildables));
+
+			let truncated = &bytes[.. rng.usize(.. bytes.len())];
+			assert!(decode_untrusted(truncated).is_err(), "accepted truncated {truncated:x?}");
+
+			let mut extended = bytes.clone();
+			extended.extend((0 .. rng.usize(1 .. 8)).map(|_| rng.u8(..)));
+			assert!(decode_untrusted(&extended).is_err(), "accepted extended {extended:x?}");
+
+			let mut corrupted = bytes.clone();
+			for _ in 0 .. rng.usize(1 ..= 4) {
+				let index = rng.usize(.. corrupted.len());
+				corrupted[index] = rng.u8(..);
+			}
+			let _ = decode_untrusted(&corrupted);
+		}
+	}
+
+	#[test]
+	fn decode_fences_without_style() {
+		let mut bytes = vec![WIRE_VERSION];
+		5u32.encode(&mut bytes);
+		assert_eq!(from_bytes::<Buildable>(&bytes), Ok(Buildable::Fence(FenceStyle::Wooden)));
+	}
+
+	#[test]
+	fn skip_unknown_fields() {
+		let command = build_command();
+		let mut bytes = vec![WIRE_VERSION];
+		encode_field(1, &command.buildable, &mut bytes);
+		// Fields of newer game versions, before, between and after the known fields.
+		encode_field(42, &"a field from the future".to_string(), &mut bytes);
+		encode_field(2, &command.start, &mut bytes);
+		encode_field(u32::MAX, &vec![1u64, 2, 3], &mut bytes);
+		encode_field(3, &command.end, &mut bytes);
+		encode_field(4, &u64::MAX, &mut bytes);
+		0u32.encode(&mut bytes);
+		assert_eq!(from_bytes::<BuildCommand>(&bytes), Ok(command));
+	}
+
+	#[test]
+	fn reject_missing_fields() {
+		let command = build_command();
+		let mut bytes = vec![WIRE_VERSION];
+		encode_field(1, &command.buildable, &mut bytes);
+		encode_field(3, &command.end, &mut bytes);
+		0u32.encode(&mut bytes);
+		assert_eq!(
+			from_bytes::<BuildCommand>(&bytes),
+			Err(WireError::MissingField { type_name: "BuildCommand", field: "start" })
+		);
+	}
+
+	#[test]
+	fn reject_truncated_input() {
+		let message = NetMessage::Build(build_command());
+		let bytes = to_bytes(&message);
+		for length in 0 .. bytes.len() {
+			assert_eq!(from_bytes::<NetMessage>(&bytes[.. length]), Err(WireError::UnexpectedEnd), "length {length}");
+		}
+		let snapshots =
+			ActorSnapshots { tick: 7, actors: vec![ActorSnapshot { id: 1, position: ActorPosition(Vec3A::ONE) }; 3] };
+		let bytes = to_bytes(&snapshots);
+		for length in 0 .. bytes.len() {
+			assert_eq!(
+				from_bytes::<ActorSnapshots>(&bytes[.. length]),
+				Err(WireError::UnexpectedEnd),
+				"length {length}"
+			);
+		}
+	}
+
+	#[test]
+	fn reject_varint_overflow() {
+		// Eleven continuation bytes never end within 64 bits.
+		assert_eq!(u64::decode(&mut &[0xff; 11][..]), Err(WireError::Overflow));
+		// The tenth byte may only contribute the highest bit.
+		let mut too_large = [0xff; 10];
+		too_large[9] = 0x02;
+		assert_eq!(u64::decode(&mut &too_large[..]), Err(WireError::Overflow));
+		too_large[9] = 0x01;
+		assert_eq!(u64::decode(&mut &too_large[..]), Ok(u64::MAX));
+
+		let mut bytes = Vec::new();
+		(u32::MAX as u64 + 1).encode(&mut bytes);
+		assert_eq!(u32::decode(&mut &bytes[..]), Err(WireError::Overflow));
+		assert_eq!(i32::decode(&mut &bytes[..]), Err(WireError::Overflow));
+	}
+
+	#[test]
+	fn reject_oversized_lengths() {
+		for length in [4, u32::MAX as u64, u64::MAX] {
+			let mut bytes = vec![WIRE_VERSION];
+			length.encode(&mut bytes);
+			bytes.extend_from_slice(&[1, 2, 3]);
+			assert_eq!(from_bytes::<Vec<u8>>(&bytes), Err(WireError::UnexpectedEnd), "length {length}");
+			assert_eq!(from_bytes::<String>(&bytes), Err(WireError::UnexpectedEnd), "length {length}");
+		}
+
+		// A field length that exceeds the remaining message.
+		let mut bytes = vec![WIRE_VERSION];
+		1u32.encode(&mut bytes);
+		u64::MAX.encode(&mut bytes);
+		bytes.push(0);
+		assert!(from_bytes::<BuildCommand>(&bytes).is_err());
+	}
+
+	#[test]
+	fn reject_trailing_data() {
+		let mut bytes = to_bytes(&build_command());
+		bytes.extend_from_slice(&[0, 0, 0]);
+		assert_eq!(from_bytes::<BuildCommand>(&bytes), Err(WireError::TrailingData(3)));
+	}
+
+	#[test]
+	fn reject_invalid_data() {
+		assert_eq!(from_bytes::<u8>(&[WIRE_VERSION + 1, 0]), Err(WireError::UnsupportedVersion(WIRE_VERSION + 1)));
+		assert_eq!(
+			from_bytes::<GroundKind>(&[WIRE_VERSION, 0]),
+			Err(WireError::UnknownTag { type_name: "GroundKind", tag: 0 })
+		);
+		assert_eq!(
+			from_bytes::<NetMessage>(&[WIRE_VERSION, 99]),
+			Err(WireError::UnknownTag { type_name: "NetMessage", tag: 99 })
+		);
+		assert_eq!(from_bytes::<String>(&[WIRE_VERSION, 2, 0xc3, 0x28]), Err(WireError::InvalidText));
+	}
+}
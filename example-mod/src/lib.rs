@@ -0,0 +1,51 @@
+//! An example code mod for CMP, which adds sand as a new ground kind, offers it in a new beach build menu, and
+//! introduces it with a dialog.
+//!
+//! Build it with `cargo build -p example-mod`, with the same toolchain and features as the game, and copy the library
+//! from `target/debug` into the `mods` folder of the game's data directory. The game has to be built with the `mods`
+//! feature, so that it shares the engine library with the mod.
+
+extern crate cmp_dylib;
+
+use bevy::prelude::*;
+use cmp::mods::{
+	Buildable, BuildableDefinition, CmpModApi, CustomGroundKind, CustomMenu, GameState, GroundKind, OpenDialog,
+};
+
+fn create(api: &mut CmpModApi) {
+	let sand = api.register_ground_kind(CustomGroundKind {
+		name:         "Sand",
+		description:  "Soft sand for a beach next to the pool. Everyone can walk here, but not very fast.",
+		image:        "concrete.qoi",
+		color:        Color::srgb_u8(222, 200, 140),
+		behaves_like: GroundKind::Grass,
+	});
+	let beach = api.register_menu(CustomMenu {
+		name:        "Beach",
+		description: "Sand and everything else for a beach.",
+		logo:        "pool.qoi",
+	});
+	api.register_buildable(BuildableDefinition {
+		buildable:          Buildable::Ground(sand),
+		name:               "Sand".into(),
+		description:        "Soft sand for a beach next to the pool. Drag to build a line of sand.".into(),
+		menu:               beach,
+		cost:               5,
+		construction_hours: 0.,
+		size:               None,
+		logo:               "concrete.qoi".into(),
+		preview:            "concrete.qoi".into(),
+		anchor:             None,
+	});
+
+	api.open_dialog(
+		OpenDialog::new("Beach")
+			.paragraph("Sand for a beach next to the pool is now offered in the beach build menu.")
+			.property("Sand cost", "5"),
+	);
+
+	let name = api.mod_name().to_owned();
+	api.add_systems(OnEnter(GameState::InGame), move || info!("{name} offers sand in the beach menu"));
+}
+
+cmp::cmp_mod!(create);